@@ -0,0 +1,48 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Blocking jni io. reading or writing java channels blocks the calling thread
+//! until the jvm side is ready, which stalls all tasks scheduled on the same
+//! tokio worker. such calls are run on the blocking threads of the current
+//! runtime instead. threads of the native runtime (including blocking ones)
+//! are started with the classloader and spark task context of the task.
+
+use datafusion::common::{DataFusionError, Result};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+/// Runs a blocking jni call on a blocking thread and waits for its result
+pub async fn spawn_jni_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| DataFusionError::Execution(format!("jni blocking task error: {err}")))?
+}
+
+/// Runs a blocking producer on a blocking thread, returns a channel receiving
+/// the produced items. at most `capacity` items are buffered in the channel,
+/// and the producer should stop once sending fails, which means the receiver
+/// is dropped. error returned by the producer is sent as the last item.
+pub fn spawn_jni_blocking_producer<T: Send + 'static>(
+    capacity: usize,
+    producer: impl FnOnce(&Sender<Result<T>>) -> Result<()> + Send + 'static,
+) -> Receiver<Result<T>> {
+    let (sender, receiver) = channel(capacity);
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = producer(&sender) {
+            let _ = sender.blocking_send(Err(err));
+        }
+    });
+    receiver
+}
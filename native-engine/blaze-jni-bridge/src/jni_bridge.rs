@@ -184,6 +184,95 @@ macro_rules! jni_new_string {
     }};
 }
 
+#[macro_export]
+macro_rules! jni_new_long_array {
+    ($values:expr) => {{
+        $crate::jni_bridge::THREAD_JNIENV.with(|env| {
+            let values: &[i64] = $values;
+            $crate::jni_map_error_with_env!(env, env.new_long_array(values.len() as i32)).and_then(
+                |array| {
+                    $crate::jni_map_error_with_env!(
+                        env,
+                        env.set_long_array_region(array, 0, values)
+                    )?;
+                    Ok($crate::jni_bridge::LocalRef(array.into()))
+                },
+            )
+        })
+    }};
+}
+
+#[macro_export]
+macro_rules! jni_new_byte_array {
+    ($len:expr) => {{
+        $crate::jni_bridge::THREAD_JNIENV.with(|env| {
+            $crate::jni_map_error_with_env!(env, env.new_byte_array($len as i32))
+                .map(|array| $crate::jni_bridge::LocalRef(array.into()))
+        })
+    }};
+}
+
+#[macro_export]
+macro_rules! jni_get_byte_array_region {
+    ($array:expr, $start:expr, $buf:expr) => {{
+        $crate::jni_bridge::THREAD_JNIENV.with(|env| {
+            let buf: &mut [u8] = $buf;
+            // safety: u8 and jbyte (i8) have the same layout
+            let buf = unsafe { std::mem::transmute::<&mut [u8], &mut [i8]>(buf) };
+            $crate::jni_map_error_with_env!(
+                env,
+                env.get_byte_array_region($array.into_raw(), $start as i32, buf)
+            )
+        })
+    }};
+}
+
+#[macro_export]
+macro_rules! jni_set_byte_array_region {
+    ($array:expr, $start:expr, $buf:expr) => {{
+        $crate::jni_bridge::THREAD_JNIENV.with(|env| {
+            let buf: &[u8] = $buf;
+            // safety: u8 and jbyte (i8) have the same layout
+            let buf = unsafe { std::mem::transmute::<&[u8], &[i8]>(buf) };
+            $crate::jni_map_error_with_env!(
+                env,
+                env.set_byte_array_region($array.into_raw(), $start as i32, buf)
+            )
+        })
+    }};
+}
+
+#[macro_export]
+macro_rules! jni_new_string_array {
+    ($values:expr) => {{
+        $crate::jni_bridge::THREAD_JNIENV.with(|env| {
+            let values = $values;
+            $crate::jni_map_error_with_env!(
+                env,
+                env.new_object_array(
+                    values.len() as i32,
+                    "java/lang/String",
+                    $crate::jni_bridge::JObject::null()
+                )
+            )
+            .and_then(|array| {
+                for (i, value) in values.iter().enumerate() {
+                    let value = $crate::jni_bridge::JObject::from($crate::jni_map_error_with_env!(
+                        env,
+                        env.new_string(value)
+                    )?);
+                    $crate::jni_map_error_with_env!(
+                        env,
+                        env.set_object_array_element(array, i as i32, value)
+                    )?;
+                    $crate::jni_map_error_with_env!(env, env.delete_local_ref(value))?;
+                }
+                Ok($crate::jni_bridge::LocalRef(array.into()))
+            })
+        })
+    }};
+}
+
 #[macro_export]
 macro_rules! jni_new_object {
     ($clsname:ident ($($args:expr),* $(,)?)) => {{
@@ -388,6 +477,7 @@ pub struct JavaClasses<'a> {
     pub cJavaFile: JavaFile<'a>,
     pub cJavaURI: JavaURI<'a>,
     pub cJavaBuffer: JavaBuffer<'a>,
+    pub cJavaByteBuffer: JavaByteBuffer<'a>,
 
     pub cScalaIterator: ScalaIterator<'a>,
     pub cScalaTuple2: ScalaTuple2<'a>,
@@ -407,6 +497,8 @@ pub struct JavaClasses<'a> {
     pub cBlazeRssPartitionWriterBase: BlazeRssPartitionWriterBase<'a>,
     pub cBlazeCallNativeWrapper: BlazeCallNativeWrapper<'a>,
     pub cBlazeOnHeapSpillManager: BlazeOnHeapSpillManager<'a>,
+    pub cBlazeNativeMemoryConsumer: BlazeNativeMemoryConsumer<'a>,
+    pub cBlazeUnsupportedPlanException: BlazeUnsupportedPlanException<'a>,
 }
 
 #[allow(clippy::non_send_fields_in_send_ty)]
@@ -451,6 +543,7 @@ impl JavaClasses<'static> {
                 cJavaFile: JavaFile::new(env).unwrap(),
                 cJavaURI: JavaURI::new(env).unwrap(),
                 cJavaBuffer: JavaBuffer::new(env).unwrap(),
+                cJavaByteBuffer: JavaByteBuffer::new(env).unwrap(),
 
                 cScalaIterator: ScalaIterator::new(env).unwrap(),
                 cScalaTuple2: ScalaTuple2::new(env).unwrap(),
@@ -470,6 +563,8 @@ impl JavaClasses<'static> {
                 cBlazeRssPartitionWriterBase: BlazeRssPartitionWriterBase::new(env).unwrap(),
                 cBlazeCallNativeWrapper: BlazeCallNativeWrapper::new(env).unwrap(),
                 cBlazeOnHeapSpillManager: BlazeOnHeapSpillManager::new(env).unwrap(),
+                cBlazeNativeMemoryConsumer: BlazeNativeMemoryConsumer::new(env).unwrap(),
+                cBlazeUnsupportedPlanException: BlazeUnsupportedPlanException::new(env).unwrap(),
             };
             log::info!("Initializing JavaClasses finished");
             java_classes
@@ -503,6 +598,10 @@ pub struct JniBridge<'a> {
     pub method_getTaskContext_ret: ReturnType,
     pub method_getTaskOnHeapSpillManager: JStaticMethodID,
     pub method_getTaskOnHeapSpillManager_ret: ReturnType,
+    pub method_getTaskNativeMemoryConsumer: JStaticMethodID,
+    pub method_getTaskNativeMemoryConsumer_ret: ReturnType,
+    pub method_getSparkLocalDirs: JStaticMethodID,
+    pub method_getSparkLocalDirs_ret: ReturnType,
     pub method_isTaskRunning: JStaticMethodID,
     pub method_isTaskRunning_ret: ReturnType,
     pub method_isDriverSide: JStaticMethodID,
@@ -551,6 +650,18 @@ impl<'a> JniBridge<'a> {
                 "()Lorg/apache/spark/sql/blaze/memory/OnHeapSpillManager;",
             )?,
             method_getTaskOnHeapSpillManager_ret: ReturnType::Object,
+            method_getTaskNativeMemoryConsumer: env.get_static_method_id(
+                class,
+                "getTaskNativeMemoryConsumer",
+                "()Lorg/apache/spark/sql/blaze/memory/NativeMemoryConsumer;",
+            )?,
+            method_getTaskNativeMemoryConsumer_ret: ReturnType::Object,
+            method_getSparkLocalDirs: env.get_static_method_id(
+                class,
+                "getSparkLocalDirs",
+                "()Ljava/lang/String;",
+            )?,
+            method_getSparkLocalDirs_ret: ReturnType::Object,
             method_isTaskRunning: env.get_static_method_id(class, "isTaskRunning", "()Z")?,
             method_isTaskRunning_ret: ReturnType::Primitive(Primitive::Boolean),
             method_isDriverSide: env.get_static_method_id(class, "isDriverSide", "()Z")?,
@@ -849,6 +960,10 @@ pub struct JavaBuffer<'a> {
     pub method_hasRemaining_ret: ReturnType,
     pub method_position: JMethodID,
     pub method_position_ret: ReturnType,
+    pub method_clear: JMethodID,
+    pub method_clear_ret: ReturnType,
+    pub method_limit: JMethodID,
+    pub method_limit_ret: ReturnType,
 }
 impl<'a> JavaBuffer<'a> {
     pub const SIG_TYPE: &'static str = "java/nio/Buffer";
@@ -861,6 +976,29 @@ impl<'a> JavaBuffer<'a> {
             method_hasRemaining_ret: ReturnType::Primitive(Primitive::Boolean),
             method_position: env.get_method_id(class, "position", "()I")?,
             method_position_ret: ReturnType::Primitive(Primitive::Int),
+            method_clear: env.get_method_id(class, "clear", "()Ljava/nio/Buffer;")?,
+            method_clear_ret: ReturnType::Object,
+            method_limit: env.get_method_id(class, "limit", "(I)Ljava/nio/Buffer;")?,
+            method_limit_ret: ReturnType::Object,
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+pub struct JavaByteBuffer<'a> {
+    pub class: JClass<'a>,
+    pub method_wrap: JStaticMethodID,
+    pub method_wrap_ret: ReturnType,
+}
+impl<'a> JavaByteBuffer<'a> {
+    pub const SIG_TYPE: &'static str = "java/nio/ByteBuffer";
+
+    pub fn new(env: &JNIEnv<'a>) -> JniResult<JavaByteBuffer<'a>> {
+        let class = get_global_jclass(env, Self::SIG_TYPE)?;
+        Ok(JavaByteBuffer {
+            class,
+            method_wrap: env.get_static_method_id(class, "wrap", "([B)Ljava/nio/ByteBuffer;")?,
+            method_wrap_ret: ReturnType::Object,
         })
     }
 }
@@ -1092,10 +1230,8 @@ impl<'a> SparkSQLMetric<'a> {
 #[allow(non_snake_case)]
 pub struct SparkMetricNode<'a> {
     pub class: JClass<'a>,
-    pub method_getChild: JMethodID,
-    pub method_getChild_ret: ReturnType,
-    pub method_add: JMethodID,
-    pub method_add_ret: ReturnType,
+    pub method_addAll: JMethodID,
+    pub method_addAll_ret: ReturnType,
 }
 impl<'a> SparkMetricNode<'a> {
     pub const SIG_TYPE: &'static str = "org/apache/spark/sql/blaze/MetricNode";
@@ -1104,18 +1240,10 @@ impl<'a> SparkMetricNode<'a> {
         let class = get_global_jclass(env, Self::SIG_TYPE)?;
         Ok(SparkMetricNode {
             class,
-            method_getChild: env
-                .get_method_id(
-                    class,
-                    "getChild",
-                    "(I)Lorg/apache/spark/sql/blaze/MetricNode;",
-                )
+            method_addAll: env
+                .get_method_id(class, "addAll", "([J[Ljava/lang/String;[J)V")
                 .unwrap(),
-            method_getChild_ret: ReturnType::Object,
-            method_add: env
-                .get_method_id(class, "add", "(Ljava/lang/String;J)V")
-                .unwrap(),
-            method_add_ret: ReturnType::Primitive(Primitive::Void),
+            method_addAll_ret: ReturnType::Primitive(Primitive::Void),
         })
     }
 }
@@ -1125,6 +1253,8 @@ pub struct BlazeConf<'a> {
     pub class: JClass<'a>,
     pub method_batchSize: JStaticMethodID,
     pub method_batchSize_ret: ReturnType,
+    pub method_batchMemBytes: JStaticMethodID,
+    pub method_batchMemBytes_ret: ReturnType,
     pub method_memoryFraction: JStaticMethodID,
     pub method_memoryFraction_ret: ReturnType,
     pub method_enableBhjFallbacksToSmj: JStaticMethodID,
@@ -1137,8 +1267,40 @@ pub struct BlazeConf<'a> {
     pub method_udfWrapperNumThreads_ret: ReturnType,
     pub method_enableInputBatchStatistics: JStaticMethodID,
     pub method_enableInputBatchStatistics_ret: ReturnType,
+    pub method_enableBatchAccounting: JStaticMethodID,
+    pub method_enableBatchAccounting_ret: ReturnType,
+    pub method_shuffleReadPrefetchSegments: JStaticMethodID,
+    pub method_shuffleReadPrefetchSegments_ret: ReturnType,
+    pub method_shuffleReadPrefetchMemThreshold: JStaticMethodID,
+    pub method_shuffleReadPrefetchMemThreshold_ret: ReturnType,
+    pub method_shuffleReadMmapEnabled: JStaticMethodID,
+    pub method_shuffleReadMmapEnabled_ret: ReturnType,
+    pub method_shufflePartitionFlushBytes: JStaticMethodID,
+    pub method_shufflePartitionFlushBytes_ret: ReturnType,
+    pub method_shuffleCompactionTargetBytes: JStaticMethodID,
+    pub method_shuffleCompactionTargetBytes_ret: ReturnType,
+    pub method_shuffleAggregateEnabled: JStaticMethodID,
+    pub method_shuffleAggregateEnabled_ret: ReturnType,
+    pub method_shuffleAggregateMemBytes: JStaticMethodID,
+    pub method_shuffleAggregateMemBytes_ret: ReturnType,
     pub method_ignoreCorruptedFiles: JStaticMethodID,
     pub method_ignoreCorruptedFiles_ret: ReturnType,
+    pub method_jniBufferSize: JStaticMethodID,
+    pub method_jniBufferSize_ret: ReturnType,
+    pub method_jniTransport: JStaticMethodID,
+    pub method_jniTransport_ret: ReturnType,
+    pub method_spillCompressionCodec: JStaticMethodID,
+    pub method_spillCompressionCodec_ret: ReturnType,
+    pub method_spillOnHeapEnabled: JStaticMethodID,
+    pub method_spillOnHeapEnabled_ret: ReturnType,
+    pub method_topKMaxLimit: JStaticMethodID,
+    pub method_topKMaxLimit_ret: ReturnType,
+    pub method_nativeUdfLibraries: JStaticMethodID,
+    pub method_nativeUdfLibraries_ret: ReturnType,
+    pub method_traceEnabled: JStaticMethodID,
+    pub method_traceEnabled_ret: ReturnType,
+    pub method_traceDir: JStaticMethodID,
+    pub method_traceDir_ret: ReturnType,
 }
 
 impl<'a> BlazeConf<'_> {
@@ -1150,6 +1312,10 @@ impl<'a> BlazeConf<'_> {
             class,
             method_batchSize: env.get_static_method_id(class, "batchSize", "()I").unwrap(),
             method_batchSize_ret: ReturnType::Primitive(Primitive::Int),
+            method_batchMemBytes: env
+                .get_static_method_id(class, "batchMemBytes", "()I")
+                .unwrap(),
+            method_batchMemBytes_ret: ReturnType::Primitive(Primitive::Int),
             method_memoryFraction: env
                 .get_static_method_id(class, "memoryFraction", "()D")
                 .unwrap(),
@@ -1174,10 +1340,74 @@ impl<'a> BlazeConf<'_> {
                 .get_static_method_id(class, "enableInputBatchStatistics", "()Z")
                 .unwrap(),
             method_enableInputBatchStatistics_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_enableBatchAccounting: env
+                .get_static_method_id(class, "enableBatchAccounting", "()Z")
+                .unwrap(),
+            method_enableBatchAccounting_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_shuffleReadPrefetchSegments: env
+                .get_static_method_id(class, "shuffleReadPrefetchSegments", "()I")
+                .unwrap(),
+            method_shuffleReadPrefetchSegments_ret: ReturnType::Primitive(Primitive::Int),
+            method_shuffleReadPrefetchMemThreshold: env
+                .get_static_method_id(class, "shuffleReadPrefetchMemThreshold", "()I")
+                .unwrap(),
+            method_shuffleReadPrefetchMemThreshold_ret: ReturnType::Primitive(Primitive::Int),
+            method_shuffleReadMmapEnabled: env
+                .get_static_method_id(class, "shuffleReadMmapEnabled", "()Z")
+                .unwrap(),
+            method_shuffleReadMmapEnabled_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_shufflePartitionFlushBytes: env
+                .get_static_method_id(class, "shufflePartitionFlushBytes", "()I")
+                .unwrap(),
+            method_shufflePartitionFlushBytes_ret: ReturnType::Primitive(Primitive::Int),
+            method_shuffleCompactionTargetBytes: env
+                .get_static_method_id(class, "shuffleCompactionTargetBytes", "()I")
+                .unwrap(),
+            method_shuffleCompactionTargetBytes_ret: ReturnType::Primitive(Primitive::Int),
+            method_shuffleAggregateEnabled: env
+                .get_static_method_id(class, "shuffleAggregateEnabled", "()Z")
+                .unwrap(),
+            method_shuffleAggregateEnabled_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_shuffleAggregateMemBytes: env
+                .get_static_method_id(class, "shuffleAggregateMemBytes", "()I")
+                .unwrap(),
+            method_shuffleAggregateMemBytes_ret: ReturnType::Primitive(Primitive::Int),
             method_ignoreCorruptedFiles: env
                 .get_static_method_id(class, "ignoreCorruptedFiles", "()Z")
                 .unwrap(),
             method_ignoreCorruptedFiles_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_jniBufferSize: env
+                .get_static_method_id(class, "jniBufferSize", "()I")
+                .unwrap(),
+            method_jniBufferSize_ret: ReturnType::Primitive(Primitive::Int),
+            method_jniTransport: env
+                .get_static_method_id(class, "jniTransport", "()Ljava/lang/String;")
+                .unwrap(),
+            method_jniTransport_ret: ReturnType::Object,
+            method_spillCompressionCodec: env
+                .get_static_method_id(class, "spillCompressionCodec", "()Ljava/lang/String;")
+                .unwrap(),
+            method_spillCompressionCodec_ret: ReturnType::Object,
+            method_spillOnHeapEnabled: env
+                .get_static_method_id(class, "spillOnHeapEnabled", "()Z")
+                .unwrap(),
+            method_spillOnHeapEnabled_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_topKMaxLimit: env
+                .get_static_method_id(class, "topKMaxLimit", "()I")
+                .unwrap(),
+            method_topKMaxLimit_ret: ReturnType::Primitive(Primitive::Int),
+            method_nativeUdfLibraries: env
+                .get_static_method_id(class, "nativeUdfLibraries", "()Ljava/lang/String;")
+                .unwrap(),
+            method_nativeUdfLibraries_ret: ReturnType::Object,
+            method_traceEnabled: env
+                .get_static_method_id(class, "traceEnabled", "()Z")
+                .unwrap(),
+            method_traceEnabled_ret: ReturnType::Primitive(Primitive::Boolean),
+            method_traceDir: env
+                .get_static_method_id(class, "traceDir", "()Ljava/lang/String;")
+                .unwrap(),
+            method_traceDir_ret: ReturnType::Object,
         })
     }
 }
@@ -1322,6 +1552,50 @@ impl<'a> BlazeOnHeapSpillManager<'a> {
     }
 }
 
+#[allow(non_snake_case)]
+pub struct BlazeNativeMemoryConsumer<'a> {
+    pub class: JClass<'a>,
+    pub method_acquire: JMethodID,
+    pub method_acquire_ret: ReturnType,
+    pub method_release: JMethodID,
+    pub method_release_ret: ReturnType,
+}
+impl<'a> BlazeNativeMemoryConsumer<'a> {
+    pub const SIG_TYPE: &'static str = "org/apache/spark/sql/blaze/memory/NativeMemoryConsumer";
+
+    pub fn new(env: &JNIEnv<'a>) -> JniResult<BlazeNativeMemoryConsumer<'a>> {
+        let class = get_global_jclass(env, Self::SIG_TYPE)?;
+        Ok(BlazeNativeMemoryConsumer {
+            class,
+            method_acquire: env.get_method_id(class, "acquire", "(J)J").unwrap(),
+            method_acquire_ret: ReturnType::Primitive(Primitive::Long),
+            method_release: env.get_method_id(class, "release", "(J)V").unwrap(),
+            method_release_ret: ReturnType::Primitive(Primitive::Void),
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+pub struct BlazeUnsupportedPlanException<'a> {
+    pub class: JClass<'a>,
+    pub ctor: JMethodID,
+}
+impl<'a> BlazeUnsupportedPlanException<'a> {
+    pub const SIG_TYPE: &'static str = "org/apache/spark/sql/blaze/BlazeUnsupportedPlanException";
+
+    pub fn new(env: &JNIEnv<'a>) -> JniResult<BlazeUnsupportedPlanException<'a>> {
+        let class = get_global_jclass(env, Self::SIG_TYPE)?;
+        Ok(BlazeUnsupportedPlanException {
+            class,
+            ctor: env.get_method_id(
+                class,
+                "<init>",
+                "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)V",
+            )?,
+        })
+    }
+}
+
 fn get_global_jclass(env: &JNIEnv<'_>, cls: &str) -> JniResult<JClass<'static>> {
     let local_jclass = env.find_class(cls)?;
     Ok(get_global_ref_jobject(env, local_jclass.into())?.into())
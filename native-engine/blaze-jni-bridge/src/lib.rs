@@ -17,6 +17,7 @@ use jni::objects::GlobalRef;
 use jni::sys::{JNI_FALSE, JNI_TRUE};
 use once_cell::sync::OnceCell;
 
+pub mod jni_blocking;
 pub mod jni_bridge;
 
 pub fn is_jni_bridge_inited() -> bool {
@@ -34,7 +34,36 @@ pub enum PlanSerDeError {
     DataFusionError(DataFusionError),
     IoError(io::Error),
     MissingRequiredField(String),
-    UnknownEnumVariant { name: String, value: i32 },
+    UnknownEnumVariant {
+        name: String,
+        value: i32,
+    },
+    UnsupportedPlan {
+        operator: String,
+        expr: Option<String>,
+        reason: UnsupportedReason,
+    },
+}
+
+/// Reason why a plan is not supported by the native engine, names are kept in
+/// sync with `BlazeUnsupportedPlanException.Reason` on the jvm side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsupportedReason {
+    Operator,
+    Expression,
+    DataType,
+    Function,
+}
+
+impl UnsupportedReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnsupportedReason::Operator => "OPERATOR",
+            UnsupportedReason::Expression => "EXPRESSION",
+            UnsupportedReason::DataType => "DATA_TYPE",
+            UnsupportedReason::Function => "FUNCTION",
+        }
+    }
 }
 
 #[allow(clippy::from_over_into)]
@@ -93,6 +122,17 @@ impl Display for PlanSerDeError {
             Self::UnknownEnumVariant { name, value } => {
                 write!(f, "Unknown i32 value for {} enum: {}", name, value)
             }
+            Self::UnsupportedPlan {
+                operator,
+                expr,
+                reason,
+            } => {
+                write!(f, "Unsupported plan: operator={}", operator)?;
+                if let Some(expr) = expr {
+                    write!(f, ", expr={}", expr)?;
+                }
+                write!(f, ", reason={}", reason.name())
+            }
         }
     }
 }
@@ -103,6 +143,35 @@ impl PlanSerDeError {
     pub(crate) fn required(field: impl Into<String>) -> PlanSerDeError {
         PlanSerDeError::MissingRequiredField(field.into())
     }
+
+    pub(crate) fn unsupported(
+        expr: impl Into<String>,
+        reason: UnsupportedReason,
+    ) -> PlanSerDeError {
+        PlanSerDeError::UnsupportedPlan {
+            operator: String::new(),
+            expr: Some(expr.into()),
+            reason,
+        }
+    }
+
+    /// Fills the operator name of an unsupported plan error raised while
+    /// converting the operator's expressions, errors of child operators which
+    /// already have their operator names are kept untouched.
+    pub(crate) fn with_operator(self, operator: impl FnOnce() -> String) -> PlanSerDeError {
+        match self {
+            PlanSerDeError::UnsupportedPlan {
+                operator: op,
+                expr,
+                reason,
+            } if op.is_empty() => PlanSerDeError::UnsupportedPlan {
+                operator: operator(),
+                expr,
+                reason,
+            },
+            err => err,
+        }
+    }
 }
 
 /// An extension trait that adds the methods `optional` and `required` to any
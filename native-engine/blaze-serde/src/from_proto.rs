@@ -21,12 +21,13 @@ use arrow::datatypes::{FieldRef, SchemaRef};
 use base64::prelude::BASE64_URL_SAFE_NO_PAD;
 use base64::Engine;
 use chrono::DateTime;
+use datafusion::common::tree_node::{Transformed, TreeNode};
 use datafusion::datasource::listing::{FileRange, PartitionedFile};
 use datafusion::datasource::object_store::ObjectStoreUrl;
 use datafusion::datasource::physical_plan::FileScanConfig;
 use datafusion::error::DataFusionError;
 use datafusion::execution::context::ExecutionProps;
-use datafusion::logical_expr::{BuiltinScalarFunction, Operator};
+use datafusion::logical_expr::{BuiltinScalarFunction, JoinType, Operator};
 use datafusion::physical_expr::expressions::{LikeExpr, SCAndExpr, SCOrExpr};
 use datafusion::physical_expr::{functions, ScalarFunctionExpr};
 use datafusion::physical_plan::joins::utils::{ColumnIndex, JoinFilter};
@@ -42,13 +43,19 @@ use datafusion::physical_plan::{
 };
 use datafusion::physical_plan::{ColumnStatistics, ExecutionPlan, PhysicalExpr, Statistics};
 
+use datafusion_ext_commons::cast::SparkCastOptions;
+use datafusion_ext_commons::io::{IpcCompressionCodec, DEFAULT_COMPRESSION_LEVEL};
 use datafusion_ext_commons::streams::ipc_stream::IpcReadMode;
 use datafusion_ext_plans::agg::{
     create_agg, AggExecMode, AggExpr, AggFunction, AggMode, GroupingExpr,
 };
 use datafusion_ext_plans::agg_exec::AggExec;
+use datafusion_ext_plans::batch_memory_guard_exec::BatchMemoryGuardExec;
+use datafusion_ext_plans::broadcast_hash_join_exec::BroadcastHashJoinExec;
 use datafusion_ext_plans::broadcast_join_exec::BroadcastJoinExec;
+use datafusion_ext_plans::collect_limit_exec::CollectLimitExec;
 use datafusion_ext_plans::debug_exec::DebugExec;
+use datafusion_ext_plans::decimal_repr_exec::DecimalReprExec;
 use datafusion_ext_plans::empty_partitions_exec::EmptyPartitionsExec;
 use datafusion_ext_plans::expand_exec::ExpandExec;
 use datafusion_ext_plans::ffi_reader_exec::FFIReaderExec;
@@ -56,25 +63,36 @@ use datafusion_ext_plans::filter_exec::FilterExec;
 use datafusion_ext_plans::ipc_reader_exec::IpcReaderExec;
 use datafusion_ext_plans::ipc_writer_exec::IpcWriterExec;
 use datafusion_ext_plans::limit_exec::LimitExec;
-use datafusion_ext_plans::parquet_exec::ParquetExec;
+use datafusion_ext_plans::parquet_exec::{DynamicPruningFilter, ParquetExec};
 use datafusion_ext_plans::project_exec::ProjectExec;
 use datafusion_ext_plans::rename_columns_exec::RenameColumnsExec;
+use datafusion_ext_plans::reservoir_sample_exec::ReservoirSampleExec;
 use datafusion_ext_plans::rss_shuffle_writer_exec::RssShuffleWriterExec;
 use datafusion_ext_plans::shuffle_writer_exec::ShuffleWriterExec;
+use datafusion_ext_plans::shuffled_hash_join_exec::ShuffledHashJoinExec;
 use datafusion_ext_plans::sort_exec::SortExec;
 use datafusion_ext_plans::sort_merge_join_exec::SortMergeJoinExec;
+use datafusion_ext_plans::topk_exec::{topk_max_limit, TopKExec};
+use datafusion_ext_plans::union_exec::try_new_union_with_type_widening;
 use object_store::path::Path;
 use object_store::ObjectMeta;
 
-use crate::error::PlanSerDeError;
+use crate::error::{PlanSerDeError, UnsupportedReason};
 use crate::protobuf::physical_expr_node::ExprType;
 use crate::protobuf::physical_plan_node::PhysicalPlanType;
 use crate::protobuf::GenerateFunction;
 use crate::{convert_box_required, convert_required, into_required, protobuf, Schema};
 use crate::{from_proto_binary_op, proto_error};
+use datafusion_ext_exprs::binary_arithmetic::{BinaryArithmeticExpr, BinaryArithmeticOp};
+use datafusion_ext_exprs::bloom_filter_might_contain::BloomFilterMightContainExpr;
+use datafusion_ext_exprs::case_when::CaseWhenExpr;
 use datafusion_ext_exprs::cast::TryCastExpr;
+use datafusion_ext_exprs::column_literal_compare::ColumnLiteralCompareExpr;
 use datafusion_ext_exprs::get_indexed_field::GetIndexedFieldExpr;
 use datafusion_ext_exprs::get_map_value::GetMapValueExpr;
+use datafusion_ext_exprs::higher_order_function::{HigherOrderFunction, HigherOrderFunctionExpr};
+use datafusion_ext_exprs::in_set::InSetExpr;
+use datafusion_ext_exprs::lambda::{LambdaFunction, LambdaVariableExpr};
 use datafusion_ext_exprs::named_struct::NamedStructExpr;
 use datafusion_ext_exprs::spark_scalar_subquery_wrapper::SparkScalarSubqueryWrapperExpr;
 use datafusion_ext_exprs::spark_udf_wrapper::SparkUDFWrapperExpr;
@@ -82,10 +100,16 @@ use datafusion_ext_exprs::string_contains::StringContainsExpr;
 use datafusion_ext_exprs::string_ends_with::StringEndsWithExpr;
 use datafusion_ext_exprs::string_starts_with::StringStartsWithExpr;
 use datafusion_ext_plans::broadcast_nested_loop_join_exec::BroadcastNestedLoopJoinExec;
+use datafusion_ext_plans::csv_scan_exec::{CsvParseMode, CsvScanExec, CsvScanOptions};
+use datafusion_ext_plans::flatten_struct_exec::FlattenStructExec;
 use datafusion_ext_plans::generate::create_generator;
 use datafusion_ext_plans::generate_exec::GenerateExec;
+use datafusion_ext_plans::generate_id_exec::GenerateIdExec;
 use datafusion_ext_plans::parquet_sink_exec::ParquetSinkExec;
-use datafusion_ext_plans::window::{WindowExpr, WindowFunction, WindowRankType};
+use datafusion_ext_plans::partition_by_column_exec::PartitionByColumnExec;
+use datafusion_ext_plans::window::{
+    WindowExpr, WindowFrame, WindowFunction, WindowOffsetType, WindowRankType,
+};
 use datafusion_ext_plans::window_exec::WindowExec;
 
 fn bind(
@@ -113,16 +137,51 @@ fn bind(
     }
 }
 
+/// Binds the expression, and uses fast path for simple column-vs-literal
+/// comparisons. only used for evaluated expressions (filter and projection),
+/// pruning predicates must keep `BinaryExpr` which is recognized by pruning.
+fn bind_with_fast_compares(
+    expr_in: Arc<dyn PhysicalExpr>,
+    input_schema: &Arc<Schema>,
+) -> Result<Arc<dyn PhysicalExpr>, DataFusionError> {
+    bind(expr_in, input_schema)?.transform_up(&|expr: Arc<dyn PhysicalExpr>| {
+        if let Some(binary) = expr.as_any().downcast_ref::<BinaryExpr>() {
+            if let Some(compare_expr) = ColumnLiteralCompareExpr::try_new(
+                binary.left(),
+                *binary.op(),
+                binary.right(),
+                input_schema,
+            ) {
+                return Ok(Transformed::Yes(Arc::new(compare_expr)));
+            }
+        }
+        Ok(Transformed::No(expr))
+    })
+}
+
 impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
     type Error = PlanSerDeError;
 
     fn try_into(self) -> Result<Arc<dyn ExecutionPlan>, Self::Error> {
-        let plan = self.physical_plan_type.as_ref().ok_or_else(|| {
-            proto_error(format!(
-                "physical_plan::from_proto() Unsupported physical plan '{:?}'",
-                self
-            ))
-        })?;
+        // a missing plan type means the plan is unknown to the native engine
+        let plan =
+            self.physical_plan_type
+                .as_ref()
+                .ok_or_else(|| PlanSerDeError::UnsupportedPlan {
+                    operator: "Unknown".to_owned(),
+                    expr: None,
+                    reason: UnsupportedReason::Operator,
+                })?;
+        self.try_parse_physical_plan(plan)
+            .map_err(|err| err.with_operator(|| plan_type_name(plan)))
+    }
+}
+
+impl protobuf::PhysicalPlanNode {
+    fn try_parse_physical_plan(
+        &self,
+        plan: &PhysicalPlanType,
+    ) -> Result<Arc<dyn ExecutionPlan>, PlanSerDeError> {
         match plan {
             PhysicalPlanType::Projection(projection) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(projection.input)?;
@@ -132,14 +191,14 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     .zip(projection.expr_name.iter())
                     .map(|(expr, name)| {
                         Ok((
-                            bind(
+                            bind_with_fast_compares(
                                 try_parse_physical_expr(expr, &input.schema())?,
                                 &input.schema(),
                             )?,
                             name.to_string(),
                         ))
                     })
-                    .collect::<Result<Vec<(Arc<dyn PhysicalExpr>, String)>, Self::Error>>()?;
+                    .collect::<Result<Vec<(Arc<dyn PhysicalExpr>, String)>, PlanSerDeError>>()?;
                 Ok(Arc::new(ProjectExec::try_new(exprs, input)?))
             }
             PhysicalPlanType::Filter(filter) => {
@@ -148,13 +207,21 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     .expr
                     .iter()
                     .map(|expr| {
-                        Ok(bind(
+                        Ok(bind_with_fast_compares(
                             try_parse_physical_expr(expr, &input.schema())?,
                             &input.schema(),
                         )?)
                     })
-                    .collect::<Result<_, Self::Error>>()?;
-                Ok(Arc::new(FilterExec::try_new(predicates, input)?))
+                    .collect::<Result<_, PlanSerDeError>>()?;
+                let mut filter_exec = FilterExec::try_new(predicates, input.clone())?;
+                if let Some(bloom_filter_key) = &filter.bloom_filter_key {
+                    let key_expr = bind(
+                        try_parse_physical_expr(bloom_filter_key, &input.schema())?,
+                        &input.schema(),
+                    )?;
+                    filter_exec = filter_exec.try_with_bloom_filter_pushdown(key_expr)?;
+                }
+                Ok(Arc::new(filter_exec))
             }
             PhysicalPlanType::ParquetScan(scan) => {
                 let conf: FileScanConfig = scan.base_conf.as_ref().unwrap().try_into()?;
@@ -167,10 +234,59 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     .fold(phys_expr::lit(true), |a, b| {
                         Arc::new(BinaryExpr::new(a, Operator::And, b))
                     });
+                let dynamic_pruning_filters = scan
+                    .dynamic_pruning_filters
+                    .iter()
+                    .map(|filter| DynamicPruningFilter {
+                        partition_column_index: filter.partition_column_index as usize,
+                        key_set_provider_resource_id: filter.key_set_provider_resource_id.clone(),
+                    })
+                    .collect();
                 Ok(Arc::new(ParquetExec::new(
                     conf,
                     scan.fs_resource_id.clone(),
                     Some(predicate),
+                    dynamic_pruning_filters,
+                )))
+            }
+            PhysicalPlanType::CsvScan(scan) => {
+                let conf: FileScanConfig = scan.base_conf.as_ref().unwrap().try_into()?;
+                let parse_char = |name: &str, value: &str| match value.as_bytes() {
+                    &[c] => Ok(c),
+                    _ => Err(proto_error(format!(
+                        "CsvScanExec: {} must be a single character, got {:?}",
+                        name, value,
+                    ))),
+                };
+                let options = CsvScanOptions {
+                    delimiter: parse_char("delimiter", &scan.delimiter)?,
+                    quote: parse_char("quote", &scan.quote)?,
+                    escape: match scan.escape.as_str() {
+                        "" => None,
+                        escape => Some(parse_char("escape", escape)?),
+                    },
+                    has_header: scan.header,
+                    null_value: scan.null_value.clone(),
+                    mode: match protobuf::CsvParseMode::from_i32(scan.mode) {
+                        Some(protobuf::CsvParseMode::Permissive) => CsvParseMode::Permissive,
+                        Some(protobuf::CsvParseMode::DropMalformed) => CsvParseMode::DropMalformed,
+                        Some(protobuf::CsvParseMode::FailFast) => CsvParseMode::FailFast,
+                        None => {
+                            return Err(proto_error(format!(
+                                "Received an unknown CsvParseMode: {}",
+                                scan.mode,
+                            )))
+                        }
+                    },
+                    corrupt_record_column: match scan.column_name_of_corrupt_record.as_str() {
+                        "" => None,
+                        name => Some(name.to_string()),
+                    },
+                };
+                Ok(Arc::new(CsvScanExec::new(
+                    conf,
+                    scan.fs_resource_id.clone(),
+                    options,
                 )))
             }
             PhysicalPlanType::SortMergeJoin(sort_merge_join) => {
@@ -188,7 +304,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                             Column::new_with_schema(right_col.name(), &right.schema())?;
                         Ok((left_col_binded, right_col_binded))
                     })
-                    .collect::<Result<_, Self::Error>>()?;
+                    .collect::<Result<_, PlanSerDeError>>()?;
 
                 let sort_options = sort_merge_join
                     .sort_options
@@ -233,14 +349,24 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                         ))
                     })
                     .map_or(Ok(None), |v: Result<_, PlanSerDeError>| v.map(Some))?;
-                Ok(Arc::new(SortMergeJoinExec::try_new(
+                let buffer_spill_threshold = match sort_merge_join.buffer_spill_threshold {
+                    0 => usize::MAX,
+                    threshold => threshold as usize,
+                };
+                let mut join = SortMergeJoinExec::try_new(
                     left,
                     right,
                     on,
                     join_type.into(),
                     join_filter,
                     sort_options,
-                )?))
+                )?
+                .with_buffer_spill_threshold(buffer_spill_threshold);
+                if !sort_merge_join.existence_column_name.is_empty() {
+                    join =
+                        join.with_existence_column(sort_merge_join.existence_column_name.clone())?;
+                }
+                Ok(Arc::new(join))
             }
             PhysicalPlanType::ShuffleWriter(shuffle_writer) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(shuffle_writer.input)?;
@@ -250,12 +376,18 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     shuffle_writer.output_partitioning.as_ref(),
                 )?;
 
-                Ok(Arc::new(ShuffleWriterExec::try_new(
-                    input,
-                    output_partitioning.unwrap(),
-                    shuffle_writer.output_data_file.clone(),
-                    shuffle_writer.output_index_file.clone(),
-                )?))
+                Ok(Arc::new(
+                    ShuffleWriterExec::try_new(
+                        input,
+                        output_partitioning.unwrap(),
+                        shuffle_writer.output_data_file.clone(),
+                        shuffle_writer.output_index_file.clone(),
+                    )?
+                    .with_compression_codec(parse_compression_codec(
+                        shuffle_writer.compression_codec,
+                        shuffle_writer.compression_level,
+                    )),
+                ))
             }
             PhysicalPlanType::RssShuffleWriter(rss_shuffle_writer) => {
                 let input: Arc<dyn ExecutionPlan> =
@@ -265,19 +397,28 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     input.clone(),
                     rss_shuffle_writer.output_partitioning.as_ref(),
                 )?;
-                Ok(Arc::new(RssShuffleWriterExec::try_new(
-                    input,
-                    output_partitioning.unwrap(),
-                    rss_shuffle_writer.rss_partition_writer_resource_id.clone(),
-                )?))
+                Ok(Arc::new(
+                    RssShuffleWriterExec::try_new(
+                        input,
+                        output_partitioning.unwrap(),
+                        rss_shuffle_writer.rss_partition_writer_resource_id.clone(),
+                    )?
+                    .with_compression_codec(parse_compression_codec(
+                        rss_shuffle_writer.compression_codec,
+                        rss_shuffle_writer.compression_level,
+                    )),
+                ))
             }
             PhysicalPlanType::IpcWriter(ipc_writer) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(ipc_writer.input)?;
 
-                Ok(Arc::new(IpcWriterExec::new(
-                    input,
-                    ipc_writer.ipc_consumer_resource_id.clone(),
-                )))
+                Ok(Arc::new(
+                    IpcWriterExec::new(input, ipc_writer.ipc_consumer_resource_id.clone())
+                        .with_compression_codec(parse_compression_codec(
+                            ipc_writer.compression_codec,
+                            ipc_writer.compression_level,
+                        )),
+                ))
             }
             PhysicalPlanType::IpcReader(ipc_reader) => {
                 let schema = Arc::new(convert_required!(ipc_reader.schema)?);
@@ -301,45 +442,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
             }
             PhysicalPlanType::Sort(sort) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(sort.input)?;
-                let exprs = sort
-                    .expr
-                    .iter()
-                    .map(|expr| {
-                        let expr = expr.expr_type.as_ref().ok_or_else(|| {
-                            proto_error(format!(
-                                "physical_plan::from_proto() Unexpected expr {:?}",
-                                self
-                            ))
-                        })?;
-                        if let protobuf::physical_expr_node::ExprType::Sort(sort_expr) = expr {
-                            let expr = sort_expr
-                                .expr
-                                .as_ref()
-                                .ok_or_else(|| {
-                                    proto_error(format!(
-                                        "physical_plan::from_proto() Unexpected sort expr {:?}",
-                                        self
-                                    ))
-                                })?
-                                .as_ref();
-                            Ok(PhysicalSortExpr {
-                                expr: bind(
-                                    try_parse_physical_expr(expr, &input.schema())?,
-                                    &input.schema(),
-                                )?,
-                                options: SortOptions {
-                                    descending: !sort_expr.asc,
-                                    nulls_first: sort_expr.nulls_first,
-                                },
-                            })
-                        } else {
-                            Err(PlanSerDeError::General(format!(
-                                "physical_plan::from_proto() {:?}",
-                                self
-                            )))
-                        }
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
+                let exprs = try_parse_physical_sort_exprs(&sort.expr, &input.schema())?;
                 // always preserve partitioning
                 Ok(Arc::new(SortExec::new(
                     input,
@@ -347,6 +450,17 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     sort.fetch_limit.map(|limit| limit as usize),
                 )))
             }
+            PhysicalPlanType::TopK(top_k) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(top_k.input)?;
+                let exprs = try_parse_physical_sort_exprs(&top_k.expr, &input.schema())?;
+                let limit = top_k.limit as usize;
+
+                // large limits fall back to the sort exec, which is memory-managed
+                if limit > topk_max_limit()? {
+                    return Ok(Arc::new(SortExec::new(input, exprs, Some(limit))));
+                }
+                Ok(Arc::new(TopKExec::new(input, exprs, limit)))
+            }
             PhysicalPlanType::BroadcastJoin(broadcast_join) => {
                 let left: Arc<dyn ExecutionPlan> = convert_box_required!(broadcast_join.left)?;
                 let right: Arc<dyn ExecutionPlan> = convert_box_required!(broadcast_join.right)?;
@@ -362,7 +476,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                             Column::new_with_schema(right_col.name(), &right.schema())?;
                         Ok((left_col_binded, right_col_binded))
                     })
-                    .collect::<Result<_, Self::Error>>()?;
+                    .collect::<Result<_, PlanSerDeError>>()?;
 
                 let join_type =
                     protobuf::JoinType::from_i32(broadcast_join.join_type).ok_or_else(|| {
@@ -399,14 +513,109 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     })
                     .map_or(Ok(None), |v: Result<_, PlanSerDeError>| v.map(Some))?;
 
+                let join_type: JoinType = join_type.into();
+                let cached_build_hash_map_id = broadcast_join.cached_build_hash_map_id.clone();
+                let broadcast_side = protobuf::JoinSide::from_i32(broadcast_join.broadcast_side)
+                    .ok_or_else(|| {
+                        proto_error(format!(
+                            "Received a BroadcastJoinNode message with unknown JoinSide {}",
+                            broadcast_join.broadcast_side
+                        ))
+                    })?;
+
+                // the broadcasted side is always the left side of the native join,
+                // swap children if the right side is broadcasted
+                if broadcast_side == protobuf::JoinSide::RightSide {
+                    let join_type = match join_type {
+                        JoinType::LeftSemi => JoinType::RightSemi,
+                        JoinType::LeftAnti => JoinType::RightAnti,
+                        other => {
+                            return Err(proto_error(format!(
+                                "BroadcastJoin with broadcasted right side does not support \
+                                 join type: {:?}",
+                                other
+                            )));
+                        }
+                    };
+                    if join_filter.is_some() {
+                        return Err(proto_error(
+                            "BroadcastJoin with broadcasted right side does not support join filter",
+                        ));
+                    }
+                    let on = on.into_iter().map(|(l, r)| (r, l)).collect();
+                    let mut join = BroadcastHashJoinExec::try_new(
+                        right,
+                        left,
+                        on,
+                        join_type,
+                        cached_build_hash_map_id,
+                    )?
+                    .with_null_aware_anti_join(broadcast_join.null_aware_anti_join)?;
+                    if !broadcast_join.existence_column_name.is_empty() {
+                        join = join
+                            .with_existence_column(broadcast_join.existence_column_name.clone())?;
+                    }
+                    return Ok(Arc::new(join));
+                }
+
+                if !cached_build_hash_map_id.is_empty()
+                    && join_filter.is_none()
+                    && BroadcastHashJoinExec::supports_join_type(join_type)
+                {
+                    return Ok(Arc::new(BroadcastHashJoinExec::try_new(
+                        left,
+                        right,
+                        on,
+                        join_type,
+                        cached_build_hash_map_id,
+                    )?));
+                }
                 Ok(Arc::new(BroadcastJoinExec::try_new(
                     left,
                     right,
                     on,
-                    join_type.into(),
+                    join_type,
                     join_filter,
                 )?))
             }
+            PhysicalPlanType::ShuffledHashJoin(shj) => {
+                let left: Arc<dyn ExecutionPlan> = convert_box_required!(shj.left)?;
+                let right: Arc<dyn ExecutionPlan> = convert_box_required!(shj.right)?;
+                let on: Vec<(Column, Column)> = shj
+                    .on
+                    .iter()
+                    .map(|col| {
+                        let left_col: Column = into_required!(col.left)?;
+                        let left_col_binded: Column =
+                            Column::new_with_schema(left_col.name(), &left.schema())?;
+                        let right_col: Column = into_required!(col.right)?;
+                        let right_col_binded: Column =
+                            Column::new_with_schema(right_col.name(), &right.schema())?;
+                        Ok((left_col_binded, right_col_binded))
+                    })
+                    .collect::<Result<_, PlanSerDeError>>()?;
+
+                let join_type = protobuf::JoinType::from_i32(shj.join_type).ok_or_else(|| {
+                    proto_error(format!(
+                        "Received a ShuffledHashJoinNode message with unknown JoinType {}",
+                        shj.join_type
+                    ))
+                })?;
+                let build_side = protobuf::JoinSide::from_i32(shj.build_side).ok_or_else(|| {
+                    proto_error(format!(
+                        "Received a ShuffledHashJoinNode message with unknown JoinSide {}",
+                        shj.build_side
+                    ))
+                })?;
+
+                Ok(Arc::new(ShuffledHashJoinExec::try_new(
+                    left,
+                    right,
+                    on,
+                    join_type.into(),
+                    build_side.into(),
+                )?))
+            }
             PhysicalPlanType::BroadcastNestedLoopJoin(bnlj) => {
                 let left: Arc<dyn ExecutionPlan> = convert_box_required!(bnlj.left)?;
                 let right: Arc<dyn ExecutionPlan> = convert_box_required!(bnlj.right)?;
@@ -456,6 +665,9 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     .iter()
                     .map(|i| i.try_into())
                     .collect::<Result<Vec<_>, _>>()?;
+                if union.widen_types {
+                    return Ok(Arc::new(try_new_union_with_type_widening(inputs)?));
+                }
                 Ok(Arc::new(UnionExec::new(inputs)))
             }
             PhysicalPlanType::EmptyPartitions(empty_partitions) => {
@@ -568,7 +780,9 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
             }
             PhysicalPlanType::Limit(limit) => {
                 let input: Arc<dyn ExecutionPlan> = convert_box_required!(limit.input)?;
-                Ok(Arc::new(LimitExec::new(input, limit.limit)))
+                Ok(Arc::new(
+                    LimitExec::new(input, limit.limit).with_offset(limit.offset),
+                ))
             }
             PhysicalPlanType::FfiReader(ffi_reader) => {
                 let schema = Arc::new(convert_required!(ffi_reader.schema)?);
@@ -598,7 +812,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                                     &input.schema(),
                                 )?)
                             })
-                            .collect::<Result<Vec<_>, Self::Error>>()
+                            .collect::<Result<Vec<_>, PlanSerDeError>>()
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
@@ -631,7 +845,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                                     &input.schema(),
                                 )?)
                             })
-                            .collect::<Result<Vec<_>, Self::Error>>()?;
+                            .collect::<Result<Vec<_>, PlanSerDeError>>()?;
 
                         let window_func = match w.func_type() {
                             protobuf::WindowFunctionType::Window => match w.window_func() {
@@ -644,6 +858,12 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                                 protobuf::WindowFunction::DenseRank => {
                                     WindowFunction::RankLike(WindowRankType::DenseRank)
                                 }
+                                protobuf::WindowFunction::Lag => {
+                                    WindowFunction::Offset(WindowOffsetType::Lag(w.offset))
+                                }
+                                protobuf::WindowFunction::Lead => {
+                                    WindowFunction::Offset(WindowOffsetType::Lead(w.offset))
+                                }
                             },
                             protobuf::WindowFunctionType::Agg => match w.agg_func() {
                                 protobuf::AggFunction::Min => WindowFunction::Agg(AggFunction::Min),
@@ -665,9 +885,29 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                                 protobuf::AggFunction::FirstIgnoresNull => {
                                     WindowFunction::Agg(AggFunction::FirstIgnoresNull)
                                 }
+                                protobuf::AggFunction::Last => {
+                                    WindowFunction::Agg(AggFunction::Last)
+                                }
+                                protobuf::AggFunction::LastIgnoresNull => {
+                                    WindowFunction::Agg(AggFunction::LastIgnoresNull)
+                                }
+                                protobuf::AggFunction::BloomFilter => {
+                                    WindowFunction::Agg(AggFunction::BloomFilter)
+                                }
                             },
                         };
-                        Ok::<_, Self::Error>(WindowExpr::new(window_func, children, field))
+                        let frame = match &w.range_frame {
+                            Some(range_frame) if range_frame.unbounded_preceding => {
+                                WindowFrame::Range { preceding: None }
+                            }
+                            Some(range_frame) => WindowFrame::Range {
+                                preceding: Some(range_frame.preceding),
+                            },
+                            None => WindowFrame::Rows,
+                        };
+                        Ok::<_, PlanSerDeError>(
+                            WindowExpr::new(window_func, children, field).with_frame(frame),
+                        )
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
@@ -680,7 +920,7 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                             &input.schema(),
                         )?)
                     })
-                    .collect::<Result<Vec<_>, Self::Error>>()?;
+                    .collect::<Result<Vec<_>, PlanSerDeError>>()?;
 
                 let order_specs = window
                     .order_spec
@@ -744,6 +984,12 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     GenerateFunction::PosExplode => {
                         datafusion_ext_plans::generate::GenerateFunc::PosExplode
                     }
+                    GenerateFunction::Inline => {
+                        datafusion_ext_plans::generate::GenerateFunc::Inline
+                    }
+                    GenerateFunction::JsonTuple => {
+                        datafusion_ext_plans::generate::GenerateFunc::JsonTuple
+                    }
                 };
                 let children = pb_generator_children
                     .iter()
@@ -789,10 +1035,68 @@ impl TryInto<Arc<dyn ExecutionPlan>> for &protobuf::PhysicalPlanNode {
                     props,
                 )))
             }
+            PhysicalPlanType::BatchMemoryGuard(batch_memory_guard) => {
+                let input: Arc<dyn ExecutionPlan> =
+                    convert_box_required!(batch_memory_guard.input)?;
+                Ok(Arc::new(BatchMemoryGuardExec::new(
+                    input,
+                    batch_memory_guard.max_in_flight_bytes as usize,
+                )))
+            }
+            PhysicalPlanType::CollectLimit(collect_limit) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(collect_limit.input)?;
+                Ok(Arc::new(CollectLimitExec::new(input, collect_limit.limit)))
+            }
+            PhysicalPlanType::DecimalRepr(decimal_repr) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(decimal_repr.input)?;
+                let output_schema = Arc::new(convert_required!(decimal_repr.output_schema)?);
+                Ok(Arc::new(DecimalReprExec::try_new(input, output_schema)?))
+            }
+            PhysicalPlanType::FlattenStruct(flatten_struct) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(flatten_struct.input)?;
+                Ok(Arc::new(FlattenStructExec::try_new(
+                    input,
+                    flatten_struct.struct_column as usize,
+                    flatten_struct.field_names.clone(),
+                )?))
+            }
+            PhysicalPlanType::GenerateId(generate_id) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(generate_id.input)?;
+                let generate_id_exec =
+                    GenerateIdExec::new(input, generate_id.id_column_name.clone());
+                generate_id_exec.set_partition_offsets(generate_id.partition_offsets.clone())?;
+                Ok(Arc::new(generate_id_exec))
+            }
+            PhysicalPlanType::PartitionByColumn(partition_by_column) => {
+                let input: Arc<dyn ExecutionPlan> =
+                    convert_box_required!(partition_by_column.input)?;
+                Ok(Arc::new(PartitionByColumnExec::try_new(
+                    input,
+                    partition_by_column.partition_id_column as usize,
+                    partition_by_column.num_partitions as usize,
+                )?))
+            }
+            PhysicalPlanType::ReservoirSample(reservoir_sample) => {
+                let input: Arc<dyn ExecutionPlan> = convert_box_required!(reservoir_sample.input)?;
+                Ok(Arc::new(ReservoirSampleExec::new(
+                    input,
+                    reservoir_sample.sample_size as usize,
+                    reservoir_sample.seed,
+                )))
+            }
         }
     }
 }
 
+fn plan_type_name(plan: &PhysicalPlanType) -> String {
+    let plan_debug = format!("{:?}", plan);
+    plan_debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
 impl From<&protobuf::PhysicalColumn> for Column {
     fn from(c: &protobuf::PhysicalColumn) -> Column {
         Column::new(&c.name, c.index as usize)
@@ -887,7 +1191,7 @@ fn try_parse_physical_expr(
     let expr_type = expr
         .expr_type
         .as_ref()
-        .ok_or_else(|| proto_error("Unexpected empty physical expression"))?;
+        .ok_or_else(|| PlanSerDeError::unsupported("Unknown", UnsupportedReason::Expression))?;
 
     let pexpr: Arc<dyn PhysicalExpr> = match expr_type {
         ExprType::Column(c) => {
@@ -899,11 +1203,12 @@ fn try_parse_physical_expr(
             let pcol: Column = bound_reference.into();
             Arc::new(pcol)
         }
-        ExprType::BinaryExpr(binary_expr) => Arc::new(BinaryExpr::new(
-            try_parse_physical_expr_box_required(&binary_expr.l.clone(), input_schema)?,
-            from_proto_binary_op(&binary_expr.op)?,
-            try_parse_physical_expr_box_required(&binary_expr.r.clone(), input_schema)?,
-        )),
+        ExprType::BinaryExpr(binary_expr) => {
+            let l = try_parse_physical_expr_box_required(&binary_expr.l.clone(), input_schema)?;
+            let op = from_proto_binary_op(&binary_expr.op)?;
+            let r = try_parse_physical_expr_box_required(&binary_expr.r.clone(), input_schema)?;
+            Arc::new(BinaryExpr::new(l, op, r))
+        }
         ExprType::AggExpr(_) => {
             return Err(PlanSerDeError::General(
                 "Cannot convert aggregate expr node to physical expression".to_owned(),
@@ -929,6 +1234,26 @@ fn try_parse_physical_expr(
             &e.expr,
             input_schema,
         )?)),
+        ExprType::InList(e)
+            if e.list
+                .iter()
+                .all(|x| matches!(&x.expr_type, Some(ExprType::Literal(_)))) =>
+        {
+            let list = e
+                .list
+                .iter()
+                .map(|x| match &x.expr_type {
+                    Some(ExprType::Literal(scalar)) => convert_required!(scalar.value),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<Vec<_>, PlanSerDeError>>()?;
+            Arc::new(InSetExpr::try_new(
+                try_parse_physical_expr_box_required(&e.expr, input_schema)?,
+                list,
+                e.negated,
+                input_schema,
+            )?)
+        }
         ExprType::InList(e) => Arc::new(InListExpr::new(
             try_parse_physical_expr_box_required(&e.expr, input_schema)?,
             e.list
@@ -938,6 +1263,28 @@ fn try_parse_physical_expr(
             e.negated,
             None,
         )),
+        ExprType::Case(e) if e.expr.is_none() => {
+            let when_then_exprs = e
+                .when_then_expr
+                .iter()
+                .map(|e| {
+                    Ok((
+                        try_parse_physical_expr_required(&e.when_expr, input_schema)?,
+                        try_parse_physical_expr_required(&e.then_expr, input_schema)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, PlanSerDeError>>()?;
+            let else_expr = e
+                .else_expr
+                .as_ref()
+                .map(|e| try_parse_physical_expr(e.as_ref(), input_schema))
+                .transpose()?;
+            let return_type = match e.return_type.as_ref() {
+                Some(return_type) => return_type.try_into()?,
+                None => when_then_exprs[0].1.data_type(input_schema)?,
+            };
+            Arc::new(CaseWhenExpr::new(when_then_exprs, else_expr, return_type))
+        }
         ExprType::Case(e) => Arc::new(CaseExpr::try_new(
             e.expr
                 .as_ref()
@@ -965,11 +1312,30 @@ fn try_parse_physical_expr(
         ExprType::TryCast(e) => {
             let expr = try_parse_physical_expr_box_required(&e.expr, input_schema)?;
             let cast_type = convert_required!(e.arrow_type)?;
-            Arc::new(TryCastExpr::new(expr, cast_type))
+            let string_to_timestamp_epoch_unit = if e.string_to_timestamp_epoch_enabled {
+                Some(protobuf::TimeUnit::from_i32_to_arrow(
+                    e.string_to_timestamp_epoch_unit,
+                )?)
+            } else {
+                None
+            };
+            Arc::new(
+                TryCastExpr::new(expr, cast_type).with_cast_options(SparkCastOptions {
+                    string_to_timestamp_epoch_unit,
+                    ansi: e.ansi,
+                    session_timezone: match e.session_timezone.as_str() {
+                        "" => None,
+                        tz => Some(tz.into()),
+                    },
+                }),
+            )
         }
         ExprType::ScalarFunction(e) => {
             let scalar_function = protobuf::ScalarFunction::from_i32(e.fun).ok_or_else(|| {
-                proto_error(format!("Received an unknown scalar function: {}", e.fun,))
+                PlanSerDeError::unsupported(
+                    format!("ScalarFunction({})", e.fun),
+                    UnsupportedReason::Function,
+                )
             })?;
 
             let args = e
@@ -978,9 +1344,18 @@ fn try_parse_physical_expr(
                 .map(|x| try_parse_physical_expr(x, input_schema))
                 .collect::<Result<Vec<_>, _>>()?;
 
+            let return_type = convert_required!(e.return_type)?;
             let execution_props = ExecutionProps::new();
             let fun_expr = if scalar_function == protobuf::ScalarFunction::SparkExtFunctions {
-                datafusion_ext_functions::create_spark_ext_function(&e.name)?
+                let ext_fun =
+                    datafusion_ext_functions::create_spark_ext_function(&e.name, &return_type);
+                ext_fun.map_err(|err| match err {
+                    DataFusionError::NotImplemented(_) => PlanSerDeError::unsupported(
+                        format!("SparkExtFunction({})", e.name),
+                        UnsupportedReason::Function,
+                    ),
+                    err => err.into(),
+                })?
             } else {
                 functions::create_physical_fun(&(&scalar_function).into(), &execution_props)?
             };
@@ -989,7 +1364,7 @@ fn try_parse_physical_expr(
                 &e.name,
                 fun_expr,
                 args,
-                &convert_required!(e.return_type)?,
+                &return_type,
             ))
         }
         ExprType::SparkUdfWrapperExpr(e) => Arc::new(SparkUDFWrapperExpr::try_new(
@@ -1030,6 +1405,15 @@ fn try_parse_physical_expr(
             let expr = try_parse_physical_expr_box_required(&e.expr, input_schema)?;
             Arc::new(StringContainsExpr::new(expr, e.infix.clone()))
         }
+        ExprType::BloomFilterMightContainExpr(e) => {
+            let bloom_filter_expr =
+                try_parse_physical_expr_box_required(&e.bloom_filter_expr, input_schema)?;
+            let value_expr = try_parse_physical_expr_box_required(&e.value_expr, input_schema)?;
+            Arc::new(BloomFilterMightContainExpr::new(
+                bloom_filter_expr,
+                value_expr,
+            ))
+        }
         ExprType::ScAndExpr(e) => {
             let l = try_parse_physical_expr_box_required(&e.left, input_schema)?;
             let r = try_parse_physical_expr_box_required(&e.right, input_schema)?;
@@ -1057,6 +1441,66 @@ fn try_parse_physical_expr(
                 data_type,
             )?)
         }
+        ExprType::BinaryArithmeticExpr(e) => {
+            let op = match protobuf::BinaryArithmeticOp::from_i32(e.op) {
+                Some(protobuf::BinaryArithmeticOp::Add) => BinaryArithmeticOp::Add,
+                Some(protobuf::BinaryArithmeticOp::Subtract) => BinaryArithmeticOp::Subtract,
+                Some(protobuf::BinaryArithmeticOp::Multiply) => BinaryArithmeticOp::Multiply,
+                Some(protobuf::BinaryArithmeticOp::Divide) => BinaryArithmeticOp::Divide,
+                None => {
+                    return Err(proto_error(format!(
+                        "Received an unknown binary arithmetic op: {}",
+                        e.op
+                    )))
+                }
+            };
+            Arc::new(BinaryArithmeticExpr::new(
+                op,
+                try_parse_physical_expr_box_required(&e.l, input_schema)?,
+                try_parse_physical_expr_box_required(&e.r, input_schema)?,
+                convert_required!(e.return_type)?,
+                e.fail_on_error,
+            ))
+        }
+        ExprType::LambdaVariable(e) => Arc::new(LambdaVariableExpr::new(
+            e.name.clone(),
+            convert_required!(e.data_type)?,
+            e.nullable,
+        )),
+        ExprType::HigherOrderFunction(e) => {
+            let func = match protobuf::HigherOrderFunction::from_i32(e.func) {
+                Some(protobuf::HigherOrderFunction::Transform) => HigherOrderFunction::Transform,
+                Some(protobuf::HigherOrderFunction::Filter) => HigherOrderFunction::Filter,
+                Some(protobuf::HigherOrderFunction::Exists) => HigherOrderFunction::Exists {
+                    follow_three_valued_logic: e.follow_three_valued_logic,
+                },
+                Some(protobuf::HigherOrderFunction::Aggregate) => HigherOrderFunction::Aggregate,
+                None => {
+                    return Err(proto_error(format!(
+                        "Received an unknown higher-order function: {}",
+                        e.func
+                    )))
+                }
+            };
+            Arc::new(HigherOrderFunctionExpr::try_new(
+                func,
+                try_parse_physical_expr_box_required(&e.array, input_schema)?,
+                e.args
+                    .iter()
+                    .map(|x| try_parse_physical_expr(x, input_schema))
+                    .collect::<Result<Vec<_>, _>>()?,
+                e.lambdas
+                    .iter()
+                    .map(|lambda| {
+                        Ok(LambdaFunction::new(
+                            try_parse_physical_expr_box_required(&lambda.body, input_schema)?,
+                            lambda.variable_names.clone(),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, PlanSerDeError>>()?,
+                convert_required!(e.return_type)?,
+            )?)
+        }
     };
 
     Ok(pexpr)
@@ -1084,6 +1528,62 @@ fn try_parse_physical_expr_box_required(
     }
 }
 
+fn try_parse_physical_sort_exprs(
+    exprs: &[protobuf::PhysicalExprNode],
+    input_schema: &SchemaRef,
+) -> Result<Vec<PhysicalSortExpr>, PlanSerDeError> {
+    exprs
+        .iter()
+        .map(|expr| {
+            let expr = expr.expr_type.as_ref().ok_or_else(|| {
+                proto_error(format!(
+                    "physical_plan::from_proto() Unexpected expr {:?}",
+                    expr
+                ))
+            })?;
+            if let protobuf::physical_expr_node::ExprType::Sort(sort_expr) = expr {
+                let expr = sort_expr
+                    .expr
+                    .as_ref()
+                    .ok_or_else(|| {
+                        proto_error(format!(
+                            "physical_plan::from_proto() Unexpected sort expr {:?}",
+                            sort_expr
+                        ))
+                    })?
+                    .as_ref();
+                Ok(PhysicalSortExpr {
+                    expr: bind(try_parse_physical_expr(expr, input_schema)?, input_schema)?,
+                    options: SortOptions {
+                        descending: !sort_expr.asc,
+                        nulls_first: sort_expr.nulls_first,
+                    },
+                })
+            } else {
+                Err(PlanSerDeError::General(format!(
+                    "physical_plan::from_proto() {:?}",
+                    expr
+                )))
+            }
+        })
+        .collect()
+}
+
+fn parse_compression_codec(codec: i32, compression_level: i32) -> IpcCompressionCodec {
+    match protobuf::CompressionCodec::from_i32(codec).unwrap_or(protobuf::CompressionCodec::Zstd) {
+        protobuf::CompressionCodec::Zstd => {
+            // unset in the serialized plan
+            if compression_level == 0 {
+                return IpcCompressionCodec::Zstd(DEFAULT_COMPRESSION_LEVEL);
+            }
+            IpcCompressionCodec::Zstd(compression_level)
+        }
+        protobuf::CompressionCodec::Lz4 => IpcCompressionCodec::Lz4,
+        protobuf::CompressionCodec::Snappy => IpcCompressionCodec::Snappy,
+        protobuf::CompressionCodec::Uncompressed => IpcCompressionCodec::Uncompressed,
+    }
+}
+
 pub fn parse_protobuf_hash_partitioning(
     input: Arc<dyn ExecutionPlan>,
     partitioning: Option<&protobuf::PhysicalHashRepartition>,
@@ -1235,3 +1735,57 @@ impl TryInto<FileScanConfig> for &protobuf::FileScanExecConf {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::error::{PlanSerDeError, UnsupportedReason};
+    use crate::protobuf;
+    use crate::protobuf::physical_plan_node::PhysicalPlanType;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::ExecutionPlan;
+    use datafusion_ext_plans::empty_partitions_exec::EmptyPartitionsExec;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_unsupported_plan() -> Result<(), PlanSerDeError> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, true)]));
+        let input: Arc<dyn ExecutionPlan> = Arc::new(EmptyPartitionsExec::new(schema, 1));
+        let input = protobuf::PhysicalPlanNode::try_from(&input)?;
+
+        // unknown expression is reported with its operator
+        let plan = protobuf::PhysicalPlanNode {
+            physical_plan_type: Some(PhysicalPlanType::Projection(
+                protobuf::ProjectionExecNode {
+                    input: Some(input.into()),
+                    expr: vec![protobuf::PhysicalExprNode { expr_type: None }],
+                    expr_name: vec!["a".to_owned()],
+                }
+                .into(),
+            )),
+        };
+        let err = TryInto::<Arc<dyn ExecutionPlan>>::try_into(&plan).unwrap_err();
+        assert!(matches!(
+            err,
+            PlanSerDeError::UnsupportedPlan {
+                ref operator,
+                expr: Some(ref expr),
+                reason: UnsupportedReason::Expression,
+            } if operator == "Projection" && expr == "Unknown"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Unsupported plan: operator=Projection, expr=Unknown, reason=EXPRESSION"
+        );
+
+        // unknown operator
+        let plan = protobuf::PhysicalPlanNode {
+            physical_plan_type: None,
+        };
+        let err = TryInto::<Arc<dyn ExecutionPlan>>::try_into(&plan).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unsupported plan: operator=Unknown, reason=OPERATOR"
+        );
+        Ok(())
+    }
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::error::PlanSerDeError;
+use crate::error::{PlanSerDeError, UnsupportedReason};
 use arrow::datatypes::{DataType, Field, Fields, IntervalUnit, Schema, TimeUnit};
 use datafusion::logical_expr::Operator;
 use datafusion::physical_plan::joins::utils::JoinSide;
@@ -29,6 +29,7 @@ pub mod protobuf {
 
 pub mod error;
 pub mod from_proto;
+pub mod to_proto;
 
 pub(crate) fn proto_error<S: Into<String>>(message: S) -> PlanSerDeError {
     PlanSerDeError::General(message.into())
@@ -94,13 +95,50 @@ pub fn from_proto_binary_op(op: &str) -> Result<Operator, PlanSerDeError> {
         "RegexNotIMatch" => Ok(Operator::RegexNotIMatch),
         "RegexNotMatch" => Ok(Operator::RegexNotMatch),
         "StringConcat" => Ok(Operator::StringConcat),
-        other => Err(proto_error(format!(
-            "Unsupported binary operator '{:?}'",
-            other
-        ))),
+        other => Err(PlanSerDeError::unsupported(
+            format!("BinaryExpr({})", other),
+            UnsupportedReason::Expression,
+        )),
     }
 }
 
+pub fn to_proto_binary_op(op: &Operator) -> Result<String, PlanSerDeError> {
+    let op = match op {
+        Operator::And => "And",
+        Operator::Or => "Or",
+        Operator::Eq => "Eq",
+        Operator::NotEq => "NotEq",
+        Operator::LtEq => "LtEq",
+        Operator::Lt => "Lt",
+        Operator::Gt => "Gt",
+        Operator::GtEq => "GtEq",
+        Operator::Plus => "Plus",
+        Operator::Minus => "Minus",
+        Operator::Multiply => "Multiply",
+        Operator::Divide => "Divide",
+        Operator::Modulo => "Modulo",
+        Operator::IsDistinctFrom => "IsDistinctFrom",
+        Operator::IsNotDistinctFrom => "IsNotDistinctFrom",
+        Operator::BitwiseAnd => "BitwiseAnd",
+        Operator::BitwiseOr => "BitwiseOr",
+        Operator::BitwiseXor => "BitwiseXor",
+        Operator::BitwiseShiftLeft => "BitwiseShiftLeft",
+        Operator::BitwiseShiftRight => "BitwiseShiftRight",
+        Operator::RegexIMatch => "RegexIMatch",
+        Operator::RegexMatch => "RegexMatch",
+        Operator::RegexNotIMatch => "RegexNotIMatch",
+        Operator::RegexNotMatch => "RegexNotMatch",
+        Operator::StringConcat => "StringConcat",
+        other => {
+            return Err(PlanSerDeError::NotImplemented(format!(
+                "Unsupported binary operator '{:?}'",
+                other
+            )))
+        }
+    };
+    Ok(op.to_string())
+}
+
 impl From<protobuf::JoinType> for JoinType {
     fn from(t: protobuf::JoinType) -> Self {
         match t {
@@ -135,6 +173,9 @@ impl From<protobuf::AggFunction> for AggFunction {
             protobuf::AggFunction::CollectSet => AggFunction::CollectSet,
             protobuf::AggFunction::First => AggFunction::First,
             protobuf::AggFunction::FirstIgnoresNull => AggFunction::FirstIgnoresNull,
+            protobuf::AggFunction::Last => AggFunction::Last,
+            protobuf::AggFunction::LastIgnoresNull => AggFunction::LastIgnoresNull,
+            protobuf::AggFunction::BloomFilter => AggFunction::BloomFilter,
         }
     }
 }
@@ -290,7 +331,10 @@ impl TryInto<arrow::datatypes::DataType> for &protobuf::arrow_type::ArrowTypeEnu
                 //     .map(|field| field.try_into())
                 //     .collect::<Result<Vec<_>, _>>()?;
                 // DataType::Union(union_types, _, union_mode)
-                unimplemented!()
+                return Err(PlanSerDeError::unsupported(
+                    "Union",
+                    UnsupportedReason::DataType,
+                ));
             }
             arrow_type::ArrowTypeEnum::Map(map) => {
                 let key_type: &protobuf::Field = map
@@ -0,0 +1,586 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serializes converted native plans back to protobuf, for asserting round-trip
+//! stability in tests and capturing the exact plan a task ran. only the plans
+//! and expressions listed below are supported yet, others are reported as
+//! `PlanSerDeError::NotImplemented`.
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field, Schema};
+use datafusion::physical_plan::expressions::{
+    BinaryExpr, CastExpr, Column, IsNotNullExpr, IsNullExpr, Literal, NegativeExpr, NotExpr,
+    PhysicalSortExpr,
+};
+use datafusion::physical_plan::union::UnionExec;
+use datafusion::physical_plan::{displayable, ExecutionPlan, PhysicalExpr};
+use datafusion::scalar::ScalarValue;
+use datafusion_ext_commons::streams::ipc_stream::IpcReadMode;
+use datafusion_ext_exprs::column_literal_compare::ColumnLiteralCompareExpr;
+use datafusion_ext_plans::debug_exec::DebugExec;
+use datafusion_ext_plans::empty_partitions_exec::EmptyPartitionsExec;
+use datafusion_ext_plans::ffi_reader_exec::FFIReaderExec;
+use datafusion_ext_plans::filter_exec::FilterExec;
+use datafusion_ext_plans::ipc_reader_exec::IpcReaderExec;
+use datafusion_ext_plans::limit_exec::LimitExec;
+use datafusion_ext_plans::project_exec::ProjectExec;
+use datafusion_ext_plans::rename_columns_exec::RenameColumnsExec;
+use datafusion_ext_plans::sort_exec::SortExec;
+
+use crate::error::PlanSerDeError;
+use crate::protobuf::physical_expr_node::ExprType;
+use crate::protobuf::physical_plan_node::PhysicalPlanType;
+use crate::{proto_error, protobuf, to_proto_binary_op};
+
+impl TryFrom<&Arc<dyn ExecutionPlan>> for protobuf::PhysicalPlanNode {
+    type Error = PlanSerDeError;
+
+    fn try_from(plan: &Arc<dyn ExecutionPlan>) -> Result<Self, Self::Error> {
+        let plan_any = plan.as_any();
+        let input = || -> Result<Option<Box<protobuf::PhysicalPlanNode>>, PlanSerDeError> {
+            let children = plan.children();
+            let input = children
+                .first()
+                .ok_or_else(|| proto_error("physical_plan::to_proto() missing input plan"))?;
+            Ok(Some(Box::new(protobuf::PhysicalPlanNode::try_from(input)?)))
+        };
+
+        let physical_plan_type = if let Some(exec) = plan_any.downcast_ref::<ProjectExec>() {
+            let (expr, expr_name) = exec
+                .exprs()
+                .iter()
+                .map(|(expr, name)| Ok((protobuf::PhysicalExprNode::try_from(expr)?, name.clone())))
+                .collect::<Result<Vec<_>, PlanSerDeError>>()?
+                .into_iter()
+                .unzip();
+            PhysicalPlanType::Projection(
+                protobuf::ProjectionExecNode {
+                    input: input()?,
+                    expr,
+                    expr_name,
+                }
+                .into(),
+            )
+        } else if let Some(exec) = plan_any.downcast_ref::<FilterExec>() {
+            PhysicalPlanType::Filter(
+                protobuf::FilterExecNode {
+                    input: input()?,
+                    expr: exec
+                        .predicates()
+                        .iter()
+                        .map(protobuf::PhysicalExprNode::try_from)
+                        .collect::<Result<_, _>>()?,
+                    bloom_filter_key: exec
+                        .bloom_filter_pushdown()
+                        .map(|pushdown| {
+                            protobuf::PhysicalExprNode::try_from(pushdown.key_expr())
+                                .map(Into::into)
+                        })
+                        .transpose()?,
+                }
+                .into(),
+            )
+        } else if let Some(exec) = plan_any.downcast_ref::<SortExec>() {
+            PhysicalPlanType::Sort(
+                protobuf::SortExecNode {
+                    input: input()?,
+                    expr: exec
+                        .exprs()
+                        .iter()
+                        .map(protobuf::PhysicalExprNode::try_from)
+                        .collect::<Result<_, _>>()?,
+                    fetch_limit: exec.fetch().map(|fetch| fetch as u64),
+                }
+                .into(),
+            )
+        } else if let Some(exec) = plan_any.downcast_ref::<LimitExec>() {
+            PhysicalPlanType::Limit(
+                protobuf::LimitExecNode {
+                    input: input()?,
+                    limit: exec.limit(),
+                    offset: exec.offset(),
+                }
+                .into(),
+            )
+        } else if plan_any.downcast_ref::<RenameColumnsExec>().is_some() {
+            PhysicalPlanType::RenameColumns(
+                protobuf::RenameColumnsExecNode {
+                    input: input()?,
+                    renamed_column_names: plan
+                        .schema()
+                        .fields()
+                        .iter()
+                        .map(|field| field.name().clone())
+                        .collect(),
+                }
+                .into(),
+            )
+        } else if let Some(exec) = plan_any.downcast_ref::<DebugExec>() {
+            PhysicalPlanType::Debug(
+                protobuf::DebugExecNode {
+                    input: input()?,
+                    debug_id: exec.debug_id().to_string(),
+                }
+                .into(),
+            )
+        } else if plan_any.downcast_ref::<UnionExec>().is_some() {
+            PhysicalPlanType::Union(
+                protobuf::UnionExecNode {
+                    children: plan
+                        .children()
+                        .iter()
+                        .map(protobuf::PhysicalPlanNode::try_from)
+                        .collect::<Result<_, _>>()?,
+                    widen_types: false,
+                }
+                .into(),
+            )
+        } else if plan_any.downcast_ref::<EmptyPartitionsExec>().is_some() {
+            PhysicalPlanType::EmptyPartitions(
+                protobuf::EmptyPartitionsExecNode {
+                    schema: Some(protobuf::Schema::try_from(plan.schema().as_ref())?),
+                    num_partitions: plan.output_partitioning().partition_count() as u32,
+                }
+                .into(),
+            )
+        } else if let Some(exec) = plan_any.downcast_ref::<IpcReaderExec>() {
+            let mode = match exec.mode {
+                IpcReadMode::ChannelUncompressed => protobuf::IpcReadMode::ChannelUncompressed,
+                IpcReadMode::Channel => protobuf::IpcReadMode::Channel,
+                IpcReadMode::ChannelAndFileSegment => protobuf::IpcReadMode::ChannelAndFileSegment,
+            };
+            PhysicalPlanType::IpcReader(
+                protobuf::IpcReaderExecNode {
+                    num_partitions: exec.num_partitions as u32,
+                    schema: Some(protobuf::Schema::try_from(exec.schema.as_ref())?),
+                    mode: mode as i32,
+                    ipc_provider_resource_id: exec.ipc_provider_resource_id.clone(),
+                }
+                .into(),
+            )
+        } else if let Some(exec) = plan_any.downcast_ref::<FFIReaderExec>() {
+            PhysicalPlanType::FfiReader(
+                protobuf::FfiReaderExecNode {
+                    num_partitions: plan.output_partitioning().partition_count() as u32,
+                    schema: Some(protobuf::Schema::try_from(plan.schema().as_ref())?),
+                    export_iter_provider_resource_id: exec
+                        .export_iter_provider_resource_id()
+                        .to_string(),
+                }
+                .into(),
+            )
+        } else {
+            return Err(PlanSerDeError::NotImplemented(format!(
+                "physical_plan::to_proto() unsupported plan: {}",
+                displayable(plan.as_ref()).one_line(),
+            )));
+        };
+        Ok(protobuf::PhysicalPlanNode {
+            physical_plan_type: Some(physical_plan_type),
+        })
+    }
+}
+
+impl TryFrom<&Arc<dyn PhysicalExpr>> for protobuf::PhysicalExprNode {
+    type Error = PlanSerDeError;
+
+    fn try_from(expr: &Arc<dyn PhysicalExpr>) -> Result<Self, Self::Error> {
+        let boxed = |expr: &Arc<dyn PhysicalExpr>| -> Result<_, PlanSerDeError> {
+            Ok(Some(Box::new(protobuf::PhysicalExprNode::try_from(expr)?)))
+        };
+        let binary = |l: &Arc<dyn PhysicalExpr>, op, r: &Arc<dyn PhysicalExpr>| {
+            Ok::<_, PlanSerDeError>(ExprType::BinaryExpr(
+                protobuf::PhysicalBinaryExprNode {
+                    l: boxed(l)?,
+                    r: boxed(r)?,
+                    op: to_proto_binary_op(op)?,
+                }
+                .into(),
+            ))
+        };
+        let expr_any = expr.as_any();
+
+        let expr_type = if let Some(e) = expr_any.downcast_ref::<Column>() {
+            ExprType::Column(protobuf::PhysicalColumn {
+                name: e.name().to_string(),
+                index: e.index() as u32,
+            })
+        } else if let Some(e) = expr_any.downcast_ref::<Literal>() {
+            ExprType::Literal(protobuf::ScalarValue::try_from(e.value())?.into())
+        } else if let Some(e) = expr_any.downcast_ref::<BinaryExpr>() {
+            binary(e.left(), e.op(), e.right())?
+        } else if let Some(e) = expr_any.downcast_ref::<ColumnLiteralCompareExpr>() {
+            let literal: Arc<dyn PhysicalExpr> = Arc::new(Literal::new(e.literal().clone()));
+            binary(e.column(), &e.op(), &literal)?
+        } else if let Some(e) = expr_any.downcast_ref::<IsNullExpr>() {
+            ExprType::IsNullExpr(
+                protobuf::PhysicalIsNull {
+                    expr: boxed(e.arg())?,
+                }
+                .into(),
+            )
+        } else if let Some(e) = expr_any.downcast_ref::<IsNotNullExpr>() {
+            ExprType::IsNotNullExpr(
+                protobuf::PhysicalIsNotNull {
+                    expr: boxed(e.arg())?,
+                }
+                .into(),
+            )
+        } else if let Some(e) = expr_any.downcast_ref::<NotExpr>() {
+            ExprType::NotExpr(
+                protobuf::PhysicalNot {
+                    expr: boxed(e.arg())?,
+                }
+                .into(),
+            )
+        } else if let Some(e) = expr_any.downcast_ref::<NegativeExpr>() {
+            ExprType::Negative(
+                protobuf::PhysicalNegativeNode {
+                    expr: boxed(e.arg())?,
+                }
+                .into(),
+            )
+        } else if let Some(e) = expr_any.downcast_ref::<CastExpr>() {
+            ExprType::Cast(
+                protobuf::PhysicalCastNode {
+                    expr: boxed(e.expr())?,
+                    arrow_type: Some(protobuf::ArrowType::try_from(e.cast_type())?.into()),
+                }
+                .into(),
+            )
+        } else {
+            return Err(PlanSerDeError::NotImplemented(format!(
+                "physical_plan::to_proto() unsupported expr: {:?}",
+                expr
+            )));
+        };
+        Ok(protobuf::PhysicalExprNode {
+            expr_type: Some(expr_type),
+        })
+    }
+}
+
+impl TryFrom<&PhysicalSortExpr> for protobuf::PhysicalExprNode {
+    type Error = PlanSerDeError;
+
+    fn try_from(sort_expr: &PhysicalSortExpr) -> Result<Self, Self::Error> {
+        Ok(protobuf::PhysicalExprNode {
+            expr_type: Some(ExprType::Sort(
+                protobuf::PhysicalSortExprNode {
+                    expr: Some(Box::new(protobuf::PhysicalExprNode::try_from(
+                        &sort_expr.expr,
+                    )?)),
+                    asc: !sort_expr.options.descending,
+                    nulls_first: sort_expr.options.nulls_first,
+                }
+                .into(),
+            )),
+        })
+    }
+}
+
+impl TryFrom<&DataType> for protobuf::ArrowType {
+    type Error = PlanSerDeError;
+
+    fn try_from(data_type: &DataType) -> Result<Self, Self::Error> {
+        use protobuf::arrow_type::ArrowTypeEnum;
+        let empty = protobuf::EmptyMessage {};
+        let boxed_field = |field: &Field| -> Result<_, PlanSerDeError> {
+            Ok(Some(Box::new(protobuf::Field::try_from(field)?)))
+        };
+        let arrow_type_enum = match data_type {
+            DataType::Null => ArrowTypeEnum::None(empty),
+            DataType::Boolean => ArrowTypeEnum::Bool(empty),
+            DataType::UInt8 => ArrowTypeEnum::Uint8(empty),
+            DataType::Int8 => ArrowTypeEnum::Int8(empty),
+            DataType::UInt16 => ArrowTypeEnum::Uint16(empty),
+            DataType::Int16 => ArrowTypeEnum::Int16(empty),
+            DataType::UInt32 => ArrowTypeEnum::Uint32(empty),
+            DataType::Int32 => ArrowTypeEnum::Int32(empty),
+            DataType::UInt64 => ArrowTypeEnum::Uint64(empty),
+            DataType::Int64 => ArrowTypeEnum::Int64(empty),
+            DataType::Float16 => ArrowTypeEnum::Float16(empty),
+            DataType::Float32 => ArrowTypeEnum::Float32(empty),
+            DataType::Float64 => ArrowTypeEnum::Float64(empty),
+            DataType::Utf8 => ArrowTypeEnum::Utf8(empty),
+            DataType::LargeUtf8 => ArrowTypeEnum::LargeUtf8(empty),
+            DataType::Binary => ArrowTypeEnum::Binary(empty),
+            DataType::FixedSizeBinary(size) => ArrowTypeEnum::FixedSizeBinary(*size),
+            DataType::LargeBinary => ArrowTypeEnum::LargeBinary(empty),
+            DataType::Date32 => ArrowTypeEnum::Date32(empty),
+            DataType::Date64 => ArrowTypeEnum::Date64(empty),
+            DataType::Duration(time_unit) => {
+                ArrowTypeEnum::Duration(protobuf::TimeUnit::from_arrow_time_unit(time_unit) as i32)
+            }
+            DataType::Timestamp(time_unit, timezone) => {
+                ArrowTypeEnum::Timestamp(protobuf::Timestamp {
+                    time_unit: protobuf::TimeUnit::from_arrow_time_unit(time_unit) as i32,
+                    timezone: timezone.as_deref().unwrap_or_default().to_string(),
+                })
+            }
+            DataType::Time32(time_unit) => {
+                ArrowTypeEnum::Time32(protobuf::TimeUnit::from_arrow_time_unit(time_unit) as i32)
+            }
+            DataType::Time64(time_unit) => {
+                ArrowTypeEnum::Time64(protobuf::TimeUnit::from_arrow_time_unit(time_unit) as i32)
+            }
+            DataType::Interval(interval_unit) => ArrowTypeEnum::Interval(
+                protobuf::IntervalUnit::from_arrow_interval_unit(interval_unit) as i32,
+            ),
+            DataType::Decimal128(precision, scale) => ArrowTypeEnum::Decimal(protobuf::Decimal {
+                whole: *precision as u64,
+                fractional: *scale as i64,
+            }),
+            DataType::List(field) => ArrowTypeEnum::List(
+                protobuf::List {
+                    field_type: boxed_field(field)?,
+                }
+                .into(),
+            ),
+            DataType::LargeList(field) => ArrowTypeEnum::LargeList(
+                protobuf::List {
+                    field_type: boxed_field(field)?,
+                }
+                .into(),
+            ),
+            DataType::FixedSizeList(field, list_size) => ArrowTypeEnum::FixedSizeList(
+                protobuf::FixedSizeList {
+                    field_type: boxed_field(field)?,
+                    list_size: *list_size,
+                }
+                .into(),
+            ),
+            DataType::Struct(fields) => ArrowTypeEnum::Struct(
+                protobuf::Struct {
+                    sub_field_types: fields
+                        .iter()
+                        .map(|field| protobuf::Field::try_from(field.as_ref()))
+                        .collect::<Result<_, _>>()?,
+                }
+                .into(),
+            ),
+            DataType::Map(entries, _) => match entries.data_type() {
+                DataType::Struct(kv) if kv.len() == 2 => ArrowTypeEnum::Map(
+                    protobuf::Map {
+                        key_type: boxed_field(&kv[0])?,
+                        value_type: boxed_field(&kv[1])?,
+                    }
+                    .into(),
+                ),
+                other => {
+                    return Err(proto_error(format!(
+                        "physical_plan::to_proto() invalid map entries type: {:?}",
+                        other
+                    )))
+                }
+            },
+            DataType::Dictionary(key, value) => ArrowTypeEnum::Dictionary(
+                protobuf::Dictionary {
+                    key: Some(Box::new(protobuf::ArrowType::try_from(key.as_ref())?)),
+                    value: Some(Box::new(protobuf::ArrowType::try_from(value.as_ref())?)),
+                }
+                .into(),
+            ),
+            other => {
+                return Err(PlanSerDeError::NotImplemented(format!(
+                    "physical_plan::to_proto() unsupported data type: {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(protobuf::ArrowType {
+            arrow_type_enum: Some(arrow_type_enum),
+        })
+    }
+}
+
+impl TryFrom<&Field> for protobuf::Field {
+    type Error = PlanSerDeError;
+
+    fn try_from(field: &Field) -> Result<Self, Self::Error> {
+        Ok(protobuf::Field {
+            name: field.name().clone(),
+            arrow_type: Some(Box::new(protobuf::ArrowType::try_from(field.data_type())?)),
+            nullable: field.is_nullable(),
+            children: vec![],
+        })
+    }
+}
+
+impl TryFrom<&Schema> for protobuf::Schema {
+    type Error = PlanSerDeError;
+
+    fn try_from(schema: &Schema) -> Result<Self, Self::Error> {
+        Ok(protobuf::Schema {
+            columns: schema
+                .fields()
+                .iter()
+                .map(|field| protobuf::Field::try_from(field.as_ref()))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<&ScalarValue> for protobuf::ScalarValue {
+    type Error = PlanSerDeError;
+
+    fn try_from(scalar: &ScalarValue) -> Result<Self, Self::Error> {
+        use protobuf::scalar_value::Value;
+        use protobuf::PrimitiveScalarType as T;
+
+        let (value, null_type) = match scalar {
+            ScalarValue::Null => (None, T::Null),
+            ScalarValue::Boolean(v) => (v.map(Value::BoolValue), T::Bool),
+            ScalarValue::Int8(v) => (v.map(|v| Value::Int8Value(v as i32)), T::Int8),
+            ScalarValue::Int16(v) => (v.map(|v| Value::Int16Value(v as i32)), T::Int16),
+            ScalarValue::Int32(v) => (v.map(Value::Int32Value), T::Int32),
+            ScalarValue::Int64(v) => (v.map(Value::Int64Value), T::Int64),
+            ScalarValue::UInt8(v) => (v.map(|v| Value::Uint8Value(v as u32)), T::Uint8),
+            ScalarValue::UInt16(v) => (v.map(|v| Value::Uint16Value(v as u32)), T::Uint16),
+            ScalarValue::UInt32(v) => (v.map(Value::Uint32Value), T::Uint32),
+            ScalarValue::UInt64(v) => (v.map(Value::Uint64Value), T::Uint64),
+            ScalarValue::Float32(v) => (v.map(Value::Float32Value), T::Float32),
+            ScalarValue::Float64(v) => (v.map(Value::Float64Value), T::Float64),
+            ScalarValue::Utf8(v) => (v.clone().map(Value::Utf8Value), T::Utf8),
+            ScalarValue::LargeUtf8(v) => (v.clone().map(Value::LargeUtf8Value), T::LargeUtf8),
+            ScalarValue::Date32(v) => (v.map(Value::Date32Value), T::Date32),
+            ScalarValue::TimestampSecond(v, _) => {
+                (v.map(Value::TimestampSecondValue), T::TimestampSecond)
+            }
+            ScalarValue::TimestampMillisecond(v, _) => (
+                v.map(Value::TimestampMillisecondValue),
+                T::TimestampMillisecond,
+            ),
+            ScalarValue::TimestampMicrosecond(v, _) => (
+                v.map(Value::TimestampMicrosecondValue),
+                T::TimestampMicrosecond,
+            ),
+            ScalarValue::TimestampNanosecond(v, _) => (
+                v.map(Value::TimestampNanosecondValue),
+                T::TimestampNanosecond,
+            ),
+            ScalarValue::Decimal128(v, precision, scale) => {
+                let value = v
+                    .map(|v| {
+                        let long_value = i64::try_from(v).map_err(|_| {
+                            proto_error(format!(
+                                "physical_plan::to_proto() decimal value out of range: {v}"
+                            ))
+                        })?;
+                        Ok::<_, PlanSerDeError>(Value::DecimalValue(protobuf::ScalarDecimalValue {
+                            decimal: Some(protobuf::Decimal {
+                                whole: *precision as u64,
+                                fractional: *scale as i64,
+                            }),
+                            long_value,
+                        }))
+                    })
+                    .transpose()?;
+                (value, T::Decimal128)
+            }
+            other => {
+                return Err(PlanSerDeError::NotImplemented(format!(
+                    "physical_plan::to_proto() unsupported scalar value: {:?}",
+                    other
+                )))
+            }
+        };
+        let value = value.unwrap_or_else(|| {
+            Value::NullValue(protobuf::ScalarType {
+                datatype: Some(protobuf::scalar_type::Datatype::Scalar(null_type as i32)),
+            })
+        });
+        Ok(protobuf::ScalarValue { value: Some(value) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::protobuf;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::common::ScalarValue;
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_plan::expressions::{
+        BinaryExpr, CastExpr, Column, IsNotNullExpr, Literal, PhysicalSortExpr,
+    };
+    use datafusion::physical_plan::sorts::sort::SortOptions;
+    use datafusion::physical_plan::{displayable, ExecutionPlan};
+    use datafusion_ext_plans::empty_partitions_exec::EmptyPartitionsExec;
+    use datafusion_ext_plans::filter_exec::FilterExec;
+    use datafusion_ext_plans::limit_exec::LimitExec;
+    use datafusion_ext_plans::project_exec::ProjectExec;
+    use datafusion_ext_plans::sort_exec::SortExec;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_plan_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+            Field::new("c", DataType::Decimal128(10, 2), false),
+        ]));
+        let a = Arc::new(Column::new("a", 0));
+        let b = Arc::new(Column::new("b", 1));
+        let c = Arc::new(Column::new("c", 2));
+
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(EmptyPartitionsExec::new(schema, 3));
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(FilterExec::try_new(
+            vec![
+                Arc::new(BinaryExpr::new(
+                    a.clone(),
+                    Operator::Gt,
+                    Arc::new(Literal::new(ScalarValue::Int32(Some(1)))),
+                )),
+                Arc::new(IsNotNullExpr::new(b.clone())),
+            ],
+            plan,
+        )?);
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(ProjectExec::try_new(
+            vec![
+                (
+                    Arc::new(CastExpr::new(a.clone(), DataType::Int64, None)),
+                    "a".to_string(),
+                ),
+                (b.clone(), "b".to_string()),
+                (c, "c".to_string()),
+            ],
+            plan,
+        )?);
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(SortExec::new(
+            plan,
+            vec![PhysicalSortExpr {
+                expr: b,
+                options: SortOptions {
+                    descending: true,
+                    nulls_first: false,
+                },
+            }],
+            Some(10),
+        ));
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(LimitExec::new(plan, 10).with_offset(5));
+
+        // native -> proto -> native -> proto
+        let proto = protobuf::PhysicalPlanNode::try_from(&plan)?;
+        let decoded: Arc<dyn ExecutionPlan> = (&proto).try_into()?;
+        let reencoded = protobuf::PhysicalPlanNode::try_from(&decoded)?;
+        assert_eq!(proto, reencoded);
+        assert_eq!(
+            displayable(plan.as_ref()).indent(true).to_string(),
+            displayable(decoded.as_ref()).indent(true).to_string(),
+        );
+        Ok(())
+    }
+}
@@ -13,9 +13,11 @@
 // limitations under the License.
 
 use crate::rt::NativeExecutionRuntime;
-use crate::{handle_unwinded_scope, SESSION};
+use crate::trace::TaskTracer;
+use crate::{handle_unwinded_scope, throw_unsupported_plan_exception, SESSION};
 use blaze_jni_bridge::jni_bridge::JavaClasses;
 use blaze_jni_bridge::*;
+use blaze_serde::error::PlanSerDeError;
 use blaze_serde::protobuf::TaskDefinition;
 use datafusion::common::Result;
 use datafusion::error::DataFusionError;
@@ -26,6 +28,7 @@ use datafusion::prelude::{SessionConfig, SessionContext};
 use datafusion_ext_plans::common::memory_manager::MemManager;
 use jni::objects::JClass;
 use jni::objects::JObject;
+use jni::sys::jstring;
 use jni::JNIEnv;
 use log::LevelFilter;
 use once_cell::sync::OnceCell;
@@ -106,9 +109,31 @@ pub extern "system" fn Java_org_apache_spark_sql_blaze_JniBridge_callNative(
         drop(raw_task_definition);
 
         // get execution plan
-        let execution_plan: Arc<dyn ExecutionPlan> = plan.try_into().map_err(|err| {
-            DataFusionError::Plan(format!("cannot create execution plan: {:?}", err))
-        })?;
+        let execution_plan: Arc<dyn ExecutionPlan> = match plan.try_into() {
+            Ok(execution_plan) => execution_plan,
+            Err(PlanSerDeError::UnsupportedPlan {
+                operator,
+                expr,
+                reason,
+            }) => {
+                // throw a structured exception to the jvm side instead of a
+                // runtime exception with the error message
+                log::warn!(
+                    "cannot create execution plan: unsupported operator={}, expr={:?}, reason={}",
+                    operator,
+                    expr,
+                    reason.name(),
+                );
+                throw_unsupported_plan_exception(&operator, expr.as_deref(), reason)?;
+                return Ok(0);
+            }
+            Err(err) => {
+                return Err(DataFusionError::Plan(format!(
+                    "cannot create execution plan: {:?}",
+                    err
+                )));
+            }
+        };
         let execution_plan_displayable = displayable(execution_plan.as_ref())
             .indent(true)
             .to_string();
@@ -117,11 +142,13 @@ pub extern "system" fn Java_org_apache_spark_sql_blaze_JniBridge_callNative(
         log::info!("  execution plan:\n{}", execution_plan_displayable);
 
         // execute to stream
+        let tracer = TaskTracer::try_new(task_id.stage_id, task_id.partition_id)?;
         let runtime = Box::new(NativeExecutionRuntime::start(
             native_wrapper,
             execution_plan,
             task_id.partition_id as usize,
             SESSION.get().unwrap().task_ctx(),
+            tracer,
         )?);
         log::info!("Blaze native thread created");
 
@@ -130,6 +157,40 @@ pub extern "system" fn Java_org_apache_spark_sql_blaze_JniBridge_callNative(
     })
 }
 
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "system" fn Java_org_apache_spark_sql_blaze_JniBridge_spillNative(
+    env: JNIEnv,
+    _: JClass,
+    jvm_consumer: JObject,
+    size: i64,
+) -> i64 {
+    handle_unwinded_scope(|| -> Result<i64> {
+        let released = MemManager::spill_jvm_consumer(
+            |c| {
+                env.is_same_object(c.as_obj(), jvm_consumer)
+                    .unwrap_or(false)
+            },
+            size as usize,
+        )?;
+        Ok(released as i64)
+    })
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+pub extern "system" fn Java_org_apache_spark_sql_blaze_JniBridge_explainNative(
+    env: JNIEnv,
+    _: JClass,
+    rtw_ptr: i64,
+) -> jstring {
+    let runtime = unsafe { &*(rtw_ptr as usize as *const NativeExecutionRuntime) };
+    let explained = handle_unwinded_scope(|| -> Result<String> { Ok(runtime.explain()) });
+    env.new_string(explained)
+        .map(|explained| JObject::from(explained).into_inner())
+        .unwrap_or(std::ptr::null_mut())
+}
+
 #[allow(non_snake_case)]
 #[no_mangle]
 pub extern "system" fn Java_org_apache_spark_sql_blaze_JniBridge_finalizeNative(
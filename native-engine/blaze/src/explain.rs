@@ -0,0 +1,96 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datafusion::physical_plan::{DisplayFormatType, ExecutionPlan};
+use std::fmt::{Display, Formatter, Write};
+
+/// Renders the native plan tree with partition counts and a snapshot of
+/// current metrics of each node, like:
+///
+/// ```text
+/// ProjectExec [...], partitions=1, metrics=[output_rows=100, elapsed_compute=1.2ms]
+///   FilterExec [...], partitions=1, metrics=[output_rows=100, elapsed_compute=3.4ms]
+/// ```
+pub fn explain_plan(plan: &dyn ExecutionPlan) -> String {
+    let mut explained = String::new();
+    explain_node(plan, 0, &mut explained);
+    explained
+}
+
+fn explain_node(plan: &dyn ExecutionPlan, indent: usize, explained: &mut String) {
+    struct FmtAs<'a>(&'a dyn ExecutionPlan);
+    impl Display for FmtAs<'_> {
+        fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+            self.0.fmt_as(DisplayFormatType::Default, f)
+        }
+    }
+
+    let _ = write!(
+        explained,
+        "{:indent$}{}, partitions={}",
+        "",
+        FmtAs(plan),
+        plan.output_partitioning().partition_count(),
+        indent = indent * 2,
+    );
+    if let Some(metrics) = plan.metrics() {
+        let metrics = metrics
+            .aggregate_by_name()
+            .sorted_for_display()
+            .timestamps_removed();
+        let _ = write!(explained, ", metrics=[{}]", metrics);
+    }
+    explained.push('\n');
+
+    for child in plan.children() {
+        explain_node(child.as_ref(), indent + 1, explained);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::explain::explain_plan;
+    use arrow::array::Int32Array;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use datafusion_ext_plans::debug_exec::DebugExec;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_explain_plan() -> Result<()> {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "a",
+            Arc::new(Int32Array::from(vec![1, 2, 3])) as _,
+        )])?;
+        let memory_exec = Arc::new(MemoryExec::try_new(
+            &[vec![batch.clone()]],
+            batch.schema(),
+            None,
+        )?);
+        let plan = Arc::new(DebugExec::new(memory_exec, "test".to_string()));
+
+        let task_ctx = SessionContext::new().task_ctx();
+        common::collect(plan.execute(0, task_ctx)?).await?;
+
+        let explained = explain_plan(plan.as_ref());
+        let lines = explained.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("DebugExec, partitions=1, metrics=[output_rows=3"));
+        assert!(lines[1].starts_with("  MemoryExec: partitions=1"));
+        Ok(())
+    }
+}
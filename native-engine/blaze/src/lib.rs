@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use blaze_jni_bridge::*;
+use blaze_serde::error::UnsupportedReason;
 use datafusion::prelude::SessionContext;
 use jni::objects::{JObject, JThrowable};
 use once_cell::sync::OnceCell;
@@ -22,8 +23,10 @@ use std::fmt::Debug;
 use std::panic::AssertUnwindSafe;
 
 mod exec;
+mod explain;
 mod metrics;
 mod rt;
+mod trace;
 
 #[global_allocator]
 static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
@@ -82,3 +85,22 @@ fn throw_runtime_exception(msg: &str, cause: JObject) -> datafusion::error::Resu
     }
     Ok(())
 }
+
+fn throw_unsupported_plan_exception(
+    operator: &str,
+    expr: Option<&str>,
+    reason: UnsupportedReason,
+) -> datafusion::error::Result<()> {
+    let operator = jni_new_string!(operator)?;
+    let expr = expr.map(|expr| jni_new_string!(expr)).transpose()?;
+    let reason = jni_new_string!(reason.name())?;
+    let e = jni_new_object!(BlazeUnsupportedPlanException(
+        operator.as_obj(),
+        expr.as_ref()
+            .map(|expr| expr.as_obj())
+            .unwrap_or(JObject::null()),
+        reason.as_obj(),
+    ))?;
+    jni_throw!(JThrowable::from(e.as_obj()))?;
+    Ok(())
+}
@@ -12,12 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use blaze_jni_bridge::{jni_call, jni_new_string};
+use blaze_jni_bridge::{jni_call, jni_new_long_array, jni_new_string_array};
 use datafusion::common::Result;
 use datafusion::physical_plan::ExecutionPlan;
 use jni::objects::JObject;
 use std::sync::Arc;
 
+/// Updates spark metrics of the whole plan tree with one jni call. metrics of
+/// all nodes are collected in pre-order, with the number of metrics and
+/// children of each node, see `MetricNode.addAll()`.
 pub fn update_spark_metric_node(
     metric_node: JObject,
     execution_plan: Arc<dyn ExecutionPlan>,
@@ -26,32 +29,42 @@ pub fn update_spark_metric_node(
         return Ok(());
     }
 
-    // update current node
-    update_metrics(
-        metric_node,
-        &execution_plan
-            .metrics()
-            .unwrap_or_default()
-            .iter()
-            .map(|m| m.value())
-            .map(|m| (m.name(), m.as_usize() as i64))
-            .collect::<Vec<_>>(),
-    )?;
+    let mut node_shapes = vec![];
+    let mut metric_names = vec![];
+    let mut metric_values = vec![];
+    collect_metrics(
+        &execution_plan,
+        &mut node_shapes,
+        &mut metric_names,
+        &mut metric_values,
+    );
 
-    // update children nodes
-    for (i, child_plan) in execution_plan.children().iter().enumerate() {
-        let child_metric_node = jni_call!(
-            SparkMetricNode(metric_node).getChild(i as i32) -> JObject
-        )?;
-        update_spark_metric_node(child_metric_node.as_obj(), child_plan.clone())?;
-    }
+    let node_shapes = jni_new_long_array!(&node_shapes)?;
+    let metric_names = jni_new_string_array!(&metric_names)?;
+    let metric_values = jni_new_long_array!(&metric_values)?;
+    jni_call!(SparkMetricNode(metric_node).addAll(
+        node_shapes.as_obj(),
+        metric_names.as_obj(),
+        metric_values.as_obj(),
+    ) -> ())?;
     Ok(())
 }
 
-fn update_metrics(metric_node: JObject, metric_values: &[(&str, i64)]) -> Result<()> {
-    for &(name, value) in metric_values {
-        let jname = jni_new_string!(&name)?;
-        jni_call!(SparkMetricNode(metric_node).add(jname.as_obj(), value) -> ())?;
+fn collect_metrics(
+    execution_plan: &Arc<dyn ExecutionPlan>,
+    node_shapes: &mut Vec<i64>,
+    metric_names: &mut Vec<String>,
+    metric_values: &mut Vec<i64>,
+) {
+    let metrics = execution_plan.metrics().unwrap_or_default();
+    let children = execution_plan.children();
+    node_shapes.push(metrics.iter().count() as i64);
+    node_shapes.push(children.len() as i64);
+    for metric in metrics.iter() {
+        metric_names.push(metric.value().name().to_string());
+        metric_values.push(metric.value().as_usize() as i64);
+    }
+    for child_plan in &children {
+        collect_metrics(child_plan, node_shapes, metric_names, metric_values);
     }
-    Ok(())
 }
@@ -12,8 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::explain::explain_plan;
 use crate::handle_unwinded_scope;
 use crate::metrics::update_spark_metric_node;
+use crate::trace::{set_thread_default, unset_thread_default, TaskTracer};
 use arrow::ffi_stream::FFI_ArrowArrayStream;
 use blaze_jni_bridge::is_task_running;
 use blaze_jni_bridge::jni_bridge::JavaClasses;
@@ -27,8 +29,11 @@ use datafusion::execution::context::TaskContext;
 use datafusion::physical_plan::metrics::Time;
 use datafusion::physical_plan::{ExecutionPlan, RecordBatchStream};
 use datafusion_ext_commons::ffi::MpscBatchReader;
+use datafusion_ext_commons::spill::SpillManager;
 use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
+use datafusion_ext_plans::common::batch_accounting::BatchAccounting;
 use datafusion_ext_plans::common::output::WrappedRecordBatchSender;
+use datafusion_ext_plans::tracing_exec::TracingExec;
 use futures::{FutureExt, StreamExt};
 use jni::objects::{GlobalRef, JObject};
 use std::panic::AssertUnwindSafe;
@@ -42,6 +47,7 @@ pub struct NativeExecutionRuntime {
     partition: usize,
     rt: Runtime,
     ffi_stream: Box<FFI_ArrowArrayStream>,
+    tracer: Option<TaskTracer>,
 }
 
 impl NativeExecutionRuntime {
@@ -50,11 +56,32 @@ impl NativeExecutionRuntime {
         plan: Arc<dyn ExecutionPlan>,
         partition: usize,
         context: Arc<TaskContext>,
+        tracer: Option<TaskTracer>,
     ) -> Result<Self> {
         let batch_size = context.session_config().batch_size();
 
-        // execute plan to output stream
-        let stream = plan.execute(partition, context.clone())?;
+        // enable batch accounting for debugging
+        if jni_call_static!(BlazeConf.enableBatchAccounting() -> bool)? {
+            BatchAccounting::register(&context, partition);
+        }
+
+        // spill files of this task are deleted on finalizing
+        SpillManager::register(&context);
+
+        // execute plan to output stream, operators are traced if enabled
+        let (plan, stream) = match &tracer {
+            Some(tracer) => {
+                let plan = TracingExec::instrument(plan)?;
+                let stream = tracing::dispatcher::with_default(tracer.dispatch(), || {
+                    plan.execute(partition, context.clone())
+                })?;
+                (plan, stream)
+            }
+            None => {
+                let stream = plan.execute(partition, context.clone())?;
+                (plan, stream)
+            }
+        };
 
         // coalesce
         let coalesce_compute_time = Time::new();
@@ -83,8 +110,12 @@ impl NativeExecutionRuntime {
         // propagate classloader and task context to spawned children threads
         let spark_task_context = jni_call_static!(JniBridge.getTaskContext() -> JObject)?;
         let spark_task_context_global = jni_new_global_ref!(spark_task_context.as_obj())?;
+        let trace_dispatch = tracer.as_ref().map(|tracer| tracer.dispatch().clone());
         let rt = tokio::runtime::Builder::new_multi_thread()
             .on_thread_start(move || {
+                if let Some(dispatch) = &trace_dispatch {
+                    set_thread_default(dispatch);
+                }
                 let classloader = JavaClasses::get().classloader;
                 let _ = jni_call_static!(
                     JniBridge.setContextClassLoader(classloader) -> ()
@@ -93,6 +124,7 @@ impl NativeExecutionRuntime {
                     JniBridge.setTaskContext(spark_task_context_global.as_obj()) -> ()
                 );
             })
+            .on_thread_stop(unset_thread_default)
             .build()?;
 
         let nrt = Self {
@@ -102,6 +134,7 @@ impl NativeExecutionRuntime {
             rt,
             ffi_stream,
             task_context: context,
+            tracer,
         };
 
         // spawn batch producer
@@ -188,13 +221,39 @@ impl NativeExecutionRuntime {
     pub fn finalize(self) {
         log::info!("native execution [partition={}] finalizing", self.partition);
         let _ = self.update_metrics();
+        if let Some(batch_accounting) = BatchAccounting::deregister(&self.task_context) {
+            log::info!(
+                "native execution [partition={}] batch accounting: {:?}",
+                self.partition,
+                batch_accounting.batch_counts(),
+            );
+        }
         drop(self.ffi_stream);
         drop(self.plan);
         WrappedRecordBatchSender::cancel_task(&self.task_context); // cancel all pending streams
         self.rt.shutdown_background();
+        if let Some(spill_manager) = SpillManager::deregister(&self.task_context) {
+            log::info!(
+                "native execution [partition={}] spilled bytes: {}",
+                self.partition,
+                spill_manager.spilled_bytes(),
+            );
+        }
+        if let Some(tracer) = self.tracer {
+            log::info!(
+                "native execution [partition={}] trace file: {}",
+                self.partition,
+                tracer.trace_file().display(),
+            );
+        }
         log::info!("native execution [partition={}] finalized", self.partition);
     }
 
+    /// Renders the executing plan with a snapshot of current metrics
+    pub fn explain(&self) -> String {
+        explain_plan(self.plan.as_ref())
+    }
+
     fn update_metrics(&self) -> Result<()> {
         let metrics = jni_call!(
             BlazeCallNativeWrapper(self.native_wrapper.as_obj()).getMetrics() -> JObject
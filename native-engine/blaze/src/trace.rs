@@ -0,0 +1,93 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-task tracing of native execution, enabled by `spark.blaze.trace.enabled`.
+//! operators are wrapped with `TracingExec` and spans of a task are written
+//! into a chrome trace file, which can be viewed in chrome://tracing (or
+//! perfetto) and converted to flamegraphs.
+
+use blaze_jni_bridge::{jni_call_static, jni_get_string};
+use datafusion::common::Result;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::dispatcher::DefaultGuard;
+use tracing::Dispatch;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::layer::SubscriberExt;
+
+pub struct TaskTracer {
+    dispatch: Dispatch,
+    trace_file: PathBuf,
+    _flush_guard: FlushGuard, // trace file is flushed on dropping
+}
+
+impl TaskTracer {
+    /// Creates a tracer writing to a new trace file, returns None if tracing is
+    /// not enabled
+    pub fn try_new(stage_id: u32, partition_id: u32) -> Result<Option<Self>> {
+        if !jni_call_static!(BlazeConf.traceEnabled() -> bool)? {
+            return Ok(None);
+        }
+        let trace_dir = jni_call_static!(BlazeConf.traceDir() -> JObject)?;
+        let trace_dir = jni_get_string!(trace_dir.as_obj().into())?;
+        let trace_dir = if trace_dir.is_empty() {
+            std::env::temp_dir()
+        } else {
+            PathBuf::from(trace_dir)
+        };
+        std::fs::create_dir_all(&trace_dir)?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let trace_file = trace_dir.join(format!(
+            "blaze-trace-stage{stage_id}-partition{partition_id}-{timestamp}.json"
+        ));
+        let (chrome_layer, flush_guard) = ChromeLayerBuilder::new()
+            .file(&trace_file)
+            .include_args(true)
+            .build();
+        let dispatch = Dispatch::new(tracing_subscriber::registry().with(chrome_layer));
+        Ok(Some(Self {
+            dispatch,
+            trace_file,
+            _flush_guard: flush_guard,
+        }))
+    }
+
+    pub fn dispatch(&self) -> &Dispatch {
+        &self.dispatch
+    }
+
+    pub fn trace_file(&self) -> &Path {
+        &self.trace_file
+    }
+}
+
+thread_local! {
+    static THREAD_DEFAULT_GUARD: RefCell<Option<DefaultGuard>> = RefCell::new(None);
+}
+
+/// Sets the default dispatch of current thread until `unset_thread_default()`,
+/// used for threads of the task runtime
+pub fn set_thread_default(dispatch: &Dispatch) {
+    let guard = tracing::dispatcher::set_default(dispatch);
+    THREAD_DEFAULT_GUARD.with(|g| *g.borrow_mut() = Some(guard));
+}
+
+pub fn unset_thread_default() {
+    THREAD_DEFAULT_GUARD.with(|g| drop(g.borrow_mut().take()));
+}
@@ -0,0 +1,231 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Splits oversized batches into sub-batches under a byte budget. row sizes are
+//! estimated per row, so skewed variable-length and nested columns (where a
+//! single row may hold a huge list) are split at the right places.
+
+use arrow::array::*;
+use arrow::datatypes::*;
+use arrow::record_batch::RecordBatch;
+use datafusion::common::Result;
+
+/// Splits the batch into zero-copy slices, each of which is estimated to be no
+/// larger than `byte_budget`, except for slices of a single oversized row.
+pub fn split_batch_by_byte_budget(
+    batch: &RecordBatch,
+    byte_budget: usize,
+) -> Result<Vec<RecordBatch>> {
+    let row_sizes = estimate_batch_row_sizes(batch)?;
+    let mut sub_batches = vec![];
+    let mut start = 0;
+    let mut cur_size = 0;
+
+    for (row_idx, &row_size) in row_sizes.iter().enumerate() {
+        if row_idx > start && cur_size + row_size > byte_budget {
+            sub_batches.push(batch.slice(start, row_idx - start));
+            start = row_idx;
+            cur_size = 0;
+        }
+        cur_size += row_size;
+    }
+    if start < batch.num_rows() {
+        sub_batches.push(batch.slice(start, batch.num_rows() - start));
+    }
+    Ok(sub_batches)
+}
+
+/// Estimates the byte size of each row of the batch
+pub fn estimate_batch_row_sizes(batch: &RecordBatch) -> Result<Vec<usize>> {
+    let mut row_sizes = vec![0; batch.num_rows()];
+    for column in batch.columns() {
+        add_row_sizes(column, &mut row_sizes)?;
+    }
+    Ok(row_sizes)
+}
+
+/// Estimates the byte size of each row of the array
+pub fn estimate_array_row_sizes(array: &dyn Array) -> Result<Vec<usize>> {
+    let mut row_sizes = vec![0; array.len()];
+    add_row_sizes(array, &mut row_sizes)?;
+    Ok(row_sizes)
+}
+
+fn add_row_sizes(array: &dyn Array, row_sizes: &mut [usize]) -> Result<()> {
+    // adds the total sizes of child rows in offsets[i]..offsets[i+1] to each row
+    fn add_child_row_sizes<O: OffsetSizeTrait>(
+        offsets: &[O],
+        child: &dyn Array,
+        row_sizes: &mut [usize],
+    ) -> Result<()> {
+        let child_row_sizes = estimate_array_row_sizes(child)?;
+        for (row_size, range) in row_sizes.iter_mut().zip(offsets.windows(2)) {
+            let (begin, end) = (range[0].as_usize(), range[1].as_usize());
+            *row_size +=
+                std::mem::size_of::<O>() + child_row_sizes[begin..end].iter().sum::<usize>();
+        }
+        Ok(())
+    }
+
+    // adds the byte lengths of variable-length values to each row
+    fn add_value_lengths<O: OffsetSizeTrait>(offsets: &[O], row_sizes: &mut [usize]) {
+        for (row_size, range) in row_sizes.iter_mut().zip(offsets.windows(2)) {
+            *row_size += std::mem::size_of::<O>() + (range[1] - range[0]).as_usize();
+        }
+    }
+
+    match array.data_type() {
+        DataType::Null => {}
+        DataType::Boolean => row_sizes.iter_mut().for_each(|row_size| *row_size += 1),
+        DataType::Utf8 => add_value_lengths(as_string_array(array).value_offsets(), row_sizes),
+        DataType::LargeUtf8 => {
+            add_value_lengths(as_largestring_array(array).value_offsets(), row_sizes)
+        }
+        DataType::Binary => add_value_lengths(
+            as_generic_binary_array::<i32>(array).value_offsets(),
+            row_sizes,
+        ),
+        DataType::LargeBinary => add_value_lengths(
+            as_generic_binary_array::<i64>(array).value_offsets(),
+            row_sizes,
+        ),
+        DataType::List(_) => {
+            let list = as_list_array(array);
+            add_child_row_sizes(list.value_offsets(), list.values(), row_sizes)?;
+        }
+        DataType::LargeList(_) => {
+            let list = as_large_list_array(array);
+            add_child_row_sizes(list.value_offsets(), list.values(), row_sizes)?;
+        }
+        DataType::Map(..) => {
+            let map = as_map_array(array);
+            add_child_row_sizes(map.value_offsets(), map.entries(), row_sizes)?;
+        }
+        DataType::FixedSizeList(_, size) => {
+            let list = as_fixed_size_list_array(array);
+            let size = *size as usize;
+            let child_row_sizes = estimate_array_row_sizes(list.values())?;
+            for (i, row_size) in row_sizes.iter_mut().enumerate() {
+                let begin = list.value_offset(i) as usize;
+                *row_size += child_row_sizes[begin..begin + size].iter().sum::<usize>();
+            }
+        }
+        DataType::Struct(_) => {
+            for column in as_struct_array(array).columns() {
+                add_row_sizes(column, row_sizes)?;
+            }
+        }
+        DataType::Dictionary(key_type, _) => {
+            // each row takes the key and the referenced value
+            let dict = array.as_any_dictionary();
+            let value_row_sizes = estimate_array_row_sizes(dict.values())?;
+            let keys = dict.normalized_keys();
+            let key_size = key_type.primitive_width().unwrap_or(0);
+            for (row_size, key) in row_sizes.iter_mut().zip(keys) {
+                *row_size += key_size + value_row_sizes.get(key).cloned().unwrap_or(0);
+            }
+        }
+        data_type => match data_type.primitive_width() {
+            Some(width) => row_sizes.iter_mut().for_each(|row_size| *row_size += width),
+            None => {
+                // unknown types, use the average row size
+                let avg_size = array.get_array_memory_size() / array.len().max(1);
+                row_sizes
+                    .iter_mut()
+                    .for_each(|row_size| *row_size += avg_size);
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::batch_splitter::{estimate_batch_row_sizes, split_batch_by_byte_budget};
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_split_skewed_lists() -> Result<()> {
+        // most rows have 1 item, while every 50th row has 2000 items
+        let mut builder = ListBuilder::new(Int64Builder::new());
+        for i in 0..1000 {
+            let num_items = if i % 50 == 0 { 2000 } else { 1 };
+            builder.values().append_slice(&vec![i; num_items]);
+            builder.append(true);
+        }
+        let list: ArrayRef = Arc::new(builder.finish());
+        let ids: ArrayRef = Arc::new(Int32Array::from_iter_values(0..1000));
+        let batch = RecordBatch::try_from_iter(vec![("id", ids), ("list", list)])?;
+
+        // a big row takes 4 + 4 + 2000 * 8 = 16008 bytes, a small row takes 16 bytes
+        let row_sizes = estimate_batch_row_sizes(&batch)?;
+        assert_eq!(row_sizes[0], 16008);
+        assert_eq!(row_sizes[1], 16);
+
+        let byte_budget = 20000;
+        let sub_batches = split_batch_by_byte_budget(&batch, byte_budget)?;
+        assert_eq!(sub_batches.len(), 20);
+
+        let mut num_rows = 0;
+        for (i, sub_batch) in sub_batches.iter().enumerate() {
+            let sub_batch_size: usize = estimate_batch_row_sizes(sub_batch)?.iter().sum();
+            assert!(sub_batch_size <= byte_budget);
+
+            // sub-batches are filled as much as possible
+            if i + 1 < sub_batches.len() {
+                let next_row_size = row_sizes[num_rows + sub_batch.num_rows()];
+                assert!(sub_batch_size + next_row_size > byte_budget);
+            }
+            num_rows += sub_batch.num_rows();
+        }
+        assert_eq!(num_rows, batch.num_rows());
+        assert_eq!(
+            arrow::compute::concat_batches(&batch.schema(), &sub_batches)?,
+            batch,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_oversized_row() -> Result<()> {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![
+            "a".repeat(10),
+            "b".repeat(1000),
+            "c".repeat(10),
+        ]));
+        let (x, y) = ("x".repeat(100), "y".repeat(100));
+        let dict: ArrayRef = Arc::new(
+            vec![x.as_str(), y.as_str(), x.as_str()]
+                .into_iter()
+                .collect::<DictionaryArray<Int32Type>>(),
+        );
+        let batch = RecordBatch::try_from_iter(vec![("s", strings), ("d", dict)])?;
+        assert_eq!(
+            estimate_batch_row_sizes(&batch)?,
+            vec![4 + 10 + 4 + 104, 4 + 1000 + 4 + 104, 4 + 10 + 4 + 104],
+        );
+
+        // the oversized row is split into its own batch
+        let sub_batches = split_batch_by_byte_budget(&batch, 500)?;
+        assert_eq!(
+            sub_batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![1, 1, 1],
+        );
+        Ok(())
+    }
+}
@@ -12,12 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use arrow::array::timezone::Tz;
 use arrow::array::*;
+use arrow::buffer::NullBuffer;
 use arrow::datatypes::*;
+use arrow::temporal_conversions::as_datetime_with_timezone;
 use bigdecimal::{FromPrimitive, ToPrimitive};
+use chrono::{Duration, LocalResult, NaiveDate, NaiveTime, Offset, TimeZone};
 use datafusion::common::cast::{as_float32_array, as_float64_array};
 use datafusion::common::{DataFusionError, Result};
-use num::{cast::AsPrimitive, Bounded, Integer, Signed};
+use num::{cast::AsPrimitive, Bounded, Integer, Signed, Zero};
 use paste::paste;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -30,6 +34,209 @@ pub fn cast_scan_input_array(array: &dyn Array, cast_type: &DataType) -> Result<
     return cast_impl(array, cast_type, true);
 }
 
+/// Options of spark compatible casts for behaviors configurable in spark
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SparkCastOptions {
+    /// If set, all-digit strings casted to timestamps are interpreted as epoch
+    /// values of this unit instead of being parsed as datetimes. Disabled by
+    /// default like standard spark.
+    pub string_to_timestamp_epoch_unit: Option<TimeUnit>,
+
+    /// If set, invalid string to integral/decimal casts and overflowing numeric
+    /// casts fail with spark's ANSI errors instead of returning nulls, like
+    /// `spark.sql.ansi.enabled`.
+    pub ansi: bool,
+
+    /// Session timezone of timestamps without timezones, used when parsing
+    /// strings without zone ids to timestamps, formatting timestamps to
+    /// strings or truncating timestamps to dates. UTC is used if absent.
+    pub session_timezone: Option<Arc<str>>,
+}
+
+pub fn cast_with_options(
+    array: &dyn Array,
+    cast_type: &DataType,
+    options: &SparkCastOptions,
+) -> Result<ArrayRef> {
+    let with_session_timezone;
+    let array = match (array.data_type(), &options.session_timezone) {
+        (DataType::Timestamp(TimeUnit::Microsecond, None), Some(tz)) => {
+            with_session_timezone = as_primitive_array::<TimestampMicrosecondType>(array)
+                .clone()
+                .with_timezone(tz.clone());
+            &with_session_timezone as &dyn Array
+        }
+        _ => array,
+    };
+
+    // strings are parsed in the session timezone, and the output type is kept
+    // without timezone
+    let parse_type = match (array.data_type(), cast_type, &options.session_timezone) {
+        (DataType::Utf8, DataType::Timestamp(TimeUnit::Microsecond, None), Some(tz)) => {
+            DataType::Timestamp(TimeUnit::Microsecond, Some(tz.clone()))
+        }
+        _ => cast_type.clone(),
+    };
+    let casted = match (
+        array.data_type(),
+        &parse_type,
+        options.string_to_timestamp_epoch_unit,
+    ) {
+        (
+            &DataType::Utf8,
+            &DataType::Timestamp(TimeUnit::Microsecond, ref tz),
+            Some(epoch_unit),
+        ) => cast_string_to_timestamp_with_epoch(array, &parse_type, tz.clone(), epoch_unit)?,
+        _ => cast(array, &parse_type)?,
+    };
+    let casted: ArrayRef = if &parse_type != cast_type {
+        Arc::new(
+            as_primitive_array::<TimestampMicrosecondType>(&casted)
+                .clone()
+                .with_timezone_opt(None::<Arc<str>>),
+        )
+    } else {
+        casted
+    };
+    if options.ansi {
+        check_ansi_cast(array, &casted)?;
+    }
+    Ok(casted)
+}
+
+/// Fails with spark's ANSI error on the first value which is invalid or
+/// overflows in the cast. non-ANSI casts turn such values into nulls, except
+/// float to integral casts which saturate like java.
+fn check_ansi_cast(array: &dyn Array, casted: &dyn Array) -> Result<()> {
+    let is_integral = |dt: &DataType| {
+        matches!(
+            dt,
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64
+        )
+    };
+    let from_type = array.data_type();
+    let to_type = casted.data_type();
+
+    match from_type {
+        DataType::Utf8
+            if is_integral(to_type)
+                || matches!(
+                    to_type,
+                    DataType::Decimal128(..) | DataType::Date32 | DataType::Timestamp(..)
+                ) => {}
+        from_type if is_integral(from_type) || matches!(from_type, DataType::Decimal128(..)) => {
+            if !is_integral(to_type) && !matches!(to_type, DataType::Decimal128(..)) {
+                return Ok(());
+            }
+        }
+        DataType::Float32 | DataType::Float64 if is_integral(to_type) => {
+            let values = arrow::compute::cast(array, &DataType::Float64)?;
+            let values = as_float64_array(&values)?;
+            let min = match to_type {
+                DataType::Int8 => i8::MIN as f64,
+                DataType::Int16 => i16::MIN as f64,
+                DataType::Int32 => i32::MIN as f64,
+                _ => i64::MIN as f64,
+            };
+            for (i, v) in values.iter().enumerate() {
+                // truncated value must be in [min, -min)
+                if let Some(v) = v.map(|v| v.trunc()) {
+                    if v.is_nan() || v < min || v >= -min {
+                        return Err(ansi_cast_overflow_error(array, i, to_type)?);
+                    }
+                }
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    }
+
+    for i in 0..array.len() {
+        if array.is_valid(i) && casted.is_null(i) {
+            return Err(match (from_type, to_type) {
+                (DataType::Utf8, DataType::Decimal128(precision, scale)) => {
+                    let s = as_string_array(array).value(i);
+                    match bigdecimal::BigDecimal::from_str(s) {
+                        Ok(_) => ansi_decimal_out_of_range_error(s, *precision, *scale),
+                        Err(_) => ansi_cast_invalid_input_error(s, to_type),
+                    }
+                }
+                (DataType::Utf8, _) => {
+                    ansi_cast_invalid_input_error(as_string_array(array).value(i), to_type)
+                }
+                (_, DataType::Decimal128(precision, scale)) => {
+                    let value = arrow::util::display::array_value_to_string(array, i)?;
+                    ansi_decimal_out_of_range_error(&value, *precision, *scale)
+                }
+                _ => ansi_cast_overflow_error(array, i, to_type)?,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn spark_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Int8 => "TINYINT".to_string(),
+        DataType::Int16 => "SMALLINT".to_string(),
+        DataType::Int32 => "INT".to_string(),
+        DataType::Int64 => "BIGINT".to_string(),
+        DataType::Float32 => "FLOAT".to_string(),
+        DataType::Float64 => "DOUBLE".to_string(),
+        DataType::Utf8 => "STRING".to_string(),
+        DataType::Date32 => "DATE".to_string(),
+        DataType::Timestamp(..) => "TIMESTAMP".to_string(),
+        DataType::Decimal128(precision, scale) => format!("DECIMAL({},{})", precision, scale),
+        other => format!("{}", other).to_uppercase(),
+    }
+}
+
+fn ansi_cast_invalid_input_error(value: &str, to_type: &DataType) -> DataFusionError {
+    DataFusionError::Execution(format!(
+        "[CAST_INVALID_INPUT] The value '{}' of the type \"STRING\" cannot be cast to \"{}\" \
+            because it is malformed. Correct the value as per the syntax, or change its target \
+            type. Use `try_cast` to tolerate malformed input and return NULL instead. If \
+            necessary set \"spark.sql.ansi.enabled\" to \"false\" to bypass this error.",
+        value,
+        spark_type_name(to_type),
+    ))
+}
+
+fn ansi_cast_overflow_error(
+    array: &dyn Array,
+    i: usize,
+    to_type: &DataType,
+) -> Result<DataFusionError> {
+    // values are formatted like spark's sql literals
+    let value = arrow::util::display::array_value_to_string(array, i)?;
+    let value = match array.data_type() {
+        DataType::Int8 => format!("{}Y", value),
+        DataType::Int16 => format!("{}S", value),
+        DataType::Int64 => format!("{}L", value),
+        DataType::Float64 => format!("{}D", value),
+        DataType::Decimal128(..) => format!("{}BD", value),
+        _ => value,
+    };
+    Ok(DataFusionError::Execution(format!(
+        "[CAST_OVERFLOW] The value {} of the type \"{}\" cannot be cast to \"{}\" due to an \
+            overflow. Use `try_cast` to tolerate overflow and return NULL instead. If necessary \
+            set \"spark.sql.ansi.enabled\" to \"false\" to bypass this error.",
+        value,
+        spark_type_name(array.data_type()),
+        spark_type_name(to_type),
+    )))
+}
+
+fn ansi_decimal_out_of_range_error(value: &str, precision: u8, scale: i8) -> DataFusionError {
+    DataFusionError::Execution(format!(
+        "[NUMERIC_VALUE_OUT_OF_RANGE] {} cannot be represented as Decimal({}, {}). If necessary \
+            set \"spark.sql.ansi.enabled\" to \"false\" to bypass this error.",
+        value.trim(),
+        precision,
+        scale,
+    ))
+}
+
 pub fn cast_impl(
     array: &dyn Array,
     cast_type: &DataType,
@@ -71,10 +278,29 @@ pub fn cast_impl(
             // spark compatible string to integer cast
             try_cast_string_array_to_integer(array, cast_type)?
         }
+        (&DataType::Utf8, &DataType::Float32 | &DataType::Float64) => {
+            // spark compatible string to float cast
+            try_cast_string_array_to_float(array, cast_type)?
+        }
         (&DataType::Utf8, &DataType::Decimal128(_, _)) => {
             // spark compatible string to decimal cast
             try_cast_string_array_to_decimal(array, cast_type)?
         }
+        (&DataType::Utf8, &DataType::Date32) => {
+            // spark compatible string to date cast
+            try_cast_string_array_to_date(array)?
+        }
+        (&DataType::Utf8, &DataType::Timestamp(TimeUnit::Microsecond, ref tz)) => {
+            // spark compatible string to timestamp cast
+            try_cast_string_array_to_timestamp(array, tz.as_deref())?
+        }
+        (&DataType::Decimal128(_, from_scale), &DataType::Decimal128(precision, scale)) => {
+            // spark compatible decimal to decimal cast
+            let array = as_primitive_array::<Decimal128Type>(array);
+            let rescaled: Decimal128Array =
+                array.unary_opt(|v| rescale_decimal(v, from_scale, precision, scale));
+            Arc::new(rescaled.with_precision_and_scale(precision, scale)?)
+        }
         (&DataType::Decimal128(_, _), DataType::Utf8) => {
             // spark compatible decimal to string cast
             try_cast_decimal_array_to_string(array, cast_type)?
@@ -86,13 +312,65 @@ pub fn cast_impl(
                 &DataType::Float64,
             )?
         }
+        (
+            &DataType::Int8 | &DataType::Int16 | &DataType::Int32 | &DataType::Int64,
+            &DataType::Timestamp(TimeUnit::Microsecond, _),
+        ) => {
+            // spark compatible integral to timestamp cast, the value is seconds since epoch
+            let secs = arrow::compute::cast(array, &DataType::Int64)?;
+            let micros: Int64Array = as_primitive_array::<Int64Type>(&secs)
+                .unary_opt(|secs| secs.checked_mul(1_000_000));
+            arrow::compute::cast(&micros, cast_type)?
+        }
+        (&DataType::Timestamp(TimeUnit::Microsecond, tz), &DataType::Date32) => {
+            // spark truncates timestamps to dates in the session timezone, which is
+            // carried by the timestamp type. UTC is used if the timezone is absent.
+            cast_timestamp_to_date(
+                as_primitive_array::<TimestampMicrosecondType>(array),
+                tz.as_deref(),
+            )?
+        }
+        (&DataType::Timestamp(TimeUnit::Microsecond, tz), &DataType::Utf8) => {
+            // spark formats timestamps in the session timezone, which is carried by
+            // the timestamp type. UTC is used if the timezone is absent.
+            cast_timestamp_to_string(
+                as_primitive_array::<TimestampMicrosecondType>(array),
+                tz.as_deref(),
+            )?
+        }
+        (&DataType::Float32, DataType::Utf8) => {
+            // spark compatible float to string cast
+            Arc::new(
+                as_float32_array(array)?
+                    .iter()
+                    .map(|v| v.map(|v| to_java_float_string(&format!("{:e}", v))))
+                    .collect::<StringArray>(),
+            )
+        }
+        (&DataType::Float64, DataType::Utf8) => {
+            // spark compatible double to string cast
+            Arc::new(
+                as_float64_array(array)?
+                    .iter()
+                    .map(|v| v.map(|v| to_java_float_string(&format!("{:e}", v))))
+                    .collect::<StringArray>(),
+            )
+        }
         (&DataType::Boolean, DataType::Utf8) => {
             // spark compatible boolean to string cast
             try_cast_boolean_array_to_string(array, cast_type)?
         }
+        (&DataType::List(_), DataType::Utf8) => {
+            // spark compatible array to string cast
+            cast_list_array_to_string(as_list_array(array), match_struct_fields)?
+        }
         (&DataType::List(_), DataType::List(to_field)) => {
             let list = as_list_array(array);
             let casted_items = cast_impl(list.values(), to_field.data_type(), match_struct_fields)?;
+
+            // spark keeps null items even if cast_type declares non-nullable items.
+            // the item field is still taken from cast_type as is, so that the output
+            // type always matches the data type of the cast expression
             make_array(ArrayData::try_new(
                 DataType::List(to_field.clone()),
                 list.len(),
@@ -103,6 +381,8 @@ pub fn cast_impl(
             )?)
         }
         (&DataType::Struct(_), DataType::Struct(to_fields)) => {
+            // output fields (including nullability) are always taken from cast_type,
+            // never inferred from the casted children, since spark checks them
             let struct_ = as_struct_array(array);
 
             if !match_struct_fields {
@@ -147,6 +427,7 @@ pub fn cast_impl(
                     })
                     .collect::<Result<Vec<_>>>()?;
 
+                // missing columns are filled with nulls, so they must be nullable
                 let casted_fields = to_fields
                     .iter()
                     .map(|field: &FieldRef| {
@@ -179,6 +460,14 @@ pub fn cast_impl(
                 match_struct_fields,
             )?;
 
+            // spark disallows null map keys
+            let casted_keys = as_struct_array(&casted_entries).column(0);
+            if casted_keys.null_count() > map.keys().null_count() {
+                return Err(DataFusionError::Execution(
+                    "cannot cast map with keys becoming null".to_string(),
+                ));
+            }
+
             make_array(ArrayData::try_new(
                 DataType::Map(to_entries_field.clone(), to_sorted),
                 map.len(),
@@ -190,7 +479,8 @@ pub fn cast_impl(
         }
         _ => {
             // default cast
-            arrow::compute::kernels::cast::cast(array, cast_type)?
+            let casted = arrow::compute::kernels::cast::cast(array, cast_type)?;
+            preserve_nulls(array, casted)?
         }
     })
 }
@@ -221,6 +511,25 @@ fn try_cast_string_array_to_integer(array: &dyn Array, cast_type: &DataType) ->
     })
 }
 
+fn try_cast_string_array_to_float(array: &dyn Array, cast_type: &DataType) -> Result<ArrayRef> {
+    let array = array.as_any().downcast_ref::<StringArray>().unwrap();
+    Ok(match cast_type {
+        DataType::Float32 => Arc::new(
+            array
+                .iter()
+                .map(|v| v.and_then(to_float::<f32>))
+                .collect::<Float32Array>(),
+        ),
+        DataType::Float64 => Arc::new(
+            array
+                .iter()
+                .map(|v| v.and_then(to_float::<f64>))
+                .collect::<Float64Array>(),
+        ),
+        _ => unreachable!("cast_type must be DataType::Float32 or DataType::Float64"),
+    })
+}
+
 fn try_cast_string_array_to_decimal(array: &dyn Array, cast_type: &DataType) -> Result<ArrayRef> {
     if let &DataType::Decimal128(precision, scale) = cast_type {
         let array = array.as_any().downcast_ref::<StringArray>().unwrap();
@@ -244,6 +553,32 @@ fn try_cast_string_array_to_decimal(array: &dyn Array, cast_type: &DataType) ->
     unreachable!("cast_type must be DataType::Decimal")
 }
 
+// spark's `Decimal.changePrecision()`, rounds with HALF_UP and returns None if
+// the rescaled value does not fit the precision
+fn rescale_decimal(value: i128, from_scale: i8, precision: u8, scale: i8) -> Option<i128> {
+    let rescaled = if scale >= from_scale {
+        match 10i128.checked_pow((scale - from_scale) as u32) {
+            Some(factor) => value.checked_mul(factor)?,
+            None if value == 0 => 0,
+            None => return None,
+        }
+    } else {
+        match 10i128.checked_pow((from_scale - scale) as u32) {
+            Some(divisor) => {
+                let (quotient, remainder) = (value / divisor, value % divisor);
+                if remainder.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+                    quotient + value.signum()
+                } else {
+                    quotient
+                }
+            }
+            None => 0, // all digits are dropped and |value| < 10^38 always rounds to zero
+        }
+    };
+    let max_unscaled = 10i128.pow(precision as u32);
+    (rescaled.unsigned_abs() < max_unscaled.unsigned_abs()).then_some(rescaled)
+}
+
 fn try_cast_decimal_array_to_string(array: &dyn Array, cast_type: &DataType) -> Result<ArrayRef> {
     if let &DataType::Utf8 = cast_type {
         let array = array.as_any().downcast_ref::<Decimal128Array>().unwrap();
@@ -260,6 +595,52 @@ fn try_cast_decimal_array_to_string(array: &dyn Array, cast_type: &DataType) ->
     unreachable!("cast_type must be DataType::Utf8")
 }
 
+// java's `Float.toString()` and `Double.toString()`, formats the shortest
+// digits (given in rust's scientific notation like `-1.2345e-5`) in plain
+// notation if 1e-3 <= |v| < 1e7, otherwise in scientific notation like `1.0E10`
+fn to_java_float_string(sci: &str) -> String {
+    let (sign, unsigned) = match sci.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", sci),
+    };
+    let (mantissa, exp) = match unsigned.split_once('e') {
+        Some((mantissa, _)) if mantissa == "0" => return format!("{}0.0", sign),
+        Some(split) => split,
+        None if unsigned == "inf" => return format!("{}Infinity", sign),
+        None => return "NaN".to_string(),
+    };
+    let exp: i32 = exp.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+    if (-3..7).contains(&exp) {
+        if exp >= 0 {
+            let num_int_digits = exp as usize + 1;
+            let (int_part, frac_part) = if digits.len() > num_int_digits {
+                digits.split_at(num_int_digits)
+            } else {
+                (digits.as_str(), "")
+            };
+            format!(
+                "{}{}{}.{}",
+                sign,
+                int_part,
+                "0".repeat(num_int_digits - int_part.len()),
+                if frac_part.is_empty() { "0" } else { frac_part },
+            )
+        } else {
+            format!("{}0.{}{}", sign, "0".repeat((-exp - 1) as usize), digits)
+        }
+    } else {
+        let (first, rest) = digits.split_at(1);
+        format!(
+            "{}{}.{}E{}",
+            sign,
+            first,
+            if rest.is_empty() { "0" } else { rest },
+            exp,
+        )
+    }
+}
+
 fn try_cast_boolean_array_to_string(array: &dyn Array, cast_type: &DataType) -> Result<ArrayRef> {
     if let &DataType::Utf8 = cast_type {
         let array = array.as_any().downcast_ref::<BooleanArray>().unwrap();
@@ -273,6 +654,143 @@ fn try_cast_boolean_array_to_string(array: &dyn Array, cast_type: &DataType) ->
     unreachable!("cast_type must be DataType::Utf8")
 }
 
+// renders arrays like spark, e.g. "[1, null, 3]". items are casted with the
+// spark compatible casts, null items are rendered as "null" and null arrays
+// stay null.
+fn cast_list_array_to_string(array: &ListArray, match_struct_fields: bool) -> Result<ArrayRef> {
+    let items = cast_impl(array.values(), &DataType::Utf8, match_struct_fields)?;
+    let items = as_string_array(&items);
+    let mut builder = StringBuilder::new();
+    let mut rendered = String::new();
+
+    for (i, range) in array.value_offsets().windows(2).enumerate() {
+        if array.is_null(i) {
+            builder.append_null();
+            continue;
+        }
+        rendered.clear();
+        rendered.push('[');
+        for item_idx in range[0] as usize..range[1] as usize {
+            if item_idx > range[0] as usize {
+                rendered.push_str(", ");
+            }
+            if items.is_valid(item_idx) {
+                rendered.push_str(items.value(item_idx));
+            } else {
+                rendered.push_str("null");
+            }
+        }
+        rendered.push(']');
+        builder.append_value(&rendered);
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+fn cast_timestamp_to_date(array: &TimestampMicrosecondArray, tz: Option<&str>) -> Result<ArrayRef> {
+    let tz: Tz = tz.unwrap_or("UTC").parse()?;
+    let dates: Date32Array = array.unary_opt(|micros| {
+        let datetime = as_datetime_with_timezone::<TimestampMicrosecondType>(micros, tz)?;
+        let offset_secs = (datetime.naive_local() - datetime.naive_utc()).num_seconds();
+        let local_micros = micros.checked_add(offset_secs * 1_000_000)?;
+        Some(local_micros.div_euclid(86_400_000_000) as i32)
+    });
+    Ok(Arc::new(dates))
+}
+
+fn cast_timestamp_to_string(
+    array: &TimestampMicrosecondArray,
+    tz: Option<&str>,
+) -> Result<ArrayRef> {
+    let tz: Tz = tz.unwrap_or("UTC").parse()?;
+    let strings: StringArray = array
+        .iter()
+        .map(|micros| {
+            let datetime = as_datetime_with_timezone::<TimestampMicrosecondType>(micros?, tz)?;
+            let mut formatted = datetime.format("%Y-%m-%d %H:%M:%S").to_string();
+
+            // like spark, fraction digits are printed without trailing zeros
+            let fraction_micros = datetime.timestamp_subsec_micros();
+            if fraction_micros > 0 {
+                let fraction = format!(".{:06}", fraction_micros);
+                formatted.push_str(fraction.trim_end_matches('0'));
+            }
+            Some(formatted)
+        })
+        .collect();
+    Ok(Arc::new(strings))
+}
+
+fn try_cast_string_array_to_date(array: &dyn Array) -> Result<ArrayRef> {
+    let dates: Date32Array = as_string_array(array)
+        .iter()
+        .map(|s| s.and_then(to_date))
+        .collect();
+    Ok(Arc::new(dates))
+}
+
+fn try_cast_string_array_to_timestamp(array: &dyn Array, tz: Option<&str>) -> Result<ArrayRef> {
+    let session_tz: Tz = tz.unwrap_or("UTC").parse()?;
+    let micros: TimestampMicrosecondArray = as_string_array(array)
+        .iter()
+        .map(|s| s.and_then(|s| to_timestamp(s, &session_tz)))
+        .collect();
+    Ok(Arc::new(micros.with_timezone_opt(tz.map(Arc::from))))
+}
+
+// null input slots must stay null after casting, which is not guaranteed by
+// every arrow cast kernel (e.g. for all-null inputs), so input nulls are always
+// merged into the output. unions and nulls cannot have null buffers.
+fn preserve_nulls(array: &dyn Array, casted: ArrayRef) -> Result<ArrayRef> {
+    if array.null_count() == 0 || matches!(casted.data_type(), DataType::Union(..) | DataType::Null)
+    {
+        return Ok(casted);
+    }
+    let nulls = NullBuffer::union(array.nulls(), casted.nulls());
+    if nulls.as_ref().map(|nulls| nulls.null_count()).unwrap_or(0) == casted.null_count() {
+        return Ok(casted);
+    }
+    Ok(make_array(
+        casted.to_data().into_builder().nulls(nulls).build()?,
+    ))
+}
+
+fn cast_string_to_timestamp_with_epoch(
+    array: &dyn Array,
+    cast_type: &DataType,
+    tz: Option<Arc<str>>,
+    epoch_unit: TimeUnit,
+) -> Result<ArrayRef> {
+    // non-numeric strings are still parsed as datetimes
+    let parsed = cast(array, cast_type)?;
+    let parsed = as_primitive_array::<TimestampMicrosecondType>(&parsed);
+    let micros: TimestampMicrosecondArray = as_string_array(array)
+        .iter()
+        .zip(parsed.iter())
+        .map(|(s, parsed)| match s.map(|s| s.trim()) {
+            Some(s) if is_integral_string(s) => s
+                .parse::<i64>()
+                .ok()
+                .and_then(|epoch| epoch_to_micros(epoch, epoch_unit)),
+            _ => parsed,
+        })
+        .collect();
+    Ok(Arc::new(micros.with_timezone_opt(tz)))
+}
+
+fn is_integral_string(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn epoch_to_micros(epoch: i64, epoch_unit: TimeUnit) -> Option<i64> {
+    match epoch_unit {
+        TimeUnit::Second => epoch.checked_mul(1_000_000),
+        TimeUnit::Millisecond => epoch.checked_mul(1_000),
+        TimeUnit::Microsecond => Some(epoch),
+        TimeUnit::Nanosecond => Some(epoch.div_euclid(1_000)),
+    }
+}
+
 fn cast_float_to_integer<F: ArrowPrimitiveType, T: ArrowPrimitiveType>(
     array: &PrimitiveArray<F>,
 ) -> PrimitiveArray<T>
@@ -283,6 +801,9 @@ where
 }
 
 // this implementation is original copied from spark UTF8String.scala
+// only ascii digits with an optional sign and fraction are accepted, like spark.
+// locale formatted numbers (with underscores, commas, currency symbols or
+// non-ascii digits) are rejected.
 fn to_integer<T: Bounded + FromPrimitive + Integer + Signed + Copy>(input: &str) -> Option<T> {
     let bytes = input.as_bytes();
 
@@ -357,21 +878,206 @@ fn to_integer<T: Bounded + FromPrimitive + Integer + Signed + Copy>(input: &str)
     Some(result)
 }
 
+// surrounding whitespaces are trimmed like spark. rust's float parser only
+// accepts digits, sign, decimal point, exponent and the inf/infinity/nan
+// literals, so any trailing non-numeric characters, including java's type
+// suffixes like "1.0d" and "1.0f" (accepted by Double.parseDouble), are rejected.
+fn to_float<T: FromStr>(input: &str) -> Option<T> {
+    input.trim().parse().ok()
+}
+
+// the parsed decimal is rescaled with java's ROUND_HALF_UP like spark's
+// `Decimal.changePrecision()`, values overflowing the precision become null.
 fn to_decimal(input: &str, precision: u8, scale: i8) -> Option<i128> {
-    let precision = precision as u64;
+    let decimal = bigdecimal::BigDecimal::from_str(input).ok()?;
+    let (unscaled, exp) = decimal.as_bigint_and_exponent();
     let scale = scale as i64;
-    bigdecimal::BigDecimal::from_str(input)
-        .ok()
-        .map(|decimal| decimal.with_prec(precision).with_scale(scale))
-        .and_then(|decimal| {
-            let (bigint, _exp) = decimal.as_bigint_and_exponent();
-            bigint.to_i128()
-        })
+    if unscaled.is_zero() || exp - scale > decimal.digits() as i64 {
+        return Some(0); // all digits are rounded off
+    }
+    if scale - exp > DECIMAL128_MAX_PRECISION as i64 {
+        return None; // always overflows
+    }
+
+    let rescaled = if exp > scale {
+        let divisor = num::BigInt::from(10).pow((exp - scale) as u32);
+        let (quotient, remainder) = unscaled.div_rem(&divisor);
+        if remainder.abs() * 2 >= divisor {
+            quotient + remainder.signum()
+        } else {
+            quotient
+        }
+    } else {
+        unscaled * num::BigInt::from(10).pow((scale - exp) as u32)
+    };
+    if rescaled.abs() >= num::BigInt::from(10).pow(precision as u32) {
+        return None;
+    }
+    rescaled.to_i128()
+}
+
+// spark's `DateTimeUtils.stringToDate()`, accepts `[+-]yyyy*`, `[+-]yyyy*-[m]m`
+// and `[+-]yyyy*-[m]m-[d]d`, optionally followed by a space or 'T' and any
+// trailing characters. the year has 4 to 7 digits.
+fn to_date(input: &str) -> Option<i32> {
+    let s = trim_spark_string(input);
+    let date_part = match s.find([' ', 'T']) {
+        Some(pos) => &s[..pos],
+        None => s,
+    };
+    let date = parse_date_segments(date_part)?;
+    Some((date - NaiveDate::from_ymd_opt(1970, 1, 1)?).num_days() as i32)
+}
+
+// spark's `DateTimeUtils.stringToTimestamp()`, accepts a date like `to_date()`
+// optionally followed by a space or 'T' and `[h]h:[m]m:[s]s.[ms][ms][ms][us][us][us]`
+// with an optional zone id, like `Z`, `+08:00`, `UTC+8` or `Asia/Shanghai`.
+// timestamps without zone ids are in the session timezone. strings of only
+// times are not supported and result in nulls.
+fn to_timestamp(input: &str, session_tz: &Tz) -> Option<i64> {
+    let s = trim_spark_string(input);
+    let (date_part, time_part) = match s.find([' ', 'T']) {
+        Some(pos) => (&s[..pos], &s[pos + 1..]),
+        None => (s, ""),
+    };
+    let date = parse_date_segments(date_part)?;
+    let (time, zone) = parse_time_segments(time_part)?;
+    let local = date.and_time(time);
+
+    let tz = match zone {
+        Some(zone) => parse_zone_id(zone)?,
+        None => *session_tz,
+    };
+    let utc = match tz.from_local_datetime(&local) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => datetime.naive_utc(),
+        LocalResult::None => {
+            // in a daylight saving gap, shift forward with the offset before the
+            // gap like java's ZonedDateTime
+            let offset = tz.offset_from_utc_datetime(&(local - Duration::days(1)));
+            local - Duration::seconds(offset.fix().local_minus_utc() as i64)
+        }
+    };
+    let micros = utc.timestamp().checked_mul(1_000_000)?;
+    micros.checked_add(utc.timestamp_subsec_micros() as i64)
+}
+
+// spark trims whitespaces and ISO control characters of datetime strings
+fn trim_spark_string(s: &str) -> &str {
+    s.trim_matches(|c: char| c.is_whitespace() || c.is_control())
+}
+
+fn parse_digits(s: &str, min_digits: usize, max_digits: usize) -> Option<u32> {
+    if s.len() < min_digits || s.len() > max_digits || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn parse_date_segments(s: &str) -> Option<NaiveDate> {
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let mut segments = s.split('-');
+    let year = parse_digits(segments.next()?, 4, 7)? as i32;
+    let month = segments
+        .next()
+        .map(|m| parse_digits(m, 1, 2))
+        .unwrap_or(Some(1))?;
+    let day = segments
+        .next()
+        .map(|d| parse_digits(d, 1, 2))
+        .unwrap_or(Some(1))?;
+    if segments.next().is_some() {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(if negative { -year } else { year }, month, day)
+}
+
+// parses `[h]h[:[m]m[:[s]s[.fraction]]][zone_id]`, an empty string is midnight.
+// fraction digits beyond microseconds are truncated.
+fn parse_time_segments(s: &str) -> Option<(NaiveTime, Option<&str>)> {
+    if s.is_empty() {
+        return Some((NaiveTime::MIN, None));
+    }
+    let end_of_digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let mut hms = [0u32; 3];
+    let mut rest = s;
+    let mut num_segments = 0;
+    while num_segments < 3 {
+        let end = end_of_digits(rest);
+        hms[num_segments] = parse_digits(&rest[..end], 1, 2)?;
+        rest = &rest[end..];
+        num_segments += 1;
+        match rest.strip_prefix(':') {
+            Some(next) if num_segments < 3 => rest = next,
+            _ => break,
+        }
+    }
+
+    let mut micros = 0;
+    if num_segments == 3 {
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let end = end_of_digits(fraction);
+            for (i, digit) in fraction[..end].bytes().take(6).enumerate() {
+                micros += (digit - b'0') as u32 * 10u32.pow(5 - i as u32);
+            }
+            rest = &fraction[end..];
+        }
+    }
+
+    // zone ids are only allowed after seconds
+    let zone = match rest.trim() {
+        "" => None,
+        _ if num_segments < 3 => return None,
+        zone => Some(zone),
+    };
+    let time = NaiveTime::from_hms_micro_opt(hms[0], hms[1], hms[2], micros)?;
+    Some((time, zone))
+}
+
+// parses zone ids like java's `ZoneId.of()`, including offsets with single
+// digit hours like spark
+fn parse_zone_id(zone: &str) -> Option<Tz> {
+    let parse_offset = |offset: &str| -> Option<Tz> {
+        let (sign, offset) = offset.split_at(1);
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let hours = parse_digits(hours, 1, 2)?;
+        let minutes = parse_digits(minutes, 1, 2)?;
+        if hours > 18 || minutes > 59 {
+            return None;
+        }
+        format!("{}{:02}:{:02}", sign, hours, minutes).parse().ok()
+    };
+
+    if zone == "Z" {
+        return "+00:00".parse().ok();
+    }
+    if zone.starts_with(['+', '-']) {
+        return parse_offset(zone);
+    }
+    for prefix in ["UTC", "GMT", "UT"] {
+        if let Some(offset) = zone.strip_prefix(prefix) {
+            if offset.is_empty() {
+                return "+00:00".parse().ok();
+            }
+            if offset.starts_with(['+', '-']) {
+                return parse_offset(offset);
+            }
+        }
+    }
+    zone.parse().ok()
 }
 
 #[cfg(test)]
 mod test {
     use crate::cast::*;
+    use arrow::buffer::OffsetBuffer;
     use datafusion::common::cast::as_int32_array;
 
     #[test]
@@ -403,4 +1109,714 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn test_struct_cast_preserves_nullability() {
+        let struct_array: ArrayRef = Arc::new(StructArray::from(vec![
+            (
+                Arc::new(Field::new("a", DataType::Int32, true)),
+                Arc::new(Int32Array::from(vec![Some(1), Some(2), Some(3)])) as ArrayRef,
+            ),
+            (
+                Arc::new(Field::new("b", DataType::Utf8, true)),
+                Arc::new(StringArray::from(vec![Some("x"), None, Some("z")])) as ArrayRef,
+            ),
+        ]));
+        let to_fields = Fields::from(vec![
+            Field::new("a", DataType::Int64, false),
+            Field::new("b", DataType::Utf8, true),
+        ]);
+        let to_type = DataType::Struct(to_fields.clone());
+
+        let casted = cast(&struct_array, &to_type).unwrap();
+        assert_eq!(casted.data_type(), &to_type);
+        let casted_struct = as_struct_array(&casted);
+        assert_eq!(casted_struct.fields(), &to_fields);
+        assert_eq!(
+            casted_struct.column(0).as_ref(),
+            &Int64Array::from(vec![1, 2, 3]) as &dyn Array,
+        );
+
+        let casted = cast_scan_input_array(&struct_array, &to_type).unwrap();
+        assert_eq!(casted.data_type(), &to_type);
+    }
+
+    #[test]
+    fn test_list_cast_keeps_null_items() {
+        // [["1", null, "x"], null, ["3"]]
+        let list_array: ArrayRef = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8, true)),
+            OffsetBuffer::new(vec![0, 3, 3, 4].into()),
+            Arc::new(StringArray::from(vec![
+                Some("1"),
+                None,
+                Some("x"),
+                Some("3"),
+            ])),
+            Some(vec![true, false, true].into()),
+        ));
+        let to_type = DataType::List(Arc::new(Field::new("item", DataType::Int32, false)));
+
+        let casted = cast(&list_array, &to_type).unwrap();
+        let casted_list = as_list_array(&casted);
+        assert_eq!(casted.data_type(), &to_type);
+        assert_eq!(casted_list.len(), 3);
+        assert!(casted_list.is_null(1));
+        assert_eq!(
+            as_int32_array(&casted_list.value(0)).unwrap(),
+            &Int32Array::from(vec![Some(1), None, None]),
+        );
+        assert_eq!(
+            as_int32_array(&casted_list.value(2)).unwrap(),
+            &Int32Array::from(vec![Some(3)]),
+        );
+
+        // output type does not depend on whether items contain nulls
+        let casted = cast(&list_array.slice(2, 1), &to_type).unwrap();
+        assert_eq!(casted.data_type(), &to_type);
+
+        // non-nullable items of the same type stay non-nullable
+        let int_list_array: ArrayRef = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Int32, false)),
+            OffsetBuffer::new(vec![0, 2, 3].into()),
+            Arc::new(Int32Array::from(vec![1, 2, 3])),
+            None,
+        ));
+        let casted = cast(&int_list_array, &to_type).unwrap();
+        assert_eq!(casted.data_type(), &to_type);
+    }
+
+    #[test]
+    fn test_int_to_timestamp() {
+        let i64_array: ArrayRef = Arc::new(Int64Array::from(vec![
+            Some(1700000000),
+            Some(-1),
+            Some(i64::MAX),
+            None,
+        ]));
+        let casted = cast(
+            &i64_array,
+            &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        )
+        .unwrap();
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted),
+            &TimestampMicrosecondArray::from(vec![
+                Some(1700000000000000),
+                Some(-1000000),
+                None,
+                None,
+            ])
+            .with_timezone("UTC"),
+        );
+
+        let i32_array: ArrayRef = Arc::new(Int32Array::from(vec![Some(86400), Some(-86400)]));
+        let casted = cast(
+            &i32_array,
+            &DataType::Timestamp(TimeUnit::Microsecond, None),
+        )
+        .unwrap();
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted),
+            &TimestampMicrosecondArray::from(vec![Some(86400000000), Some(-86400000000)]),
+        );
+    }
+
+    #[test]
+    fn test_string_to_int_rejects_locale_formats() {
+        let cases: Vec<(&str, Option<i32>)> = vec![
+            ("42", Some(42)),
+            ("-42", Some(-42)),
+            ("+42", Some(42)),
+            ("42.99", Some(42)),
+            ("-42.", Some(-42)),
+            ("1_000", None),
+            ("1,000", None),
+            ("$42", None),
+            ("42€", None),
+            ("١٢٣", None),
+            ("４２", None),
+            ("4.2e1", None),
+            ("", None),
+            ("-", None),
+        ];
+        let string_array: ArrayRef =
+            Arc::new(StringArray::from_iter_values(cases.iter().map(|(s, _)| *s)));
+        let casted = cast(&string_array, &DataType::Int32).unwrap();
+        let casted = as_int32_array(&casted).unwrap();
+        for (i, (s, v)) in cases.iter().enumerate() {
+            let casted_value = casted.is_valid(i).then(|| casted.value(i));
+            assert_eq!(casted_value, *v, "casting {:?}", s);
+        }
+
+        // also applies to byte/short, with overflowed values rejected
+        let string_array: ArrayRef = Arc::new(StringArray::from(vec!["127", "128", "1_0"]));
+        let casted = cast(&string_array, &DataType::Int8).unwrap();
+        assert_eq!(
+            as_primitive_array::<Int8Type>(&casted),
+            &Int8Array::from(vec![Some(127), None, None]),
+        );
+        let string_array: ArrayRef = Arc::new(StringArray::from(vec!["-32768", "1,000", "٣"]));
+        let casted = cast(&string_array, &DataType::Int16).unwrap();
+        assert_eq!(
+            as_primitive_array::<Int16Type>(&casted),
+            &Int16Array::from(vec![Some(-32768), None, None]),
+        );
+    }
+
+    #[test]
+    fn test_string_to_float_rejects_suffixes() {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("1.0"),
+            Some(" 1.5 "),
+            Some("1.0d"),
+            Some("1.0f"),
+            Some("1.0D"),
+            Some("1.0F"),
+            Some("1.0x"),
+            Some("-1e3"),
+            Some("-Infinity"),
+            None,
+        ]));
+        let expected = vec![
+            Some(1.0),
+            Some(1.5),
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(-1000.0),
+            Some(f64::NEG_INFINITY),
+            None,
+        ];
+
+        let casted = cast(&strings, &DataType::Float64).unwrap();
+        assert_eq!(
+            as_float64_array(&casted).unwrap(),
+            &Float64Array::from(expected.clone()),
+        );
+        let casted = cast(&strings, &DataType::Float32).unwrap();
+        assert_eq!(
+            as_float32_array(&casted).unwrap(),
+            &Float32Array::from(
+                expected
+                    .iter()
+                    .map(|v| v.map(|v| v as f32))
+                    .collect::<Vec<_>>()
+            ),
+        );
+    }
+
+    #[test]
+    fn test_string_to_decimal_rounds_half_up() {
+        // expected values are from spark's `cast(s as decimal(p, s))`
+        let cases = [
+            ("1.005", 10, 2, Some(101)),
+            ("1.004", 10, 2, Some(100)),
+            ("1.0049999", 10, 2, Some(100)),
+            ("-1.005", 10, 2, Some(-101)),
+            ("0.5", 10, 0, Some(1)),
+            ("-0.5", 10, 0, Some(-1)),
+            ("2.5", 10, 0, Some(3)),
+            ("0.125", 10, 2, Some(13)),
+            ("1.5e1", 10, 0, Some(15)),
+            ("1e-5", 10, 2, Some(0)),
+            ("12345.678", 10, 2, Some(1234568)),
+            ("9.995", 3, 2, None),
+            ("9.994", 3, 2, Some(999)),
+            ("12", 3, 2, None),
+            ("abc", 10, 2, None),
+        ];
+        for (s, precision, scale, expected) in cases {
+            let strings: ArrayRef = Arc::new(StringArray::from(vec![s]));
+            let casted = cast(&strings, &DataType::Decimal128(precision, scale)).unwrap();
+            assert_eq!(
+                as_primitive_array::<Decimal128Type>(&casted)
+                    .iter()
+                    .next()
+                    .unwrap(),
+                expected,
+                "cast {s} to decimal({precision}, {scale})",
+            );
+        }
+    }
+
+    #[test]
+    fn test_string_to_timestamp_with_epoch() {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("1577836800"),
+            Some(" -86400 "),
+            Some("2020-01-01T00:00:00"),
+            Some("99999999999999999999"),
+            Some("abc"),
+            None,
+        ]));
+        let cast_type = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+
+        // disabled by default, numeric strings are parsed as years of 4 to 7 digits
+        let casted = cast_with_options(&strings, &cast_type, &SparkCastOptions::default()).unwrap();
+        assert_eq!(casted.data_type(), &cast_type);
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted),
+            &TimestampMicrosecondArray::from(vec![
+                None,
+                Some(-2788687872000000000), // -86400-01-01 00:00:00
+                Some(1577836800000000),
+                None,
+                None,
+                None,
+            ])
+            .with_timezone("UTC"),
+        );
+
+        // numeric strings are epoch seconds
+        let options = SparkCastOptions {
+            string_to_timestamp_epoch_unit: Some(TimeUnit::Second),
+            ..Default::default()
+        };
+        let casted = cast_with_options(&strings, &cast_type, &options).unwrap();
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted),
+            &TimestampMicrosecondArray::from(vec![
+                Some(1577836800000000),
+                Some(-86400000000),
+                Some(1577836800000000),
+                None,
+                None,
+                None,
+            ])
+            .with_timezone("UTC"),
+        );
+
+        // numeric strings are epoch milliseconds
+        let options = SparkCastOptions {
+            string_to_timestamp_epoch_unit: Some(TimeUnit::Millisecond),
+            ..Default::default()
+        };
+        let casted = cast_with_options(&strings, &cast_type, &options).unwrap();
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted).value(0),
+            1577836800000,
+        );
+    }
+
+    #[test]
+    fn test_timestamp_to_string_in_session_timezone() {
+        // 2023-01-01 20:00:00.1 UTC = 2023-01-02 04:00:00.1 +08:00
+        let timestamps: ArrayRef = Arc::new(TimestampMicrosecondArray::from(vec![
+            Some(1672603200100000),
+            Some(1672603200123456),
+            Some(-1),
+            None,
+        ]));
+
+        // UTC is used without timezone
+        let casted = cast(&timestamps, &DataType::Utf8).unwrap();
+        assert_eq!(
+            as_string_array(&casted),
+            &StringArray::from(vec![
+                Some("2023-01-01 20:00:00.1"),
+                Some("2023-01-01 20:00:00.123456"),
+                Some("1969-12-31 23:59:59.999999"),
+                None,
+            ]),
+        );
+
+        let options = SparkCastOptions {
+            session_timezone: Some("Asia/Shanghai".into()),
+            ..Default::default()
+        };
+        let casted = cast_with_options(&timestamps, &DataType::Utf8, &options).unwrap();
+        assert_eq!(
+            as_string_array(&casted),
+            &StringArray::from(vec![
+                Some("2023-01-02 04:00:00.1"),
+                Some("2023-01-02 04:00:00.123456"),
+                Some("1970-01-01 07:59:59.999999"),
+                None,
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_float_to_string() {
+        let f64_array: ArrayRef = Arc::new(Float64Array::from(vec![
+            Some(1.0),
+            Some(-0.0),
+            Some(123.456),
+            Some(1234567.0),
+            Some(1e7),
+            Some(1e10),
+            Some(-1.2345e-5),
+            Some(0.001),
+            Some(0.1 + 0.2),
+            Some(f64::NAN),
+            Some(f64::NEG_INFINITY),
+            Some(f64::MAX),
+            None,
+        ]));
+        let casted = cast(&f64_array, &DataType::Utf8).unwrap();
+        assert_eq!(
+            as_string_array(&casted),
+            &StringArray::from(vec![
+                Some("1.0"),
+                Some("-0.0"),
+                Some("123.456"),
+                Some("1234567.0"),
+                Some("1.0E7"),
+                Some("1.0E10"),
+                Some("-1.2345E-5"),
+                Some("0.001"),
+                Some("0.30000000000000004"),
+                Some("NaN"),
+                Some("-Infinity"),
+                Some("1.7976931348623157E308"),
+                None,
+            ]),
+        );
+
+        // floats are formatted with their own shortest digits
+        let f32_array: ArrayRef = Arc::new(Float32Array::from(vec![
+            Some(0.1),
+            Some(3.4028235e38),
+            Some(f32::INFINITY),
+        ]));
+        let casted = cast(&f32_array, &DataType::Utf8).unwrap();
+        assert_eq!(
+            as_string_array(&casted),
+            &StringArray::from(vec![Some("0.1"), Some("3.4028235E38"), Some("Infinity")]),
+        );
+    }
+
+    #[test]
+    fn test_decimal_to_decimal() {
+        let decimals: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![
+                Some(12345),
+                Some(-12345),
+                Some(12344),
+                Some(-12355),
+                Some(99995),
+                Some(0),
+                None,
+            ])
+            .with_precision_and_scale(5, 3)
+            .unwrap(),
+        );
+
+        // rounds half up, 99.995 overflows decimal(4, 2)
+        let casted = cast(&decimals, &DataType::Decimal128(4, 2)).unwrap();
+        assert_eq!(casted.data_type(), &DataType::Decimal128(4, 2));
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&casted),
+            &Decimal128Array::from(vec![
+                Some(1235),
+                Some(-1235),
+                Some(1234),
+                Some(-1236),
+                None,
+                Some(0),
+                None,
+            ])
+            .with_precision_and_scale(4, 2)
+            .unwrap(),
+        );
+
+        // scaling up overflows decimal(6, 4) with integral digits more than 2
+        let casted = cast(&decimals, &DataType::Decimal128(6, 4)).unwrap();
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&casted),
+            &Decimal128Array::from(vec![
+                Some(123450),
+                Some(-123450),
+                Some(123440),
+                Some(-123550),
+                None,
+                Some(0),
+                None,
+            ])
+            .with_precision_and_scale(6, 4)
+            .unwrap(),
+        );
+
+        // overflows are errors in ansi mode
+        let options = SparkCastOptions {
+            ansi: true,
+            ..Default::default()
+        };
+        let err = cast_with_options(&decimals, &DataType::Decimal128(4, 2), &options)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(
+            "[NUMERIC_VALUE_OUT_OF_RANGE] 99.995 cannot be represented as Decimal(4, 2)."
+        ));
+    }
+
+    #[test]
+    fn test_string_to_date() {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("2020"),
+            Some("2020-2"),
+            Some(" 2020-02-29 "),
+            Some("2020-01-01T12:34:56"),
+            Some("2020-01-01 garbage"),
+            Some("+12020-01-01"),
+            Some("-0001-12-31"),
+            Some("2020-02-30"),
+            Some("2020-1-1-1"),
+            Some("20-01-01"),
+            Some("2020/01/01"),
+            Some(""),
+            None,
+        ]));
+        let casted = cast(&strings, &DataType::Date32).unwrap();
+        assert_eq!(
+            as_primitive_array::<Date32Type>(&casted),
+            &Date32Array::from(vec![
+                Some(18262),
+                Some(18293),
+                Some(18321),
+                Some(18262),
+                Some(18262),
+                Some(3670687),
+                Some(-719529),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_string_to_timestamp_in_session_timezone() {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("2020-01-01 12:34:56"),
+            Some("2020-01-01T12:34:56Z"),
+            None,
+        ]));
+        let cast_type = DataType::Timestamp(TimeUnit::Microsecond, None);
+        let options = SparkCastOptions {
+            session_timezone: Some("Asia/Shanghai".into()),
+            ..Default::default()
+        };
+        let casted = cast_with_options(&strings, &cast_type, &options).unwrap();
+        assert_eq!(casted.data_type(), &cast_type);
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted),
+            &TimestampMicrosecondArray::from(vec![
+                Some(1577853296000000),
+                Some(1577882096000000),
+                None,
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_string_to_timestamp() {
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("2020-01-01"),
+            Some("2020-01-01 12"),
+            Some("2020-01-01T12:34"),
+            Some("2020-01-01 12:34:56.123456789"),
+            Some("2020-01-01T12:34:56Z"),
+            Some("2020-01-01T12:34:56+8"),
+            Some("2020-01-01T12:34:56-08:30"),
+            Some("2020-01-01T12:34:56 UTC+0800"),
+            Some("2020-01-01T12:34:56 Asia/Shanghai"),
+            Some("2020-01-01T25:00:00"),
+            Some("2020-01-01T12:34:56 Mars/Olympus"),
+            Some("12:34:56"),
+            None,
+        ]));
+
+        // timestamps without zone ids are in the session timezone
+        let cast_type = DataType::Timestamp(TimeUnit::Microsecond, Some("+08:00".into()));
+        let casted = cast(&strings, &cast_type).unwrap();
+        assert_eq!(casted.data_type(), &cast_type);
+        assert_eq!(
+            as_primitive_array::<TimestampMicrosecondType>(&casted),
+            &TimestampMicrosecondArray::from(vec![
+                Some(1577808000000000),
+                Some(1577851200000000),
+                Some(1577853240000000),
+                Some(1577853296123456),
+                Some(1577882096000000),
+                Some(1577853296000000),
+                Some(1577912696000000),
+                Some(1577853296000000),
+                Some(1577853296000000),
+                None,
+                None,
+                None,
+                None,
+            ])
+            .with_timezone("+08:00"),
+        );
+    }
+
+    #[test]
+    fn test_list_to_string() {
+        let list: ArrayRef = Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), None, Some(3)]),
+            Some(vec![]),
+            None,
+            Some(vec![None]),
+        ]));
+        let casted = cast(&list, &DataType::Utf8).unwrap();
+        assert_eq!(
+            as_string_array(&casted),
+            &StringArray::from(vec![Some("[1, null, 3]"), Some("[]"), None, Some("[null]")]),
+        );
+
+        // nested items use spark compatible casts
+        let list: ArrayRef = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Boolean, true)),
+            OffsetBuffer::new(vec![0, 2, 3].into()),
+            Arc::new(BooleanArray::from(vec![Some(true), Some(false), None])),
+            None,
+        ));
+        let casted = cast(&list, &DataType::Utf8).unwrap();
+        assert_eq!(
+            as_string_array(&casted),
+            &StringArray::from(vec!["[true, false]", "[null]"]),
+        );
+    }
+
+    #[test]
+    fn test_timestamp_to_date_in_session_timezone() {
+        // 2023-01-01 20:00:00 UTC = 2023-01-02 04:00:00 +08:00
+        let timestamps =
+            TimestampMicrosecondArray::from(vec![Some(1672603200000000), Some(-1), None]);
+
+        let utc: ArrayRef = Arc::new(timestamps.clone().with_timezone("UTC"));
+        let casted = cast(&utc, &DataType::Date32).unwrap();
+        assert_eq!(
+            as_primitive_array::<Date32Type>(&casted),
+            &Date32Array::from(vec![Some(19358), Some(-1), None]),
+        );
+
+        let shanghai: ArrayRef = Arc::new(timestamps.clone().with_timezone("+08:00"));
+        let casted = cast(&shanghai, &DataType::Date32).unwrap();
+        assert_eq!(
+            as_primitive_array::<Date32Type>(&casted),
+            &Date32Array::from(vec![Some(19359), Some(0), None]),
+        );
+
+        // UTC is used without timezone
+        let no_tz: ArrayRef = Arc::new(timestamps);
+        let casted = cast(&no_tz, &DataType::Date32).unwrap();
+        assert_eq!(
+            as_primitive_array::<Date32Type>(&casted),
+            &Date32Array::from(vec![Some(19358), Some(-1), None]),
+        );
+    }
+
+    #[test]
+    fn test_map_cast() {
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+        builder.keys().append_value("a");
+        builder.values().append_value("1");
+        builder.keys().append_value("b");
+        builder.values().append_value("x");
+        builder.append(true).unwrap();
+        builder.append(false).unwrap();
+        builder.keys().append_value("c");
+        builder.values().append_value("3");
+        builder.append(true).unwrap();
+        let map: ArrayRef = Arc::new(builder.finish());
+
+        let to_map_type = |key_type: DataType| {
+            DataType::Map(
+                Arc::new(Field::new(
+                    "entries",
+                    DataType::Struct(Fields::from(vec![
+                        Field::new("keys", key_type, false),
+                        Field::new("values", DataType::Int32, true),
+                    ])),
+                    false,
+                )),
+                false,
+            )
+        };
+
+        // unparseable values become null, keys and entry boundaries are unchanged
+        let casted = cast(&map, &to_map_type(DataType::Utf8)).unwrap();
+        let casted = as_map_array(&casted);
+        assert_eq!(casted.value_offsets(), &[0, 2, 2, 3]);
+        assert!(casted.is_null(1));
+        assert_eq!(
+            as_string_array(casted.keys()),
+            &StringArray::from(vec!["a", "b", "c"]),
+        );
+        assert_eq!(
+            as_primitive_array::<Int32Type>(casted.values()),
+            &Int32Array::from(vec![Some(1), None, Some(3)]),
+        );
+
+        // keys becoming null is an error
+        assert!(cast(&map, &to_map_type(DataType::Int32)).is_err());
+    }
+
+    #[test]
+    fn test_ansi_cast_errors() {
+        let options = SparkCastOptions {
+            ansi: true,
+            ..Default::default()
+        };
+        let cast_err = |array: ArrayRef, cast_type: DataType| {
+            cast_with_options(&array, &cast_type, &options)
+                .unwrap_err()
+                .to_string()
+        };
+
+        // valid values and nulls are casted as usual
+        let strings: ArrayRef = Arc::new(StringArray::from(vec![Some("1"), None, Some("-3")]));
+        let casted = cast_with_options(&strings, &DataType::Int32, &options).unwrap();
+        assert_eq!(
+            as_int32_array(&casted).unwrap(),
+            &Int32Array::from(vec![Some(1), None, Some(-3)]),
+        );
+
+        let err = cast_err(
+            Arc::new(StringArray::from(vec![Some("1"), None, Some("12a")])),
+            DataType::Int32,
+        );
+        assert!(err.contains(
+            "[CAST_INVALID_INPUT] The value '12a' of the type \"STRING\" cannot be cast to \"INT\" \
+                because it is malformed."
+        ));
+
+        let err = cast_err(
+            Arc::new(StringArray::from(vec!["1.5", "123.45"])),
+            DataType::Decimal128(3, 1),
+        );
+        assert!(err.contains(
+            "[NUMERIC_VALUE_OUT_OF_RANGE] 123.45 cannot be represented as Decimal(3, 1)."
+        ));
+
+        let err = cast_err(Arc::new(Int16Array::from(vec![127, 128])), DataType::Int8);
+        assert!(err.contains(
+            "[CAST_OVERFLOW] The value 128S of the type \"SMALLINT\" cannot be cast to \
+                \"TINYINT\" due to an overflow."
+        ));
+
+        let err = cast_err(
+            Arc::new(Float64Array::from(vec![2147483647.9, 2147483648.0])),
+            DataType::Int32,
+        );
+        assert!(err.contains("[CAST_OVERFLOW]"));
+        let err = cast_err(
+            Arc::new(Float32Array::from(vec![f32::NAN])),
+            DataType::Int64,
+        );
+        assert!(err.contains("[CAST_OVERFLOW]"));
+
+        // non-ANSI casts return nulls
+        let casted = cast(&Int16Array::from(vec![127, 128]), &DataType::Int8).unwrap();
+        assert_eq!(casted.null_count(), 1);
+    }
 }
@@ -16,9 +16,10 @@ use blaze_jni_bridge::{
     jni_call, jni_call_static, jni_new_direct_byte_buffer, jni_new_global_ref, jni_new_object,
     jni_new_string,
 };
-use datafusion::error::Result;
+use datafusion::error::{DataFusionError, Result};
 use datafusion::physical_plan::metrics::Time;
 use jni::objects::{GlobalRef, JObject};
+use std::io::{BufRead, Read, Seek, SeekFrom};
 
 pub struct Fs {
     fs: GlobalRef,
@@ -33,6 +34,11 @@ impl Fs {
         }
     }
 
+    /// Opens the file with known length as a `Read + Seek` reader
+    pub fn open_reader(&self, path: &str, len: u64) -> Result<FsDataInputReader> {
+        Ok(FsDataInputReader::new(self.open(path)?, len))
+    }
+
     pub fn open(&self, path: &str) -> Result<FsDataInputStream> {
         let _timer = self.io_time.timer();
         let path_str = jni_new_string!(path)?;
@@ -81,6 +87,131 @@ impl FsDataInputStream {
     }
 }
 
+/// Source of positional reads, implemented by `FsDataInputStream`
+pub trait ReadAt {
+    fn read_fully(&self, pos: u64, buf: &mut [u8]) -> Result<()>;
+}
+
+impl ReadAt for FsDataInputStream {
+    fn read_fully(&self, pos: u64, buf: &mut [u8]) -> Result<()> {
+        FsDataInputStream::read_fully(self, pos, buf)
+    }
+}
+
+const DEFAULT_READER_BUFFER_SIZE: usize = 1048576;
+
+/// Buffered reader of a hadoop file with known length, implementing
+/// `BufRead + Seek` for readers which do not support positional reads.
+/// every positional read of the input is a jni call, so small reads are served
+/// from an internal buffer filled by one `read_fully()` at a time. reads never
+/// go beyond the file length.
+pub struct FsDataInputReader<R: ReadAt = FsDataInputStream> {
+    input: R,
+    pos: u64,
+    len: u64,
+    buf: Box<[u8]>,
+    buf_start: u64, // file position of buf[0]
+    buf_len: usize,
+}
+
+impl<R: ReadAt> FsDataInputReader<R> {
+    pub fn new(input: R, len: u64) -> Self {
+        Self::with_capacity(input, len, DEFAULT_READER_BUFFER_SIZE)
+    }
+
+    pub fn with_capacity(input: R, len: u64, capacity: usize) -> Self {
+        Self {
+            input,
+            pos: 0,
+            len,
+            buf: vec![0u8; capacity.max(1)].into_boxed_slice(),
+            buf_start: 0,
+            buf_len: 0,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn buffered(&self) -> Option<&[u8]> {
+        let offset = self.pos.checked_sub(self.buf_start)? as usize;
+        (offset < self.buf_len).then(|| &self.buf[offset..self.buf_len])
+    }
+}
+
+fn to_io_error(err: DataFusionError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, err)
+}
+
+impl<R: ReadAt> Read for FsDataInputReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // large reads bypass the internal buffer
+        if self.buffered().is_none() && buf.len() >= self.buf.len() {
+            let num_bytes = buf.len().min(self.len.saturating_sub(self.pos) as usize);
+            if num_bytes > 0 {
+                self.input
+                    .read_fully(self.pos, &mut buf[..num_bytes])
+                    .map_err(to_io_error)?;
+                self.pos += num_bytes as u64;
+            }
+            return Ok(num_bytes);
+        }
+        let available = self.fill_buf()?;
+        let num_bytes = buf.len().min(available.len());
+        buf[..num_bytes].copy_from_slice(&available[..num_bytes]);
+        self.consume(num_bytes);
+        Ok(num_bytes)
+    }
+}
+
+impl<R: ReadAt> BufRead for FsDataInputReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.buffered().is_none() {
+            let num_bytes = self
+                .buf
+                .len()
+                .min(self.len.saturating_sub(self.pos) as usize);
+            if num_bytes == 0 {
+                return Ok(&[]);
+            }
+            self.buf_start = self.pos;
+            self.buf_len = 0;
+            self.input
+                .read_fully(self.pos, &mut self.buf[..num_bytes])
+                .map_err(to_io_error)?;
+            self.buf_len = num_bytes;
+        }
+        Ok(self.buffered().unwrap_or_default())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl<R: ReadAt> Seek for FsDataInputReader<R> {
+    // the internal buffer is kept, so seeking inside it costs no jni calls
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        self.pos = new_pos.ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seeking to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
 impl Drop for FsDataInputStream {
     fn drop(&mut self) {
         let _timer = self.io_time.timer();
@@ -130,3 +261,65 @@ impl FsProvider {
         Ok(Fs::new(jni_new_global_ref!(fs.as_obj())?, &self.io_time))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::hadoop_fs::{FsDataInputReader, ReadAt};
+    use datafusion::error::Result;
+    use std::cell::Cell;
+    use std::io::{BufRead, Read, Seek, SeekFrom};
+
+    struct CountingInput {
+        data: Vec<u8>,
+        num_reads: Cell<usize>,
+    }
+
+    impl ReadAt for CountingInput {
+        fn read_fully(&self, pos: u64, buf: &mut [u8]) -> Result<()> {
+            self.num_reads.set(self.num_reads.get() + 1);
+            buf.copy_from_slice(&self.data[pos as usize..][..buf.len()]);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_buffered_reads() -> std::io::Result<()> {
+        let data = (0..100u8).collect::<Vec<_>>();
+        let input = CountingInput {
+            data: data.clone(),
+            num_reads: Cell::new(0),
+        };
+        let mut reader = FsDataInputReader::with_capacity(input, data.len() as u64, 16);
+        let num_reads = |reader: &FsDataInputReader<CountingInput>| reader.input.num_reads.get();
+
+        // small reads are served from the buffer
+        let mut buf = [0u8; 4];
+        for i in 0..4 {
+            reader.read_exact(&mut buf)?;
+            assert_eq!(buf, data[i * 4..][..4]);
+        }
+        assert_eq!(num_reads(&reader), 1);
+
+        // seeking inside the buffer does not read the input
+        reader.seek(SeekFrom::Start(2))?;
+        assert_eq!(reader.fill_buf()?, &data[2..16]);
+        assert_eq!(num_reads(&reader), 1);
+
+        // large reads bypass the buffer
+        reader.seek(SeekFrom::Start(20))?;
+        let mut large_buf = [0u8; 32];
+        reader.read_exact(&mut large_buf)?;
+        assert_eq!(large_buf, data[20..52]);
+        assert_eq!(num_reads(&reader), 2);
+
+        // reads stop at the end of the file
+        reader.seek(SeekFrom::End(-10))?;
+        let mut remaining = vec![];
+        reader.read_to_end(&mut remaining)?;
+        assert_eq!(remaining, data[90..]);
+        assert_eq!(num_reads(&reader), 3);
+        assert_eq!(reader.read(&mut buf)?, 0);
+        assert_eq!(num_reads(&reader), 3);
+        Ok(())
+    }
+}
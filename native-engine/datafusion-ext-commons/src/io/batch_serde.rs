@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::io::ipc_compression::{new_decoder, IpcCompressionCodec};
 use crate::io::{read_bytes_slice, read_len, write_len};
 use arrow::array::*;
 use arrow::buffer::{Buffer, MutableBuffer};
@@ -27,7 +28,7 @@ use std::sync::Arc;
 pub fn write_batch<W: Write>(
     batch: &RecordBatch,
     output: &mut W,
-    compress: bool,
+    compression_codec: Option<IpcCompressionCodec>,
     uncompressed_size: Option<&mut usize>,
 ) -> Result<()> {
     struct CountWriter<W: Write> {
@@ -47,26 +48,17 @@ pub fn write_batch<W: Write>(
     }
 
     let num_bytes_written_uncompressed = Arc::new(AtomicUsize::new(0));
-    let mut output: Box<dyn Write> = if compress {
-        let w = zstd::Encoder::new(output, 1)?.auto_finish();
-        if uncompressed_size.is_some() {
-            Box::new(CountWriter {
-                num_bytes_written: num_bytes_written_uncompressed.clone(),
-                inner: w,
-            })
-        } else {
-            Box::new(w)
-        }
+    let w: Box<dyn Write> = match compression_codec {
+        Some(codec) => codec.new_encoder(output)?,
+        None => Box::new(BufWriter::new(output)),
+    };
+    let mut output: Box<dyn Write> = if uncompressed_size.is_some() {
+        Box::new(CountWriter {
+            num_bytes_written: num_bytes_written_uncompressed.clone(),
+            inner: w,
+        })
     } else {
-        let w = BufWriter::new(output);
-        if uncompressed_size.is_some() {
-            Box::new(CountWriter {
-                num_bytes_written: num_bytes_written_uncompressed.clone(),
-                inner: w,
-            })
-        } else {
-            Box::new(w)
-        }
+        w
     };
 
     let schema = batch.schema();
@@ -109,7 +101,7 @@ pub fn write_batch<W: Write>(
 
 pub fn read_batch<R: Read>(input: &mut R, compress: bool) -> Result<RecordBatch> {
     let mut input: Box<dyn Read> = if compress {
-        Box::new(BufReader::new(zstd::Decoder::new(input)?))
+        Box::new(BufReader::new(new_decoder(input)?))
     } else {
         Box::new(BufReader::new(input))
     };
@@ -669,12 +661,13 @@ fn read_bytes_array<R: Read>(
 #[cfg(test)]
 mod test {
     use crate::io::batch_serde::{read_batch, write_batch};
+    use crate::io::ipc_compression::IpcCompressionCodec;
     use crate::io::name_batch;
     use arrow::array::*;
     use arrow::datatypes::*;
     use arrow::record_batch::RecordBatch;
     use datafusion::assert_batches_eq;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read};
     use std::sync::Arc;
 
     #[test]
@@ -706,7 +699,7 @@ mod test {
 
         // test read after write
         let mut buf = vec![];
-        write_batch(&batch, &mut buf, true, None).unwrap();
+        write_batch(&batch, &mut buf, Some(IpcCompressionCodec::default()), None).unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_eq!(name_batch(decoded_batch, &batch.schema()).unwrap(), batch);
@@ -714,7 +707,13 @@ mod test {
         // test read after write sliced
         let sliced = batch.slice(1, 2);
         let mut buf = vec![];
-        write_batch(&sliced, &mut buf, true, None).unwrap();
+        write_batch(
+            &sliced,
+            &mut buf,
+            Some(IpcCompressionCodec::default()),
+            None,
+        )
+        .unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_eq!(name_batch(decoded_batch, &sliced.schema()).unwrap(), sliced);
@@ -752,7 +751,7 @@ mod test {
 
         // test read after write
         let mut buf = vec![];
-        write_batch(&batch, &mut buf, true, None).unwrap();
+        write_batch(&batch, &mut buf, Some(IpcCompressionCodec::default()), None).unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_batches_eq!(
@@ -772,7 +771,13 @@ mod test {
         // test read after write sliced
         let sliced = batch.slice(1, 2);
         let mut buf = vec![];
-        write_batch(&sliced, &mut buf, true, None).unwrap();
+        write_batch(
+            &sliced,
+            &mut buf,
+            Some(IpcCompressionCodec::default()),
+            None,
+        )
+        .unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_batches_eq!(
@@ -816,7 +821,7 @@ mod test {
 
         // test read after write
         let mut buf = vec![];
-        write_batch(&batch, &mut buf, true, None).unwrap();
+        write_batch(&batch, &mut buf, Some(IpcCompressionCodec::default()), None).unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_eq!(name_batch(decoded_batch, &batch.schema()).unwrap(), batch);
@@ -824,7 +829,13 @@ mod test {
         // test read after write sliced
         let sliced = batch.slice(1, 2);
         let mut buf = vec![];
-        write_batch(&sliced, &mut buf, true, None).unwrap();
+        write_batch(
+            &sliced,
+            &mut buf,
+            Some(IpcCompressionCodec::default()),
+            None,
+        )
+        .unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_eq!(name_batch(decoded_batch, &sliced.schema()).unwrap(), sliced);
@@ -848,7 +859,7 @@ mod test {
 
         // test read after write
         let mut buf = vec![];
-        write_batch(&batch, &mut buf, true, None).unwrap();
+        write_batch(&batch, &mut buf, Some(IpcCompressionCodec::default()), None).unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_eq!(name_batch(decoded_batch, &batch.schema()).unwrap(), batch);
@@ -856,9 +867,65 @@ mod test {
         // test read after write sliced
         let sliced = batch.slice(1, 2);
         let mut buf = vec![];
-        write_batch(&sliced, &mut buf, true, None).unwrap();
+        write_batch(
+            &sliced,
+            &mut buf,
+            Some(IpcCompressionCodec::default()),
+            None,
+        )
+        .unwrap();
         let mut cursor = Cursor::new(buf);
         let decoded_batch = read_batch(&mut cursor, true).unwrap();
         assert_eq!(name_batch(decoded_batch, &sliced.schema()).unwrap(), sliced);
     }
+
+    #[test]
+    fn test_write_and_read_batch_with_compression_levels() {
+        let array: ArrayRef = Arc::new(StringArray::from_iter_values(
+            (0..10000).map(|i| format!("value-{}", i % 100)),
+        ));
+        let batch = RecordBatch::try_from_iter_with_nullable(vec![("str", array, true)]).unwrap();
+
+        let mut compressed_sizes = vec![];
+        for compression_level in [1, 9, 19] {
+            let mut buf = vec![];
+            let codec = IpcCompressionCodec::Zstd(compression_level);
+            write_batch(&batch, &mut buf, Some(codec), None).unwrap();
+            compressed_sizes.push(buf.len());
+            let mut cursor = Cursor::new(buf);
+            let decoded_batch = read_batch(&mut cursor, true).unwrap();
+            assert_eq!(name_batch(decoded_batch, &batch.schema()).unwrap(), batch);
+        }
+        // higher levels compress repetitive data better
+        assert!(compressed_sizes[2] <= compressed_sizes[0]);
+    }
+
+    #[test]
+    fn test_write_and_read_batch_with_codecs() {
+        let array: ArrayRef = Arc::new(StringArray::from_iter_values(
+            (0..1000).map(|i| format!("value-{}", i % 10)),
+        ));
+        let batch = RecordBatch::try_from_iter_with_nullable(vec![("str", array, true)]).unwrap();
+
+        // batches of different codecs in the same stream
+        let codecs = [
+            IpcCompressionCodec::Zstd(3),
+            IpcCompressionCodec::Lz4,
+            IpcCompressionCodec::Snappy,
+            IpcCompressionCodec::Uncompressed,
+        ];
+        let mut buf = vec![];
+        let mut batch_lens = vec![];
+        for codec in codecs {
+            let start = buf.len();
+            write_batch(&batch, &mut buf, Some(codec), None).unwrap();
+            batch_lens.push(buf.len() - start);
+        }
+        let mut cursor = Cursor::new(buf);
+        for batch_len in batch_lens {
+            let mut input = (&mut cursor).take(batch_len as u64);
+            let decoded_batch = read_batch(&mut input, true).unwrap();
+            assert_eq!(name_batch(decoded_batch, &batch.schema()).unwrap(), batch);
+        }
+    }
 }
@@ -0,0 +1,128 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datafusion::common::{DataFusionError, Result};
+use std::io::{BufWriter, Read, Write};
+
+/// Default zstd level for compressing batches
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 1;
+
+/// Codec of compressed batches. each compressed batch starts with a one-byte
+/// codec tag, so readers decode batches of any codec without configuration,
+/// and streams mixing batches of different codecs are readable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcCompressionCodec {
+    Uncompressed,
+    Zstd(i32),
+    Lz4,
+    Snappy,
+}
+
+impl Default for IpcCompressionCodec {
+    fn default() -> Self {
+        IpcCompressionCodec::Zstd(DEFAULT_COMPRESSION_LEVEL)
+    }
+}
+
+const TAG_UNCOMPRESSED: u8 = 0;
+const TAG_ZSTD: u8 = 1;
+const TAG_LZ4: u8 = 2;
+const TAG_SNAPPY: u8 = 3;
+
+impl IpcCompressionCodec {
+    /// Writes the codec tag and creates an encoder writing to the output. the
+    /// encoder finishes the compressed frame when dropped.
+    pub(crate) fn new_encoder<'a, W: Write + 'a>(
+        &self,
+        mut output: W,
+    ) -> Result<Box<dyn Write + 'a>> {
+        Ok(match *self {
+            IpcCompressionCodec::Uncompressed => {
+                output.write_all(&[TAG_UNCOMPRESSED])?;
+                Box::new(BufWriter::new(output))
+            }
+            IpcCompressionCodec::Zstd(level) => {
+                output.write_all(&[TAG_ZSTD])?;
+                Box::new(zstd::Encoder::new(output, level)?.auto_finish())
+            }
+            IpcCompressionCodec::Lz4 => {
+                output.write_all(&[TAG_LZ4])?;
+                Box::new(lz4_flex::frame::FrameEncoder::new(output).auto_finish())
+            }
+            IpcCompressionCodec::Snappy => {
+                output.write_all(&[TAG_SNAPPY])?;
+                Box::new(snap::write::FrameEncoder::new(output))
+            }
+        })
+    }
+
+    /// Same as `new_encoder()`, for outputs which need to be sent across threads
+    pub(crate) fn new_send_encoder<'a, W: Write + Send + 'a>(
+        &self,
+        mut output: W,
+    ) -> Result<Box<dyn Write + Send + 'a>> {
+        Ok(match *self {
+            IpcCompressionCodec::Uncompressed => {
+                output.write_all(&[TAG_UNCOMPRESSED])?;
+                Box::new(BufWriter::new(output))
+            }
+            IpcCompressionCodec::Zstd(level) => {
+                output.write_all(&[TAG_ZSTD])?;
+                Box::new(zstd::Encoder::new(output, level)?.auto_finish())
+            }
+            IpcCompressionCodec::Lz4 => {
+                output.write_all(&[TAG_LZ4])?;
+                Box::new(lz4_flex::frame::FrameEncoder::new(output).auto_finish())
+            }
+            IpcCompressionCodec::Snappy => {
+                output.write_all(&[TAG_SNAPPY])?;
+                Box::new(snap::write::FrameEncoder::new(output))
+            }
+        })
+    }
+}
+
+/// Reads the codec tag and creates a decoder of the compressed input
+pub(crate) fn new_decoder<'a, R: Read + 'a>(mut input: R) -> Result<Box<dyn Read + 'a>> {
+    Ok(match read_codec_tag(&mut input)? {
+        TAG_UNCOMPRESSED => Box::new(input),
+        TAG_ZSTD => Box::new(zstd::Decoder::new(input)?),
+        TAG_LZ4 => Box::new(lz4_flex::frame::FrameDecoder::new(input)),
+        _ => Box::new(snap::read::FrameDecoder::new(input)),
+    })
+}
+
+/// Same as `new_decoder()`, for inputs which need to be sent across threads
+pub(crate) fn new_send_decoder<'a, R: Read + Send + 'a>(
+    mut input: R,
+) -> Result<Box<dyn Read + Send + 'a>> {
+    Ok(match read_codec_tag(&mut input)? {
+        TAG_UNCOMPRESSED => Box::new(input),
+        TAG_ZSTD => Box::new(zstd::Decoder::new(input)?),
+        TAG_LZ4 => Box::new(lz4_flex::frame::FrameDecoder::new(input)),
+        _ => Box::new(snap::read::FrameDecoder::new(input)),
+    })
+}
+
+fn read_codec_tag<R: Read>(input: &mut R) -> Result<u8> {
+    let mut tag = [0u8; 1];
+    input.read_exact(&mut tag)?;
+    match tag[0] {
+        TAG_UNCOMPRESSED | TAG_ZSTD | TAG_LZ4 | TAG_SNAPPY => Ok(tag[0]),
+        other => Err(DataFusionError::Execution(format!(
+            "unknown ipc compression codec tag: {}",
+            other
+        ))),
+    }
+}
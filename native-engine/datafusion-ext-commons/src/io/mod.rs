@@ -23,12 +23,33 @@ use datafusion::common::cast::as_struct_array;
 use datafusion::common::Result;
 
 mod batch_serde;
+mod ipc_compression;
+
+pub(crate) use ipc_compression::new_send_decoder;
+pub use ipc_compression::{IpcCompressionCodec, DEFAULT_COMPRESSION_LEVEL};
 
 pub fn write_one_batch<W: Write + Seek>(
     batch: &RecordBatch,
     output: &mut W,
     compress: bool,
     uncompressed_size: Option<&mut usize>,
+) -> Result<usize> {
+    write_one_batch_with_codec(
+        batch,
+        output,
+        compress.then(IpcCompressionCodec::default),
+        uncompressed_size,
+    )
+}
+
+/// Writes the batch compressed with the specified codec, or in the raw format
+/// if the codec is `None`. the written batch is readable by `read_one_batch()`
+/// with `compress` set accordingly, whatever the codec is.
+pub fn write_one_batch_with_codec<W: Write + Seek>(
+    batch: &RecordBatch,
+    output: &mut W,
+    compression_codec: Option<IpcCompressionCodec>,
+    uncompressed_size: Option<&mut usize>,
 ) -> Result<usize> {
     if batch.num_rows() == 0 {
         return Ok(0);
@@ -38,7 +59,7 @@ pub fn write_one_batch<W: Write + Seek>(
     output.write_all(&[0u8; 8])?;
 
     // write
-    batch_serde::write_batch(batch, output, compress, uncompressed_size)?;
+    batch_serde::write_batch(batch, output, compression_codec, uncompressed_size)?;
     let end_pos = output.stream_position()?;
     let ipc_length = end_pos - start_pos - 8;
 
@@ -0,0 +1,184 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reusable buffers for transferring bytes between native and jvm. creating a
+//! direct byte buffer for every channel read costs several jni calls, which is
+//! significant for small reads. buffers are created once with the configured
+//! size and kind, and pooled to be shared by ipc reading and writing.
+
+use blaze_jni_bridge::{
+    is_jni_bridge_inited, jni_call, jni_call_static, jni_get_byte_array_region, jni_get_string,
+    jni_new_byte_array, jni_new_direct_byte_buffer, jni_new_global_ref, jni_set_byte_array_region,
+};
+use datafusion::common::{DataFusionError, Result};
+use jni::objects::{GlobalRef, JObject};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::ops::{Deref, DerefMut};
+
+const DEFAULT_BUFFER_SIZE: usize = 65536;
+const MAX_POOLED_BUFFERS: usize = 64;
+
+/// Kind of jni buffers, configured by `spark.blaze.jni.transport`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JniTransport {
+    /// a direct byte buffer over native memory, accessed by both sides
+    /// without copying
+    Direct,
+
+    /// a byte buffer wrapping a jvm byte array, copied from/to native memory
+    /// by jni. this avoids exposing native memory to the jvm
+    Heap,
+}
+
+/// Returns the kind and size of jni buffers, configured by
+/// `spark.blaze.jni.transport` and `spark.blaze.jni.buffer.size`.
+pub fn jni_buffer_conf() -> Result<(JniTransport, usize)> {
+    static CONF: OnceCell<(JniTransport, usize)> = OnceCell::new();
+    if !is_jni_bridge_inited() {
+        return Ok((JniTransport::Direct, DEFAULT_BUFFER_SIZE));
+    }
+    CONF.get_or_try_init(|| {
+        let transport = jni_call_static!(BlazeConf.jniTransport() -> JObject)?;
+        let transport = match jni_get_string!(transport.as_obj().into())?.as_str() {
+            "direct" => JniTransport::Direct,
+            "heap" => JniTransport::Heap,
+            other => {
+                return Err(DataFusionError::Configuration(format!(
+                    "unsupported jni transport: {other}"
+                )))
+            }
+        };
+        let buffer_size = jni_call_static!(BlazeConf.jniBufferSize() -> i32)?;
+        Ok((transport, buffer_size.max(1) as usize))
+    })
+    .copied()
+}
+
+/// A jvm byte buffer with native memory of the same capacity
+pub struct JniBuffer {
+    byte_buffer: GlobalRef,
+    byte_array: Option<GlobalRef>, // backing array of heap buffers
+    data: Box<[u8]>,
+}
+
+impl JniBuffer {
+    pub fn try_new(transport: JniTransport, capacity: usize) -> Result<Self> {
+        let data = vec![0u8; capacity].into_boxed_slice();
+        Ok(match transport {
+            JniTransport::Direct => {
+                // safety: data is never moved and outlives byte_buffer
+                let byte_buffer = jni_new_direct_byte_buffer!(&data)?;
+                Self {
+                    byte_buffer: jni_new_global_ref!(byte_buffer.as_obj())?,
+                    byte_array: None,
+                    data,
+                }
+            }
+            JniTransport::Heap => {
+                let byte_array = jni_new_byte_array!(capacity)?;
+                let byte_buffer =
+                    jni_call_static!(JavaByteBuffer.wrap(byte_array.as_obj()) -> JObject)?;
+                Self {
+                    byte_buffer: jni_new_global_ref!(byte_buffer.as_obj())?,
+                    byte_array: Some(jni_new_global_ref!(byte_array.as_obj())?),
+                    data,
+                }
+            }
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the jvm byte buffer object
+    pub fn as_obj(&self) -> JObject {
+        self.byte_buffer.as_obj()
+    }
+
+    /// Resets the jvm byte buffer to position 0 and limit `len`
+    pub fn reset(&self, len: usize) -> Result<()> {
+        jni_call!(JavaBuffer(self.as_obj()).clear() -> JObject)?;
+        if len < self.capacity() {
+            jni_call!(JavaBuffer(self.as_obj()).limit(len as i32) -> JObject)?;
+        }
+        Ok(())
+    }
+
+    /// Returns bytes of the buffer in native memory, which are synchronized
+    /// with the jvm byte buffer by `read_from_jvm()` and `write_to_jvm()`
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the first `len` bytes written into the jvm byte buffer
+    pub fn read_from_jvm(&mut self, len: usize) -> Result<&[u8]> {
+        if let Some(byte_array) = &self.byte_array {
+            jni_get_byte_array_region!(byte_array.as_obj(), 0, &mut self.data[..len])?;
+        }
+        Ok(&self.data[..len])
+    }
+
+    /// Copies bytes into the buffer, and resets the jvm byte buffer to them.
+    /// bytes must not exceed the capacity.
+    pub fn write_to_jvm(&mut self, bytes: &[u8]) -> Result<()> {
+        match &self.byte_array {
+            Some(byte_array) => jni_set_byte_array_region!(byte_array.as_obj(), 0, bytes)?,
+            None => self.data[..bytes.len()].copy_from_slice(bytes),
+        }
+        self.reset(bytes.len())
+    }
+}
+
+fn pool() -> &'static Mutex<Vec<JniBuffer>> {
+    static POOL: OnceCell<Mutex<Vec<JniBuffer>>> = OnceCell::new();
+    POOL.get_or_init(|| Mutex::new(vec![]))
+}
+
+/// A jni buffer which is returned to the pool when dropped
+pub struct PooledJniBuffer(Option<JniBuffer>);
+
+impl Deref for PooledJniBuffer {
+    type Target = JniBuffer;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledJniBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledJniBuffer {
+    fn drop(&mut self) {
+        let mut pool = pool().lock();
+        if pool.len() < MAX_POOLED_BUFFERS {
+            pool.extend(self.0.take());
+        }
+    }
+}
+
+/// Takes a jni buffer from the pool, or creates a new one if the pool is empty
+pub fn acquire_jni_buffer() -> Result<PooledJniBuffer> {
+    if let Some(buffer) = pool().lock().pop() {
+        return Ok(PooledJniBuffer(Some(buffer)));
+    }
+    let (transport, buffer_size) = jni_buffer_conf()?;
+    let buffer = JniBuffer::try_new(transport, buffer_size)?;
+    Ok(PooledJniBuffer(Some(buffer)))
+}
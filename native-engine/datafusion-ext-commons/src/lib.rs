@@ -21,12 +21,19 @@ use arrow::record_batch::{RecordBatch, RecordBatchOptions};
 use log::trace;
 
 pub mod array_builder;
+pub mod batch_splitter;
 pub mod cast;
 pub mod ffi;
 pub mod hadoop_fs;
 pub mod io;
+pub mod jni_buffer;
 pub mod loser_tree;
+pub mod metrics;
+pub mod nulls;
+pub mod selection;
+pub mod spark_bloom_filter;
 pub mod spark_hash;
+pub mod spill;
 pub mod streams;
 pub mod uda;
 
@@ -0,0 +1,55 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Custom metrics shared by native operators. metrics are exported to the
+//! spark metrics of the same names (see `NativeHelper.getDefaultNativeMetrics`)
+//! after execution, together with baseline metrics, spilled bytes recorded by
+//! `BaselineMetrics::record_spill()` and spill counts of spilling operators.
+
+use datafusion::physical_plan::metrics::{ExecutionPlanMetricsSet, MetricBuilder, Time};
+use std::io::Read;
+
+/// Time spent waiting for reading or writing files and channels
+pub fn io_time(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Time {
+    MetricBuilder::new(metrics).subset_time("io_time", partition)
+}
+
+/// Time spent calling into the jvm, including jvm side computation
+pub fn jni_time(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Time {
+    MetricBuilder::new(metrics).subset_time("jni_time", partition)
+}
+
+/// Time spent decompressing and decoding batches, excluding io time
+pub fn decompress_time(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Time {
+    MetricBuilder::new(metrics).subset_time("decompress_time", partition)
+}
+
+/// A reader recording time spent reading the inner reader
+pub struct TimedReader<R> {
+    inner: R,
+    time: Time,
+}
+
+impl<R: Read> TimedReader<R> {
+    pub fn new(inner: R, time: Time) -> Self {
+        Self { inner, time }
+    }
+}
+
+impl<R: Read> Read for TimedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let _timer = self.time.timer();
+        self.inner.read(buf)
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::array::{make_array, Array, ArrayRef, UnionArray};
+use arrow::buffer::NullBuffer;
+use arrow::datatypes::DataType;
+use datafusion::common::Result;
+
+/// Masks the array with the nulls, so that every slot which is null in `nulls`
+/// becomes null in the output, e.g. propagating nulls of a struct into its
+/// fields.
+///
+/// arrays of null type are already all null. unions cannot have null buffers,
+/// so their children are masked at the slots referenced by the masked rows.
+pub fn mask_nulls(array: &ArrayRef, nulls: Option<&NullBuffer>) -> Result<ArrayRef> {
+    let nulls = match nulls {
+        Some(nulls) if nulls.null_count() > 0 => nulls,
+        _ => return Ok(array.clone()),
+    };
+
+    match array.data_type() {
+        DataType::Null => Ok(array.clone()),
+        DataType::Union(fields, _) => {
+            let union_array = array
+                .as_any()
+                .downcast_ref::<UnionArray>()
+                .expect("expect union array");
+            let data = array.to_data();
+            let mut child_valids = data
+                .child_data()
+                .iter()
+                .map(|child| vec![true; child.len()])
+                .collect::<Vec<_>>();
+            for i in (0..nulls.len()).filter(|&i| nulls.is_null(i)) {
+                let type_id = union_array.type_id(i);
+                let child_idx = fields
+                    .iter()
+                    .position(|(field_type_id, _)| field_type_id == type_id)
+                    .expect("invalid union type id");
+                child_valids[child_idx][union_array.value_offset(i) as usize] = false;
+            }
+
+            let child_data = data
+                .child_data()
+                .iter()
+                .zip(child_valids)
+                .map(|(child, valids)| {
+                    let child_nulls = NullBuffer::from(valids);
+                    Ok(mask_nulls(&make_array(child.clone()), Some(&child_nulls))?.to_data())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(make_array(
+                data.into_builder().child_data(child_data).build()?,
+            ))
+        }
+        _ => {
+            let nulls = NullBuffer::union(Some(nulls), array.nulls());
+            Ok(make_array(
+                array.to_data().into_builder().nulls(nulls).build()?,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::nulls::mask_nulls;
+    use arrow::array::*;
+    use arrow::buffer::{Buffer, NullBuffer};
+    use arrow::datatypes::{DataType, Field, Int32Type};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_mask_nulls() -> Result<(), Box<dyn std::error::Error>> {
+        let nulls = NullBuffer::from(vec![true, false, true]);
+
+        let array: ArrayRef = Arc::new(Int32Array::from(vec![None, Some(2), Some(3)]));
+        let masked = mask_nulls(&array, Some(&nulls))?;
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&masked),
+            &Int32Array::from(vec![None, None, Some(3)])
+        );
+
+        let array = new_null_array(&DataType::Null, 3);
+        let masked = mask_nulls(&array, Some(&nulls))?;
+        assert_eq!(masked.data_type(), &DataType::Null);
+        assert_eq!(masked.len(), 3);
+
+        // row 1 refers to the first slot of the string child
+        let array: ArrayRef = Arc::new(UnionArray::try_new(
+            &[0, 1],
+            Buffer::from_slice_ref([0i8, 1, 1]),
+            Some(Buffer::from_slice_ref([0i32, 0, 1])),
+            vec![
+                (
+                    Field::new("i", DataType::Int32, true),
+                    Arc::new(Int32Array::from(vec![1])) as ArrayRef,
+                ),
+                (
+                    Field::new("s", DataType::Utf8, true),
+                    Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef,
+                ),
+            ],
+        )?);
+        let masked = mask_nulls(&array, Some(&nulls))?;
+        let masked = masked.as_any().downcast_ref::<UnionArray>().unwrap();
+        assert_eq!(masked.child(0).null_count(), 0);
+        assert_eq!(
+            as_string_array(masked.child(1)),
+            &StringArray::from(vec![None, Some("b")])
+        );
+        Ok(())
+    }
+}
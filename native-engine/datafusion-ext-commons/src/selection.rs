@@ -0,0 +1,306 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection vectors of record batches. filtered rows are kept as indices into
+//! the original batches and copied only once when a coalesced output batch is
+//! materialized, instead of being copied by filtering and again by coalescing.
+
+use crate::concat_batches;
+use arrow::array::{Array, ArrayRef, BooleanArray, UInt32Array};
+use arrow::compute::{interleave, prep_null_mask_filter, take};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use datafusion::common::Result;
+
+/// Rows selected from a record batch
+#[derive(Clone, Debug)]
+pub enum Selection {
+    /// all rows are selected
+    All,
+
+    /// ascending indices of the selected rows
+    Indices(UInt32Array),
+}
+
+/// A record batch with selected rows not materialized yet
+#[derive(Clone, Debug)]
+pub struct SelectedBatch {
+    batch: RecordBatch,
+    selection: Selection,
+}
+
+impl SelectedBatch {
+    pub fn new(batch: RecordBatch) -> Self {
+        Self {
+            batch,
+            selection: Selection::All,
+        }
+    }
+
+    /// Selects rows of the batch with a predicate, null values are treated as
+    /// false. a selection of a single contiguous range of rows is turned into
+    /// a zero-copy slice.
+    pub fn from_predicate(batch: RecordBatch, predicate: &BooleanArray) -> Self {
+        let predicate = if predicate.null_count() > 0 {
+            prep_null_mask_filter(predicate)
+        } else {
+            predicate.clone()
+        };
+        let num_selected = predicate.true_count();
+        if num_selected == batch.num_rows() {
+            return Self::new(batch);
+        }
+
+        let mut selected_slices = predicate.values().set_slices();
+        match (selected_slices.next(), selected_slices.next()) {
+            (None, _) => Self::new(batch.slice(0, 0)),
+            (Some((start, end)), None) => Self::new(batch.slice(start, end - start)),
+            _ => {
+                let indices = UInt32Array::from_iter_values(
+                    predicate
+                        .values()
+                        .set_slices()
+                        .flat_map(|(start, end)| start as u32..end as u32),
+                );
+                Self {
+                    batch,
+                    selection: Selection::Indices(indices),
+                }
+            }
+        }
+    }
+
+    pub fn batch(&self) -> &RecordBatch {
+        &self.batch
+    }
+
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    pub fn num_rows(&self) -> usize {
+        match &self.selection {
+            Selection::All => self.batch.num_rows(),
+            Selection::Indices(indices) => indices.len(),
+        }
+    }
+
+    /// Returns a batch containing only the selected rows
+    pub fn materialize(&self) -> Result<RecordBatch> {
+        match &self.selection {
+            Selection::All => Ok(self.batch.clone()),
+            Selection::Indices(indices) => {
+                let columns = self
+                    .batch
+                    .columns()
+                    .iter()
+                    .map(|column| Ok(take(column, indices, None)?))
+                    .collect::<Result<Vec<ArrayRef>>>()?;
+                Ok(RecordBatch::try_new_with_options(
+                    self.batch.schema(),
+                    columns,
+                    &RecordBatchOptions::new().with_row_count(Some(indices.len())),
+                )?)
+            }
+        }
+    }
+}
+
+/// Materializes selected rows of all batches into one batch, copying each row
+/// only once.
+pub fn materialize_selected_batches(
+    schema: &SchemaRef,
+    batches: &[SelectedBatch],
+) -> Result<RecordBatch> {
+    let num_rows = batches.iter().map(|batch| batch.num_rows()).sum();
+    if batches.len() == 1 {
+        return batches[0].materialize();
+    }
+    if batches
+        .iter()
+        .all(|batch| matches!(batch.selection, Selection::All))
+    {
+        let batches = batches
+            .iter()
+            .map(|batch| batch.batch.clone())
+            .collect::<Vec<_>>();
+        return Ok(concat_batches(schema, &batches, num_rows)?);
+    }
+
+    let mut interleave_indices = Vec::with_capacity(num_rows);
+    for (batch_idx, batch) in batches.iter().enumerate() {
+        match &batch.selection {
+            Selection::All => {
+                interleave_indices.extend((0..batch.batch.num_rows()).map(|i| (batch_idx, i)))
+            }
+            Selection::Indices(indices) => {
+                interleave_indices.extend(indices.values().iter().map(|&i| (batch_idx, i as usize)))
+            }
+        }
+    }
+    let columns = (0..schema.fields().len())
+        .map(|i| {
+            let arrays = batches
+                .iter()
+                .map(|batch| batch.batch.column(i).as_ref())
+                .collect::<Vec<_>>();
+            Ok(interleave(&arrays, &interleave_indices)?)
+        })
+        .collect::<Result<Vec<ArrayRef>>>()?;
+    Ok(RecordBatch::try_new_with_options(
+        schema.clone(),
+        columns,
+        &RecordBatchOptions::new().with_row_count(Some(num_rows)),
+    )?)
+}
+
+/// Stages selected batches and materializes them into batches of `batch_size`
+/// rows. staging is also flushed when the size of the staging input batches
+/// (which are held entirely until materialized) exceeds `byte_budget`, so the
+/// estimated size of selected rows never exceeds it either.
+pub struct SelectionCoalescer {
+    schema: SchemaRef,
+    batch_size: usize,
+    byte_budget: usize,
+    staging_batches: Vec<SelectedBatch>,
+    staging_rows: usize,
+    staging_input_mem_size: usize,
+}
+
+impl SelectionCoalescer {
+    pub fn new(schema: SchemaRef, batch_size: usize, byte_budget: usize) -> Self {
+        Self {
+            schema,
+            batch_size,
+            byte_budget,
+            staging_batches: vec![],
+            staging_rows: 0,
+            staging_input_mem_size: 0,
+        }
+    }
+
+    /// Stages a selected batch, returns a materialized batch if staging is full
+    pub fn push(&mut self, batch: SelectedBatch) -> Result<Option<RecordBatch>> {
+        let num_rows = batch.num_rows();
+        if num_rows == 0 {
+            return Ok(None);
+        }
+        let input_mem_size = batch.batch.get_array_memory_size();
+        self.staging_rows += num_rows;
+        self.staging_input_mem_size += input_mem_size;
+        self.staging_batches.push(batch);
+
+        if self.staging_rows >= self.batch_size || self.staging_input_mem_size >= self.byte_budget {
+            return self.flush();
+        }
+        Ok(None)
+    }
+
+    /// Materializes all staging batches
+    pub fn flush(&mut self) -> Result<Option<RecordBatch>> {
+        if self.staging_batches.is_empty() {
+            return Ok(None);
+        }
+        let staging_batches = std::mem::take(&mut self.staging_batches);
+        self.staging_rows = 0;
+        self.staging_input_mem_size = 0;
+        Ok(Some(materialize_selected_batches(
+            &self.schema,
+            &staging_batches,
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::selection::{SelectedBatch, Selection, SelectionCoalescer};
+    use arrow::array::{ArrayRef, BooleanArray, Int32Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use std::sync::Arc;
+
+    fn batch(values: Vec<i32>) -> Result<RecordBatch> {
+        let strings: ArrayRef = Arc::new(StringArray::from_iter_values(
+            values.iter().map(|v| format!("s{v}")),
+        ));
+        let ints: ArrayRef = Arc::new(Int32Array::from(values));
+        Ok(RecordBatch::try_from_iter(vec![
+            ("i", ints),
+            ("s", strings),
+        ])?)
+    }
+
+    #[test]
+    fn test_selected_batch() -> Result<()> {
+        let input = batch(vec![1, 2, 3, 4, 5])?;
+
+        // contiguous rows are sliced
+        let predicate = BooleanArray::from(vec![Some(false), Some(true), Some(true), None, None]);
+        let selected = SelectedBatch::from_predicate(input.clone(), &predicate);
+        assert!(matches!(selected.selection(), Selection::All));
+        assert_eq!(selected.num_rows(), 2);
+        assert_eq!(selected.materialize()?, input.slice(1, 2));
+
+        // other rows are selected by indices
+        let predicate = BooleanArray::from(vec![true, false, true, false, true]);
+        let selected = SelectedBatch::from_predicate(input.clone(), &predicate);
+        assert!(matches!(selected.selection(), Selection::Indices(_)));
+        assert_eq!(selected.materialize()?, batch(vec![1, 3, 5])?);
+
+        let predicate = BooleanArray::from(vec![false; 5]);
+        assert_eq!(
+            SelectedBatch::from_predicate(input, &predicate).num_rows(),
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_selection_coalescer() -> Result<()> {
+        let input = batch(vec![1, 2, 3, 4, 5])?;
+        let mut coalescer = SelectionCoalescer::new(input.schema(), 4, usize::MAX);
+        let mut output = vec![];
+
+        let predicate = BooleanArray::from(vec![true, false, true, false, true]);
+        let selected = SelectedBatch::from_predicate(input.clone(), &predicate);
+        output.extend(coalescer.push(selected)?);
+        assert!(output.is_empty());
+
+        let selected = SelectedBatch::new(input.slice(3, 2));
+        output.extend(coalescer.push(selected)?);
+        assert_eq!(output.len(), 1);
+
+        let predicate = BooleanArray::from(vec![false, true, false, false, false]);
+        let selected = SelectedBatch::from_predicate(input, &predicate);
+        output.extend(coalescer.push(selected)?);
+        output.extend(coalescer.flush()?);
+        assert_batches_eq!(
+            vec![
+                "+---+----+",
+                "| i | s  |",
+                "+---+----+",
+                "| 1 | s1 |",
+                "| 3 | s3 |",
+                "| 5 | s5 |",
+                "| 4 | s4 |",
+                "| 5 | s5 |",
+                "| 2 | s2 |",
+                "+---+----+",
+            ],
+            &output
+        );
+        Ok(())
+    }
+}
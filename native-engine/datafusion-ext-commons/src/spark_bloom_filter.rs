@@ -0,0 +1,199 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{Read, Write};
+
+use datafusion::error::{DataFusionError, Result};
+
+use crate::spark_hash::spark_compatible_murmur3_hash;
+
+/// Port of spark's `BloomFilterImpl`, the bit layout, hashing and serialized
+/// format are identical so filters can be exchanged with the jvm side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparkBloomFilter {
+    num_hash_functions: i32,
+    bits: Vec<u64>,
+}
+
+impl SparkBloomFilter {
+    const VERSION: i32 = 1;
+
+    /// Creates a bloom filter with `num_bits` bits for `expected_num_items` items,
+    /// the number of hash functions is computed the same way as spark.
+    pub fn new(expected_num_items: usize, num_bits: usize) -> Self {
+        let num_hash_functions = ((num_bits as f64 / expected_num_items.max(1) as f64)
+            * std::f64::consts::LN_2)
+            .round()
+            .max(1.0) as i32;
+        let num_words = (num_bits.max(1) + 63) / 64;
+        Self {
+            num_hash_functions,
+            bits: vec![0; num_words],
+        }
+    }
+
+    pub fn num_hash_functions(&self) -> i32 {
+        self.num_hash_functions
+    }
+
+    pub fn bit_size(&self) -> usize {
+        self.bits.len() * 64
+    }
+
+    pub fn mem_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.bits.len() * 8
+    }
+
+    /// Number of set bits, zero if nothing has been put into the filter
+    pub fn cardinality(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn put_long(&mut self, item: i64) {
+        let bit_size = self.bit_size() as i32;
+        for bit_idx in self.bit_indices(item, bit_size) {
+            self.bits[bit_idx / 64] |= 1u64 << (bit_idx % 64);
+        }
+    }
+
+    pub fn might_contain_long(&self, item: i64) -> bool {
+        let bit_size = self.bit_size() as i32;
+        self.bit_indices(item, bit_size)
+            .all(|bit_idx| self.bits[bit_idx / 64] & (1u64 << (bit_idx % 64)) != 0)
+    }
+
+    /// Combines another bloom filter into this one, both filters must be
+    /// created with the same number of bits and hash functions.
+    pub fn merge_in_place(&mut self, other: &Self) -> Result<()> {
+        if self.bits.len() != other.bits.len()
+            || self.num_hash_functions != other.num_hash_functions
+        {
+            return Err(DataFusionError::Execution(format!(
+                "cannot merge incompatible bloom filters: bit_size={}/{}, num_hash_functions={}/{}",
+                self.bit_size(),
+                other.bit_size(),
+                self.num_hash_functions,
+                other.num_hash_functions,
+            )));
+        }
+        for (word, other_word) in self.bits.iter_mut().zip(&other.bits) {
+            *word |= *other_word;
+        }
+        Ok(())
+    }
+
+    fn bit_indices(&self, item: i64, bit_size: i32) -> impl Iterator<Item = usize> {
+        // same as spark: h1 + i * h2 with java int arithmetics
+        let h1 = spark_compatible_murmur3_hash(item.to_le_bytes(), 0) as i32;
+        let h2 = spark_compatible_murmur3_hash(item.to_le_bytes(), h1 as u32) as i32;
+        (1..=self.num_hash_functions).map(move |i| {
+            let mut combined_hash = h1.wrapping_add(i.wrapping_mul(h2));
+            if combined_hash < 0 {
+                combined_hash = !combined_hash;
+            }
+            (combined_hash % bit_size) as usize
+        })
+    }
+
+    /// Reads a bloom filter serialized by spark's `BloomFilter.writeTo()`
+    pub fn read_from(mut r: impl Read) -> Result<Self> {
+        fn read_i32(r: &mut impl Read) -> Result<i32> {
+            let mut buf = [0u8; 4];
+            r.read_exact(&mut buf)?;
+            Ok(i32::from_be_bytes(buf))
+        }
+        let version = read_i32(&mut r)?;
+        if version != Self::VERSION {
+            return Err(DataFusionError::Execution(format!(
+                "unexpected bloom filter version: {version}"
+            )));
+        }
+        let num_hash_functions = read_i32(&mut r)?;
+        let num_words = read_i32(&mut r)?;
+        let mut bits = Vec::with_capacity(num_words as usize);
+        for _ in 0..num_words {
+            let mut buf = [0u8; 8];
+            r.read_exact(&mut buf)?;
+            bits.push(u64::from_be_bytes(buf));
+        }
+        Ok(Self {
+            num_hash_functions,
+            bits,
+        })
+    }
+
+    /// Writes the bloom filter in the format of spark's `BloomFilter.writeTo()`
+    pub fn write_to(&self, mut w: impl Write) -> Result<()> {
+        w.write_all(&Self::VERSION.to_be_bytes())?;
+        w.write_all(&self.num_hash_functions.to_be_bytes())?;
+        w.write_all(&(self.bits.len() as i32).to_be_bytes())?;
+        for &word in &self.bits {
+            w.write_all(&word.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_bloom_filter::SparkBloomFilter;
+    use datafusion::error::Result;
+
+    #[test]
+    fn test_spark_bloom_filter() -> Result<()> {
+        let mut bloom_filter = SparkBloomFilter::new(1000, 8192);
+        assert_eq!(bloom_filter.num_hash_functions(), 6);
+        assert_eq!(bloom_filter.bit_size(), 8192);
+
+        for i in 0..1000 {
+            bloom_filter.put_long(i * 7);
+        }
+
+        // no false negatives
+        assert!((0..1000).all(|i| bloom_filter.might_contain_long(i * 7)));
+
+        // few false positives
+        let num_false_positives = (0..1000)
+            .filter(|i| bloom_filter.might_contain_long(i * 7 + 10000))
+            .count();
+        assert!(num_false_positives < 100);
+
+        // serialization round trip
+        let mut buf = vec![];
+        bloom_filter.write_to(&mut buf)?;
+        assert_eq!(buf.len(), 12 + 8192 / 8);
+        assert_eq!(SparkBloomFilter::read_from(&buf[..])?, bloom_filter);
+
+        // merging
+        let mut merged = SparkBloomFilter::new(1000, 8192);
+        assert_eq!(merged.cardinality(), 0);
+        let mut other = SparkBloomFilter::new(1000, 8192);
+        for i in 0..1000 {
+            if i % 2 == 0 {
+                merged.put_long(i * 7);
+            } else {
+                other.put_long(i * 7);
+            }
+        }
+        merged.merge_in_place(&other)?;
+        assert_eq!(merged, bloom_filter);
+        assert!(merged
+            .merge_in_place(&SparkBloomFilter::new(1000, 4096))
+            .is_err());
+        Ok(())
+    }
+}
@@ -17,14 +17,11 @@
 use std::sync::Arc;
 
 use arrow::array::*;
-use arrow::datatypes::{
-    ArrowDictionaryKeyType, ArrowNativeType, DataType, Int16Type, Int32Type, Int64Type, Int8Type,
-    TimeUnit,
-};
+use arrow::datatypes::*;
 use datafusion::error::{DataFusionError, Result};
 
 #[inline]
-fn spark_compatible_murmur3_hash<T: AsRef<[u8]>>(data: T, seed: u32) -> u32 {
+pub fn spark_compatible_murmur3_hash<T: AsRef<[u8]>>(data: T, seed: u32) -> u32 {
     #[inline]
     fn mix_k1(mut k1: i32) -> i32 {
         k1 *= 0xcc9e2d51u32 as i32;
@@ -96,465 +93,361 @@ fn test_murmur3() {
     assert_eq!(_hashes, _expected)
 }
 
-macro_rules! hash_array {
-    ($array_type:ident, $column: ident, $hashes: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        if array.null_count() == 0 {
-            for (i, hash) in $hashes.iter_mut().enumerate() {
-                *hash = spark_compatible_murmur3_hash(&array.value(i), *hash);
-            }
-        } else {
-            for (i, hash) in $hashes.iter_mut().enumerate() {
-                if !array.is_null(i) {
-                    *hash = spark_compatible_murmur3_hash(&array.value(i), *hash);
-                }
-            }
-        }
-    };
-}
-
-macro_rules! hash_list {
-    ($array_type:ident, $column: ident, $hash: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        if array.null_count() == 0 {
-            for i in 0..array.len() {
-                *$hash = spark_compatible_murmur3_hash(&array.value(i), *$hash);
-            }
-        } else {
-            for i in 0..array.len() {
-                if !array.is_null(i) {
-                    *$hash = spark_compatible_murmur3_hash(&array.value(i), *$hash);
-                }
-            }
-        }
-    };
-}
-
-macro_rules! hash_array_primitive {
-    ($array_type:ident, $column: ident, $ty: ident, $hashes: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        let values = array.values();
-
-        if array.null_count() == 0 {
-            for (hash, value) in $hashes.iter_mut().zip(values.iter()) {
-                *hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *hash);
-            }
-        } else {
-            for (i, (hash, value)) in $hashes.iter_mut().zip(values.iter()).enumerate() {
-                if !array.is_null(i) {
-                    *hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *hash);
-                }
-            }
-        }
-    };
-}
+#[inline]
+pub fn spark_compatible_xxhash64_hash<T: AsRef<[u8]>>(data: T, seed: u64) -> u64 {
+    const PRIME64_1: u64 = 0x9E3779B185EBCA87;
+    const PRIME64_2: u64 = 0xC2B2AE3D27D4EB4F;
+    const PRIME64_3: u64 = 0x165667B19E3779F9;
+    const PRIME64_4: u64 = 0x85EBCA77C2B2AE63;
+    const PRIME64_5: u64 = 0x27D4EB2F165667C5;
 
-macro_rules! hash_list_primitive {
-    ($array_type:ident, $column: ident, $ty: ident, $hash: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        let values = array.values();
-        if array.null_count() == 0 {
-            for value in values.iter() {
-                *$hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *$hash);
-            }
-        } else {
-            for (i, value) in values.iter().enumerate() {
-                if !array.is_null(i) {
-                    *$hash = spark_compatible_murmur3_hash((*value as $ty).to_le_bytes(), *$hash);
-                }
-            }
-        }
-    };
-}
+    #[inline]
+    fn read_u64(data: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }
 
-macro_rules! hash_array_decimal {
-    ($array_type:ident, $column: ident, $hashes: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
+    #[inline]
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
 
-        if array.null_count() == 0 {
-            for (i, hash) in $hashes.iter_mut().enumerate() {
-                *hash = spark_compatible_murmur3_hash(array.value(i).to_le_bytes(), *hash);
-            }
-        } else {
-            for (i, hash) in $hashes.iter_mut().enumerate() {
-                if !array.is_null(i) {
-                    *hash = spark_compatible_murmur3_hash(array.value(i).to_le_bytes(), *hash);
-                }
-            }
-        }
-    };
-}
+    #[inline]
+    fn round(acc: u64, lane: u64) -> u64 {
+        acc.wrapping_add(lane.wrapping_mul(PRIME64_2))
+            .rotate_left(31)
+            .wrapping_mul(PRIME64_1)
+    }
 
-macro_rules! hash_list_decimal {
-    ($array_type:ident, $column: ident, $hash: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
+    #[inline]
+    fn merge_round(hash: u64, acc: u64) -> u64 {
+        (hash ^ round(0, acc))
+            .wrapping_mul(PRIME64_1)
+            .wrapping_add(PRIME64_4)
+    }
 
-        if array.null_count() == 0 {
-            for i in 0..array.len() {
-                *$hash = spark_compatible_murmur3_hash(array.value(i).to_le_bytes(), *$hash);
-            }
-        } else {
-            for i in 0..array.len() {
-                if !array.is_null(i) {
-                    *$hash = spark_compatible_murmur3_hash(array.value(i).to_le_bytes(), *$hash);
-                }
-            }
+    let data = data.as_ref();
+    let len = data.len();
+    let mut offset = 0;
+    let mut hash = if len >= 32 {
+        let mut v1 = seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2);
+        let mut v2 = seed.wrapping_add(PRIME64_2);
+        let mut v3 = seed;
+        let mut v4 = seed.wrapping_sub(PRIME64_1);
+        while offset + 32 <= len {
+            v1 = round(v1, read_u64(data, offset));
+            v2 = round(v2, read_u64(data, offset + 8));
+            v3 = round(v3, read_u64(data, offset + 16));
+            v4 = round(v4, read_u64(data, offset + 24));
+            offset += 32;
         }
+        let mut hash = v1
+            .rotate_left(1)
+            .wrapping_add(v2.rotate_left(7))
+            .wrapping_add(v3.rotate_left(12))
+            .wrapping_add(v4.rotate_left(18));
+        hash = merge_round(hash, v1);
+        hash = merge_round(hash, v2);
+        hash = merge_round(hash, v3);
+        merge_round(hash, v4)
+    } else {
+        seed.wrapping_add(PRIME64_5)
     };
-}
-
-/// Hash the values in a dictionary array
-fn create_hashes_dictionary<K: ArrowDictionaryKeyType>(
-    array: &ArrayRef,
-    hashes_buffer: &mut [u32],
-) -> Result<()> {
-    let dict_array = array.as_any().downcast_ref::<DictionaryArray<K>>().unwrap();
-
-    // Hash each dictionary value once, and then use that computed
-    // hash for each key value to avoid a potentially expensive
-    // redundant hashing for large dictionary elements (e.g. strings)
-    let dict_values = Arc::clone(dict_array.values());
-    let mut dict_hashes = vec![0; dict_values.len()];
-    create_hashes(&[dict_values], &mut dict_hashes)?;
-
-    for (hash, key) in hashes_buffer.iter_mut().zip(dict_array.keys().iter()) {
-        if let Some(key) = key {
-            let idx = key.to_usize().ok_or_else(|| {
-                DataFusionError::Internal(format!(
-                    "Can not convert key value {:?} to usize in dictionary of type {:?}",
-                    key,
-                    dict_array.data_type()
-                ))
-            })?;
-            *hash = dict_hashes[idx]
-        } // no update for Null, consistent with other hashes
+    hash = hash.wrapping_add(len as u64);
+
+    while offset + 8 <= len {
+        hash ^= round(0, read_u64(data, offset));
+        hash = hash
+            .rotate_left(27)
+            .wrapping_mul(PRIME64_1)
+            .wrapping_add(PRIME64_4);
+        offset += 8;
     }
-    Ok(())
+    if offset + 4 <= len {
+        hash ^= (read_u32(data, offset) as u64).wrapping_mul(PRIME64_1);
+        hash = hash
+            .rotate_left(23)
+            .wrapping_mul(PRIME64_2)
+            .wrapping_add(PRIME64_3);
+        offset += 4;
+    }
+    while offset < len {
+        hash ^= (data[offset] as u64).wrapping_mul(PRIME64_5);
+        hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+        offset += 1;
+    }
+
+    // avalanche
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^= hash >> 32;
+    hash
 }
 
-/// Creates hash values for every row, based on the values in the
-/// columns.
+/// Creates murmur3 hash values for every row like spark's `hash()`, based on
+/// the values in the columns. null values are skipped.
 ///
 /// The number of rows to hash is determined by `hashes_buffer.len()`.
-/// `hashes_buffer` should be pre-sized appropriately
+/// `hashes_buffer` should be pre-sized appropriately and filled with the seed.
 pub fn create_hashes<'a>(
     arrays: &[ArrayRef],
     hashes_buffer: &'a mut Vec<u32>,
 ) -> Result<&'a mut Vec<u32>> {
-    for col in arrays {
-        match col.data_type() {
-            DataType::Null => {}
-            DataType::Boolean => {
-                let array = col.as_any().downcast_ref::<BooleanArray>().unwrap();
-                if array.null_count() == 0 {
-                    for (i, hash) in hashes_buffer.iter_mut().enumerate() {
-                        *hash = spark_compatible_murmur3_hash(
-                            (if array.value(i) { 1u32 } else { 0u32 }).to_le_bytes(),
-                            *hash,
-                        );
-                    }
-                } else {
-                    for (i, hash) in hashes_buffer.iter_mut().enumerate() {
-                        if !array.is_null(i) {
-                            *hash = spark_compatible_murmur3_hash(
-                                (if array.value(i) { 1u32 } else { 0u32 }).to_le_bytes(),
-                                *hash,
-                            );
-                        }
-                    }
-                }
-            }
-            DataType::Int8 => {
-                hash_array_primitive!(Int8Array, col, i32, hashes_buffer);
-            }
-            DataType::Int16 => {
-                hash_array_primitive!(Int16Array, col, i32, hashes_buffer);
-            }
-            DataType::Int32 => {
-                hash_array_primitive!(Int32Array, col, i32, hashes_buffer);
-            }
-            DataType::Int64 => {
-                hash_array_primitive!(Int64Array, col, i64, hashes_buffer);
-            }
-            DataType::Float32 => {
-                hash_array_primitive!(Float32Array, col, f32, hashes_buffer);
-            }
-            DataType::Float64 => {
-                hash_array_primitive!(Float64Array, col, f64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Second, _) => {
-                hash_array_primitive!(TimestampSecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Millisecond, _) => {
-                hash_array_primitive!(TimestampMillisecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Microsecond, _) => {
-                hash_array_primitive!(TimestampMicrosecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
-                hash_array_primitive!(TimestampNanosecondArray, col, i64, hashes_buffer);
-            }
-            DataType::Date32 => {
-                hash_array_primitive!(Date32Array, col, i32, hashes_buffer);
-            }
-            DataType::Date64 => {
-                hash_array_primitive!(Date64Array, col, i64, hashes_buffer);
-            }
-            DataType::Binary => {
-                hash_array!(BinaryArray, col, hashes_buffer);
-            }
-            DataType::LargeBinary => {
-                hash_array!(LargeBinaryArray, col, hashes_buffer);
-            }
-            DataType::Utf8 => {
-                hash_array!(StringArray, col, hashes_buffer);
-            }
-            DataType::LargeUtf8 => {
-                hash_array!(LargeStringArray, col, hashes_buffer);
-            }
-            DataType::Decimal128(_, _) => {
-                hash_array_decimal!(Decimal128Array, col, hashes_buffer);
-            }
-            DataType::Dictionary(index_type, _) => match **index_type {
-                DataType::Int8 => {
-                    create_hashes_dictionary::<Int8Type>(col, hashes_buffer)?;
-                }
-                DataType::Int16 => {
-                    create_hashes_dictionary::<Int16Type>(col, hashes_buffer)?;
-                }
-                DataType::Int32 => {
-                    create_hashes_dictionary::<Int32Type>(col, hashes_buffer)?;
-                }
-                DataType::Int64 => {
-                    create_hashes_dictionary::<Int64Type>(col, hashes_buffer)?;
-                }
-                _ => {
-                    return Err(DataFusionError::Internal(format!(
-                        "Unsupported dictionary type in hasher hashing: {}",
-                        col.data_type(),
-                    )))
-                }
-            },
-            DataType::List(field) => {
-                let list_array = col.as_any().downcast_ref::<ListArray>().unwrap();
-                for (i, hash) in hashes_buffer.iter_mut().enumerate() {
-                    let sub_array = &list_array.value(i);
-                    match field.data_type() {
-                        DataType::Boolean => {
-                            let array = sub_array.as_any().downcast_ref::<BooleanArray>().unwrap();
-                            if array.null_count() == 0 {
-                                for index in 0..array.len() {
-                                    *hash = spark_compatible_murmur3_hash(
-                                        (if array.value(index) { 1u32 } else { 0u32 })
-                                            .to_le_bytes(),
-                                        *hash,
-                                    );
-                                }
-                            } else {
-                                for index in 0..array.len() {
-                                    if !array.is_null(index) {
-                                        *hash = spark_compatible_murmur3_hash(
-                                            (if array.value(index) { 1u32 } else { 0u32 })
-                                                .to_le_bytes(),
-                                            *hash,
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                        DataType::Int8 => {
-                            hash_list_primitive!(Int8Array, sub_array, i32, hash);
-                        }
-                        DataType::Int16 => {
-                            hash_list_primitive!(Int16Array, sub_array, i32, hash);
-                        }
-                        DataType::Int32 => {
-                            hash_list_primitive!(Int32Array, sub_array, i32, hash);
-                        }
-                        DataType::Int64 => {
-                            hash_list_primitive!(Int64Array, sub_array, i64, hash);
-                        }
-                        DataType::Float32 => {
-                            hash_list_primitive!(Float32Array, sub_array, f32, hash);
-                        }
-                        DataType::Float64 => {
-                            hash_list_primitive!(Float64Array, sub_array, f64, hash);
-                        }
-                        DataType::Timestamp(TimeUnit::Second, _) => {
-                            hash_list_primitive!(TimestampSecondArray, sub_array, i64, hash);
-                        }
-                        DataType::Timestamp(TimeUnit::Millisecond, _) => {
-                            hash_list_primitive!(TimestampMillisecondArray, sub_array, i64, hash);
-                        }
-                        DataType::Timestamp(TimeUnit::Microsecond, _) => {
-                            hash_list_primitive!(TimestampMicrosecondArray, sub_array, i64, hash);
-                        }
-                        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
-                            hash_list_primitive!(TimestampNanosecondArray, sub_array, i64, hash);
-                        }
-                        DataType::Date32 => {
-                            hash_list_primitive!(Date32Array, sub_array, i32, hash);
-                        }
-                        DataType::Date64 => {
-                            hash_list_primitive!(Date64Array, sub_array, i64, hash);
-                        }
-                        DataType::Binary => {
-                            hash_list!(BinaryArray, sub_array, hash);
-                        }
-                        DataType::LargeBinary => {
-                            hash_list!(LargeBinaryArray, sub_array, hash);
-                        }
-                        DataType::Utf8 => {
-                            hash_list!(StringArray, sub_array, hash);
-                        }
-                        DataType::LargeUtf8 => {
-                            hash_list!(LargeStringArray, sub_array, hash);
-                        }
-                        DataType::Decimal128(_, _) => {
-                            hash_list_decimal!(Decimal128Array, sub_array, hash);
-                        }
-                        _ => {
-                            return Err(DataFusionError::Internal(format!(
-                                "Unsupported list data type in hasher: {}",
-                                field.data_type()
-                            )));
-                        }
-                    }
-                }
-            }
-            DataType::Map(_, _) => {
-                let map_array = col.as_any().downcast_ref::<MapArray>().unwrap();
-                let key_array = map_array.keys();
-                let value_array = map_array.values();
-                let offsets_buffer = map_array.value_offsets();
-                let mut cur_offset = 0;
-                for (&next_offset, hash) in
-                    offsets_buffer.iter().skip(1).zip(hashes_buffer.iter_mut())
-                {
-                    for idx in cur_offset..next_offset {
-                        update_map_hashes(key_array, idx, hash)?;
-                        update_map_hashes(value_array, idx, hash)?;
-                    }
-                    cur_offset = next_offset;
-                }
-            }
-            DataType::Struct(_) => {
-                let struct_array = col.as_any().downcast_ref::<StructArray>().unwrap();
-                create_hashes(struct_array.columns(), hashes_buffer)?;
-            }
-            _ => {
-                // This is internal because we should have caught this before.
-                return Err(DataFusionError::Internal(format!(
-                    "Unsupported data type in hasher: {}",
-                    col.data_type()
-                )));
-            }
-        }
+    let indices = (0..hashes_buffer.len()).collect::<Vec<_>>();
+    for array in arrays {
+        hash_array(array, &indices, hashes_buffer, &|data: &[u8], seed| {
+            spark_compatible_murmur3_hash(data, seed)
+        })?;
     }
     Ok(hashes_buffer)
 }
 
-macro_rules! hash_map_primitive {
-    ($array_type:ident, $column: ident, $ty: ident, $hash: ident, $idx: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        *$hash = spark_compatible_murmur3_hash(
-            (array.value($idx as usize) as $ty).to_le_bytes(),
-            *$hash,
-        );
-    };
+/// Creates xxhash64 hash values for every row like spark's `xxhash64()`, see
+/// `create_hashes()`.
+pub fn create_xxhash64_hashes<'a>(
+    arrays: &[ArrayRef],
+    hashes_buffer: &'a mut Vec<u64>,
+) -> Result<&'a mut Vec<u64>> {
+    let indices = (0..hashes_buffer.len()).collect::<Vec<_>>();
+    for array in arrays {
+        hash_array(array, &indices, hashes_buffer, &|data: &[u8], seed| {
+            spark_compatible_xxhash64_hash(data, seed)
+        })?;
+    }
+    Ok(hashes_buffer)
 }
 
-macro_rules! hash_map_binary {
-    ($array_type:ident, $column: ident, $hash: ident, $idx: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        *$hash = spark_compatible_murmur3_hash(&array.value($idx as usize), *$hash);
-    };
-}
+/// Updates `hashes[i]` with the value at `indices[i]` of the array.
+///
+/// spark hashes ints (including bytes, shorts and dates) and longs as their
+/// little-endian bytes, which is exactly what murmur3/xxhash64 `hashInt()` and
+/// `hashLong()` compute.
+fn hash_array<H: Copy>(
+    array: &dyn Array,
+    indices: &[usize],
+    hashes: &mut [H],
+    hash_bytes: &impl Fn(&[u8], H) -> H,
+) -> Result<()> {
+    macro_rules! hash_values {
+        ($array:expr, |$value:ident| $bytes:expr) => {{
+            let array = $array;
+            for (&idx, hash) in indices.iter().zip(hashes.iter_mut()) {
+                if array.is_valid(idx) {
+                    let $value = array.value(idx);
+                    let bytes = $bytes;
+                    *hash = hash_bytes(AsRef::<[u8]>::as_ref(&bytes), *hash);
+                }
+            }
+        }};
+    }
 
-macro_rules! hash_map_decimal {
-    ($array_type:ident, $column: ident, $hash: ident, $idx: ident) => {
-        let array = $column.as_any().downcast_ref::<$array_type>().unwrap();
-        *$hash = spark_compatible_murmur3_hash(array.value($idx as usize).to_le_bytes(), *$hash);
-    };
+    match array.data_type() {
+        DataType::Null => {}
+        DataType::Boolean => hash_values!(as_boolean_array(array), |v| (v as i32).to_le_bytes()),
+        DataType::Int8 => hash_values!(as_primitive_array::<Int8Type>(array), |v| {
+            (v as i32).to_le_bytes()
+        }),
+        DataType::Int16 => hash_values!(as_primitive_array::<Int16Type>(array), |v| {
+            (v as i32).to_le_bytes()
+        }),
+        DataType::Int32 => hash_values!(as_primitive_array::<Int32Type>(array), |v| {
+            v.to_le_bytes()
+        }),
+        DataType::Int64 => hash_values!(as_primitive_array::<Int64Type>(array), |v| {
+            v.to_le_bytes()
+        }),
+        DataType::Float32 => hash_values!(as_primitive_array::<Float32Type>(array), |v| {
+            // like java's floatToIntBits() with -0.0 normalized to 0.0
+            let bits = if v == 0.0 {
+                0
+            } else if v.is_nan() {
+                0x7fc00000
+            } else {
+                v.to_bits()
+            };
+            bits.to_le_bytes()
+        }),
+        DataType::Float64 => hash_values!(as_primitive_array::<Float64Type>(array), |v| {
+            // like java's doubleToLongBits() with -0.0 normalized to 0.0
+            let bits = if v == 0.0 {
+                0
+            } else if v.is_nan() {
+                0x7ff8000000000000
+            } else {
+                v.to_bits()
+            };
+            bits.to_le_bytes()
+        }),
+        DataType::Date32 => hash_values!(as_primitive_array::<Date32Type>(array), |v| {
+            v.to_le_bytes()
+        }),
+        DataType::Date64 => hash_values!(as_primitive_array::<Date64Type>(array), |v| {
+            v.to_le_bytes()
+        }),
+        DataType::Timestamp(TimeUnit::Second, _) => {
+            hash_values!(as_primitive_array::<TimestampSecondType>(array), |v| {
+                v.to_le_bytes()
+            })
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            hash_values!(as_primitive_array::<TimestampMillisecondType>(array), |v| {
+                v.to_le_bytes()
+            })
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            hash_values!(as_primitive_array::<TimestampMicrosecondType>(array), |v| {
+                v.to_le_bytes()
+            })
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            hash_values!(as_primitive_array::<TimestampNanosecondType>(array), |v| {
+                v.to_le_bytes()
+            })
+        }
+        DataType::Utf8 => hash_values!(as_string_array(array), |v| v),
+        DataType::LargeUtf8 => hash_values!(as_largestring_array(array), |v| v),
+        DataType::Binary => hash_values!(as_generic_binary_array::<i32>(array), |v| v),
+        DataType::LargeBinary => hash_values!(as_generic_binary_array::<i64>(array), |v| v),
+        DataType::Decimal128(precision, _) if *precision <= 18 => {
+            // hashed as the unscaled long value
+            hash_values!(as_primitive_array::<Decimal128Type>(array), |v| {
+                (v as i64).to_le_bytes()
+            })
+        }
+        DataType::Decimal128(..) => {
+            // hashed as java's BigInteger.toByteArray() of the unscaled value
+            hash_values!(as_primitive_array::<Decimal128Type>(array), |v| {
+                java_big_integer_bytes(v)
+            })
+        }
+        DataType::Dictionary(_, value_type) => {
+            let values = arrow::compute::cast(array, value_type)?;
+            hash_array(&values, indices, hashes, hash_bytes)?;
+        }
+        DataType::List(_) => {
+            let list = as_list_array(array);
+            hash_list_elements(
+                list,
+                list.value_offsets(),
+                list.values(),
+                indices,
+                hashes,
+                hash_bytes,
+            )?;
+        }
+        DataType::LargeList(_) => {
+            let list = as_large_list_array(array);
+            hash_list_elements(
+                list,
+                list.value_offsets(),
+                list.values(),
+                indices,
+                hashes,
+                hash_bytes,
+            )?;
+        }
+        DataType::Map(..) => {
+            // entries are hashed as key1, value1, key2, value2, ...
+            let map = as_map_array(array);
+            let entries: ArrayRef = Arc::new(map.entries().clone());
+            hash_list_elements(
+                map,
+                map.value_offsets(),
+                &entries,
+                indices,
+                hashes,
+                hash_bytes,
+            )?;
+        }
+        DataType::Struct(_) => {
+            // fields of null structs are not hashed
+            let struct_array = as_struct_array(array);
+            let (valid_indices, mut valid_hashes): (Vec<usize>, Vec<H>) = indices
+                .iter()
+                .zip(hashes.iter())
+                .filter(|(&idx, _)| struct_array.is_valid(idx))
+                .map(|(&idx, &hash)| (idx, hash))
+                .unzip();
+            for column in struct_array.columns() {
+                hash_array(column, &valid_indices, &mut valid_hashes, hash_bytes)?;
+            }
+            let mut valid_hashes = valid_hashes.into_iter();
+            for (&idx, hash) in indices.iter().zip(hashes.iter_mut()) {
+                if struct_array.is_valid(idx) {
+                    *hash = valid_hashes.next().unwrap();
+                }
+            }
+        }
+        other => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "Unsupported data type in hasher: {}",
+                other
+            )));
+        }
+    }
+    Ok(())
 }
 
-fn update_map_hashes(array: &ArrayRef, idx: i32, hash: &mut u32) -> Result<()> {
-    if array.is_valid(idx as usize) {
-        match array.data_type() {
-            DataType::Boolean => {
-                let array = array.as_any().downcast_ref::<BooleanArray>().unwrap();
-                *hash = spark_compatible_murmur3_hash(
-                    (if array.value(idx as usize) {
-                        1u32
-                    } else {
-                        0u32
-                    })
-                    .to_le_bytes(),
-                    *hash,
-                );
-            }
-            DataType::Int8 => {
-                hash_map_primitive!(Int8Array, array, i32, hash, idx);
-            }
-            DataType::Int16 => {
-                hash_map_primitive!(Int16Array, array, i32, hash, idx);
-            }
-            DataType::Int32 => {
-                hash_map_primitive!(Int32Array, array, i32, hash, idx);
-            }
-            DataType::Int64 => {
-                hash_map_primitive!(Int64Array, array, i64, hash, idx);
-            }
-            DataType::Float32 => {
-                hash_map_primitive!(Float32Array, array, f32, hash, idx);
-            }
-            DataType::Float64 => {
-                hash_map_primitive!(Float64Array, array, f64, hash, idx);
-            }
-            DataType::Timestamp(TimeUnit::Second, None) => {
-                hash_map_primitive!(TimestampSecondArray, array, i64, hash, idx);
-            }
-            DataType::Timestamp(TimeUnit::Millisecond, None) => {
-                hash_map_primitive!(TimestampMillisecondArray, array, i64, hash, idx);
-            }
-            DataType::Timestamp(TimeUnit::Microsecond, None) => {
-                hash_map_primitive!(TimestampMicrosecondArray, array, i64, hash, idx);
-            }
-            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
-                hash_map_primitive!(TimestampNanosecondArray, array, i64, hash, idx);
-            }
-            DataType::Date32 => {
-                hash_map_primitive!(Date32Array, array, i32, hash, idx);
-            }
-            DataType::Date64 => {
-                hash_map_primitive!(Date64Array, array, i64, hash, idx);
-            }
-            DataType::Binary => {
-                hash_map_binary!(BinaryArray, array, hash, idx);
-            }
-            DataType::LargeBinary => {
-                hash_map_binary!(LargeBinaryArray, array, hash, idx);
-            }
-            DataType::Utf8 => {
-                hash_map_binary!(StringArray, array, hash, idx);
-            }
-            DataType::LargeUtf8 => {
-                hash_map_binary!(LargeStringArray, array, hash, idx);
-            }
-            DataType::Decimal128(_, _) => {
-                hash_map_decimal!(Decimal128Array, array, hash, idx);
-            }
-            _ => {
-                return Err(DataFusionError::Internal(format!(
-                    "Unsupported map key/value data type in hasher: {}",
-                    array.data_type()
-                )));
+/// Hashes elements of each list sequentially into the hash of the list. the
+/// k-th elements of all lists are hashed together in the k-th round.
+fn hash_list_elements<H: Copy, O: OffsetSizeTrait>(
+    list: &dyn Array,
+    offsets: &[O],
+    values: &ArrayRef,
+    indices: &[usize],
+    hashes: &mut [H],
+    hash_bytes: &impl Fn(&[u8], H) -> H,
+) -> Result<()> {
+    // (position in hashes, current element index, end element index)
+    let mut active = indices
+        .iter()
+        .enumerate()
+        .filter(|(_, &idx)| list.is_valid(idx))
+        .map(|(pos, &idx)| (pos, offsets[idx].as_usize(), offsets[idx + 1].as_usize()))
+        .filter(|&(_, start, end)| start < end)
+        .collect::<Vec<_>>();
+
+    while !active.is_empty() {
+        let element_indices = active.iter().map(|&(_, cur, _)| cur).collect::<Vec<_>>();
+        let mut element_hashes = active
+            .iter()
+            .map(|&(pos, _, _)| hashes[pos])
+            .collect::<Vec<_>>();
+
+        match values.data_type() {
+            DataType::Struct(_) if matches!(list.data_type(), DataType::Map(..)) => {
+                for column in as_struct_array(values).columns() {
+                    hash_array(column, &element_indices, &mut element_hashes, hash_bytes)?;
+                }
             }
+            _ => hash_array(values, &element_indices, &mut element_hashes, hash_bytes)?,
+        }
+        for (&(pos, _, _), hash) in active.iter().zip(element_hashes) {
+            hashes[pos] = hash;
         }
+        active.iter_mut().for_each(|(_, cur, _)| *cur += 1);
+        active.retain(|&(_, cur, end)| cur < end);
     }
     Ok(())
 }
 
+/// Returns the minimal big-endian two's complement bytes of the value, same as
+/// java's `BigInteger.toByteArray()`
+fn java_big_integer_bytes(value: i128) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start + 1 < bytes.len() {
+        let (cur, next) = (bytes[start], bytes[start + 1]);
+        if (cur == 0x00 && next & 0x80 == 0) || (cur == 0xff && next & 0x80 != 0) {
+            start += 1;
+        } else {
+            break;
+        }
+    }
+    bytes[start..].to_vec()
+}
+
 pub fn pmod(hash: u32, n: usize) -> usize {
     let hash = hash as i32;
     let n = n as i32;
@@ -567,13 +460,16 @@ pub fn pmod(hash: u32, n: usize) -> usize {
 mod tests {
     use std::sync::Arc;
 
-    use crate::spark_hash::{create_hashes, pmod, spark_compatible_murmur3_hash};
+    use crate::spark_hash::{
+        create_hashes, create_xxhash64_hashes, java_big_integer_bytes, pmod,
+        spark_compatible_murmur3_hash,
+    };
     use arrow::array::{
-        make_array, Array, ArrayData, ArrayRef, Int32Array, Int64Array, Int8Array, MapArray,
-        StringArray, StructArray, UInt32Array,
+        make_array, Array, ArrayData, ArrayRef, Decimal128Array, Float64Array, Int32Array,
+        Int64Array, Int8Array, ListArray, MapArray, StringArray, StructArray, UInt32Array,
     };
-    use arrow::buffer::Buffer;
-    use arrow::datatypes::{DataType, Field, ToByteSlice};
+    use arrow::buffer::{Buffer, NullBuffer};
+    use arrow::datatypes::{DataType, Field, Fields, Int32Type, ToByteSlice};
 
     #[test]
     fn test_list() {
@@ -644,6 +540,84 @@ mod tests {
         assert_eq!(hashes, expected);
     }
 
+    #[test]
+    fn test_hash_with_array() {
+        // generated with spark: SELECT hash('Spark', array(123), 2), xxhash64('Spark', array(123), 2)
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec!["Spark"])),
+            Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(123)]),
+            ])),
+            Arc::new(Int32Array::from(vec![2])),
+        ];
+        let mut hashes = vec![42; 1];
+        create_hashes(&arrays, &mut hashes).unwrap();
+        assert_eq!(hashes[0] as i32, -1321691492);
+
+        let mut hashes = vec![42; 1];
+        create_xxhash64_hashes(&arrays, &mut hashes).unwrap();
+        assert_eq!(hashes[0] as i64, 5602566077635097486);
+    }
+
+    #[test]
+    fn test_normalized_floats() {
+        let floats: ArrayRef = Arc::new(Float64Array::from(vec![0.0, -0.0, f64::NAN, -f64::NAN]));
+        let mut hashes = vec![42; 4];
+        create_hashes(&[floats], &mut hashes).unwrap();
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[2], hashes[3]);
+        assert_eq!(
+            hashes[0],
+            spark_compatible_murmur3_hash(0i64.to_le_bytes(), 42)
+        );
+    }
+
+    #[test]
+    fn test_decimal() {
+        // small decimals are hashed as longs, big decimals as java BigInteger bytes
+        let small: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![12345])
+                .with_precision_and_scale(18, 2)
+                .unwrap(),
+        );
+        let mut hashes = vec![42; 1];
+        create_hashes(&[small], &mut hashes).unwrap();
+        assert_eq!(
+            hashes[0],
+            spark_compatible_murmur3_hash(12345i64.to_le_bytes(), 42)
+        );
+
+        let big: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![128])
+                .with_precision_and_scale(38, 2)
+                .unwrap(),
+        );
+        let mut hashes = vec![42; 1];
+        create_hashes(&[big], &mut hashes).unwrap();
+        assert_eq!(hashes[0], spark_compatible_murmur3_hash([0x00, 0x80], 42));
+
+        assert_eq!(java_big_integer_bytes(0), vec![0x00]);
+        assert_eq!(java_big_integer_bytes(-1), vec![0xff]);
+        assert_eq!(java_big_integer_bytes(-129), vec![0xff, 0x7f]);
+    }
+
+    #[test]
+    fn test_null_struct() {
+        // fields of null structs are skipped even if they have values
+        let struct_array: ArrayRef = Arc::new(StructArray::new(
+            Fields::from(vec![Field::new("a", DataType::Int32, true)]),
+            vec![Arc::new(Int32Array::from(vec![1, 1]))],
+            Some(NullBuffer::from(vec![true, false])),
+        ));
+        let mut hashes = vec![42; 2];
+        create_hashes(&[struct_array], &mut hashes).unwrap();
+        assert_eq!(
+            hashes[0],
+            spark_compatible_murmur3_hash(1i32.to_le_bytes(), 42)
+        );
+        assert_eq!(hashes[1], 42);
+    }
+
     #[test]
     fn test_pmod() {
         let i: Vec<u32> = vec![0x99f0149d, 0x9c67b85d, 0xc8008529, 0xa05b5d7b, 0xcd1e64fb];
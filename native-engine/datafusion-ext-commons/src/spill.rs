@@ -0,0 +1,386 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! On-disk spill files of native operators.
+//!
+//! spill files of a task are allocated by the task's `SpillManager` in spark's
+//! local dirs, and are deleted when dropped, or at the latest when the task
+//! is deregistered on completion or failure.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Weak};
+
+use arrow::record_batch::RecordBatch;
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static, jni_get_string};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::execution::context::TaskContext;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use tempfile::NamedTempFile;
+
+use crate::io::{write_one_batch_with_codec, IpcCompressionCodec, DEFAULT_COMPRESSION_LEVEL};
+
+fn registered_spill_managers() -> &'static Mutex<Vec<(Weak<TaskContext>, Arc<SpillManager>)>> {
+    static REGISTERED: OnceCell<Mutex<Vec<(Weak<TaskContext>, Arc<SpillManager>)>>> =
+        OnceCell::new();
+    REGISTERED.get_or_init(|| Mutex::default())
+}
+
+pub struct SpillManager {
+    local_dirs: Vec<PathBuf>,
+    codec: IpcCompressionCodec,
+    next_file_id: AtomicUsize,
+    spilled_bytes: AtomicUsize,
+    live_files: Mutex<HashMap<usize, PathBuf>>,
+}
+
+impl SpillManager {
+    pub fn new(local_dirs: Vec<PathBuf>, codec: IpcCompressionCodec) -> Self {
+        assert!(!local_dirs.is_empty(), "spill manager requires local dirs");
+        Self {
+            local_dirs,
+            codec,
+            next_file_id: AtomicUsize::new(0),
+            spilled_bytes: AtomicUsize::new(0),
+            live_files: Mutex::default(),
+        }
+    }
+
+    /// Creates a spill manager with local dirs and codec configured in spark,
+    /// or with the system temp dir and lz4 codec when running without jvm.
+    pub fn try_new_configured() -> Result<Self> {
+        if !is_jni_bridge_inited() {
+            return Ok(Self::new(
+                vec![std::env::temp_dir()],
+                IpcCompressionCodec::Lz4,
+            ));
+        }
+        let local_dirs = jni_call_static!(JniBridge.getSparkLocalDirs() -> JObject)?;
+        let local_dirs = jni_get_string!(local_dirs.as_obj().into())?;
+        let codec = jni_call_static!(BlazeConf.spillCompressionCodec() -> JObject)?;
+        let codec = parse_spill_codec(&jni_get_string!(codec.as_obj().into())?)?;
+
+        let local_dirs = local_dirs
+            .split(',')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        if local_dirs.is_empty() {
+            return Ok(Self::new(vec![std::env::temp_dir()], codec));
+        }
+        Ok(Self::new(local_dirs, codec))
+    }
+
+    /// Same as `try_new_configured()`, but falls back to the system temp dir
+    /// and lz4 codec on configuration errors, so that spilling never fails a
+    /// task for misconfiguration.
+    fn new_configured_or_default() -> Self {
+        Self::try_new_configured().unwrap_or_else(|err| {
+            log::warn!("error configuring spill manager, using temp dir and lz4: {err}");
+            Self::new(vec![std::env::temp_dir()], IpcCompressionCodec::Lz4)
+        })
+    }
+
+    /// Registers a spill manager for all operators executed with the task
+    /// context, spill files are guaranteed to be deleted on deregistering.
+    pub fn register(task_context: &Arc<TaskContext>) -> Arc<Self> {
+        let spill_manager = Arc::new(Self::new_configured_or_default());
+        let mut registered = registered_spill_managers().lock();
+        registered
+            .retain(|(registered_task_context, _)| registered_task_context.strong_count() > 0);
+        registered.push((Arc::downgrade(task_context), spill_manager.clone()));
+        spill_manager
+    }
+
+    /// Deregisters the spill manager of the task context and deletes all its
+    /// remaining spill files, returns the deregistered manager if registered
+    pub fn deregister(task_context: &Arc<TaskContext>) -> Option<Arc<Self>> {
+        let mut registered = registered_spill_managers().lock();
+        let idx = registered.iter().position(|(registered_task_context, _)| {
+            std::ptr::eq(registered_task_context.as_ptr(), Arc::as_ptr(task_context))
+        })?;
+        let spill_manager = registered.swap_remove(idx).1;
+        drop(registered);
+
+        spill_manager.delete_live_files();
+        Some(spill_manager)
+    }
+
+    /// Gets the spill manager of the task context, falls back to a shared
+    /// unregistered manager if the task context is not registered (driver
+    /// side and testing).
+    pub fn get(task_context: &Arc<TaskContext>) -> Arc<Self> {
+        let registered = registered_spill_managers().lock();
+        let found = registered
+            .iter()
+            .find(|(registered_task_context, _)| {
+                std::ptr::eq(registered_task_context.as_ptr(), Arc::as_ptr(task_context))
+            })
+            .map(|(_, spill_manager)| spill_manager.clone());
+        drop(registered);
+
+        if let Some(spill_manager) = found {
+            return spill_manager;
+        }
+        static UNREGISTERED: OnceCell<Arc<SpillManager>> = OnceCell::new();
+        UNREGISTERED
+            .get_or_init(|| Arc::new(Self::new_configured_or_default()))
+            .clone()
+    }
+
+    pub fn codec(&self) -> IpcCompressionCodec {
+        self.codec
+    }
+
+    /// Creates a writer compressing spilled data with the configured codec,
+    /// the compressed frame is finished when the writer is dropped.
+    pub fn compressed_writer<'a, W: Write + Send + 'a>(
+        &self,
+        output: W,
+    ) -> Result<Box<dyn Write + Send + 'a>> {
+        self.codec.new_send_encoder(output)
+    }
+
+    /// Writes a batch compressed with the configured codec, which is readable
+    /// by `read_one_batch()` with `compress` enabled.
+    pub fn write_batch(&self, batch: &RecordBatch, output: &mut impl Write) -> Result<()> {
+        let mut buf = vec![];
+        write_one_batch_with_codec(batch, &mut Cursor::new(&mut buf), Some(self.codec), None)?;
+        output.write_all(&buf)?;
+        Ok(())
+    }
+
+    /// Creates a reader decompressing data written by `compressed_writer()`,
+    /// the codec is read from the data.
+    pub fn compressed_reader<'a, R: Read + Send + 'a>(
+        input: R,
+    ) -> Result<Box<dyn Read + Send + 'a>> {
+        crate::io::new_send_decoder(input)
+    }
+
+    /// Total on-disk size of all completed spill files
+    pub fn spilled_bytes(&self) -> usize {
+        self.spilled_bytes.load(SeqCst)
+    }
+
+    pub fn num_live_files(&self) -> usize {
+        self.live_files.lock().len()
+    }
+
+    /// Allocates a new spill file, local dirs are used in round-robin order
+    pub fn new_spill_file(self: &Arc<Self>) -> Result<SpillFile> {
+        let file_id = self.next_file_id.fetch_add(1, SeqCst);
+        let local_dir = &self.local_dirs[file_id % self.local_dirs.len()];
+        let file = tempfile::Builder::new()
+            .prefix("blaze-spill-")
+            .tempfile_in(local_dir)?;
+        self.live_files
+            .lock()
+            .insert(file_id, file.path().to_path_buf());
+
+        Ok(SpillFile {
+            spill_manager: self.clone(),
+            file_id,
+            file,
+            disk_size: 0,
+        })
+    }
+
+    fn delete_live_files(&self) {
+        let live_files = std::mem::take(&mut *self.live_files.lock());
+        for path in live_files.into_values() {
+            log::warn!("deleting leaked spill file: {:?}", path);
+            if let Err(err) = std::fs::remove_file(&path) {
+                log::warn!("error deleting spill file {:?}: {}", path, err);
+            }
+        }
+    }
+}
+
+fn parse_spill_codec(codec: &str) -> Result<IpcCompressionCodec> {
+    match codec.to_ascii_lowercase().as_str() {
+        "lz4" => Ok(IpcCompressionCodec::Lz4),
+        "zstd" => Ok(IpcCompressionCodec::Zstd(DEFAULT_COMPRESSION_LEVEL)),
+        other => Err(DataFusionError::Configuration(format!(
+            "unsupported spill compression codec: {}, expect lz4 or zstd",
+            other
+        ))),
+    }
+}
+
+/// A spill file allocated by `SpillManager`, deleted when dropped.
+///
+/// data is readable after completed.
+pub struct SpillFile {
+    spill_manager: Arc<SpillManager>,
+    file_id: usize,
+    file: NamedTempFile,
+    disk_size: usize,
+}
+
+impl SpillFile {
+    /// Completes writing and rewinds the file for reading, disk usage is
+    /// recorded in the spill manager.
+    pub fn complete(&mut self) -> Result<()> {
+        let file = self.file.as_file_mut();
+        file.flush()?;
+        let disk_size = file.stream_position()? as usize;
+        file.seek(SeekFrom::Start(0))?;
+
+        self.spill_manager
+            .spilled_bytes
+            .fetch_add(disk_size.saturating_sub(self.disk_size), SeqCst);
+        self.disk_size = disk_size;
+        Ok(())
+    }
+
+    /// Returns a handle of the underlying file for uncompressed reading and
+    /// writing, which shares the file position with other handles.
+    pub fn try_clone_file(&self) -> Result<File> {
+        Ok(self.file.as_file().try_clone()?)
+    }
+
+    pub fn disk_size(&self) -> usize {
+        self.disk_size
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        // the file itself is deleted by NamedTempFile
+        self.spill_manager.live_files.lock().remove(&self.file_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+
+    use arrow::array::{ArrayRef, Int32Array};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+
+    use crate::io::{read_one_batch, IpcCompressionCodec};
+    use crate::spill::SpillManager;
+
+    #[test]
+    fn test_spill_file() -> Result<()> {
+        let dirs = vec![tempfile::tempdir()?, tempfile::tempdir()?];
+        for codec in [IpcCompressionCodec::Lz4, IpcCompressionCodec::Zstd(1)] {
+            let spill_manager = Arc::new(SpillManager::new(
+                dirs.iter().map(|dir| dir.path().to_path_buf()).collect(),
+                codec,
+            ));
+            let data = (0..100000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+
+            let mut spills = vec![];
+            for _ in 0..4 {
+                let mut spill = spill_manager.new_spill_file()?;
+                let mut writer = spill_manager.compressed_writer(spill.try_clone_file()?)?;
+                writer.write_all(&data)?;
+                drop(writer);
+                spill.complete()?;
+                spills.push(spill);
+            }
+            assert_eq!(spill_manager.num_live_files(), 4);
+            assert_eq!(
+                spill_manager.spilled_bytes(),
+                spills.iter().map(|spill| spill.disk_size()).sum::<usize>(),
+            );
+            assert!(spill_manager.spilled_bytes() < data.len());
+
+            // local dirs are used in round-robin order
+            for dir in &dirs {
+                assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+            }
+
+            for spill in &spills {
+                let mut read = vec![];
+                SpillManager::compressed_reader(spill.try_clone_file()?)?.read_to_end(&mut read)?;
+                assert_eq!(read, data);
+            }
+
+            // files are deleted on drop
+            drop(spills);
+            assert_eq!(spill_manager.num_live_files(), 0);
+            for dir in &dirs {
+                assert_eq!(std::fs::read_dir(dir.path())?.count(), 0);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_batch() -> Result<()> {
+        let array: ArrayRef = Arc::new(Int32Array::from_iter_values((0..10000).map(|i| i % 7)));
+        let batch = RecordBatch::try_from_iter(vec![("a", array)])?;
+
+        // batches written with any codec are readable without configuration
+        for codec in [IpcCompressionCodec::Lz4, IpcCompressionCodec::Zstd(1)] {
+            let spill_manager = SpillManager::new(vec![std::env::temp_dir()], codec);
+            let mut buf = vec![];
+            spill_manager.write_batch(&batch, &mut buf)?;
+            spill_manager.write_batch(&batch, &mut buf)?;
+            assert!(buf.len() < batch.get_array_memory_size());
+
+            let mut input = buf.as_slice();
+            assert_eq!(
+                read_one_batch(&mut input, Some(batch.schema()), true)?,
+                Some(batch.clone())
+            );
+            assert_eq!(
+                read_one_batch(&mut input, Some(batch.schema()), true)?,
+                Some(batch.clone())
+            );
+            assert_eq!(
+                read_one_batch(&mut input, Some(batch.schema()), true)?,
+                None
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_deregister_deletes_files() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let spill_manager = SpillManager::register(&task_ctx);
+        assert!(Arc::ptr_eq(&SpillManager::get(&task_ctx), &spill_manager));
+
+        let mut spill = spill_manager.new_spill_file()?;
+        spill_manager
+            .compressed_writer(spill.try_clone_file()?)?
+            .write_all(b"hello")?;
+        spill.complete()?;
+        let path = spill.file.path().to_path_buf();
+        assert!(path.exists());
+
+        // leaked files are deleted when the task is deregistered
+        std::mem::forget(spill);
+        let deregistered = SpillManager::deregister(&task_ctx).unwrap();
+        assert!(Arc::ptr_eq(&deregistered, &spill_manager));
+        assert_eq!(deregistered.num_live_files(), 0);
+        assert!(!path.exists());
+
+        // falls back to the unregistered manager
+        assert!(!Arc::ptr_eq(&SpillManager::get(&task_ctx), &spill_manager));
+        Ok(())
+    }
+}
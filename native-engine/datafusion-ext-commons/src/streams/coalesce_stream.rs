@@ -12,24 +12,43 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::batch_splitter::split_batch_by_byte_budget;
 use crate::concat_batches;
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static};
 use datafusion::common::Result;
 use datafusion::physical_plan::metrics::Time;
 use datafusion::physical_plan::{RecordBatchStream, SendableRecordBatchStream};
 use futures::{Stream, StreamExt};
+use std::collections::VecDeque;
 use std::pin::Pin;
 use std::task::{ready, Context, Poll};
 
 const STAGING_BATCHES_MEM_SIZE_LIMIT: usize = 1 << 26; // limit output batch size to 64MB
 
+/// Returns the byte budget of output batches configured by
+/// `spark.blaze.batch.mem.bytes`, or the default budget without jvm (in tests).
+pub fn batch_byte_budget() -> Result<usize> {
+    if !is_jni_bridge_inited() {
+        return Ok(STAGING_BATCHES_MEM_SIZE_LIMIT);
+    }
+    let byte_budget = jni_call_static!(BlazeConf.batchMemBytes() -> i32)?;
+    Ok(byte_budget.max(1) as usize)
+}
+
+/// Coalesces small batches into batches of `batch_size` rows, and limits the
+/// memory size of coalesced batches. with `with_byte_budget()`, oversized input
+/// batches of wide rows are also split into zero-copy slices under the budget.
 pub struct CoalesceStream {
     input: SendableRecordBatchStream,
     staging_batches: Vec<RecordBatch>,
     staging_rows: usize,
     staging_batches_mem_size: usize,
+    split_batches: VecDeque<RecordBatch>,
     batch_size: usize,
+    byte_budget: usize,
+    split_oversized: bool,
     elapsed_compute: Time,
 }
 
@@ -40,11 +59,24 @@ impl CoalesceStream {
             staging_batches: vec![],
             staging_rows: 0,
             staging_batches_mem_size: 0,
+            split_batches: VecDeque::new(),
             batch_size,
+            byte_budget: STAGING_BATCHES_MEM_SIZE_LIMIT,
+            split_oversized: false,
             elapsed_compute,
         }
     }
 
+    /// Limits both coalesced and input batches to `byte_budget`, input batches
+    /// larger than the budget are split by estimated row sizes.
+    pub fn with_byte_budget(self, byte_budget: usize) -> Self {
+        Self {
+            byte_budget,
+            split_oversized: true,
+            ..self
+        }
+    }
+
     fn coalesce(&mut self) -> Result<RecordBatch> {
         let coalesced = concat_batches(
             &self.schema(),
@@ -58,12 +90,20 @@ impl CoalesceStream {
 
     fn should_flush(&self) -> bool {
         let (batch_size_limit, mem_size_limit) = if self.staging_batches.len() > 1 {
-            (self.batch_size, STAGING_BATCHES_MEM_SIZE_LIMIT)
+            (self.batch_size, self.byte_budget)
         } else {
-            (self.batch_size / 2, STAGING_BATCHES_MEM_SIZE_LIMIT / 2)
+            (self.batch_size / 2, self.byte_budget / 2)
         };
         self.staging_rows >= batch_size_limit || self.staging_batches_mem_size > mem_size_limit
     }
+
+    fn should_split(&self, batch: &RecordBatch) -> bool {
+        // memory size is an upper bound of the estimated size, so this check
+        // avoids estimating row sizes of most batches
+        self.split_oversized
+            && batch.num_rows() > 1
+            && batch.get_array_memory_size() > self.byte_budget
+    }
 }
 
 impl RecordBatchStream for CoalesceStream {
@@ -78,7 +118,23 @@ impl Stream for CoalesceStream {
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let elapsed_time = self.elapsed_compute.clone();
         loop {
+            // output split batches directly, they are already under the budget
+            if let Some(split_batch) = self.split_batches.pop_front() {
+                return Poll::Ready(Some(Ok(split_batch)));
+            }
+
             match ready!(self.input.poll_next_unpin(cx)).transpose()? {
+                Some(batch) if self.should_split(&batch) => {
+                    let _timer = elapsed_time.timer();
+                    let split_batches = split_batch_by_byte_budget(&batch, self.byte_budget)?;
+                    self.split_batches.extend(split_batches);
+
+                    // flush staging batches first to keep the order of rows
+                    if !self.staging_batches.is_empty() {
+                        let coalesced = self.coalesce()?;
+                        return Poll::Ready(Some(Ok(coalesced)));
+                    }
+                }
                 Some(batch) => {
                     let _timer = elapsed_time.timer();
                     let num_rows = batch.num_rows();
@@ -105,3 +161,55 @@ impl Stream for CoalesceStream {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::batch_splitter::estimate_batch_row_sizes;
+    use crate::streams::coalesce_stream::CoalesceStream;
+    use arrow::array::{ArrayRef, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::common;
+    use datafusion::physical_plan::metrics::Time;
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use std::sync::Arc;
+
+    fn string_batch(num_rows: usize, width: usize) -> Result<RecordBatch> {
+        let strings: ArrayRef = Arc::new(StringArray::from_iter_values(
+            (0..num_rows).map(|i| format!("{:0width$}", i, width = width)),
+        ));
+        Ok(RecordBatch::try_from_iter(vec![("s", strings)])?)
+    }
+
+    #[test]
+    fn test_coalesce_with_byte_budget() -> Result<()> {
+        // a batch of wide rows followed by some batches of narrow rows
+        let batches = vec![
+            string_batch(1000, 100)?,
+            string_batch(10, 1)?,
+            string_batch(10, 1)?,
+            string_batch(10, 1)?,
+        ];
+        let schema = batches[0].schema();
+        let input = Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            futures::stream::iter(batches.clone().into_iter().map(Ok)),
+        ));
+
+        let byte_budget = 10000;
+        let coalesced = CoalesceStream::new(input, 100, Time::new()).with_byte_budget(byte_budget);
+        let output = futures::executor::block_on(common::collect(Box::pin(coalesced)))?;
+
+        // each wide row takes 4 + 100 bytes, so 96 rows fit in the budget
+        let num_rows = output.iter().map(|b| b.num_rows()).collect::<Vec<_>>();
+        assert_eq!(num_rows, [vec![96; 10], vec![40, 30]].concat());
+        for batch in &output {
+            assert!(estimate_batch_row_sizes(batch)?.iter().sum::<usize>() <= byte_budget);
+        }
+        assert_eq!(
+            arrow::compute::concat_batches(&schema, &output)?,
+            arrow::compute::concat_batches(&schema, &batches)?,
+        );
+        Ok(())
+    }
+}
@@ -18,7 +18,7 @@ use arrow::ffi::{from_ffi, FFI_ArrowArray, FFI_ArrowSchema};
 use arrow::record_batch::RecordBatch;
 use blaze_jni_bridge::{jni_call, jni_new_object};
 use datafusion::error::Result;
-use datafusion::physical_plan::metrics::{BaselineMetrics, Count};
+use datafusion::physical_plan::metrics::{BaselineMetrics, Count, Time};
 use datafusion::physical_plan::RecordBatchStream;
 use futures::Stream;
 use jni::objects::{GlobalRef, JObject};
@@ -31,6 +31,7 @@ pub struct FFIReaderStream {
     export_iter: GlobalRef,
     baseline_metrics: BaselineMetrics,
     size_counter: Count,
+    jni_time: Time,
 }
 
 impl FFIReaderStream {
@@ -45,8 +46,14 @@ impl FFIReaderStream {
             export_iter,
             baseline_metrics,
             size_counter,
+            jni_time: Time::new(),
         }
     }
+
+    /// Records time spent exporting batches in the jvm
+    pub fn with_jni_time(self, jni_time: Time) -> Self {
+        Self { jni_time, ..self }
+    }
 }
 
 impl RecordBatchStream for FFIReaderStream {
@@ -70,6 +77,7 @@ impl Stream for FFIReaderStream {
 
 impl FFIReaderStream {
     fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let jni_timer = self.jni_time.timer();
         let has_next = jni_call!(
             ScalaIterator(self.export_iter.as_obj()).hasNext() -> jboolean
         )?;
@@ -93,6 +101,7 @@ impl FFIReaderStream {
             ffi_arrow_schema_ptr.as_obj(),
             ffi_arrow_array_ptr.as_obj(),
         ) -> JObject)?;
+        jni_timer.done();
 
         let imported = from_ffi(ffi_arrow_array, &ffi_arrow_schema)?;
         let struct_array = StructArray::from(imported);
@@ -12,27 +12,45 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::VecDeque;
 use std::fmt::Debug;
 
 use crate::io::read_one_batch;
+use crate::jni_buffer::{acquire_jni_buffer, PooledJniBuffer};
+use crate::metrics::TimedReader;
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
-use blaze_jni_bridge::{
-    jni_call, jni_get_object_class, jni_get_string, jni_new_direct_byte_buffer, jni_new_global_ref,
-};
-use datafusion::error::Result;
-use datafusion::physical_plan::metrics::{BaselineMetrics, Count};
-use datafusion::physical_plan::RecordBatchStream;
-use futures::Stream;
+use blaze_jni_bridge::jni_blocking::spawn_jni_blocking_producer;
+use blaze_jni_bridge::{jni_call, jni_get_object_class, jni_get_string, jni_new_global_ref};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::physical_plan::metrics::{BaselineMetrics, Count, Time};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{RecordBatchStream, SendableRecordBatchStream};
+use futures::{Stream, StreamExt};
 use jni::objects::{GlobalRef, JObject};
 use jni::sys::{jboolean, jint, jlong, JNI_TRUE};
+use memmap2::Mmap;
 use std::fs::File;
-use std::io::{BufReader, Read, SeekFrom};
+use std::io::{BufReader, Cursor, Read, SeekFrom};
 use std::io::{Error as IoError, Seek};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Receiver;
+use tracing::{debug, debug_span, Span};
 
+/// Number of decoded batches buffered between the blocking reading thread and
+/// the consumer of an ipc reader stream
+const BLOCKING_READ_CHANNEL_CAPACITY: usize = 2;
+
+/// Max number of data files kept mapped by an ipc reader stream. a mapping
+/// evicted from the cache is unmapped once all its segments are consumed
+const MAX_CACHED_MMAPS: usize = 16;
+
+/// batches of the compressed modes are decoded with the codec tagged in each
+/// batch, see `IpcCompressionCodec`.
 #[derive(Debug, Clone, Copy)]
 pub enum IpcReadMode {
     /// for ConvertToNative
@@ -48,13 +66,48 @@ pub enum IpcReadMode {
 pub struct IpcReaderStream {
     schema: SchemaRef,
     mode: IpcReadMode,
-    segments: GlobalRef,
-    reader: Option<RecordBatchReader>,
+    segments: SegmentSource,
+    segments_exhausted: bool,
+    reader: Option<SegmentReader>,
+    prefetched: VecDeque<SegmentReader>,
+    prefetched_bytes: usize,
+    prefetch_num_segments: usize,
+    prefetch_mem_bytes: usize,
+    mmap_enabled: bool,
+    mmaps: VecDeque<(String, Option<Arc<Mmap>>)>, // recently used first
+    io_time: Time,
+    decompress_time: Time,
     baseline_metrics: BaselineMetrics,
     size_counter: Count,
+    trace: IpcReadTrace,
 }
 unsafe impl Send for IpcReaderStream {}
 
+/// Source of segments read by an ipc reader stream
+enum SegmentSource {
+    /// scala iterator of jvm segments (channels or spark FileSegments)
+    Jvm(GlobalRef),
+
+    /// (path, offset, length) of file segments, for reading real segments in
+    /// tests without a jvm
+    #[cfg(test)]
+    Files(VecDeque<(String, u64, u64)>),
+}
+
+/// Reader of a fetched segment
+enum SegmentReader {
+    /// segment decoded on reading
+    Reader(RecordBatchReader),
+
+    /// file segment decoded on a background task, decoded batches are received
+    /// from a bounded channel. `reserved` is the length of the segment counted
+    /// in the prefetched bytes until it becomes the current segment.
+    Prefetching {
+        receiver: Receiver<Result<RecordBatch>>,
+        reserved: usize,
+    },
+}
+
 impl IpcReaderStream {
     pub fn new(
         schema: SchemaRef,
@@ -62,49 +115,386 @@ impl IpcReaderStream {
         mode: IpcReadMode,
         baseline_metrics: BaselineMetrics,
         size_counter: Count,
+        ipc_provider_resource_id: String,
+        partition: usize,
+    ) -> IpcReaderStream {
+        Self::new_with_source(
+            schema,
+            SegmentSource::Jvm(segments),
+            mode,
+            baseline_metrics,
+            size_counter,
+            ipc_provider_resource_id,
+            partition,
+        )
+    }
+
+    /// Creates a stream reading compressed file segments at the given (path,
+    /// offset, length) locations, without iterating jvm segments.
+    #[cfg(test)]
+    fn from_file_segments(
+        schema: SchemaRef,
+        segments: Vec<(String, u64, u64)>,
+        baseline_metrics: BaselineMetrics,
+        size_counter: Count,
+        ipc_provider_resource_id: String,
+        partition: usize,
+    ) -> IpcReaderStream {
+        Self::new_with_source(
+            schema,
+            SegmentSource::Files(segments.into()),
+            IpcReadMode::ChannelAndFileSegment,
+            baseline_metrics,
+            size_counter,
+            ipc_provider_resource_id,
+            partition,
+        )
+    }
+
+    fn new_with_source(
+        schema: SchemaRef,
+        segments: SegmentSource,
+        mode: IpcReadMode,
+        baseline_metrics: BaselineMetrics,
+        size_counter: Count,
+        ipc_provider_resource_id: String,
+        partition: usize,
     ) -> IpcReaderStream {
         IpcReaderStream {
             schema,
             mode,
             segments,
+            segments_exhausted: false,
             reader: None,
+            prefetched: VecDeque::new(),
+            prefetched_bytes: 0,
+            prefetch_num_segments: 0,
+            prefetch_mem_bytes: 0,
+            mmap_enabled: false,
+            mmaps: VecDeque::new(),
+            io_time: Time::new(),
+            decompress_time: Time::new(),
             baseline_metrics,
             size_counter,
+            trace: IpcReadTrace::new(ipc_provider_resource_id, partition),
+        }
+    }
+
+    /// Prefetches up to `num_segments` file segments ahead of the current one in
+    /// ChannelAndFileSegment mode. prefetched segments are read and decoded on
+    /// background tasks, each buffering at most a few decoded batches. no more
+    /// segments are prefetched once the total length of prefetched segments not
+    /// yet being read exceeds `mem_bytes`. channel segments are still read
+    /// sequentially on the current thread.
+    pub fn with_prefetch(mut self, num_segments: usize, mem_bytes: usize) -> Self {
+        self.prefetch_num_segments = num_segments;
+        self.prefetch_mem_bytes = mem_bytes;
+        self
+    }
+
+    /// Reads file segments from memory-mapped shuffle data files instead of
+    /// seeking and reading each segment. mappings of recently read data files
+    /// are cached and shared by their segments read in this stream. falls back
+    /// to normal reading if a file cannot be mapped.
+    pub fn with_mmap(mut self, enabled: bool) -> Self {
+        self.mmap_enabled = enabled;
+        self
+    }
+
+    /// Records time spent reading segments as `io_time`, and the remaining time
+    /// of reading batches as `decompress_time`.
+    pub fn with_io_metrics(mut self, io_time: Time, decompress_time: Time) -> Self {
+        self.io_time = io_time;
+        self.decompress_time = decompress_time;
+        self
+    }
+
+    /// Reads the stream on a blocking thread, since iterating segments and
+    /// reading channels block on jvm io. decoded batches are sent through a
+    /// bounded channel, on which the returned stream awaits without blocking
+    /// async workers.
+    pub fn into_blocking_read(self) -> SendableRecordBatchStream {
+        let schema = self.schema.clone();
+        let stream = futures::stream::once(async move {
+            let receiver =
+                spawn_jni_blocking_producer(BLOCKING_READ_CHANNEL_CAPACITY, move |sender| {
+                    let mut stream = Box::pin(self);
+                    while let Some(batch) = futures::executor::block_on(stream.next()) {
+                        if sender.blocking_send(batch).is_err() {
+                            break; // receiver is dropped
+                        }
+                    }
+                    Ok(())
+                });
+            futures::stream::unfold(receiver, |mut receiver| async move {
+                receiver.recv().await.map(|batch| (batch, receiver))
+            })
+        })
+        .flatten();
+        Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+    }
+
+    /// Returns the mapped data file, or None if mmap is disabled or failed
+    fn get_mmap(&mut self, path: &str) -> Option<Arc<Mmap>> {
+        if !self.mmap_enabled {
+            return None;
+        }
+        let cached_idx = self.mmaps.iter().position(|(cached, _)| cached == path);
+        let cached = match cached_idx {
+            Some(idx) => self.mmaps.remove(idx).unwrap(),
+            None => {
+                let mmap = mmap_file(path)
+                    .map_err(|err| {
+                        log::warn!("ipc_reader: cannot mmap {path}, fallback to reading: {err}")
+                    })
+                    .ok()
+                    .map(Arc::new);
+                (path.to_string(), mmap)
+            }
+        };
+        let mmap = cached.1.clone();
+        self.mmaps.push_front(cached);
+        self.mmaps.truncate(MAX_CACHED_MMAPS);
+        mmap
+    }
+
+    fn open_file_segment(
+        &mut self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Box<dyn Read + Send>> {
+        if let Some(mmap) = self.get_mmap(path) {
+            if offset + length <= mmap.len() as u64 {
+                return Ok(Box::new(Cursor::new(MmapSegment {
+                    mmap,
+                    offset: offset as usize,
+                    length: length as usize,
+                })));
+            }
         }
+        Ok(Box::new(open_file_segment(path, offset, length)?))
     }
 
     fn next_segment(&mut self) -> Result<bool> {
-        let has_next = jni_call!(
-            ScalaIterator(self.segments.as_obj()).hasNext() -> jboolean
-        )?;
-        if has_next != JNI_TRUE {
-            self.reader = None;
-            return Ok(false);
+        let fetch_span = self.trace.fetch_segment_span();
+        let _entered = fetch_span.enter();
+
+        if self.reader.is_some() {
+            self.trace.on_segment_finished();
         }
-        let segment = jni_call!(
-            ScalaIterator(self.segments.as_obj()).next() -> JObject
-        )?;
+        self.fill_prefetched()?;
 
-        let schema = self.schema.clone();
-        self.reader = Some(match self.mode {
-            IpcReadMode::ChannelUncompressed => {
-                get_channel_reader(Some(schema), segment.as_obj(), false)?
+        let reader = match self.prefetched.pop_front() {
+            Some(reader) => {
+                if let SegmentReader::Prefetching { reserved, .. } = &reader {
+                    self.prefetched_bytes -= reserved;
+                }
+                reader
             }
-            IpcReadMode::Channel => get_channel_reader(Some(schema), segment.as_obj(), true)?,
+            None => {
+                self.reader = None;
+                self.trace.on_all_segments_finished();
+                return Ok(false);
+            }
+        };
+        self.reader = Some(reader);
+        self.trace.on_segment_started();
+
+        // keep following segments prefetching while reading the current one
+        if self.prefetch_num_segments > 0 {
+            self.fill_prefetched()?;
+        }
+        Ok(true)
+    }
+
+    fn fill_prefetched(&mut self) -> Result<()> {
+        let max_prefetched = self.prefetch_num_segments.max(1);
+
+        while !self.segments_exhausted
+            && self.prefetched.len() < max_prefetched
+            && (self.prefetched.is_empty() || self.prefetched_bytes < self.prefetch_mem_bytes)
+        {
+            // channel segments are not prefetched, stop at the first one
+            if let Some(SegmentReader::Reader(_)) = self.prefetched.back() {
+                break;
+            }
+
+            let (path, offset, length) = match &mut self.segments {
+                #[cfg(test)]
+                SegmentSource::Files(files) => match files.pop_front() {
+                    Some(location) => location,
+                    None => {
+                        self.segments_exhausted = true;
+                        break;
+                    }
+                },
+                SegmentSource::Jvm(segments) => {
+                    let has_next = jni_call!(
+                        ScalaIterator(segments.as_obj()).hasNext() -> jboolean
+                    )?;
+                    if has_next != JNI_TRUE {
+                        self.segments_exhausted = true;
+                        break;
+                    }
+                    let segment = jni_call!(
+                        ScalaIterator(segments.as_obj()).next() -> JObject
+                    )?;
+                    match self.jvm_segment_reader(segment.as_obj())? {
+                        Some(reader) => {
+                            self.prefetched.push_back(reader);
+                            continue;
+                        }
+                        None => get_file_segment_location(segment.as_obj())?,
+                    }
+                }
+            };
+            let reader = self.file_segment_reader(&path, offset, length)?;
+            self.prefetched.push_back(reader);
+        }
+        Ok(())
+    }
+
+    /// Returns the reader of a jvm segment, or None if it is a spark FileSegment
+    /// which is read by its location
+    fn jvm_segment_reader(&self, segment: JObject) -> Result<Option<SegmentReader>> {
+        let schema = self.schema.clone();
+        let io_time = self.io_time.clone();
+        let decompress_time = self.decompress_time.clone();
+        let compressed = match self.mode {
+            IpcReadMode::ChannelUncompressed => false,
+            IpcReadMode::Channel => true,
             IpcReadMode::ChannelAndFileSegment => {
-                let segment_class = jni_get_object_class!(segment.as_obj())?;
+                let segment_class = jni_get_object_class!(segment)?;
                 let segment_classname_obj =
                     jni_call!(Class(segment_class.as_obj()).getName() -> JObject)?;
                 let segment_classname = jni_get_string!(segment_classname_obj.as_obj().into())?;
-
                 if segment_classname == "org.apache.spark.storage.FileSegment" {
-                    get_file_segment_reader(Some(schema), segment.as_obj())?
-                } else {
-                    get_channel_reader(Some(schema), segment.as_obj(), true)?
+                    return Ok(None);
                 }
+                true
             }
+        };
+        Ok(Some(SegmentReader::Reader(
+            get_channel_reader(Some(schema), segment, compressed)?
+                .with_io_metrics(io_time, decompress_time),
+        )))
+    }
+
+    /// Returns the reader of a file segment, which is decoded on a background
+    /// task if prefetching is enabled. the segment length is counted in the
+    /// prefetched bytes before the task is spawned.
+    fn file_segment_reader(
+        &mut self,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<SegmentReader> {
+        let schema = self.schema.clone();
+        let io_time = self.io_time.clone();
+        let decompress_time = self.decompress_time.clone();
+        let input = self.open_file_segment(path, offset, length)?;
+        if self.prefetch_num_segments == 0 {
+            return Ok(SegmentReader::Reader(
+                RecordBatchReader::new(input, Some(schema), true)
+                    .with_io_metrics(io_time, decompress_time),
+            ));
+        }
+
+        let reserved = length as usize;
+        self.prefetched_bytes += reserved;
+        let receiver = spawn_jni_blocking_producer(BLOCKING_READ_CHANNEL_CAPACITY, move |sender| {
+            let mut reader = RecordBatchReader::new(input, Some(schema), true)
+                .with_io_metrics(io_time, decompress_time);
+            while let Some(batch) = reader.next_batch()? {
+                if sender.blocking_send(Ok(batch)).is_err() {
+                    break; // stream is dropped
+                }
+            }
+            Ok(())
         });
-        Ok(true)
+        Ok(SegmentReader::Prefetching { receiver, reserved })
+    }
+}
+
+/// Tracing context of an ipc reader stream. all spans and events are tagged with
+/// the provider resource id, partition and segment index.
+struct IpcReadTrace {
+    resource_id: String,
+    partition: usize,
+    segment_idx: usize,
+    segment_num_batches: usize,
+    segment_num_rows: usize,
+}
+
+impl IpcReadTrace {
+    fn new(resource_id: String, partition: usize) -> Self {
+        Self {
+            resource_id,
+            partition,
+            segment_idx: 0,
+            segment_num_batches: 0,
+            segment_num_rows: 0,
+        }
+    }
+
+    /// Span of fetching the next segment, `segment_idx` is the index of the
+    /// segment to be fetched
+    fn fetch_segment_span(&self) -> Span {
+        debug_span!(
+            "ipc_reader.fetch_segment",
+            resource_id = %self.resource_id,
+            partition = self.partition,
+            segment_idx = self.segment_idx
+        )
+    }
+
+    /// Span of reading a batch from the current segment, including decompression
+    /// and decoding
+    fn read_batch_span(&self) -> Span {
+        debug_span!(
+            "ipc_reader.read_batch",
+            resource_id = %self.resource_id,
+            partition = self.partition,
+            segment_idx = self.segment_idx.saturating_sub(1)
+        )
+    }
+
+    fn on_segment_started(&mut self) {
+        debug!(
+            resource_id = %self.resource_id,
+            partition = self.partition,
+            segment_idx = self.segment_idx,
+            "ipc_reader: started reading segment"
+        );
+        self.segment_idx += 1;
+        self.segment_num_batches = 0;
+        self.segment_num_rows = 0;
+    }
+
+    fn on_batch(&mut self, num_rows: usize) {
+        self.segment_num_batches += 1;
+        self.segment_num_rows += num_rows;
+    }
+
+    fn on_segment_finished(&self) {
+        debug!(
+            resource_id = %self.resource_id,
+            partition = self.partition,
+            segment_idx = self.segment_idx.saturating_sub(1),
+            num_batches = self.segment_num_batches,
+            num_rows = self.segment_num_rows,
+            "ipc_reader: finished reading segment"
+        );
+    }
+
+    fn on_all_segments_finished(&self) {
+        debug!(
+            resource_id = %self.resource_id,
+            partition = self.partition,
+            num_segments = self.segment_idx,
+            "ipc_reader: finished reading all segments"
+        );
     }
 }
 
@@ -114,10 +504,11 @@ pub fn get_channel_reader(
     compressed: bool,
 ) -> Result<RecordBatchReader> {
     let global_ref = jni_new_global_ref!(channel)?;
-    let channel_reader = ReadableByteChannelReader::new(global_ref);
+    let channel_reader = ReadableByteChannelReader::try_new(global_ref)?;
 
+    // channel reader is already buffered
     Ok(RecordBatchReader::new(
-        Box::new(BufReader::with_capacity(65536, channel_reader)),
+        Box::new(channel_reader),
         schema,
         compressed,
     ))
@@ -127,61 +518,111 @@ pub fn get_file_segment_reader(
     schema: Option<SchemaRef>,
     file_segment: JObject,
 ) -> Result<RecordBatchReader> {
+    let (path, offset, length) = get_file_segment_location(file_segment)?;
+    Ok(RecordBatchReader::new(
+        Box::new(open_file_segment(&path, offset, length)?),
+        schema,
+        true,
+    ))
+}
+
+/// Returns (path, offset, length) of a spark FileSegment
+fn get_file_segment_location(file_segment: JObject) -> Result<(String, u64, u64)> {
     let file = jni_call!(SparkFileSegment(file_segment).file() -> JObject)?;
     let path = jni_call!(JavaFile(file.as_obj()).getPath() -> JObject)?;
     let path = jni_get_string!(path.as_obj().into())?;
     let offset = jni_call!(SparkFileSegment(file_segment).offset() -> jlong)?;
     let length = jni_call!(SparkFileSegment(file_segment).length() -> jlong)?;
+    Ok((path, offset as u64, length as u64))
+}
 
+fn open_file_segment(path: &str, offset: u64, length: u64) -> Result<impl Read> {
     let mut file = File::open(path)?;
-    file.seek(SeekFrom::Start(offset as u64))?;
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(BufReader::with_capacity(65536, file.take(length)))
+}
 
-    Ok(RecordBatchReader::new(
-        Box::new(file.take(length as u64)),
-        schema,
-        true,
-    ))
+fn mmap_file(path: &str) -> Result<Mmap> {
+    let file = File::open(path)?;
+    // safety: shuffle data files are immutable once committed
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+/// A file segment in a memory-mapped shuffle data file
+struct MmapSegment {
+    mmap: Arc<Mmap>,
+    offset: usize,
+    length: usize,
+}
+
+impl AsRef<[u8]> for MmapSegment {
+    fn as_ref(&self) -> &[u8] {
+        &self.mmap[self.offset..][..self.length]
+    }
 }
 
 impl Stream for IpcReaderStream {
     type Item = Result<RecordBatch>;
 
-    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let elapsed_compute = self.baseline_metrics.elapsed_compute().clone();
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let elapsed_compute = this.baseline_metrics.elapsed_compute().clone();
         let _timer = elapsed_compute.timer();
 
-        if let Some(reader) = &mut self.reader {
-            if let Some(batch) = reader.next_batch()? {
-                self.size_counter.add(batch.get_array_memory_size());
-                return self
+        loop {
+            let batch = match &mut this.reader {
+                Some(SegmentReader::Reader(reader)) => {
+                    let read_span = this.trace.read_batch_span();
+                    read_span.in_scope(|| reader.next_batch())?
+                }
+                Some(SegmentReader::Prefetching { receiver, .. }) => match receiver.poll_recv(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(batch) => batch.transpose()?,
+                },
+                None => None,
+            };
+
+            if let Some(batch) = batch {
+                this.trace.on_batch(batch.num_rows());
+                this.size_counter.add(batch.get_array_memory_size());
+                return this
                     .baseline_metrics
                     .record_poll(Poll::Ready(Some(Ok(batch))));
             }
-        }
 
-        // current arrow file reader reaches EOF, try next ipc
-        if self.next_segment()? {
-            return self.poll_next(_cx);
+            // current segment reaches EOF, try next segment
+            if !this.next_segment()? {
+                return Poll::Ready(None);
+            }
         }
-        Poll::Ready(None)
     }
 }
+
 impl RecordBatchStream for IpcReaderStream {
     fn schema(&self) -> SchemaRef {
         self.schema.clone()
     }
 }
 
+/// A buffered reader of a java ReadableByteChannel. bytes are read into a
+/// pooled jni buffer, so that each read from the channel costs only two jni
+/// calls and small reads are served from the buffer.
 pub struct ReadableByteChannelReader {
     channel: GlobalRef,
+    buffer: PooledJniBuffer,
+    buffer_pos: usize,
+    buffer_len: usize,
     closed: bool,
 }
 impl ReadableByteChannelReader {
-    pub fn new(channel: GlobalRef) -> Self {
-        Self {
+    pub fn try_new(channel: GlobalRef) -> Result<Self> {
+        Ok(Self {
             channel,
+            buffer: acquire_jni_buffer()?,
+            buffer_pos: 0,
+            buffer_len: 0,
             closed: false,
-        }
+        })
     }
 
     pub fn close(&mut self) -> Result<()> {
@@ -193,26 +634,36 @@ impl ReadableByteChannelReader {
     }
 
     fn read_impl(&mut self, buf: &mut [u8]) -> Result<usize> {
-        if self.closed {
+        if self.buffer_pos == self.buffer_len && !self.fill_buffer()? {
             return Ok(0);
         }
-        let buf = jni_new_direct_byte_buffer!(buf)?;
+        let len = buf.len().min(self.buffer_len - self.buffer_pos);
+        buf[..len].copy_from_slice(&self.buffer.data()[self.buffer_pos..][..len]);
+        self.buffer_pos += len;
+        Ok(len)
+    }
 
-        while {
-            let has_remaining = jni_call!(JavaBuffer(buf.as_obj()).hasRemaining() -> jboolean)?;
-            has_remaining == JNI_TRUE
-        } {
+    /// Reads the next bytes from the channel into buffer, returns false if
+    /// the channel reaches EOF
+    fn fill_buffer(&mut self) -> Result<bool> {
+        self.buffer_pos = 0;
+        self.buffer_len = 0;
+        while !self.closed {
+            self.buffer.reset(self.buffer.capacity())?;
             let read_bytes = jni_call!(JavaReadableByteChannel(self.channel.as_obj())
-                .read(buf.as_obj()) -> jint
+                .read(self.buffer.as_obj()) -> jint
             )?;
-
             if read_bytes < 0 {
                 self.close()?;
                 break;
             }
+            if read_bytes > 0 {
+                self.buffer_len = read_bytes as usize;
+                self.buffer.read_from_jvm(self.buffer_len)?;
+                return Ok(true);
+            }
         }
-        let position = jni_call!(JavaBuffer(buf.as_obj()).position() -> jint)?;
-        Ok(position as usize)
+        Ok(false)
     }
 }
 
@@ -233,6 +684,14 @@ pub struct RecordBatchReader {
     input: Box<dyn Read>,
     schema: Option<SchemaRef>,
     compress: bool,
+    io_metrics: Option<ReadIoMetrics>,
+}
+
+struct ReadIoMetrics {
+    input_io_time: Time,
+    reported_io_nanos: usize,
+    io_time: Time,
+    decompress_time: Time,
 }
 
 impl RecordBatchReader {
@@ -241,10 +700,261 @@ impl RecordBatchReader {
             input,
             schema,
             compress,
+            io_metrics: None,
+        }
+    }
+
+    /// Records time spent reading the input as `io_time`, and the remaining
+    /// time of reading batches as `decompress_time`.
+    pub fn with_io_metrics(self, io_time: Time, decompress_time: Time) -> Self {
+        let input_io_time = Time::new();
+        Self {
+            input: Box::new(TimedReader::new(self.input, input_io_time.clone())),
+            io_metrics: Some(ReadIoMetrics {
+                input_io_time,
+                reported_io_nanos: 0,
+                io_time,
+                decompress_time,
+            }),
+            ..self
         }
     }
 
     pub fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
-        read_one_batch(&mut self.input, self.schema.clone(), self.compress)
+        let start_time = Instant::now();
+        let batch = read_one_batch(&mut self.input, self.schema.clone(), self.compress)?;
+
+        if let Some(io_metrics) = &mut self.io_metrics {
+            let io_nanos = io_metrics.input_io_time.value() - io_metrics.reported_io_nanos;
+            let io_duration = Duration::from_nanos(io_nanos as u64);
+            io_metrics.reported_io_nanos += io_nanos;
+            io_metrics.io_time.add_duration(io_duration);
+            io_metrics
+                .decompress_time
+                .add_duration(start_time.elapsed().saturating_sub(io_duration));
+        }
+        Ok(batch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::io::{read_one_batch, write_one_batch};
+    use crate::streams::ipc_stream::{mmap_file, IpcReaderStream, MmapSegment};
+    use arrow::array::{ArrayRef, Int32Array};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::physical_plan::metrics::{BaselineMetrics, Count, ExecutionPlanMetricsSet};
+    use futures::TryStreamExt;
+    use std::fmt::Debug;
+    use std::io::{Cursor, Seek};
+    use std::sync::atomic::{AtomicU64, Ordering::SeqCst};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// collects all spans and events as strings
+    #[derive(Default)]
+    struct TraceCollector {
+        logs: Arc<Mutex<Vec<String>>>,
+        next_span_id: AtomicU64,
+    }
+
+    #[derive(Default)]
+    struct FieldsVisitor {
+        message: String,
+        fields: Vec<String>,
+    }
+
+    impl Visit for FieldsVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() == "message" {
+                self.message = format!("{:?}", value);
+            } else {
+                self.fields.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+    }
+
+    impl Subscriber for TraceCollector {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut visitor = FieldsVisitor::default();
+            span.record(&mut visitor);
+            self.logs.lock().unwrap().push(format!(
+                "span {} {}",
+                span.metadata().name(),
+                visitor.fields.join(" "),
+            ));
+            Id::from_u64(self.next_span_id.fetch_add(1, SeqCst) + 1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut visitor = FieldsVisitor::default();
+            event.record(&mut visitor);
+            self.logs.lock().unwrap().push(format!(
+                "event {} {}",
+                visitor.message,
+                visitor.fields.join(" "),
+            ));
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_mmap_segment() -> Result<(), Box<dyn std::error::Error>> {
+        let batches = (0..3)
+            .map(|i| {
+                let array: ArrayRef = Arc::new(Int32Array::from(vec![i, i + 1, i + 2]));
+                RecordBatch::try_from_iter(vec![("a", array)])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // write three batches and read the segment of the last two
+        let mut file = tempfile::NamedTempFile::new()?;
+        write_one_batch(&batches[0], &mut file, true, None)?;
+        let offset = file.stream_position()?;
+        write_one_batch(&batches[1], &mut file, true, None)?;
+        write_one_batch(&batches[2], &mut file, true, None)?;
+        let length = file.stream_position()? - offset;
+
+        let mut input = Cursor::new(MmapSegment {
+            mmap: Arc::new(mmap_file(file.path().to_str().unwrap())?),
+            offset: offset as usize,
+            length: length as usize,
+        });
+        let schema = batches[0].schema();
+        assert_eq!(
+            read_one_batch(&mut input, Some(schema.clone()), true)?.as_ref(),
+            Some(&batches[1])
+        );
+        assert_eq!(
+            read_one_batch(&mut input, Some(schema.clone()), true)?.as_ref(),
+            Some(&batches[2])
+        );
+        assert_eq!(read_one_batch(&mut input, Some(schema), true)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefetch_segments() -> Result<(), Box<dyn std::error::Error>> {
+        let batches = (0..4)
+            .map(|i| {
+                let array: ArrayRef = Arc::new(Int32Array::from_iter_values(i * 10..i * 10 + 10));
+                RecordBatch::try_from_iter(vec![("a", array)])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let schema = batches[0].schema();
+
+        // one segment per batch
+        let mut file = tempfile::NamedTempFile::new()?;
+        let path = file.path().to_str().unwrap().to_string();
+        let mut segments = vec![];
+        for batch in &batches {
+            let offset = file.stream_position()?;
+            write_one_batch(batch, &mut file, true, None)?;
+            let length = file.stream_position()? - offset;
+            segments.push((path.clone(), offset, length));
+        }
+        let new_stream = |mem_bytes| {
+            IpcReaderStream::from_file_segments(
+                schema.clone(),
+                segments.clone(),
+                BaselineMetrics::new(&ExecutionPlanMetricsSet::new(), 0),
+                Count::new(),
+                "rid".to_string(),
+                0,
+            )
+            .with_prefetch(3, mem_bytes)
+        };
+        let runtime = tokio::runtime::Builder::new_multi_thread().build()?;
+        let _guard = runtime.enter();
+
+        // segment lengths are counted before decoding, so prefetching stops at
+        // the first segment exceeding the budget
+        let mut stream = new_stream(1);
+        stream.fill_prefetched()?;
+        assert_eq!(stream.prefetched.len(), 1);
+        assert_eq!(stream.prefetched_bytes, segments[0].2 as usize);
+        assert_eq!(runtime.block_on(stream.try_collect::<Vec<_>>())?, batches);
+
+        let mut stream = new_stream(1 << 20);
+        stream.fill_prefetched()?;
+        assert_eq!(stream.prefetched.len(), 3);
+        assert_eq!(
+            stream.prefetched_bytes,
+            segments[..3].iter().map(|s| s.2 as usize).sum::<usize>()
+        );
+        assert_eq!(runtime.block_on(stream.try_collect::<Vec<_>>())?, batches);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ipc_read_trace() -> Result<(), Box<dyn std::error::Error>> {
+        let batches = [10, 20, 5]
+            .into_iter()
+            .map(|num_rows| {
+                let array: ArrayRef = Arc::new(Int32Array::from_iter_values(0..num_rows));
+                RecordBatch::try_from_iter(vec![("a", array)])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let schema = batches[0].schema();
+
+        // two segments with one and two batches
+        let mut file = tempfile::NamedTempFile::new()?;
+        write_one_batch(&batches[0], &mut file, true, None)?;
+        let offset = file.stream_position()?;
+        write_one_batch(&batches[1], &mut file, true, None)?;
+        write_one_batch(&batches[2], &mut file, true, None)?;
+        let length = file.stream_position()? - offset;
+        let path = file.path().to_str().unwrap().to_string();
+        let stream = IpcReaderStream::from_file_segments(
+            schema,
+            vec![(path.clone(), 0, offset), (path, offset, length)],
+            BaselineMetrics::new(&ExecutionPlanMetricsSet::new(), 0),
+            Count::new(),
+            "rid".to_string(),
+            3,
+        );
+
+        let collector = TraceCollector::default();
+        let logs = collector.logs.clone();
+        let output = tracing::subscriber::with_default(collector, || {
+            futures::executor::block_on(stream.try_collect::<Vec<_>>())
+        })?;
+        assert_eq!(output, batches);
+
+        // every read of a segment, including the last one reaching EOF, is
+        // traced in a read_batch span
+        let logs = logs.lock().unwrap().clone();
+        assert_eq!(
+            logs,
+            vec![
+                "span ipc_reader.fetch_segment resource_id=rid partition=3 segment_idx=0",
+                "event ipc_reader: started reading segment resource_id=rid partition=3 segment_idx=0",
+                "span ipc_reader.read_batch resource_id=rid partition=3 segment_idx=0",
+                "span ipc_reader.read_batch resource_id=rid partition=3 segment_idx=0",
+                "span ipc_reader.fetch_segment resource_id=rid partition=3 segment_idx=1",
+                "event ipc_reader: finished reading segment resource_id=rid partition=3 segment_idx=0 num_batches=1 num_rows=10",
+                "event ipc_reader: started reading segment resource_id=rid partition=3 segment_idx=1",
+                "span ipc_reader.read_batch resource_id=rid partition=3 segment_idx=1",
+                "span ipc_reader.read_batch resource_id=rid partition=3 segment_idx=1",
+                "span ipc_reader.read_batch resource_id=rid partition=3 segment_idx=1",
+                "span ipc_reader.fetch_segment resource_id=rid partition=3 segment_idx=2",
+                "event ipc_reader: finished reading segment resource_id=rid partition=3 segment_idx=1 num_batches=2 num_rows=25",
+                "event ipc_reader: finished reading all segments resource_id=rid partition=3 num_segments=2",
+            ],
+        );
+        Ok(())
     }
 }
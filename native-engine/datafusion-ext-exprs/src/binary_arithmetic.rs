@@ -0,0 +1,528 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use arrow::array::*;
+use arrow::compute::kernels::arity::try_binary;
+use arrow::compute::kernels::boolean::{and, is_not_null};
+use arrow::compute::kernels::nullif::nullif;
+use arrow::compute::kernels::numeric::{
+    add, add_wrapping, div, mul, mul_wrapping, sub, sub_wrapping,
+};
+use arrow::datatypes::*;
+use arrow::record_batch::RecordBatch;
+use datafusion::common::{DataFusionError, Result};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion::physical_expr::PhysicalExpr;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::{any::Any, sync::Arc};
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq)]
+pub enum BinaryArithmeticOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl std::fmt::Display for BinaryArithmeticOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryArithmeticOp::Add => write!(f, "+"),
+            BinaryArithmeticOp::Subtract => write!(f, "-"),
+            BinaryArithmeticOp::Multiply => write!(f, "*"),
+            BinaryArithmeticOp::Divide => write!(f, "/"),
+        }
+    }
+}
+
+/// binary arithmetic compatible with spark:
+///  - integral overflow wraps, or fails if `fail_on_error` (ansi mode) is set.
+///  - decimal results are computed exactly and rounded (half-up) to the
+///    return type, overflowed values are null or fail in ansi mode.
+///  - division by zero returns null, or fails in ansi mode.
+///
+/// both sides are expected to have the same type, except for decimals whose
+/// precisions/scales may differ from each other and from the return type.
+#[derive(Debug, Hash)]
+pub struct BinaryArithmeticExpr {
+    op: BinaryArithmeticOp,
+    left: Arc<dyn PhysicalExpr>,
+    right: Arc<dyn PhysicalExpr>,
+    return_type: DataType,
+    fail_on_error: bool,
+}
+
+impl BinaryArithmeticExpr {
+    pub fn new(
+        op: BinaryArithmeticOp,
+        left: Arc<dyn PhysicalExpr>,
+        right: Arc<dyn PhysicalExpr>,
+        return_type: DataType,
+        fail_on_error: bool,
+    ) -> Self {
+        Self {
+            op,
+            left,
+            right,
+            return_type,
+            fail_on_error,
+        }
+    }
+
+    fn eval_primitive<T: ArrowNumericType>(
+        &self,
+        left: &dyn Array,
+        right: &dyn Array,
+        is_integral: bool,
+    ) -> Result<ArrayRef> {
+        let checked = is_integral && self.fail_on_error;
+        let output = match self.op {
+            BinaryArithmeticOp::Add if checked => add(&left, &right),
+            BinaryArithmeticOp::Add => add_wrapping(&left, &right),
+            BinaryArithmeticOp::Subtract if checked => sub(&left, &right),
+            BinaryArithmeticOp::Subtract => sub_wrapping(&left, &right),
+            BinaryArithmeticOp::Multiply if checked => mul(&left, &right),
+            BinaryArithmeticOp::Multiply => mul_wrapping(&left, &right),
+            BinaryArithmeticOp::Divide => {
+                // zero divisors are replaced with nulls, so that division by zero
+                // returns null. in ansi mode it fails unless the dividend is null
+                let zeros =
+                    BooleanArray::from_unary(as_primitive_array::<T>(right), |r| r.is_zero());
+                if self.fail_on_error && and(&zeros, &is_not_null(left)?)?.true_count() > 0 {
+                    return Err(self.divide_by_zero_error());
+                }
+                let right = nullif(right, &zeros)?;
+                if is_integral && !self.fail_on_error {
+                    // overflow (MIN / -1) wraps like java
+                    try_binary::<_, _, _, T>(
+                        as_primitive_array::<T>(left),
+                        as_primitive_array::<T>(&right),
+                        |l, r| Ok(l.div_wrapping(r)),
+                    )
+                    .map(|output| Arc::new(output) as ArrayRef)
+                } else {
+                    div(&left, &right)
+                }
+            }
+        };
+        output.map_err(|err| {
+            if !checked {
+                return err.into();
+            }
+            DataFusionError::Execution(format!(
+                "[ARITHMETIC_OVERFLOW] {} overflow. If necessary set \"spark.sql.ansi.enabled\" \
+                    to \"false\" to bypass this error.",
+                spark_integral_type_name(&self.return_type),
+            ))
+        })
+    }
+
+    fn eval_decimal(&self, left: &dyn Array, right: &dyn Array) -> Result<ArrayRef> {
+        let (precision, scale) = match &self.return_type {
+            &DataType::Decimal128(precision, scale) => (precision, scale),
+            other => {
+                return Err(DataFusionError::Execution(format!(
+                    "decimal arithmetic: unsupported return type: {other}"
+                )))
+            }
+        };
+        let left = as_primitive_array::<Decimal128Type>(left);
+        let right = as_primitive_array::<Decimal128Type>(right);
+        let (s1, s2, s) = (left.scale() as i32, right.scale() as i32, scale as i32);
+        let max_unscaled = pow10(precision as i32).expect("max unscaled value out of range");
+
+        let output = left
+            .iter()
+            .zip(right.iter())
+            .map(|(l, r)| match (l, r) {
+                (Some(l), Some(r)) => {
+                    let (l, r) = (i256::from_i128(l), i256::from_i128(r));
+                    let result = match self.op {
+                        BinaryArithmeticOp::Add | BinaryArithmeticOp::Subtract => {
+                            let common_scale = s1.max(s2);
+                            rescale(l, s1, common_scale)
+                                .zip(rescale(r, s2, common_scale))
+                                .and_then(|(l, r)| match self.op {
+                                    BinaryArithmeticOp::Add => l.checked_add(r),
+                                    _ => l.checked_sub(r),
+                                })
+                                .and_then(|v| rescale(v, common_scale, s))
+                        }
+                        BinaryArithmeticOp::Multiply => {
+                            l.checked_mul(r).and_then(|v| rescale(v, s1 + s2, s))
+                        }
+                        BinaryArithmeticOp::Divide => {
+                            if r == i256::ZERO {
+                                return self.divide_by_zero();
+                            }
+                            // l / r at scale s = l * 10^(s + s2 - s1) / r
+                            let exp = s + s2 - s1;
+                            if exp >= 0 {
+                                pow10(exp)
+                                    .and_then(|p| l.checked_mul(p))
+                                    .and_then(|l| div_round_half_up(l, r))
+                            } else {
+                                pow10(-exp)
+                                    .and_then(|p| r.checked_mul(p))
+                                    .and_then(|r| div_round_half_up(l, r))
+                            }
+                        }
+                    };
+                    match result.filter(|v| abs(*v).map(|v| v < max_unscaled).unwrap_or(false)) {
+                        Some(v) => Ok(v.to_i128()),
+                        None if self.fail_on_error => Err(DataFusionError::Execution(format!(
+                            "[NUMERIC_VALUE_OUT_OF_RANGE] the result of decimal arithmetic \
+                                cannot be represented as Decimal({precision}, {scale}). If \
+                                necessary set \"spark.sql.ansi.enabled\" to \"false\" to bypass \
+                                this error.",
+                        ))),
+                        None => Ok(None),
+                    }
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<Decimal128Array>>()?;
+        Ok(Arc::new(output.with_precision_and_scale(precision, scale)?))
+    }
+
+    fn divide_by_zero<N>(&self) -> Result<Option<N>> {
+        if self.fail_on_error {
+            return Err(self.divide_by_zero_error());
+        }
+        Ok(None)
+    }
+
+    fn divide_by_zero_error(&self) -> DataFusionError {
+        DataFusionError::Execution(
+            "[DIVIDE_BY_ZERO] Division by zero. Use `try_divide` to tolerate divisor being 0 and \
+                return NULL instead. If necessary set \"spark.sql.ansi.enabled\" to \"false\" to \
+                bypass this error."
+                .to_string(),
+        )
+    }
+}
+
+impl std::fmt::Display for BinaryArithmeticExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
+impl PartialEq<dyn Any> for BinaryArithmeticExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.op == x.op
+                    && self.left.eq(&x.left)
+                    && self.right.eq(&x.right)
+                    && self.return_type == x.return_type
+                    && self.fail_on_error == x.fail_on_error
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for BinaryArithmeticExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(self.return_type.clone())
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        // division by zero and decimal overflow produce nulls in legacy mode
+        if !self.fail_on_error
+            && (self.op == BinaryArithmeticOp::Divide
+                || matches!(self.return_type, DataType::Decimal128(..)))
+        {
+            return Ok(true);
+        }
+        Ok(self.left.nullable(input_schema)? || self.right.nullable(input_schema)?)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let left = self.left.evaluate(batch)?.into_array(batch.num_rows());
+        let right = self.right.evaluate(batch)?.into_array(batch.num_rows());
+
+        if let DataType::Decimal128(..) = &self.return_type {
+            return Ok(ColumnarValue::Array(self.eval_decimal(&left, &right)?));
+        }
+        if left.data_type() != &self.return_type || right.data_type() != &self.return_type {
+            return Err(DataFusionError::Execution(format!(
+                "binary arithmetic: input types {} {} {} do not match return type {}",
+                left.data_type(),
+                self.op,
+                right.data_type(),
+                self.return_type,
+            )));
+        }
+        Ok(ColumnarValue::Array(match &self.return_type {
+            DataType::Int8 => self.eval_primitive::<Int8Type>(&left, &right, true)?,
+            DataType::Int16 => self.eval_primitive::<Int16Type>(&left, &right, true)?,
+            DataType::Int32 => self.eval_primitive::<Int32Type>(&left, &right, true)?,
+            DataType::Int64 => self.eval_primitive::<Int64Type>(&left, &right, true)?,
+            DataType::Float32 => self.eval_primitive::<Float32Type>(&left, &right, false)?,
+            DataType::Float64 => self.eval_primitive::<Float64Type>(&left, &right, false)?,
+            other => {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "binary arithmetic: unsupported data type: {other}"
+                )))
+            }
+        }))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(Self::new(
+            self.op,
+            children[0].clone(),
+            children[1].clone(),
+            self.return_type.clone(),
+            self.fail_on_error,
+        )))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+fn spark_integral_type_name(dt: &DataType) -> &'static str {
+    match dt {
+        DataType::Int8 => "tinyint",
+        DataType::Int16 => "smallint",
+        DataType::Int32 => "integer",
+        _ => "long",
+    }
+}
+
+fn pow10(exp: i32) -> Option<i256> {
+    i256::from_i128(10).checked_pow(exp as u32)
+}
+
+fn abs(v: i256) -> Option<i256> {
+    if v.is_negative() {
+        v.checked_neg()
+    } else {
+        Some(v)
+    }
+}
+
+fn rescale(v: i256, from_scale: i32, to_scale: i32) -> Option<i256> {
+    if to_scale >= from_scale {
+        v.checked_mul(pow10(to_scale - from_scale)?)
+    } else {
+        div_round_half_up(v, pow10(from_scale - to_scale)?)
+    }
+}
+
+fn div_round_half_up(n: i256, d: i256) -> Option<i256> {
+    let quotient = n.checked_div(d)?;
+    let remainder = n.checked_rem(d)?;
+    if abs(remainder)?.checked_mul(i256::from_i128(2))? >= abs(d)? {
+        if n.is_negative() == d.is_negative() {
+            return quotient.checked_add(i256::ONE);
+        }
+        return quotient.checked_sub(i256::ONE);
+    }
+    Some(quotient)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::binary_arithmetic::{BinaryArithmeticExpr, BinaryArithmeticOp};
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::PhysicalExpr;
+    use std::sync::Arc;
+
+    fn arith(op: BinaryArithmeticOp, return_type: DataType, ansi: bool) -> BinaryArithmeticExpr {
+        BinaryArithmeticExpr::new(
+            op,
+            Arc::new(Column::new("a", 0)),
+            Arc::new(Column::new("b", 1)),
+            return_type,
+            ansi,
+        )
+    }
+
+    #[test]
+    fn test_integral_overflow() -> Result<(), Box<dyn std::error::Error>> {
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![
+            (
+                "a",
+                Arc::new(Int32Array::from(vec![Some(i32::MAX), Some(1), None])) as ArrayRef,
+                true,
+            ),
+            (
+                "b",
+                Arc::new(Int32Array::from(vec![Some(1), Some(0), Some(1)])) as ArrayRef,
+                true,
+            ),
+        ])?;
+
+        // legacy: overflow wraps and division by zero returns null
+        let expr = arith(BinaryArithmeticOp::Add, DataType::Int32, false);
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&output_array),
+            &Int32Array::from(vec![Some(i32::MIN), Some(1), None])
+        );
+        let expr = arith(BinaryArithmeticOp::Divide, DataType::Int32, false);
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&output_array),
+            &Int32Array::from(vec![Some(i32::MAX), None, None])
+        );
+
+        // ansi: both fail
+        let expr = arith(BinaryArithmeticOp::Add, DataType::Int32, true);
+        assert!(expr.evaluate(&input_batch).is_err());
+        let expr = arith(BinaryArithmeticOp::Divide, DataType::Int32, true);
+        assert!(expr.evaluate(&input_batch).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_divide() -> Result<(), Box<dyn std::error::Error>> {
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![
+            (
+                "a",
+                Arc::new(Int32Array::from(vec![None, Some(i32::MIN), Some(5)])) as ArrayRef,
+                true,
+            ),
+            (
+                "b",
+                Arc::new(Int32Array::from(vec![Some(0), Some(-1), Some(2)])) as ArrayRef,
+                true,
+            ),
+        ])?;
+
+        // legacy: overflow wraps
+        let expr = arith(BinaryArithmeticOp::Divide, DataType::Int32, false);
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&output_array),
+            &Int32Array::from(vec![None, Some(i32::MIN), Some(2)])
+        );
+
+        // ansi: null divided by zero is null, overflow fails
+        let expr = arith(BinaryArithmeticOp::Divide, DataType::Int32, true);
+        let output_array = expr.evaluate(&input_batch.slice(0, 1))?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&output_array),
+            &Int32Array::from(vec![None])
+        );
+        assert!(expr.evaluate(&input_batch).is_err());
+
+        // floating division by positive or negative zero returns null
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![
+            (
+                "a",
+                Arc::new(Float64Array::from(vec![1.0, 1.0, 1.0])) as ArrayRef,
+                true,
+            ),
+            (
+                "b",
+                Arc::new(Float64Array::from(vec![0.0, -0.0, 4.0])) as ArrayRef,
+                true,
+            ),
+        ])?;
+        let expr = arith(BinaryArithmeticOp::Divide, DataType::Float64, false);
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Float64Type>(&output_array),
+            &Float64Array::from(vec![None, None, Some(0.25)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() -> Result<(), Box<dyn std::error::Error>> {
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![
+            (
+                "a",
+                Arc::new(
+                    Decimal128Array::from(vec![Some(1000), Some(-1000), Some(99999)])
+                        .with_precision_and_scale(5, 2)?,
+                ) as ArrayRef,
+                true,
+            ),
+            (
+                "b",
+                Arc::new(
+                    Decimal128Array::from(vec![Some(3), Some(3), Some(0)])
+                        .with_precision_and_scale(3, 1)?,
+                ) as ArrayRef,
+                true,
+            ),
+        ])?;
+
+        // decimal(5,2) + decimal(3,1) => decimal(6,2)
+        let expr = arith(BinaryArithmeticOp::Add, DataType::Decimal128(6, 2), false);
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&output_array),
+            &Decimal128Array::from(vec![Some(1030), Some(-970), Some(99999)])
+                .with_precision_and_scale(6, 2)?
+        );
+
+        // decimal(5,2) * decimal(3,1) => decimal(4,1), rounded half-up
+        let expr = arith(
+            BinaryArithmeticOp::Multiply,
+            DataType::Decimal128(4, 1),
+            false,
+        );
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&output_array),
+            &Decimal128Array::from(vec![Some(30), Some(-30), Some(0)])
+                .with_precision_and_scale(4, 1)?
+        );
+
+        // decimal(5,2) / decimal(3,1) => decimal(11,6), rounded half-up
+        let expr = arith(
+            BinaryArithmeticOp::Divide,
+            DataType::Decimal128(11, 6),
+            false,
+        );
+        let output_array = expr.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&output_array),
+            &Decimal128Array::from(vec![Some(33333333), Some(-33333333), None])
+                .with_precision_and_scale(11, 6)?
+        );
+        let expr = arith(
+            BinaryArithmeticOp::Divide,
+            DataType::Decimal128(11, 6),
+            true,
+        );
+        assert!(expr.evaluate(&input_batch).is_err());
+        Ok(())
+    }
+}
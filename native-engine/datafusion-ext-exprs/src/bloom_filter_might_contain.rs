@@ -0,0 +1,201 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use arrow::array::BooleanArray;
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::RecordBatch;
+use datafusion::common::cast::as_int64_array;
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion::physical_plan::PhysicalExpr;
+use datafusion_ext_commons::spark_bloom_filter::SparkBloomFilter;
+use once_cell::sync::OnceCell;
+use std::any::Any;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Native implementation of spark's `BloomFilterMightContain`.
+///
+/// the bloom filter expr must be evaluated to a scalar (a literal or a scalar
+/// subquery) of the serialized filter, it is deserialized only once.
+pub struct BloomFilterMightContainExpr {
+    bloom_filter_expr: Arc<dyn PhysicalExpr>,
+    value_expr: Arc<dyn PhysicalExpr>,
+    bloom_filter: OnceCell<Option<Arc<SparkBloomFilter>>>,
+}
+
+impl BloomFilterMightContainExpr {
+    pub fn new(
+        bloom_filter_expr: Arc<dyn PhysicalExpr>,
+        value_expr: Arc<dyn PhysicalExpr>,
+    ) -> Self {
+        Self {
+            bloom_filter_expr,
+            value_expr,
+            bloom_filter: OnceCell::new(),
+        }
+    }
+
+    fn get_bloom_filter(&self, batch: &RecordBatch) -> Result<Option<Arc<SparkBloomFilter>>> {
+        let bloom_filter = self.bloom_filter.get_or_try_init(|| {
+            match self.bloom_filter_expr.evaluate(batch)? {
+                ColumnarValue::Scalar(ScalarValue::Binary(Some(serialized))) => Ok(Some(Arc::new(
+                    SparkBloomFilter::read_from(serialized.as_slice())?,
+                ))),
+                ColumnarValue::Scalar(ScalarValue::Binary(None) | ScalarValue::Null) => Ok(None),
+                other => Err(DataFusionError::Plan(format!(
+                    "might_contain: expect serialized bloom filter scalar, got {other:?}"
+                ))),
+            }
+        })?;
+        Ok(bloom_filter.clone())
+    }
+}
+
+impl Display for BloomFilterMightContainExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Debug for BloomFilterMightContainExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "MightContain({:?}, {:?})",
+            self.bloom_filter_expr, self.value_expr
+        )
+    }
+}
+
+impl PartialEq<dyn Any> for BloomFilterMightContainExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|other| {
+                self.bloom_filter_expr.eq(&other.bloom_filter_expr)
+                    && self.value_expr.eq(&other.value_expr)
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for BloomFilterMightContainExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn nullable(&self, _input_schema: &Schema) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        // like spark, returns null if the bloom filter is null
+        let bloom_filter = match self.get_bloom_filter(batch)? {
+            Some(bloom_filter) => bloom_filter,
+            None => return Ok(ColumnarValue::Scalar(ScalarValue::Boolean(None))),
+        };
+
+        match self.value_expr.evaluate(batch)? {
+            ColumnarValue::Array(values) => {
+                let values = as_int64_array(&values)?;
+                let might_contain: BooleanArray = values
+                    .iter()
+                    .map(|value| value.map(|value| bloom_filter.might_contain_long(value)))
+                    .collect();
+                Ok(ColumnarValue::Array(Arc::new(might_contain)))
+            }
+            ColumnarValue::Scalar(ScalarValue::Int64(value)) => Ok(ColumnarValue::Scalar(
+                ScalarValue::Boolean(value.map(|value| bloom_filter.might_contain_long(value))),
+            )),
+            other => Err(DataFusionError::Plan(format!(
+                "might_contain: expect long values, got {other:?}"
+            ))),
+        }
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.bloom_filter_expr.clone(), self.value_expr.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(Self::new(
+            children[0].clone(),
+            children[1].clone(),
+        )))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.bloom_filter_expr.hash(&mut s);
+        self.value_expr.hash(&mut s);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bloom_filter_might_contain::BloomFilterMightContainExpr;
+    use arrow::array::{ArrayRef, BooleanArray, Int64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_expr::{expressions as phys_expr, PhysicalExpr};
+    use datafusion_ext_commons::spark_bloom_filter::SparkBloomFilter;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_might_contain() -> Result<()> {
+        let mut bloom_filter = SparkBloomFilter::new(100, 1024);
+        bloom_filter.put_long(1);
+        bloom_filter.put_long(2);
+        let mut serialized = vec![];
+        bloom_filter.write_to(&mut serialized)?;
+
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Int64, true)]));
+        let values: ArrayRef = Arc::new(Int64Array::from(vec![Some(1), Some(2), Some(3), None]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![values])?;
+
+        let expr = BloomFilterMightContainExpr::new(
+            phys_expr::lit(ScalarValue::Binary(Some(serialized))),
+            phys_expr::col("v", &schema)?,
+        );
+        let ret = expr.evaluate(&batch)?.into_array(batch.num_rows());
+        let expected: ArrayRef = Arc::new(BooleanArray::from(vec![
+            Some(true),
+            Some(true),
+            Some(false),
+            None,
+        ]));
+        assert_eq!(&ret, &expected);
+
+        // null bloom filter
+        let expr = BloomFilterMightContainExpr::new(
+            phys_expr::lit(ScalarValue::Binary(None)),
+            phys_expr::col("v", &schema)?,
+        );
+        let ret = expr.evaluate(&batch)?.into_array(batch.num_rows());
+        let expected: ArrayRef = Arc::new(BooleanArray::from(vec![None, None, None, None]));
+        assert_eq!(&ret, &expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use crate::lambda::take_referenced_columns;
+use arrow::array::*;
+use arrow::compute::{cast, interleave};
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use datafusion::common::cast::as_boolean_array;
+use datafusion::common::Result;
+use datafusion::logical_expr::ColumnarValue;
+use datafusion::physical_expr::PhysicalExpr;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::{any::Any, sync::Arc};
+
+/// expression of spark's CASE WHEN. branch conditions are evaluated only on
+/// rows not matched by previous branches, and branch values are evaluated only
+/// on rows matching their conditions. null conditions are treated as false.
+#[derive(Debug, Hash)]
+pub struct CaseWhenExpr {
+    when_then_exprs: Vec<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)>,
+    else_expr: Option<Arc<dyn PhysicalExpr>>,
+    return_type: DataType,
+}
+
+impl CaseWhenExpr {
+    pub fn new(
+        when_then_exprs: Vec<(Arc<dyn PhysicalExpr>, Arc<dyn PhysicalExpr>)>,
+        else_expr: Option<Arc<dyn PhysicalExpr>>,
+        return_type: DataType,
+    ) -> Self {
+        Self {
+            when_then_exprs,
+            else_expr,
+            return_type,
+        }
+    }
+
+    /// Evaluates the expression on the given rows (ascending and distinct)
+    fn evaluate_rows(
+        &self,
+        expr: &Arc<dyn PhysicalExpr>,
+        batch: &RecordBatch,
+        rows: &[u32],
+    ) -> Result<ArrayRef> {
+        if rows.len() == batch.num_rows() {
+            return Ok(expr.evaluate(batch)?.into_array(rows.len()));
+        }
+        let row_indices = UInt32Array::from(rows.to_vec());
+        let (fields, columns) = take_referenced_columns(batch, expr, &row_indices)?;
+        let rows_batch = RecordBatch::try_new_with_options(
+            Arc::new(Schema::new(fields)),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(rows.len())),
+        )?;
+        Ok(expr.evaluate(&rows_batch)?.into_array(rows.len()))
+    }
+
+    /// Evaluates the branch value, casting it to the return type if needed
+    fn evaluate_value(
+        &self,
+        expr: &Arc<dyn PhysicalExpr>,
+        batch: &RecordBatch,
+        rows: &[u32],
+    ) -> Result<ArrayRef> {
+        let value = self.evaluate_rows(expr, batch, rows)?;
+        if value.data_type() == &self.return_type {
+            return Ok(value);
+        }
+        Ok(cast(&value, &self.return_type)?)
+    }
+}
+
+impl std::fmt::Display for CaseWhenExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CASE")?;
+        for (when, then) in &self.when_then_exprs {
+            write!(f, " WHEN {when} THEN {then}")?;
+        }
+        if let Some(else_expr) = &self.else_expr {
+            write!(f, " ELSE {else_expr}")?;
+        }
+        write!(f, " END")
+    }
+}
+
+impl PartialEq<dyn Any> for CaseWhenExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.when_then_exprs.len() == x.when_then_exprs.len()
+                    && self
+                        .when_then_exprs
+                        .iter()
+                        .zip(&x.when_then_exprs)
+                        .all(|((w1, t1), (w2, t2))| w1.eq(w2) && t1.eq(t2))
+                    && match (&self.else_expr, &x.else_expr) {
+                        (Some(e1), Some(e2)) => e1.eq(e2),
+                        (None, None) => true,
+                        _ => false,
+                    }
+                    && self.return_type == x.return_type
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for CaseWhenExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(self.return_type.clone())
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        for (_, then) in &self.when_then_exprs {
+            if then.nullable(input_schema)? {
+                return Ok(true);
+            }
+        }
+        match &self.else_expr {
+            Some(else_expr) => else_expr.nullable(input_schema),
+            None => Ok(true),
+        }
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let num_rows = batch.num_rows();
+        let mut remaining_rows = (0..num_rows as u32).collect::<Vec<_>>();
+
+        // values of each branch and where each output row comes from, the
+        // first value is a single null for unmatched rows without else
+        let mut values = vec![new_null_array(&self.return_type, 1)];
+        let mut interleave_indices = vec![(0, 0); num_rows];
+        for (when, then) in &self.when_then_exprs {
+            if remaining_rows.is_empty() {
+                break;
+            }
+            let conditions = self.evaluate_rows(when, batch, &remaining_rows)?;
+            let conditions = as_boolean_array(&conditions)?;
+            let mut matched_rows = vec![];
+            let mut unmatched_rows = vec![];
+            for (i, &row) in remaining_rows.iter().enumerate() {
+                if conditions.is_valid(i) && conditions.value(i) {
+                    matched_rows.push(row);
+                } else {
+                    unmatched_rows.push(row);
+                }
+            }
+            if !matched_rows.is_empty() {
+                for (i, &row) in matched_rows.iter().enumerate() {
+                    interleave_indices[row as usize] = (values.len(), i);
+                }
+                values.push(self.evaluate_value(then, batch, &matched_rows)?);
+            }
+            remaining_rows = unmatched_rows;
+        }
+        if let Some(else_expr) = &self.else_expr {
+            if !remaining_rows.is_empty() {
+                for (i, &row) in remaining_rows.iter().enumerate() {
+                    interleave_indices[row as usize] = (values.len(), i);
+                }
+                values.push(self.evaluate_value(else_expr, batch, &remaining_rows)?);
+            }
+        }
+
+        // all rows come from the same branch
+        if values.len() == 2 && values[1].len() == num_rows {
+            return Ok(ColumnarValue::Array(values[1].clone()));
+        }
+        let values = values
+            .iter()
+            .map(|value| value.as_ref())
+            .collect::<Vec<_>>();
+        Ok(ColumnarValue::Array(interleave(
+            &values,
+            &interleave_indices,
+        )?))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        let mut children = vec![];
+        for (when, then) in &self.when_then_exprs {
+            children.push(when.clone());
+            children.push(then.clone());
+        }
+        children.extend(self.else_expr.iter().cloned());
+        children
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        let num_when_thens = self.when_then_exprs.len();
+        let when_then_exprs = children[..num_when_thens * 2]
+            .chunks(2)
+            .map(|when_then| (when_then[0].clone(), when_then[1].clone()))
+            .collect();
+        let else_expr = children.get(num_when_thens * 2).cloned();
+        Ok(Arc::new(Self::new(
+            when_then_exprs,
+            else_expr,
+            self.return_type.clone(),
+        )))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::case_when::CaseWhenExpr;
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{BinaryExpr, Column, Literal};
+    use datafusion::physical_plan::PhysicalExpr;
+    use datafusion::scalar::ScalarValue;
+    use std::sync::Arc;
+
+    fn binary(
+        l: &Arc<dyn PhysicalExpr>,
+        op: Operator,
+        r: &Arc<dyn PhysicalExpr>,
+    ) -> Arc<dyn PhysicalExpr> {
+        Arc::new(BinaryExpr::new(l.clone(), op, r.clone()))
+    }
+
+    fn lit(v: i32) -> Arc<dyn PhysicalExpr> {
+        Arc::new(Literal::new(ScalarValue::Int32(Some(v))))
+    }
+
+    #[test]
+    fn test_case_when() -> Result<(), Box<dyn std::error::Error>> {
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![
+            (
+                "a",
+                Arc::new(Int32Array::from(vec![Some(1), Some(0), None, Some(2)])) as ArrayRef,
+                true,
+            ),
+            (
+                "b",
+                Arc::new(Int32Array::from(vec![10, 20, 30, 40])) as ArrayRef,
+                true,
+            ),
+        ])?;
+        let a: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+        let b: Arc<dyn PhysicalExpr> = Arc::new(Column::new("b", 1));
+
+        // CASE WHEN a = 0 THEN -1 WHEN a > 0 THEN b / a END, the division
+        // is only evaluated on rows where a > 0
+        let case_when = CaseWhenExpr::new(
+            vec![
+                (binary(&a, Operator::Eq, &lit(0)), lit(-1)),
+                (
+                    binary(&a, Operator::Gt, &lit(0)),
+                    binary(&b, Operator::Divide, &a),
+                ),
+            ],
+            None,
+            DataType::Int64,
+        );
+        let output_array = case_when.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int64Type>(&output_array),
+            &Int64Array::from(vec![Some(10), Some(-1), None, Some(20)])
+        );
+
+        // CASE WHEN a > 0 THEN b ELSE 0 END
+        let case_when = CaseWhenExpr::new(
+            vec![(binary(&a, Operator::Gt, &lit(0)), b.clone())],
+            Some(lit(0)),
+            DataType::Int32,
+        );
+        let output_array = case_when.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&output_array),
+            &Int32Array::from(vec![10, 0, 0, 40])
+        );
+        Ok(())
+    }
+}
@@ -13,12 +13,14 @@
 // limitations under the License.
 
 use crate::down_cast_any_ref;
+use arrow::array::as_primitive_array;
 use arrow::datatypes::*;
 use arrow::record_batch::RecordBatch;
 use datafusion::common::Result;
 use datafusion::logical_expr::ColumnarValue;
 use datafusion::physical_expr::PhysicalExpr;
 use datafusion::scalar::ScalarValue;
+use datafusion_ext_commons::cast::{cast_with_options, SparkCastOptions};
 use std::any::Any;
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
@@ -29,20 +31,34 @@ use std::sync::Arc;
 pub struct TryCastExpr {
     pub expr: Arc<dyn PhysicalExpr>,
     pub cast_type: DataType,
+    pub cast_options: SparkCastOptions,
 }
 
 impl PartialEq<dyn Any> for TryCastExpr {
     fn eq(&self, other: &dyn Any) -> bool {
         down_cast_any_ref(other)
             .downcast_ref::<Self>()
-            .map(|x| self.expr.eq(&x.expr) && self.cast_type == x.cast_type)
+            .map(|x| {
+                self.expr.eq(&x.expr)
+                    && self.cast_type == x.cast_type
+                    && self.cast_options == x.cast_options
+            })
             .unwrap_or(false)
     }
 }
 
 impl TryCastExpr {
     pub fn new(expr: Arc<dyn PhysicalExpr>, cast_type: DataType) -> Self {
-        Self { expr, cast_type }
+        Self {
+            expr,
+            cast_type,
+            cast_options: SparkCastOptions::default(),
+        }
+    }
+
+    pub fn with_cast_options(mut self, cast_options: SparkCastOptions) -> Self {
+        self.cast_options = cast_options;
+        self
     }
 }
 
@@ -67,13 +83,28 @@ impl PhysicalExpr for TryCastExpr {
 
     fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
         Ok(match self.expr.evaluate(batch)? {
-            ColumnarValue::Array(array) => {
-                ColumnarValue::Array(datafusion_ext_commons::cast::cast(&array, &self.cast_type)?)
-            }
+            ColumnarValue::Array(array) => match (array.data_type(), &self.cast_type) {
+                // widening precision with the same scale does not change any values, so
+                // we only need to replace the data type
+                (&DataType::Decimal128(p1, s1), &DataType::Decimal128(p2, s2))
+                    if p2 >= p1 && s2 == s1 =>
+                {
+                    ColumnarValue::Array(Arc::new(
+                        as_primitive_array::<Decimal128Type>(&array)
+                            .clone()
+                            .with_precision_and_scale(p2, s2)?,
+                    ))
+                }
+                _ => ColumnarValue::Array(cast_with_options(
+                    &array,
+                    &self.cast_type,
+                    &self.cast_options,
+                )?),
+            },
             ColumnarValue::Scalar(scalar) => {
                 let array = scalar.to_array();
                 ColumnarValue::Scalar(ScalarValue::try_from_array(
-                    &datafusion_ext_commons::cast::cast(&array, &self.cast_type)?,
+                    &cast_with_options(&array, &self.cast_type, &self.cast_options)?,
                     0,
                 )?)
             }
@@ -88,10 +119,10 @@ impl PhysicalExpr for TryCastExpr {
         self: Arc<Self>,
         children: Vec<Arc<dyn PhysicalExpr>>,
     ) -> Result<Arc<dyn PhysicalExpr>> {
-        Ok(Arc::new(Self::new(
-            children[0].clone(),
-            self.cast_type.clone(),
-        )))
+        Ok(Arc::new(
+            Self::new(children[0].clone(), self.cast_type.clone())
+                .with_cast_options(self.cast_options.clone()),
+        ))
     }
 
     fn dyn_hash(&self, state: &mut dyn Hasher) {
@@ -102,10 +133,16 @@ impl PhysicalExpr for TryCastExpr {
 #[cfg(test)]
 mod test {
     use crate::cast::TryCastExpr;
-    use arrow::array::{ArrayRef, Float32Array, Int32Array, StringArray};
+    use arrow::array::{
+        as_list_array, as_primitive_array, Array, ArrayRef, Decimal128Array, Float32Array,
+        Int32Array, Int64Array, ListArray, StringArray,
+    };
 
-    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::buffer::OffsetBuffer;
+    use arrow::datatypes::{DataType, Decimal128Type, Field, Schema};
     use arrow::record_batch::RecordBatch;
+    use datafusion::common::ScalarValue;
+    use datafusion::logical_expr::ColumnarValue;
     use datafusion::physical_expr::{expressions as phys_expr, PhysicalExpr};
     use std::sync::Arc;
 
@@ -228,4 +265,138 @@ mod test {
         ]));
         assert_eq!(&ret, &expected);
     }
+
+    #[test]
+    fn test_decimal_precision_widening() {
+        let decimal_arr: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![Some(12345), None, Some(-67890)])
+                .with_precision_and_scale(10, 2)
+                .unwrap(),
+        );
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "col",
+            DataType::Decimal128(10, 2),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![decimal_arr.clone()])
+            .expect("Error creating RecordBatch");
+
+        let cast_type = DataType::Decimal128(20, 2);
+        let expr = Arc::new(TryCastExpr::new(
+            phys_expr::col("col", &batch.schema()).unwrap(),
+            cast_type.clone(),
+        ));
+        let ret = expr
+            .evaluate(&batch)
+            .expect("Error evaluating expr")
+            .into_array(batch.num_rows());
+
+        let expected: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![Some(12345), None, Some(-67890)])
+                .with_precision_and_scale(20, 2)
+                .unwrap(),
+        );
+        assert_eq!(ret.data_type(), &cast_type);
+        assert_eq!(&ret, &expected);
+
+        // values buffer is shared with the input, no data is copied
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&ret).values().as_ptr(),
+            as_primitive_array::<Decimal128Type>(&decimal_arr)
+                .values()
+                .as_ptr(),
+        );
+    }
+
+    #[test]
+    fn test_all_null_cast_preserves_nulls() {
+        let int_arr: ArrayRef = Arc::new(Int32Array::from(vec![None, None, None]));
+        let schema = Arc::new(Schema::new(vec![Field::new("col", DataType::Int32, true)]));
+        let batch =
+            RecordBatch::try_new(schema, vec![int_arr]).expect("Error creating RecordBatch");
+
+        // array branch
+        let expr = Arc::new(TryCastExpr::new(
+            phys_expr::col("col", &batch.schema()).unwrap(),
+            DataType::Int64,
+        ));
+        let ret = expr
+            .evaluate(&batch)
+            .expect("Error evaluating expr")
+            .into_array(batch.num_rows());
+        let expected: ArrayRef = Arc::new(Int64Array::from(vec![None, None, None]));
+        assert_eq!(ret.data_type(), &DataType::Int64);
+        assert_eq!(ret.null_count(), 3);
+        assert_eq!(&ret, &expected);
+
+        // null type has no null buffer
+        let expr = Arc::new(TryCastExpr::new(
+            phys_expr::col("col", &batch.schema()).unwrap(),
+            DataType::Null,
+        ));
+        let ret = expr
+            .evaluate(&batch)
+            .expect("Error evaluating expr")
+            .into_array(batch.num_rows());
+        assert_eq!(ret.data_type(), &DataType::Null);
+        assert_eq!(ret.len(), 3);
+
+        // scalar branch
+        let expr = Arc::new(TryCastExpr::new(
+            phys_expr::lit(ScalarValue::Int32(None)),
+            DataType::Int64,
+        ));
+        match expr.evaluate(&batch).expect("Error evaluating expr") {
+            ColumnarValue::Scalar(scalar) => assert_eq!(scalar, ScalarValue::Int64(None)),
+            ColumnarValue::Array(_) => panic!("expect scalar value"),
+        }
+    }
+
+    #[test]
+    fn test_list_cast_output_matches_schema() {
+        // [["1", null], ["x"]]
+        let list_arr: ArrayRef = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8, true)),
+            OffsetBuffer::new(vec![0, 2, 3].into()),
+            Arc::new(StringArray::from(vec![Some("1"), None, Some("x")])),
+            None,
+        ));
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "col",
+            list_arr.data_type().clone(),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![list_arr])
+            .expect("Error creating RecordBatch");
+
+        // items are declared non-nullable, but null items are kept
+        let cast_type = DataType::List(Arc::new(Field::new("item", DataType::Int32, false)));
+        let expr = Arc::new(TryCastExpr::new(
+            phys_expr::col("col", &schema).unwrap(),
+            cast_type.clone(),
+        ));
+        let ret = expr
+            .evaluate(&batch)
+            .expect("Error evaluating expr")
+            .into_array(batch.num_rows());
+        assert_eq!(ret.data_type(), &cast_type);
+
+        // the output can be put into a batch of the schema derived from the expr
+        let output_schema = Arc::new(Schema::new(vec![Field::new(
+            "casted",
+            expr.data_type(&schema).unwrap(),
+            expr.nullable(&schema).unwrap(),
+        )]));
+        let output_batch = RecordBatch::try_new(output_schema, vec![ret])
+            .expect("Error creating output RecordBatch");
+        let casted_list = as_list_array(output_batch.column(0));
+        assert_eq!(
+            casted_list.value(0).as_ref(),
+            &Int32Array::from(vec![Some(1), None]) as &dyn Array,
+        );
+        assert_eq!(
+            casted_list.value(1).as_ref(),
+            &Int32Array::from(vec![None]) as &dyn Array,
+        );
+    }
 }
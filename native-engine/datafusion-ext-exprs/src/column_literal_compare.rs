@@ -0,0 +1,264 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use arrow::array::{as_primitive_array, Array, ArrayRef, BooleanArray};
+use arrow::buffer::BooleanBuffer;
+use arrow::datatypes::*;
+use arrow::record_batch::RecordBatch;
+use datafusion::common::{Result, ScalarValue};
+use datafusion::logical_expr::{ColumnarValue, Operator};
+use datafusion::physical_expr::expressions::{Column, Literal};
+use datafusion::physical_plan::PhysicalExpr;
+use std::any::Any;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Fast path of `col <op> literal` comparisons on primitive columns, which
+/// compares the value buffer with the literal directly and writes the packed
+/// result bitmap, skipping the generic dispatching of `BinaryExpr`.
+#[derive(Debug, Hash)]
+pub struct ColumnLiteralCompareExpr {
+    column: Arc<dyn PhysicalExpr>,
+    op: Operator,
+    literal: ScalarValue,
+}
+
+impl PartialEq<dyn Any> for ColumnLiteralCompareExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.column.eq(&x.column) && self.op == x.op && self.literal == x.literal)
+            .unwrap_or(false)
+    }
+}
+
+impl ColumnLiteralCompareExpr {
+    /// Returns None if the fast path is not applicable, in which case the
+    /// generic `BinaryExpr` should be used
+    pub fn try_new(
+        left: &Arc<dyn PhysicalExpr>,
+        op: Operator,
+        right: &Arc<dyn PhysicalExpr>,
+        input_schema: &Schema,
+    ) -> Option<Self> {
+        if !matches!(
+            op,
+            Operator::Eq
+                | Operator::NotEq
+                | Operator::Lt
+                | Operator::LtEq
+                | Operator::Gt
+                | Operator::GtEq
+        ) {
+            return None;
+        }
+        left.as_any().downcast_ref::<Column>()?;
+        let literal = right.as_any().downcast_ref::<Literal>()?.value();
+        if literal.is_null() || left.data_type(input_schema).ok()? != literal.get_datatype() {
+            return None;
+        }
+        if !matches!(
+            literal.get_datatype(),
+            DataType::Int8
+                | DataType::Int16
+                | DataType::Int32
+                | DataType::Int64
+                | DataType::UInt8
+                | DataType::UInt16
+                | DataType::UInt32
+                | DataType::UInt64
+                | DataType::Float32
+                | DataType::Float64
+                | DataType::Date32
+                | DataType::Date64
+        ) {
+            return None;
+        }
+        Some(Self {
+            column: left.clone(),
+            op,
+            literal: literal.clone(),
+        })
+    }
+
+    pub fn column(&self) -> &Arc<dyn PhysicalExpr> {
+        &self.column
+    }
+
+    pub fn op(&self) -> Operator {
+        self.op
+    }
+
+    pub fn literal(&self) -> &ScalarValue {
+        &self.literal
+    }
+}
+
+impl Display for ColumnLiteralCompareExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.column, self.op, self.literal)
+    }
+}
+
+impl PhysicalExpr for ColumnLiteralCompareExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        self.column.nullable(input_schema)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let array = self.column.evaluate(batch)?.into_array(batch.num_rows());
+        let op = self.op;
+        let compared = match &self.literal {
+            ScalarValue::Int8(Some(v)) => compare_primitive::<Int8Type>(&array, *v, op),
+            ScalarValue::Int16(Some(v)) => compare_primitive::<Int16Type>(&array, *v, op),
+            ScalarValue::Int32(Some(v)) => compare_primitive::<Int32Type>(&array, *v, op),
+            ScalarValue::Int64(Some(v)) => compare_primitive::<Int64Type>(&array, *v, op),
+            ScalarValue::UInt8(Some(v)) => compare_primitive::<UInt8Type>(&array, *v, op),
+            ScalarValue::UInt16(Some(v)) => compare_primitive::<UInt16Type>(&array, *v, op),
+            ScalarValue::UInt32(Some(v)) => compare_primitive::<UInt32Type>(&array, *v, op),
+            ScalarValue::UInt64(Some(v)) => compare_primitive::<UInt64Type>(&array, *v, op),
+            ScalarValue::Float32(Some(v)) => compare_primitive::<Float32Type>(&array, *v, op),
+            ScalarValue::Float64(Some(v)) => compare_primitive::<Float64Type>(&array, *v, op),
+            ScalarValue::Date32(Some(v)) => compare_primitive::<Date32Type>(&array, *v, op),
+            ScalarValue::Date64(Some(v)) => compare_primitive::<Date64Type>(&array, *v, op),
+            other => unreachable!("unsupported literal: {other:?}"),
+        };
+        Ok(ColumnarValue::Array(Arc::new(compared)))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.column.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(Self {
+            column: children[0].clone(),
+            op: self.op,
+            literal: self.literal.clone(),
+        }))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+fn compare_primitive<T: ArrowPrimitiveType>(
+    array: &ArrayRef,
+    literal: T::Native,
+    op: Operator,
+) -> BooleanArray {
+    let values = as_primitive_array::<T>(array).values();
+
+    // uses the same total ordering as arrow's comparison kernels
+    let compared = match op {
+        Operator::Eq => BooleanBuffer::collect_bool(values.len(), |i| values[i].is_eq(literal)),
+        Operator::NotEq => BooleanBuffer::collect_bool(values.len(), |i| values[i].is_ne(literal)),
+        Operator::Lt => BooleanBuffer::collect_bool(values.len(), |i| values[i].is_lt(literal)),
+        Operator::LtEq => BooleanBuffer::collect_bool(values.len(), |i| values[i].is_le(literal)),
+        Operator::Gt => BooleanBuffer::collect_bool(values.len(), |i| values[i].is_gt(literal)),
+        Operator::GtEq => BooleanBuffer::collect_bool(values.len(), |i| values[i].is_ge(literal)),
+        other => unreachable!("unsupported operator: {other}"),
+    };
+    BooleanArray::new(compared, array.nulls().cloned())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::column_literal_compare::ColumnLiteralCompareExpr;
+    use arrow::array::{ArrayRef, Float64Array, Int32Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::ScalarValue;
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{col, lit, BinaryExpr};
+    use datafusion::physical_expr::PhysicalExpr;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_fast_path_matches_binary_expr() {
+        let num_rows = 10000;
+        let ints: ArrayRef = Arc::new(Int32Array::from_iter(
+            (0..num_rows).map(|i| (i % 7 != 0).then_some(i % 100 - 50)),
+        ));
+        let floats: ArrayRef = Arc::new(Float64Array::from_iter((0..num_rows).map(|i| {
+            match i % 11 {
+                0 => None,
+                1 => Some(f64::NAN),
+                _ => Some((i % 100) as f64 / 4.0 - 12.5),
+            }
+        })));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("i", DataType::Int32, true),
+            Field::new("f", DataType::Float64, true),
+        ]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![ints, floats]).unwrap();
+
+        let cases = [
+            ("i", ScalarValue::Int32(Some(0))),
+            ("i", ScalarValue::Int32(Some(-50))),
+            ("f", ScalarValue::Float64(Some(0.25))),
+            ("f", ScalarValue::Float64(Some(f64::NAN))),
+        ];
+        let ops = [
+            Operator::Eq,
+            Operator::NotEq,
+            Operator::Lt,
+            Operator::LtEq,
+            Operator::Gt,
+            Operator::GtEq,
+        ];
+        for (col_name, literal) in cases {
+            for op in ops {
+                let left = col(col_name, &schema).unwrap();
+                let right = lit(literal.clone());
+                let fast = ColumnLiteralCompareExpr::try_new(&left, op, &right, &schema).unwrap();
+                let generic = BinaryExpr::new(left, op, right);
+
+                // also compare on a sliced batch with non-zero offsets
+                for batch in [batch.clone(), batch.slice(3, 5000)] {
+                    let fast_result = fast.evaluate(&batch).unwrap().into_array(batch.num_rows());
+                    let generic_result = generic
+                        .evaluate(&batch)
+                        .unwrap()
+                        .into_array(batch.num_rows());
+                    assert_eq!(&fast_result, &generic_result, "{col_name} {op} {literal}");
+                }
+            }
+        }
+
+        // not applicable to null literals and mismatched types
+        let left = col("i", &schema).unwrap();
+        for literal in [ScalarValue::Int32(None), ScalarValue::Int64(Some(0))] {
+            let right = lit(literal);
+            assert!(
+                ColumnLiteralCompareExpr::try_new(&left, Operator::Eq, &right, &schema).is_none()
+            );
+        }
+    }
+}
@@ -22,6 +22,7 @@ use datafusion::common::Result;
 use datafusion::common::ScalarValue;
 use datafusion::logical_expr::ColumnarValue;
 use datafusion::physical_expr::PhysicalExpr;
+use datafusion_ext_commons::nulls::mask_nulls;
 use std::convert::TryInto;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
@@ -71,7 +72,13 @@ impl PhysicalExpr for GetIndexedFieldExpr {
 
     fn nullable(&self, input_schema: &Schema) -> Result<bool> {
         let data_type = self.arg.data_type(input_schema)?;
-        get_indexed_field(&data_type, &self.key).map(|f| f.is_nullable())
+        let field_nullable = get_indexed_field(&data_type, &self.key)?.is_nullable();
+
+        // field of a nullable struct is also nullable
+        match data_type {
+            DataType::Struct(_) => Ok(field_nullable || self.arg.nullable(input_schema)?),
+            _ => Ok(field_nullable),
+        }
     }
 
     fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
@@ -110,9 +117,14 @@ impl PhysicalExpr for GetIndexedFieldExpr {
             }
             (DataType::Struct(_), ScalarValue::Int32(Some(k))) => {
                 let as_struct_array = as_struct_array(&array)?;
-                Ok(ColumnarValue::Array(
-                    as_struct_array.column(*k as usize).clone(),
-                ))
+                let field_array = as_struct_array.column(*k as usize);
+
+                // field values of null structs are undefined, so they must be
+                // masked with the struct nulls
+                Ok(ColumnarValue::Array(mask_nulls(
+                    field_array,
+                    as_struct_array.nulls(),
+                )?))
             }
             (DataType::List(_), key) => Err(DataFusionError::Execution(format!(
                 "get indexed field is only possible on lists with int64 indexes. \
@@ -230,4 +242,43 @@ mod test {
         assert_batches_eq!(expected, &[output_batch]);
         Ok(())
     }
+
+    #[test]
+    fn test_struct_nulls() -> Result<(), Box<dyn std::error::Error>> {
+        let inner: ArrayRef = Arc::new(StructArray::from(vec![(
+            Arc::new(Field::new("b", DataType::Int32, true)),
+            Arc::new(Int32Array::from(vec![Some(1), None, Some(3)])) as ArrayRef,
+        )]));
+        let outer: ArrayRef = Arc::new(StructArray::try_new(
+            Fields::from(vec![Field::new("a", inner.data_type().clone(), false)]),
+            vec![inner],
+            Some(vec![true, true, false].into()),
+        )?);
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![("s", outer, true)])?;
+
+        // s.a.b
+        let get_indexed = Arc::new(GetIndexedFieldExpr::new(
+            Arc::new(GetIndexedFieldExpr::new(
+                Arc::new(Column::new("s", 0)),
+                ScalarValue::from(0_i32),
+            )),
+            ScalarValue::from(0_i32),
+        ));
+        assert!(get_indexed.nullable(&input_batch.schema())?);
+
+        let output_array = get_indexed.evaluate(&input_batch)?.into_array(0);
+        let output_batch =
+            RecordBatch::try_from_iter_with_nullable(vec![("s.a.b", output_array, true)])?;
+        let expected = vec![
+            "+-------+",
+            "| s.a.b |",
+            "+-------+",
+            "| 1     |",
+            "|       |",
+            "|       |",
+            "+-------+",
+        ];
+        assert_batches_eq!(expected, &[output_batch]);
+        Ok(())
+    }
 }
@@ -0,0 +1,558 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use crate::lambda::LambdaFunction;
+use arrow::array::*;
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::compute::{cast, interleave, take};
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::RecordBatch;
+use datafusion::common::cast::{as_boolean_array, as_list_array};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion::physical_expr::{expr_list_eq_strict_order, PhysicalExpr};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::{any::Any, sync::Arc};
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq)]
+pub enum HigherOrderFunction {
+    /// transform(array, (x[, i]) -> ...)
+    Transform,
+
+    /// filter(array, (x[, i]) -> ...)
+    Filter,
+
+    /// exists(array, x -> ...), returns null instead of false if the lambda
+    /// returns null for any element when following three-valued logic.
+    Exists { follow_three_valued_logic: bool },
+
+    /// aggregate(array, zero, (acc, x) -> ..., acc -> ...)
+    Aggregate,
+}
+
+/// expression to evaluate higher-order functions over list arrays.
+#[derive(Debug, Hash)]
+pub struct HigherOrderFunctionExpr {
+    func: HigherOrderFunction,
+    array: Arc<dyn PhysicalExpr>,
+    args: Vec<Arc<dyn PhysicalExpr>>,
+    lambdas: Vec<LambdaFunction>,
+    return_type: DataType,
+}
+
+impl HigherOrderFunctionExpr {
+    pub fn try_new(
+        func: HigherOrderFunction,
+        array: Arc<dyn PhysicalExpr>,
+        args: Vec<Arc<dyn PhysicalExpr>>,
+        lambdas: Vec<LambdaFunction>,
+        return_type: DataType,
+    ) -> Result<Self> {
+        let (num_args, num_lambdas) = match func {
+            HigherOrderFunction::Transform => (0, 1),
+            HigherOrderFunction::Filter => (0, 1),
+            HigherOrderFunction::Exists { .. } => (0, 1),
+            HigherOrderFunction::Aggregate => (1, 2),
+        };
+        if args.len() != num_args || lambdas.len() != num_lambdas {
+            return Err(DataFusionError::Plan(format!(
+                "{func:?} expects {num_args} args and {num_lambdas} lambda functions, \
+                 got {} and {}",
+                args.len(),
+                lambdas.len(),
+            )));
+        }
+        Ok(Self {
+            func,
+            array,
+            args,
+            lambdas,
+            return_type,
+        })
+    }
+
+    fn transform(&self, batch: &RecordBatch, list: &ListArray) -> Result<ArrayRef> {
+        let (elements, row_indices) = flatten_list(list);
+        let variables = self.element_variables(&self.lambdas[0], list, elements)?;
+        let transformed = self.lambdas[0].evaluate(batch, &row_indices, variables)?;
+        let field = match &self.return_type {
+            DataType::List(field) => field.clone(),
+            other => {
+                return Err(DataFusionError::Execution(format!(
+                    "transform expects returning list type, but got {other}"
+                )))
+            }
+        };
+        let transformed = cast_if_needed(transformed, field.data_type())?;
+        Ok(Arc::new(ListArray::try_new(
+            field,
+            offsets_from_zero(list.value_offsets()),
+            transformed,
+            list.nulls().cloned(),
+        )?))
+    }
+
+    fn filter(&self, batch: &RecordBatch, list: &ListArray) -> Result<ArrayRef> {
+        let (elements, row_indices) = flatten_list(list);
+        let variables = self.element_variables(&self.lambdas[0], list, elements.clone())?;
+        let predicate = self.lambdas[0].evaluate(batch, &row_indices, variables)?;
+        let predicate = as_boolean_array(&predicate)?;
+
+        let value_offsets = list.value_offsets();
+        let first = value_offsets[0];
+        let mut offsets = Vec::with_capacity(list.len() + 1);
+        let mut num_filtered = 0;
+        offsets.push(0);
+        for (&start, &end) in value_offsets.iter().zip(&value_offsets[1..]) {
+            num_filtered += ((start - first) as usize..(end - first) as usize)
+                .filter(|&j| predicate.is_valid(j) && predicate.value(j))
+                .count() as i32;
+            offsets.push(num_filtered);
+        }
+        let field = match list.data_type() {
+            DataType::List(field) => field.clone(),
+            _ => unreachable!("expect list type"),
+        };
+        Ok(Arc::new(ListArray::try_new(
+            field,
+            OffsetBuffer::new(offsets.into()),
+            arrow::compute::filter(&elements, predicate)?,
+            list.nulls().cloned(),
+        )?))
+    }
+
+    fn exists(
+        &self,
+        batch: &RecordBatch,
+        list: &ListArray,
+        follow_three_valued_logic: bool,
+    ) -> Result<ArrayRef> {
+        let (elements, row_indices) = flatten_list(list);
+        let variables = self.element_variables(&self.lambdas[0], list, elements)?;
+        let predicate = self.lambdas[0].evaluate(batch, &row_indices, variables)?;
+        let predicate = as_boolean_array(&predicate)?;
+
+        let first = list.value_offsets()[0];
+        let exists: BooleanArray = (0..list.len())
+            .map(|i| {
+                if list.is_null(i) {
+                    return None;
+                }
+                let start = (list.value_offsets()[i] - first) as usize;
+                let end = (list.value_offsets()[i + 1] - first) as usize;
+                let mut found_null = false;
+                for j in start..end {
+                    if predicate.is_null(j) {
+                        found_null = true;
+                    } else if predicate.value(j) {
+                        return Some(true);
+                    }
+                }
+                (!(found_null && follow_three_valued_logic)).then_some(false)
+            })
+            .collect();
+        Ok(Arc::new(exists))
+    }
+
+    fn aggregate(&self, batch: &RecordBatch, list: &ListArray) -> Result<ArrayRef> {
+        let num_rows = list.len();
+        let value_offsets = list.value_offsets();
+        let list_len = |i: usize| (value_offsets[i + 1] - value_offsets[i]) as usize;
+        let zero = self.args[0].evaluate(batch)?.into_array(num_rows);
+        let acc_type = zero.data_type().clone();
+
+        // merge elements at the same position of the rows not yet finished in each
+        // round. accumulators of finished rows are left in the merged arrays and
+        // gathered at the end
+        let mut merged_arrays = vec![zero.clone()];
+        let mut acc_indices = (0..num_rows).map(|i| (0, i)).collect::<Vec<_>>();
+        let mut active_rows = (0..num_rows)
+            .filter(|&i| list.is_valid(i) && list_len(i) > 0)
+            .collect::<Vec<_>>();
+        let mut active_acc = take(
+            &zero,
+            &UInt32Array::from_iter_values(active_rows.iter().map(|&i| i as u32)),
+            None,
+        )?;
+        let mut k = 0;
+        while !active_rows.is_empty() {
+            let row_indices = UInt32Array::from_iter_values(active_rows.iter().map(|&i| i as u32));
+            let element_indices = UInt32Array::from_iter_values(
+                active_rows
+                    .iter()
+                    .map(|&i| value_offsets[i] as u32 + k as u32),
+            );
+            let variables = vec![active_acc, take(list.values(), &element_indices, None)?];
+            let merged = self.lambdas[0].evaluate(batch, &row_indices, variables)?;
+            let merged = cast_if_needed(merged, &acc_type)?;
+            k += 1;
+
+            let mut remaining = vec![];
+            for (j, &i) in active_rows.iter().enumerate() {
+                if list_len(i) == k {
+                    acc_indices[i] = (merged_arrays.len(), j);
+                } else {
+                    remaining.push(j);
+                }
+            }
+            active_acc = take(
+                &merged,
+                &UInt32Array::from_iter_values(remaining.iter().map(|&j| j as u32)),
+                None,
+            )?;
+            active_rows = remaining.into_iter().map(|j| active_rows[j]).collect();
+            merged_arrays.push(merged);
+        }
+        let merged_arrays = merged_arrays
+            .iter()
+            .map(|array| array.as_ref())
+            .collect::<Vec<_>>();
+        let acc = interleave(&merged_arrays, &acc_indices)?;
+
+        let row_indices = UInt32Array::from_iter_values(0..num_rows as u32);
+        let finished = self.lambdas[1].evaluate(batch, &row_indices, vec![acc])?;
+        let finished = cast_if_needed(finished, &self.return_type)?;
+
+        // aggregating a null array returns null
+        if list.null_count() == 0 {
+            return Ok(finished);
+        }
+        let nulls = NullBuffer::union(list.nulls(), finished.nulls());
+        Ok(make_array(
+            finished.to_data().into_builder().nulls(nulls).build()?,
+        ))
+    }
+
+    /// Variables of lambda functions taking (element[, index])
+    fn element_variables(
+        &self,
+        lambda: &LambdaFunction,
+        list: &ListArray,
+        elements: ArrayRef,
+    ) -> Result<Vec<ArrayRef>> {
+        match lambda.variable_names().len() {
+            1 => Ok(vec![elements]),
+            2 => {
+                let value_offsets = list.value_offsets();
+                let indices = Int32Array::from_iter_values(
+                    value_offsets
+                        .iter()
+                        .zip(&value_offsets[1..])
+                        .flat_map(|(&start, &end)| 0..end - start),
+                );
+                Ok(vec![elements, Arc::new(indices)])
+            }
+            n => Err(DataFusionError::Execution(format!(
+                "{:?} does not support lambda function with {n} variables",
+                self.func,
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for HigherOrderFunctionExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}({})", self.func, self.array)
+    }
+}
+
+impl PartialEq<dyn Any> for HigherOrderFunctionExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.func == x.func
+                    && self.array.eq(&x.array)
+                    && expr_list_eq_strict_order(&self.args, &x.args)
+                    && self.lambdas == x.lambdas
+                    && self.return_type == x.return_type
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for HigherOrderFunctionExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(self.return_type.clone())
+    }
+
+    fn nullable(&self, _input_schema: &Schema) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let array = self.array.evaluate(batch)?.into_array(batch.num_rows());
+        let list = as_list_array(&array)?;
+        let result = match self.func {
+            HigherOrderFunction::Transform => self.transform(batch, list)?,
+            HigherOrderFunction::Filter => self.filter(batch, list)?,
+            HigherOrderFunction::Exists {
+                follow_three_valued_logic,
+            } => self.exists(batch, list, follow_three_valued_logic)?,
+            HigherOrderFunction::Aggregate => self.aggregate(batch, list)?,
+        };
+        Ok(ColumnarValue::Array(result))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        let mut children = vec![self.array.clone()];
+        children.extend(self.args.iter().cloned());
+        children.extend(self.lambdas.iter().map(|lambda| lambda.body().clone()));
+        children
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        let num_args = self.args.len();
+        let lambdas = self
+            .lambdas
+            .iter()
+            .zip(&children[1 + num_args..])
+            .map(|(lambda, body)| lambda.with_new_body(body.clone()))
+            .collect();
+        Ok(Arc::new(Self::try_new(
+            self.func,
+            children[0].clone(),
+            children[1..1 + num_args].to_vec(),
+            lambdas,
+            self.return_type.clone(),
+        )?))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+/// Returns all elements of the list array and the row index of each element
+fn flatten_list(list: &ListArray) -> (ArrayRef, UInt32Array) {
+    let value_offsets = list.value_offsets();
+    let first = value_offsets[0] as usize;
+    let last = value_offsets[list.len()] as usize;
+    let elements = list.values().slice(first, last - first);
+    let row_indices = UInt32Array::from_iter_values(
+        value_offsets
+            .iter()
+            .zip(&value_offsets[1..])
+            .enumerate()
+            .flat_map(|(i, (&start, &end))| {
+                std::iter::repeat(i as u32).take((end - start) as usize)
+            }),
+    );
+    (elements, row_indices)
+}
+
+fn offsets_from_zero(value_offsets: &[i32]) -> OffsetBuffer<i32> {
+    let first = value_offsets[0];
+    OffsetBuffer::new(
+        value_offsets
+            .iter()
+            .map(|&offset| offset - first)
+            .collect::<Vec<_>>()
+            .into(),
+    )
+}
+
+fn cast_if_needed(array: ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    if array.data_type() == data_type {
+        return Ok(array);
+    }
+    Ok(cast(&array, data_type)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::higher_order_function::{HigherOrderFunction, HigherOrderFunctionExpr};
+    use crate::lambda::{LambdaFunction, LambdaVariableExpr};
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{BinaryExpr, Column, Literal};
+    use datafusion::physical_plan::PhysicalExpr;
+    use datafusion::scalar::ScalarValue;
+    use std::sync::Arc;
+
+    fn input_batch() -> RecordBatch {
+        let array: ArrayRef = Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), Some(2), Some(3)]),
+            Some(vec![]),
+            None,
+            Some(vec![Some(4), None]),
+        ]));
+        let offsets: ArrayRef = Arc::new(Int32Array::from(vec![10, 20, 30, 40]));
+        RecordBatch::try_from_iter_with_nullable(vec![("arr", array, true), ("o", offsets, true)])
+            .unwrap()
+    }
+
+    fn variable(name: &str) -> Arc<dyn PhysicalExpr> {
+        Arc::new(LambdaVariableExpr::new(
+            name.to_string(),
+            DataType::Int32,
+            true,
+        ))
+    }
+
+    fn binary(
+        l: Arc<dyn PhysicalExpr>,
+        op: Operator,
+        r: Arc<dyn PhysicalExpr>,
+    ) -> Arc<dyn PhysicalExpr> {
+        Arc::new(BinaryExpr::new(l, op, r))
+    }
+
+    #[test]
+    fn test_transform() -> Result<(), Box<dyn std::error::Error>> {
+        let batch = input_batch();
+
+        // transform(arr, (x, i) -> x + o + i)
+        let lambda = LambdaFunction::new(
+            binary(
+                binary(variable("x"), Operator::Plus, Arc::new(Column::new("o", 1))),
+                Operator::Plus,
+                variable("i"),
+            ),
+            vec!["x".to_string(), "i".to_string()],
+        );
+        let expr = HigherOrderFunctionExpr::try_new(
+            HigherOrderFunction::Transform,
+            Arc::new(Column::new("arr", 0)),
+            vec![],
+            vec![lambda],
+            DataType::List(Arc::new(Field::new("item", DataType::Int32, true))),
+        )?;
+        let output_array = expr.evaluate(&batch)?.into_array(0);
+        assert_eq!(
+            as_list_array(&output_array),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(11), Some(13), Some(15)]),
+                Some(vec![]),
+                None,
+                Some(vec![Some(44), None]),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_and_exists() -> Result<(), Box<dyn std::error::Error>> {
+        let batch = input_batch();
+
+        // x -> x % 2 = 0
+        let lambda = LambdaFunction::new(
+            binary(
+                binary(
+                    variable("x"),
+                    Operator::Modulo,
+                    Arc::new(Literal::new(ScalarValue::Int32(Some(2)))),
+                ),
+                Operator::Eq,
+                Arc::new(Literal::new(ScalarValue::Int32(Some(0)))),
+            ),
+            vec!["x".to_string()],
+        );
+        let filter_expr = HigherOrderFunctionExpr::try_new(
+            HigherOrderFunction::Filter,
+            Arc::new(Column::new("arr", 0)),
+            vec![],
+            vec![lambda.clone()],
+            DataType::List(Arc::new(Field::new("item", DataType::Int32, true))),
+        )?;
+        let output_array = filter_expr.evaluate(&batch)?.into_array(0);
+        assert_eq!(
+            as_list_array(&output_array),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(2)]),
+                Some(vec![]),
+                None,
+                Some(vec![Some(4)]),
+            ])
+        );
+
+        // x -> x % 2 = 1
+        let lambda = LambdaFunction::new(
+            binary(
+                binary(
+                    variable("x"),
+                    Operator::Modulo,
+                    Arc::new(Literal::new(ScalarValue::Int32(Some(2)))),
+                ),
+                Operator::Eq,
+                Arc::new(Literal::new(ScalarValue::Int32(Some(1)))),
+            ),
+            vec!["x".to_string()],
+        );
+        for (follow_three_valued_logic, expected) in [
+            (true, vec![Some(true), Some(false), None, None]),
+            (false, vec![Some(true), Some(false), None, Some(false)]),
+        ] {
+            let exists_expr = HigherOrderFunctionExpr::try_new(
+                HigherOrderFunction::Exists {
+                    follow_three_valued_logic,
+                },
+                Arc::new(Column::new("arr", 0)),
+                vec![],
+                vec![lambda.clone()],
+                DataType::Boolean,
+            )?;
+            let output_array = exists_expr.evaluate(&batch)?.into_array(0);
+            assert_eq!(
+                as_boolean_array(&output_array),
+                &BooleanArray::from(expected)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate() -> Result<(), Box<dyn std::error::Error>> {
+        let batch = input_batch();
+
+        // aggregate(arr, o, (acc, x) -> acc + x, acc -> acc * 10)
+        let merge = LambdaFunction::new(
+            binary(variable("acc"), Operator::Plus, variable("x")),
+            vec!["acc".to_string(), "x".to_string()],
+        );
+        let finish = LambdaFunction::new(
+            binary(
+                variable("acc"),
+                Operator::Multiply,
+                Arc::new(Literal::new(ScalarValue::Int32(Some(10)))),
+            ),
+            vec!["acc".to_string()],
+        );
+        let expr = HigherOrderFunctionExpr::try_new(
+            HigherOrderFunction::Aggregate,
+            Arc::new(Column::new("arr", 0)),
+            vec![Arc::new(Column::new("o", 1))],
+            vec![merge, finish],
+            DataType::Int32,
+        )?;
+        let output_array = expr.evaluate(&batch)?.into_array(0);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&output_array),
+            &Int32Array::from(vec![Some(160), Some(200), None, None])
+        );
+        Ok(())
+    }
+}
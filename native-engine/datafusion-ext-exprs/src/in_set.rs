@@ -0,0 +1,248 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use arrow::array::*;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::RecordBatch;
+use arrow::row::{RowConverter, SortField};
+use datafusion::common::{Result, ScalarValue};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion::physical_expr::PhysicalExpr;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::{any::Any, sync::Arc};
+
+/// expression of spark's IN/InSet with literal lists. values of the list are
+/// encoded in row format and stored in a hash set, which supports all types
+/// including binary, decimal and struct.
+///
+/// returns null if the value is null, or if the value is not found but the
+/// list contains null.
+///
+/// the same row converter is used for building the set and evaluating, so that
+/// encodings of dictionary values are consistent.
+pub struct InSetExpr {
+    value: Arc<dyn PhysicalExpr>,
+    list: Vec<ScalarValue>,
+    negated: bool,
+    data_type: DataType,
+    converter: Arc<Mutex<RowConverter>>,
+    set: Arc<HashSet<Vec<u8>>>,
+    set_contains_null: bool,
+}
+
+impl InSetExpr {
+    pub fn try_new(
+        value: Arc<dyn PhysicalExpr>,
+        list: Vec<ScalarValue>,
+        negated: bool,
+        input_schema: &Schema,
+    ) -> Result<Self> {
+        let data_type = value.data_type(input_schema)?;
+        let set_contains_null = list.iter().any(|v| v.is_null());
+        let non_null_values = list.iter().filter(|v| !v.is_null()).cloned();
+
+        let mut converter = RowConverter::new(vec![SortField::new(data_type.clone())])?;
+        let mut set = HashSet::new();
+        if list.len() > usize::from(set_contains_null) {
+            let values = ScalarValue::iter_to_array(non_null_values)?;
+            let values = cast(&values, &data_type)?;
+            let rows = converter.convert_columns(&[values])?;
+            set.extend(rows.iter().map(|row| row.as_ref().to_vec()));
+        }
+        Ok(Self {
+            value,
+            list,
+            negated,
+            data_type,
+            converter: Arc::new(Mutex::new(converter)),
+            set: Arc::new(set),
+            set_contains_null,
+        })
+    }
+}
+
+impl Debug for InSetExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InSetExpr")
+            .field("value", &self.value)
+            .field("list", &self.list)
+            .field("negated", &self.negated)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for InSetExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let not = if self.negated { "NOT " } else { "" };
+        write!(f, "{} {not}IN SET ({} values)", self.value, self.list.len())
+    }
+}
+
+impl Hash for InSetExpr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+        self.list.hash(state);
+        self.negated.hash(state);
+    }
+}
+
+impl PartialEq<dyn Any> for InSetExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| self.value.eq(&x.value) && self.list == x.list && self.negated == x.negated)
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for InSetExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+        Ok(self.set_contains_null || self.value.nullable(input_schema)?)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let values = self.value.evaluate(batch)?.into_array(batch.num_rows());
+        let values = cast(&values, &self.data_type)?;
+        let rows = self.converter.lock().convert_columns(&[values.clone()])?;
+
+        let result: BooleanArray = rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                if values.is_null(i) {
+                    return None;
+                }
+                if self.set.contains(row.as_ref()) {
+                    return Some(!self.negated);
+                }
+                (!self.set_contains_null).then_some(self.negated)
+            })
+            .collect();
+        Ok(ColumnarValue::Array(Arc::new(result)))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.value.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(Arc::new(Self {
+            value: children[0].clone(),
+            list: self.list.clone(),
+            negated: self.negated,
+            data_type: self.data_type.clone(),
+            converter: self.converter.clone(),
+            set: self.set.clone(),
+            set_contains_null: self.set_contains_null,
+        }))
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::in_set::InSetExpr;
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::PhysicalExpr;
+    use datafusion::scalar::ScalarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_in_set() -> Result<(), Box<dyn std::error::Error>> {
+        let array: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![Some(100), Some(200), None, Some(300)])
+                .with_precision_and_scale(10, 2)?,
+        );
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![("a", array, true)])?;
+        let value: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+
+        // a IN (1.00, 3.00)
+        let list = vec![
+            ScalarValue::Decimal128(Some(100), 10, 2),
+            ScalarValue::Decimal128(Some(300), 10, 2),
+        ];
+        let in_set = InSetExpr::try_new(value.clone(), list, false, &input_batch.schema())?;
+        let output_array = in_set.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_boolean_array(&output_array),
+            &BooleanArray::from(vec![Some(true), Some(false), None, Some(true)])
+        );
+
+        // a IN (1.00, null)
+        let list =
+            vec![ScalarValue::Decimal128(Some(100), 10, 2), ScalarValue::Decimal128(None, 10, 2)];
+        let in_set = InSetExpr::try_new(value.clone(), list.clone(), false, &input_batch.schema())?;
+        let output_array = in_set.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_boolean_array(&output_array),
+            &BooleanArray::from(vec![Some(true), None, None, None])
+        );
+
+        // a NOT IN (1.00, null)
+        let in_set = InSetExpr::try_new(value, list, true, &input_batch.schema())?;
+        let output_array = in_set.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_boolean_array(&output_array),
+            &BooleanArray::from(vec![Some(false), None, None, None])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_set_dictionary() -> Result<(), Box<dyn std::error::Error>> {
+        let array: DictionaryArray<Int32Type> =
+            vec![Some("x"), Some("b"), None, Some("c"), Some("a")]
+                .into_iter()
+                .collect();
+        let array: ArrayRef = Arc::new(array);
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![("a", array, true)])?;
+        let value: Arc<dyn PhysicalExpr> = Arc::new(Column::new("a", 0));
+
+        // a IN ('a', 'c')
+        let list = vec![
+            ScalarValue::Utf8(Some("a".to_string())),
+            ScalarValue::Utf8(Some("c".to_string())),
+        ];
+        let in_set = InSetExpr::try_new(value, list, false, &input_batch.schema())?;
+        let output_array = in_set.evaluate(&input_batch)?.into_array(0);
+        assert_eq!(
+            as_boolean_array(&output_array),
+            &BooleanArray::from(vec![Some(false), Some(false), None, Some(true), Some(true)])
+        );
+        Ok(())
+    }
+}
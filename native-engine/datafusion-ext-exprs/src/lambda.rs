@@ -0,0 +1,262 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::down_cast_any_ref;
+use arrow::array::*;
+use arrow::compute::take;
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::logical_expr::ColumnarValue;
+use datafusion::physical_expr::expressions::Column;
+use datafusion::physical_expr::PhysicalExpr;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::{any::Any, sync::Arc};
+
+/// expression to reference a lambda variable. lambda variables are bound as
+/// named columns of the batch which the lambda body is evaluated on.
+#[derive(Debug, Hash)]
+pub struct LambdaVariableExpr {
+    name: String,
+    data_type: DataType,
+    nullable: bool,
+}
+
+impl LambdaVariableExpr {
+    pub fn new(name: String, data_type: DataType, nullable: bool) -> Self {
+        Self {
+            name,
+            data_type,
+            nullable,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl std::fmt::Display for LambdaVariableExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LambdaVariable({})", self.name)
+    }
+}
+
+impl PartialEq<dyn Any> for LambdaVariableExpr {
+    fn eq(&self, other: &dyn Any) -> bool {
+        down_cast_any_ref(other)
+            .downcast_ref::<Self>()
+            .map(|x| {
+                self.name == x.name && self.data_type == x.data_type && self.nullable == x.nullable
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl PhysicalExpr for LambdaVariableExpr {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn data_type(&self, _input_schema: &Schema) -> Result<DataType> {
+        Ok(self.data_type.clone())
+    }
+
+    fn nullable(&self, _input_schema: &Schema) -> Result<bool> {
+        Ok(self.nullable)
+    }
+
+    fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+        let index = batch.schema().index_of(&self.name).map_err(|_| {
+            DataFusionError::Execution(format!("lambda variable not bound: {}", self.name))
+        })?;
+        Ok(ColumnarValue::Array(batch.column(index).clone()))
+    }
+
+    fn children(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        Ok(self)
+    }
+
+    fn dyn_hash(&self, state: &mut dyn Hasher) {
+        let mut s = state;
+        self.hash(&mut s);
+    }
+}
+
+/// lambda function with its body and names of the bound variables.
+#[derive(Debug, Clone, Hash)]
+pub struct LambdaFunction {
+    body: Arc<dyn PhysicalExpr>,
+    variable_names: Vec<String>,
+}
+
+impl LambdaFunction {
+    pub fn new(body: Arc<dyn PhysicalExpr>, variable_names: Vec<String>) -> Self {
+        Self {
+            body,
+            variable_names,
+        }
+    }
+
+    pub fn body(&self) -> &Arc<dyn PhysicalExpr> {
+        &self.body
+    }
+
+    pub fn variable_names(&self) -> &[String] {
+        &self.variable_names
+    }
+
+    pub fn with_new_body(&self, body: Arc<dyn PhysicalExpr>) -> Self {
+        Self::new(body, self.variable_names.clone())
+    }
+
+    /// Evaluates the body with the given variables, the i-th output row is
+    /// evaluated with the input row at `row_indices[i]` and the i-th values
+    /// of variables.
+    pub fn evaluate(
+        &self,
+        batch: &RecordBatch,
+        row_indices: &UInt32Array,
+        variables: Vec<ArrayRef>,
+    ) -> Result<ArrayRef> {
+        if variables.len() != self.variable_names.len() {
+            return Err(DataFusionError::Execution(format!(
+                "lambda function expects {} variables, got {}",
+                self.variable_names.len(),
+                variables.len(),
+            )));
+        }
+        let num_rows = row_indices.len();
+        let (mut fields, mut columns) = take_referenced_columns(batch, &self.body, row_indices)?;
+        for (name, variable) in self.variable_names.iter().zip(variables) {
+            fields.push(Field::new(name, variable.data_type().clone(), true));
+            columns.push(variable);
+        }
+
+        let lambda_batch = RecordBatch::try_new_with_options(
+            Arc::new(Schema::new(fields)),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(num_rows)),
+        )?;
+        Ok(self.body.evaluate(&lambda_batch)?.into_array(num_rows))
+    }
+}
+
+impl PartialEq for LambdaFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.body.eq(&other.body) && self.variable_names == other.variable_names
+    }
+}
+
+/// Takes rows of the batch for evaluating the expression. columns which are
+/// not referenced by the expression are replaced with null arrays, avoiding
+/// unnecessary copying while keeping the column indices.
+pub(crate) fn take_referenced_columns(
+    batch: &RecordBatch,
+    expr: &Arc<dyn PhysicalExpr>,
+    row_indices: &UInt32Array,
+) -> Result<(Vec<Field>, Vec<ArrayRef>)> {
+    let mut referenced_columns = HashSet::new();
+    let mut referenced_variables = HashSet::new();
+    collect_references(expr, &mut referenced_columns, &mut referenced_variables);
+
+    let mut fields = Vec::with_capacity(batch.num_columns());
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    for (i, field) in batch.schema().fields().iter().enumerate() {
+        if referenced_columns.contains(&i) || referenced_variables.contains(field.name()) {
+            fields.push(field.as_ref().clone());
+            columns.push(take(batch.column(i), row_indices, None)?);
+        } else {
+            fields.push(Field::new(field.name(), DataType::Null, true));
+            columns.push(new_null_array(&DataType::Null, row_indices.len()));
+        }
+    }
+    Ok((fields, columns))
+}
+
+fn collect_references(
+    expr: &Arc<dyn PhysicalExpr>,
+    columns: &mut HashSet<usize>,
+    variables: &mut HashSet<String>,
+) {
+    if let Some(column) = expr.as_any().downcast_ref::<Column>() {
+        columns.insert(column.index());
+    }
+    if let Some(variable) = expr.as_any().downcast_ref::<LambdaVariableExpr>() {
+        variables.insert(variable.name().to_string());
+    }
+    for child in expr.children() {
+        collect_references(&child, columns, variables);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lambda::{LambdaFunction, LambdaVariableExpr};
+    use arrow::array::*;
+    use arrow::datatypes::DataType;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{BinaryExpr, Column};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_lambda_function() -> Result<(), Box<dyn std::error::Error>> {
+        let input_batch = RecordBatch::try_from_iter_with_nullable(vec![
+            (
+                "a",
+                Arc::new(Int32Array::from(vec![10, 20])) as ArrayRef,
+                true,
+            ),
+            (
+                "b",
+                Arc::new(StringArray::from(vec!["x", "y"])) as ArrayRef,
+                true,
+            ),
+        ])?;
+
+        // x -> x + a
+        let lambda = LambdaFunction::new(
+            Arc::new(BinaryExpr::new(
+                Arc::new(LambdaVariableExpr::new(
+                    "x".to_string(),
+                    DataType::Int32,
+                    true,
+                )),
+                Operator::Plus,
+                Arc::new(Column::new("a", 0)),
+            )),
+            vec!["x".to_string()],
+        );
+        let output_array = lambda.evaluate(
+            &input_batch,
+            &UInt32Array::from(vec![0, 0, 1]),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )?;
+        assert_eq!(
+            as_primitive_array::<arrow::datatypes::Int32Type>(&output_array),
+            &Int32Array::from(vec![11, 12, 23])
+        );
+        Ok(())
+    }
+}
@@ -16,9 +16,16 @@ use datafusion::physical_expr::PhysicalExpr;
 use std::any::Any;
 use std::sync::Arc;
 
+pub mod binary_arithmetic;
+pub mod bloom_filter_might_contain;
+pub mod case_when;
 pub mod cast;
+pub mod column_literal_compare;
 pub mod get_indexed_field;
 pub mod get_map_value;
+pub mod higher_order_function;
+pub mod in_set;
+pub mod lambda;
 pub mod named_struct;
 pub mod spark_scalar_subquery_wrapper;
 pub mod spark_udf_wrapper;
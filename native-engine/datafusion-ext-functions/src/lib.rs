@@ -12,31 +12,67 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use arrow::datatypes::DataType;
 use datafusion::common::{DataFusionError, Result};
 use datafusion::logical_expr::ScalarFunctionImplementation;
 use std::sync::Arc;
 
+pub mod native_udf;
+mod spark_arrays;
 mod spark_check_overflow;
+mod spark_dates;
+mod spark_from_json;
 mod spark_get_json_object;
 mod spark_make_array;
 mod spark_make_decimal;
+mod spark_maps;
+mod spark_math;
 mod spark_murmur3_hash;
+mod spark_nanvl;
 mod spark_null_if_zero;
+mod spark_regexp;
 mod spark_strings;
+mod spark_structs;
 mod spark_unscaled_value;
+mod spark_xxhash64;
 
-pub fn create_spark_ext_function(name: &str) -> Result<ScalarFunctionImplementation> {
+pub fn create_spark_ext_function(
+    name: &str,
+    return_type: &DataType,
+) -> Result<ScalarFunctionImplementation> {
     Ok(match name {
         "Placeholder" => Arc::new(|_| panic!("placeholder() should never be called")),
         "NullIfZero" => Arc::new(spark_null_if_zero::spark_null_if_zero),
+        "NaNvl" => Arc::new(spark_nanvl::spark_nanvl),
+        "Pmod" => Arc::new(spark_math::spark_pmod),
+        "Factorial" => Arc::new(spark_math::spark_factorial),
+        "Hypot" => Arc::new(spark_math::spark_hypot),
+        "LogWithBase" => Arc::new(spark_math::spark_log_with_base),
+        "Rint" => Arc::new(spark_math::spark_rint),
+        "Round" => Arc::new(spark_math::spark_round),
+        "BRound" => Arc::new(spark_math::spark_bround),
         "UnscaledValue" => Arc::new(spark_unscaled_value::spark_unscaled_value),
         "MakeDecimal" => Arc::new(spark_make_decimal::spark_make_decimal),
         "CheckOverflow" => Arc::new(spark_check_overflow::spark_check_overflow),
         "Murmur3Hash" => Arc::new(spark_murmur3_hash::spark_murmur3_hash),
+        "XxHash64" => Arc::new(spark_xxhash64::spark_xxhash64),
         "GetJsonObject" => Arc::new(spark_get_json_object::spark_get_json_object),
         "GetParsedJsonObject" => Arc::new(spark_get_json_object::spark_get_parsed_json_object),
         "ParseJson" => Arc::new(spark_get_json_object::spark_parse_json),
+        "FromJson" => Arc::new(spark_from_json::spark_from_json),
         "MakeArray" => Arc::new(spark_make_array::array),
+        "ArraySize" => Arc::new(spark_arrays::array_size),
+        "ArrayContains" => Arc::new(spark_arrays::array_contains),
+        "ArraySort" => Arc::new(spark_arrays::array_sort),
+        "ArraySlice" => Arc::new(spark_arrays::array_slice),
+        "ArrayElementAt" => Arc::new(spark_arrays::array_element_at),
+        "ArrayJoin" => Arc::new(spark_arrays::array_join),
+        "MapKeys" => Arc::new(spark_maps::map_keys),
+        "MapValues" => Arc::new(spark_maps::map_values),
+        "MapFromArrays" => Arc::new(spark_maps::map_from_arrays),
+        "MapConcat" => Arc::new(spark_maps::map_concat),
+        "MapGetValue" => Arc::new(spark_maps::map_get_value),
+        "StructEqual" => Arc::new(spark_structs::struct_equal),
         "StringSpace" => Arc::new(spark_strings::string_space),
         "StringRepeat" => Arc::new(spark_strings::string_repeat),
         "StringSplit" => Arc::new(spark_strings::string_split),
@@ -44,10 +80,27 @@ pub fn create_spark_ext_function(name: &str) -> Result<ScalarFunctionImplementat
         "StringConcatWs" => Arc::new(spark_strings::string_concat_ws),
         "StringLower" => Arc::new(spark_strings::string_lower),
         "StringUpper" => Arc::new(spark_strings::string_upper),
+        "StringLPad" => Arc::new(spark_strings::string_lpad),
+        "StringRPad" => Arc::new(spark_strings::string_rpad),
+        "StringTranslate" => Arc::new(spark_strings::string_translate),
+        "StringOverlay" => Arc::new(spark_strings::string_overlay),
+        "RegexpLike" => Arc::new(spark_regexp::regexp_like),
+        "RegexpReplace" => Arc::new(spark_regexp::regexp_replace),
+        "RegexpExtract" => Arc::new(spark_regexp::regexp_extract),
+        "DateAdd" => Arc::new(spark_dates::date_add),
+        "DateSub" => Arc::new(spark_dates::date_sub),
+        "DateDiff" => Arc::new(spark_dates::date_diff),
+        "TruncDate" => Arc::new(spark_dates::trunc_date),
+        "DateFormat" => Arc::new(spark_dates::date_format),
+        "FromUnixTime" => Arc::new(spark_dates::from_unixtime),
+        "UnixTimestamp" => Arc::new(spark_dates::unix_timestamp),
 
-        _ => Err(DataFusionError::NotImplemented(format!(
-            "spark ext function not implemented: {}",
-            name
-        )))?,
+        _ => match name.strip_prefix("NativeUDF:") {
+            Some(udf_name) => native_udf::get_native_udf(udf_name, return_type)?,
+            None => Err(DataFusionError::NotImplemented(format!(
+                "spark ext function not implemented: {}",
+                name
+            )))?,
+        },
     })
 }
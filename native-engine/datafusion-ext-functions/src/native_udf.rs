@@ -0,0 +1,299 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Registry of user-defined native functions.
+//!
+//! native udfs are loaded from plugin libraries (cdylib) configured in
+//! `spark.blaze.nativeUdf.libraries`, and referenced by name from the spark
+//! side. a plugin library exports a registering entry point with C ABI:
+//!
+//! ```c
+//! void blaze_register_native_udfs(NativeUdfRegisterFn register);
+//! ```
+//!
+//! which calls `register(name, func)` for each provided function. arguments
+//! and results are exchanged with the arrow C data interface, see
+//! [`NativeUdfFn`] for details.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::{make_array, Array, ArrayRef};
+use arrow::datatypes::DataType;
+use arrow::ffi::{from_ffi, to_ffi, FFI_ArrowArray, FFI_ArrowSchema};
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static, jni_get_string};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::logical_expr::ScalarFunctionImplementation;
+use datafusion::physical_plan::ColumnarValue;
+use libloading::Library;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr};
+use std::sync::Arc;
+
+/// signature of native udfs.
+///
+/// `args` and `arg_schemas` point to `num_args` arrays with `num_rows` rows.
+/// the function may take ownership of an argument by moving it out and
+/// leaving a released (empty) struct in place, arguments not moved are
+/// released by the caller. the result is exported into `result` and
+/// `result_schema`, which are initialized as empty structs.
+///
+/// returns 0 on success, or non-zero with a null-terminated error message
+/// written into `err_msg` (no more than `err_msg_len` bytes).
+pub type NativeUdfFn = unsafe extern "C" fn(
+    args: *mut FFI_ArrowArray,
+    arg_schemas: *const FFI_ArrowSchema,
+    num_args: usize,
+    num_rows: usize,
+    result: *mut FFI_ArrowArray,
+    result_schema: *mut FFI_ArrowSchema,
+    err_msg: *mut c_char,
+    err_msg_len: usize,
+) -> i32;
+
+/// signature of the callback passed to `blaze_register_native_udfs`.
+pub type NativeUdfRegisterFn = unsafe extern "C" fn(name: *const c_char, func: NativeUdfFn);
+
+const REGISTER_SYMBOL: &[u8] = b"blaze_register_native_udfs";
+const ERR_MSG_LEN: usize = 4096;
+
+#[derive(Default)]
+struct Registry {
+    udfs: HashMap<String, NativeUdfFn>,
+    libraries: Vec<Library>, // keeps loaded libraries alive
+    configured_libraries_loaded: bool,
+    configured_libraries_error: Option<String>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceCell<Mutex<Registry>> = OnceCell::new();
+    REGISTRY.get_or_init(|| Mutex::default())
+}
+
+/// Registers a native udf, replacing any previously registered one with the
+/// same name.
+pub fn register_native_udf(name: &str, func: NativeUdfFn) {
+    registry().lock().udfs.insert(name.to_string(), func);
+}
+
+/// Loads a plugin library and registers all native udfs it provides.
+pub fn load_native_udf_library(path: &str) -> Result<()> {
+    let mut registry = registry().lock();
+    load_library(&mut registry, path)
+}
+
+fn load_library(registry: &mut Registry, path: &str) -> Result<()> {
+    unsafe extern "C" fn register(name: *const c_char, func: NativeUdfFn) {
+        let name = CStr::from_ptr(name).to_string_lossy().to_string();
+        log::info!("registering native udf: {name}");
+        REGISTERING.with(|udfs| udfs.borrow_mut().push((name, func)));
+    }
+    thread_local! {
+        static REGISTERING: std::cell::RefCell<Vec<(String, NativeUdfFn)>> = Default::default();
+    }
+
+    log::info!("loading native udf library: {path}");
+    let lib_err = |err: libloading::Error| {
+        DataFusionError::Execution(format!("error loading native udf library {path}: {err}"))
+    };
+    let library = unsafe { Library::new(path) }.map_err(lib_err)?;
+    unsafe {
+        let register_udfs = library
+            .get::<unsafe extern "C" fn(NativeUdfRegisterFn)>(REGISTER_SYMBOL)
+            .map_err(lib_err)?;
+        register_udfs(register);
+    }
+    for (name, func) in REGISTERING.with(|udfs| std::mem::take(&mut *udfs.borrow_mut())) {
+        registry.udfs.insert(name, func);
+    }
+    registry.libraries.push(library);
+    Ok(())
+}
+
+/// Finds a registered native udf returning values of `return_type`, plugin
+/// libraries configured in spark are loaded on the first call. loading is
+/// never retried, udfs of libraries failed to load are reported as not
+/// registered with the loading error.
+pub(crate) fn get_native_udf(
+    name: &str,
+    return_type: &DataType,
+) -> Result<ScalarFunctionImplementation> {
+    let mut registry = registry().lock();
+    if !registry.configured_libraries_loaded && is_jni_bridge_inited() {
+        registry.configured_libraries_loaded = true;
+        if let Err(err) = load_configured_libraries(&mut registry) {
+            log::error!("error loading configured native udf libraries: {err}");
+            registry.configured_libraries_error = Some(err.to_string());
+        }
+    }
+
+    let func = *registry.udfs.get(name).ok_or_else(|| {
+        DataFusionError::Plan(match &registry.configured_libraries_error {
+            Some(err) => format!("native udf not registered: {name} ({err})"),
+            None => format!("native udf not registered: {name}"),
+        })
+    })?;
+    let name = name.to_string();
+    let return_type = return_type.clone();
+    Ok(Arc::new(move |args: &[ColumnarValue]| {
+        invoke_native_udf(&name, func, &return_type, args).map(ColumnarValue::Array)
+    }))
+}
+
+fn load_configured_libraries(registry: &mut Registry) -> Result<()> {
+    let paths = jni_call_static!(BlazeConf.nativeUdfLibraries() -> JObject)?;
+    let paths = jni_get_string!(paths.as_obj().into())?;
+    for path in paths
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        load_library(registry, path)?;
+    }
+    Ok(())
+}
+
+fn invoke_native_udf(
+    name: &str,
+    func: NativeUdfFn,
+    return_type: &DataType,
+    args: &[ColumnarValue],
+) -> Result<ArrayRef> {
+    let arrays = args_to_arrays(args);
+    let num_rows = arrays.first().map(|array| array.len()).unwrap_or(1);
+    let (mut ffi_args, ffi_arg_schemas): (Vec<_>, Vec<_>) = arrays
+        .iter()
+        .map(|array| to_ffi(&array.to_data()))
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+
+    let mut ffi_result = FFI_ArrowArray::empty();
+    let mut ffi_result_schema = FFI_ArrowSchema::empty();
+    let mut err_msg = vec![0 as c_char; ERR_MSG_LEN];
+    let ret = unsafe {
+        func(
+            ffi_args.as_mut_ptr(),
+            ffi_arg_schemas.as_ptr(),
+            ffi_args.len(),
+            num_rows,
+            &mut ffi_result,
+            &mut ffi_result_schema,
+            err_msg.as_mut_ptr(),
+            err_msg.len(),
+        )
+    };
+    if ret != 0 {
+        err_msg[ERR_MSG_LEN - 1] = 0;
+        let err_msg = unsafe { CStr::from_ptr(err_msg.as_ptr()) }.to_string_lossy();
+        return Err(DataFusionError::Execution(format!(
+            "native udf {name} failed ({ret}): {err_msg}"
+        )));
+    }
+
+    let result = make_array(from_ffi(ffi_result, &ffi_result_schema)?);
+    if result.len() != num_rows {
+        return Err(DataFusionError::Execution(format!(
+            "native udf {name} returned {} rows, expected {num_rows}",
+            result.len(),
+        )));
+    }
+    if result.data_type() != return_type {
+        return Err(DataFusionError::Execution(format!(
+            "native udf {name} returned {}, expected {return_type}",
+            result.data_type(),
+        )));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::native_udf::{get_native_udf, register_native_udf};
+    use arrow::array::*;
+    use arrow::datatypes::{DataType, Int32Type};
+    use arrow::ffi::{from_ffi, to_ffi, FFI_ArrowArray, FFI_ArrowSchema};
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::ffi::c_char;
+    use std::sync::Arc;
+
+    // a + b for int32 arrays, implemented like a plugin library would do
+    #[allow(clippy::too_many_arguments)]
+    unsafe extern "C" fn plus(
+        args: *mut FFI_ArrowArray,
+        arg_schemas: *const FFI_ArrowSchema,
+        num_args: usize,
+        _num_rows: usize,
+        result: *mut FFI_ArrowArray,
+        result_schema: *mut FFI_ArrowSchema,
+        err_msg: *mut c_char,
+        _err_msg_len: usize,
+    ) -> i32 {
+        if num_args != 2 {
+            *err_msg = b'!' as c_char;
+            *err_msg.add(1) = 0;
+            return 1;
+        }
+        let args = (0..num_args)
+            .map(|i| {
+                let arg = std::ptr::replace(args.add(i), FFI_ArrowArray::empty());
+                make_array(from_ffi(arg, &*arg_schemas.add(i)).unwrap())
+            })
+            .collect::<Vec<_>>();
+        let sum: Int32Array = as_primitive_array::<Int32Type>(&args[0])
+            .iter()
+            .zip(as_primitive_array::<Int32Type>(&args[1]))
+            .map(|(a, b)| Some(a? + b?))
+            .collect();
+        let (array, schema) = to_ffi(&sum.to_data()).unwrap();
+        std::ptr::write(result, array);
+        std::ptr::write(result_schema, schema);
+        0
+    }
+
+    #[test]
+    fn test_native_udf() -> Result<()> {
+        register_native_udf("test_plus", plus);
+        let udf = get_native_udf("test_plus", &DataType::Int32)?;
+        let r = udf(&[
+            ColumnarValue::Array(Arc::new(Int32Array::from(vec![Some(1), Some(2), None]))),
+            ColumnarValue::Scalar(ScalarValue::Int32(Some(10))),
+        ])?
+        .into_array(3);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![Some(11), Some(12), None])
+        );
+
+        let r = udf(&[ColumnarValue::Scalar(ScalarValue::Int32(Some(10)))]);
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains("native udf test_plus failed"));
+        assert!(get_native_udf("test_not_registered", &DataType::Int32).is_err());
+
+        // returning values of an unexpected type
+        let udf = get_native_udf("test_plus", &DataType::Int64)?;
+        let r = udf(&[
+            ColumnarValue::Scalar(ScalarValue::Int32(Some(1))),
+            ColumnarValue::Scalar(ScalarValue::Int32(Some(10))),
+        ]);
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .contains("native udf test_plus returned Int32, expected Int64"));
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::*;
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::compute::{cast, sort_to_indices, take, SortOptions};
+use arrow::datatypes::{DataType, FieldRef, Int32Type};
+use arrow::row::{RowConverter, SortField};
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use datafusion::physical_plan::ColumnarValue;
+use itertools::Itertools;
+use std::sync::Arc;
+
+/// size() function compatible with spark, returns number of elements of arrays
+/// or maps. the second argument is spark.sql.legacy.sizeOfNull, which makes
+/// size(null) return -1 instead of null.
+pub fn array_size(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let legacy_size_of_null = match &args[1] {
+        ColumnarValue::Scalar(ScalarValue::Boolean(Some(legacy))) => *legacy,
+        _ => false,
+    };
+    let array = args[0].clone().into_array(1);
+    let value_offsets = match array.data_type() {
+        DataType::List(_) => as_list_array(&array).value_offsets(),
+        DataType::Map(..) => as_map_array(&array).value_offsets(),
+        other => {
+            return Err(DataFusionError::Execution(format!(
+                "size() not supported for type: {other}"
+            )));
+        }
+    };
+
+    let sizes: Int32Array = value_offsets
+        .iter()
+        .tuple_windows()
+        .enumerate()
+        .map(|(i, (start, end))| match array.is_valid(i) {
+            true => Some(end - start),
+            false => legacy_size_of_null.then_some(-1),
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(sizes)))
+}
+
+/// array_contains() function compatible with spark, returns null if the value
+/// is not found but the array contains nulls.
+pub fn array_contains(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let list = as_list_array(&arrays[0]);
+    let value_offsets = list.value_offsets();
+    let items = list.values();
+
+    // compare items and values in row format, which supports nested types
+    let mut converter = RowConverter::new(vec![SortField::new(items.data_type().clone())])?;
+    let item_rows = converter.convert_columns(&[items.clone()])?;
+    let value_rows = converter.convert_columns(&[cast(&arrays[1], items.data_type())?])?;
+
+    let contains: BooleanArray = (0..list.len())
+        .map(|i| {
+            if list.is_null(i) || arrays[1].is_null(i) {
+                return None;
+            }
+            let item_range = value_offsets[i] as usize..value_offsets[i + 1] as usize;
+            let mut has_null = false;
+            for j in item_range {
+                if items.is_null(j) {
+                    has_null = true;
+                } else if item_rows.row(j) == value_rows.row(i) {
+                    return Some(true);
+                }
+            }
+            (!has_null).then_some(false)
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(contains)))
+}
+
+/// sort_array() function compatible with spark, nulls are placed first in
+/// ascending order and last in descending order.
+pub fn array_sort(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let ascending = match &args[1] {
+        ColumnarValue::Scalar(ScalarValue::Boolean(Some(ascending))) => *ascending,
+        _ => {
+            return Err(DataFusionError::Execution(
+                "sort_array() ascending only supports literal boolean".to_string(),
+            ));
+        }
+    };
+    let array = args[0].clone().into_array(1);
+    let list = as_list_array(&array);
+    let value_offsets = list.value_offsets();
+    let sort_options = SortOptions {
+        descending: !ascending,
+        nulls_first: ascending,
+    };
+
+    let mut indices = Vec::with_capacity(list.values().len());
+    for (i, (&start, &end)) in value_offsets.iter().tuple_windows().enumerate() {
+        if list.is_valid(i) {
+            let items = list.values().slice(start as usize, (end - start) as usize);
+            let sorted_indices = sort_to_indices(&items, Some(sort_options), None)?;
+            indices.extend(sorted_indices.values().iter().map(|&j| start as u32 + j));
+        } else {
+            indices.extend(start as u32..end as u32);
+        }
+    }
+    let sorted_items = take(list.values(), &UInt32Array::from(indices), None)?;
+    let sorted = ListArray::try_new(
+        list_field(list),
+        offsets_from_zero(value_offsets),
+        sorted_items,
+        list.nulls().cloned(),
+    )?;
+    Ok(ColumnarValue::Array(Arc::new(sorted)))
+}
+
+/// slice() function compatible with spark, returns elements from the 1-based
+/// start (negative to count from the end) with the given length.
+pub fn array_slice(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let list = as_list_array(&arrays[0]);
+    let starts = cast(&arrays[1], &DataType::Int32)?;
+    let starts = as_primitive_array::<Int32Type>(&starts);
+    let lengths = cast(&arrays[2], &DataType::Int32)?;
+    let lengths = as_primitive_array::<Int32Type>(&lengths);
+    let value_offsets = list.value_offsets();
+
+    let mut indices = vec![];
+    let mut offsets = vec![0i32];
+    let mut valids = vec![];
+    for i in 0..list.len() {
+        if list.is_null(i) || starts.is_null(i) || lengths.is_null(i) {
+            valids.push(false);
+            offsets.push(indices.len() as i32);
+            continue;
+        }
+        let (start, length) = (starts.value(i), lengths.value(i));
+        if start == 0 {
+            return Err(DataFusionError::Execution(
+                "Unexpected value for start in function slice: \
+                 SQL array indices start at 1."
+                    .to_string(),
+            ));
+        }
+        if length < 0 {
+            return Err(DataFusionError::Execution(
+                "Unexpected value for length in function slice: \
+                 length must be greater than or equal to 0."
+                    .to_string(),
+            ));
+        }
+
+        let num_items = value_offsets[i + 1] - value_offsets[i];
+        let start_index = if start < 0 {
+            start + num_items
+        } else {
+            start - 1
+        };
+        if (0..num_items).contains(&start_index) {
+            let end_index = start_index + length.min(num_items - start_index);
+            let first = value_offsets[i] as u32;
+            indices.extend(first + start_index as u32..first + end_index as u32);
+        }
+        valids.push(true);
+        offsets.push(indices.len() as i32);
+    }
+
+    let sliced_items = take(list.values(), &UInt32Array::from(indices), None)?;
+    let sliced = ListArray::try_new(
+        list_field(list),
+        OffsetBuffer::new(offsets.into()),
+        sliced_items,
+        Some(NullBuffer::from(valids)),
+    )?;
+    Ok(ColumnarValue::Array(Arc::new(sliced)))
+}
+
+/// element_at() function of arrays compatible with spark (non-ansi mode),
+/// returns the element at the 1-based index (negative to count from the end),
+/// or null if the index is out of bounds.
+pub fn array_element_at(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let list = as_list_array(&arrays[0]);
+    let indices = cast(&arrays[1], &DataType::Int32)?;
+    let indices = as_primitive_array::<Int32Type>(&indices);
+    let value_offsets = list.value_offsets();
+
+    let take_indices = (0..list.len())
+        .map(|i| {
+            if list.is_null(i) || indices.is_null(i) {
+                return Ok(None);
+            }
+            let num_items = value_offsets[i + 1] - value_offsets[i];
+            let index = match indices.value(i) {
+                0 => {
+                    return Err(DataFusionError::Execution(
+                        "SQL array indices start at 1".to_string(),
+                    ));
+                }
+                index if index > 0 => index - 1,
+                index => num_items + index,
+            };
+            Ok((0..num_items)
+                .contains(&index)
+                .then_some((value_offsets[i] + index) as u32))
+        })
+        .collect::<Result<UInt32Array>>()?;
+    Ok(ColumnarValue::Array(take(
+        list.values(),
+        &take_indices,
+        None,
+    )?))
+}
+
+/// array_join() function compatible with spark, null elements are skipped
+/// unless a null replacement (the optional third argument) is given.
+pub fn array_join(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let list = as_list_array(&arrays[0]);
+    let items = as_string_array(list.values());
+    let delimiters = as_string_array(&arrays[1]);
+    let null_replacements = arrays.get(2).map(|array| as_string_array(array));
+    let value_offsets = list.value_offsets();
+
+    let joined: StringArray = (0..list.len())
+        .map(|i| {
+            if list.is_null(i) || delimiters.is_null(i) {
+                return None;
+            }
+            let null_replacement = match null_replacements {
+                Some(null_replacements) if null_replacements.is_null(i) => return None,
+                Some(null_replacements) => Some(null_replacements.value(i)),
+                None => None,
+            };
+            let item_range = value_offsets[i] as usize..value_offsets[i + 1] as usize;
+            Some(
+                item_range
+                    .filter_map(|j| match items.is_valid(j) {
+                        true => Some(items.value(j)),
+                        false => null_replacement,
+                    })
+                    .join(delimiters.value(i)),
+            )
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(joined)))
+}
+
+fn list_field(list: &ListArray) -> FieldRef {
+    match list.data_type() {
+        DataType::List(field) => field.clone(),
+        _ => unreachable!("expect list type"),
+    }
+}
+
+/// Offsets of list values after being taken into a new array
+fn offsets_from_zero(value_offsets: &[i32]) -> OffsetBuffer<i32> {
+    let first = value_offsets[0];
+    OffsetBuffer::new(
+        value_offsets
+            .iter()
+            .map(|&offset| offset - first)
+            .collect::<Vec<_>>()
+            .into(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_arrays::{
+        array_contains, array_element_at, array_join, array_size, array_slice, array_sort,
+    };
+    use arrow::array::*;
+    use arrow::datatypes::Int32Type;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    fn int_lists(lists: Vec<Option<Vec<Option<i32>>>>) -> ColumnarValue {
+        ColumnarValue::Array(Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(
+            lists,
+        )))
+    }
+
+    #[test]
+    fn test_array_size() -> Result<()> {
+        let lists = int_lists(vec![Some(vec![Some(1), None]), Some(vec![]), None]);
+        let r = array_size(&[lists.clone(), ColumnarValue::Scalar(ScalarValue::from(false))])?;
+        let r = r.into_array(3);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![Some(2), Some(0), None])
+        );
+
+        let r = array_size(&[lists, ColumnarValue::Scalar(ScalarValue::from(true))])?;
+        let r = r.into_array(3);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![2, 0, -1])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_contains() -> Result<()> {
+        let lists = int_lists(vec![
+            Some(vec![Some(1), Some(2)]),
+            Some(vec![Some(1), None]),
+            Some(vec![Some(3), None]),
+            Some(vec![Some(4)]),
+            None,
+        ]);
+        let r = array_contains(&[lists, ColumnarValue::Scalar(ScalarValue::Int32(Some(3)))])?;
+        let r = r.into_array(5);
+        assert_eq!(
+            as_boolean_array(&r),
+            &BooleanArray::from(vec![Some(false), None, Some(true), Some(false), None])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_sort() -> Result<()> {
+        let lists = int_lists(vec![
+            Some(vec![Some(3), None, Some(1), Some(2)]),
+            None,
+            Some(vec![]),
+            Some(vec![Some(5), Some(4)]),
+        ]);
+        let r = array_sort(&[lists.clone(), ColumnarValue::Scalar(ScalarValue::from(true))])?;
+        let r = r.into_array(4);
+        assert_eq!(
+            as_list_array(&r),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![None, Some(1), Some(2), Some(3)]),
+                None,
+                Some(vec![]),
+                Some(vec![Some(4), Some(5)]),
+            ])
+        );
+
+        let r = array_sort(&[lists, ColumnarValue::Scalar(ScalarValue::from(false))])?;
+        let r = r.into_array(4);
+        assert_eq!(
+            as_list_array(&r),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(3), Some(2), Some(1), None]),
+                None,
+                Some(vec![]),
+                Some(vec![Some(5), Some(4)]),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_slice() -> Result<()> {
+        let lists = int_lists(vec![
+            Some(vec![Some(1), Some(2), Some(3), Some(4)]),
+            Some(vec![Some(1), Some(2), Some(3), Some(4)]),
+            Some(vec![Some(1), Some(2), Some(3), Some(4)]),
+            Some(vec![Some(1), Some(2)]),
+            None,
+        ]);
+        let starts = ColumnarValue::Array(Arc::new(Int32Array::from(vec![2, -2, -5, 3, 1])));
+        let lengths = ColumnarValue::Scalar(ScalarValue::Int32(Some(2)));
+        let r = array_slice(&[lists.clone(), starts, lengths.clone()])?.into_array(5);
+        assert_eq!(
+            as_list_array(&r),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(2), Some(3)]),
+                Some(vec![Some(3), Some(4)]),
+                Some(vec![]),
+                Some(vec![]),
+                None,
+            ])
+        );
+
+        let zero_start = ColumnarValue::Scalar(ScalarValue::Int32(Some(0)));
+        assert!(array_slice(&[lists.clone(), zero_start, lengths]).is_err());
+        let negative_length = ColumnarValue::Scalar(ScalarValue::Int32(Some(-1)));
+        let start = ColumnarValue::Scalar(ScalarValue::Int32(Some(1)));
+        assert!(array_slice(&[lists, start, negative_length]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_element_at() -> Result<()> {
+        let lists = int_lists(vec![
+            Some(vec![Some(1), Some(2), Some(3)]),
+            Some(vec![Some(1), Some(2), Some(3)]),
+            Some(vec![Some(1), Some(2), Some(3)]),
+            None,
+        ]);
+        let indices = ColumnarValue::Array(Arc::new(Int32Array::from(vec![1, -1, 4, 1])));
+        let r = array_element_at(&[lists.clone(), indices])?.into_array(4);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![Some(1), Some(3), None, None])
+        );
+
+        let zero_index = ColumnarValue::Scalar(ScalarValue::Int32(Some(0)));
+        assert!(array_element_at(&[lists, zero_index]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_join() -> Result<()> {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        builder.append_value([Some("a"), None, Some("c")]);
+        builder.append_value([None::<&str>]);
+        builder.append_null();
+        let lists = ColumnarValue::Array(Arc::new(builder.finish()));
+        let delimiter = ColumnarValue::Scalar(ScalarValue::from(","));
+
+        let r = array_join(&[lists.clone(), delimiter.clone()])?.into_array(3);
+        assert_eq!(
+            as_string_array(&r),
+            &StringArray::from(vec![Some("a,c"), Some(""), None])
+        );
+
+        let null_replacement = ColumnarValue::Scalar(ScalarValue::from("-"));
+        let r = array_join(&[lists.clone(), delimiter.clone(), null_replacement])?;
+        let r = r.into_array(3);
+        assert_eq!(
+            as_string_array(&r),
+            &StringArray::from(vec![Some("a,-,c"), Some("-"), None])
+        );
+
+        let null_replacement = ColumnarValue::Scalar(ScalarValue::Utf8(None));
+        let r = array_join(&[lists, delimiter, null_replacement])?.into_array(3);
+        assert_eq!(r.null_count(), 3);
+        Ok(())
+    }
+}
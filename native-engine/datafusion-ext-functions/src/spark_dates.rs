@@ -0,0 +1,792 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::timezone::Tz;
+use arrow::array::{
+    Array, Date32Array, Int32Array, Int64Array, StringArray, TimestampMicrosecondArray,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, TimeUnit};
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Timelike,
+};
+use datafusion::common::cast::{
+    as_date32_array, as_int32_array, as_int64_array, as_string_array,
+    as_timestamp_microsecond_array,
+};
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use datafusion::physical_plan::ColumnarValue;
+use std::sync::Arc;
+
+/// days from 0001-01-01 to 1970-01-01
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAY_NAMES: [&str; 7] =
+    ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+/// date_add() function compatible with spark
+/// date_add('2023-01-30', 3) = '2023-02-02'
+pub fn date_add(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    date_add_days(args, false)
+}
+
+/// date_sub() function compatible with spark
+/// date_sub('2023-03-01', 1) = '2023-02-28'
+pub fn date_sub(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    date_add_days(args, true)
+}
+
+fn date_add_days(args: &[ColumnarValue], negated: bool) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let dates = as_date32_array(&arrays[0])?;
+    let days = cast(&arrays[1], &DataType::Int32)?; // days can be byte/short/int
+    let days = as_int32_array(&days)?;
+
+    // int arithmetics overflow like java
+    let added: Date32Array = dates
+        .iter()
+        .zip(days.iter())
+        .map(|(date, days)| match negated {
+            false => Some(date?.wrapping_add(days?)),
+            true => Some(date?.wrapping_sub(days?)),
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(added)))
+}
+
+/// datediff() function compatible with spark, returns the number of days from
+/// start to end
+/// datediff('2023-03-01', '2023-02-01') = 28
+pub fn date_diff(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let ends = as_date32_array(&arrays[0])?;
+    let starts = as_date32_array(&arrays[1])?;
+
+    let diffs: Int32Array = ends
+        .iter()
+        .zip(starts.iter())
+        .map(|(end, start)| Some(end?.wrapping_sub(start?)))
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(diffs)))
+}
+
+/// trunc() function compatible with spark, truncates dates to the first day of
+/// the year, quarter, month or week (starting on monday). returns null for
+/// other formats.
+/// trunc('2023-05-17', 'quarter') = '2023-04-01'
+pub fn trunc_date(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let dates = as_date32_array(&arrays[0])?;
+    let formats = as_string_array(&arrays[1])?;
+
+    let truncated: Date32Array = dates
+        .iter()
+        .zip(formats.iter())
+        .map(|(date, format)| {
+            let date = date_from_epoch_days(date?)?;
+            let truncated = match format?.to_uppercase().as_str() {
+                "YEAR" | "YYYY" | "YY" => date.with_ordinal(1)?,
+                "QUARTER" => NaiveDate::from_ymd_opt(date.year(), date.month0() / 3 * 3 + 1, 1)?,
+                "MONTH" | "MON" | "MM" => date.with_day(1)?,
+                "WEEK" => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+                _ => return None,
+            };
+            Some(truncated.num_days_from_ce() - UNIX_EPOCH_DAYS_FROM_CE)
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(truncated)))
+}
+
+/// date_format() function compatible with spark, formats timestamps in the
+/// session timezone (the third argument) with java's datetime pattern.
+/// date_format('2023-01-02 03:04:05', 'yyyy/MM/dd HH:mm') = '2023/01/02 03:04'
+pub fn date_format(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let timezone = timezone_arg(&args[2], "date_format")?;
+    let arrays = args_to_arrays(&args[..2]);
+    let timestamps = as_timestamp_microsecond_array(&arrays[0])?;
+    let formats = as_string_array(&arrays[1])?;
+
+    // constant formats are parsed only once per batch
+    let mut parsed_format: Option<(&str, Vec<DateFormatToken>)> = None;
+    let formatted: StringArray = (0..timestamps.len())
+        .map(|i| {
+            if arrays.iter().any(|array| array.is_null(i)) {
+                return Ok(None);
+            }
+            let format = formats.value(i);
+            if !matches!(parsed_format, Some((parsed, _)) if parsed == format) {
+                parsed_format = Some((format, parse_date_format(format)?));
+            }
+            let tokens = &parsed_format.as_ref().unwrap().1;
+
+            let datetime = NaiveDateTime::from_timestamp_micros(timestamps.value(i))
+                .map(|datetime| timezone.from_utc_datetime(&datetime))
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "date_format timestamp out of range: {}",
+                        timestamps.value(i)
+                    ))
+                })?;
+            Ok(Some(format_datetime(&datetime, tokens)))
+        })
+        .collect::<Result<_>>()?;
+    Ok(ColumnarValue::Array(Arc::new(formatted)))
+}
+
+/// from_unixtime() function compatible with spark, formats seconds since epoch
+/// like date_format()
+/// from_unixtime(0, 'yyyy-MM-dd HH:mm:ss') = '1970-01-01 00:00:00' (in UTC)
+pub fn from_unixtime(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(&args[..2]);
+    let seconds = cast(&arrays[0], &DataType::Int64)?;
+    let timestamps: TimestampMicrosecondArray = as_int64_array(&seconds)?
+        .iter()
+        .map(|seconds| {
+            seconds
+                .map(|seconds| {
+                    seconds.checked_mul(1_000_000).ok_or_else(|| {
+                        DataFusionError::Execution(format!(
+                            "from_unixtime seconds out of range: {seconds}"
+                        ))
+                    })
+                })
+                .transpose()
+        })
+        .collect::<Result<_>>()?;
+
+    date_format(&[
+        ColumnarValue::Array(Arc::new(timestamps)),
+        ColumnarValue::Array(arrays[1].clone()),
+        args[2].clone(),
+    ])
+}
+
+/// unix_timestamp()/to_unix_timestamp() function compatible with spark,
+/// returns seconds since epoch of timestamps, dates (at the start of day in the
+/// session timezone) or strings parsed with java's datetime pattern. strings
+/// not matching the pattern result in null.
+/// unix_timestamp('2023-01-02 03:04:05', 'yyyy-MM-dd HH:mm:ss') = 1672628645 (in UTC)
+pub fn unix_timestamp(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let timezone = timezone_arg(&args[2], "unix_timestamp")?;
+    let arrays = args_to_arrays(&args[..2]);
+
+    let seconds: Int64Array = match arrays[0].data_type() {
+        DataType::Timestamp(TimeUnit::Microsecond, _) => {
+            as_timestamp_microsecond_array(&arrays[0])?
+                .iter()
+                .map(|micros| Some(micros?.div_euclid(1_000_000)))
+                .collect()
+        }
+        DataType::Date32 => as_date32_array(&arrays[0])?
+            .iter()
+            .map(|days| {
+                let start_of_day = date_from_epoch_days(days?)?.and_hms_opt(0, 0, 0)?;
+                Some(resolve_local_datetime(&timezone, &start_of_day)?.timestamp())
+            })
+            .collect(),
+        DataType::Utf8 => {
+            let strings = as_string_array(&arrays[0])?;
+            let formats = as_string_array(&arrays[1])?;
+
+            // constant formats are parsed only once per batch
+            let mut parsed_format: Option<(&str, Vec<DateFormatToken>)> = None;
+            (0..strings.len())
+                .map(|i| {
+                    if arrays.iter().any(|array| array.is_null(i)) {
+                        return Ok(None);
+                    }
+                    let format = formats.value(i);
+                    if !matches!(parsed_format, Some((parsed, _)) if parsed == format) {
+                        parsed_format = Some((format, parse_date_format_for_parsing(format)?));
+                    }
+                    let tokens = &parsed_format.as_ref().unwrap().1;
+                    Ok(parse_datetime(strings.value(i), tokens, &timezone))
+                })
+                .collect::<Result<_>>()?
+        }
+        other => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "unix_timestamp not supported for type: {other}"
+            )));
+        }
+    };
+    Ok(ColumnarValue::Array(Arc::new(seconds)))
+}
+
+fn timezone_arg(arg: &ColumnarValue, function_name: &str) -> Result<Tz> {
+    match arg {
+        ColumnarValue::Scalar(ScalarValue::Utf8(Some(timezone))) => Ok(timezone.parse()?),
+        _ => Err(DataFusionError::Execution(format!(
+            "{function_name} timezone only supports literal string"
+        ))),
+    }
+}
+
+fn date_from_epoch_days(days: i32) -> Option<NaiveDate> {
+    NaiveDate::from_num_days_from_ce_opt(days.checked_add(UNIX_EPOCH_DAYS_FROM_CE)?)
+}
+
+/// Resolves local datetimes like java's ZonedDateTime: the earlier offset is
+/// used in overlaps and datetimes in gaps are shifted later by an hour
+fn resolve_local_datetime(timezone: &Tz, datetime: &NaiveDateTime) -> Option<DateTime<Tz>> {
+    match timezone.from_local_datetime(datetime) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => Some(datetime),
+        LocalResult::None => timezone
+            .from_local_datetime(&(*datetime + Duration::hours(1)))
+            .earliest(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DateFormatToken {
+    Literal(String),
+    Field(char, usize),
+}
+
+/// Parses java's datetime pattern into literals and fields (pattern letters
+/// repeated count times). text in single quotes is literal and `''` is a
+/// single quote.
+fn parse_date_format(format: &str) -> Result<Vec<DateFormatToken>> {
+    let unsupported = |reason: String| {
+        Err(DataFusionError::Execution(format!(
+            "unsupported datetime pattern {format:?}: {reason}"
+        )))
+    };
+    let chars = format.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut literal = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' {
+            if chars.get(i + 1) == Some(&'\'') {
+                literal.push('\'');
+                i += 2;
+                continue;
+            }
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+                        literal.push('\'');
+                        i += 2;
+                    }
+                    Some('\'') => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&c) => {
+                        literal.push(c);
+                        i += 1;
+                    }
+                    None => return unsupported("unterminated quote".to_string()),
+                }
+            }
+        } else if c.is_ascii_alphabetic() {
+            let count = chars[i..].iter().take_while(|&&next| next == c).count();
+            let supported = match c {
+                'y' | 'M' | 'L' | 'd' | 'D' | 'H' | 'h' | 'k' | 'K' | 'm' | 's' | 'a' | 'E' => true,
+                'S' => count <= 9,
+                'Z' | 'X' | 'x' => count <= 3,
+                _ => false,
+            };
+            if !supported {
+                return unsupported(format!("pattern letter {}", c.to_string().repeat(count)));
+            }
+            if !literal.is_empty() {
+                tokens.push(DateFormatToken::Literal(std::mem::take(&mut literal)));
+            }
+            tokens.push(DateFormatToken::Field(c, count));
+            i += count;
+        } else {
+            literal.push(c);
+            i += 1;
+        }
+    }
+    if !literal.is_empty() {
+        tokens.push(DateFormatToken::Literal(literal));
+    }
+    Ok(tokens)
+}
+
+fn format_datetime(datetime: &DateTime<Tz>, tokens: &[DateFormatToken]) -> String {
+    let mut formatted = String::new();
+    for token in tokens {
+        let (letter, count) = match token {
+            DateFormatToken::Literal(literal) => {
+                formatted.push_str(literal);
+                continue;
+            }
+            &DateFormatToken::Field(letter, count) => (letter, count),
+        };
+        let padded = |value: i64| format!("{value:0count$}");
+        let offset_seconds = datetime.offset().fix().local_minus_utc();
+
+        let field = match letter {
+            'y' if count == 2 => format!("{:02}", datetime.year().rem_euclid(100)),
+            'y' => padded(datetime.year() as i64),
+            'M' | 'L' => match count {
+                1 | 2 => padded(datetime.month() as i64),
+                3 => MONTH_NAMES[datetime.month0() as usize][..3].to_string(),
+                _ => MONTH_NAMES[datetime.month0() as usize].to_string(),
+            },
+            'd' => padded(datetime.day() as i64),
+            'D' => padded(datetime.ordinal() as i64),
+            'H' => padded(datetime.hour() as i64),
+            'h' => padded(datetime.hour12().1 as i64),
+            'k' => padded((datetime.hour() as i64 + 23) % 24 + 1), // 1-24
+            'K' => padded(datetime.hour() as i64 % 12),
+            'm' => padded(datetime.minute() as i64),
+            's' => padded(datetime.second() as i64),
+            'S' => {
+                // fraction of second is truncated to count digits
+                let nanos = datetime.nanosecond() % 1_000_000_000;
+                padded((nanos / 10u32.pow(9 - count as u32)) as i64)
+            }
+            'a' => (if datetime.hour() < 12 { "AM" } else { "PM" }).to_string(),
+            'E' => {
+                let name = WEEKDAY_NAMES[datetime.weekday().num_days_from_monday() as usize];
+                (if count <= 3 { &name[..3] } else { name }).to_string()
+            }
+            'X' if offset_seconds == 0 => "Z".to_string(),
+            'Z' => format_offset(offset_seconds, 2),
+            'X' | 'x' => format_offset(offset_seconds, count),
+            _ => unreachable!("unsupported pattern letter: {letter}"),
+        };
+        formatted.push_str(&field);
+    }
+    formatted
+}
+
+fn parse_date_format_for_parsing(format: &str) -> Result<Vec<DateFormatToken>> {
+    let tokens = parse_date_format(format)?;
+    for token in &tokens {
+        if let &DateFormatToken::Field(letter @ ('D' | 'E'), _) = token {
+            return Err(DataFusionError::Execution(format!(
+                "unsupported datetime pattern {format:?}: pattern letter {letter} is not \
+                 supported in parsing"
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses datetime strings like java's DateTimeFormatter in strict and case
+/// insensitive mode, returning seconds since epoch. missing fields default to
+/// 1970-01-01 00:00:00 and the datetime is resolved in the given timezone
+/// unless an offset is parsed. returns None if the whole string does not match
+/// the pattern or is not a valid datetime.
+fn parse_datetime(s: &str, tokens: &[DateFormatToken], timezone: &Tz) -> Option<i64> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut pos = 0;
+    let (mut year, mut month, mut day) = (1970, 1, 1);
+    let (mut hour, mut minute, mut second) = (0, 0, 0);
+    let mut hour_of_ampm = None;
+    let mut is_pm = None;
+    let mut offset_seconds = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let (letter, count) = match token {
+            DateFormatToken::Literal(literal) => {
+                for c in literal.chars() {
+                    if !chars.get(pos)?.eq_ignore_ascii_case(&c) {
+                        return None;
+                    }
+                    pos += 1;
+                }
+                continue;
+            }
+            &DateFormatToken::Field(letter, count) => (letter, count),
+        };
+
+        // variable width numbers leave digits to the following fixed width fields
+        let mut parse_number = |min_width: usize, max_width: usize| -> Option<i64> {
+            let digits = chars[pos..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+            let width = match min_width == max_width {
+                true => min_width,
+                false => {
+                    let reserved = tokens[i + 1..]
+                        .iter()
+                        .map_while(|token| match token {
+                            &DateFormatToken::Field(letter, count) => {
+                                fixed_parsing_width(letter, count)
+                            }
+                            _ => None,
+                        })
+                        .sum::<usize>();
+                    digits.saturating_sub(reserved).min(max_width)
+                }
+            };
+            if width < min_width || width > digits {
+                return None;
+            }
+            let number = chars[pos..][..width]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .ok()?;
+            pos += width;
+            Some(number)
+        };
+
+        match letter {
+            'y' if count == 2 => year = 2000 + parse_number(2, 2)? as i32,
+            'y' => year = i32::try_from(parse_number(count, 9)?).ok()?,
+            'M' | 'L' if count <= 2 => month = parse_number(count, 2)? as u32,
+            'M' | 'L' => {
+                let rest = chars[pos..].iter().collect::<String>().to_lowercase();
+                let (month0, name) = MONTH_NAMES.iter().enumerate().find_map(|(i, name)| {
+                    let name = if count == 3 { &name[..3] } else { name };
+                    rest.starts_with(&name.to_lowercase()).then_some((i, name))
+                })?;
+                month = month0 as u32 + 1;
+                pos += name.len();
+            }
+            'd' => day = parse_number(count, 2)? as u32,
+            'H' => hour = parse_number(count, 2)? as u32,
+            'k' => {
+                let clock_hour = parse_number(count, 2)? as u32;
+                hour = (1..=24).contains(&clock_hour).then_some(clock_hour % 24)?;
+            }
+            'h' => {
+                let clock_hour = parse_number(count, 2)? as u32;
+                hour_of_ampm = Some((1..=12).contains(&clock_hour).then_some(clock_hour % 12)?);
+            }
+            'K' => {
+                let hour = parse_number(count, 2)? as u32;
+                hour_of_ampm = Some((0..12).contains(&hour).then_some(hour)?);
+            }
+            'm' => minute = parse_number(count, 2)? as u32,
+            's' => second = parse_number(count, 2)? as u32,
+            'S' => {
+                parse_number(1, count)?; // fraction of second is ignored
+            }
+            'a' => {
+                let ampm = chars.get(pos..pos + 2)?.iter().collect::<String>();
+                is_pm = Some(match ampm.to_uppercase().as_str() {
+                    "AM" => false,
+                    "PM" => true,
+                    _ => return None,
+                });
+                pos += 2;
+            }
+            'Z' | 'X' | 'x' => {
+                offset_seconds = Some(parse_offset(&chars, &mut pos, letter, count)?)
+            }
+            _ => return None,
+        }
+    }
+    if pos < chars.len() {
+        return None;
+    }
+
+    // like spark, hours of am/pm without am/pm field are treated as am
+    if let Some(hour_of_ampm) = hour_of_ampm {
+        hour = hour_of_ampm + if is_pm == Some(true) { 12 } else { 0 };
+    }
+    let datetime = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, second)?;
+    match offset_seconds {
+        Some(offset_seconds) => Some(datetime.timestamp() - offset_seconds as i64),
+        None => Some(resolve_local_datetime(timezone, &datetime)?.timestamp()),
+    }
+}
+
+/// Width of numeric fields which are parsed with fixed width
+fn fixed_parsing_width(letter: char, count: usize) -> Option<usize> {
+    match letter {
+        'y' if count == 2 => Some(2),
+        'M' | 'L' if count == 2 => Some(2),
+        'd' | 'H' | 'k' | 'h' | 'K' | 'm' | 's' if count == 2 => Some(2),
+        _ => None,
+    }
+}
+
+/// Parses zone offsets in the forms formatted by [`format_offset`], 'X' also
+/// accepts 'Z' for zero offset
+fn parse_offset(chars: &[char], pos: &mut usize, letter: char, count: usize) -> Option<i32> {
+    if letter == 'X' && matches!(chars.get(*pos), Some('Z' | 'z')) {
+        *pos += 1;
+        return Some(0);
+    }
+    let parse_two_digits = |pos: &mut usize| -> Option<i32> {
+        let digits = chars.get(*pos..*pos + 2)?;
+        if !digits.iter().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        *pos += 2;
+        digits.iter().collect::<String>().parse().ok()
+    };
+
+    let sign = match chars.get(*pos)? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    *pos += 1;
+    let hours = parse_two_digits(pos)?;
+    let minutes = match (letter, count) {
+        ('X' | 'x', 1) if chars.get(*pos).map_or(true, |c| !c.is_ascii_digit()) => 0,
+        ('X' | 'x', 3) => {
+            if chars.get(*pos) != Some(&':') {
+                return None;
+            }
+            *pos += 1;
+            parse_two_digits(pos)?
+        }
+        _ => parse_two_digits(pos)?,
+    };
+    if hours > 18 || minutes >= 60 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Formats zone offsets like java's X/x pattern letters: +08 (minutes are
+/// appended if non-zero), +0800 or +08:00
+fn format_offset(offset_seconds: i32, count: usize) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let hours = offset_seconds.abs() / 3600;
+    let minutes = offset_seconds.abs() / 60 % 60;
+    match count {
+        1 if minutes == 0 => format!("{sign}{hours:02}"),
+        1 | 2 => format!("{sign}{hours:02}{minutes:02}"),
+        _ => format!("{sign}{hours:02}:{minutes:02}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_dates::{
+        date_add, date_diff, date_format, date_sub, from_unixtime, trunc_date, unix_timestamp,
+    };
+    use arrow::array::{
+        Date32Array, Int32Array, Int64Array, StringArray, TimestampMicrosecondArray,
+    };
+    use datafusion::common::cast::{
+        as_date32_array, as_int32_array, as_int64_array, as_string_array,
+    };
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_date_add_sub_diff() -> Result<()> {
+        // 2023-01-30, 2023-03-01
+        let dates = ColumnarValue::Array(Arc::new(Date32Array::from(vec![
+            Some(19387),
+            Some(19417),
+            None,
+        ])));
+        let days = ColumnarValue::Array(Arc::new(Int32Array::from(vec![Some(3), None, Some(1)])));
+
+        let r = date_add(&[dates.clone(), days.clone()])?.into_array(3);
+        let r = as_date32_array(&r)?;
+        assert_eq!(r, &Date32Array::from(vec![Some(19390), None, None]));
+
+        let r = date_sub(&[dates.clone(), days])?.into_array(3);
+        let r = as_date32_array(&r)?;
+        assert_eq!(r, &Date32Array::from(vec![Some(19384), None, None]));
+
+        let start = ColumnarValue::Scalar(ScalarValue::Date32(Some(19389)));
+        let r = date_diff(&[dates, start])?.into_array(3);
+        let r = as_int32_array(&r)?;
+        assert_eq!(r, &Int32Array::from(vec![Some(-2), Some(28), None]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_trunc_date() -> Result<()> {
+        // 2023-05-17 (wednesday)
+        let date = ColumnarValue::Scalar(ScalarValue::Date32(Some(19494)));
+        let formats = ColumnarValue::Array(Arc::new(StringArray::from(vec![
+            Some("year"),
+            Some("QUARTER"),
+            Some("mm"),
+            Some("week"),
+            Some("day"),
+            None,
+        ])));
+        let r = trunc_date(&[date, formats])?.into_array(6);
+        let r = as_date32_array(&r)?;
+        assert_eq!(
+            r,
+            &Date32Array::from(vec![
+                Some(19358), // 2023-01-01
+                Some(19448), // 2023-04-01
+                Some(19478), // 2023-05-01
+                Some(19492), // 2023-05-15
+                None,
+                None,
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_format() -> Result<()> {
+        // 2023-01-02 03:04:05.123456 UTC
+        let timestamp = ColumnarValue::Array(Arc::new(TimestampMicrosecondArray::from(vec![
+            Some(1672628645123456),
+            None,
+        ])));
+        let format = |format: &str, timezone: &str| -> Result<Vec<Option<String>>> {
+            let r = date_format(&[
+                timestamp.clone(),
+                ColumnarValue::Scalar(ScalarValue::from(format)),
+                ColumnarValue::Scalar(ScalarValue::from(timezone)),
+            ])?
+            .into_array(2);
+            Ok(as_string_array(&r)?
+                .iter()
+                .map(|s| s.map(|s| s.to_string()))
+                .collect())
+        };
+
+        assert_eq!(
+            format("yyyy-MM-dd HH:mm:ss.SSS", "UTC")?,
+            vec![Some("2023-01-02 03:04:05.123".to_string()), None]
+        );
+        assert_eq!(
+            format("yy/M/d h:m a E EEEE MMM MMMM", "Asia/Shanghai")?,
+            vec![Some("23/1/2 11:4 AM Mon Monday Jan January".to_string()), None]
+        );
+        assert_eq!(
+            format("yyyy-MM-dd'T'HH:mm:ssXXX ''Z'' x", "Asia/Kolkata")?,
+            vec![Some("2023-01-02T08:34:05+05:30 '+0530' +0530".to_string()), None]
+        );
+        assert_eq!(
+            format("D k K SSSSSS X", "UTC")?,
+            vec![Some("2 3 3 123456 Z".to_string()), None]
+        );
+        assert_eq!(
+            format("'o''clock' HH", "-08:00")?,
+            vec![Some("o'clock 19".to_string()), None]
+        );
+        assert!(format("yyyy G", "UTC").is_err());
+        assert!(format("'yyyy", "UTC").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_unixtime() -> Result<()> {
+        let seconds = ColumnarValue::Array(Arc::new(Int64Array::from(vec![
+            Some(0),
+            Some(1672628645),
+            None,
+        ])));
+        let r = from_unixtime(&[
+            seconds,
+            ColumnarValue::Scalar(ScalarValue::from("yyyy-MM-dd HH:mm:ss")),
+            ColumnarValue::Scalar(ScalarValue::from("Asia/Shanghai")),
+        ])?
+        .into_array(3);
+        let r = as_string_array(&r)?;
+        assert_eq!(
+            r,
+            &StringArray::from(vec![
+                Some("1970-01-01 08:00:00"),
+                Some("2023-01-02 11:04:05"),
+                None,
+            ])
+        );
+
+        let overflow = from_unixtime(&[
+            ColumnarValue::Scalar(ScalarValue::Int64(Some(i64::MAX))),
+            ColumnarValue::Scalar(ScalarValue::from("yyyy")),
+            ColumnarValue::Scalar(ScalarValue::from("UTC")),
+        ]);
+        assert!(overflow.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unix_timestamp() -> Result<()> {
+        let unix_timestamp_of = |value: ColumnarValue, format: &str| -> Result<Vec<Option<i64>>> {
+            let r = unix_timestamp(&[
+                value,
+                ColumnarValue::Scalar(ScalarValue::from(format)),
+                ColumnarValue::Scalar(ScalarValue::from("Asia/Shanghai")),
+            ])?
+            .into_array(1);
+            Ok(as_int64_array(&r)?.iter().collect())
+        };
+
+        // timestamps and dates ignore the format
+        let timestamp = ColumnarValue::Array(Arc::new(TimestampMicrosecondArray::from(vec![
+            Some(1672628645123456),
+            Some(-1),
+            None,
+        ])));
+        assert_eq!(
+            unix_timestamp_of(timestamp, "yyyy")?,
+            vec![Some(1672628645), Some(-1), None]
+        );
+        let date = ColumnarValue::Scalar(ScalarValue::Date32(Some(19359))); // 2023-01-02
+        assert_eq!(unix_timestamp_of(date, "yyyy")?, vec![Some(1672588800)]);
+
+        let strings = ColumnarValue::Array(Arc::new(StringArray::from(vec![
+            Some("2023-01-02 11:04:05"),
+            Some("2023-1-2 11:04:05"),
+            Some("2023-02-30 00:00:00"),
+            Some("2023-01-02 11:04:05 "),
+            None,
+        ])));
+        assert_eq!(
+            unix_timestamp_of(strings, "yyyy-MM-dd HH:mm:ss")?,
+            vec![Some(1672628645), None, None, None, None]
+        );
+
+        let strings = ColumnarValue::Array(Arc::new(StringArray::from(vec![
+            "20230102",
+            "02/Jan/2023:03:04:05 +0000",
+            "02 JAN 23 12:04 PM",
+            "2023-01-02T03:04:05.1+05:30",
+        ])));
+        let formats = [
+            "yyyyMMdd",
+            "dd/MMM/yyyy:HH:mm:ss Z",
+            "dd MMM yy hh:mm a",
+            "yyyy-MM-dd'T'HH:mm:ss.SSSXXX",
+        ];
+        let expected = [1672588800, 1672628645, 1672632240, 1672608845];
+        for ((i, format), expected) in formats.iter().enumerate().zip(expected) {
+            let r = unix_timestamp_of(strings.clone(), format)?;
+            assert_eq!(r[i], Some(expected), "format: {format}");
+        }
+
+        let unsupported = unix_timestamp_of(ColumnarValue::Scalar(ScalarValue::from("1")), "D");
+        assert!(unsupported.is_err());
+        Ok(())
+    }
+}
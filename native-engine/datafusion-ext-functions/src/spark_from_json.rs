@@ -0,0 +1,350 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::array::*;
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::datatypes::{DataType, FieldRef, Fields};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::physical_plan::ColumnarValue;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// from_json() function compatible with spark in permissive mode, parses json
+/// strings into structs of the schema given by the data type of the second
+/// argument (a typed null literal).
+///
+/// null or blank strings result in null structs. malformed json, non-object
+/// json and values not convertible to the schema result in structs with all
+/// fields null, like spark's permissive mode. objects parsed into string fields
+/// are output as json text with keys sorted.
+pub fn spark_from_json(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let json_string_array = match &args[0] {
+        ColumnarValue::Array(array) => array.clone(),
+        ColumnarValue::Scalar(scalar) => scalar.to_array_of_size(1),
+    };
+    let json_strings = as_string_array(&json_string_array);
+    let schema = match args[1].data_type() {
+        DataType::Struct(fields) => fields,
+        other => {
+            return Err(DataFusionError::Execution(format!(
+                "from_json only supports struct schema, got {other}"
+            )))
+        }
+    };
+    let struct_type = DataType::Struct(schema.clone());
+
+    let json_values = json_strings
+        .iter()
+        .map(|json_string| {
+            let json_string = json_string.filter(|s| !s.trim().is_empty())?;
+            let malformed = Value::Object(Default::default()); // all fields null
+            Some(match serde_json::from_str::<Value>(json_string) {
+                Ok(value @ Value::Object(_)) if is_convertible(&value, &struct_type) => value,
+                _ => malformed,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let json_values = json_values.iter().map(|v| v.as_ref()).collect::<Vec<_>>();
+    Ok(ColumnarValue::Array(json_values_to_array(
+        &json_values,
+        &struct_type,
+    )?))
+}
+
+/// Checks whether a json value can be converted to the data type like spark's
+/// JacksonParser. nulls are convertible to all types.
+fn is_convertible(value: &Value, data_type: &DataType) -> bool {
+    match (value, data_type) {
+        (Value::Null, _) => true,
+        (Value::Bool(_), DataType::Boolean) => true,
+        (Value::Number(n), DataType::Int8) => {
+            n.as_i64().and_then(|i| i8::try_from(i).ok()).is_some()
+        }
+        (Value::Number(n), DataType::Int16) => {
+            n.as_i64().and_then(|i| i16::try_from(i).ok()).is_some()
+        }
+        (Value::Number(n), DataType::Int32) => {
+            n.as_i64().and_then(|i| i32::try_from(i).ok()).is_some()
+        }
+        (Value::Number(n), DataType::Int64) => n.as_i64().is_some(),
+        (_, DataType::Float32 | DataType::Float64) => json_float(value).is_some(),
+        (_, DataType::Utf8) => true,
+        (Value::Object(object), DataType::Struct(fields)) => fields.iter().all(|field| {
+            object
+                .get(field.name())
+                .map(|value| is_convertible(value, field.data_type()))
+                .unwrap_or(true)
+        }),
+        (Value::Array(array), DataType::List(field)) => array
+            .iter()
+            .all(|value| is_convertible(value, field.data_type())),
+        (Value::Object(object), DataType::Map(entries_field, _)) => {
+            let entry_fields = map_entry_fields(entries_field);
+            object
+                .values()
+                .all(|value| is_convertible(value, entry_fields[1].data_type()))
+        }
+        _ => false,
+    }
+}
+
+/// Map keys are always strings in json objects
+fn map_entry_fields(entries_field: &FieldRef) -> &Fields {
+    match entries_field.data_type() {
+        DataType::Struct(entry_fields) => entry_fields,
+        _ => unreachable!("map entries must be struct"),
+    }
+}
+
+/// Float values also accept NaN and (-)Infinity strings like spark
+fn json_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => match s.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Converts json values (which have been checked by `is_convertible`) to an
+/// array of the data type
+fn json_values_to_array(values: &[Option<&Value>], data_type: &DataType) -> Result<ArrayRef> {
+    let non_null = |value: &Option<&Value>| value.filter(|value| !value.is_null());
+
+    macro_rules! json_integer_array {
+        ($arraytype:ty, $nativetype:ty) => {{
+            Arc::new(
+                values
+                    .iter()
+                    .map(|value| Some(non_null(value)?.as_i64()? as $nativetype))
+                    .collect::<$arraytype>(),
+            )
+        }};
+    }
+
+    Ok(match data_type {
+        DataType::Boolean => Arc::new(
+            values
+                .iter()
+                .map(|value| non_null(value)?.as_bool())
+                .collect::<BooleanArray>(),
+        ),
+        DataType::Int8 => json_integer_array!(Int8Array, i8),
+        DataType::Int16 => json_integer_array!(Int16Array, i16),
+        DataType::Int32 => json_integer_array!(Int32Array, i32),
+        DataType::Int64 => json_integer_array!(Int64Array, i64),
+        DataType::Float32 => Arc::new(
+            values
+                .iter()
+                .map(|value| Some(json_float(non_null(value)?)? as f32))
+                .collect::<Float32Array>(),
+        ),
+        DataType::Float64 => Arc::new(
+            values
+                .iter()
+                .map(|value| json_float(non_null(value)?))
+                .collect::<Float64Array>(),
+        ),
+        DataType::Utf8 => Arc::new(
+            values
+                .iter()
+                .map(|value| match non_null(value)? {
+                    Value::String(s) => Some(s.clone()),
+                    other => Some(other.to_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+        DataType::Struct(fields) => {
+            let objects = values
+                .iter()
+                .map(|value| non_null(value)?.as_object())
+                .collect::<Vec<_>>();
+            let columns = fields
+                .iter()
+                .map(|field| {
+                    let field_values = objects
+                        .iter()
+                        .map(|&object| object?.get(field.name()))
+                        .collect::<Vec<_>>();
+                    json_values_to_array(&field_values, field.data_type())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let nulls = NullBuffer::from(
+                objects
+                    .iter()
+                    .map(|object| object.is_some())
+                    .collect::<Vec<_>>(),
+            );
+            Arc::new(StructArray::try_new(fields.clone(), columns, Some(nulls))?)
+        }
+        DataType::List(field) => {
+            let (offsets, items, nulls) =
+                flatten_json_values(values, |value| Some(value.as_array()?.iter().collect()));
+            let items = json_values_to_array(&items, field.data_type())?;
+            Arc::new(ListArray::try_new(
+                field.clone(),
+                offsets,
+                items,
+                Some(nulls),
+            )?)
+        }
+        DataType::Map(entries_field, _) => {
+            let entry_fields = map_entry_fields(entries_field);
+            let (offsets, entries, nulls) =
+                flatten_json_values(values, |value| Some(value.as_object()?.iter().collect()));
+            let keys = StringArray::from_iter_values(entries.iter().map(|(key, _)| key));
+            let values = entries
+                .iter()
+                .map(|&(_, value)| Some(value))
+                .collect::<Vec<_>>();
+            let values = json_values_to_array(&values, entry_fields[1].data_type())?;
+            let entries =
+                StructArray::try_new(entry_fields.clone(), vec![Arc::new(keys), values], None)?;
+            make_array(
+                ArrayData::builder(data_type.clone())
+                    .len(offsets.len() - 1)
+                    .add_buffer(offsets.into_inner().into_inner())
+                    .add_child_data(entries.into_data())
+                    .nulls(Some(nulls))
+                    .build()?,
+            )
+        }
+        other => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "from_json not supported for type: {other}"
+            )))
+        }
+    })
+}
+
+/// Flattens nested items of json arrays/objects, returning their offsets,
+/// the items and the nulls of the outer values
+fn flatten_json_values<'a, T>(
+    values: &[Option<&'a Value>],
+    items_of: impl Fn(&'a Value) -> Option<Vec<T>>,
+) -> (OffsetBuffer<i32>, Vec<T>, NullBuffer) {
+    let mut offsets = vec![0];
+    let mut items = vec![];
+    let mut valids = vec![];
+    for value in values {
+        match value.and_then(&items_of) {
+            Some(value_items) => {
+                items.extend(value_items);
+                valids.push(true);
+            }
+            None => valids.push(false),
+        }
+        offsets.push(items.len() as i32);
+    }
+    (
+        OffsetBuffer::new(offsets.into()),
+        items,
+        NullBuffer::from(valids),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_from_json::spark_from_json;
+    use arrow::array::*;
+    use arrow::datatypes::{DataType, Field, Fields, Float64Type, Int64Type};
+    use datafusion::common::Result;
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_from_json() -> Result<()> {
+        let map_type = DataType::Map(
+            Arc::new(Field::new(
+                "entries",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("key", DataType::Utf8, false),
+                    Field::new("value", DataType::Int64, true),
+                ])),
+                false,
+            )),
+            false,
+        );
+        let schema = DataType::Struct(Fields::from(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+            Field::new(
+                "c",
+                DataType::List(Arc::new(Field::new("item", DataType::Float64, true))),
+                true,
+            ),
+            Field::new("d", map_type, true),
+        ]));
+        let json_strings: ArrayRef = Arc::new(StringArray::from(vec![
+            Some(r#"{"a": 1, "b": "x", "c": [1, 2.5, "NaN"], "d": {"k": 10}, "e": 0}"#),
+            Some(r#"{"a": null, "b": {"y": [1, 2]}, "c": null}"#),
+            Some(r#"{"a": 2147483648, "b": "overflow"}"#),
+            Some(r#"{"a": "1"}"#),
+            Some(r#"{"a": 1"#),
+            Some(r#"[{"a": 1}]"#),
+            Some("  "),
+            None,
+        ]));
+
+        let r = spark_from_json(&[
+            ColumnarValue::Array(json_strings),
+            ColumnarValue::Array(new_null_array(&schema, 1)),
+        ])?
+        .into_array(8);
+        let r = as_struct_array(&r);
+        assert_eq!(r.data_type(), &schema);
+        assert_eq!(
+            (0..8).map(|i| r.is_valid(i)).collect::<Vec<_>>(),
+            vec![true, true, true, true, true, true, false, false],
+        );
+        assert_eq!(
+            r.column(0).as_ref(),
+            &Int32Array::from(vec![Some(1), None, None, None, None, None, None, None]),
+        );
+        assert_eq!(
+            r.column(1).as_ref(),
+            &StringArray::from(vec![
+                Some("x"),
+                Some(r#"{"y":[1,2]}"#),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ]),
+        );
+
+        let c = as_list_array(r.column(2));
+        let c0 = c.value(0);
+        let c0 = as_primitive_array::<Float64Type>(&c0);
+        assert_eq!(&c0.values()[..2], &[1.0, 2.5]);
+        assert!(c0.value(2).is_nan());
+        assert!(c.is_null(1));
+
+        let d = as_map_array(r.column(3));
+        assert_eq!(d.value(0).column(0).as_ref(), &StringArray::from(vec!["k"]));
+        assert_eq!(
+            as_primitive_array::<Int64Type>(d.value(0).column(1)).values(),
+            &[10]
+        );
+        assert!(d.is_null(1));
+        Ok(())
+    }
+}
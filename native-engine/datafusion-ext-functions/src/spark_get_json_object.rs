@@ -100,6 +100,9 @@ pub fn spark_parse_json(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     )))
 }
 
+/// get_parsed_json_object(parsed_json, path[, spark_path_semantics])
+/// spark's get_json_object() differs from hive in not mapping child names over
+/// elements of arrays, which is enabled by the optional third argument.
 pub fn spark_get_parsed_json_object(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     let json_array = match &args[0] {
         ColumnarValue::Array(array) => array.as_any().downcast_ref::<UserDefinedArray>().unwrap(),
@@ -119,6 +122,11 @@ pub fn spark_get_parsed_json_object(args: &[ColumnarValue]) -> Result<ColumnarVa
         _ => unreachable!("path should be ScalarValue"),
     };
 
+    let spark_path_semantics = matches!(
+        args.get(2),
+        Some(ColumnarValue::Scalar(ScalarValue::Boolean(Some(true))))
+    );
+
     let mut evaluator = match HiveGetJsonObjectEvaluator::try_new(path_string) {
         Ok(evaluator) => evaluator,
         Err(_) => {
@@ -128,6 +136,7 @@ pub fn spark_get_parsed_json_object(args: &[ColumnarValue]) -> Result<ColumnarVa
             )));
         }
     };
+    evaluator.map_children_of_arrays = !spark_path_semantics;
 
     let output = StringArray::from(
         json_array
@@ -154,11 +163,15 @@ enum HiveGetJsonObjectError {
 
 struct HiveGetJsonObjectEvaluator {
     matchers: Vec<HiveGetJsonObjectMatcher>,
+    map_children_of_arrays: bool,
 }
 
 impl HiveGetJsonObjectEvaluator {
     fn try_new(json_path: &str) -> std::result::Result<Self, HiveGetJsonObjectError> {
-        let mut evaluator = Self { matchers: vec![] };
+        let mut evaluator = Self {
+            matchers: vec![],
+            map_children_of_arrays: true,
+        };
         let chars = json_path.chars();
         let mut peekable = chars.peekable();
 
@@ -213,7 +226,15 @@ impl HiveGetJsonObjectEvaluator {
         let mut value_ref = value;
         for matcher in &self.matchers {
             if !value_ref.is_null() {
-                match matcher.evaluate_ref(value_ref) {
+                let evaluated = match matcher {
+                    HiveGetJsonObjectMatcher::Child(_)
+                        if value_ref.is_array() && !self.map_children_of_arrays =>
+                    {
+                        Either::Right(serde_json::Value::Null)
+                    }
+                    _ => matcher.evaluate_ref(value_ref),
+                };
+                match evaluated {
                     Either::Left(v) => {
                         value_ref = v;
                     }
@@ -573,5 +594,14 @@ mod test {
             .into_array(1);
         let v = r.as_string::<i32>().iter().next().unwrap();
         assert_eq!(v, Some(r#"{"city":"1.234","county":"浦东"}"#));
+
+        // spark does not map child names over arrays
+        let path = ColumnarValue::Scalar(ScalarValue::from("$.message.location.county"));
+        let spark_path_semantics = ColumnarValue::Scalar(ScalarValue::from(true));
+        let r = spark_get_parsed_json_object(&[parsed.clone(), path, spark_path_semantics])
+            .unwrap()
+            .into_array(1);
+        let v = r.as_string::<i32>().iter().next().unwrap();
+        assert_eq!(v, None);
     }
 }
@@ -0,0 +1,451 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::*;
+use arrow::buffer::{Buffer, NullBuffer, OffsetBuffer};
+use arrow::compute::{cast, interleave, take};
+use arrow::datatypes::{DataType, Field, FieldRef, Fields};
+use arrow::row::{RowConverter, SortField};
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use datafusion::physical_plan::ColumnarValue;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// map_keys() function compatible with spark
+pub fn map_keys(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let array = args[0].clone().into_array(1);
+    let map = as_map_array(&array);
+    let keys = map.keys().clone();
+    let field = Arc::new(Field::new("item", keys.data_type().clone(), false));
+    let list = ListArray::try_new(
+        field,
+        OffsetBuffer::new(map.value_offsets().to_vec().into()),
+        keys,
+        map.nulls().cloned(),
+    )?;
+    Ok(ColumnarValue::Array(Arc::new(list)))
+}
+
+/// map_values() function compatible with spark
+pub fn map_values(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let array = args[0].clone().into_array(1);
+    let map = as_map_array(&array);
+    let values = map.values().clone();
+    let value_nullable = map_entry_fields(map)[1].is_nullable();
+    let field = Arc::new(Field::new(
+        "item",
+        values.data_type().clone(),
+        value_nullable,
+    ));
+    let list = ListArray::try_new(
+        field,
+        OffsetBuffer::new(map.value_offsets().to_vec().into()),
+        values,
+        map.nulls().cloned(),
+    )?;
+    Ok(ColumnarValue::Array(Arc::new(list)))
+}
+
+/// map_from_arrays() function compatible with spark, the last argument is
+/// spark.sql.mapKeyDedupPolicy.
+pub fn map_from_arrays(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let dedup_policy = MapKeyDedupPolicy::try_from_arg(&args[2])?;
+    let arrays = args_to_arrays(&args[0..2]);
+    let key_lists = as_list_array(&arrays[0]);
+    let value_lists = as_list_array(&arrays[1]);
+    let key_offsets = key_lists.value_offsets();
+    let value_offsets = value_lists.value_offsets();
+
+    let mut map_entries = Vec::with_capacity(key_lists.len());
+    for i in 0..key_lists.len() {
+        if key_lists.is_null(i) || value_lists.is_null(i) {
+            map_entries.push(None);
+            continue;
+        }
+        let num_keys = key_offsets[i + 1] - key_offsets[i];
+        let num_values = value_offsets[i + 1] - value_offsets[i];
+        if num_keys != num_values {
+            return Err(DataFusionError::Execution(
+                "The key array and value array of MapData must have the same length.".to_string(),
+            ));
+        }
+        map_entries.push(Some(
+            (0..num_keys)
+                .map(|j| MapEntry {
+                    source: 0,
+                    key_index: (key_offsets[i] + j) as usize,
+                    value_index: (value_offsets[i] + j) as usize,
+                })
+                .collect(),
+        ));
+    }
+
+    let keys = key_lists.values().clone();
+    let values = value_lists.values().clone();
+    let value_nullable = match value_lists.data_type() {
+        DataType::List(field) => field.is_nullable(),
+        _ => unreachable!("expect list type"),
+    };
+    let entries_field = map_entries_field(keys.data_type(), values.data_type(), value_nullable);
+    let map = build_map_array(entries_field, &[keys], &[values], map_entries, dedup_policy)?;
+    Ok(ColumnarValue::Array(map))
+}
+
+/// map_concat() function compatible with spark, the last argument is
+/// spark.sql.mapKeyDedupPolicy. returns null if any of the input maps is null.
+pub fn map_concat(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let dedup_policy = MapKeyDedupPolicy::try_from_arg(&args[args.len() - 1])?;
+    let arrays = args_to_arrays(&args[..args.len() - 1]);
+    let maps = arrays
+        .iter()
+        .map(|array| as_map_array(array))
+        .collect::<Vec<_>>();
+    let num_rows = maps[0].len();
+
+    let map_entries = (0..num_rows)
+        .map(|i| {
+            if maps.iter().any(|map| map.is_null(i)) {
+                return None;
+            }
+            let mut entries = vec![];
+            for (source, map) in maps.iter().enumerate() {
+                let value_offsets = map.value_offsets();
+                let entry_range = value_offsets[i] as usize..value_offsets[i + 1] as usize;
+                entries.extend(entry_range.map(|j| MapEntry {
+                    source,
+                    key_index: j,
+                    value_index: j,
+                }));
+            }
+            Some(entries)
+        })
+        .collect();
+
+    let keys = maps
+        .iter()
+        .map(|map| map.keys().clone())
+        .collect::<Vec<_>>();
+    let values = maps
+        .iter()
+        .map(|map| map.values().clone())
+        .collect::<Vec<_>>();
+    let value_nullable = maps
+        .iter()
+        .any(|map| map_entry_fields(map)[1].is_nullable());
+    let entries_field =
+        map_entries_field(keys[0].data_type(), values[0].data_type(), value_nullable);
+    let map = build_map_array(entries_field, &keys, &values, map_entries, dedup_policy)?;
+    Ok(ColumnarValue::Array(map))
+}
+
+/// map value extraction (m[key]) compatible with spark, for keys that are
+/// not literals. returns null if the key is not found.
+pub fn map_get_value(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let map = as_map_array(&arrays[0]);
+    let value_offsets = map.value_offsets();
+    let keys = map.keys();
+
+    let mut converter = RowConverter::new(vec![SortField::new(keys.data_type().clone())])?;
+    let key_rows = converter.convert_columns(&[keys.clone()])?;
+    let lookup_rows = converter.convert_columns(&[cast(&arrays[1], keys.data_type())?])?;
+
+    let take_indices: UInt32Array = (0..map.len())
+        .map(|i| {
+            if map.is_null(i) || arrays[1].is_null(i) {
+                return None;
+            }
+            let entry_range = value_offsets[i] as usize..value_offsets[i + 1] as usize;
+            entry_range
+                .find(|&j| key_rows.row(j) == lookup_rows.row(i))
+                .map(|j| j as u32)
+        })
+        .collect();
+    Ok(ColumnarValue::Array(take(
+        map.values(),
+        &take_indices,
+        None,
+    )?))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MapKeyDedupPolicy {
+    Exception,
+    LastWin,
+}
+
+impl MapKeyDedupPolicy {
+    fn try_from_arg(arg: &ColumnarValue) -> Result<Self> {
+        match arg {
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(policy))) => {
+                match policy.to_uppercase().as_str() {
+                    "EXCEPTION" => Ok(Self::Exception),
+                    "LAST_WIN" => Ok(Self::LastWin),
+                    other => Err(DataFusionError::Execution(format!(
+                        "unsupported map key dedup policy: {other}"
+                    ))),
+                }
+            }
+            _ => Err(DataFusionError::Execution(
+                "map key dedup policy must be a literal string".to_string(),
+            )),
+        }
+    }
+}
+
+/// An entry of the map being built, pointing to keys[source][key_index] and
+/// values[source][value_index]
+struct MapEntry {
+    source: usize,
+    key_index: usize,
+    value_index: usize,
+}
+
+/// Builds a map array from entries of each row, duplicated keys are either
+/// rejected or overwritten by the last value (keeping the first position),
+/// like spark's ArrayBasedMapBuilder.
+fn build_map_array(
+    entries_field: FieldRef,
+    keys: &[ArrayRef],
+    values: &[ArrayRef],
+    map_entries: Vec<Option<Vec<MapEntry>>>,
+    dedup_policy: MapKeyDedupPolicy,
+) -> Result<ArrayRef> {
+    let mut converter = RowConverter::new(vec![SortField::new(keys[0].data_type().clone())])?;
+    let key_rows = keys
+        .iter()
+        .map(|keys| converter.convert_columns(&[keys.clone()]))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut offsets = vec![0i32];
+    let mut valids = vec![];
+    let mut key_indices = vec![];
+    let mut value_indices = vec![];
+    for entries in map_entries {
+        let entries = match entries {
+            Some(entries) => entries,
+            None => {
+                valids.push(false);
+                offsets.push(key_indices.len() as i32);
+                continue;
+            }
+        };
+        let mut key_positions = HashMap::new();
+        for entry in entries {
+            if keys[entry.source].is_null(entry.key_index) {
+                return Err(DataFusionError::Execution(
+                    "Cannot use null as map key.".to_string(),
+                ));
+            }
+            match key_positions.entry(key_rows[entry.source].row(entry.key_index)) {
+                Entry::Occupied(occupied) => match dedup_policy {
+                    MapKeyDedupPolicy::Exception => {
+                        let key =
+                            ScalarValue::try_from_array(&keys[entry.source], entry.key_index)?;
+                        return Err(DataFusionError::Execution(format!(
+                            "Duplicate map key {key} was found, please check the input data. \
+                             If you want to remove the duplicated keys, you can set \
+                             spark.sql.mapKeyDedupPolicy to LAST_WIN so that the key \
+                             inserted at last takes precedence."
+                        )));
+                    }
+                    MapKeyDedupPolicy::LastWin => {
+                        value_indices[*occupied.get()] = (entry.source, entry.value_index);
+                    }
+                },
+                Entry::Vacant(vacant) => {
+                    vacant.insert(key_indices.len());
+                    key_indices.push((entry.source, entry.key_index));
+                    value_indices.push((entry.source, entry.value_index));
+                }
+            }
+        }
+        valids.push(true);
+        offsets.push(key_indices.len() as i32);
+    }
+
+    let keys = keys.iter().map(|keys| keys.as_ref()).collect::<Vec<_>>();
+    let values = values
+        .iter()
+        .map(|values| values.as_ref())
+        .collect::<Vec<_>>();
+    let entry_fields = match entries_field.data_type() {
+        DataType::Struct(entry_fields) => entry_fields.clone(),
+        _ => unreachable!("map entries must be struct"),
+    };
+    let entries = StructArray::try_new(
+        entry_fields,
+        vec![interleave(&keys, &key_indices)?, interleave(&values, &value_indices)?],
+        None,
+    )?;
+    Ok(make_array(
+        ArrayData::builder(DataType::Map(entries_field, false))
+            .len(valids.len())
+            .add_buffer(Buffer::from_vec(offsets))
+            .add_child_data(entries.into_data())
+            .nulls(Some(NullBuffer::from(valids)))
+            .build()?,
+    ))
+}
+
+/// Entries field of map type, in the same layout as converted from spark
+fn map_entries_field(key_type: &DataType, value_type: &DataType, value_nullable: bool) -> FieldRef {
+    let entry_fields = vec![
+        Field::new("key", key_type.clone(), false),
+        Field::new("value", value_type.clone(), value_nullable),
+    ];
+    Arc::new(Field::new(
+        "entries",
+        DataType::Struct(entry_fields.into()),
+        false,
+    ))
+}
+
+fn map_entry_fields(map: &MapArray) -> &Fields {
+    match map.data_type() {
+        DataType::Map(entries_field, _) => match entries_field.data_type() {
+            DataType::Struct(entry_fields) => entry_fields,
+            _ => unreachable!("map entries must be struct"),
+        },
+        _ => unreachable!("expect map type"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_maps::{map_concat, map_from_arrays, map_get_value, map_keys, map_values};
+    use arrow::array::*;
+    use arrow::datatypes::Int32Type;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    fn int_lists(lists: Vec<Option<Vec<Option<i32>>>>) -> ColumnarValue {
+        ColumnarValue::Array(Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(
+            lists,
+        )))
+    }
+
+    fn dedup_policy(policy: &str) -> ColumnarValue {
+        ColumnarValue::Scalar(ScalarValue::from(policy))
+    }
+
+    #[test]
+    fn test_map_from_arrays() -> Result<()> {
+        let keys = int_lists(vec![
+            Some(vec![Some(1), Some(2), Some(1)]),
+            None,
+            Some(vec![]),
+        ]);
+        let values = int_lists(vec![
+            Some(vec![Some(10), Some(20), Some(30)]),
+            Some(vec![Some(1)]),
+            Some(vec![]),
+        ]);
+        let map = map_from_arrays(&[keys.clone(), values.clone(), dedup_policy("LAST_WIN")])?;
+        let map = map.into_array(3);
+        let map = as_map_array(&map);
+        assert_eq!(map.value_offsets(), &[0, 2, 2, 2]);
+        assert!(map.is_valid(0) && map.is_null(1) && map.is_valid(2));
+        assert_eq!(
+            as_primitive_array::<Int32Type>(map.keys()),
+            &Int32Array::from(vec![1, 2])
+        );
+        assert_eq!(
+            as_primitive_array::<Int32Type>(map.values()),
+            &Int32Array::from(vec![30, 20])
+        );
+
+        assert!(map_from_arrays(&[keys, values, dedup_policy("EXCEPTION")]).is_err());
+
+        let keys = int_lists(vec![Some(vec![Some(1), None])]);
+        let values = int_lists(vec![Some(vec![Some(10), Some(20)])]);
+        assert!(map_from_arrays(&[keys, values, dedup_policy("LAST_WIN")]).is_err());
+
+        let keys = int_lists(vec![Some(vec![Some(1)])]);
+        let values = int_lists(vec![Some(vec![Some(10), Some(20)])]);
+        assert!(map_from_arrays(&[keys, values, dedup_policy("LAST_WIN")]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_keys_values_and_get_value() -> Result<()> {
+        let keys = int_lists(vec![
+            Some(vec![Some(1), Some(2)]),
+            None,
+            Some(vec![Some(3)]),
+        ]);
+        let values = int_lists(vec![Some(vec![Some(10), None]), None, Some(vec![Some(30)])]);
+        let map = map_from_arrays(&[keys, values, dedup_policy("EXCEPTION")])?;
+
+        let r = map_keys(&[map.clone()])?.into_array(3);
+        assert_eq!(
+            as_list_array(&r),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(1), Some(2)]),
+                None,
+                Some(vec![Some(3)]),
+            ])
+        );
+        let r = map_values(&[map.clone()])?.into_array(3);
+        assert_eq!(
+            as_list_array(&r),
+            &ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(10), None]),
+                None,
+                Some(vec![Some(30)]),
+            ])
+        );
+
+        let lookup_keys = ColumnarValue::Array(Arc::new(Int32Array::from(vec![1, 1, 4])));
+        let r = map_get_value(&[map, lookup_keys])?.into_array(3);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![Some(10), None, None])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_concat() -> Result<()> {
+        let map1 = map_from_arrays(&[
+            int_lists(vec![Some(vec![Some(1), Some(2)]), Some(vec![Some(1)])]),
+            int_lists(vec![Some(vec![Some(10), Some(20)]), Some(vec![Some(10)])]),
+            dedup_policy("EXCEPTION"),
+        ])?;
+        let map2 = map_from_arrays(&[
+            int_lists(vec![Some(vec![Some(3), Some(1)]), None]),
+            int_lists(vec![Some(vec![Some(30), Some(100)]), None]),
+            dedup_policy("EXCEPTION"),
+        ])?;
+        let r = map_concat(&[map1.clone(), map2.clone(), dedup_policy("LAST_WIN")])?;
+        let r = r.into_array(2);
+        let r = as_map_array(&r);
+        assert_eq!(r.value_offsets(), &[0, 3, 3]);
+        assert!(r.is_valid(0) && r.is_null(1));
+        assert_eq!(
+            as_primitive_array::<Int32Type>(r.keys()),
+            &Int32Array::from(vec![1, 2, 3])
+        );
+        assert_eq!(
+            as_primitive_array::<Int32Type>(r.values()),
+            &Int32Array::from(vec![100, 20, 30])
+        );
+
+        assert!(map_concat(&[map1, map2, dedup_policy("EXCEPTION")]).is_err());
+        Ok(())
+    }
+}
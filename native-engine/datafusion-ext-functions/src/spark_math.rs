@@ -0,0 +1,457 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::*;
+use arrow::datatypes::*;
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use datafusion::physical_plan::ColumnarValue;
+use std::sync::Arc;
+
+/// pmod(a, n, ansi) function compatible with spark, returns the positive
+/// remainder of a / n. returns null if n is zero, or fails in ansi mode.
+/// decimal operands are expected to have the same scale.
+pub fn spark_pmod(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let ansi = match &args[2] {
+        ColumnarValue::Scalar(ScalarValue::Boolean(Some(ansi))) => *ansi,
+        _ => false,
+    };
+    let arrays = args_to_arrays(&args[..2]);
+    let (a, n) = (&arrays[0], &arrays[1]);
+
+    fn pmod<T: ArrowPrimitiveType>(a: &dyn Array, n: &dyn Array, ansi: bool) -> Result<ArrayRef> {
+        let zero = T::Native::ZERO;
+        let a = as_primitive_array::<T>(a);
+        let n = as_primitive_array::<T>(n);
+        let output = a
+            .iter()
+            .zip(n.iter())
+            .map(|(a, n)| match (a, n) {
+                (Some(_), Some(n)) if n.is_zero() => {
+                    if ansi {
+                        return Err(DataFusionError::Execution(
+                            "[DIVIDE_BY_ZERO] Division by zero. Use `try_divide` to tolerate \
+                                divisor being 0 and return NULL instead. If necessary set \
+                                \"spark.sql.ansi.enabled\" to \"false\" to bypass this error."
+                                .to_string(),
+                        ));
+                    }
+                    Ok(None)
+                }
+                (Some(a), Some(n)) => {
+                    let r = a.mod_wrapping(n);
+                    if r.is_lt(zero) {
+                        return Ok(Some(r.add_wrapping(n).mod_wrapping(n)));
+                    }
+                    Ok(Some(r))
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<PrimitiveArray<T>>>()?;
+        Ok(Arc::new(output.with_data_type(a.data_type().clone())))
+    }
+
+    Ok(ColumnarValue::Array(match a.data_type() {
+        DataType::Int8 => pmod::<Int8Type>(a, n, ansi)?,
+        DataType::Int16 => pmod::<Int16Type>(a, n, ansi)?,
+        DataType::Int32 => pmod::<Int32Type>(a, n, ansi)?,
+        DataType::Int64 => pmod::<Int64Type>(a, n, ansi)?,
+        DataType::Float32 => pmod::<Float32Type>(a, n, ansi)?,
+        DataType::Float64 => pmod::<Float64Type>(a, n, ansi)?,
+        DataType::Decimal128(..) => pmod::<Decimal128Type>(a, n, ansi)?,
+        other => {
+            return Err(DataFusionError::Execution(format!(
+                "pmod: unsupported data type: {other}"
+            )));
+        }
+    }))
+}
+
+/// factorial() function compatible with spark, returns null if the input is
+/// out of range [0, 20].
+pub fn spark_factorial(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let input = as_primitive_array::<Int32Type>(&arrays[0]);
+    let output: Int64Array = input
+        .iter()
+        .map(|v| match v {
+            Some(v @ 0..=20) => Some((1..=v as i64).product::<i64>()),
+            _ => None,
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(output)))
+}
+
+/// hypot() function compatible with java.lang.Math.hypot, computing
+/// sqrt(a^2 + b^2) without intermediate overflow or underflow.
+pub fn spark_hypot(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let a = as_primitive_array::<Float64Type>(&arrays[0]);
+    let b = as_primitive_array::<Float64Type>(&arrays[1]);
+    let output: Float64Array = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| Some(a?.hypot(b?)))
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(output)))
+}
+
+/// log(base, x) function compatible with spark, returns null if either the
+/// base or x is not positive.
+pub fn spark_log_with_base(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let base = as_primitive_array::<Float64Type>(&arrays[0]);
+    let x = as_primitive_array::<Float64Type>(&arrays[1]);
+    let output: Float64Array = base
+        .iter()
+        .zip(x.iter())
+        .map(|(base, x)| match (base, x) {
+            (Some(base), Some(x)) if base > 0.0 && x > 0.0 => Some(x.ln() / base.ln()),
+            _ => None,
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(output)))
+}
+
+/// rint() function compatible with java.lang.Math.rint, rounding to the
+/// nearest integer and to the even one on ties.
+pub fn spark_rint(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let input = as_primitive_array::<Float64Type>(&arrays[0]);
+    let output: Float64Array = input.iter().map(|v| v.map(rint)).collect();
+    Ok(ColumnarValue::Array(Arc::new(output)))
+}
+
+fn rint(v: f64) -> f64 {
+    let rounded = v.round();
+    if (rounded - v).abs() == 0.5 {
+        return 2.0 * (v / 2.0).round();
+    }
+    rounded
+}
+
+/// round(x, scale) function compatible with spark, rounding with HALF_UP.
+pub fn spark_round(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    round_impl(args, false)
+}
+
+/// bround(x, scale) function compatible with spark, rounding with HALF_EVEN.
+pub fn spark_bround(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    round_impl(args, true)
+}
+
+fn round_impl(args: &[ColumnarValue], half_even: bool) -> Result<ColumnarValue> {
+    let scale = match &args[1] {
+        ColumnarValue::Scalar(ScalarValue::Int32(Some(scale))) => *scale,
+        _ => {
+            return Err(DataFusionError::Execution(
+                "round: scale must be an int32 literal".to_string(),
+            ))
+        }
+    };
+    let array = args[0].clone().into_array(1);
+
+    macro_rules! handle_integral {
+        ($dt:ident) => {{
+            type T = paste::paste! {arrow::datatypes::[<$dt Type>]};
+            let array = as_primitive_array::<T>(&array);
+            let output: PrimitiveArray<T> =
+                array.unary(|v| round_i128(v as i128, scale, half_even) as _);
+            Arc::new(output) as ArrayRef
+        }};
+    }
+    Ok(ColumnarValue::Array(match array.data_type() {
+        DataType::Int8 => handle_integral!(Int8),
+        DataType::Int16 => handle_integral!(Int16),
+        DataType::Int32 => handle_integral!(Int32),
+        DataType::Int64 => handle_integral!(Int64),
+        DataType::Float32 => {
+            let output: Float32Array = as_primitive_array::<Float32Type>(&array)
+                .iter()
+                .map(|v| v.map(|v| round_f64(v as f64, scale, half_even) as f32))
+                .collect();
+            Arc::new(output)
+        }
+        DataType::Float64 => {
+            let output: Float64Array = as_primitive_array::<Float64Type>(&array)
+                .iter()
+                .map(|v| v.map(|v| round_f64(v, scale, half_even)))
+                .collect();
+            Arc::new(output)
+        }
+        &DataType::Decimal128(precision, from_scale) if scale >= 0 => {
+            // the result scale is never larger than the input scale
+            let to_scale = from_scale.min(scale as i8);
+            let max_unscaled = 10i128.pow(precision as u32);
+            let output: Decimal128Array = as_primitive_array::<Decimal128Type>(&array)
+                .iter()
+                .map(|v| {
+                    let v = round_i128(v?, to_scale as i32 - from_scale as i32, half_even);
+                    let rescaled = v / 10i128.pow((from_scale - to_scale) as u32);
+                    (rescaled.abs() < max_unscaled).then_some(rescaled)
+                })
+                .collect();
+            Arc::new(output.with_precision_and_scale(precision, to_scale)?)
+        }
+        other => {
+            return Err(DataFusionError::Execution(format!(
+                "round: unsupported data type: {other}"
+            )));
+        }
+    }))
+}
+
+/// rounds an integer to 10^(-scale), integers are unchanged if scale >= 0.
+fn round_i128(v: i128, scale: i32, half_even: bool) -> i128 {
+    if scale >= 0 {
+        return v;
+    }
+    let unit = match 10i128.checked_pow(-scale as u32) {
+        Some(unit) => unit,
+        None => return 0,
+    };
+    let (quotient, remainder) = (v / unit, (v % unit).abs());
+    let round_up = match (remainder * 2).cmp(&unit) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Equal => !half_even || quotient % 2 != 0,
+        std::cmp::Ordering::Less => false,
+    };
+    let rounded = if round_up {
+        quotient + v.signum()
+    } else {
+        quotient
+    };
+    rounded.wrapping_mul(unit)
+}
+
+/// rounds a double like spark, which converts the double to a decimal from its
+/// shortest string representation before rounding, so round(2.675, 2) = 2.68.
+fn round_f64(v: f64, scale: i32, half_even: bool) -> f64 {
+    if !v.is_finite() {
+        return v;
+    }
+
+    // v = 0.d1d2d3... * 10^(exp + 1)
+    let repr = format!("{:e}", v.abs());
+    let (mantissa, exp) = repr.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let digits: Vec<u8> = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| b - b'0')
+        .collect();
+
+    let num_kept_digits = exp + scale + 1;
+    if num_kept_digits >= digits.len() as i32 {
+        return v;
+    }
+    if num_kept_digits < 0 {
+        return 0.0;
+    }
+    let (kept, rest) = digits.split_at(num_kept_digits as usize);
+    let mut rounded = kept.iter().fold(0u64, |acc, &d| acc * 10 + d as u64);
+    let round_up = match rest[0].cmp(&5) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Equal => {
+            !half_even || rest[1..].iter().any(|&d| d != 0) || rounded % 2 != 0
+        }
+        std::cmp::Ordering::Less => false,
+    };
+    if round_up {
+        rounded += 1;
+    }
+    if rounded == 0 {
+        return 0.0;
+    }
+    let rounded: f64 = format!("{}e{}", rounded, -scale).parse().unwrap();
+    if v.is_sign_negative() {
+        -rounded
+    } else {
+        rounded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_math::*;
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_pmod() -> Result<()> {
+        let r = spark_pmod(&[
+            ColumnarValue::Array(Arc::new(Int32Array::from(vec![
+                Some(7),
+                Some(-7),
+                Some(i32::MIN),
+                Some(1),
+                None,
+            ]))),
+            ColumnarValue::Array(Arc::new(Int32Array::from(vec![
+                Some(3),
+                Some(3),
+                Some(-1),
+                Some(0),
+                Some(1),
+            ]))),
+            ColumnarValue::Scalar(ScalarValue::from(false)),
+        ])?
+        .into_array(5);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![Some(1), Some(2), Some(0), None, None])
+        );
+
+        let r = spark_pmod(&[
+            ColumnarValue::Array(Arc::new(
+                Decimal128Array::from(vec![-750]).with_precision_and_scale(5, 2)?,
+            )),
+            ColumnarValue::Scalar(ScalarValue::Decimal128(Some(200), 5, 2)),
+            ColumnarValue::Scalar(ScalarValue::from(false)),
+        ])?
+        .into_array(1);
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&r),
+            &Decimal128Array::from(vec![50]).with_precision_and_scale(5, 2)?
+        );
+
+        assert!(spark_pmod(&[
+            ColumnarValue::Scalar(ScalarValue::Int64(Some(1))),
+            ColumnarValue::Scalar(ScalarValue::Int64(Some(0))),
+            ColumnarValue::Scalar(ScalarValue::from(true)),
+        ])
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_factorial() -> Result<()> {
+        let r = spark_factorial(&[ColumnarValue::Array(Arc::new(Int32Array::from(vec![
+            Some(0),
+            Some(5),
+            Some(20),
+            Some(21),
+            Some(-1),
+            None,
+        ])))])?
+        .into_array(6);
+        assert_eq!(
+            as_primitive_array::<Int64Type>(&r),
+            &Int64Array::from(vec![
+                Some(1),
+                Some(120),
+                Some(2432902008176640000),
+                None,
+                None,
+                None
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_round() -> Result<()> {
+        let r = spark_round(&[
+            ColumnarValue::Array(Arc::new(Float64Array::from(vec![
+                2.675,
+                -2.5,
+                0.125,
+                1234.5,
+                f64::NAN,
+            ]))),
+            ColumnarValue::Scalar(ScalarValue::Int32(Some(2))),
+        ])?
+        .into_array(5);
+        let r = as_primitive_array::<Float64Type>(&r);
+        assert_eq!(&r.values()[..4], &[2.68, -2.5, 0.13, 1234.5]);
+        assert!(r.value(4).is_nan());
+
+        let r = spark_bround(&[
+            ColumnarValue::Array(Arc::new(Float64Array::from(vec![2.5, 3.5, -0.125, 1234.5]))),
+            ColumnarValue::Scalar(ScalarValue::Int32(Some(0))),
+        ])?
+        .into_array(4);
+        assert_eq!(
+            as_primitive_array::<Float64Type>(&r),
+            &Float64Array::from(vec![2.0, 4.0, 0.0, 1234.0])
+        );
+
+        let r = spark_round(&[
+            ColumnarValue::Array(Arc::new(Int32Array::from(vec![15, -15, 14, 25]))),
+            ColumnarValue::Scalar(ScalarValue::Int32(Some(-1))),
+        ])?
+        .into_array(4);
+        assert_eq!(
+            as_primitive_array::<Int32Type>(&r),
+            &Int32Array::from(vec![20, -20, 10, 30])
+        );
+
+        // decimal(5,3) => decimal(5,1)
+        let decimals = ColumnarValue::Array(Arc::new(
+            Decimal128Array::from(vec![Some(12250), Some(-12250), Some(99999), None])
+                .with_precision_and_scale(5, 3)?,
+        ));
+        let r =
+            spark_round(&[decimals.clone(), ColumnarValue::Scalar(ScalarValue::Int32(Some(1)))])?
+                .into_array(4);
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&r),
+            &Decimal128Array::from(vec![Some(123), Some(-123), Some(1000), None])
+                .with_precision_and_scale(5, 1)?
+        );
+        let r = spark_bround(&[decimals, ColumnarValue::Scalar(ScalarValue::Int32(Some(1)))])?
+            .into_array(4);
+        assert_eq!(
+            as_primitive_array::<Decimal128Type>(&r),
+            &Decimal128Array::from(vec![Some(122), Some(-122), Some(1000), None])
+                .with_precision_and_scale(5, 1)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hypot_log_rint() -> Result<()> {
+        let r = spark_hypot(&[
+            ColumnarValue::Scalar(ScalarValue::Float64(Some(3.0))),
+            ColumnarValue::Array(Arc::new(Float64Array::from(vec![Some(4.0), None]))),
+        ])?
+        .into_array(2);
+        assert_eq!(
+            as_primitive_array::<Float64Type>(&r),
+            &Float64Array::from(vec![Some(5.0), None])
+        );
+
+        let r = spark_log_with_base(&[
+            ColumnarValue::Scalar(ScalarValue::Float64(Some(2.0))),
+            ColumnarValue::Array(Arc::new(Float64Array::from(vec![4.0, 0.0, -1.0]))),
+        ])?
+        .into_array(3);
+        assert_eq!(
+            as_primitive_array::<Float64Type>(&r),
+            &Float64Array::from(vec![Some(2.0), None, None])
+        );
+
+        let r = spark_rint(&[ColumnarValue::Array(Arc::new(Float64Array::from(vec![
+            2.5, 3.5, -2.5, 1.4, -0.5,
+        ])))])?
+        .into_array(5);
+        let r = as_primitive_array::<Float64Type>(&r);
+        assert_eq!(r, &Float64Array::from(vec![2.0, 4.0, -2.0, 1.0, -0.0]));
+        assert!(r.value(4).is_sign_negative());
+        Ok(())
+    }
+}
@@ -18,7 +18,7 @@ use datafusion::physical_plan::ColumnarValue;
 use datafusion_ext_commons::spark_hash::create_hashes;
 use std::sync::Arc;
 
-/// implements org.apache.spark.sql.catalyst.expressions.UnscaledValue
+/// implements org.apache.spark.sql.catalyst.expressions.Murmur3Hash
 pub fn spark_murmur3_hash(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     let len = args
         .iter()
@@ -0,0 +1,88 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::*;
+use arrow::compute::kernels::zip::zip;
+use arrow::datatypes::*;
+use datafusion::common::{DataFusionError, Result};
+use datafusion::physical_plan::ColumnarValue;
+
+/// nanvl(expr1, expr2) - returns expr1 if it's not NaN, or expr2 otherwise.
+/// both arguments are expected to have been casted to the same float type.
+pub fn spark_nanvl(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let (left, right) = (&arrays[0], &arrays[1]);
+
+    let is_nan: BooleanArray = match left.data_type() {
+        DataType::Float32 => as_primitive_array::<Float32Type>(left)
+            .iter()
+            .map(|v| Some(v.map(|v| v.is_nan()).unwrap_or(false)))
+            .collect(),
+        DataType::Float64 => as_primitive_array::<Float64Type>(left)
+            .iter()
+            .map(|v| Some(v.map(|v| v.is_nan()).unwrap_or(false)))
+            .collect(),
+        dt => {
+            return Err(DataFusionError::Execution(format!(
+                "nanvl: unsupported data type: {:?}",
+                dt
+            )));
+        }
+    };
+    Ok(ColumnarValue::Array(zip(&is_nan, right, left)?))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_nanvl::spark_nanvl;
+    use arrow::array::*;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_nanvl() -> Result<()> {
+        let r = spark_nanvl(&vec![
+            ColumnarValue::Array(Arc::new(Float64Array::from(vec![
+                Some(1.0),
+                Some(f64::NAN),
+                None,
+                Some(f64::NAN),
+            ]))),
+            ColumnarValue::Array(Arc::new(Float64Array::from(vec![
+                Some(10.0),
+                Some(20.0),
+                Some(30.0),
+                None,
+            ]))),
+        ])?
+        .into_array(4);
+        assert_eq!(
+            as_primitive_array::<arrow::datatypes::Float64Type>(&r),
+            &Float64Array::from(vec![Some(1.0), Some(20.0), None, None])
+        );
+
+        let r = spark_nanvl(&vec![
+            ColumnarValue::Scalar(ScalarValue::Float32(Some(f32::NAN))),
+            ColumnarValue::Scalar(ScalarValue::Float32(Some(0.0))),
+        ])?
+        .into_array(1);
+        assert_eq!(
+            as_primitive_array::<arrow::datatypes::Float32Type>(&r),
+            &Float32Array::from(vec![0.0])
+        );
+        Ok(())
+    }
+}
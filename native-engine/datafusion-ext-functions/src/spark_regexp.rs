@@ -0,0 +1,515 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::{Array, ArrayRef, BooleanArray, StringArray};
+use datafusion::common::cast::{as_int32_array, as_string_array};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::physical_plan::ColumnarValue;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// rlike() function compatible with spark, returns true if any substring
+/// matches the java regex
+pub fn regexp_like(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let strings = as_string_array(&arrays[0])?;
+    let patterns = as_string_array(&arrays[1])?;
+    let mut regexes = RegexCache::default();
+
+    let matched: BooleanArray = (0..strings.len())
+        .map(|i| {
+            if arrays.iter().any(|array| array.is_null(i)) {
+                return Ok(None);
+            }
+            let regex = regexes.get(patterns.value(i))?;
+            Ok(Some(regex.is_match(strings.value(i))))
+        })
+        .collect::<Result<_>>()?;
+    Ok(ColumnarValue::Array(Arc::new(matched)))
+}
+
+/// regexp_replace() function compatible with spark, replaces all matches
+/// starting from 1-based pos. the replacement uses java's syntax (`$1`,
+/// `${name}` and `\$`).
+/// regexp_replace('100-200', '(\d+)', 'num:$1', 1) = 'num:100-num:200'
+pub fn regexp_replace(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let strings = as_string_array(&arrays[0])?;
+    let patterns = as_string_array(&arrays[1])?;
+    let replacements = as_string_array(&arrays[2])?;
+    let positions = as_int32_array(&arrays[3])?;
+    let mut regexes = RegexCache::default();
+
+    let replaced: StringArray = (0..strings.len())
+        .map(|i| {
+            if arrays.iter().any(|array| array.is_null(i)) {
+                return Ok(None);
+            }
+            let pos = positions.value(i);
+            if pos <= 0 {
+                return Err(DataFusionError::Execution(format!(
+                    "regexp_replace position must be positive, got {pos}"
+                )));
+            }
+            let regex = regexes.get(patterns.value(i))?;
+            let replacement =
+                translate_java_replacement(replacements.value(i), regex.captures_len() - 1)?;
+
+            // like spark, the string is unchanged if pos exceeds its length
+            let s = strings.value(i);
+            Ok(Some(match s.char_indices().nth(pos as usize - 1) {
+                Some((start, _)) => {
+                    let replaced = regex.replace_all(&s[start..], replacement.as_str());
+                    format!("{}{}", &s[..start], replaced)
+                }
+                None => s.to_owned(),
+            }))
+        })
+        .collect::<Result<_>>()?;
+    Ok(ColumnarValue::Array(Arc::new(replaced)))
+}
+
+/// regexp_extract() function compatible with spark, returns the idx-th group
+/// of the first match, or an empty string if there is no match or the group
+/// does not participate in the match.
+/// regexp_extract('100-200', '(\d+)-(\d+)', 2) = '200'
+pub fn regexp_extract(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let strings = as_string_array(&arrays[0])?;
+    let patterns = as_string_array(&arrays[1])?;
+    let indices = as_int32_array(&arrays[2])?;
+    let mut regexes = RegexCache::default();
+
+    let extracted: StringArray = (0..strings.len())
+        .map(|i| {
+            if arrays.iter().any(|array| array.is_null(i)) {
+                return Ok(None);
+            }
+            let regex = regexes.get(patterns.value(i))?;
+            let idx = indices.value(i);
+            let num_groups = regex.captures_len() - 1;
+            if idx < 0 || idx as usize > num_groups {
+                return Err(DataFusionError::Execution(format!(
+                    "Regex group count is {num_groups}, but the specified group index is {idx}"
+                )));
+            }
+            Ok(Some(
+                regex
+                    .captures(strings.value(i))
+                    .and_then(|captures| captures.get(idx as usize))
+                    .map(|group| group.as_str().to_owned())
+                    .unwrap_or_default(),
+            ))
+        })
+        .collect::<Result<_>>()?;
+    Ok(ColumnarValue::Array(Arc::new(extracted)))
+}
+
+/// Compiled regexes of the current batch, constant patterns are compiled only
+/// once per batch.
+#[derive(Default)]
+struct RegexCache {
+    regexes: HashMap<String, Regex>,
+}
+
+impl RegexCache {
+    fn get(&mut self, pattern: &str) -> Result<&Regex> {
+        if !self.regexes.contains_key(pattern) {
+            let translated = translate_java_regex(pattern)?;
+            let regex = Regex::new(&translated).map_err(|err| {
+                DataFusionError::Execution(format!("cannot compile regex {pattern:?}: {err}"))
+            })?;
+            self.regexes.insert(pattern.to_owned(), regex);
+        }
+        Ok(&self.regexes[pattern])
+    }
+}
+
+/// Translates a java regex to the rust regex dialect:
+///  - predefined classes (`\d`, `\w`, `\s`) and posix classes (`\p{Alpha}`)
+///    are ascii-only like in java
+///  - named groups `(?<name>...)` are written as `(?P<name>...)`
+///  - `\Q...\E` quotes literals
+///  - embedded flags `i`, `m`, `s` and `x` are kept, `u` and `d` are dropped
+///
+/// constructs without rust equivalents (lookaround, atomic groups, possessive
+/// quantifiers and backreferences) are rejected.
+fn translate_java_regex(pattern: &str) -> Result<String> {
+    let unsupported = |construct: &str| {
+        Err(DataFusionError::Execution(format!(
+            "unsupported regex construct {construct} in {pattern:?}"
+        )))
+    };
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut translated = String::with_capacity(pattern.len());
+    let mut class_depth = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                let escaped = match chars.get(i) {
+                    Some(&escaped) => escaped,
+                    None => return unsupported("trailing backslash"),
+                };
+                match escaped {
+                    // nested classes are also allowed inside character classes
+                    'd' => translated.push_str("[0-9]"),
+                    'D' => translated.push_str("[^0-9]"),
+                    'w' => translated.push_str("[a-zA-Z0-9_]"),
+                    'W' => translated.push_str("[^a-zA-Z0-9_]"),
+                    's' => translated.push_str(r"[\t\n\x0B\f\r ]"),
+                    'S' => translated.push_str(r"[^\t\n\x0B\f\r ]"),
+                    'p' | 'P' => {
+                        let name = if chars.get(i + 1) == Some(&'{') {
+                            let end = match chars[i..].iter().position(|&c| c == '}') {
+                                Some(offset) => i + offset,
+                                None => return unsupported("unclosed \\p{"),
+                            };
+                            let name = chars[i + 2..end].iter().collect::<String>();
+                            i = end;
+                            name
+                        } else {
+                            i += 1;
+                            match chars.get(i) {
+                                Some(c) => c.to_string(),
+                                None => return unsupported("trailing \\p"),
+                            }
+                        };
+                        let negated = if escaped == 'P' { "^" } else { "" };
+                        match translate_java_property(&name) {
+                            JavaProperty::Posix(class) => {
+                                translated.push_str(&format!("[{negated}[:{class}:]]"))
+                            }
+                            JavaProperty::Unicode(property) => {
+                                translated.push_str(&format!("\\{escaped}{{{property}}}"))
+                            }
+                            JavaProperty::Unsupported => {
+                                return unsupported(&format!("\\{escaped}{{{name}}}"))
+                            }
+                        }
+                    }
+                    'Q' => {
+                        let quoted_end = (i + 1..chars.len())
+                            .find(|&j| chars[j] == '\\' && chars.get(j + 1) == Some(&'E'))
+                            .unwrap_or(chars.len());
+                        let quoted = chars[i + 1..quoted_end].iter().collect::<String>();
+                        translated.push_str(&regex::escape(&quoted));
+                        i = quoted_end + 1;
+                    }
+                    'e' => translated.push_str(r"\x1B"),
+                    'a' => translated.push_str(r"\x07"),
+                    '0' => {
+                        // octal escape \0n, \0nn or \0mnn (m <= 3)
+                        let mut value = 0;
+                        let mut num_digits = 0;
+                        while let Some(digit) = chars.get(i + 1).and_then(|c| c.to_digit(8)) {
+                            if num_digits == 3 || (num_digits == 2 && value > 0o37) {
+                                break;
+                            }
+                            value = value * 8 + digit;
+                            num_digits += 1;
+                            i += 1;
+                        }
+                        if num_digits == 0 {
+                            return unsupported("illegal octal escape");
+                        }
+                        translated.push_str(&format!("\\x{{{value:X}}}"));
+                    }
+                    'c' => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some(&c) => translated.push_str(&format!("\\x{{{:X}}}", c as u32 ^ 64)),
+                            None => return unsupported("trailing \\c"),
+                        }
+                    }
+                    '1'..='9' | 'k' => return unsupported("backreference"),
+                    'G' | 'Z' | 'R' | 'X' | 'h' | 'H' | 'V' => {
+                        return unsupported(&format!("\\{escaped}"))
+                    }
+                    _ => {
+                        translated.push('\\');
+                        translated.push(escaped);
+                    }
+                }
+            }
+            '[' => {
+                class_depth += 1;
+                translated.push('[');
+            }
+            ']' if class_depth > 0 => {
+                class_depth -= 1;
+                translated.push(']');
+            }
+            '(' if class_depth == 0 && chars.get(i + 1) == Some(&'?') => {
+                let next = chars.get(i + 2).cloned();
+                let next2 = chars.get(i + 3).cloned();
+                match (next, next2) {
+                    (Some('=' | '!'), _) | (Some('<'), Some('=' | '!')) => {
+                        return unsupported("lookaround")
+                    }
+                    (Some('>'), _) => return unsupported("atomic group"),
+                    (Some('<'), _) => {
+                        translated.push_str("(?P<");
+                        i += 2;
+                    }
+                    (Some(':'), _) => {
+                        translated.push_str("(?:");
+                        i += 2;
+                    }
+                    _ => {
+                        // embedded flags: (?flags) or (?flags:...)
+                        let mut flags = String::new();
+                        let mut j = i + 2;
+                        while let Some(&flag) = chars.get(j) {
+                            match flag {
+                                'i' | 'm' | 's' | 'x' | '-' => flags.push(flag),
+                                // rust regexes are always unicode-aware, and
+                                // only '\n' is a line terminator
+                                'u' | 'd' => {}
+                                ')' | ':' => break,
+                                _ => return unsupported(&format!("flag {flag}")),
+                            }
+                            j += 1;
+                        }
+                        let terminator = match chars.get(j) {
+                            Some(&terminator) => terminator,
+                            None => return unsupported("unclosed group"),
+                        };
+                        let flags = flags.trim_end_matches('-');
+                        match (flags.is_empty(), terminator) {
+                            (true, ')') => {} // no flags left
+                            (true, _) => translated.push_str("(?:"),
+                            (false, _) => translated.push_str(&format!("(?{flags}{terminator}")),
+                        }
+                        i = j;
+                    }
+                }
+            }
+            '*' | '+' | '?' | '}' if class_depth == 0 && chars.get(i + 1) == Some(&'+') => {
+                return unsupported("possessive quantifier");
+            }
+            c => translated.push(c),
+        }
+        i += 1;
+    }
+    Ok(translated)
+}
+
+enum JavaProperty {
+    Posix(&'static str),
+    Unicode(String),
+    Unsupported,
+}
+
+fn translate_java_property(name: &str) -> JavaProperty {
+    match name {
+        "Lower" => JavaProperty::Posix("lower"),
+        "Upper" => JavaProperty::Posix("upper"),
+        "ASCII" => JavaProperty::Posix("ascii"),
+        "Alpha" => JavaProperty::Posix("alpha"),
+        "Digit" => JavaProperty::Posix("digit"),
+        "Alnum" => JavaProperty::Posix("alnum"),
+        "Punct" => JavaProperty::Posix("punct"),
+        "Graph" => JavaProperty::Posix("graph"),
+        "Print" => JavaProperty::Posix("print"),
+        "Blank" => JavaProperty::Posix("blank"),
+        "Cntrl" => JavaProperty::Posix("cntrl"),
+        "XDigit" => JavaProperty::Posix("xdigit"),
+        "Space" => JavaProperty::Posix("space"),
+        name if name.starts_with("In") || name.starts_with("java") => JavaProperty::Unsupported,
+        name => JavaProperty::Unicode(name.strip_prefix("Is").unwrap_or(name).to_owned()),
+    }
+}
+
+/// Translates a java replacement string to the rust regex syntax. like java,
+/// `$n` takes the longest group number not exceeding the group count.
+fn translate_java_replacement(replacement: &str, num_groups: usize) -> Result<String> {
+    let illegal = |reason: &str| {
+        Err(DataFusionError::Execution(format!(
+            "illegal regex replacement {replacement:?}: {reason}"
+        )))
+    };
+    let chars = replacement.chars().collect::<Vec<_>>();
+    let mut translated = String::with_capacity(replacement.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                match chars.get(i) {
+                    Some('$') => translated.push_str("$$"),
+                    Some(&c) => translated.push(c),
+                    None => return illegal("character to be escaped is missing"),
+                }
+            }
+            '$' => {
+                i += 1;
+                match chars.get(i) {
+                    Some('{') => {
+                        let end = match chars[i..].iter().position(|&c| c == '}') {
+                            Some(offset) => i + offset,
+                            None => {
+                                return illegal("named capturing group is missing trailing '}'")
+                            }
+                        };
+                        let name = chars[i + 1..end].iter().collect::<String>();
+                        translated.push_str(&format!("${{{name}}}"));
+                        i = end;
+                    }
+                    Some(c) if c.is_ascii_digit() => {
+                        let mut group = c.to_digit(10).unwrap() as usize;
+                        if group > num_groups {
+                            return illegal(&format!("no group {group}"));
+                        }
+                        while let Some(digit) = chars.get(i + 1).and_then(|c| c.to_digit(10)) {
+                            let new_group = group * 10 + digit as usize;
+                            if new_group > num_groups {
+                                break;
+                            }
+                            group = new_group;
+                            i += 1;
+                        }
+                        translated.push_str(&format!("${{{group}}}"));
+                    }
+                    _ => return illegal("illegal group reference"),
+                }
+            }
+            c => translated.push(c),
+        }
+        i += 1;
+    }
+    Ok(translated)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_regexp::{
+        regexp_extract, regexp_like, regexp_replace, translate_java_regex,
+        translate_java_replacement,
+    };
+    use arrow::array::StringArray;
+    use datafusion::common::cast::{as_boolean_array, as_string_array};
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_translate_java_regex() -> Result<()> {
+        assert_eq!(translate_java_regex(r"\d+")?, "[0-9]+");
+        assert_eq!(translate_java_regex(r"[\w-]")?, "[[a-zA-Z0-9_]-]");
+        assert_eq!(
+            translate_java_regex(r"\p{Alpha}\P{Lower}")?,
+            "[[:alpha:]][^[:lower:]]"
+        );
+        assert_eq!(translate_java_regex(r"\p{IsLatin}\pL")?, r"\p{Latin}\p{L}");
+        assert_eq!(
+            translate_java_regex(r"(?<year>\d{4})")?,
+            "(?P<year>[0-9]{4})"
+        );
+        assert_eq!(translate_java_regex(r"(?iu)a(?s-d:.)")?, "(?i)a(?s:.)");
+        assert_eq!(translate_java_regex(r"\Q1+1\E=2")?, r"1\+1=2");
+        assert_eq!(translate_java_regex(r"\0101\cA")?, r"\x{41}\x{1}");
+
+        assert!(translate_java_regex(r"a(?=b)").is_err());
+        assert!(translate_java_regex(r"(?<!a)b").is_err());
+        assert!(translate_java_regex(r"(a)\1").is_err());
+        assert!(translate_java_regex(r"a++").is_err());
+        assert!(translate_java_regex(r"\p{InGreek}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_java_replacement() -> Result<()> {
+        assert_eq!(translate_java_replacement(r"<$1>", 1)?, "<${1}>");
+        assert_eq!(translate_java_replacement(r"$12", 1)?, "${1}2");
+        assert_eq!(translate_java_replacement(r"$12", 12)?, "${12}");
+        assert_eq!(
+            translate_java_replacement(r"${name}\$\\", 1)?,
+            r"${name}$$\"
+        );
+        assert!(translate_java_replacement(r"$2", 1).is_err());
+        assert!(translate_java_replacement(r"$", 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_regexp_functions() -> Result<()> {
+        let strings = ColumnarValue::Array(Arc::new(StringArray::from_iter(vec![
+            Some(format!("100-200")),
+            Some(format!("abc")),
+            Some(format!("")),
+            None,
+        ])));
+
+        let r = regexp_like(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(r"\d+")),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_boolean_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some(true), Some(false), Some(false), None]
+        );
+
+        let r = regexp_replace(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(r"(\d+)")),
+            ColumnarValue::Scalar(ScalarValue::from("<$1>")),
+            ColumnarValue::Scalar(ScalarValue::from(2_i32)),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("1<00>-<200>"), Some("abc"), Some(""), None]
+        );
+
+        // empty matches
+        let r = regexp_replace(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from("x*")),
+            ColumnarValue::Scalar(ScalarValue::from("-")),
+            ColumnarValue::Scalar(ScalarValue::from(1_i32)),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("-1-0-0---2-0-0-"), Some("-a-b-c-"), Some(""), None]
+        );
+
+        let r = regexp_extract(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(r"(\d+)-(\d+)|(c)")),
+            ColumnarValue::Scalar(ScalarValue::from(2_i32)),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("200"), Some(""), Some(""), None]
+        );
+
+        // group index out of range
+        assert!(regexp_extract(&vec![
+            strings,
+            ColumnarValue::Scalar(ScalarValue::from(r"(\d+)")),
+            ColumnarValue::Scalar(ScalarValue::from(2_i32)),
+        ])
+        .is_err());
+        Ok(())
+    }
+}
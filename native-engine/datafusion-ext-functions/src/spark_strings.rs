@@ -17,6 +17,7 @@ use arrow::datatypes::DataType;
 use datafusion::common::cast::{as_int32_array, as_list_array, as_string_array};
 use datafusion::common::{DataFusionError, Result, ScalarValue};
 use datafusion::physical_plan::ColumnarValue;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub fn string_lower(args: &[ColumnarValue]) -> Result<ColumnarValue> {
@@ -334,11 +335,159 @@ pub fn string_concat_ws(args: &[ColumnarValue]) -> Result<ColumnarValue> {
     Ok(ColumnarValue::Array(concatenated_string_array))
 }
 
+/// lpad() function compatible with spark, the string is truncated to len
+/// chars if it is longer than len or the pad string is empty
+/// lpad('hi', 5, 'ab') = 'abahi'
+/// lpad('hello', 2, 'ab') = 'he'
+pub fn string_lpad(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    string_pad(args, true)
+}
+
+/// rpad() function compatible with spark, see `string_lpad()`
+/// rpad('hi', 5, 'ab') = 'hiaba'
+pub fn string_rpad(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    string_pad(args, false)
+}
+
+fn string_pad(args: &[ColumnarValue], left: bool) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let strings = as_string_array(&arrays[0])?;
+    let lens = as_int32_array(&arrays[1])?;
+    let pads = as_string_array(&arrays[2])?;
+
+    let padded_string_array: ArrayRef =
+        Arc::new(StringArray::from_iter((0..strings.len()).map(|i| {
+            if arrays.iter().any(|array| array.is_null(i)) {
+                return None;
+            }
+            let s = strings.value(i);
+            let len = lens.value(i).max(0) as usize;
+            let pad = pads.value(i);
+            let num_chars = s.chars().count();
+            if num_chars >= len || pad.is_empty() {
+                return Some(s.chars().take(len).collect::<String>());
+            }
+            let padding = pad.chars().cycle().take(len - num_chars);
+            Some(if left {
+                padding.chain(s.chars()).collect()
+            } else {
+                s.chars().chain(padding).collect()
+            })
+        })));
+    Ok(ColumnarValue::Array(padded_string_array))
+}
+
+/// translate() function compatible with spark, chars in matching are replaced
+/// with chars at the same positions in replace, or deleted if there are none.
+/// the first occurrence wins for duplicated chars in matching.
+/// translate('AaBbCc', 'abc', '12') = 'A1B2C'
+pub fn string_translate(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let string_array = args[0].clone().into_array(1);
+    let (matching, replace) = match (&args[1], &args[2]) {
+        (
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(matching))),
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some(replace))),
+        ) => (matching, replace),
+        (ColumnarValue::Scalar(matching), ColumnarValue::Scalar(replace))
+            if matching.is_null() || replace.is_null() =>
+        {
+            return Ok(ColumnarValue::Scalar(ScalarValue::Utf8(None)));
+        }
+        _ => {
+            return Err(DataFusionError::Execution(format!(
+                "string_translate matching and replace only support literal string"
+            )));
+        }
+    };
+
+    let mut dict: HashMap<char, Option<char>> = HashMap::new();
+    let mut replace_chars = replace.chars();
+    for c in matching.chars() {
+        let replace_char = replace_chars.next();
+        dict.entry(c).or_insert(replace_char);
+    }
+
+    let translated_string_array: ArrayRef = Arc::new(StringArray::from_iter(
+        as_string_array(&string_array)?.into_iter().map(|s| {
+            s.map(|s| {
+                s.chars()
+                    .filter_map(|c| dict.get(&c).cloned().unwrap_or(Some(c)))
+                    .collect::<String>()
+            })
+        }),
+    ));
+    Ok(ColumnarValue::Array(translated_string_array))
+}
+
+/// overlay() function compatible with spark, replaces len chars of the input
+/// starting at 1-based pos with the replace string. len defaults to the length
+/// of replace if negative.
+/// overlay('Spark SQL', '_', 6, -1) = 'Spark_SQL'
+/// overlay('Spark SQL', 'CORE', 7, 0) = 'Spark CORESQL'
+pub fn string_overlay(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    let inputs = as_string_array(&arrays[0])?;
+    let replaces = as_string_array(&arrays[1])?;
+    let positions = as_int32_array(&arrays[2])?;
+    let lens = as_int32_array(&arrays[3])?;
+
+    let overlaid_string_array: ArrayRef =
+        Arc::new(StringArray::from_iter((0..inputs.len()).map(|i| {
+            if arrays.iter().any(|array| array.is_null(i)) {
+                return None;
+            }
+            let input = inputs.value(i).chars().collect::<Vec<_>>();
+            let replace = replaces.value(i);
+            let pos = positions.value(i);
+            let len = match lens.value(i) {
+                len if len >= 0 => len,
+                _ => replace.chars().count() as i32,
+            };
+
+            // int arithmetics overflow like java
+            let mut overlaid = substring_sql(&input, 1, pos.wrapping_sub(1));
+            overlaid.push_str(replace);
+            overlaid.push_str(&substring_sql(&input, pos.wrapping_add(len), i32::MAX));
+            Some(overlaid)
+        })));
+    Ok(ColumnarValue::Array(overlaid_string_array))
+}
+
+/// substring with the semantics of spark's `UTF8String.substringSQL()`, pos is
+/// 1-based and counts from the end if negative
+fn substring_sql(chars: &[char], pos: i32, len: i32) -> String {
+    let num_chars = chars.len() as i64;
+    let start = match pos {
+        pos if pos > 0 => pos as i64 - 1,
+        pos if pos < 0 => num_chars + pos as i64,
+        _ => 0,
+    };
+    let end = (start + len as i64).min(num_chars);
+    let start = start.max(0);
+    if start >= end {
+        return String::new();
+    }
+    chars[start as usize..end as usize].iter().collect()
+}
+
+pub(crate) fn args_to_arrays(args: &[ColumnarValue]) -> Vec<ArrayRef> {
+    let num_rows = args
+        .iter()
+        .find_map(|arg| match arg {
+            ColumnarValue::Array(array) => Some(array.len()),
+            ColumnarValue::Scalar(_) => None,
+        })
+        .unwrap_or(1);
+    args.iter()
+        .map(|arg| arg.clone().into_array(num_rows))
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use crate::spark_strings::{
-        string_concat, string_concat_ws, string_lower, string_repeat, string_space, string_split,
-        string_upper,
+        string_concat, string_concat_ws, string_lower, string_lpad, string_overlay, string_repeat,
+        string_rpad, string_space, string_split, string_translate, string_upper,
     };
     use arrow::array::{Int32Array, ListBuilder, StringArray, StringBuilder};
     use datafusion::common::cast::{as_list_array, as_string_array};
@@ -531,4 +680,115 @@ mod test {
         );
         Ok(())
     }
+    #[test]
+    fn test_string_pad() -> Result<()> {
+        let strings = ColumnarValue::Array(Arc::new(StringArray::from_iter(vec![
+            Some(format!("hi")),
+            Some(format!("hello")),
+            Some(format!("中文")),
+            None,
+        ])));
+        let r = string_lpad(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(5_i32)),
+            ColumnarValue::Scalar(ScalarValue::from("ab")),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("abahi"), Some("hello"), Some("aba中文"), None]
+        );
+
+        let r = string_rpad(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(3_i32)),
+            ColumnarValue::Scalar(ScalarValue::from("ab")),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("hia"), Some("hel"), Some("中文a"), None]
+        );
+
+        // empty pad and negative len
+        let r = string_lpad(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(4_i32)),
+            ColumnarValue::Scalar(ScalarValue::from("")),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("hi"), Some("hell"), Some("中文"), None]
+        );
+        let r = string_rpad(&vec![
+            strings.clone(),
+            ColumnarValue::Scalar(ScalarValue::from(-1_i32)),
+            ColumnarValue::Scalar(ScalarValue::from("ab")),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some(""), Some(""), Some(""), None]
+        );
+
+        // null pad
+        let r = string_lpad(&vec![
+            strings,
+            ColumnarValue::Scalar(ScalarValue::from(5_i32)),
+            ColumnarValue::Scalar(ScalarValue::Utf8(None)),
+        ])?;
+        let s = r.into_array(4);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![None, None, None, None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_translate() -> Result<()> {
+        let r = string_translate(&vec![
+            ColumnarValue::Array(Arc::new(StringArray::from_iter(vec![
+                Some(format!("AaBbCc")),
+                Some(format!("abcabc")),
+                None,
+            ]))),
+            ColumnarValue::Scalar(ScalarValue::from("abca")),
+            ColumnarValue::Scalar(ScalarValue::from("12")),
+        ])?;
+        let s = r.into_array(3);
+        assert_eq!(
+            as_string_array(&s)?.into_iter().collect::<Vec<_>>(),
+            vec![Some("A1B2C"), Some("1212"), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_overlay() -> Result<()> {
+        let overlay = |pos: i32, len: i32| -> Result<Vec<Option<String>>> {
+            let r = string_overlay(&vec![
+                ColumnarValue::Array(Arc::new(StringArray::from_iter(vec![
+                    Some(format!("Spark SQL")),
+                    None,
+                ]))),
+                ColumnarValue::Scalar(ScalarValue::from("_")),
+                ColumnarValue::Scalar(ScalarValue::from(pos)),
+                ColumnarValue::Scalar(ScalarValue::from(len)),
+            ])?;
+            let s = r.into_array(2);
+            Ok(as_string_array(&s)?
+                .into_iter()
+                .map(|s| s.map(|s| s.to_string()))
+                .collect())
+        };
+        assert_eq!(overlay(6, -1)?, vec![Some(format!("Spark_SQL")), None]);
+        assert_eq!(overlay(7, 0)?, vec![Some(format!("Spark _SQL")), None]);
+        assert_eq!(overlay(1, 100)?, vec![Some(format!("_")), None]);
+        assert_eq!(overlay(100, 1)?, vec![Some(format!("Spark SQL_")), None]);
+        assert_eq!(overlay(0, 2)?, vec![Some(format!("_park SQL")), None]);
+        assert_eq!(overlay(-3, 1)?, vec![Some(format!("_QL")), None]);
+        Ok(())
+    }
 }
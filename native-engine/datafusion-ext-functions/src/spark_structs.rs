@@ -0,0 +1,89 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::spark_strings::args_to_arrays;
+use arrow::array::*;
+use arrow::row::{RowConverter, SortField};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::physical_plan::ColumnarValue;
+use std::sync::Arc;
+
+/// struct equality compatible with spark, returns null if either struct is
+/// null. null fields are considered equal to each other.
+pub fn struct_equal(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let arrays = args_to_arrays(args);
+    if arrays[0].data_type() != arrays[1].data_type() {
+        return Err(DataFusionError::Execution(format!(
+            "struct_equal() requires same types, got {} and {}",
+            arrays[0].data_type(),
+            arrays[1].data_type(),
+        )));
+    }
+
+    // compare in row format, in which null fields are encoded identically
+    let mut converter = RowConverter::new(vec![SortField::new(arrays[0].data_type().clone())])?;
+    let lhs_rows = converter.convert_columns(&[arrays[0].clone()])?;
+    let rhs_rows = converter.convert_columns(&[arrays[1].clone()])?;
+
+    let equals: BooleanArray = (0..arrays[0].len())
+        .map(|i| {
+            if arrays[0].is_null(i) || arrays[1].is_null(i) {
+                return None;
+            }
+            Some(lhs_rows.row(i) == rhs_rows.row(i))
+        })
+        .collect();
+    Ok(ColumnarValue::Array(Arc::new(equals)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_structs::struct_equal;
+    use arrow::array::*;
+    use arrow::datatypes::{DataType, Field};
+    use datafusion::common::Result;
+    use datafusion::physical_plan::ColumnarValue;
+    use std::sync::Arc;
+
+    fn structs(a: Vec<Option<i32>>, b: Vec<Option<&str>>, valids: Vec<bool>) -> ColumnarValue {
+        let array = StructArray::try_new(
+            vec![Field::new("a", DataType::Int32, true), Field::new("b", DataType::Utf8, true)]
+                .into(),
+            vec![Arc::new(Int32Array::from(a)) as ArrayRef, Arc::new(StringArray::from(b))],
+            Some(valids.into()),
+        )
+        .unwrap();
+        ColumnarValue::Array(Arc::new(array))
+    }
+
+    #[test]
+    fn test_struct_equal() -> Result<()> {
+        let lhs = structs(
+            vec![Some(1), Some(1), None, Some(3), Some(4)],
+            vec![Some("x"), Some("x"), Some("z"), None, Some("w")],
+            vec![true, true, true, true, false],
+        );
+        let rhs = structs(
+            vec![Some(1), Some(2), None, Some(3), Some(4)],
+            vec![Some("x"), Some("x"), Some("z"), None, Some("w")],
+            vec![true, true, true, true, true],
+        );
+        let r = struct_equal(&[lhs, rhs])?.into_array(5);
+        assert_eq!(
+            as_boolean_array(&r),
+            &BooleanArray::from(vec![Some(true), Some(false), Some(true), Some(true), None])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::array::*;
+use datafusion::common::Result;
+use datafusion::physical_plan::ColumnarValue;
+use datafusion_ext_commons::spark_hash::create_xxhash64_hashes;
+use std::sync::Arc;
+
+/// implements org.apache.spark.sql.catalyst.expressions.XxHash64
+pub fn spark_xxhash64(args: &[ColumnarValue]) -> Result<ColumnarValue> {
+    let len = args
+        .iter()
+        .map(|arg| match arg {
+            ColumnarValue::Array(array) => array.len(),
+            ColumnarValue::Scalar(_) => 1,
+        })
+        .max()
+        .unwrap_or(0);
+
+    let arrays = args
+        .iter()
+        .map(|arg| match arg {
+            ColumnarValue::Array(array) => array.clone(),
+            ColumnarValue::Scalar(scalar) => scalar.to_array_of_size(len),
+        })
+        .collect::<Vec<_>>();
+
+    // use identical seed as spark xxhash64()
+    let spark_xxhash64_default_seed = 42u64;
+    let mut hash_buffer = vec![spark_xxhash64_default_seed; len];
+    create_xxhash64_hashes(&arrays, &mut hash_buffer)?;
+
+    Ok(ColumnarValue::Array(Arc::new(
+        Int64Array::from_iter_values(hash_buffer.into_iter().map(|hash| hash as i64)),
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spark_xxhash64::spark_xxhash64;
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use datafusion::logical_expr::ColumnarValue;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_xxhash64_string() {
+        let result = spark_xxhash64(&vec![ColumnarValue::Array(Arc::new(
+            StringArray::from_iter_values(["Spark"]),
+        ))])
+        .unwrap()
+        .into_array(1);
+
+        // same as spark: SELECT xxhash64('Spark')
+        let expected: ArrayRef = Arc::new(Int64Array::from(vec![-4294468057691064905]));
+        assert_eq!(&result, &expected);
+    }
+}
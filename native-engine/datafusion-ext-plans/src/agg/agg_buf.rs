@@ -16,9 +16,10 @@ use crate::common::slim_bytes::SlimBytes;
 use arrow::array::Array;
 use datafusion::common::{Result, ScalarValue};
 use datafusion_ext_commons::io::{
-    read_array, read_bytes_slice, read_data_type, read_len, write_array, write_data_type,
+    read_array, read_bytes_slice, read_data_type, read_len, read_u8, write_array, write_data_type,
     write_len, write_u8,
 };
+use datafusion_ext_commons::spark_bloom_filter::SparkBloomFilter;
 use slimmer_box::SlimmerBox;
 use std::any::Any;
 use std::collections::HashSet;
@@ -140,6 +141,10 @@ pub enum AccumInitialValue {
     Scalar(ScalarValue),
     DynList,
     DynSet,
+    DynBloomFilter {
+        expected_num_items: usize,
+        num_bits: usize,
+    },
 }
 
 pub fn create_agg_buf_from_initial_value(
@@ -212,6 +217,16 @@ pub fn create_agg_buf_from_initial_value(
                 addrs.push(make_dyn_addr(dyns.len()));
                 dyns.push(Box::new(AggDynSet::default()));
             }
+            AccumInitialValue::DynBloomFilter {
+                expected_num_items,
+                num_bits,
+            } => {
+                addrs.push(make_dyn_addr(dyns.len()));
+                dyns.push(Box::new(AggDynBloomFilter::new(
+                    *expected_num_items,
+                    *num_bits,
+                )));
+            }
         }
     }
 
@@ -257,6 +272,7 @@ impl dyn AggDynValue {
         handle_dyn_type!(AggDynStr);
         handle_dyn_type!(AggDynList);
         handle_dyn_type!(AggDynSet);
+        handle_dyn_type!(AggDynBloomFilter);
         unreachable!("unknown dyn value")
     }
 
@@ -273,6 +289,7 @@ impl dyn AggDynValue {
         handle_dyn_type!(AggDynStr);
         handle_dyn_type!(AggDynList);
         handle_dyn_type!(AggDynSet);
+        handle_dyn_type!(AggDynBloomFilter);
         unreachable!("unknown dyn value")
     }
 }
@@ -628,6 +645,91 @@ impl AggDynValue for AggDynSet {
     }
 }
 
+/// bloom filter buffer of spark's `BloomFilterAggregate`, the filter is lazily
+/// created when the first item is put
+#[derive(Clone, Eq, PartialEq)]
+pub struct AggDynBloomFilter {
+    pub expected_num_items: usize,
+    pub num_bits: usize,
+    pub value: Option<SparkBloomFilter>,
+}
+
+impl AggDynBloomFilter {
+    pub fn new(expected_num_items: usize, num_bits: usize) -> Self {
+        Self {
+            expected_num_items,
+            num_bits,
+            value: None,
+        }
+    }
+
+    pub fn put_long(&mut self, item: i64) {
+        let (expected_num_items, num_bits) = (self.expected_num_items, self.num_bits);
+        self.value
+            .get_or_insert_with(|| SparkBloomFilter::new(expected_num_items, num_bits))
+            .put_long(item);
+    }
+
+    pub fn merge(&mut self, other: &mut Self) -> Result<()> {
+        match (&mut self.value, other.value.take()) {
+            (Some(value), Some(other_value)) => value.merge_in_place(&other_value)?,
+            (None, Some(other_value)) => self.value = Some(other_value),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn load(&mut self, mut r: impl Read) -> Result<()> {
+        self.value = match read_u8(&mut r)? {
+            0 => None,
+            _ => Some(SparkBloomFilter::read_from(&mut r)?),
+        };
+        Ok(())
+    }
+
+    pub fn save(&mut self, mut w: impl Write) -> Result<()> {
+        match &self.value {
+            Some(value) => {
+                write_u8(1, &mut w)?;
+                value.write_to(&mut w)?;
+            }
+            None => {
+                write_u8(0, &mut w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AggDynValue for AggDynBloomFilter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn mem_size(&self) -> usize {
+        size_of::<Self>() + self.value.as_ref().map(|v| v.mem_size()).unwrap_or(0)
+    }
+
+    fn eq_boxed(&self, that: &Box<dyn AggDynValue>) -> bool {
+        match that.as_any().downcast_ref() {
+            Some(that) => self.eq(that),
+            None => false,
+        }
+    }
+
+    fn default_boxed(&self) -> Box<dyn AggDynValue> {
+        Box::new(Self::new(self.expected_num_items, self.num_bits))
+    }
+
+    fn clone_boxed(&self) -> Box<dyn AggDynValue> {
+        Box::new(self.clone())
+    }
+}
+
 #[inline]
 fn get_fixed_addr_offset(addr: u64) -> usize {
     (addr & 0x0000_0000_ffff_ffff) as usize
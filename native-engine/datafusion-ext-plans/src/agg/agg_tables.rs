@@ -14,24 +14,23 @@
 
 use ahash::RandomState;
 use std::hash::{BuildHasher, Hash, Hasher};
-use std::io::{BufReader, Read, Write};
+use std::io::{Read, Write};
 use std::mem::{size_of, ManuallyDrop};
 use std::sync::{Arc, Weak};
 
 use arrow::row::{RowConverter, Rows};
 use async_trait::async_trait;
 use datafusion::common::Result;
-use datafusion::error::DataFusionError;
 use datafusion::execution::context::TaskContext;
 
-use datafusion::physical_plan::metrics::BaselineMetrics;
+use datafusion::physical_plan::metrics::{BaselineMetrics, Count};
 use futures::lock::Mutex;
 use hashbrown::hash_map::{Entry, RawEntryMut};
 use hashbrown::HashMap;
-use lz4_flex::frame::FrameDecoder;
 
 use datafusion_ext_commons::io::{read_bytes_slice, read_len, write_len};
 use datafusion_ext_commons::loser_tree::LoserTree;
+use datafusion_ext_commons::spill::SpillManager;
 
 use crate::agg::agg_buf::AggBuf;
 use crate::agg::agg_context::AggContext;
@@ -61,7 +60,9 @@ pub struct AggTables {
     spills: Mutex<Vec<Box<dyn Spill>>>,
     agg_ctx: Arc<AggContext>,
     context: Arc<TaskContext>,
+    spill_manager: Arc<SpillManager>,
     metrics: BaselineMetrics,
+    spill_count: Count,
 }
 
 impl AggTables {
@@ -69,6 +70,7 @@ impl AggTables {
         partition_id: usize,
         agg_ctx: Arc<AggContext>,
         metrics: BaselineMetrics,
+        spill_count: Count,
         context: Arc<TaskContext>,
     ) -> Self {
         Self {
@@ -77,8 +79,10 @@ impl AggTables {
             in_mem: Mutex::new(InMemTable::new(true)), // only the first im-mem table uses hash
             spills: Mutex::default(),
             agg_ctx,
+            spill_manager: SpillManager::get(&context),
             context,
             metrics,
+            spill_count,
         }
     }
 
@@ -152,7 +156,7 @@ impl AggTables {
         let mut cursors = vec![];
         if in_mem.num_records() > 0 {
             // spill staging records
-            if let Some(spill) = in_mem.try_into_spill()? {
+            if let Some(spill) = in_mem.try_into_spill(&self.spill_manager)? {
                 spills.push(spill);
             }
             self.update_mem_used(spills.len() * SPILL_OFFHEAP_MEM_COST)
@@ -259,7 +263,12 @@ impl MemConsumer for AggTables {
         let mut in_mem = self.in_mem.lock().await;
         let mut spills = self.spills.lock().await;
 
-        spills.extend(std::mem::replace(&mut *in_mem, InMemTable::new(false)).try_into_spill()?);
+        if let Some(spill) = std::mem::replace(&mut *in_mem, InMemTable::new(false))
+            .try_into_spill(&self.spill_manager)?
+        {
+            spills.push(spill);
+            self.spill_count.add(1);
+        }
         drop(spills);
         drop(in_mem);
 
@@ -428,7 +437,7 @@ impl InMemTable {
         Ok(())
     }
 
-    fn try_into_spill(self) -> Result<Option<Box<dyn Spill>>> {
+    fn try_into_spill(self, spill_manager: &Arc<SpillManager>) -> Result<Option<Box<dyn Spill>>> {
         if self.map.is_empty() && self.unsorted_values.is_empty() {
             return Ok(None);
         }
@@ -457,8 +466,8 @@ impl InMemTable {
         };
         let counts = rdxsort::radix_sort_u16_by(&mut sorted, |(h, _, _)| *h);
 
-        let spill = try_new_spill()?;
-        let mut writer = lz4_flex::frame::FrameEncoder::new(spill.get_buf_writer());
+        let spill = try_new_spill(spill_manager)?;
+        let mut writer = spill_manager.compressed_writer(spill.get_buf_writer())?;
         let mut beg = 0;
 
         for i in 0..65536 {
@@ -482,9 +491,8 @@ impl InMemTable {
         write_len(65536, &mut writer)?; // EOF
         write_len(0, &mut writer)?;
 
-        writer
-            .finish()
-            .map_err(|err| DataFusionError::Execution(format!("{}", err)))?;
+        writer.flush()?;
+        drop(writer); // finishes the compressed frame
         spill.complete()?;
         Ok(Some(spill))
     }
@@ -492,13 +500,13 @@ impl InMemTable {
 
 struct SpillCursor {
     agg_ctx: Arc<AggContext>,
-    input: FrameDecoder<BufReader<Box<dyn Read + Send>>>,
+    input: Box<dyn Read + Send>,
     pub cur_bucket_idx: usize,
     pub cur_bucket_count: usize,
 }
 impl SpillCursor {
     fn try_from_spill(spill: &Box<dyn Spill>, agg_ctx: &Arc<AggContext>) -> Result<Self> {
-        let input = FrameDecoder::new(spill.get_buf_reader());
+        let input = SpillManager::compressed_reader(spill.get_buf_reader())?;
         let mut cursor = SpillCursor {
             agg_ctx: agg_ctx.clone(),
             input,
@@ -29,26 +29,48 @@ use std::sync::Arc;
 pub struct AggAvg {
     child: Arc<dyn PhysicalExpr>,
     data_type: DataType,
+    sum_type: DataType,
     agg_sum: AggSum,
     agg_count: AggCount,
     accums_initial: Vec<AccumInitialValue>,
-    final_merger: fn(ScalarValue, i64) -> ScalarValue,
 }
 
 impl AggAvg {
-    pub fn try_new(child: Arc<dyn PhysicalExpr>, data_type: DataType) -> Result<Self> {
-        let agg_sum = AggSum::try_new(child.clone(), data_type.clone())?;
+    /// creates an avg agg, values are summed in `sum_type` and then divided into
+    /// `data_type`. for decimals, spark sums in decimal(p + 10, s) and outputs
+    /// decimal(p + 4, s + 4).
+    pub fn try_new(
+        child: Arc<dyn PhysicalExpr>,
+        data_type: DataType,
+        sum_type: DataType,
+    ) -> Result<Self> {
+        match (&data_type, &sum_type) {
+            (DataType::Decimal128(_, scale), DataType::Decimal128(_, sum_scale))
+                if scale < sum_scale =>
+            {
+                return Err(DataFusionError::Plan(format!(
+                    "avg(): result type {data_type} has less scale than sum type {sum_type}"
+                )));
+            }
+            (DataType::Decimal128(..), DataType::Decimal128(..)) => {}
+            (DataType::Decimal128(..), _) | (_, DataType::Decimal128(..)) => {
+                return Err(DataFusionError::Plan(format!(
+                    "avg(): mismatched result type {data_type} and sum type {sum_type}"
+                )));
+            }
+            _ => {}
+        }
+        let agg_sum = AggSum::try_new(child.clone(), sum_type.clone())?;
         let agg_count = AggCount::try_new(child.clone(), DataType::Int64)?;
         let accums_initial = [agg_sum.accums_initial(), agg_count.accums_initial()].concat();
-        let final_merger = get_final_merger(&data_type)?;
 
         Ok(Self {
             child,
             data_type,
+            sum_type,
             agg_sum,
             agg_count,
             accums_initial,
-            final_merger,
         })
     }
 }
@@ -72,6 +94,7 @@ impl Agg for AggAvg {
         Ok(Arc::new(Self::try_new(
             exprs[0].clone(),
             self.data_type.clone(),
+            self.sum_type.clone(),
         )?))
     }
 
@@ -88,10 +111,10 @@ impl Agg for AggAvg {
     }
 
     fn prepare_partial_args(&self, partial_inputs: &[ArrayRef]) -> Result<Vec<ArrayRef>> {
-        // cast arg1 to target data type
+        // cast arg1 to sum data type
         Ok(vec![datafusion_ext_commons::cast::cast(
             &partial_inputs[0],
-            &self.data_type,
+            &self.sum_type,
         )?])
     }
 
@@ -168,13 +191,8 @@ impl Agg for AggAvg {
     }
 
     fn final_merge(&self, agg_buf: &mut AggBuf, agg_buf_addrs: &[u64]) -> Result<ScalarValue> {
-        let sum = self.agg_sum.final_merge(agg_buf, agg_buf_addrs)?;
-        let count = match self.agg_count.final_merge(agg_buf, &agg_buf_addrs[1..])? {
-            ScalarValue::Int64(Some(count)) => count,
-            _ => unreachable!(),
-        };
-        let final_merger = self.final_merger;
-        Ok(final_merger(sum, count))
+        let avgs = self.final_batch_merge(std::slice::from_mut(agg_buf), agg_buf_addrs)?;
+        ScalarValue::try_from_array(&avgs, 0)
     }
 
     fn final_batch_merge(
@@ -192,13 +210,18 @@ impl Agg for AggAvg {
             not_zero.then_some(count)
         });
 
-        if let &DataType::Decimal128(prec, scale) = self.data_type() {
+        if let (&DataType::Decimal128(prec, scale), &DataType::Decimal128(_, sum_scale)) =
+            (&self.data_type, &self.sum_type)
+        {
             let sums = as_decimal128_array(&sums)?;
             let counts = counts_zero_free;
-            let avgs =
-                arrow::compute::binary::<_, _, _, Decimal128Type>(&sums, &counts, |sum, count| {
-                    sum.checked_div_euclid(count as i128).unwrap_or_default()
-                })?;
+            let scale_up = 10_i128.pow((scale - sum_scale) as u32);
+            let bound = 10_i128.pow(prec as u32);
+            let avgs: Decimal128Array = sums
+                .iter()
+                .zip(counts.iter())
+                .map(|(sum, count)| decimal_avg(sum?, count?, scale_up, bound))
+                .collect();
             Ok(Arc::new(avgs.with_precision_and_scale(prec, scale)?))
         } else {
             let counts = counts_zero_free;
@@ -210,55 +233,39 @@ impl Agg for AggAvg {
     }
 }
 
-fn get_final_merger(dt: &DataType) -> Result<fn(ScalarValue, i64) -> ScalarValue> {
-    macro_rules! get_fn {
-        ($ty:ident, f64) => {{
-            Ok(|sum: ScalarValue, count: i64| {
-                let avg = match sum {
-                    ScalarValue::$ty(sum, ..) => ScalarValue::Float64(if !count.is_zero() {
-                        sum.map(|sum| sum as f64 / (count as f64))
-                    } else {
-                        None
-                    }),
-                    _ => unreachable!(),
-                };
-                avg
-            })
-        }};
-        (Decimal128) => {{
-            Ok(|sum: ScalarValue, count: i64| {
-                let avg = match sum {
-                    ScalarValue::Decimal128(sum, prec, scale) => ScalarValue::Decimal128(
-                        if !count.is_zero() {
-                            sum.map(|sum| sum / (count as i128))
-                        } else {
-                            None
-                        },
-                        prec,
-                        scale,
-                    ),
-                    _ => unreachable!(),
-                };
-                avg
-            })
-        }};
+/// divides the decimal sum by count and rescales it with HALF_UP rounding, like
+/// spark's decimal division does. returns None if the result overflows.
+fn decimal_avg(sum: i128, count: i64, scale_up: i128, bound: i128) -> Option<i128> {
+    let count = count as i128;
+    // |rem| < count, so rem * scale_up never overflows
+    let (quot, rem) = (sum / count, sum % count);
+    let rem_scaled = rem * scale_up;
+    let mut avg = quot
+        .checked_mul(scale_up)?
+        .checked_add(rem_scaled / count)?;
+    if (rem_scaled % count).unsigned_abs() * 2 >= count as u128 {
+        avg = avg.checked_add(sum.signum())?;
     }
-    match dt {
-        DataType::Null => Ok(|_, _| ScalarValue::Null),
-        DataType::Float32 => get_fn!(Float32, f64),
-        DataType::Float64 => get_fn!(Float64, f64),
-        DataType::Int8 => get_fn!(Int8, f64),
-        DataType::Int16 => get_fn!(Int16, f64),
-        DataType::Int32 => get_fn!(Int32, f64),
-        DataType::Int64 => get_fn!(Int64, f64),
-        DataType::UInt8 => get_fn!(UInt8, f64),
-        DataType::UInt16 => get_fn!(UInt16, f64),
-        DataType::UInt32 => get_fn!(UInt32, f64),
-        DataType::UInt64 => get_fn!(UInt64, f64),
-        DataType::Decimal128(..) => get_fn!(Decimal128),
-        other => Err(DataFusionError::NotImplemented(format!(
-            "unsupported data type in avg(): {}",
-            other
-        ))),
+    (avg.unsigned_abs() < bound as u128).then_some(avg)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::agg::avg::decimal_avg;
+
+    #[test]
+    fn test_decimal_avg_rounding() {
+        // avg of decimal(p, 2) values with result scale 6
+        assert_eq!(decimal_avg(100, 3, 10000, i128::MAX), Some(333333));
+        assert_eq!(decimal_avg(200, 3, 10000, i128::MAX), Some(666667));
+        assert_eq!(decimal_avg(-200, 3, 10000, i128::MAX), Some(-666667));
+        assert_eq!(decimal_avg(1, 8, 1, i128::MAX), Some(0));
+        assert_eq!(decimal_avg(1, 2, 1, i128::MAX), Some(1));
+        assert_eq!(decimal_avg(-1, 2, 1, i128::MAX), Some(-1));
+
+        // overflows the result precision
+        assert_eq!(decimal_avg(999, 1, 10, 10000), Some(9990));
+        assert_eq!(decimal_avg(1000, 1, 10, 10000), None);
+        assert_eq!(decimal_avg(i128::MAX / 2, 1, 10, i128::MAX), None);
     }
 }
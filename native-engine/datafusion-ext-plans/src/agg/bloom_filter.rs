@@ -0,0 +1,173 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::agg::agg_buf::{AccumInitialValue, AggBuf, AggDynBloomFilter};
+use crate::agg::Agg;
+use arrow::array::*;
+use arrow::datatypes::*;
+use datafusion::common::cast::as_int64_array;
+use datafusion::common::{Result, ScalarValue};
+use datafusion::physical_expr::PhysicalExpr;
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// Native implementation of spark's `BloomFilterAggregate`, puts long values
+/// into a bloom filter and outputs it serialized in spark's format, or null
+/// if no values are put.
+pub struct AggBloomFilter {
+    child: Arc<dyn PhysicalExpr>,
+    data_type: DataType,
+    expected_num_items: usize,
+    num_bits: usize,
+    accums_initial: Vec<AccumInitialValue>,
+}
+
+impl AggBloomFilter {
+    pub fn try_new(
+        child: Arc<dyn PhysicalExpr>,
+        expected_num_items: usize,
+        num_bits: usize,
+    ) -> Result<Self> {
+        Ok(Self {
+            child,
+            data_type: DataType::Binary,
+            expected_num_items,
+            num_bits,
+            accums_initial: vec![AccumInitialValue::DynBloomFilter {
+                expected_num_items,
+                num_bits,
+            }],
+        })
+    }
+}
+
+impl Debug for AggBloomFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BloomFilter({:?}, expected_num_items={}, num_bits={})",
+            self.child, self.expected_num_items, self.num_bits,
+        )
+    }
+}
+
+impl Agg for AggBloomFilter {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn exprs(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.child.clone()]
+    }
+
+    fn with_new_exprs(&self, exprs: Vec<Arc<dyn PhysicalExpr>>) -> Result<Arc<dyn Agg>> {
+        Ok(Arc::new(Self::try_new(
+            exprs[0].clone(),
+            self.expected_num_items,
+            self.num_bits,
+        )?))
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn nullable(&self) -> bool {
+        true
+    }
+
+    fn accums_initial(&self) -> &[AccumInitialValue] {
+        &self.accums_initial
+    }
+
+    fn partial_update(
+        &self,
+        agg_buf: &mut AggBuf,
+        agg_buf_addrs: &[u64],
+        values: &[ArrayRef],
+        row_idx: usize,
+    ) -> Result<()> {
+        let values = as_int64_array(&values[0])?;
+        if values.is_valid(row_idx) {
+            get_bloom_filter(agg_buf, agg_buf_addrs).put_long(values.value(row_idx));
+        }
+        Ok(())
+    }
+
+    fn partial_update_all(
+        &self,
+        agg_buf: &mut AggBuf,
+        agg_buf_addrs: &[u64],
+        values: &[ArrayRef],
+    ) -> Result<()> {
+        let values = as_int64_array(&values[0])?;
+        let bloom_filter = get_bloom_filter(agg_buf, agg_buf_addrs);
+        for value in values.iter().flatten() {
+            bloom_filter.put_long(value);
+        }
+        Ok(())
+    }
+
+    fn partial_merge(
+        &self,
+        agg_buf: &mut AggBuf,
+        merging_agg_buf: &mut AggBuf,
+        agg_buf_addrs: &[u64],
+    ) -> Result<()> {
+        let bloom_filter2 = get_bloom_filter(merging_agg_buf, agg_buf_addrs);
+        get_bloom_filter(agg_buf, agg_buf_addrs).merge(bloom_filter2)
+    }
+
+    fn final_merge(&self, agg_buf: &mut AggBuf, agg_buf_addrs: &[u64]) -> Result<ScalarValue> {
+        Ok(ScalarValue::Binary(serialize_bloom_filter(
+            get_bloom_filter(agg_buf, agg_buf_addrs),
+        )?))
+    }
+
+    fn final_batch_merge(
+        &self,
+        agg_bufs: &mut [AggBuf],
+        agg_buf_addrs: &[u64],
+    ) -> Result<ArrayRef> {
+        let serialized: Vec<Option<Vec<u8>>> = agg_bufs
+            .iter_mut()
+            .map(|agg_buf| serialize_bloom_filter(get_bloom_filter(agg_buf, agg_buf_addrs)))
+            .collect::<Result<_>>()?;
+        Ok(Arc::new(BinaryArray::from_iter(serialized)))
+    }
+}
+
+fn get_bloom_filter<'a>(
+    agg_buf: &'a mut AggBuf,
+    agg_buf_addrs: &[u64],
+) -> &'a mut AggDynBloomFilter {
+    agg_buf
+        .dyn_value_mut(agg_buf_addrs[0])
+        .as_any_mut()
+        .downcast_mut::<AggDynBloomFilter>()
+        .unwrap()
+}
+
+fn serialize_bloom_filter(bloom_filter: &mut AggDynBloomFilter) -> Result<Option<Vec<u8>>> {
+    // like spark, empty bloom filters are output as null
+    match std::mem::take(&mut bloom_filter.value) {
+        Some(value) if value.cardinality() > 0 => {
+            let mut serialized = vec![];
+            value.write_to(&mut serialized)?;
+            Ok(Some(serialized))
+        }
+        _ => Ok(None),
+    }
+}
@@ -16,11 +16,14 @@ pub mod agg_buf;
 pub mod agg_context;
 pub mod agg_tables;
 pub mod avg;
+pub mod bloom_filter;
 pub mod collect_list;
 pub mod collect_set;
 pub mod count;
 pub mod first;
 pub mod first_ignores_null;
+pub mod last;
+pub mod last_ignores_null;
 pub mod maxmin;
 pub mod sum;
 
@@ -29,6 +32,7 @@ use arrow::array::*;
 use arrow::datatypes::*;
 use datafusion::common::{DataFusionError, Result, ScalarValue};
 use datafusion::logical_expr::aggregate_function;
+use datafusion::physical_expr::expressions::Literal;
 use datafusion::physical_expr::PhysicalExpr;
 use datafusion_ext_exprs::cast::TryCastExpr;
 use std::any::Any;
@@ -72,8 +76,11 @@ pub enum AggFunction {
     Min,
     First,
     FirstIgnoresNull,
+    Last,
+    LastIgnoresNull,
     CollectList,
     CollectSet,
+    BloomFilter,
 }
 
 #[derive(Debug, Clone)]
@@ -161,94 +168,7 @@ pub trait Agg: Send + Sync + Debug {
         // default implementation:
         // extract the only one values from agg_buf and convert to ScalarValue
         // this works for sum/min/max/first
-        let addr = agg_buf_addrs[0];
-
-        macro_rules! handle_fixed {
-            ($ty:ident) => {{
-                if agg_buf.is_fixed_valid(addr) {
-                    ScalarValue::$ty(Some(agg_buf.fixed_value(addr)))
-                } else {
-                    ScalarValue::$ty(None)
-                }
-            }};
-        }
-        macro_rules! handle_timestamp {
-            ($ty:ident, $tz:expr) => {{
-                let v = if agg_buf.is_fixed_valid(addr) {
-                    Some(agg_buf.fixed_value(addr))
-                } else {
-                    None
-                };
-                ScalarValue::$ty(v, $tz.clone())
-            }};
-        }
-        Ok(match self.data_type() {
-            DataType::Null => ScalarValue::Null,
-            DataType::Boolean => handle_fixed!(Boolean),
-            DataType::Float32 => handle_fixed!(Float32),
-            DataType::Float64 => handle_fixed!(Float64),
-            DataType::Int8 => handle_fixed!(Int8),
-            DataType::Int16 => handle_fixed!(Int16),
-            DataType::Int32 => handle_fixed!(Int32),
-            DataType::Int64 => handle_fixed!(Int64),
-            DataType::UInt8 => handle_fixed!(UInt8),
-            DataType::UInt16 => handle_fixed!(UInt16),
-            DataType::UInt32 => handle_fixed!(UInt32),
-            DataType::UInt64 => handle_fixed!(UInt64),
-            DataType::Decimal128(prec, scale) => {
-                let v = if agg_buf.is_fixed_valid(addr) {
-                    Some(agg_buf.fixed_value(addr))
-                } else {
-                    None
-                };
-                ScalarValue::Decimal128(v, *prec, *scale)
-            }
-            DataType::Date32 => handle_fixed!(Date32),
-            DataType::Date64 => handle_fixed!(Date64),
-            DataType::Timestamp(TimeUnit::Second, tz) => handle_timestamp!(TimestampSecond, tz),
-            DataType::Timestamp(TimeUnit::Millisecond, tz) => {
-                handle_timestamp!(TimestampMillisecond, tz)
-            }
-            DataType::Timestamp(TimeUnit::Microsecond, tz) => {
-                handle_timestamp!(TimestampMicrosecond, tz)
-            }
-            DataType::Timestamp(TimeUnit::Nanosecond, tz) => {
-                handle_timestamp!(TimestampNanosecond, tz)
-            }
-            DataType::Utf8 => ScalarValue::Utf8(
-                agg_buf
-                    .dyn_value(addr)
-                    .as_any()
-                    .downcast_ref::<AggDynStr>()
-                    .unwrap()
-                    .value
-                    .as_ref()
-                    .map(|s| s.as_ref().to_owned()),
-            ),
-            DataType::Binary => ScalarValue::Binary(
-                agg_buf
-                    .dyn_value(addr)
-                    .as_any()
-                    .downcast_ref::<AggDynBinary>()
-                    .unwrap()
-                    .value
-                    .as_ref()
-                    .map(|s| s.as_ref().to_owned()),
-            ),
-            other => {
-                if let Some(s) = agg_buf
-                    .dyn_value(addr)
-                    .as_any()
-                    .downcast_ref::<AggDynScalar>()
-                {
-                    s.value.clone()
-                } else {
-                    return Err(DataFusionError::NotImplemented(format!(
-                        "unsupported data type: {other}"
-                    )));
-                }
-            }
-        })
+        default_final_merge_with_addr(self.data_type(), agg_buf, agg_buf_addrs[0])
     }
 
     fn final_batch_merge(
@@ -259,111 +179,210 @@ pub trait Agg: Send + Sync + Debug {
         // default implementation:
         // extract the only one values from agg_buf and convert to ScalarValue
         // this works for sum/min/max/first
-        let addr = agg_buf_addrs[0];
+        default_final_batch_merge_with_addr(self.data_type(), agg_bufs, agg_buf_addrs[0])
+    }
+}
 
-        macro_rules! handle_fixed {
-            ($ty:ident) => {{
-                type B = paste::paste! {[< $ty Builder >]};
-                let mut builder = B::with_capacity(agg_bufs.len());
-                for agg_buf in agg_bufs {
-                    if agg_buf.is_fixed_valid(addr) {
-                        builder.append_value(agg_buf.fixed_value(addr));
-                    } else {
-                        builder.append_null();
-                    };
-                }
-                builder.finish()
-            }};
+pub fn default_final_merge_with_addr(
+    data_type: &DataType,
+    agg_buf: &mut AggBuf,
+    addr: u64,
+) -> Result<ScalarValue> {
+    macro_rules! handle_fixed {
+        ($ty:ident) => {{
+            if agg_buf.is_fixed_valid(addr) {
+                ScalarValue::$ty(Some(agg_buf.fixed_value(addr)))
+            } else {
+                ScalarValue::$ty(None)
+            }
+        }};
+    }
+    macro_rules! handle_timestamp {
+        ($ty:ident, $tz:expr) => {{
+            let v = if agg_buf.is_fixed_valid(addr) {
+                Some(agg_buf.fixed_value(addr))
+            } else {
+                None
+            };
+            ScalarValue::$ty(v, $tz.clone())
+        }};
+    }
+    Ok(match data_type {
+        DataType::Null => ScalarValue::Null,
+        DataType::Boolean => handle_fixed!(Boolean),
+        DataType::Float32 => handle_fixed!(Float32),
+        DataType::Float64 => handle_fixed!(Float64),
+        DataType::Int8 => handle_fixed!(Int8),
+        DataType::Int16 => handle_fixed!(Int16),
+        DataType::Int32 => handle_fixed!(Int32),
+        DataType::Int64 => handle_fixed!(Int64),
+        DataType::UInt8 => handle_fixed!(UInt8),
+        DataType::UInt16 => handle_fixed!(UInt16),
+        DataType::UInt32 => handle_fixed!(UInt32),
+        DataType::UInt64 => handle_fixed!(UInt64),
+        DataType::Decimal128(prec, scale) => {
+            let v = if agg_buf.is_fixed_valid(addr) {
+                Some(agg_buf.fixed_value(addr))
+            } else {
+                None
+            };
+            ScalarValue::Decimal128(v, *prec, *scale)
         }
-        macro_rules! mkarray {
-            ($a:expr) => {{
-                let array: Arc<dyn Array + 'static> = Arc::new($a);
-                array
-            }};
+        DataType::Date32 => handle_fixed!(Date32),
+        DataType::Date64 => handle_fixed!(Date64),
+        DataType::Timestamp(TimeUnit::Second, tz) => handle_timestamp!(TimestampSecond, tz),
+        DataType::Timestamp(TimeUnit::Millisecond, tz) => {
+            handle_timestamp!(TimestampMillisecond, tz)
         }
-        Ok(match self.data_type() {
-            DataType::Null => mkarray!(NullArray::new(agg_bufs.len())),
-            DataType::Boolean => mkarray!(handle_fixed!(Boolean)),
-            DataType::Float32 => mkarray!(handle_fixed!(Float32)),
-            DataType::Float64 => mkarray!(handle_fixed!(Float64)),
-            DataType::Int8 => mkarray!(handle_fixed!(Int8)),
-            DataType::Int16 => mkarray!(handle_fixed!(Int16)),
-            DataType::Int32 => mkarray!(handle_fixed!(Int32)),
-            DataType::Int64 => mkarray!(handle_fixed!(Int64)),
-            DataType::UInt8 => mkarray!(handle_fixed!(UInt8)),
-            DataType::UInt16 => mkarray!(handle_fixed!(UInt16)),
-            DataType::UInt32 => mkarray!(handle_fixed!(UInt32)),
-            DataType::UInt64 => mkarray!(handle_fixed!(UInt64)),
-            DataType::Decimal128(prec, scale) => {
-                mkarray!(handle_fixed!(Decimal128).with_precision_and_scale(*prec, *scale)?)
-            }
-            DataType::Date32 => mkarray!(handle_fixed!(Date32)),
-            DataType::Date64 => mkarray!(handle_fixed!(Date64)),
-            DataType::Timestamp(TimeUnit::Second, tz) => {
-                mkarray!(handle_fixed!(TimestampSecond).with_timezone_opt(tz.clone()))
-            }
-            DataType::Timestamp(TimeUnit::Millisecond, tz) => {
-                mkarray!(handle_fixed!(TimestampMillisecond).with_timezone_opt(tz.clone()))
-            }
-            DataType::Timestamp(TimeUnit::Microsecond, tz) => {
-                mkarray!(handle_fixed!(TimestampMicrosecond).with_timezone_opt(tz.clone()))
-            }
-            DataType::Timestamp(TimeUnit::Nanosecond, tz) => {
-                mkarray!(handle_fixed!(TimestampNanosecond).with_timezone_opt(tz.clone()))
-            }
-            DataType::Utf8 => {
-                mkarray!(agg_bufs
-                    .iter_mut()
-                    .map(|agg_buf| {
-                        let value = std::mem::take(
-                            &mut agg_buf
-                                .dyn_value_mut(addr)
-                                .as_any_mut()
-                                .downcast_mut::<AggDynStr>()
-                                .unwrap()
-                                .value,
-                        );
-                        value.map(|v| v.into_string())
-                    })
-                    .collect::<StringArray>())
-            }
-            DataType::Binary => {
-                mkarray!(agg_bufs
-                    .iter_mut()
-                    .map(|agg_buf| {
-                        let value = std::mem::take(
-                            &mut agg_buf
-                                .dyn_value_mut(addr)
-                                .as_any_mut()
-                                .downcast_mut::<AggDynBinary>()
-                                .unwrap()
-                                .value,
-                        );
-                        value.map(|v| v.into_vec())
-                    })
-                    .collect::<BinaryArray>())
+        DataType::Timestamp(TimeUnit::Microsecond, tz) => {
+            handle_timestamp!(TimestampMicrosecond, tz)
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, tz) => {
+            handle_timestamp!(TimestampNanosecond, tz)
+        }
+        DataType::Utf8 => ScalarValue::Utf8(
+            agg_buf
+                .dyn_value(addr)
+                .as_any()
+                .downcast_ref::<AggDynStr>()
+                .unwrap()
+                .value
+                .as_ref()
+                .map(|s| s.as_ref().to_owned()),
+        ),
+        DataType::Binary => ScalarValue::Binary(
+            agg_buf
+                .dyn_value(addr)
+                .as_any()
+                .downcast_ref::<AggDynBinary>()
+                .unwrap()
+                .value
+                .as_ref()
+                .map(|s| s.as_ref().to_owned()),
+        ),
+        other => {
+            if let Some(s) = agg_buf
+                .dyn_value(addr)
+                .as_any()
+                .downcast_ref::<AggDynScalar>()
+            {
+                s.value.clone()
+            } else {
+                return Err(DataFusionError::NotImplemented(format!(
+                    "unsupported data type: {other}"
+                )));
             }
-            _other => {
-                println!("{:?}", _other);
-                let scalars = agg_bufs
-                    .iter_mut()
-                    .map(|agg_buf| {
-                        let value = std::mem::replace(
-                            &mut agg_buf
-                                .dyn_value_mut(addr)
-                                .as_any_mut()
-                                .downcast_mut::<AggDynScalar>()
-                                .unwrap()
-                                .value,
-                            ScalarValue::Null,
-                        );
-                        value
-                    })
-                    .collect::<Vec<_>>();
-                ScalarValue::iter_to_array(scalars)?
+        }
+    })
+}
+
+pub fn default_final_batch_merge_with_addr(
+    data_type: &DataType,
+    agg_bufs: &mut [AggBuf],
+    addr: u64,
+) -> Result<ArrayRef> {
+    macro_rules! handle_fixed {
+        ($ty:ident) => {{
+            type B = paste::paste! {[< $ty Builder >]};
+            let mut builder = B::with_capacity(agg_bufs.len());
+            for agg_buf in agg_bufs {
+                if agg_buf.is_fixed_valid(addr) {
+                    builder.append_value(agg_buf.fixed_value(addr));
+                } else {
+                    builder.append_null();
+                };
             }
-        })
+            builder.finish()
+        }};
     }
+    macro_rules! mkarray {
+        ($a:expr) => {{
+            let array: Arc<dyn Array + 'static> = Arc::new($a);
+            array
+        }};
+    }
+    Ok(match data_type {
+        DataType::Null => mkarray!(NullArray::new(agg_bufs.len())),
+        DataType::Boolean => mkarray!(handle_fixed!(Boolean)),
+        DataType::Float32 => mkarray!(handle_fixed!(Float32)),
+        DataType::Float64 => mkarray!(handle_fixed!(Float64)),
+        DataType::Int8 => mkarray!(handle_fixed!(Int8)),
+        DataType::Int16 => mkarray!(handle_fixed!(Int16)),
+        DataType::Int32 => mkarray!(handle_fixed!(Int32)),
+        DataType::Int64 => mkarray!(handle_fixed!(Int64)),
+        DataType::UInt8 => mkarray!(handle_fixed!(UInt8)),
+        DataType::UInt16 => mkarray!(handle_fixed!(UInt16)),
+        DataType::UInt32 => mkarray!(handle_fixed!(UInt32)),
+        DataType::UInt64 => mkarray!(handle_fixed!(UInt64)),
+        DataType::Decimal128(prec, scale) => {
+            mkarray!(handle_fixed!(Decimal128).with_precision_and_scale(*prec, *scale)?)
+        }
+        DataType::Date32 => mkarray!(handle_fixed!(Date32)),
+        DataType::Date64 => mkarray!(handle_fixed!(Date64)),
+        DataType::Timestamp(TimeUnit::Second, tz) => {
+            mkarray!(handle_fixed!(TimestampSecond).with_timezone_opt(tz.clone()))
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, tz) => {
+            mkarray!(handle_fixed!(TimestampMillisecond).with_timezone_opt(tz.clone()))
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, tz) => {
+            mkarray!(handle_fixed!(TimestampMicrosecond).with_timezone_opt(tz.clone()))
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, tz) => {
+            mkarray!(handle_fixed!(TimestampNanosecond).with_timezone_opt(tz.clone()))
+        }
+        DataType::Utf8 => {
+            mkarray!(agg_bufs
+                .iter_mut()
+                .map(|agg_buf| {
+                    let value = std::mem::take(
+                        &mut agg_buf
+                            .dyn_value_mut(addr)
+                            .as_any_mut()
+                            .downcast_mut::<AggDynStr>()
+                            .unwrap()
+                            .value,
+                    );
+                    value.map(|v| v.into_string())
+                })
+                .collect::<StringArray>())
+        }
+        DataType::Binary => {
+            mkarray!(agg_bufs
+                .iter_mut()
+                .map(|agg_buf| {
+                    let value = std::mem::take(
+                        &mut agg_buf
+                            .dyn_value_mut(addr)
+                            .as_any_mut()
+                            .downcast_mut::<AggDynBinary>()
+                            .unwrap()
+                            .value,
+                    );
+                    value.map(|v| v.into_vec())
+                })
+                .collect::<BinaryArray>())
+        }
+        _other => {
+            println!("{:?}", _other);
+            let scalars = agg_bufs
+                .iter_mut()
+                .map(|agg_buf| {
+                    let value = std::mem::replace(
+                        &mut agg_buf
+                            .dyn_value_mut(addr)
+                            .as_any_mut()
+                            .downcast_mut::<AggDynScalar>()
+                            .unwrap()
+                            .value,
+                        ScalarValue::Null,
+                    );
+                    value
+                })
+                .collect::<Vec<_>>();
+            ScalarValue::iter_to_array(scalars)?
+        }
+    })
 }
 
 pub fn create_agg(
@@ -391,11 +410,21 @@ pub fn create_agg(
             let arg_type = children[0].data_type(input_schema)?;
             let return_type = aggregate_function::AggregateFunction::return_type(
                 &aggregate_function::AggregateFunction::Avg,
-                &[arg_type],
+                &[arg_type.clone()],
             )?;
+            // decimals are summed in the same type as sum(), which is wider than the
+            // result type of avg()
+            let sum_type = match &arg_type {
+                DataType::Decimal128(..) => aggregate_function::AggregateFunction::return_type(
+                    &aggregate_function::AggregateFunction::Sum,
+                    &[arg_type.clone()],
+                )?,
+                _ => return_type.clone(),
+            };
             Arc::new(avg::AggAvg::try_new(
-                Arc::new(TryCastExpr::new(children[0].clone(), return_type.clone())),
+                Arc::new(TryCastExpr::new(children[0].clone(), sum_type.clone())),
                 return_type,
+                sum_type,
             )?)
         }
         AggFunction::Max => {
@@ -417,6 +446,17 @@ pub fn create_agg(
                 dt,
             )?)
         }
+        AggFunction::Last => {
+            let dt = children[0].data_type(input_schema)?;
+            Arc::new(last::AggLast::try_new(children[0].clone(), dt)?)
+        }
+        AggFunction::LastIgnoresNull => {
+            let dt = children[0].data_type(input_schema)?;
+            Arc::new(last_ignores_null::AggLastIgnoresNull::try_new(
+                children[0].clone(),
+                dt,
+            )?)
+        }
         AggFunction::CollectList => {
             let arg_type = children[0].data_type(input_schema)?;
             let return_type = DataType::List(Arc::new(Field::new("item", arg_type.clone(), true)));
@@ -435,5 +475,32 @@ pub fn create_agg(
                 arg_type,
             )?)
         }
+        AggFunction::BloomFilter => {
+            // children: [item, expected_num_items, num_bits], the last two are
+            // long literals already clamped to spark's configured maximums
+            let literal_usize = |expr: &Arc<dyn PhysicalExpr>| -> Result<usize> {
+                match expr
+                    .as_any()
+                    .downcast_ref::<Literal>()
+                    .map(|lit| lit.value())
+                {
+                    Some(&ScalarValue::Int64(Some(v))) if v > 0 => Ok(v as usize),
+                    _ => Err(DataFusionError::Plan(format!(
+                        "bloom_filter_agg: expect positive long literal, got {expr:?}"
+                    ))),
+                }
+            };
+            let arg_type = children[0].data_type(input_schema)?;
+            if arg_type != DataType::Int64 {
+                return Err(DataFusionError::Plan(format!(
+                    "bloom_filter_agg: expect long values, got {arg_type}"
+                )));
+            }
+            Arc::new(bloom_filter::AggBloomFilter::try_new(
+                children[0].clone(),
+                literal_usize(&children[1])?,
+                literal_usize(&children[2])?,
+            )?)
+        }
     })
 }
@@ -13,9 +13,10 @@
 // limitations under the License.
 
 use crate::agg::agg_buf::{AccumInitialValue, AggBuf};
-use crate::agg::Agg;
+use crate::agg::{default_final_batch_merge_with_addr, default_final_merge_with_addr, Agg};
 use arrow::array::*;
 use arrow::datatypes::*;
+use datafusion::common::cast::as_decimal128_array;
 use datafusion::common::{Result, ScalarValue};
 use datafusion::error::DataFusionError;
 
@@ -26,9 +27,15 @@ use std::fmt::{Debug, Formatter};
 use std::ops::Add;
 use std::sync::Arc;
 
+/// an overflowed decimal sum is kept as this value, which is out of the range
+/// of any valid decimal128 value, and is finally output as null like spark
+/// does in non-ansi mode.
+const DECIMAL_SUM_OVERFLOWED: i128 = i128::MIN;
+
 pub struct AggSum {
     child: Arc<dyn PhysicalExpr>,
     data_type: DataType,
+    decimal_bound: Option<i128>,
     accums_initial: Vec<AccumInitialValue>,
     partial_updater: fn(&mut AggBuf, u64, &ArrayRef, usize),
     partial_batch_updater: fn(&mut [AggBuf], u64, &ArrayRef),
@@ -41,9 +48,14 @@ impl AggSum {
         let partial_updater = get_partial_updater(&data_type)?;
         let partial_batch_updater = get_partial_batch_updater(&data_type)?;
         let partial_buf_merger = get_partial_buf_merger(&data_type)?;
+        let decimal_bound = match &data_type {
+            DataType::Decimal128(prec, _) => Some(10_i128.pow(*prec as u32)),
+            _ => None,
+        };
         Ok(Self {
             child,
             data_type,
+            decimal_bound,
             accums_initial,
             partial_updater,
             partial_batch_updater,
@@ -101,8 +113,15 @@ impl Agg for AggSum {
         values: &[ArrayRef],
         row_idx: usize,
     ) -> Result<()> {
-        let partial_updater = self.partial_updater;
         let addr = agg_buf_addrs[0];
+        if let Some(bound) = self.decimal_bound {
+            let value = as_decimal128_array(&values[0])?;
+            if value.is_valid(row_idx) {
+                partial_update_decimal(agg_buf, addr, value.value(row_idx), bound);
+            }
+            return Ok(());
+        }
+        let partial_updater = self.partial_updater;
         partial_updater(agg_buf, addr, &values[0], row_idx);
         Ok(())
     }
@@ -113,8 +132,17 @@ impl Agg for AggSum {
         agg_buf_addrs: &[u64],
         values: &[ArrayRef],
     ) -> Result<usize> {
-        let partial_batch_updater = self.partial_batch_updater;
         let addr = agg_buf_addrs[0];
+        if let Some(bound) = self.decimal_bound {
+            let value = as_decimal128_array(&values[0])?;
+            for (agg_buf, value) in agg_bufs.iter_mut().zip(value.iter()) {
+                if let Some(value) = value {
+                    partial_update_decimal(agg_buf, addr, value, bound);
+                }
+            }
+            return Ok(0);
+        }
+        let partial_batch_updater = self.partial_batch_updater;
         partial_batch_updater(agg_bufs, addr, &values[0]);
         Ok(0)
     }
@@ -126,6 +154,13 @@ impl Agg for AggSum {
         values: &[ArrayRef],
     ) -> Result<()> {
         let addr = agg_buf_addrs[0];
+        if let Some(bound) = self.decimal_bound {
+            let value = as_decimal128_array(&values[0])?;
+            for value in value.iter().flatten() {
+                partial_update_decimal(agg_buf, addr, value, bound);
+            }
+            return Ok(());
+        }
 
         macro_rules! handle {
             ($ty:ident) => {{
@@ -148,7 +183,6 @@ impl Agg for AggSum {
             DataType::UInt16 => handle!(UInt16),
             DataType::UInt32 => handle!(UInt32),
             DataType::UInt64 => handle!(UInt64),
-            DataType::Decimal128(..) => handle!(Decimal128),
             other => {
                 return Err(DataFusionError::NotImplemented(format!(
                     "unsupported data type in sum(): {}",
@@ -165,8 +199,12 @@ impl Agg for AggSum {
         agg_buf2: &mut AggBuf,
         agg_buf_addrs: &[u64],
     ) -> Result<()> {
-        let partial_buf_merger = self.partial_buf_merger;
         let addr = agg_buf_addrs[0];
+        if let Some(bound) = self.decimal_bound {
+            partial_merge_decimal(agg_buf1, agg_buf2, addr, bound);
+            return Ok(());
+        }
+        let partial_buf_merger = self.partial_buf_merger;
         partial_buf_merger(agg_buf1, agg_buf2, addr);
         Ok(())
     }
@@ -177,13 +215,41 @@ impl Agg for AggSum {
         merging_agg_bufs: &mut [AggBuf],
         agg_buf_addrs: &[u64],
     ) -> Result<usize> {
-        let partial_buf_merger = self.partial_buf_merger;
         let addr = agg_buf_addrs[0];
+        if let Some(bound) = self.decimal_bound {
+            for (agg_buf, merging_agg_buf) in agg_bufs.iter_mut().zip(merging_agg_bufs) {
+                partial_merge_decimal(agg_buf, merging_agg_buf, addr, bound);
+            }
+            return Ok(0);
+        }
+        let partial_buf_merger = self.partial_buf_merger;
         for (agg_buf, merging_agg_buf) in agg_bufs.iter_mut().zip(merging_agg_bufs) {
             partial_buf_merger(agg_buf, merging_agg_buf, addr);
         }
         Ok(0)
     }
+
+    fn final_merge(&self, agg_buf: &mut AggBuf, agg_buf_addrs: &[u64]) -> Result<ScalarValue> {
+        let addr = agg_buf_addrs[0];
+        if self.decimal_bound.is_some() {
+            clear_decimal_overflowed(agg_buf, addr);
+        }
+        default_final_merge_with_addr(&self.data_type, agg_buf, addr)
+    }
+
+    fn final_batch_merge(
+        &self,
+        agg_bufs: &mut [AggBuf],
+        agg_buf_addrs: &[u64],
+    ) -> Result<ArrayRef> {
+        let addr = agg_buf_addrs[0];
+        if self.decimal_bound.is_some() {
+            for agg_buf in agg_bufs.iter_mut() {
+                clear_decimal_overflowed(agg_buf, addr);
+            }
+        }
+        default_final_batch_merge_with_addr(&self.data_type, agg_bufs, addr)
+    }
 }
 
 fn partial_update_prim<T: Copy + Add<Output = T>>(agg_buf: &mut AggBuf, addr: u64, v: T) {
@@ -195,6 +261,39 @@ fn partial_update_prim<T: Copy + Add<Output = T>>(agg_buf: &mut AggBuf, addr: u6
     }
 }
 
+/// adds a decimal value to the sum, the sum is marked overflowed once it
+/// exceeds the precision of the result type.
+fn partial_update_decimal(agg_buf: &mut AggBuf, addr: u64, v: i128, bound: i128) {
+    let add = |w: i128| {
+        if w == DECIMAL_SUM_OVERFLOWED || v == DECIMAL_SUM_OVERFLOWED {
+            return DECIMAL_SUM_OVERFLOWED;
+        }
+        match w.checked_add(v) {
+            Some(sum) if sum.unsigned_abs() < bound as u128 => sum,
+            _ => DECIMAL_SUM_OVERFLOWED,
+        }
+    };
+    if agg_buf.is_fixed_valid(addr) {
+        agg_buf.update_fixed_value::<i128>(addr, add);
+    } else {
+        agg_buf.set_fixed_value::<i128>(addr, add(0));
+        agg_buf.set_fixed_valid(addr, true);
+    }
+}
+
+fn partial_merge_decimal(agg_buf1: &mut AggBuf, agg_buf2: &mut AggBuf, addr: u64, bound: i128) {
+    if agg_buf2.is_fixed_valid(addr) {
+        let v = agg_buf2.fixed_value::<i128>(addr);
+        partial_update_decimal(agg_buf1, addr, v, bound);
+    }
+}
+
+fn clear_decimal_overflowed(agg_buf: &mut AggBuf, addr: u64) {
+    if agg_buf.is_fixed_valid(addr) && agg_buf.fixed_value::<i128>(addr) == DECIMAL_SUM_OVERFLOWED {
+        agg_buf.set_fixed_valid(addr, false);
+    }
+}
+
 fn get_partial_updater(dt: &DataType) -> Result<fn(&mut AggBuf, u64, &ArrayRef, usize)> {
     macro_rules! fn_fixed {
         ($ty:ident) => {{
@@ -293,3 +392,64 @@ fn get_partial_buf_merger(dt: &DataType) -> Result<fn(&mut AggBuf, &mut AggBuf,
         ))),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::agg::agg_buf::create_agg_buf_from_initial_value;
+    use crate::agg::sum::AggSum;
+    use crate::agg::Agg;
+    use arrow::array::{ArrayRef, Decimal128Array};
+    use arrow::datatypes::DataType;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_expr::expressions::Column;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_decimal_sum_overflow() -> Result<()> {
+        let decimal = |values: Vec<Option<i128>>| -> Result<ArrayRef> {
+            Ok(Arc::new(
+                Decimal128Array::from(values).with_precision_and_scale(3, 0)?,
+            ))
+        };
+        let agg = AggSum::try_new(Arc::new(Column::new("a", 0)), DataType::Decimal128(3, 0))?;
+        let (mut agg_buf, agg_buf_addrs) = create_agg_buf_from_initial_value(agg.accums_initial())?;
+
+        agg.partial_update_all(
+            &mut agg_buf,
+            &agg_buf_addrs,
+            &[decimal(vec![Some(500), None, Some(400)])?],
+        )?;
+        assert_eq!(
+            agg.final_merge(&mut agg_buf.clone(), &agg_buf_addrs)?,
+            ScalarValue::Decimal128(Some(900), 3, 0),
+        );
+
+        // once overflowed, the sum is null even if it gets back into range
+        agg.partial_update(
+            &mut agg_buf,
+            &agg_buf_addrs,
+            &[decimal(vec![Some(100)])?],
+            0,
+        )?;
+        agg.partial_update(
+            &mut agg_buf,
+            &agg_buf_addrs,
+            &[decimal(vec![Some(-999)])?],
+            0,
+        )?;
+        assert_eq!(
+            agg.final_merge(&mut agg_buf.clone(), &agg_buf_addrs)?,
+            ScalarValue::Decimal128(None, 3, 0),
+        );
+
+        // overflowed state is kept through merging
+        let (mut agg_buf2, _) = create_agg_buf_from_initial_value(agg.accums_initial())?;
+        agg.partial_update(&mut agg_buf2, &agg_buf_addrs, &[decimal(vec![Some(1)])?], 0)?;
+        agg.partial_merge(&mut agg_buf2, &mut agg_buf, &agg_buf_addrs)?;
+        assert_eq!(
+            agg.final_merge(&mut agg_buf2, &agg_buf_addrs)?,
+            ScalarValue::Decimal128(None, 3, 0),
+        );
+        Ok(())
+    }
+}
@@ -20,7 +20,9 @@ use arrow::row::{RowConverter, SortField};
 use datafusion::common::{Result, Statistics};
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_expr::PhysicalSortExpr;
-use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
+};
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
@@ -70,6 +72,18 @@ impl AggExec {
             metrics: ExecutionPlanMetricsSet::new(),
         })
     }
+
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+
+    pub fn agg_ctx(&self) -> &Arc<AggContext> {
+        &self.agg_ctx
+    }
+
+    pub fn metrics_set(&self) -> &ExecutionPlanMetricsSet {
+        &self.metrics
+    }
 }
 
 impl ExecutionPlan for AggExec {
@@ -188,6 +202,7 @@ async fn execute_agg_with_grouping_hash(
         partition_id,
         agg_ctx.clone(),
         BaselineMetrics::new(&metrics, partition_id),
+        MetricBuilder::new(&metrics).spill_count(partition_id),
         context.clone(),
     ));
     MemManager::register_consumer(tables.clone(), true);
@@ -456,17 +471,18 @@ async fn execute_agg_sorted(
 #[cfg(test)]
 mod test {
     use crate::agg::AggExecMode::HashAgg;
-    use crate::agg::AggMode::{Final, Partial};
+    use crate::agg::AggMode::{Final, Partial, PartialMerge};
     use crate::agg::{create_agg, AggExpr, AggFunction, GroupingExpr};
     use crate::agg_exec::AggExec;
     use crate::common::memory_manager::MemManager;
-    use arrow::array::Int32Array;
-    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::array::{Int32Array, ListArray};
+    use arrow::datatypes::{DataType, Field, Int32Type, Schema};
     use arrow::record_batch::RecordBatch;
     use datafusion::assert_batches_sorted_eq;
     use datafusion::common::{Result, ScalarValue};
     use datafusion::physical_expr::expressions as phys_expr;
     use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_expr::PhysicalExpr;
     use datafusion::physical_plan::memory::MemoryExec;
     use datafusion::physical_plan::{common, ExecutionPlan};
     use datafusion::prelude::SessionContext;
@@ -703,4 +719,339 @@ mod test {
         assert_batches_sorted_eq!(expected, &batches);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_agg_distinct() -> Result<()> {
+        MemManager::init(10000);
+
+        // select c, sum(a), count(distinct b) from t group by c
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+            Field::new("c", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5, 6])),
+                Arc::new(Int32Array::from(vec![1, 1, 2, 3, 3, 3])),
+                Arc::new(Int32Array::from(vec![1, 1, 1, 2, 2, 2])),
+            ],
+        )?;
+        let input: Arc<dyn ExecutionPlan> =
+            Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None)?);
+        let null_expr =
+            || -> Arc<dyn PhysicalExpr> { Arc::new(phys_expr::Literal::new(ScalarValue::Null)) };
+        let grouping = |name: &str, idx: usize| GroupingExpr {
+            field_name: name.to_string(),
+            expr: Arc::new(Column::new(name, idx)),
+        };
+        let agg_sum = create_agg(AggFunction::Sum, &[phys_expr::col("a", &schema)?], &schema)?;
+
+        // partial aggregation of non-distinct aggs, grouping by keys and distinct column
+        let agg_exec_partial = Arc::new(AggExec::try_new(
+            HashAgg,
+            vec![grouping("c", 2), grouping("b", 1)],
+            vec![AggExpr {
+                field_name: "sum".to_string(),
+                mode: Partial,
+                agg: agg_sum.clone(),
+            }],
+            0,
+            input,
+        )?);
+        let agg_exec_partial_merge = Arc::new(AggExec::try_new(
+            HashAgg,
+            vec![grouping("c", 0), grouping("b", 1)],
+            vec![AggExpr {
+                field_name: "sum".to_string(),
+                mode: PartialMerge,
+                agg: agg_sum.with_new_exprs(vec![null_expr()])?,
+            }],
+            2,
+            agg_exec_partial,
+        )?);
+
+        // merges non-distinct aggs and partially updates distinct aggs in the same agg
+        let agg_count = create_agg(
+            AggFunction::Count,
+            &[phys_expr::col("b", &agg_exec_partial_merge.schema())?],
+            &agg_exec_partial_merge.schema(),
+        )?;
+        let agg_exec_distinct_partial = Arc::new(AggExec::try_new(
+            HashAgg,
+            vec![grouping("c", 0)],
+            vec![
+                AggExpr {
+                    field_name: "sum".to_string(),
+                    mode: PartialMerge,
+                    agg: agg_sum.with_new_exprs(vec![null_expr()])?,
+                },
+                AggExpr {
+                    field_name: "count_distinct".to_string(),
+                    mode: Partial,
+                    agg: agg_count.clone(),
+                },
+            ],
+            2,
+            agg_exec_partial_merge,
+        )?);
+        let agg_exec_final = AggExec::try_new(
+            HashAgg,
+            vec![grouping("c", 0)],
+            vec![
+                AggExpr {
+                    field_name: "sum".to_string(),
+                    mode: Final,
+                    agg: agg_sum.with_new_exprs(vec![null_expr()])?,
+                },
+                AggExpr {
+                    field_name: "count_distinct".to_string(),
+                    mode: Final,
+                    agg: agg_count.with_new_exprs(vec![null_expr()])?,
+                },
+            ],
+            1,
+            agg_exec_distinct_partial,
+        )?;
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let output_final = agg_exec_final.execute(0, task_ctx)?;
+        let batches = common::collect(output_final).await?;
+        let expected = vec![
+            "+---+-----+----------------+",
+            "| c | sum | count_distinct |",
+            "+---+-----+----------------+",
+            "| 1 | 6   | 2              |",
+            "| 2 | 15  | 1              |",
+            "+---+-----+----------------+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_agg_last_and_nested_collect() -> Result<()> {
+        MemManager::init(10000);
+
+        let item_field = Arc::new(Field::new("item", DataType::Int32, true));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("k", DataType::Int32, false),
+            Field::new("v", DataType::Int32, true),
+            Field::new("l", DataType::List(item_field), false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 1, 1, 2, 2])),
+                Arc::new(Int32Array::from(vec![
+                    Some(1),
+                    Some(2),
+                    None,
+                    None,
+                    Some(4),
+                ])),
+                Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                    Some(vec![Some(1)]),
+                    Some(vec![Some(2), Some(3)]),
+                    Some(vec![Some(4)]),
+                    Some(vec![Some(5)]),
+                    Some(vec![Some(6)]),
+                ])),
+            ],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None)?);
+
+        let aggs_agg_expr = vec![
+            AggExpr {
+                field_name: "last".to_string(),
+                mode: Partial,
+                agg: create_agg(AggFunction::Last, &[phys_expr::col("v", &schema)?], &schema)?,
+            },
+            AggExpr {
+                field_name: "last_ign".to_string(),
+                mode: Partial,
+                agg: create_agg(
+                    AggFunction::LastIgnoresNull,
+                    &[phys_expr::col("v", &schema)?],
+                    &schema,
+                )?,
+            },
+            AggExpr {
+                field_name: "collect_list".to_string(),
+                mode: Partial,
+                agg: create_agg(
+                    AggFunction::CollectList,
+                    &[phys_expr::col("l", &schema)?],
+                    &schema,
+                )?,
+            },
+        ];
+        let agg_exec_partial = AggExec::try_new(
+            HashAgg,
+            vec![GroupingExpr {
+                field_name: "k".to_string(),
+                expr: Arc::new(Column::new("k", 0)),
+            }],
+            aggs_agg_expr.clone(),
+            0,
+            input,
+        )?;
+        let agg_exec_final = AggExec::try_new(
+            HashAgg,
+            vec![GroupingExpr {
+                field_name: "k".to_string(),
+                expr: Arc::new(Column::new("k", 0)),
+            }],
+            aggs_agg_expr
+                .into_iter()
+                .map(|mut agg| {
+                    agg.agg = agg
+                        .agg
+                        .with_new_exprs(vec![Arc::new(phys_expr::Literal::new(
+                            ScalarValue::Null,
+                        ))])?;
+                    agg.mode = Final;
+                    Ok(agg)
+                })
+                .collect::<Result<_>>()?,
+            0,
+            Arc::new(agg_exec_partial),
+        )?;
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let output_final = agg_exec_final.execute(0, task_ctx)?;
+        let batches = common::collect(output_final).await?;
+        let expected = vec![
+            "+---+------+----------+--------------------+",
+            "| k | last | last_ign | collect_list       |",
+            "+---+------+----------+--------------------+",
+            "| 1 |      | 2        | [[1], [2, 3], [4]] |",
+            "| 2 | 4    | 4        | [[5], [6]]         |",
+            "+---+------+----------+--------------------+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod fuzztest {
+    use crate::agg::AggExecMode::HashAgg;
+    use crate::agg::AggMode::{Final, Partial};
+    use crate::agg::{create_agg, AggExpr, AggFunction, GroupingExpr};
+    use crate::agg_exec::AggExec;
+    use crate::common::memory_manager::MemManager;
+    use arrow::array::{ArrayRef, Int64Array};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::cast::as_int64_array;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_expr::expressions as phys_expr;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn fuzztest() -> Result<()> {
+        MemManager::init(10000);
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(10000));
+        let task_ctx = session_ctx.task_ctx();
+        let n = 2000000;
+        let num_keys = 1000000;
+
+        // too many groups to be held in memory, so the agg tables are spilled
+        // and merged on output
+        let mut batches = vec![];
+        let mut expected: HashMap<i64, (i64, i64)> = HashMap::new();
+        let mut num_rows = 0;
+        while num_rows < n {
+            let batch_num_rows = (n - num_rows).min(10000);
+            let keys = Int64Array::from_iter_values(
+                (num_rows..num_rows + batch_num_rows).map(|i| (i as i64 * 7919) % num_keys),
+            );
+            let values = Int64Array::from_iter_values(
+                (num_rows..num_rows + batch_num_rows).map(|i| i as i64 % 100),
+            );
+            for (&k, &v) in keys.values().iter().zip(values.values().iter()) {
+                let entry = expected.entry(k).or_default();
+                entry.0 += v;
+                entry.1 += 1;
+            }
+            batches.push(RecordBatch::try_from_iter_with_nullable(vec![
+                ("k", Arc::new(keys) as ArrayRef, false),
+                ("v", Arc::new(values) as ArrayRef, false),
+            ])?);
+            num_rows += batch_num_rows;
+        }
+        let schema = batches[0].schema();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema.clone(), None)?);
+
+        let aggs_agg_expr = vec![
+            AggExpr {
+                field_name: "sum".to_string(),
+                mode: Partial,
+                agg: create_agg(AggFunction::Sum, &[phys_expr::col("v", &schema)?], &schema)?,
+            },
+            AggExpr {
+                field_name: "count".to_string(),
+                mode: Partial,
+                agg: create_agg(
+                    AggFunction::Count,
+                    &[phys_expr::col("v", &schema)?],
+                    &schema,
+                )?,
+            },
+        ];
+        let agg_exec_partial = AggExec::try_new(
+            HashAgg,
+            vec![GroupingExpr {
+                field_name: "k".to_string(),
+                expr: Arc::new(Column::new("k", 0)),
+            }],
+            aggs_agg_expr.clone(),
+            0,
+            input,
+        )?;
+        let agg_exec_final = AggExec::try_new(
+            HashAgg,
+            vec![GroupingExpr {
+                field_name: "k".to_string(),
+                expr: Arc::new(Column::new("k", 0)),
+            }],
+            aggs_agg_expr
+                .into_iter()
+                .map(|mut agg| {
+                    agg.agg = agg
+                        .agg
+                        .with_new_exprs(vec![Arc::new(phys_expr::Literal::new(
+                            ScalarValue::Null,
+                        ))])?;
+                    agg.mode = Final;
+                    Ok(agg)
+                })
+                .collect::<Result<_>>()?,
+            0,
+            Arc::new(agg_exec_partial),
+        )?;
+
+        let output = common::collect(agg_exec_final.execute(0, task_ctx)?).await?;
+        let mut actual: HashMap<i64, (i64, i64)> = HashMap::new();
+        for batch in &output {
+            let keys = as_int64_array(batch.column(0))?;
+            let sums = as_int64_array(batch.column(1))?;
+            let counts = as_int64_array(batch.column(2))?;
+            for i in 0..batch.num_rows() {
+                let duplicated = actual.insert(keys.value(i), (sums.value(i), counts.value(i)));
+                assert!(duplicated.is_none(), "duplicated group: {}", keys.value(i));
+            }
+        }
+        assert_eq!(actual.len(), num_keys as usize);
+        assert!(actual == expected);
+        Ok(())
+    }
 }
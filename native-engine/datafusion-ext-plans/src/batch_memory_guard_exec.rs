@@ -0,0 +1,315 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
+use crate::common::output::output_with_sender;
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use futures::{Stream, StreamExt};
+use std::any::Any;
+use std::fmt::Formatter;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use tokio::sync::Notify;
+
+/// Caps the bytes of batches read from the child but not yet taken by the
+/// parent (usually a shuffle writer). the child is read ahead in background,
+/// and is not polled any more once the in-flight bytes reach the cap, until the
+/// parent drains them. at least one batch is always allowed in flight.
+///
+/// in-flight bytes are reported to the memory manager as an unspillable
+/// consumer, so spillable consumers like the shuffle repartitioner are forced
+/// to spill earlier instead of running out of memory on skewed partitions.
+#[derive(Debug)]
+pub struct BatchMemoryGuardExec {
+    input: Arc<dyn ExecutionPlan>,
+    max_in_flight_bytes: usize,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl BatchMemoryGuardExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>, max_in_flight_bytes: usize) -> Self {
+        Self {
+            input,
+            max_in_flight_bytes,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl DisplayAs for BatchMemoryGuardExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "BatchMemoryGuardExec(max_in_flight_bytes={})",
+            self.max_in_flight_bytes
+        )
+    }
+}
+
+impl ExecutionPlan for BatchMemoryGuardExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        self.input.output_ordering()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::new(
+                children[0].clone(),
+                self.max_in_flight_bytes,
+            ))),
+            _ => Err(DataFusionError::Internal(
+                "BatchMemoryGuardExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let input = self.input.execute(partition, context.clone())?;
+        guard_batch_memory(
+            input,
+            self.max_in_flight_bytes,
+            format!("BatchMemoryGuard[partition={}]", partition),
+            BaselineMetrics::new(&self.metrics, partition),
+            context,
+        )
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+/// Reads the input stream ahead in background with at most
+/// `max_in_flight_bytes` bytes of batches not yet taken by the output stream.
+pub fn guard_batch_memory(
+    mut input: SendableRecordBatchStream,
+    max_in_flight_bytes: usize,
+    name: String,
+    baseline_metrics: BaselineMetrics,
+    context: Arc<TaskContext>,
+) -> Result<SendableRecordBatchStream> {
+    let schema = input.schema();
+    let in_flight = Arc::new(InFlightBatches {
+        name,
+        mem_consumer_info: None,
+        bytes: AtomicUsize::new(0),
+        drained: Notify::new(),
+    });
+    MemManager::register_consumer(in_flight.clone(), false);
+
+    let producer_in_flight = in_flight.clone();
+    let output = output_with_sender(
+        "BatchMemoryGuard",
+        context,
+        schema.clone(),
+        move |sender| async move {
+            let in_flight = producer_in_flight;
+            loop {
+                // wait until the output stream drains the in-flight batches
+                while in_flight.bytes.load(SeqCst) >= max_in_flight_bytes.max(1) {
+                    in_flight.drained.notified().await;
+                }
+                in_flight
+                    .update_mem_used(in_flight.bytes.load(SeqCst))
+                    .await?;
+
+                let batch = match input.next().await.transpose()? {
+                    Some(batch) => batch,
+                    None => return Ok(()),
+                };
+                in_flight
+                    .bytes
+                    .fetch_add(batch.get_array_memory_size(), SeqCst);
+                in_flight
+                    .update_mem_used(in_flight.bytes.load(SeqCst))
+                    .await?;
+                sender.send(Ok(batch), None).await;
+            }
+        },
+    )?;
+
+    Ok(Box::pin(BatchMemoryGuardStream {
+        schema,
+        output,
+        in_flight,
+        baseline_metrics,
+    }))
+}
+
+struct InFlightBatches {
+    name: String,
+    mem_consumer_info: Option<Weak<MemConsumerInfo>>,
+    bytes: AtomicUsize,
+    drained: Notify,
+}
+
+#[async_trait]
+impl MemConsumer for InFlightBatches {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_consumer_info(&mut self, consumer_info: Weak<MemConsumerInfo>) {
+        self.mem_consumer_info = Some(consumer_info);
+    }
+
+    fn get_consumer_info(&self) -> &Weak<MemConsumerInfo> {
+        self.mem_consumer_info
+            .as_ref()
+            .expect("consumer info not set")
+    }
+}
+
+impl Drop for InFlightBatches {
+    fn drop(&mut self) {
+        MemManager::deregister_consumer(self);
+    }
+}
+
+struct BatchMemoryGuardStream {
+    schema: SchemaRef,
+    output: SendableRecordBatchStream,
+    in_flight: Arc<InFlightBatches>,
+    baseline_metrics: BaselineMetrics,
+}
+
+impl RecordBatchStream for BatchMemoryGuardStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for BatchMemoryGuardStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let polled = match self.output.poll_next_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(None) => None,
+            Poll::Ready(Some(Err(err))) => Some(Err(err)),
+            Poll::Ready(Some(Ok(batch))) => {
+                // the batch is drained, wake up the producer
+                let batch_mem_size = batch.get_array_memory_size();
+                self.in_flight.bytes.fetch_sub(batch_mem_size, SeqCst);
+                self.in_flight.drained.notify_one();
+                Some(Ok(batch))
+            }
+        };
+        self.baseline_metrics.record_poll(Poll::Ready(polled))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::batch_memory_guard_exec::guard_batch_memory;
+    use crate::common::memory_manager::MemManager;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use datafusion::prelude::SessionContext;
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_batch_memory_guard() -> Result<()> {
+        MemManager::init(1000000);
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Int64, false)]));
+        let batches = (0..10)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int64Array::from_iter_values(i * 100..(i + 1) * 100))],
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // counts how many batches are pulled from the child
+        let num_polled = Arc::new(AtomicUsize::new(0));
+        let num_polled_cloned = num_polled.clone();
+        let input = Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            futures::stream::iter(batches.into_iter().map(Ok)).inspect(move |_| {
+                num_polled_cloned.fetch_add(1, SeqCst);
+            }),
+        ));
+
+        // a tiny cap allows only one batch in flight
+        let metrics = ExecutionPlanMetricsSet::new();
+        let session_ctx = SessionContext::new();
+        let mut output = guard_batch_memory(
+            input,
+            1,
+            "BatchMemoryGuard".to_string(),
+            BaselineMetrics::new(&metrics, 0),
+            session_ctx.task_ctx(),
+        )?;
+
+        // a slow writer, the child is polled only as the writer drains
+        let mut num_drained = 0;
+        while let Some(batch) = output.next().await.transpose()? {
+            assert_eq!(batch.num_rows(), 100);
+            num_drained += 1;
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            assert!(num_polled.load(SeqCst) <= num_drained + 1);
+        }
+        assert_eq!(num_drained, 10);
+        assert_eq!(num_polled.load(SeqCst), 10);
+        Ok(())
+    }
+}
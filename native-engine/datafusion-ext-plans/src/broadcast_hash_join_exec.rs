@@ -0,0 +1,725 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::join_hash_map::{evaluate_keys, keys_valids, JoinHashMap};
+use crate::common::output::output_with_sender;
+use arrow::array::{Array, BooleanArray, UInt32Array, UInt32Builder};
+use arrow::compute::{concat_batches, filter_record_batch, take};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::logical_expr::JoinType;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::expressions::Column;
+use datafusion::physical_plan::joins::utils::{build_join_schema, check_join_is_valid, JoinOn};
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet, Time,
+};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+};
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
+use futures::stream::once;
+use futures::{Future, StreamExt, TryStreamExt};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::sync::{Arc, Weak};
+
+/// Broadcast hash join which builds the hash map of the broadcasted (left) side
+/// only once per executor. the built hash map is cached by its id and shared by
+/// all running tasks of the executor, so the broadcasted batches are read and
+/// hashed by the first task only.
+///
+/// supports join types in which the streamed (right) side drives the output:
+/// inner, right outer, right semi and right anti. a right semi join can be
+/// turned into an existence join, and a right anti join can be null-aware.
+#[derive(Debug)]
+pub struct BroadcastHashJoinExec {
+    left: Arc<dyn ExecutionPlan>,
+    right: Arc<dyn ExecutionPlan>,
+    on: JoinOn,
+    join_type: JoinType,
+    cached_build_hash_map_id: String,
+    null_aware_anti_join: bool,
+    existence_column_name: Option<String>,
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl BroadcastHashJoinExec {
+    pub fn try_new(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        on: JoinOn,
+        join_type: JoinType,
+        cached_build_hash_map_id: String,
+    ) -> Result<Self> {
+        if !Self::supports_join_type(join_type) {
+            return Err(DataFusionError::Plan(format!(
+                "BroadcastHashJoin does not support join type: {:?}",
+                join_type
+            )));
+        }
+        let left_schema = left.schema();
+        let right_schema = right.schema();
+        check_join_is_valid(&left_schema, &right_schema, &on)?;
+        let schema = Arc::new(build_join_schema(&left_schema, &right_schema, &join_type).0);
+
+        Ok(Self {
+            left,
+            right,
+            on,
+            join_type,
+            cached_build_hash_map_id,
+            null_aware_anti_join: false,
+            existence_column_name: None,
+            schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+
+    /// Makes the right anti join null-aware, which is how spark evaluates `NOT
+    /// IN` subqueries: no rows are output if any broadcasted key is null, and
+    /// probed rows with null keys are never output unless the broadcasted
+    /// side is empty. only single join key is supported.
+    pub fn with_null_aware_anti_join(self, null_aware_anti_join: bool) -> Result<Self> {
+        if null_aware_anti_join && (self.join_type != JoinType::RightAnti || self.on.len() != 1) {
+            return Err(DataFusionError::Plan(format!(
+                "BroadcastHashJoin null-aware anti join requires RightAnti join type and \
+                 single join key, got: {:?}, {} keys",
+                self.join_type,
+                self.on.len(),
+            )));
+        }
+        Ok(Self {
+            null_aware_anti_join,
+            ..self
+        })
+    }
+
+    /// Turns the right semi join into an existence join, which outputs all
+    /// probed rows with an extra non-null boolean column telling whether each
+    /// row has matched rows.
+    pub fn with_existence_column(self, name: String) -> Result<Self> {
+        if self.join_type != JoinType::RightSemi {
+            return Err(DataFusionError::Plan(format!(
+                "BroadcastHashJoin existence join requires RightSemi join type, got: {:?}",
+                self.join_type,
+            )));
+        }
+        let mut fields = self.schema.fields().to_vec();
+        fields.push(Arc::new(Field::new(&name, DataType::Boolean, false)));
+        Ok(Self {
+            schema: Arc::new(Schema::new(fields)),
+            existence_column_name: Some(name),
+            ..self
+        })
+    }
+
+    pub fn supports_join_type(join_type: JoinType) -> bool {
+        matches!(
+            join_type,
+            JoinType::Inner | JoinType::Right | JoinType::RightSemi | JoinType::RightAnti
+        )
+    }
+}
+
+impl DisplayAs for BroadcastHashJoinExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "BroadcastHashJoin")
+    }
+}
+
+impl ExecutionPlan for BroadcastHashJoinExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.right.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut join = Self::try_new(
+            children[0].clone(),
+            children[1].clone(),
+            self.on.clone(),
+            self.join_type,
+            self.cached_build_hash_map_id.clone(),
+        )?
+        .with_null_aware_anti_join(self.null_aware_anti_join)?;
+        if let Some(existence_column_name) = &self.existence_column_name {
+            join = join.with_existence_column(existence_column_name.clone())?;
+        }
+        Ok(Arc::new(join))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let batch_size = context.session_config().batch_size();
+        let metrics = BaselineMetrics::new(&self.metrics, partition);
+        let build_time = MetricBuilder::new(&self.metrics).subset_time("build_time", partition);
+        let output = Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            once(execute_broadcast_hash_join(
+                self.left.clone(),
+                self.right.clone(),
+                partition,
+                context,
+                self.on.clone(),
+                self.join_type,
+                self.cached_build_hash_map_id.clone(),
+                self.null_aware_anti_join,
+                self.existence_column_name.is_some(),
+                self.schema(),
+                metrics.clone(),
+                build_time,
+            ))
+            .try_flatten(),
+        ));
+        Ok(Box::pin(
+            CoalesceStream::new(output, batch_size, metrics.elapsed_compute().clone())
+                .with_byte_budget(batch_byte_budget()?),
+        ))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+async fn execute_broadcast_hash_join(
+    left: Arc<dyn ExecutionPlan>,
+    right: Arc<dyn ExecutionPlan>,
+    partition: usize,
+    context: Arc<TaskContext>,
+    on: JoinOn,
+    join_type: JoinType,
+    cached_build_hash_map_id: String,
+    null_aware_anti_join: bool,
+    existence_join: bool,
+    output_schema: SchemaRef,
+    metrics: BaselineMetrics,
+    build_time: Time,
+) -> Result<SendableRecordBatchStream> {
+    let batch_size = context.session_config().batch_size().max(1);
+    let (left_keys, right_keys): (Vec<Column>, Vec<Column>) = on.into_iter().unzip();
+
+    // the broadcasted side is always executed so that its resources are taken
+    // and released, even if the hash map is already cached
+    let left_schema = left.schema();
+    let left_stream = left.execute(0, context.clone())?;
+    let hash_map = get_or_build_join_hash_map(&cached_build_hash_map_id, async move {
+        let _timer = build_time.timer();
+        let left_batches: Vec<RecordBatch> = left_stream.try_collect().await?;
+        let left_batch = concat_batches(&left_schema, &left_batches)?;
+        let hash_map = JoinHashMap::try_new(left_batch, &left_keys)?;
+        log::info!(
+            "BroadcastHashJoin built hash map: rows={}, mem_size={}",
+            hash_map.batch().num_rows(),
+            hash_map.mem_size(),
+        );
+        Ok(Arc::new(hash_map))
+    })
+    .await
+    .map_err(|err| err.context("broadcast_hash_join: building hash map error"))?;
+
+    let mut right_stream = right.execute(partition, context.clone())?;
+    output_with_sender(
+        "BroadcastHashJoin",
+        context,
+        output_schema.clone(),
+        move |sender| async move {
+            while let Some(batch) =
+                right_stream.next().await.transpose().map_err(|err| {
+                    err.context("broadcast_hash_join: polling probed batches error")
+                })?
+            {
+                let mut timer = metrics.elapsed_compute().timer();
+                let output_batches = join_probed_batch(
+                    &hash_map,
+                    &batch,
+                    &right_keys,
+                    join_type,
+                    null_aware_anti_join,
+                    existence_join,
+                    &output_schema,
+                    batch_size,
+                )?;
+                for output_batch in output_batches {
+                    metrics.record_output(output_batch.num_rows());
+                    sender.send(Ok(output_batch), Some(&mut timer)).await;
+                }
+            }
+            Ok(())
+        },
+    )
+}
+
+type CachedJoinHashMap = Arc<tokio::sync::Mutex<Weak<JoinHashMap>>>;
+
+fn cached_join_hash_maps() -> &'static Mutex<HashMap<String, CachedJoinHashMap>> {
+    static CACHED_JOIN_HASH_MAPS: OnceCell<Mutex<HashMap<String, CachedJoinHashMap>>> =
+        OnceCell::new();
+    CACHED_JOIN_HASH_MAPS.get_or_init(|| Mutex::default())
+}
+
+/// Gets the cached hash map, or builds and caches it. concurrent tasks with the
+/// same id wait for the first one to build it. a failed building is not cached
+/// and will be retried by the next task. the hash map is not cached if the id
+/// is empty.
+///
+/// only weak references are cached, so a hash map is released as soon as no
+/// running task holds it, that is, at the latest when the stage completes.
+async fn get_or_build_join_hash_map(
+    id: &str,
+    build: impl Future<Output = Result<Arc<JoinHashMap>>>,
+) -> Result<Arc<JoinHashMap>> {
+    if id.is_empty() {
+        return build.await;
+    }
+    let cached = {
+        let mut cached_hash_maps = cached_join_hash_maps().lock();
+
+        // remove released hash maps which are not being built by other tasks
+        cached_hash_maps.retain(|_, cached| {
+            Arc::strong_count(cached) > 1
+                || cached
+                    .try_lock()
+                    .map(|hash_map| hash_map.strong_count() > 0)
+                    .unwrap_or(true)
+        });
+        cached_hash_maps.entry(id.to_owned()).or_default().clone()
+    };
+
+    let mut cached_hash_map = cached.lock().await;
+    if let Some(hash_map) = cached_hash_map.upgrade() {
+        return Ok(hash_map);
+    }
+    let hash_map = build.await?;
+    *cached_hash_map = Arc::downgrade(&hash_map);
+    Ok(hash_map)
+}
+
+/// Joins a probed batch with the hash map, outputs batches with at most
+/// `batch_size` rows.
+fn join_probed_batch(
+    hash_map: &JoinHashMap,
+    probed_batch: &RecordBatch,
+    probed_key_exprs: &[Column],
+    join_type: JoinType,
+    null_aware_anti_join: bool,
+    existence_join: bool,
+    output_schema: &SchemaRef,
+    batch_size: usize,
+) -> Result<Vec<RecordBatch>> {
+    let matched = hash_map.probe(probed_batch, probed_key_exprs)?;
+
+    match join_type {
+        JoinType::Inner | JoinType::Right => {
+            let mut build_indices = UInt32Builder::new();
+            let mut probed_indices = UInt32Builder::new();
+            for (row_idx, matched) in matched.into_iter().enumerate() {
+                match matched {
+                    Some(matched) => {
+                        for &build_idx in matched {
+                            build_indices.append_value(build_idx);
+                            probed_indices.append_value(row_idx as u32);
+                        }
+                    }
+                    None if join_type == JoinType::Right => {
+                        build_indices.append_null();
+                        probed_indices.append_value(row_idx as u32);
+                    }
+                    None => {}
+                }
+            }
+            let build_indices = build_indices.finish();
+            let probed_indices = probed_indices.finish();
+
+            let mut output_batches = vec![];
+            for start in (0..probed_indices.len()).step_by(batch_size) {
+                let len = batch_size.min(probed_indices.len() - start);
+                output_batches.push(take_output_batch(
+                    hash_map.batch(),
+                    probed_batch,
+                    &build_indices.slice(start, len),
+                    &probed_indices.slice(start, len),
+                    output_schema,
+                )?);
+            }
+            Ok(output_batches)
+        }
+        JoinType::RightSemi if existence_join => {
+            let exists =
+                BooleanArray::from_iter(matched.into_iter().map(|matched| Some(matched.is_some())));
+            let mut cols = probed_batch.columns().to_vec();
+            cols.push(Arc::new(exists));
+            Ok(vec![RecordBatch::try_new_with_options(
+                output_schema.clone(),
+                cols,
+                &RecordBatchOptions::new().with_row_count(Some(probed_batch.num_rows())),
+            )?])
+        }
+        JoinType::RightAnti if null_aware_anti_join && hash_map.batch().num_rows() > 0 => {
+            // `x NOT IN (subquery)` is null (not true) if x is null or the subquery
+            // returns a null, unless the subquery returns nothing
+            if hash_map.has_null_keys() {
+                return Ok(vec![]);
+            }
+            let probed_keys = evaluate_keys(probed_batch, probed_key_exprs)?;
+            let probed_key_valids = keys_valids(&probed_keys);
+            let selected = BooleanArray::from_iter(matched.into_iter().enumerate().map(
+                |(row_idx, matched)| {
+                    let valid = probed_key_valids
+                        .as_ref()
+                        .map(|v| v.is_valid(row_idx))
+                        .unwrap_or(true);
+                    Some(valid && matched.is_none())
+                },
+            ));
+            let filtered = filter_record_batch(probed_batch, &selected)?;
+            if filtered.num_rows() == 0 {
+                return Ok(vec![]);
+            }
+            Ok(vec![RecordBatch::try_new(
+                output_schema.clone(),
+                filtered.columns().to_vec(),
+            )?])
+        }
+        JoinType::RightSemi | JoinType::RightAnti => {
+            let semi = join_type == JoinType::RightSemi;
+            let selected = BooleanArray::from_iter(
+                matched
+                    .into_iter()
+                    .map(|matched| Some(matched.is_some() == semi)),
+            );
+            let filtered = filter_record_batch(probed_batch, &selected)?;
+            if filtered.num_rows() == 0 {
+                return Ok(vec![]);
+            }
+            Ok(vec![RecordBatch::try_new(
+                output_schema.clone(),
+                filtered.columns().to_vec(),
+            )?])
+        }
+        other => Err(DataFusionError::Execution(format!(
+            "BroadcastHashJoin does not support join type: {:?}",
+            other
+        ))),
+    }
+}
+
+fn take_output_batch(
+    build_batch: &RecordBatch,
+    probed_batch: &RecordBatch,
+    build_indices: &UInt32Array,
+    probed_indices: &UInt32Array,
+    output_schema: &SchemaRef,
+) -> Result<RecordBatch> {
+    let cols = build_batch
+        .columns()
+        .iter()
+        .map(|col| take(col, build_indices, None))
+        .chain(
+            probed_batch
+                .columns()
+                .iter()
+                .map(|col| take(col, probed_indices, None)),
+        )
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new_with_options(
+        output_schema.clone(),
+        cols,
+        &RecordBatchOptions::new().with_row_count(Some(probed_indices.len())),
+    )?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::broadcast_hash_join_exec::{get_or_build_join_hash_map, BroadcastHashJoinExec};
+    use crate::common::join_hash_map::JoinHashMap;
+    use arrow::array::*;
+    use arrow::compute::concat_batches;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::{DataFusionError, JoinType, Result};
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    fn build_table(
+        a: (&str, &Vec<Option<i32>>),
+        b: (&str, &Vec<Option<i32>>),
+    ) -> Arc<dyn ExecutionPlan> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(a.0, DataType::Int32, true),
+            Field::new(b.0, DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(a.1.clone())), Arc::new(Int32Array::from(b.1.clone()))],
+        )
+        .unwrap();
+        Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+    }
+
+    async fn join_collect(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        join_type: JoinType,
+        cached_build_hash_map_id: &str,
+    ) -> Result<Vec<RecordBatch>> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let on = vec![(
+            Column::new_with_schema("k1", &left.schema())?,
+            Column::new_with_schema("k2", &right.schema())?,
+        )];
+        let join = BroadcastHashJoinExec::try_new(
+            left,
+            right,
+            on,
+            join_type,
+            cached_build_hash_map_id.to_owned(),
+        )?;
+        let stream = join.execute(0, task_ctx)?;
+        common::collect(stream).await
+    }
+
+    fn left_table() -> Arc<dyn ExecutionPlan> {
+        build_table(
+            ("k1", &vec![Some(1), Some(2), Some(2), None]),
+            ("a1", &vec![Some(10), Some(20), Some(21), Some(30)]),
+        )
+    }
+
+    fn right_table() -> Arc<dyn ExecutionPlan> {
+        build_table(
+            ("k2", &vec![Some(2), Some(3), None, Some(1)]),
+            ("b2", &vec![Some(200), Some(300), Some(400), Some(100)]),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_inner_join() -> Result<()> {
+        let batches =
+            join_collect(left_table(), right_table(), JoinType::Inner, "test_inner").await?;
+        let expected = vec![
+            "+----+----+----+-----+",
+            "| k1 | a1 | k2 | b2  |",
+            "+----+----+----+-----+",
+            "| 1  | 10 | 1  | 100 |",
+            "| 2  | 20 | 2  | 200 |",
+            "| 2  | 21 | 2  | 200 |",
+            "+----+----+----+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_right_join() -> Result<()> {
+        let batches =
+            join_collect(left_table(), right_table(), JoinType::Right, "test_right").await?;
+        let expected = vec![
+            "+----+----+----+-----+",
+            "| k1 | a1 | k2 | b2  |",
+            "+----+----+----+-----+",
+            "|    |    |    | 400 |",
+            "|    |    | 3  | 300 |",
+            "| 1  | 10 | 1  | 100 |",
+            "| 2  | 20 | 2  | 200 |",
+            "| 2  | 21 | 2  | 200 |",
+            "+----+----+----+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semi_anti_join() -> Result<()> {
+        let batches = join_collect(
+            left_table(),
+            right_table(),
+            JoinType::RightSemi,
+            "test_semi",
+        )
+        .await?;
+        let expected = vec![
+            "+----+-----+",
+            "| k2 | b2  |",
+            "+----+-----+",
+            "| 1  | 100 |",
+            "| 2  | 200 |",
+            "+----+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+
+        let batches = join_collect(
+            left_table(),
+            right_table(),
+            JoinType::RightAnti,
+            "test_anti",
+        )
+        .await?;
+        let expected = vec![
+            "+----+-----+",
+            "| k2 | b2  |",
+            "+----+-----+",
+            "|    | 400 |",
+            "| 3  | 300 |",
+            "+----+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_existence_join() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let (left, right) = (left_table(), right_table());
+        let on = vec![(
+            Column::new_with_schema("k1", &left.schema())?,
+            Column::new_with_schema("k2", &right.schema())?,
+        )];
+        let join =
+            BroadcastHashJoinExec::try_new(left, right, on, JoinType::RightSemi, String::new())?
+                .with_existence_column("exists".to_owned())?;
+        let batches = common::collect(join.execute(0, task_ctx)?).await?;
+        let expected = vec![
+            "+----+-----+--------+",
+            "| k2 | b2  | exists |",
+            "+----+-----+--------+",
+            "|    | 400 | false  |",
+            "| 1  | 100 | true   |",
+            "| 2  | 200 | true   |",
+            "| 3  | 300 | false  |",
+            "+----+-----+--------+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_null_aware_anti_join() -> Result<()> {
+        async fn null_aware_anti_join_collect(
+            left: Arc<dyn ExecutionPlan>,
+        ) -> Result<Vec<RecordBatch>> {
+            let session_ctx = SessionContext::new();
+            let task_ctx = session_ctx.task_ctx();
+            let right = right_table();
+            let on = vec![(
+                Column::new_with_schema("k1", &left.schema())?,
+                Column::new_with_schema("k2", &right.schema())?,
+            )];
+            let join = BroadcastHashJoinExec::try_new(
+                left,
+                right,
+                on,
+                JoinType::RightAnti,
+                String::new(),
+            )?
+            .with_null_aware_anti_join(true)?;
+            common::collect(join.execute(0, task_ctx)?).await
+        }
+
+        // broadcasted side has null keys
+        let batches = null_aware_anti_join_collect(left_table()).await?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 0);
+
+        // broadcasted side has no null keys
+        let left = build_table(
+            ("k1", &vec![Some(1), Some(2)]),
+            ("a1", &vec![Some(10), Some(20)]),
+        );
+        let batches = null_aware_anti_join_collect(left).await?;
+        let expected =
+            vec!["+----+-----+", "| k2 | b2  |", "+----+-----+", "| 3  | 300 |", "+----+-----+"];
+        assert_batches_sorted_eq!(expected, &batches);
+
+        // broadcasted side is empty
+        let empty_left = build_table(("k1", &vec![]), ("a1", &vec![]));
+        let batches = null_aware_anti_join_collect(empty_left).await?;
+        let expected = vec![
+            "+----+-----+",
+            "| k2 | b2  |",
+            "+----+-----+",
+            "|    | 400 |",
+            "| 1  | 100 |",
+            "| 2  | 200 |",
+            "| 3  | 300 |",
+            "+----+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reuse_cached_hash_map() -> Result<()> {
+        let build = |table: Arc<dyn ExecutionPlan>| async move {
+            let session_ctx = SessionContext::new();
+            let batches = common::collect(table.execute(0, session_ctx.task_ctx())?).await?;
+            let batch = concat_batches(&table.schema(), &batches)?;
+            let keys = vec![Column::new_with_schema("k1", &table.schema())?];
+            Ok::<_, DataFusionError>(Arc::new(JoinHashMap::try_new(batch, &keys)?))
+        };
+        let hash_map1 = get_or_build_join_hash_map("test_reuse", build(left_table())).await?;
+        assert_eq!(hash_map1.batch().num_rows(), 4);
+
+        // the broadcasted side is not read again with the same id
+        let empty_left = build_table(("k1", &vec![]), ("a1", &vec![]));
+        let hash_map2 = get_or_build_join_hash_map("test_reuse", build(empty_left)).await?;
+        assert!(Arc::ptr_eq(&hash_map1, &hash_map2));
+
+        // the hash map is released when no task holds it
+        drop(hash_map1);
+        drop(hash_map2);
+        let empty_left = build_table(("k1", &vec![]), ("a1", &vec![]));
+        let hash_map3 = get_or_build_join_hash_map("test_reuse", build(empty_left)).await?;
+        assert_eq!(hash_map3.batch().num_rows(), 0);
+        Ok(())
+    }
+}
@@ -32,6 +32,7 @@ use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
 };
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
 use futures::stream::once;
 use futures::{StreamExt, TryStreamExt};
 use jni::sys::{jboolean, JNI_TRUE};
@@ -249,6 +250,8 @@ async fn execute_broadcast_join(
             log::info!("BroadcastJoin is using hash join mode: {:?}", &join);
 
             let join_schema = join.schema();
+            let batch_size = context.session_config().batch_size();
+            let elapsed_compute = metrics.elapsed_compute().clone();
             let completed = join
                 .execute(partition, context)?
                 .chain(futures::stream::poll_fn(move |_| {
@@ -270,10 +273,17 @@ async fn execute_broadcast_join(
                     ));
                     Poll::Ready(None)
                 }));
-            Ok(Box::pin(RecordBatchStreamAdapter::new(
-                join_schema,
-                completed,
-            )))
+
+            // hash join outputs one batch for each probed batch, which may be
+            // oversized if the build side has many matched rows
+            Ok(Box::pin(
+                CoalesceStream::new(
+                    Box::pin(RecordBatchStreamAdapter::new(join_schema, completed)),
+                    batch_size,
+                    elapsed_compute,
+                )
+                .with_byte_budget(batch_byte_budget()?),
+            ))
         }
         JoinMode::SortMerge => {
             let sort_exprs: Vec<PhysicalSortExpr> = on
@@ -24,12 +24,15 @@ use datafusion::physical_plan::memory::MemoryExec;
 use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan};
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
 use futures::stream::once;
 use futures::{StreamExt, TryStreamExt};
 use parking_lot::Mutex;
 use std::any::Any;
 use std::fmt::Formatter;
 use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct BroadcastNestedLoopJoinExec {
@@ -224,19 +227,44 @@ async fn execute_join(
     } else {
         NestedLoopJoinExec::try_new(outer_exec, inner_exec, filter, &join_type)?
     };
-    let joined = nlj.execute(partition, context)?;
-
+    let nlj = Arc::new(nlj);
+    let joined_schema = nlj.schema();
     let baseline_metrics = BaselineMetrics::new(&metrics, partition);
-    let output_stream = Box::pin(RecordBatchStreamAdapter::new(
-        joined.schema(),
-        joined.map(move |batch_result| {
-            if let Ok(batch) = &batch_result {
-                baseline_metrics.record_output(batch.num_rows());
-            }
-            batch_result
-        }),
-    ));
-    Ok(output_stream)
+    let elapsed_compute = baseline_metrics.elapsed_compute().clone();
+    let completed = nlj
+        .execute(partition, context)?
+        .chain(futures::stream::poll_fn(move |_| {
+            // update metrics
+            let nlj_metrics = nlj.metrics().unwrap();
+            baseline_metrics.record_output(nlj_metrics.output_rows().unwrap_or(0));
+            baseline_metrics
+                .elapsed_compute()
+                .add_duration(Duration::from_nanos(
+                    [
+                        nlj_metrics
+                            .sum_by_name("build_time")
+                            .map(|v| v.as_usize() as u64),
+                        nlj_metrics
+                            .sum_by_name("join_time")
+                            .map(|v| v.as_usize() as u64),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .sum(),
+                ));
+            Poll::Ready(None)
+        }));
+
+    // nested loop join outputs one batch for each chunk of the outer side,
+    // which may be undersized if the join condition is selective
+    Ok(Box::pin(
+        CoalesceStream::new(
+            Box::pin(RecordBatchStreamAdapter::new(joined_schema, completed)),
+            target_output_num_rows,
+            elapsed_compute,
+        )
+        .with_byte_budget(batch_byte_budget()?),
+    ))
 }
 
 fn left_is_build_side(join_type: JoinType) -> bool {
@@ -245,3 +273,140 @@ fn left_is_build_side(join_type: JoinType) -> bool {
         JoinType::Right | JoinType::RightSemi | JoinType::RightAnti | JoinType::Full
     )
 }
+
+#[cfg(test)]
+mod test {
+    use crate::broadcast_nested_loop_join_exec::BroadcastNestedLoopJoinExec;
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::{JoinType, Result};
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{BinaryExpr, Column};
+    use datafusion::physical_plan::joins::utils::{ColumnIndex, JoinFilter, JoinSide};
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    fn build_table(a: (&str, &Vec<i32>)) -> Arc<dyn ExecutionPlan> {
+        let schema = Arc::new(Schema::new(vec![Field::new(a.0, DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(a.1.clone()))],
+        )
+        .unwrap();
+        Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+    }
+
+    /// join filter: left.a >= right.b
+    fn build_filter() -> JoinFilter {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]);
+        let expression = Arc::new(BinaryExpr::new(
+            Arc::new(Column::new("a", 0)),
+            Operator::GtEq,
+            Arc::new(Column::new("b", 1)),
+        ));
+        let column_indices = vec![
+            ColumnIndex {
+                index: 0,
+                side: JoinSide::Left,
+            },
+            ColumnIndex {
+                index: 0,
+                side: JoinSide::Right,
+            },
+        ];
+        JoinFilter::new(expression, column_indices, schema)
+    }
+
+    async fn join_collect(
+        join_type: JoinType,
+        filter: Option<JoinFilter>,
+    ) -> Result<Vec<RecordBatch>> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let left = build_table(("a", &vec![1, 2, 3]));
+        let right = build_table(("b", &vec![2, 3, 4]));
+        let join = BroadcastNestedLoopJoinExec::try_new(left, right, join_type, filter)?;
+        let stream = join.execute(0, task_ctx)?;
+        common::collect(stream).await
+    }
+
+    #[tokio::test]
+    async fn join_cross() -> Result<()> {
+        let batches = join_collect(JoinType::Inner, None).await?;
+        let expected = vec![
+            "+---+---+",
+            "| a | b |",
+            "+---+---+",
+            "| 1 | 2 |",
+            "| 1 | 3 |",
+            "| 1 | 4 |",
+            "| 2 | 2 |",
+            "| 2 | 3 |",
+            "| 2 | 4 |",
+            "| 3 | 2 |",
+            "| 3 | 3 |",
+            "| 3 | 4 |",
+            "+---+---+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_inner_with_filter() -> Result<()> {
+        let batches = join_collect(JoinType::Inner, Some(build_filter())).await?;
+        let expected = vec![
+            "+---+---+",
+            "| a | b |",
+            "+---+---+",
+            "| 2 | 2 |",
+            "| 3 | 2 |",
+            "| 3 | 3 |",
+            "+---+---+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_left_with_filter() -> Result<()> {
+        let batches = join_collect(JoinType::Left, Some(build_filter())).await?;
+        let expected = vec![
+            "+---+---+",
+            "| a | b |",
+            "+---+---+",
+            "| 1 |   |",
+            "| 2 | 2 |",
+            "| 3 | 2 |",
+            "| 3 | 3 |",
+            "+---+---+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_full_with_filter() -> Result<()> {
+        let batches = join_collect(JoinType::Full, Some(build_filter())).await?;
+        let expected = vec![
+            "+---+---+",
+            "| a | b |",
+            "+---+---+",
+            "|   | 4 |",
+            "| 1 |   |",
+            "| 2 | 2 |",
+            "| 3 | 2 |",
+            "| 3 | 3 |",
+            "+---+---+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+}
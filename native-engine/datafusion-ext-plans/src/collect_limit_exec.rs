@@ -0,0 +1,306 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use datafusion::common::{DataFusionError, Result};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream, Statistics,
+};
+use futures::{Stream, StreamExt};
+use std::any::Any;
+use std::fmt::Formatter;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Collects at most `limit` rows from all input partitions into a single partition,
+/// like spark's `CollectLimit`. input partitions are read one by one and reading
+/// stops as soon as enough rows are collected.
+#[derive(Debug)]
+pub struct CollectLimitExec {
+    input: Arc<dyn ExecutionPlan>,
+    limit: u64,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl CollectLimitExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>, limit: u64) -> Self {
+        Self {
+            input,
+            limit,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl DisplayAs for CollectLimitExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "CollectLimitExec(limit={})", self.limit)
+    }
+}
+
+impl ExecutionPlan for CollectLimitExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::new(children[0].clone(), self.limit))),
+            _ => Err(DataFusionError::Internal(
+                "CollectLimitExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        if partition != 0 {
+            return Err(DataFusionError::Internal(format!(
+                "CollectLimitExec invalid partition {}",
+                partition
+            )));
+        }
+        Ok(Box::pin(CollectLimitStream {
+            input: self.input.clone(),
+            context,
+            input_stream: None,
+            next_input_partition: 0,
+            limit: self.limit,
+            cur: 0,
+            baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+        }))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+struct CollectLimitStream {
+    input: Arc<dyn ExecutionPlan>,
+    context: Arc<TaskContext>,
+    input_stream: Option<SendableRecordBatchStream>,
+    next_input_partition: usize,
+    limit: u64,
+    cur: u64,
+    baseline_metrics: BaselineMetrics,
+}
+
+impl RecordBatchStream for CollectLimitStream {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
+
+impl Stream for CollectLimitStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let rest = self.limit.saturating_sub(self.cur);
+            if rest == 0 {
+                // drop input stream to cancel all upstream reading
+                self.input_stream = None;
+                return Poll::Ready(None);
+            }
+
+            // start reading next input partition
+            if self.input_stream.is_none() {
+                if self.next_input_partition >= self.input.output_partitioning().partition_count() {
+                    return Poll::Ready(None);
+                }
+                let input_partition = self.next_input_partition;
+                self.next_input_partition += 1;
+                match self.input.execute(input_partition, self.context.clone()) {
+                    Ok(input_stream) => self.input_stream = Some(input_stream),
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            }
+
+            let input_stream = self.input_stream.as_mut().unwrap();
+            match input_stream.poll_next_unpin(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => self.input_stream = None,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(Some(Ok(batch))) => {
+                    let batch = if batch.num_rows() <= rest as usize {
+                        self.cur += batch.num_rows() as u64;
+                        batch
+                    } else {
+                        self.cur += rest;
+                        batch.slice(0, rest as usize)
+                    };
+                    return self
+                        .baseline_metrics
+                        .record_poll(Poll::Ready(Some(Ok(batch))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::collect_limit_exec::CollectLimitExec;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::{DataFusionError, Result};
+    use datafusion::execution::context::TaskContext;
+    use datafusion::physical_expr::PhysicalSortExpr;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use datafusion::physical_plan::{
+        common, DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning,
+        SendableRecordBatchStream, Statistics,
+    };
+    use datafusion::prelude::SessionContext;
+    use futures::StreamExt;
+    use std::any::Any;
+    use std::fmt::Formatter;
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::sync::Arc;
+
+    /// counts batches polled from the wrapped input
+    #[derive(Debug)]
+    struct PollCountingExec {
+        input: Arc<dyn ExecutionPlan>,
+        num_polled: Arc<AtomicUsize>,
+    }
+
+    impl DisplayAs for PollCountingExec {
+        fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+            write!(f, "PollCountingExec")
+        }
+    }
+
+    impl ExecutionPlan for PollCountingExec {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn schema(&self) -> SchemaRef {
+            self.input.schema()
+        }
+
+        fn output_partitioning(&self) -> Partitioning {
+            self.input.output_partitioning()
+        }
+
+        fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+            None
+        }
+
+        fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+            vec![self.input.clone()]
+        }
+
+        fn with_new_children(
+            self: Arc<Self>,
+            _children: Vec<Arc<dyn ExecutionPlan>>,
+        ) -> Result<Arc<dyn ExecutionPlan>> {
+            Err(DataFusionError::Internal(
+                "PollCountingExec does not support with_new_children()".to_owned(),
+            ))
+        }
+
+        fn execute(
+            &self,
+            partition: usize,
+            context: Arc<TaskContext>,
+        ) -> Result<SendableRecordBatchStream> {
+            let num_polled = self.num_polled.clone();
+            let input = self.input.execute(partition, context)?;
+            Ok(Box::pin(RecordBatchStreamAdapter::new(
+                self.schema(),
+                input.inspect(move |_| {
+                    num_polled.fetch_add(1, SeqCst);
+                }),
+            )))
+        }
+
+        fn statistics(&self) -> Statistics {
+            todo!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_limit_exec() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = |values: Vec<i32>| {
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))])
+        };
+
+        // 2 partitions, each with 3 batches of 2 rows
+        let partitions = vec![
+            vec![batch(vec![1, 2])?, batch(vec![3, 4])?, batch(vec![5, 6])?],
+            vec![batch(vec![7, 8])?, batch(vec![9, 10])?, batch(vec![11, 12])?],
+        ];
+        let num_polled = Arc::new(AtomicUsize::new(0));
+        let input = Arc::new(PollCountingExec {
+            input: Arc::new(MemoryExec::try_new(&partitions, schema.clone(), None)?),
+            num_polled: num_polled.clone(),
+        });
+
+        let collect_limit = CollectLimitExec::new(input, 3);
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let batches = common::collect(collect_limit.execute(0, task_ctx.clone())?).await?;
+        let expected = vec!["+---+", "| a |", "+---+", "| 1 |", "| 2 |", "| 3 |", "+---+"];
+        assert_batches_eq!(expected, &batches);
+
+        // stopped polling after 3 rows were collected
+        assert_eq!(num_polled.load(SeqCst), 2);
+
+        // reading continues with the next partition
+        let collect_limit = CollectLimitExec::new(collect_limit.input.clone(), 8);
+        let batches = common::collect(collect_limit.execute(0, task_ctx)?).await?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 8);
+        assert_eq!(num_polled.load(SeqCst), 2 + 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Diagnostic accounting of batches flowing through operators of a task,
+//! useful for finding where a pipeline stalls or drops data.
+//!
+//! accounting is disabled unless explicitly registered for a task context,
+//! operators record their output batches in `output_with_sender()`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Weak};
+
+use datafusion::execution::context::TaskContext;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+
+fn registered_accountings() -> &'static Mutex<Vec<(Weak<TaskContext>, Arc<BatchAccounting>)>> {
+    static REGISTERED: OnceCell<Mutex<Vec<(Weak<TaskContext>, Arc<BatchAccounting>)>>> =
+        OnceCell::new();
+    REGISTERED.get_or_init(|| Mutex::default())
+}
+
+/// An operator instance executed in a task. `operator_id` is allocated in the
+/// order that operators start outputting, so that operators with the same name
+/// are accounted separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperatorKey {
+    pub partition: usize,
+    pub operator_id: usize,
+    pub name: &'static str,
+}
+
+#[derive(Debug, Default)]
+pub struct BatchAccounting {
+    partition: usize,
+    next_operator_id: AtomicUsize,
+    next_seq: AtomicU64,
+    batch_counts: Mutex<HashMap<OperatorKey, usize>>,
+}
+
+impl BatchAccounting {
+    /// Enables batch accounting for all operators executed with the task context
+    pub fn register(task_context: &Arc<TaskContext>, partition: usize) -> Arc<Self> {
+        let accounting = Arc::new(Self {
+            partition,
+            ..Self::default()
+        });
+        let mut registered = registered_accountings().lock();
+        registered
+            .retain(|(registered_task_context, _)| registered_task_context.strong_count() > 0);
+        registered.push((Arc::downgrade(task_context), accounting.clone()));
+        accounting
+    }
+
+    /// Disables batch accounting of the task context, returns the collected
+    /// accounting if registered
+    pub fn deregister(task_context: &Arc<TaskContext>) -> Option<Arc<Self>> {
+        let mut registered = registered_accountings().lock();
+        let idx = registered.iter().position(|(registered_task_context, _)| {
+            registered_task_context.strong_count() > 0
+                && std::ptr::eq(registered_task_context.as_ptr(), Arc::as_ptr(task_context))
+        })?;
+        Some(registered.swap_remove(idx).1)
+    }
+
+    /// Returns the accounting registered for the task context. a dropped task
+    /// context may share the address of a new one, so dead entries are skipped
+    pub fn get(task_context: &Arc<TaskContext>) -> Option<Arc<Self>> {
+        let registered = registered_accountings().lock();
+        registered
+            .iter()
+            .find(|(registered_task_context, _)| {
+                registered_task_context.strong_count() > 0
+                    && std::ptr::eq(registered_task_context.as_ptr(), Arc::as_ptr(task_context))
+            })
+            .map(|(_, accounting)| accounting.clone())
+    }
+
+    /// Allocates the key of a new operator instance
+    pub fn new_operator_key(&self, operator_name: &'static str) -> OperatorKey {
+        OperatorKey {
+            partition: self.partition,
+            operator_id: self.next_operator_id.fetch_add(1, SeqCst),
+            name: operator_name,
+        }
+    }
+
+    /// Records an output batch of the operator, returns its sequence id
+    pub fn record_batch(&self, operator: OperatorKey, num_rows: usize) -> u64 {
+        let seq = self.next_seq.fetch_add(1, SeqCst);
+        *self.batch_counts.lock().entry(operator).or_default() += 1;
+        log::trace!("batch accounting: {operator:?} outputs batch #{seq} ({num_rows} rows)");
+        seq
+    }
+
+    /// Number of batches flowed through each operator instance
+    pub fn batch_counts(&self) -> HashMap<OperatorKey, usize> {
+        self.batch_counts.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::batch_accounting::BatchAccounting;
+    use crate::common::memory_manager::MemManager;
+    use crate::filter_exec::FilterExec;
+    use crate::sort_exec::SortExec;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{binary, col, lit};
+    use datafusion::physical_expr::PhysicalSortExpr;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_batch_accounting() -> Result<()> {
+        MemManager::init(10000);
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batches = (0..3)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from_iter_values(i * 3..(i + 1) * 3))],
+                )
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema.clone(), None)?);
+
+        // sort -> filter -> filter
+        let sort = Arc::new(SortExec::new(
+            input,
+            vec![PhysicalSortExpr {
+                expr: col("a", &schema)?,
+                options: Default::default(),
+            }],
+            None,
+        ));
+        let predicate = binary(
+            col("a", &schema)?,
+            Operator::Lt,
+            lit(ScalarValue::from(5)),
+            &schema,
+        )?;
+        let filter = Arc::new(FilterExec::try_new(vec![predicate.clone()], sort)?);
+        let filter = FilterExec::try_new(vec![predicate], filter)?;
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let accounting = BatchAccounting::register(&task_ctx, 0);
+        let output = common::collect(filter.execute(0, task_ctx.clone())?).await?;
+        assert_eq!(output.iter().map(|b| b.num_rows()).sum::<usize>(), 5);
+
+        // every batch from sort flows through both filters, which are accounted
+        // as different operators
+        let batch_counts = accounting.batch_counts();
+        let counts_of = |name: &str| {
+            batch_counts
+                .iter()
+                .filter(|(operator, _)| operator.name == name)
+                .map(|(_, &count)| count)
+                .collect::<Vec<_>>()
+        };
+        assert!(batch_counts.keys().all(|operator| operator.partition == 0));
+        let sort_counts = counts_of("Sort");
+        assert_eq!(sort_counts.len(), 1);
+        assert!(sort_counts[0] > 0);
+        assert_eq!(counts_of("Filter"), vec![sort_counts[0]; 2]);
+        assert!(Arc::ptr_eq(
+            &BatchAccounting::deregister(&task_ctx).unwrap(),
+            &accounting,
+        ));
+
+        // disabled by default
+        let task_ctx = session_ctx.task_ctx();
+        let _ = common::collect(filter.execute(0, task_ctx.clone())?).await?;
+        assert!(BatchAccounting::get(&task_ctx).is_none());
+        Ok(())
+    }
+}
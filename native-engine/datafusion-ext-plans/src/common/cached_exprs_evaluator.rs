@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use arrow::array::{Array, ArrayRef, BooleanArray};
-use arrow::compute::{filter, filter_record_batch, prep_null_mask_filter};
+use arrow::compute::{filter, prep_null_mask_filter};
 use arrow::datatypes::{DataType, Schema, SchemaRef};
 use arrow::record_batch::{RecordBatch, RecordBatchOptions};
 use datafusion::common::cast::as_boolean_array;
@@ -24,7 +24,10 @@ use datafusion::physical_expr::expressions::{
 };
 use datafusion::physical_expr::{scatter, PhysicalExpr, PhysicalExprRef};
 use datafusion::physical_plan::ColumnarValue;
+use datafusion_ext_commons::selection::SelectedBatch;
 use datafusion_ext_commons::uda::UserDefinedArray;
+use datafusion_ext_exprs::case_when::CaseWhenExpr;
+use datafusion_ext_exprs::higher_order_function::HigherOrderFunctionExpr;
 use itertools::Itertools;
 use parking_lot::Mutex;
 use std::any::Any;
@@ -35,6 +38,9 @@ use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 use std::sync::Arc;
 
+/// Evaluates filter and projection exprs with common subexpression elimination:
+/// identical subtrees appearing more than once across all exprs are evaluated
+/// once per batch, and the cached values are reused by later occurrences.
 pub struct CachedExprsEvaluator {
     transformed_projection_exprs: Vec<PhysicalExprRef>,
     transformed_pruned_filter_exprs: Vec<(PhysicalExprRef, Vec<usize>)>,
@@ -65,6 +71,11 @@ impl CachedExprsEvaluator {
     }
 
     pub fn filter(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        self.filter_selected(batch)?.materialize()
+    }
+
+    /// Filters the batch without materializing the selected rows
+    pub fn filter_selected(&self, batch: &RecordBatch) -> Result<SelectedBatch> {
         self.cache.with(|_| self.filter_impl(batch))
     }
 
@@ -77,7 +88,7 @@ impl CachedExprsEvaluator {
             .with(|_| self.filter_project_impl(batch, output_schema.clone()))
     }
 
-    fn filter_impl(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+    fn filter_impl(&self, batch: &RecordBatch) -> Result<SelectedBatch> {
         // filter
         let mut current_filtered = FilterStat::AllRetained;
         for (filter_expr, proj) in &self.transformed_pruned_filter_exprs {
@@ -91,7 +102,7 @@ impl CachedExprsEvaluator {
             // execute current filtering
             current_filtered = filter_one_pred(batch, filter_expr, proj, current_filtered)?;
             if let FilterStat::AllFiltered = &current_filtered {
-                return Ok(RecordBatch::new_empty(batch.schema()));
+                return Ok(SelectedBatch::new(batch.slice(0, 0)));
             }
             if let FilterStat::Some(selected) = &current_filtered {
                 self.cache.update_all(|value| {
@@ -117,12 +128,11 @@ impl CachedExprsEvaluator {
                 })?;
             }
         }
-        let batch = match current_filtered {
-            FilterStat::AllFiltered => RecordBatch::new_empty(batch.schema()),
-            FilterStat::AllRetained => batch.clone(),
-            FilterStat::Some(selected) => filter_record_batch(batch, &selected)?,
-        };
-        Ok(batch)
+        Ok(match current_filtered {
+            FilterStat::AllFiltered => SelectedBatch::new(batch.slice(0, 0)),
+            FilterStat::AllRetained => SelectedBatch::new(batch.clone()),
+            FilterStat::Some(selected) => SelectedBatch::from_predicate(batch.clone(), &selected),
+        })
     }
 
     fn filter_project_impl(
@@ -131,7 +141,7 @@ impl CachedExprsEvaluator {
         output_schema: SchemaRef,
     ) -> Result<RecordBatch> {
         // execute filters, cache are retained for later projection
-        let filtered_batch = self.filter_impl(batch)?;
+        let filtered_batch = self.filter_impl(batch)?.materialize()?;
         if filtered_batch.num_rows() == 0 {
             return Ok(RecordBatch::new_empty(output_schema));
         }
@@ -192,10 +202,7 @@ fn transform_to_cached_exprs(exprs: &[PhysicalExprRef]) -> Result<(Vec<PhysicalE
         }
 
         // traverse children, excluding exprs with short circuiting evaluation
-        if expr.as_any().downcast_ref::<CaseExpr>().is_some()
-            || expr.as_any().downcast_ref::<SCAndExpr>().is_some()
-            || expr.as_any().downcast_ref::<SCOrExpr>().is_some()
-        {
+        if is_short_circuiting(&expr) {
             // short circuiting expression - only first child can be cached
             collect_dups(&expr.children()[0], current_count, expr_counts, dups);
         } else {
@@ -235,10 +242,7 @@ fn transform_to_cached_exprs(exprs: &[PhysicalExprRef]) -> Result<(Vec<PhysicalE
         let current_cache_id = cached_expr_ids.get(&expr_key).cloned();
 
         // transform children
-        let transformed_expr = if expr.as_any().downcast_ref::<CaseExpr>().is_some()
-            || expr.as_any().downcast_ref::<SCAndExpr>().is_some()
-            || expr.as_any().downcast_ref::<SCOrExpr>().is_some()
-        {
+        let transformed_expr = if is_short_circuiting(&expr) {
             // short circuiting expression - only first child can be cached
             let mut children = expr.children().clone();
             children[0] = transform(children[0].clone(), cached_expr_ids, cache)?;
@@ -271,6 +275,17 @@ fn transform_to_cached_exprs(exprs: &[PhysicalExprRef]) -> Result<(Vec<PhysicalE
     Ok((transformed_exprs, cache))
 }
 
+/// Exprs whose children other than the first may be evaluated on partial (or
+/// derived) batches, so these children cannot be cached
+fn is_short_circuiting(expr: &PhysicalExprRef) -> bool {
+    let any = expr.as_any();
+    any.downcast_ref::<CaseExpr>().is_some()
+        || any.downcast_ref::<CaseWhenExpr>().is_some()
+        || any.downcast_ref::<HigherOrderFunctionExpr>().is_some()
+        || any.downcast_ref::<SCAndExpr>().is_some()
+        || any.downcast_ref::<SCOrExpr>().is_some()
+}
+
 /// A physical expr wrapper to use in HashSet/HashMap
 #[derive(Clone, Debug, Hash)]
 struct ExprKey(PhysicalExprRef);
@@ -467,6 +482,15 @@ fn filter_one_pred(
             if new_selected.null_count() > 0 {
                 new_selected = prep_null_mask_filter(&new_selected);
             }
+
+            // avoid filtering batch and cached arrays if nothing is filtered
+            let num_selected = new_selected.true_count();
+            if num_selected == 0 {
+                return Ok(FilterStat::AllFiltered);
+            }
+            if num_selected == new_selected.len() {
+                return Ok(FilterStat::AllRetained);
+            }
             Ok(FilterStat::Some(new_selected))
         }
     }
@@ -0,0 +1,138 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::array::{Array, ArrayRef};
+use arrow::buffer::NullBuffer;
+use arrow::record_batch::RecordBatch;
+use arrow::row::{RowConverter, SortField};
+use datafusion::common::Result;
+use datafusion::physical_plan::expressions::Column;
+use datafusion::physical_plan::PhysicalExpr;
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+
+/// Hash map of the build side of a hash join, mapping encoded join keys to row
+/// indices of the build side batch. rows with null keys never match and are not
+/// inserted.
+pub struct JoinHashMap {
+    batch: RecordBatch,
+    key_converter: Mutex<RowConverter>,
+    map: HashMap<Box<[u8]>, Vec<u32>>,
+    has_null_keys: bool,
+    mem_size: usize,
+}
+
+impl JoinHashMap {
+    pub fn try_new(batch: RecordBatch, key_exprs: &[Column]) -> Result<Self> {
+        let keys = evaluate_keys(&batch, key_exprs)?;
+        let mut key_converter = RowConverter::new(
+            keys.iter()
+                .map(|key| SortField::new(key.data_type().clone()))
+                .collect(),
+        )?;
+        let key_rows = key_converter.convert_columns(&keys)?;
+        let key_valids = keys_valids(&keys);
+        let has_null_keys = key_valids
+            .as_ref()
+            .map(|v| v.null_count() > 0)
+            .unwrap_or(false);
+
+        let mut map: HashMap<Box<[u8]>, Vec<u32>> = HashMap::new();
+        let mut keys_mem_size = 0;
+        for (row_idx, key_row) in key_rows.iter().enumerate() {
+            if !key_valids
+                .as_ref()
+                .map(|v| v.is_valid(row_idx))
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            match map.get_mut(key_row.as_ref()) {
+                Some(row_indices) => row_indices.push(row_idx as u32),
+                None => {
+                    keys_mem_size += key_row.as_ref().len();
+                    map.insert(key_row.as_ref().into(), vec![row_idx as u32]);
+                }
+            }
+        }
+        let mem_size = batch.get_array_memory_size()
+            + key_converter.size()
+            + keys_mem_size
+            + batch.num_rows() * std::mem::size_of::<u32>()
+            + map.capacity() * std::mem::size_of::<(Box<[u8]>, Vec<u32>)>();
+
+        Ok(Self {
+            batch,
+            key_converter: Mutex::new(key_converter),
+            map,
+            has_null_keys,
+            mem_size,
+        })
+    }
+
+    pub fn batch(&self) -> &RecordBatch {
+        &self.batch
+    }
+
+    /// Returns true if any row of the build side has null keys.
+    pub fn has_null_keys(&self) -> bool {
+        self.has_null_keys
+    }
+
+    pub fn mem_size(&self) -> usize {
+        self.mem_size
+    }
+
+    /// Looks up the build side row indices matching each probed row, `None` if
+    /// the probed key is null or not found.
+    pub fn probe(
+        &self,
+        probed_batch: &RecordBatch,
+        probed_key_exprs: &[Column],
+    ) -> Result<Vec<Option<&[u32]>>> {
+        let keys = evaluate_keys(probed_batch, probed_key_exprs)?;
+        let key_rows = self.key_converter.lock().convert_columns(&keys)?;
+        let key_valids = keys_valids(&keys);
+
+        Ok((0..probed_batch.num_rows())
+            .map(|row_idx| {
+                if !key_valids
+                    .as_ref()
+                    .map(|v| v.is_valid(row_idx))
+                    .unwrap_or(true)
+                {
+                    return None;
+                }
+                self.map
+                    .get(key_rows.row(row_idx).as_ref())
+                    .map(|row_indices| row_indices.as_slice())
+            })
+            .collect())
+    }
+}
+
+pub fn evaluate_keys(batch: &RecordBatch, key_exprs: &[Column]) -> Result<Vec<ArrayRef>> {
+    key_exprs
+        .iter()
+        .map(|key| Ok(key.evaluate(batch)?.into_array(batch.num_rows())))
+        .collect()
+}
+
+/// Returns the validity of join keys, a row is valid only if all its keys are
+/// non-null.
+pub fn keys_valids(keys: &[ArrayRef]) -> Option<NullBuffer> {
+    keys.iter().fold(None, |valids, key| {
+        NullBuffer::union(valids.as_ref(), key.nulls())
+    })
+}
@@ -13,18 +13,29 @@
 // limitations under the License.
 
 use async_trait::async_trait;
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call, jni_call_static, jni_new_global_ref};
 use bytesize::ByteSize;
-use datafusion::common::Result;
+use datafusion::common::{DataFusionError, Result};
+use jni::objects::GlobalRef;
+use jni::sys::{jboolean, JNI_TRUE};
 use once_cell::sync::OnceCell;
 use parking_lot::{Condvar, Mutex};
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
+use tokio::runtime::Handle;
 
 static MEM_MANAGER: OnceCell<Arc<MemManager>> = OnceCell::new();
 
 // never triggers waiting/spilling for consumers which use very little memory
 const MIN_TRIGGER_SIZE: usize = 1 << 24; // 16MB
 
+// memory is acquired from/released to the jvm in chunks, to reduce jni calls
+const JVM_CHUNK_SIZE: usize = 1 << 22; // 4MB
+
+// max time waiting for a consumer to spill when requested by the jvm
+const JVM_SPILL_TIMEOUT: Duration = Duration::from_millis(10000);
+
 pub struct MemManager {
     total: usize,
     consumers: Mutex<Vec<Arc<MemConsumerInfo>>>,
@@ -53,12 +64,28 @@ impl MemManager {
         MEM_MANAGER.get().expect("mem manager not initialized")
     }
 
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
     pub fn num_consumers(&self) -> usize {
         self.consumers.lock().len()
     }
 
     pub fn register_consumer(mut consumer: Arc<dyn MemConsumer>, spillable: bool) {
+        let jvm_consumer = get_task_jvm_consumer().unwrap_or_else(|err| {
+            log::warn!(
+                "mem manager: cannot get jvm memory consumer of {}: {}",
+                consumer.name(),
+                err,
+            );
+            None
+        });
         let consumer_info = Arc::new(MemConsumerInfo {
+            consumer: Arc::downgrade(&consumer),
+            jvm_consumer,
+            jvm_acquired: Mutex::new(0),
+            runtime: Handle::try_current().ok(),
             status: Mutex::new(MemConsumerStatus {
                 mem_used: 0,
                 spillable,
@@ -109,13 +136,132 @@ impl MemManager {
                 log::info!("mem manager deregistered consumer: {}", consumer.name());
                 mm_consumers.swap_remove(i);
 
+                drop(consumer_status);
                 drop(mm_status);
                 drop(mm_consumers);
+
+                // give back all memory acquired from jvm
+                if let Err(err) = consumer_info.sync_jvm_mem_used(0) {
+                    log::warn!(
+                        "mem manager: consumer {} error releasing jvm memory: {}",
+                        consumer.name(),
+                        err,
+                    );
+                }
                 return;
             }
         }
         unreachable!("deregistering non-registered memory consumer")
     }
+
+    /// Spills the largest spillable consumers holding memory acquired from
+    /// the specified jvm consumer, until at least `size` bytes are released
+    /// to the jvm. this is called when spark asks native memory to be freed
+    /// for other jvm consumers in the same task.
+    ///
+    /// returns the number of bytes released to the jvm.
+    pub fn spill_jvm_consumer(
+        is_same_jvm_consumer: impl Fn(&GlobalRef) -> bool,
+        size: usize,
+    ) -> Result<usize> {
+        let mm = Self::get();
+        let mut released = 0;
+        let mut spilled: Vec<Arc<MemConsumerInfo>> = vec![];
+
+        while released < size {
+            // consumers which are acquiring memory from jvm are skipped, spilling them
+            // here may deadlock with the acquiring thread
+            let largest = mm
+                .consumers
+                .lock()
+                .iter()
+                .filter(|info| !spilled.iter().any(|s| Arc::ptr_eq(s, *info)))
+                .filter(|info| info.jvm_consumer.iter().any(&is_same_jvm_consumer))
+                .filter_map(|info| {
+                    let jvm_acquired = *info.jvm_acquired.try_lock()?;
+                    let status = *info.status.lock();
+                    (status.spillable && jvm_acquired > 0).then_some((info, status.mem_used))
+                })
+                .max_by_key(|(_, mem_used)| *mem_used)
+                .map(|(info, _)| info.clone());
+
+            let consumer_info = match largest {
+                Some(consumer_info) => consumer_info,
+                None => break,
+            };
+            spilled.push(consumer_info.clone());
+
+            let consumer = match consumer_info.consumer.upgrade() {
+                Some(consumer) => consumer,
+                None => continue,
+            };
+            log::info!(
+                "mem manager spilling {} (mem_used: {}) requested by jvm",
+                consumer.name(),
+                ByteSize(consumer_info.status.lock().mem_used as u64),
+            );
+            let jvm_acquired_before = *consumer_info.jvm_acquired.lock();
+
+            let consumer_name = consumer.name().to_owned();
+            if !spill_in_consumer_runtime(&consumer_info, consumer, JVM_SPILL_TIMEOUT)? {
+                log::warn!(
+                    "mem manager: timeout waiting for {} to spill",
+                    consumer_name,
+                );
+                break;
+            }
+            let jvm_acquired_after = *consumer_info.jvm_acquired.lock();
+            released += jvm_acquired_before.saturating_sub(jvm_acquired_after);
+        }
+        Ok(released)
+    }
+}
+
+/// Spills a consumer on a blocking thread of the runtime it is registered in,
+/// whose threads carry the spark task context. the consumer may be blocked on
+/// the task memory manager which is locked by the caller, in that case we give
+/// up waiting after `timeout` and return false. the spill is cancelled if it
+/// has not started yet, otherwise it cannot be safely interrupted and finishes
+/// on the runtime.
+fn spill_in_consumer_runtime(
+    consumer_info: &MemConsumerInfo,
+    consumer: Arc<dyn MemConsumer>,
+    timeout: Duration,
+) -> Result<bool> {
+    let runtime = consumer_info.runtime.as_ref().ok_or_else(|| {
+        DataFusionError::Execution(format!(
+            "mem manager: {} is not registered in a runtime",
+            consumer.name(),
+        ))
+    })?;
+    let consumer_name = consumer.name().to_owned();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = runtime.spawn_blocking(move || {
+        let _ = tx.send(futures::executor::block_on(consumer.spill()));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result.map(|_| true),
+        Err(RecvTimeoutError::Timeout) => {
+            handle.abort();
+            Ok(false)
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(DataFusionError::Execution(format!(
+            "mem manager: spilling task of {} exited unexpectedly",
+            consumer_name,
+        ))),
+    }
+}
+
+/// Gets the jvm memory consumer of the current spark task, returns None in
+/// driver side or when running without jvm (testing).
+fn get_task_jvm_consumer() -> Result<Option<GlobalRef>> {
+    if !is_jni_bridge_inited()
+        || jni_call_static!(JniBridge.isDriverSide() -> jboolean)? == JNI_TRUE
+    {
+        return Ok(None);
+    }
+    let jvm_consumer = jni_call_static!(JniBridge.getTaskNativeMemoryConsumer() -> JObject)?;
+    Ok(Some(jni_new_global_ref!(jvm_consumer.as_obj())?))
 }
 
 #[derive(Default, Clone, Copy)]
@@ -141,11 +287,39 @@ impl MemManagerStatus {
     }
 }
 
-#[derive(Debug)]
 pub struct MemConsumerInfo {
+    consumer: Weak<dyn MemConsumer>,
+    jvm_consumer: Option<GlobalRef>,
+    jvm_acquired: Mutex<usize>,
+    runtime: Option<Handle>,
     status: Mutex<MemConsumerStatus>,
 }
 
+impl MemConsumerInfo {
+    /// Acquires/releases memory from/to the jvm so that the acquired size
+    /// covers the current memory usage. returns false if jvm cannot grant
+    /// enough memory.
+    fn sync_jvm_mem_used(&self, mem_used: usize) -> Result<bool> {
+        let jvm_consumer = match &self.jvm_consumer {
+            Some(jvm_consumer) => jvm_consumer,
+            None => return Ok(true),
+        };
+        let mut jvm_acquired = self.jvm_acquired.lock();
+        let target = (mem_used + JVM_CHUNK_SIZE - 1) / JVM_CHUNK_SIZE * JVM_CHUNK_SIZE;
+
+        if target > *jvm_acquired {
+            let granted = jni_call!(BlazeNativeMemoryConsumer(jvm_consumer.as_obj())
+                .acquire((target - *jvm_acquired) as i64) -> i64)?;
+            *jvm_acquired += granted as usize;
+        } else if target < *jvm_acquired {
+            jni_call!(BlazeNativeMemoryConsumer(jvm_consumer.as_obj())
+                .release((*jvm_acquired - target) as i64) -> ())?;
+            *jvm_acquired = target;
+        }
+        Ok(*jvm_acquired >= mem_used)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 struct MemConsumerStatus {
     mem_used: usize,
@@ -258,23 +432,28 @@ async fn update_consumer_mem_used_with_custom_updater(
             mm_status.mem_spillables = (mm_status.mem_spillables as isize + diff_used) as usize;
         }
 
-        // consumer is unspillable/shrinking, no need to wait or spill
-        if !spillable || new_used < old_used {
-            return Ok(());
-        }
-
         // unlock
         let num_spillables = mm_status.num_spillables;
         let mem_spillables = mm_status.mem_spillables;
         drop(consumer_status);
         drop(mm_status);
 
+        // acquire/release memory from jvm, must be called without holding any locks
+        // because jvm may call back to spill other consumers
+        let jvm_granted = consumer_info.sync_jvm_mem_used(new_used)?;
+
+        // consumer is unspillable/shrinking, no need to wait or spill
+        if !spillable || new_used < old_used {
+            return Ok(());
+        }
+
         let consumer_mem_max = (total - (total_used - mem_spillables)) / num_spillables;
         let consumer_mem_min = consumer_mem_max / 8;
 
         let total_overflowed = total_used > total;
         let consumer_overflowed = new_used > consumer_mem_max;
-        let operation = if (total_overflowed || consumer_overflowed)
+        let jvm_overflowed = !jvm_granted;
+        let operation = if (total_overflowed || consumer_overflowed || jvm_overflowed)
             && new_used > MIN_TRIGGER_SIZE
             && new_used > old_used
         {
@@ -334,3 +513,107 @@ fn print_stats(by: &dyn MemConsumer, old_used: usize, new_used: usize) {
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::{
+        spill_in_consumer_runtime, MemConsumer, MemConsumerInfo, MemManager,
+    };
+    use async_trait::async_trait;
+    use datafusion::common::Result;
+    use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+    use std::sync::{Arc, Weak};
+    use std::time::Duration;
+    use tokio::runtime::Handle;
+
+    struct TestConsumer {
+        name: String,
+        mem_consumer_info: Option<Weak<MemConsumerInfo>>,
+        spill_delay: Duration,
+        spilled_in_runtime: AtomicBool,
+    }
+
+    impl TestConsumer {
+        fn new(name: &str, spill_delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                name: name.to_string(),
+                mem_consumer_info: None,
+                spill_delay,
+                spilled_in_runtime: AtomicBool::new(false),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl MemConsumer for TestConsumer {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn set_consumer_info(&mut self, consumer_info: Weak<MemConsumerInfo>) {
+            self.mem_consumer_info = Some(consumer_info);
+        }
+
+        fn get_consumer_info(&self) -> &Weak<MemConsumerInfo> {
+            self.mem_consumer_info
+                .as_ref()
+                .expect("consumer info not set")
+        }
+
+        async fn spill(&self) -> Result<()> {
+            std::thread::sleep(self.spill_delay);
+            self.spilled_in_runtime
+                .store(Handle::try_current().is_ok(), SeqCst);
+            Ok(())
+        }
+    }
+
+    impl Drop for TestConsumer {
+        fn drop(&mut self) {
+            MemManager::deregister_consumer(self);
+        }
+    }
+
+    #[test]
+    fn test_spill_in_consumer_runtime() -> Result<()> {
+        MemManager::init(1000000);
+        let runtime = tokio::runtime::Runtime::new()?;
+        let (consumer, slow_consumer) = {
+            let _guard = runtime.enter();
+            let consumer = TestConsumer::new("consumer", Duration::ZERO);
+            let slow_consumer = TestConsumer::new("slow_consumer", Duration::from_millis(500));
+            MemManager::register_consumer(consumer.clone(), true);
+            MemManager::register_consumer(slow_consumer.clone(), true);
+            (consumer, slow_consumer)
+        };
+
+        // spilling is requested from a thread outside the runtime, like a jvm
+        // thread, and runs on the runtime where the consumer is registered
+        let timeout = Duration::from_millis(10000);
+        assert!(spill_in_consumer_runtime(
+            &consumer.consumer_info(),
+            consumer.clone(),
+            timeout
+        )?);
+        assert!(consumer.spilled_in_runtime.load(SeqCst));
+
+        // gives up waiting for a slow spill
+        let timeout = Duration::from_millis(10);
+        assert!(!spill_in_consumer_runtime(
+            &slow_consumer.consumer_info(),
+            slow_consumer.clone(),
+            timeout,
+        )?);
+
+        // consumers registered outside any runtime cannot be spilled
+        let unbound_consumer = TestConsumer::new("unbound_consumer", Duration::ZERO);
+        MemManager::register_consumer(unbound_consumer.clone(), true);
+        assert!(spill_in_consumer_runtime(
+            &unbound_consumer.consumer_info(),
+            unbound_consumer.clone(),
+            timeout,
+        )
+        .is_err());
+        Ok(())
+    }
+}
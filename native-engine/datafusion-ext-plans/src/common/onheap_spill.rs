@@ -16,11 +16,11 @@ use blaze_jni_bridge::{
     is_jni_bridge_inited, jni_call, jni_call_static, jni_new_direct_byte_buffer, jni_new_global_ref,
 };
 use datafusion::common::Result;
-use datafusion::parquet::file::reader::Length;
+use datafusion_ext_commons::spill::{SpillFile, SpillManager};
 use jni::objects::GlobalRef;
 use jni::sys::{jboolean, jlong, JNI_TRUE};
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use parking_lot::Mutex;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::sync::Arc;
 
 pub trait Spill: Send + Sync {
@@ -30,45 +30,54 @@ pub trait Spill: Send + Sync {
     fn get_buf_writer(&self) -> BufWriter<Box<dyn Write + Send>>;
 }
 
-pub fn try_new_spill() -> Result<Box<dyn Spill>> {
+/// Creates a spill of the task, which is a file allocated by the task's spill
+/// manager, or an on-heap spill if enabled on executor side.
+pub fn try_new_spill(spill_manager: &Arc<SpillManager>) -> Result<Box<dyn Spill>> {
     if !is_jni_bridge_inited()
         || jni_call_static!(JniBridge.isDriverSide() -> jboolean)? == JNI_TRUE
+        || jni_call_static!(BlazeConf.spillOnHeapEnabled() -> jboolean)? != JNI_TRUE
     {
-        Ok(Box::new(FileSpill::try_new()?))
+        Ok(Box::new(FileSpill::try_new(spill_manager)?))
     } else {
         Ok(Box::new(OnHeapSpill::try_new()?))
     }
 }
 
-/// A spill structure which write data to temporary files
-/// used in driver side
-struct FileSpill(File);
+/// A spill structure which write data to spill files in spark local dirs,
+/// files are deleted when dropped or on task completion
+struct FileSpill(Mutex<SpillFile>);
 impl FileSpill {
-    fn try_new() -> Result<Self> {
-        let file = tempfile::tempfile()?;
-        Ok(Self(file))
+    fn try_new(spill_manager: &Arc<SpillManager>) -> Result<Self> {
+        Ok(Self(Mutex::new(spill_manager.new_spill_file()?)))
     }
 }
 
 impl Spill for FileSpill {
     fn complete(&self) -> Result<()> {
-        let mut file_cloned = self.0.try_clone().expect("File.try_clone() returns error");
-        file_cloned.sync_data()?;
-        file_cloned.rewind()?;
-        Ok(())
+        let mut spill_file = self.0.lock();
+        spill_file.try_clone_file()?.sync_data()?;
+        spill_file.complete()
     }
 
     fn get_disk_usage(&self) -> Result<u64> {
-        Ok(self.0.len())
+        Ok(self.0.lock().disk_size() as u64)
     }
 
     fn get_buf_reader(&self) -> BufReader<Box<dyn Read + Send>> {
-        let file_cloned = self.0.try_clone().expect("File.try_clone() returns error");
+        let file_cloned = self
+            .0
+            .lock()
+            .try_clone_file()
+            .expect("File.try_clone() returns error");
         BufReader::with_capacity(65536, Box::new(file_cloned))
     }
 
     fn get_buf_writer(&self) -> BufWriter<Box<dyn Write + Send>> {
-        let file_cloned = self.0.try_clone().expect("File.try_clone() returns error");
+        let file_cloned = self
+            .0
+            .lock()
+            .try_clone_file()
+            .expect("File.try_clone() returns error");
         BufWriter::with_capacity(65536, Box::new(file_cloned))
     }
 }
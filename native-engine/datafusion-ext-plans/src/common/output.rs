@@ -13,10 +13,11 @@
 // limitations under the License.
 
 use std::future::Future;
-use std::io::{Cursor, Write};
+use std::io::Write;
 use std::panic::AssertUnwindSafe;
 use std::sync::{Arc, Weak};
 
+use crate::common::batch_accounting::{BatchAccounting, OperatorKey};
 use crate::common::memory_manager::{MemConsumer, MemManager};
 use crate::common::onheap_spill::try_new_spill;
 use arrow::datatypes::SchemaRef;
@@ -27,7 +28,8 @@ use datafusion::execution::context::TaskContext;
 use datafusion::physical_plan::metrics::ScopedTimerGuard;
 use datafusion::physical_plan::stream::RecordBatchReceiverStream;
 use datafusion::physical_plan::SendableRecordBatchStream;
-use datafusion_ext_commons::io::{read_one_batch, write_one_batch};
+use datafusion_ext_commons::io::read_one_batch;
+use datafusion_ext_commons::spill::SpillManager;
 use futures::{FutureExt, StreamExt, TryFutureExt};
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
@@ -41,13 +43,23 @@ fn working_senders() -> &'static Mutex<Vec<Weak<WrappedRecordBatchSender>>> {
 pub struct WrappedRecordBatchSender {
     task_context: Arc<TaskContext>,
     sender: Sender<Result<RecordBatch>>,
+    batch_accounting: Option<(Arc<BatchAccounting>, OperatorKey)>,
 }
 
 impl WrappedRecordBatchSender {
-    pub fn new(task_context: Arc<TaskContext>, sender: Sender<Result<RecordBatch>>) -> Arc<Self> {
+    pub fn new(
+        desc: &'static str,
+        task_context: Arc<TaskContext>,
+        sender: Sender<Result<RecordBatch>>,
+    ) -> Arc<Self> {
+        let batch_accounting = BatchAccounting::get(&task_context).map(|batch_accounting| {
+            let operator = batch_accounting.new_operator_key(desc);
+            (batch_accounting, operator)
+        });
         let wrapped = Arc::new(Self {
             task_context,
             sender,
+            batch_accounting,
         });
         let mut working_senders = working_senders().lock();
         working_senders.push(Arc::downgrade(&wrapped));
@@ -80,6 +92,10 @@ impl WrappedRecordBatchSender {
         let batch = batch_result
             .unwrap_or_else(|err| panic!("output_with_sender: received an error: {}", err));
 
+        if let Some((batch_accounting, operator)) = &self.batch_accounting {
+            batch_accounting.record_batch(*operator, batch.num_rows());
+        }
+
         stop_timer.iter_mut().for_each(|timer| timer.stop());
         self.sender
             .send(Ok(batch))
@@ -100,7 +116,7 @@ pub fn output_with_sender<Fut: Future<Output = Result<()>> + Send>(
     let err_sender = sender.clone();
 
     stream_builder.spawn(async move {
-        let wrapped = WrappedRecordBatchSender::new(task_context, sender);
+        let wrapped = WrappedRecordBatchSender::new(desc, task_context, sender);
         let result = AssertUnwindSafe(async move {
             let task_running = is_task_running();
             if !task_running {
@@ -151,6 +167,7 @@ pub fn output_bufferable_with_spill(
     mut stream: SendableRecordBatchStream,
 ) -> Result<SendableRecordBatchStream> {
     let output_schema = stream.schema();
+    let spill_manager = SpillManager::get(&task_context);
 
     output_with_sender(
         "OutputBufferableWithSpill",
@@ -162,16 +179,15 @@ pub fn output_bufferable_with_spill(
                 // to receive all of its outputs and release all memory.
                 // outputs can be read from spill later.
                 if MemManager::get().num_consumers() > 1 && mem_consumer.mem_used_percent() > 0.8 {
-                    let spill = try_new_spill()?;
+                    let spill = try_new_spill(&spill_manager)?;
                     let mut spill_writer = spill.get_buf_writer();
 
                     // write all batches to spill
                     while let Some(batch) = stream.next().await.transpose()? {
-                        let mut buf = vec![];
-                        write_one_batch(&batch, &mut Cursor::new(&mut buf), true, None)?;
-                        spill_writer.write_all(&buf)?;
+                        spill_manager.write_batch(&batch, &mut spill_writer)?;
                     }
                     spill_writer.flush()?;
+                    drop(spill_writer);
                     spill.complete()?;
 
                     // read all batches from spill and output
@@ -0,0 +1,780 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution plan for reading CSV files
+
+use crate::common::output::output_with_sender;
+use arrow::array::{Array, ArrayRef, BooleanArray, StringArray, StringBuilder};
+use arrow::compute::filter_record_batch;
+use arrow::datatypes::{DataType, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use blaze_jni_bridge::{jni_call_static, jni_new_global_ref, jni_new_string};
+use csv::ByteRecord;
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::datasource::listing::FileRange;
+use datafusion::datasource::physical_plan::{
+    FileMeta, FileOpenFuture, FileOpener, FileScanConfig, FileStream, OnError,
+};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricValue, MetricsSet, Time,
+};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Metric, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use datafusion_ext_commons::cast::cast;
+use datafusion_ext_commons::hadoop_fs::FsProvider;
+use futures::stream::once;
+use futures::{StreamExt, TryStreamExt};
+use std::any::Any;
+use std::fmt::Formatter;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+/// Spark's parse modes of malformed CSV records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvParseMode {
+    /// malformed fields are set to nulls, missing fields of records are nulls
+    /// and extra fields are ignored
+    Permissive,
+    /// records with any malformed fields are dropped
+    DropMalformed,
+    /// fails on the first malformed record
+    FailFast,
+}
+
+/// Spark's CSV reading options supported by the native scan
+#[derive(Debug, Clone)]
+pub struct CsvScanOptions {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: Option<u8>,
+    pub has_header: bool,
+    pub null_value: String,
+    pub mode: CsvParseMode,
+
+    /// the string field of the file schema filled with raw text of malformed
+    /// records, like spark's `columnNameOfCorruptRecord`. the field is not read
+    /// from files
+    pub corrupt_record_column: Option<String>,
+}
+
+impl Default for CsvScanOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            escape: Some(b'\\'),
+            has_header: false,
+            null_value: String::new(),
+            mode: CsvParseMode::Permissive,
+            corrupt_record_column: None,
+        }
+    }
+}
+
+/// Execution plan for scanning CSV files with spark's CSV options. fields are
+/// parsed as strings and casted to the file schema with spark compatible
+/// casts, a field is malformed if it is not null but casted to null. records
+/// with a wrong number of fields are also malformed.
+///
+/// the scan is native-only for now: spark's csv scans are not converted to it
+/// since options like dateFormat/timestampFormat/multiLine are not supported,
+/// so it is only planned from protobuf plans built outside of spark.
+#[derive(Debug, Clone)]
+pub struct CsvScanExec {
+    fs_resource_id: String,
+    base_config: FileScanConfig,
+    options: CsvScanOptions,
+    projected_statistics: Statistics,
+    projected_schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl CsvScanExec {
+    pub fn new(
+        base_config: FileScanConfig,
+        fs_resource_id: String,
+        options: CsvScanOptions,
+    ) -> Self {
+        let (projected_schema, projected_statistics, _) = base_config.project();
+        Self {
+            fs_resource_id,
+            base_config,
+            options,
+            projected_statistics,
+            projected_schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl DisplayAs for CsvScanExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        let file_group = self
+            .base_config
+            .file_groups
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "CsvScanExec: limit={:?}, file_group={:?}, options={:?}",
+            self.base_config.limit, file_group, self.options,
+        )
+    }
+}
+
+impl ExecutionPlan for CsvScanExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.projected_schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.base_config.file_groups.len())
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        let elapsed_compute = baseline_metrics.elapsed_compute().clone();
+        let io_time = Time::default();
+        self.metrics.register(Arc::new(Metric::new(
+            MetricValue::Time {
+                name: "io_time".into(),
+                time: io_time.clone(),
+            },
+            Some(partition),
+        )));
+        let malformed_rows = MetricBuilder::new(&self.metrics).counter("malformed_rows", partition);
+
+        // get fs object from jni bridge resource
+        let resource_id = jni_new_string!(&self.fs_resource_id)?;
+        let fs = jni_call_static!(JniBridge.getResource(resource_id.as_obj()) -> JObject)?;
+        let fs_provider = Arc::new(FsProvider::new(jni_new_global_ref!(fs.as_obj())?, &io_time));
+
+        let projection = match self.base_config.file_column_projection_indices() {
+            Some(projection) => projection,
+            None => (0..self.base_config.file_schema.fields().len()).collect(),
+        };
+        let opener = CsvOpener {
+            fs_provider,
+            file_schema: self.base_config.file_schema.clone(),
+            projection: Arc::from(projection),
+            batch_size: context.session_config().batch_size(),
+            options: self.options.clone(),
+            malformed_rows,
+        };
+        let mut file_stream = FileStream::new(&self.base_config, partition, opener, &self.metrics)?;
+        if jni_call_static!(BlazeConf.ignoreCorruptedFiles() -> bool)? {
+            file_stream = file_stream.with_on_error(OnError::Skip);
+        }
+        let mut stream = Box::pin(file_stream);
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            once(async move {
+                output_with_sender(
+                    "CsvScan",
+                    context,
+                    stream.schema(),
+                    move |sender| async move {
+                        let mut timer = elapsed_compute.timer();
+                        while let Some(batch) = stream.next().await.transpose()? {
+                            sender.send(Ok(batch), Some(&mut timer)).await;
+                        }
+                        Ok(())
+                    },
+                )
+            })
+            .try_flatten(),
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.projected_statistics.clone()
+    }
+}
+
+#[derive(Clone)]
+struct CsvOpener {
+    fs_provider: Arc<FsProvider>,
+    file_schema: SchemaRef,
+    projection: Arc<[usize]>,
+    batch_size: usize,
+    options: CsvScanOptions,
+    malformed_rows: Count,
+}
+
+impl FileOpener for CsvOpener {
+    fn open(&self, file_meta: FileMeta) -> Result<FileOpenFuture> {
+        let opener = self.clone();
+        Ok(Box::pin(async move {
+            let path = BASE64_URL_SAFE_NO_PAD
+                .decode(file_meta.location().filename().expect("missing filename"))
+                .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+                .map_err(|_| {
+                    DataFusionError::Execution(format!(
+                        "cannot decode filename: {:?}",
+                        file_meta.location().filename()
+                    ))
+                })?;
+            let fs = opener.fs_provider.provide(&path)?;
+            let input = fs.open_reader(&path, file_meta.object_meta.size as u64)?;
+            let range_reader = read_range(input, file_meta.range.as_ref())?;
+            let is_first_range = file_meta.range.map(|r| r.start == 0).unwrap_or(true);
+
+            let batches = read_csv(
+                range_reader,
+                &opener.file_schema,
+                &opener.projection,
+                &opener.options,
+                is_first_range,
+                opener.batch_size,
+                opener.malformed_rows.clone(),
+            )?
+            .map(|batch| batch.map_err(|err| ArrowError::ExternalError(Box::new(err))));
+            Ok(futures::stream::iter(batches).boxed())
+        }))
+    }
+}
+
+/// Reads CSV records of the input, the schema of output batches is the projected
+/// file schema.
+fn read_csv<R: Read>(
+    input: R,
+    file_schema: &SchemaRef,
+    projection: &[usize],
+    options: &CsvScanOptions,
+    skip_header: bool,
+    batch_size: usize,
+    malformed_rows: Count,
+) -> Result<impl Iterator<Item = Result<RecordBatch>>> {
+    // the corrupt record column is not read from the file, other fields of the
+    // file schema are mapped to csv fields in order
+    let corrupt_record_idx = options
+        .corrupt_record_column
+        .as_ref()
+        .and_then(|name| file_schema.index_of(name).ok());
+    if let Some(idx) = corrupt_record_idx {
+        let data_type = file_schema.field(idx).data_type();
+        if data_type != &DataType::Utf8 {
+            return Err(DataFusionError::Plan(format!(
+                "CsvScanExec: the field for corrupt records must be string type, got {data_type}"
+            )));
+        }
+    }
+    let columns = projection
+        .iter()
+        .map(|&idx| match corrupt_record_idx {
+            Some(corrupt_record_idx) if idx == corrupt_record_idx => CsvColumn::CorruptRecord,
+            Some(corrupt_record_idx) if idx > corrupt_record_idx => CsvColumn::Field(idx - 1),
+            _ => CsvColumn::Field(idx),
+        })
+        .collect::<Vec<_>>();
+    let capture_records = columns.contains(&CsvColumn::CorruptRecord);
+
+    // records are read in flexible mode, records with a wrong number of fields
+    // are malformed
+    let reader = csv::ReaderBuilder::new()
+        .has_headers(options.has_header && skip_header)
+        .flexible(true)
+        .delimiter(options.delimiter)
+        .quote(options.quote)
+        .escape(options.escape)
+        .from_reader(RecordCapture::new(input, capture_records));
+
+    Ok(CsvBatchReader {
+        reader,
+        record: ByteRecord::new(),
+        schema: Arc::new(file_schema.project(projection)?),
+        columns,
+        num_csv_fields: file_schema.fields().len() - usize::from(corrupt_record_idx.is_some()),
+        options: options.clone(),
+        batch_size,
+        malformed_rows,
+    })
+}
+
+/// A column of output batches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsvColumn {
+    /// the field of csv records at the index
+    Field(usize),
+
+    /// raw text of malformed records, null for well-formed records
+    CorruptRecord,
+}
+
+struct CsvBatchReader<R: Read> {
+    reader: csv::Reader<RecordCapture<R>>,
+    record: ByteRecord,
+    schema: SchemaRef,
+    columns: Vec<CsvColumn>,
+    num_csv_fields: usize,
+    options: CsvScanOptions,
+    batch_size: usize,
+    malformed_rows: Count,
+}
+
+impl<R: Read> Iterator for CsvBatchReader<R> {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+impl<R: Read> CsvBatchReader<R> {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let mut builders = self
+            .columns
+            .iter()
+            .map(|_| StringBuilder::new())
+            .collect::<Vec<_>>();
+        let mut wrong_num_fields = vec![];
+        let mut raw_records = vec![];
+
+        while wrong_num_fields.len() < self.batch_size
+            && self
+                .reader
+                .read_byte_record(&mut self.record)
+                .map_err(|err| DataFusionError::External(Box::new(err)))?
+        {
+            // missing fields are nulls and extra fields are ignored
+            for (column, builder) in self.columns.iter().zip(&mut builders) {
+                match column {
+                    CsvColumn::Field(idx) => {
+                        builder.append_option(self.record.get(*idx).map(String::from_utf8_lossy))
+                    }
+                    CsvColumn::CorruptRecord => builder.append_null(),
+                }
+            }
+            wrong_num_fields.push(self.record.len() != self.num_csv_fields);
+
+            if self.reader.get_ref().enabled {
+                let start = self.record.position().map(|pos| pos.byte()).unwrap_or(0);
+                let end = self.reader.position().byte();
+                raw_records.push(self.reader.get_mut().take_record(start, end));
+            }
+        }
+        if wrong_num_fields.is_empty() {
+            return Ok(None);
+        }
+        let strings = builders
+            .into_iter()
+            .map(|mut builder| builder.finish())
+            .collect();
+        cast_csv_batch(
+            strings,
+            wrong_num_fields,
+            raw_records,
+            &self.schema,
+            &self.columns,
+            &self.options,
+            &self.malformed_rows,
+        )
+        .map(Some)
+    }
+}
+
+fn cast_csv_batch(
+    strings: Vec<StringArray>,
+    mut malformed: Vec<bool>,
+    raw_records: Vec<String>,
+    schema: &SchemaRef,
+    columns: &[CsvColumn],
+    options: &CsvScanOptions,
+    malformed_rows: &Count,
+) -> Result<RecordBatch> {
+    let mut casted_columns = strings
+        .iter()
+        .zip(schema.fields())
+        .zip(columns)
+        .map(|((strings, field), column)| {
+            if *column == CsvColumn::CorruptRecord {
+                return Ok(None); // filled after all fields are casted
+            }
+            // empty strings are always nulls like spark's default nullValue
+            let strings: StringArray = strings
+                .iter()
+                .map(|s| s.filter(|s| !s.is_empty() && *s != options.null_value))
+                .collect();
+            let casted = cast(&strings, field.data_type())?;
+            for (row_idx, is_malformed) in malformed.iter_mut().enumerate() {
+                *is_malformed |= strings.is_valid(row_idx) && casted.is_null(row_idx);
+            }
+            Ok(Some(casted))
+        })
+        .collect::<Result<Vec<Option<ArrayRef>>>>()?;
+
+    let corrupt_records: ArrayRef = Arc::new(
+        raw_records
+            .into_iter()
+            .zip(&malformed)
+            .map(|(raw_record, &is_malformed)| is_malformed.then_some(raw_record))
+            .collect::<StringArray>(),
+    );
+    for casted in &mut casted_columns {
+        casted.get_or_insert_with(|| corrupt_records.clone());
+    }
+    let casted_batch = RecordBatch::try_new_with_options(
+        schema.clone(),
+        casted_columns.into_iter().flatten().collect(),
+        &RecordBatchOptions::new().with_row_count(Some(malformed.len())),
+    )?;
+
+    let num_malformed = malformed.iter().filter(|&&m| m).count();
+    if num_malformed == 0 {
+        return Ok(casted_batch);
+    }
+    malformed_rows.add(num_malformed);
+    match options.mode {
+        CsvParseMode::Permissive => Ok(casted_batch),
+        CsvParseMode::DropMalformed => {
+            let valid = BooleanArray::from_iter(malformed.iter().map(|&m| Some(!m)));
+            Ok(filter_record_batch(&casted_batch, &valid)?)
+        }
+        CsvParseMode::FailFast => Err(DataFusionError::Execution(
+            "Malformed records are detected in record parsing. Parse Mode: FAILFAST. To \
+                process malformed records as null result, try setting the option 'mode' as \
+                'PERMISSIVE'."
+                .to_string(),
+        )),
+    }
+}
+
+/// Input of the csv reader keeping the bytes read, to provide raw text of
+/// records for the corrupt record column. bytes are kept only if enabled.
+struct RecordCapture<R: Read> {
+    input: R,
+    enabled: bool,
+    buf: Vec<u8>,
+    buf_start: u64, // input position of buf[0]
+    taken: usize,   // bytes of buf already taken, discarded on next read
+}
+
+impl<R: Read> RecordCapture<R> {
+    fn new(input: R, enabled: bool) -> Self {
+        Self {
+            input,
+            enabled,
+            buf: vec![],
+            buf_start: 0,
+            taken: 0,
+        }
+    }
+
+    /// Returns text of the record in [start, end) of the input, without line
+    /// terminators.
+    fn take_record(&mut self, start: u64, end: u64) -> String {
+        let start = (start.saturating_sub(self.buf_start) as usize).min(self.buf.len());
+        let end = (end.saturating_sub(self.buf_start) as usize).clamp(start, self.buf.len());
+        self.taken = end;
+        String::from_utf8_lossy(&self.buf[start..end])
+            .trim_matches(|c| c == '\r' || c == '\n')
+            .to_string()
+    }
+}
+
+impl<R: Read> Read for RecordCapture<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let num_bytes = self.input.read(buf)?;
+        if self.enabled {
+            self.buf.drain(..self.taken);
+            self.buf_start += self.taken as u64;
+            self.taken = 0;
+            self.buf.extend_from_slice(&buf[..num_bytes]);
+        }
+        Ok(num_bytes)
+    }
+}
+
+/// Positions the input at the range like hadoop's `LineRecordReader`: a range
+/// not starting at 0 skips the first (partial) line, and the range ends with the
+/// line containing its end offset, so every line is read by exactly one range.
+fn read_range<R: BufRead + Seek>(
+    mut input: R,
+    range: Option<&FileRange>,
+) -> std::io::Result<CsvRangeReader<R>> {
+    let range = match range {
+        Some(range) => range,
+        None => {
+            return Ok(CsvRangeReader {
+                input,
+                remaining: u64::MAX,
+                finished: false,
+            })
+        }
+    };
+
+    let mut pos = range.start as u64;
+    if pos > 0 {
+        input.seek(SeekFrom::Start(pos))?;
+        pos += input.read_until(b'\n', &mut vec![])? as u64;
+    }
+    Ok(CsvRangeReader {
+        input,
+        remaining: (range.end as u64).saturating_sub(pos),
+        // the skipped line crosses the whole range
+        finished: pos > range.end as u64,
+    })
+}
+
+struct CsvRangeReader<R: BufRead> {
+    input: R,
+    remaining: u64,
+    finished: bool,
+}
+
+impl<R: BufRead> Read for CsvRangeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining > 0 {
+            let max_len = buf
+                .len()
+                .min(self.remaining.min(usize::MAX as u64) as usize);
+            let num_bytes = self.input.read(&mut buf[..max_len])?;
+            self.remaining -= num_bytes as u64;
+            self.finished = num_bytes == 0;
+            return Ok(num_bytes);
+        }
+
+        // reached the range end, continue reading until the end of current line
+        let available = self.input.fill_buf()?;
+        let num_bytes = match available.iter().position(|&b| b == b'\n') {
+            Some(line_end) if line_end < buf.len() => {
+                self.finished = true;
+                line_end + 1
+            }
+            _ => available.len().min(buf.len()),
+        };
+        self.finished |= num_bytes == 0;
+        buf[..num_bytes].copy_from_slice(&available[..num_bytes]);
+        self.input.consume(num_bytes);
+        Ok(num_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::csv_scan_exec::{read_csv, read_range, CsvParseMode, CsvScanOptions};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::datasource::listing::FileRange;
+    use datafusion::physical_plan::metrics::Count;
+    use std::io::{Cursor, Read};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_read_ranges() -> Result<()> {
+        let data = "a,1\nbb,2\nccc,3\ndddd,4\n";
+        let read_range_string = |start: i64, end: i64| -> Result<String> {
+            let range = FileRange { start, end };
+            let mut output = String::new();
+            read_range(Cursor::new(data), Some(&range))?.read_to_string(&mut output)?;
+            Ok(output)
+        };
+
+        // every line is read by exactly one range
+        for split in 1..data.len() as i64 {
+            let first = read_range_string(0, split)?;
+            let second = read_range_string(split, data.len() as i64)?;
+            assert_eq!(first + &second, data, "split at {split}");
+        }
+        assert_eq!(read_range_string(0, 4)?, "a,1\nbb,2\n");
+        assert_eq!(read_range_string(4, 9)?, "ccc,3\n");
+        assert_eq!(read_range_string(5, 7)?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_csv_with_parse_modes() -> Result<()> {
+        let data = "id|name|score\n1|'a|b'|1.5\n2|NULL|x\n3||\nabc|d|2.0\n";
+        let file_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("score", DataType::Float64, true),
+        ]));
+        let read = |mode: CsvParseMode, projection: &[usize]| -> Result<_> {
+            let options = CsvScanOptions {
+                delimiter: b'|',
+                quote: b'\'',
+                has_header: true,
+                null_value: "NULL".to_string(),
+                mode,
+                ..Default::default()
+            };
+            let malformed_rows = Count::new();
+            let batches = read_csv(
+                Cursor::new(data),
+                &file_schema,
+                projection,
+                &options,
+                true,
+                2,
+                malformed_rows.clone(),
+            )?
+            .collect::<Result<Vec<_>>>();
+            Ok((batches, malformed_rows.value()))
+        };
+
+        let (batches, malformed_rows) = read(CsvParseMode::Permissive, &[0, 1, 2])?;
+        assert_batches_eq!(
+            vec![
+                "+----+------+-------+",
+                "| id | name | score |",
+                "+----+------+-------+",
+                "| 1  | a|b  | 1.5   |",
+                "| 2  |      |       |",
+                "| 3  |      |       |",
+                "|    | d    | 2.0   |",
+                "+----+------+-------+",
+            ],
+            &batches?
+        );
+        assert_eq!(malformed_rows, 2);
+
+        let (batches, _) = read(CsvParseMode::DropMalformed, &[2, 0])?;
+        assert_batches_eq!(
+            vec![
+                "+-------+----+",
+                "| score | id |",
+                "+-------+----+",
+                "| 1.5   | 1  |",
+                "|       | 3  |",
+                "+-------+----+",
+            ],
+            &batches?
+        );
+
+        let (batches, _) = read(CsvParseMode::FailFast, &[0, 1, 2])?;
+        assert!(batches
+            .unwrap_err()
+            .to_string()
+            .contains("Malformed records are detected in record parsing. Parse Mode: FAILFAST."));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_csv_with_wrong_num_fields() -> Result<()> {
+        let data = "1,a,1.5\n2,b\n3,c,3.5,x\nd,e,4.5\n";
+        let file_schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("_corrupt_record", DataType::Utf8, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("score", DataType::Float64, true),
+        ]));
+        let read = |mode: CsvParseMode, projection: &[usize]| -> Result<_> {
+            let options = CsvScanOptions {
+                mode,
+                corrupt_record_column: Some("_corrupt_record".to_string()),
+                ..Default::default()
+            };
+            let malformed_rows = Count::new();
+            let batches = read_csv(
+                Cursor::new(data),
+                &file_schema,
+                projection,
+                &options,
+                true,
+                2,
+                malformed_rows.clone(),
+            )?
+            .collect::<Result<Vec<_>>>()?;
+            Ok((batches, malformed_rows.value()))
+        };
+
+        // missing fields are nulls and extra fields are ignored
+        let (batches, malformed_rows) = read(CsvParseMode::Permissive, &[0, 1, 2, 3])?;
+        assert_batches_eq!(
+            vec![
+                "+----+-----------------+------+-------+",
+                "| id | _corrupt_record | name | score |",
+                "+----+-----------------+------+-------+",
+                "| 1  |                 | a    | 1.5   |",
+                "| 2  | 2,b             | b    |       |",
+                "| 3  | 3,c,3.5,x       | c    | 3.5   |",
+                "|    | d,e,4.5         | e    | 4.5   |",
+                "+----+-----------------+------+-------+",
+            ],
+            &batches
+        );
+        assert_eq!(malformed_rows, 3);
+
+        let (batches, _) = read(CsvParseMode::Permissive, &[3])?;
+        assert_batches_eq!(
+            vec![
+                "+-------+",
+                "| score |",
+                "+-------+",
+                "| 1.5   |",
+                "|       |",
+                "| 3.5   |",
+                "| 4.5   |",
+                "+-------+",
+            ],
+            &batches
+        );
+
+        let (batches, _) = read(CsvParseMode::DropMalformed, &[2, 0])?;
+        assert_batches_eq!(
+            vec![
+                "+------+----+",
+                "| name | id |",
+                "+------+----+",
+                "| a    | 1  |",
+                "+------+----+",
+            ],
+            &batches
+        );
+        Ok(())
+    }
+}
@@ -53,6 +53,10 @@ impl DebugExec {
             metrics: ExecutionPlanMetricsSet::new(),
         }
     }
+
+    pub fn debug_id(&self) -> &str {
+        &self.debug_id
+    }
 }
 
 impl DisplayAs for DebugExec {
@@ -93,7 +97,7 @@ impl ExecutionPlan for DebugExec {
             ));
         }
         Ok(Arc::new(DebugExec::new(
-            self.input.clone(),
+            children[0].clone(),
             self.debug_id.clone(),
         )))
     }
@@ -0,0 +1,278 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt::Formatter;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow::array::{as_primitive_array, Array, ArrayRef, Decimal128Array, Int64Array};
+use arrow::datatypes::{DataType, Decimal128Type, Int64Type, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream, Statistics,
+};
+use futures::{Stream, StreamExt};
+
+/// Converts decimal columns between arrow's `Decimal128` and spark's compact
+/// representation (unscaled values stored in `Int64`, used by spark for decimals
+/// with precision <= 18).
+///
+/// each input column is converted to the type of the corresponding output field:
+/// `Decimal128 -> Int64` compacts the values, `Int64 -> Decimal128` expands them,
+/// and other columns must have the same types. values that cannot be represented
+/// in the target type are converted to nulls.
+#[derive(Debug)]
+pub struct DecimalReprExec {
+    input: Arc<dyn ExecutionPlan>,
+    output_schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DecimalReprExec {
+    pub fn try_new(input: Arc<dyn ExecutionPlan>, output_schema: SchemaRef) -> Result<Self> {
+        let input_schema = input.schema();
+        if input_schema.fields().len() != output_schema.fields().len() {
+            return Err(DataFusionError::Plan(format!(
+                "DecimalReprExec: output schema not matched with input schema, \
+                    input schema: {}, output schema: {}",
+                input_schema, output_schema,
+            )));
+        }
+        for (input_field, output_field) in input_schema.fields().iter().zip(output_schema.fields())
+        {
+            match (input_field.data_type(), output_field.data_type()) {
+                (DataType::Decimal128(..), DataType::Int64) => {}
+                (DataType::Int64, DataType::Decimal128(..)) => {}
+                (t1, t2) if t1 == t2 => {}
+                (t1, t2) => {
+                    return Err(DataFusionError::Plan(format!(
+                        "DecimalReprExec: cannot convert column {} from {:?} to {:?}",
+                        input_field.name(),
+                        t1,
+                        t2,
+                    )));
+                }
+            }
+        }
+        Ok(Self {
+            input,
+            output_schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl DisplayAs for DecimalReprExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "DecimalReprExec")
+    }
+}
+
+impl ExecutionPlan for DecimalReprExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.output_schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::try_new(
+                children[0].clone(),
+                self.output_schema.clone(),
+            )?)),
+            _ => Err(DataFusionError::Internal(
+                "DecimalReprExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        Ok(Box::pin(DecimalReprStream {
+            input: self.input.execute(partition, context)?,
+            output_schema: self.output_schema.clone(),
+            baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+        }))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
+}
+
+struct DecimalReprStream {
+    input: SendableRecordBatchStream,
+    output_schema: SchemaRef,
+    baseline_metrics: BaselineMetrics,
+}
+
+impl RecordBatchStream for DecimalReprStream {
+    fn schema(&self) -> SchemaRef {
+        self.output_schema.clone()
+    }
+}
+
+impl Stream for DecimalReprStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.input.poll_next_unpin(cx)? {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(batch)) => {
+                let timer = self.baseline_metrics.elapsed_compute().timer();
+                let columns = batch
+                    .columns()
+                    .iter()
+                    .zip(self.output_schema.fields())
+                    .map(|(column, field)| convert_decimal_repr(column, field.data_type()))
+                    .collect::<Result<Vec<_>>>()?;
+                let output_batch = RecordBatch::try_new(self.output_schema.clone(), columns)?;
+                drop(timer);
+                self.baseline_metrics
+                    .record_poll(Poll::Ready(Some(Ok(output_batch))))
+            }
+        }
+    }
+}
+
+fn convert_decimal_repr(array: &ArrayRef, to_type: &DataType) -> Result<ArrayRef> {
+    Ok(match (array.data_type(), to_type) {
+        (DataType::Decimal128(..), DataType::Int64) => {
+            let decimals = as_primitive_array::<Decimal128Type>(array);
+            let compacted: Int64Array = decimals.unary_opt(|v| i64::try_from(v).ok());
+            Arc::new(compacted)
+        }
+        (DataType::Int64, &DataType::Decimal128(precision, scale)) => {
+            let max_unscaled = 10i128.pow(precision as u32);
+            let unscaled = as_primitive_array::<Int64Type>(array);
+            let expanded: Decimal128Array = unscaled.unary_opt(|v| {
+                let v = v as i128;
+                (v.abs() < max_unscaled).then_some(v)
+            });
+            Arc::new(expanded.with_precision_and_scale(precision, scale)?)
+        }
+        _ => array.clone(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::decimal_repr_exec::DecimalReprExec;
+    use arrow::array::{Array, ArrayRef, Decimal128Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    async fn convert(batch: RecordBatch, output_type: DataType) -> Result<ArrayRef> {
+        let output_schema = Arc::new(Schema::new(vec![Field::new("d", output_type, true)]));
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![batch.clone()]],
+            batch.schema(),
+            None,
+        )?);
+        let exec = DecimalReprExec::try_new(input, output_schema)?;
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let output = common::collect(exec.execute(0, task_ctx)?).await?;
+        Ok(output[0].column(0).clone())
+    }
+
+    fn decimal_batch(array: Decimal128Array) -> RecordBatch {
+        let schema = Schema::new(vec![Field::new("d", array.data_type().clone(), true)]);
+        RecordBatch::try_new(Arc::new(schema), vec![Arc::new(array)]).unwrap()
+    }
+
+    fn compact_batch(array: Int64Array) -> RecordBatch {
+        let schema = Schema::new(vec![Field::new("d", DataType::Int64, true)]);
+        RecordBatch::try_new(Arc::new(schema), vec![Arc::new(array)]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_decimal_repr_round_trip() -> Result<()> {
+        let decimals =
+            Decimal128Array::from(vec![Some(12345), None, Some(-999999999999999999), Some(0)])
+                .with_precision_and_scale(18, 2)?;
+
+        let compacted = convert(decimal_batch(decimals.clone()), DataType::Int64).await?;
+        let expected_compacted: ArrayRef = Arc::new(Int64Array::from(vec![
+            Some(12345),
+            None,
+            Some(-999999999999999999),
+            Some(0),
+        ]));
+        assert_eq!(&compacted, &expected_compacted);
+
+        let compacted = compact_batch(Int64Array::from(compacted.to_data()));
+        let expanded = convert(compacted, DataType::Decimal128(18, 2)).await?;
+        let expected_expanded: ArrayRef = Arc::new(decimals);
+        assert_eq!(&expanded, &expected_expanded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decimal_repr_overflow() -> Result<()> {
+        // values not fitting into the compact form are converted to null
+        let decimals = Decimal128Array::from(vec![Some(i64::MAX as i128), Some(10i128.pow(20))])
+            .with_precision_and_scale(38, 0)?;
+        let compacted = convert(decimal_batch(decimals), DataType::Int64).await?;
+        let expected_compacted: ArrayRef = Arc::new(Int64Array::from(vec![Some(i64::MAX), None]));
+        assert_eq!(&compacted, &expected_compacted);
+
+        // values exceeding the target precision are converted to null
+        let compacted = compact_batch(Int64Array::from(vec![Some(99999), Some(100000)]));
+        let expanded = convert(compacted, DataType::Decimal128(5, 1)).await?;
+        let expected_expanded: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![Some(99999), None]).with_precision_and_scale(5, 1)?,
+        );
+        assert_eq!(&expanded, &expected_expanded);
+        Ok(())
+    }
+}
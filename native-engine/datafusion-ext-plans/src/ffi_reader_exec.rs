@@ -25,6 +25,7 @@ use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
     Statistics,
 };
+use datafusion_ext_commons::metrics::jni_time;
 use datafusion_ext_commons::streams::ffi_stream::FFIReaderStream;
 use jni::objects::JObject;
 use std::any::Any;
@@ -51,6 +52,10 @@ impl FFIReaderExec {
             metrics: ExecutionPlanMetricsSet::new(),
         }
     }
+
+    pub fn export_iter_provider_resource_id(&self) -> &str {
+        &self.export_iter_provider_resource_id
+    }
 }
 
 impl Debug for FFIReaderExec {
@@ -113,12 +118,15 @@ impl ExecutionPlan for FFIReaderExec {
         let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
         let size_counter = MetricBuilder::new(&self.metrics).counter("size", partition);
 
-        Ok(Box::pin(FFIReaderStream::new(
-            self.schema.clone(),
-            export_iter,
-            baseline_metrics,
-            size_counter,
-        )))
+        Ok(Box::pin(
+            FFIReaderStream::new(
+                self.schema.clone(),
+                export_iter,
+                baseline_metrics,
+                size_counter,
+            )
+            .with_jni_time(jni_time(&self.metrics, partition)),
+        ))
     }
 
     fn metrics(&self) -> Option<MetricsSet> {
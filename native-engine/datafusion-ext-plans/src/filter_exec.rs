@@ -17,21 +17,30 @@ use crate::common::cached_exprs_evaluator::CachedExprsEvaluator;
 use crate::common::column_pruning::ExecuteWithColumnPruning;
 use crate::common::output::output_with_sender;
 use crate::project_exec::ProjectExec;
+use arrow::array::BooleanArray;
+use arrow::compute::filter_record_batch;
 use arrow::datatypes::{DataType, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use datafusion::common::cast::as_int64_array;
 use datafusion::common::Statistics;
 use datafusion::common::{DataFusionError, Result};
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_expr::expressions::Column;
 use datafusion::physical_expr::{PhysicalExprRef, PhysicalSortExpr};
-use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
+};
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
 };
-use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
+use datafusion_ext_commons::selection::SelectionCoalescer;
+use datafusion_ext_commons::spark_bloom_filter::SparkBloomFilter;
+use datafusion_ext_commons::streams::coalesce_stream::batch_byte_budget;
 use futures::stream::once;
 use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
+use parking_lot::Mutex;
 use std::any::Any;
 use std::fmt::Formatter;
 use std::sync::Arc;
@@ -40,9 +49,33 @@ use std::sync::Arc;
 pub struct FilterExec {
     input: Arc<dyn ExecutionPlan>,
     predicates: Vec<PhysicalExprRef>,
+    bloom_filter_pushdown: Option<BloomFilterPushdown>,
     metrics: ExecutionPlanMetricsSet,
 }
 
+/// Bloom filter pushed down from the build side of a join, rows whose key
+/// might not be contained in the filter are dropped.
+///
+/// the key expr must be evaluated to the same long values inserted into the
+/// bloom filter (like `xxhash64(key)` in spark's runtime filters). the filter is
+/// injected at execute time with `FilterExec::inject_bloom_filter()`, batches
+/// are not pruned until then.
+#[derive(Debug, Clone)]
+pub struct BloomFilterPushdown {
+    key_expr: PhysicalExprRef,
+    bloom_filter: Arc<Mutex<Option<Arc<SparkBloomFilter>>>>,
+}
+
+impl BloomFilterPushdown {
+    pub fn key_expr(&self) -> &PhysicalExprRef {
+        &self.key_expr
+    }
+
+    pub fn bloom_filter(&self) -> Option<Arc<SparkBloomFilter>> {
+        self.bloom_filter.lock().clone()
+    }
+}
+
 impl FilterExec {
     pub fn try_new(
         predicates: Vec<PhysicalExprRef>,
@@ -66,13 +99,46 @@ impl FilterExec {
         Ok(Self {
             input,
             predicates,
+            bloom_filter_pushdown: None,
             metrics: ExecutionPlanMetricsSet::new(),
         })
     }
 
+    /// Enables pruning rows with a bloom filter on the key expr, which must
+    /// return long values
+    pub fn try_with_bloom_filter_pushdown(mut self, key_expr: PhysicalExprRef) -> Result<Self> {
+        if !matches!(
+            key_expr.data_type(&self.input.schema()),
+            Ok(DataType::Int64)
+        ) {
+            return Err(DataFusionError::Plan(
+                "Filter bloom filter key must return long values".to_string(),
+            ));
+        }
+        self.bloom_filter_pushdown = Some(BloomFilterPushdown {
+            key_expr,
+            bloom_filter: Arc::default(),
+        });
+        Ok(self)
+    }
+
     pub fn predicates(&self) -> &[PhysicalExprRef] {
         &self.predicates
     }
+
+    pub fn bloom_filter_pushdown(&self) -> Option<&BloomFilterPushdown> {
+        self.bloom_filter_pushdown.as_ref()
+    }
+
+    /// Injects the bloom filter built from the join's build side. takes effect
+    /// on all running and later executed partitions of this plan.
+    pub fn inject_bloom_filter(&self, bloom_filter: Arc<SparkBloomFilter>) -> Result<()> {
+        let bloom_filter_pushdown = self.bloom_filter_pushdown.as_ref().ok_or_else(|| {
+            DataFusionError::Execution("Filter bloom filter pushdown not enabled".to_string())
+        })?;
+        *bloom_filter_pushdown.bloom_filter.lock() = Some(bloom_filter);
+        Ok(())
+    }
 }
 
 impl DisplayAs for FilterExec {
@@ -81,7 +147,11 @@ impl DisplayAs for FilterExec {
             f,
             "FilterExec [{}]",
             self.predicates.iter().map(|e| format!("{e}")).join(", ")
-        )
+        )?;
+        if let Some(bloom_filter_pushdown) = &self.bloom_filter_pushdown {
+            write!(f, ", bloom_filter_key={}", bloom_filter_pushdown.key_expr)?;
+        }
+        Ok(())
     }
 }
 
@@ -110,10 +180,9 @@ impl ExecutionPlan for FilterExec {
         self: Arc<Self>,
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        Ok(Arc::new(Self::try_new(
-            self.predicates.clone(),
-            children[0].clone(),
-        )?))
+        let mut new_filter = Self::try_new(self.predicates.clone(), children[0].clone())?;
+        new_filter.bloom_filter_pushdown = self.bloom_filter_pushdown.clone();
+        Ok(Arc::new(new_filter))
     }
 
     fn execute(
@@ -121,21 +190,29 @@ impl ExecutionPlan for FilterExec {
         partition: usize,
         context: Arc<TaskContext>,
     ) -> Result<SendableRecordBatchStream> {
-        let batch_size = context.session_config().batch_size();
         let predicates = self.predicates.clone();
+        let bloom_filter_pushdown = self.bloom_filter_pushdown.clone();
         let metrics = BaselineMetrics::new(&self.metrics, partition);
-        let elapsed_compute = metrics.elapsed_compute().clone();
+        let bloom_filter_pruned_rows =
+            MetricBuilder::new(&self.metrics).counter("bloom_filter_pruned_rows", partition);
 
         let input = stat_input(
             InputBatchStatistics::from_metrics_set_and_blaze_conf(&self.metrics, partition)?,
             self.input.execute(partition, context.clone())?,
         )?;
-        let filtered = Box::pin(RecordBatchStreamAdapter::new(
+        // output batches are already coalesced by the selection coalescer
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
             self.schema(),
-            once(execute_filter(input, context, predicates, metrics)).try_flatten(),
-        ));
-        let coalesced = Box::pin(CoalesceStream::new(filtered, batch_size, elapsed_compute));
-        Ok(coalesced)
+            once(execute_filter(
+                input,
+                context,
+                predicates,
+                bloom_filter_pushdown,
+                metrics,
+                bloom_filter_pruned_rows,
+            ))
+            .try_flatten(),
+        )))
     }
 
     fn metrics(&self) -> Option<MetricsSet> {
@@ -176,9 +253,16 @@ async fn execute_filter(
     mut input: SendableRecordBatchStream,
     context: Arc<TaskContext>,
     predicates: Vec<PhysicalExprRef>,
+    bloom_filter_pushdown: Option<BloomFilterPushdown>,
     metrics: BaselineMetrics,
+    bloom_filter_pruned_rows: Count,
 ) -> Result<SendableRecordBatchStream> {
     let cached_exprs_evaluator = CachedExprsEvaluator::try_new(predicates, vec![])?;
+    let mut coalescer = SelectionCoalescer::new(
+        input.schema(),
+        context.session_config().batch_size(),
+        batch_byte_budget()?,
+    );
 
     output_with_sender(
         "Filter",
@@ -187,11 +271,159 @@ async fn execute_filter(
         move |sender| async move {
             while let Some(batch) = input.next().await.transpose()? {
                 let mut timer = metrics.elapsed_compute().timer();
-                let filtered_batch = cached_exprs_evaluator.filter(&batch)?;
+                let batch = match &bloom_filter_pushdown {
+                    Some(bloom_filter_pushdown) => {
+                        let num_rows = batch.num_rows();
+                        let pruned_batch = prune_with_bloom_filter(batch, bloom_filter_pushdown)?;
+                        bloom_filter_pruned_rows.add(num_rows - pruned_batch.num_rows());
+                        pruned_batch
+                    }
+                    None => batch,
+                };
+
+                // selected rows are materialized once they are enough to fill
+                // an output batch
+                let selected = cached_exprs_evaluator.filter_selected(&batch)?;
+                if let Some(filtered_batch) = coalescer.push(selected)? {
+                    metrics.record_output(filtered_batch.num_rows());
+                    sender.send(Ok(filtered_batch), Some(&mut timer)).await;
+                }
+            }
+            if let Some(filtered_batch) = coalescer.flush()? {
                 metrics.record_output(filtered_batch.num_rows());
-                sender.send(Ok(filtered_batch), Some(&mut timer)).await;
+                sender.send(Ok(filtered_batch), None).await;
             }
             Ok(())
         },
     )
 }
+
+fn prune_with_bloom_filter(
+    batch: RecordBatch,
+    bloom_filter_pushdown: &BloomFilterPushdown,
+) -> Result<RecordBatch> {
+    let bloom_filter = match bloom_filter_pushdown.bloom_filter() {
+        Some(bloom_filter) => bloom_filter,
+        None => return Ok(batch), // not injected yet
+    };
+    let keys = bloom_filter_pushdown
+        .key_expr
+        .evaluate(&batch)?
+        .into_array(batch.num_rows());
+    let selected: BooleanArray = as_int64_array(&keys)?
+        .iter()
+        .map(|key| Some(key.map(|key| bloom_filter.might_contain_long(key)) == Some(true)))
+        .collect();
+    Ok(filter_record_batch(&batch, &selected)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::cached_exprs_evaluator::CachedExprsEvaluator;
+    use crate::common::memory_manager::MemManager;
+    use crate::filter_exec::FilterExec;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::cast::as_int64_array;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{binary, col, lit};
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use datafusion_ext_commons::spark_bloom_filter::SparkBloomFilter;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_filter_with_bloom_filter_pushdown() -> Result<()> {
+        MemManager::init(10000);
+        let schema = Arc::new(Schema::new(vec![Field::new("k", DataType::Int64, true)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int64Array::from(vec![
+                Some(1),
+                Some(2),
+                None,
+                Some(3),
+                Some(100),
+                Some(200),
+                Some(300),
+            ]))],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None)?);
+        let predicate = binary(
+            col("k", &schema)?,
+            Operator::Gt,
+            lit(ScalarValue::from(1i64)),
+            &schema,
+        )?;
+        let filter = FilterExec::try_new(vec![predicate], input)?
+            .try_with_bloom_filter_pushdown(col("k", &schema)?)?;
+
+        // no pruning before the bloom filter is injected
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let output = common::collect(filter.execute(0, task_ctx.clone())?).await?;
+        assert_eq!(output.iter().map(|b| b.num_rows()).sum::<usize>(), 5);
+
+        // build side contains keys 1-100
+        let mut bloom_filter = SparkBloomFilter::new(100, 4096);
+        for key in 1..=100 {
+            bloom_filter.put_long(key);
+        }
+        filter.inject_bloom_filter(Arc::new(bloom_filter))?;
+
+        let output = common::collect(filter.execute(0, task_ctx)?).await?;
+        let expected = vec![
+            "+-----+", //
+            "| k   |", "+-----+", "| 2   |", "| 3   |", "| 100 |", "+-----+",
+        ];
+        assert_batches_eq!(expected, &output);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_high_selectivity_without_copying() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("k", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int64Array::from_iter_values(0..100))],
+        )?;
+        let input_values = as_int64_array(batch.column(0))?.values();
+        let filter = |op: Operator, value: i64| -> Result<RecordBatch> {
+            let predicate = binary(col("k", &schema)?, op, lit(value), &schema)?;
+            CachedExprsEvaluator::try_new(vec![predicate], vec![])?.filter(&batch)
+        };
+
+        // 100% selective, the batch is passed through
+        let output = filter(Operator::GtEq, 0)?;
+        assert!(Arc::ptr_eq(output.column(0), batch.column(0)));
+
+        // contiguous rows are sliced without copying
+        let output = filter(Operator::Lt, 95)?;
+        let output_values = as_int64_array(output.column(0))?.values();
+        assert_eq!(output_values.as_ptr(), input_values.as_ptr());
+        assert_eq!(&output_values[..], &input_values[..95]);
+
+        let output = filter(Operator::GtEq, 10)?;
+        let output_values = as_int64_array(output.column(0))?.values();
+        assert_eq!(output_values.as_ptr(), input_values[10..].as_ptr());
+        assert_eq!(&output_values[..], &input_values[10..]);
+
+        // partially selective, falls back to the filter kernel
+        let predicate = binary(
+            binary(col("k", &schema)?, Operator::Modulo, lit(3i64), &schema)?,
+            Operator::NotEq,
+            lit(0i64),
+            &schema,
+        )?;
+        let output = CachedExprsEvaluator::try_new(vec![predicate], vec![])?.filter(&batch)?;
+        assert_eq!(
+            as_int64_array(output.column(0))?.values().to_vec(),
+            (0..100).filter(|k| k % 3 != 0).collect::<Vec<i64>>(),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::array::{Array, ArrayRef};
+use arrow::datatypes::{DataType, Field, FieldRef, Fields, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use datafusion::common::cast::as_struct_array;
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use datafusion_ext_commons::nulls::mask_nulls;
+use futures::{Stream, StreamExt};
+use std::any::Any;
+use std::fmt::Formatter;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Flattens a struct column into its child fields as top-level columns, like
+/// spark's `select("struct.*")`. the child columns take the names specified by
+/// `field_names` and are placed at the position of the struct column.
+///
+/// child arrays are shared without copying, a null parent struct makes all its
+/// children null in the output.
+#[derive(Debug)]
+pub struct FlattenStructExec {
+    input: Arc<dyn ExecutionPlan>,
+    struct_column: usize,
+    field_names: Vec<String>,
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl FlattenStructExec {
+    pub fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        struct_column: usize,
+        field_names: Vec<String>,
+    ) -> Result<Self> {
+        let input_schema = input.schema();
+        let struct_field = input_schema.fields().get(struct_column).ok_or_else(|| {
+            DataFusionError::Plan(format!(
+                "FlattenStructExec: struct column {} out of range",
+                struct_column,
+            ))
+        })?;
+        let child_fields = match struct_field.data_type() {
+            DataType::Struct(child_fields) => child_fields,
+            other => {
+                return Err(DataFusionError::Plan(format!(
+                    "FlattenStructExec: column {} is not a struct: {:?}",
+                    struct_column, other,
+                )))
+            }
+        };
+        if field_names.len() != child_fields.len() {
+            return Err(DataFusionError::Plan(format!(
+                "FlattenStructExec: field_names length not matched with struct fields, \
+                    field_names: {:?}, struct fields: {:?}",
+                field_names, child_fields,
+            )));
+        }
+
+        let flattened_fields = child_fields
+            .iter()
+            .zip(&field_names)
+            .map(|(child_field, name)| {
+                Arc::new(Field::new(
+                    name,
+                    child_field.data_type().clone(),
+                    child_field.is_nullable() || struct_field.is_nullable(),
+                ))
+            })
+            .collect::<Vec<FieldRef>>();
+        let fields = input_schema.fields()[..struct_column]
+            .iter()
+            .cloned()
+            .chain(flattened_fields)
+            .chain(input_schema.fields()[struct_column + 1..].iter().cloned())
+            .collect::<Fields>();
+        let schema = Arc::new(Schema::new(fields));
+
+        Ok(Self {
+            input,
+            struct_column,
+            field_names,
+            schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl DisplayAs for FlattenStructExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "FlattenStructExec(struct_column={}, field_names={:?})",
+            self.struct_column, self.field_names,
+        )
+    }
+}
+
+impl ExecutionPlan for FlattenStructExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::try_new(
+                children[0].clone(),
+                self.struct_column,
+                self.field_names.clone(),
+            )?)),
+            _ => Err(DataFusionError::Internal(
+                "FlattenStructExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let input = self.input.execute(partition, context)?;
+        Ok(Box::pin(FlattenStructStream {
+            input,
+            schema: self.schema(),
+            struct_column: self.struct_column,
+            baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+        }))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+struct FlattenStructStream {
+    input: SendableRecordBatchStream,
+    schema: SchemaRef,
+    struct_column: usize,
+    baseline_metrics: BaselineMetrics,
+}
+
+impl RecordBatchStream for FlattenStructStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for FlattenStructStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.input.poll_next_unpin(cx)? {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(batch)) => {
+                let flattened = flatten_struct(&batch, self.struct_column, self.schema.clone());
+                self.baseline_metrics
+                    .record_poll(Poll::Ready(Some(flattened)))
+            }
+        }
+    }
+}
+
+fn flatten_struct(
+    batch: &RecordBatch,
+    struct_column: usize,
+    schema: SchemaRef,
+) -> Result<RecordBatch> {
+    let struct_array = as_struct_array(batch.column(struct_column))?;
+    let flattened_columns = struct_array
+        .columns()
+        .iter()
+        // propagate nulls of the parent struct into the child
+        .map(|child| mask_nulls(child, struct_array.nulls()))
+        .collect::<Result<Vec<ArrayRef>>>()?;
+
+    let columns = batch.columns()[..struct_column]
+        .iter()
+        .cloned()
+        .chain(flattened_columns)
+        .chain(batch.columns()[struct_column + 1..].iter().cloned())
+        .collect();
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::flatten_struct_exec::FlattenStructExec;
+    use arrow::array::{Array, ArrayRef, Int32Array, StringArray, StructArray};
+    use arrow::buffer::NullBuffer;
+    use arrow::datatypes::{DataType, Field, Fields, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_flatten_struct() -> Result<()> {
+        let struct_fields = Fields::from(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, false),
+        ]);
+        let child_a: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None, Some(3), Some(4)]));
+        let child_b: ArrayRef = Arc::new(StringArray::from(vec!["x", "y", "z", "w"]));
+        let struct_array: ArrayRef = Arc::new(StructArray::new(
+            struct_fields.clone(),
+            vec![child_a.clone(), child_b.clone()],
+            Some(NullBuffer::from(vec![true, true, false, true])),
+        ));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("s", DataType::Struct(struct_fields), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![10, 20, 30, 40])), struct_array],
+        )?;
+
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None)?);
+        let exec =
+            FlattenStructExec::try_new(input, 1, vec!["s_a".to_string(), "s_b".to_string()])?;
+        assert_eq!(exec.schema().fields().len(), 3);
+        assert!(exec.schema().field(2).is_nullable()); // nullable parent
+
+        let session_ctx = SessionContext::new();
+        let output = common::collect(exec.execute(0, session_ctx.task_ctx())?).await?;
+        let expected = vec![
+            "+----+-----+-----+",
+            "| id | s_a | s_b |",
+            "+----+-----+-----+",
+            "| 10 | 1   | x   |",
+            "| 20 |     | y   |",
+            "| 30 |     |     |",
+            "| 40 | 4   | w   |",
+            "+----+-----+-----+",
+        ];
+        assert_batches_eq!(expected, &output);
+
+        // child values are shared without copying
+        let flattened_b = output[0].column(2);
+        assert_eq!(flattened_b.null_count(), 1);
+        assert_eq!(
+            flattened_b.to_data().buffers()[1].as_ptr(),
+            child_b.to_data().buffers()[1].as_ptr(),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::generate::{GeneratedRows, Generator};
+use arrow::array::*;
+use arrow::record_batch::RecordBatch;
+use datafusion::common::Result;
+use datafusion::physical_expr::PhysicalExpr;
+use datafusion_ext_commons::nulls::mask_nulls;
+use itertools::Itertools;
+use std::sync::Arc;
+
+/// Explodes an array of structs into rows, with struct fields as columns. a null
+/// struct element generates a row with all fields null, like spark.
+#[derive(Debug)]
+pub struct InlineArray {
+    child: Arc<dyn PhysicalExpr>,
+}
+
+impl InlineArray {
+    pub fn new(child: Arc<dyn PhysicalExpr>) -> Self {
+        Self { child }
+    }
+}
+
+impl Generator for InlineArray {
+    fn exprs(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        vec![self.child.clone()]
+    }
+
+    fn with_new_exprs(&self, exprs: Vec<Arc<dyn PhysicalExpr>>) -> Result<Arc<dyn Generator>> {
+        Ok(Arc::new(Self {
+            child: exprs[0].clone(),
+        }))
+    }
+
+    fn eval(&self, batch: &RecordBatch) -> Result<GeneratedRows> {
+        let input_array = self.child.evaluate(batch)?.into_array(batch.num_rows());
+        let list = as_list_array(&input_array);
+        let value_offsets = list.value_offsets();
+        let mut orig_row_id_builder = UInt32Builder::new();
+        let mut value_ids_builder = UInt32Builder::new();
+
+        // build row_id and value_id arrays
+        for (orig_row_id, (&start, &end)) in value_offsets.iter().tuple_windows().enumerate() {
+            if list.is_valid(orig_row_id) && start < end {
+                for i in start..end {
+                    orig_row_id_builder.append_value(orig_row_id as u32);
+                    value_ids_builder.append_value(i as u32);
+                }
+            }
+        }
+
+        let orig_row_ids = orig_row_id_builder.finish();
+        let values = arrow::compute::take(list.values(), &value_ids_builder.finish(), None)?;
+        let values = as_struct_array(&values);
+
+        // propagate nulls of struct elements into the fields
+        let cols = values
+            .columns()
+            .iter()
+            .map(|col| mask_nulls(col, values.nulls()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(GeneratedRows { orig_row_ids, cols })
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::generate::{GeneratedRows, Generator};
+use arrow::array::*;
+use arrow::record_batch::RecordBatch;
+use datafusion::common::{DataFusionError, Result};
+use datafusion::physical_expr::PhysicalExpr;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Extracts top-level fields of json objects into string columns, like spark's
+/// json_tuple(json, field1, field2, ...). every input row generates exactly one
+/// row, with all fields null if the json is null or not an object. non-string
+/// values are output as their json text in the input, with whitespaces removed
+/// like spark.
+#[derive(Debug)]
+pub struct JsonTuple {
+    json: Arc<dyn PhysicalExpr>,
+    field_names: Vec<Arc<dyn PhysicalExpr>>,
+}
+
+impl JsonTuple {
+    pub fn new(json: Arc<dyn PhysicalExpr>, field_names: Vec<Arc<dyn PhysicalExpr>>) -> Self {
+        Self { json, field_names }
+    }
+}
+
+impl Generator for JsonTuple {
+    fn exprs(&self) -> Vec<Arc<dyn PhysicalExpr>> {
+        [self.json.clone()]
+            .into_iter()
+            .chain(self.field_names.iter().cloned())
+            .collect()
+    }
+
+    fn with_new_exprs(&self, exprs: Vec<Arc<dyn PhysicalExpr>>) -> Result<Arc<dyn Generator>> {
+        Ok(Arc::new(Self {
+            json: exprs[0].clone(),
+            field_names: exprs[1..].to_vec(),
+        }))
+    }
+
+    fn eval(&self, batch: &RecordBatch) -> Result<GeneratedRows> {
+        let num_rows = batch.num_rows();
+        let json_array = self.json.evaluate(batch)?.into_array(num_rows);
+        let json_strings = as_string_array(&json_array);
+        let field_name_arrays = self
+            .field_names
+            .iter()
+            .map(|field_name| Ok(field_name.evaluate(batch)?.into_array(num_rows)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut builders = (0..self.field_names.len())
+            .map(|_| StringBuilder::new())
+            .collect::<Vec<_>>();
+        for (row_idx, json_string) in json_strings.iter().enumerate() {
+            let json_object = json_string
+                .and_then(|s| serde_json::from_str::<HashMap<String, &RawValue>>(s).ok());
+
+            for (builder, field_names) in builders.iter_mut().zip(&field_name_arrays) {
+                let field_names = as_string_array(field_names);
+                let field_value = json_object.as_ref().and_then(|object| {
+                    field_names
+                        .is_valid(row_idx)
+                        .then(|| object.get(field_names.value(row_idx)))
+                        .flatten()
+                });
+                match field_value.map(|value| value.get()) {
+                    None | Some("null") => builder.append_null(),
+                    Some(value) if value.starts_with('"') => builder.append_value(
+                        serde_json::from_str::<String>(value).map_err(|err| {
+                            DataFusionError::Execution(format!(
+                                "json_tuple: invalid json string: {err}"
+                            ))
+                        })?,
+                    ),
+                    Some(value) => builder.append_value(remove_whitespaces(value)),
+                }
+            }
+        }
+
+        let orig_row_ids = UInt32Array::from_iter_values(0..num_rows as u32);
+        let cols = builders
+            .into_iter()
+            .map(|mut builder| Arc::new(builder.finish()) as ArrayRef)
+            .collect();
+        Ok(GeneratedRows { orig_row_ids, cols })
+    }
+}
+
+/// Removes whitespaces outside of strings in a json text
+fn remove_whitespaces(json: &str) -> String {
+    let mut output = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_ascii_whitespace() {
+            continue;
+        }
+        output.push(c);
+    }
+    output
+}
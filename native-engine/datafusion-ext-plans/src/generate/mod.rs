@@ -13,8 +13,12 @@
 // limitations under the License.
 
 pub mod explode;
+pub mod inline;
+pub mod json_tuple;
 
 use crate::generate::explode::{ExplodeArray, ExplodeMap};
+use crate::generate::inline::InlineArray;
+use crate::generate::json_tuple::JsonTuple;
 
 use arrow::datatypes::{DataType, SchemaRef};
 
@@ -44,6 +48,8 @@ pub struct GeneratedRows {
 pub enum GenerateFunc {
     Explode,
     PosExplode,
+    Inline,
+    JsonTuple,
 }
 
 pub fn create_generator(
@@ -68,5 +74,18 @@ pub fn create_generator(
                 other
             ))),
         },
+        GenerateFunc::Inline => match children[0].data_type(input_schema)? {
+            DataType::List(field) if matches!(field.data_type(), DataType::Struct(_)) => {
+                Ok(Arc::new(InlineArray::new(children[0].clone())))
+            }
+            other => Err(DataFusionError::Plan(format!(
+                "unsupported inline type: {}",
+                other
+            ))),
+        },
+        GenerateFunc::JsonTuple => Ok(Arc::new(JsonTuple::new(
+            children[0].clone(),
+            children[1..].to_vec(),
+        ))),
     }
 }
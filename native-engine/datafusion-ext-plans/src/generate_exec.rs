@@ -300,11 +300,12 @@ mod test {
     use crate::generate::{create_generator, GenerateFunc};
     use crate::generate_exec::GenerateExec;
     use arrow::array::*;
+    use arrow::buffer::{NullBuffer, OffsetBuffer};
     use arrow::datatypes::*;
     use arrow::record_batch::RecordBatch;
     use datafusion::assert_batches_eq;
-    use datafusion::common::Result;
-    use datafusion::physical_expr::expressions::Column;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_expr::expressions::{Column, Literal};
     use datafusion::physical_plan::memory::MemoryExec;
     use datafusion::physical_plan::{common, ExecutionPlan};
     use datafusion::prelude::SessionContext;
@@ -488,4 +489,139 @@ mod test {
         assert_batches_eq!(expected, &batches);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_inline() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        // [{1, x}, null], [{3, z}], [], null
+        let struct_fields = Fields::from(vec![
+            Field::new("x", DataType::Int32, true),
+            Field::new("y", DataType::Utf8, true),
+        ]);
+        let values = StructArray::new(
+            struct_fields.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), Some(2), Some(3)])),
+                Arc::new(StringArray::from(vec![Some("x"), Some("y"), Some("z")])),
+            ],
+            Some(NullBuffer::from(vec![true, false, true])),
+        );
+        let item_field = Arc::new(Field::new("item", DataType::Struct(struct_fields), true));
+        let col_b: ArrayRef = Arc::new(ListArray::new(
+            item_field,
+            OffsetBuffer::new(vec![0, 2, 3, 3, 3].into()),
+            Arc::new(values),
+            Some(NullBuffer::from(vec![true, true, true, false])),
+        ));
+        let col_a: ArrayRef = Arc::new(Int32Array::from(vec![1, 2, 3, 4]));
+        let input_batch = RecordBatch::try_from_iter(vec![("a", col_a), ("b", col_b)])?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![input_batch.clone()]],
+            input_batch.schema(),
+            None,
+        )?);
+
+        let generator = create_generator(
+            &input.schema(),
+            GenerateFunc::Inline,
+            vec![Arc::new(Column::new("b", 1))],
+        )?;
+        let generate = Arc::new(GenerateExec::try_new(
+            input.clone(),
+            generator,
+            vec![Column::new("a", 0)],
+            Arc::new(Schema::new(vec![
+                Field::new("x", DataType::Int32, true),
+                Field::new("y", DataType::Utf8, true),
+            ])),
+            false,
+        )?);
+        let output = generate.execute(0, task_ctx.clone())?;
+        let batches = common::collect(output).await?;
+        let expected = vec![
+            "+---+---+---+",
+            "| a | x | y |",
+            "+---+---+---+",
+            "| 1 | 1 | x |",
+            "| 1 |   |   |",
+            "| 2 | 3 | z |",
+            "+---+---+---+",
+        ];
+        assert_batches_eq!(expected, &batches);
+
+        // inline (outer)
+        let generate = generate.with_outer(true);
+        let output = generate.execute(0, task_ctx.clone())?;
+        let batches = common::collect(output).await?;
+        let expected = vec![
+            "+---+---+---+",
+            "| a | x | y |",
+            "+---+---+---+",
+            "| 1 | 1 | x |",
+            "| 1 |   |   |",
+            "| 2 | 3 | z |",
+            "| 3 |   |   |",
+            "| 4 |   |   |",
+            "+---+---+---+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_json_tuple() -> Result<()> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        let col_a: ArrayRef = Arc::new(Int32Array::from(vec![1, 2, 3, 4]));
+        let col_b: ArrayRef = Arc::new(StringArray::from(vec![
+            Some(r#"{"x": "abc", "y": 1.5, "z": {"k": [1, null], "a": " b "}}"#),
+            Some(r#"{"x": null, "y": true}"#),
+            Some(r#"[1, 2]"#),
+            None,
+        ]));
+        let input_batch = RecordBatch::try_from_iter(vec![("a", col_a), ("b", col_b)])?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![input_batch.clone()]],
+            input_batch.schema(),
+            None,
+        )?);
+
+        let generator = create_generator(
+            &input.schema(),
+            GenerateFunc::JsonTuple,
+            vec![
+                Arc::new(Column::new("b", 1)),
+                Arc::new(Literal::new(ScalarValue::from("x"))),
+                Arc::new(Literal::new(ScalarValue::from("y"))),
+                Arc::new(Literal::new(ScalarValue::from("z"))),
+            ],
+        )?;
+        let generate = Arc::new(GenerateExec::try_new(
+            input.clone(),
+            generator,
+            vec![Column::new("a", 0)],
+            Arc::new(Schema::new(vec![
+                Field::new("c0", DataType::Utf8, true),
+                Field::new("c1", DataType::Utf8, true),
+                Field::new("c2", DataType::Utf8, true),
+            ])),
+            false,
+        )?);
+        let output = generate.execute(0, task_ctx.clone())?;
+        let batches = common::collect(output).await?;
+        let expected = vec![
+            "+---+-----+------+--------------------------+",
+            "| a | c0  | c1   | c2                       |",
+            "+---+-----+------+--------------------------+",
+            "| 1 | abc | 1.5  | {\"k\":[1,null],\"a\":\" b \"} |",
+            "| 2 |     | true |                          |",
+            "| 3 |     |      |                          |",
+            "| 4 |     |      |                          |",
+            "+---+-----+------+--------------------------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
 }
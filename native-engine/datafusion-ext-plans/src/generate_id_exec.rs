@@ -0,0 +1,291 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::array::{ArrayRef, Int64Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream,
+};
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use std::any::Any;
+use std::fmt::Formatter;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Appends a dense and globally unique Int64 id column. unlike
+/// `monotonically_increasing_id`, ids are contiguous across partitions: rows of
+/// partition `i` are numbered from the i-th partition offset, which is the
+/// total row count of the preceding partitions.
+///
+/// the partition offsets are computed from the upstream partition row counts
+/// and supplied at execute time with `GenerateIdExec::set_partition_offsets()`.
+#[derive(Debug)]
+pub struct GenerateIdExec {
+    input: Arc<dyn ExecutionPlan>,
+    id_column_name: String,
+    schema: SchemaRef,
+    partition_offsets: Arc<Mutex<Option<Vec<i64>>>>,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl GenerateIdExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>, id_column_name: String) -> Self {
+        let mut fields = input.schema().fields().iter().cloned().collect::<Vec<_>>();
+        fields.push(Arc::new(Field::new(
+            &id_column_name,
+            DataType::Int64,
+            false,
+        )));
+        let schema = Arc::new(Schema::new(fields));
+        Self {
+            input,
+            id_column_name,
+            schema,
+            partition_offsets: Arc::default(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+
+    /// Sets the starting id of each partition, must be called before executing
+    pub fn set_partition_offsets(&self, partition_offsets: Vec<i64>) -> Result<()> {
+        let num_partitions = self.output_partitioning().partition_count();
+        if partition_offsets.len() != num_partitions {
+            return Err(DataFusionError::Execution(format!(
+                "GenerateIdExec expects {} partition offsets, got {}",
+                num_partitions,
+                partition_offsets.len(),
+            )));
+        }
+        *self.partition_offsets.lock() = Some(partition_offsets);
+        Ok(())
+    }
+
+    /// Computes the partition offsets from the row count of each partition
+    pub fn partition_offsets_from_row_counts(partition_row_counts: &[u64]) -> Vec<i64> {
+        partition_row_counts
+            .iter()
+            .scan(0i64, |offset, &num_rows| {
+                let partition_offset = *offset;
+                *offset += num_rows as i64;
+                Some(partition_offset)
+            })
+            .collect()
+    }
+}
+
+impl DisplayAs for GenerateIdExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "GenerateIdExec(id_column_name={})", self.id_column_name)
+    }
+}
+
+impl ExecutionPlan for GenerateIdExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        self.input.output_ordering()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => {
+                let mut new_exec = Self::new(children[0].clone(), self.id_column_name.clone());
+                new_exec.partition_offsets = self.partition_offsets.clone();
+                Ok(Arc::new(new_exec))
+            }
+            _ => Err(DataFusionError::Internal(
+                "GenerateIdExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let partition_offset = self
+            .partition_offsets
+            .lock()
+            .as_ref()
+            .and_then(|partition_offsets| partition_offsets.get(partition).cloned())
+            .ok_or_else(|| {
+                DataFusionError::Execution(format!(
+                    "GenerateIdExec partition offset of partition {} is not set",
+                    partition
+                ))
+            })?;
+
+        let input_stream = self.input.execute(partition, context)?;
+        Ok(Box::pin(GenerateIdStream {
+            input_stream,
+            schema: self.schema(),
+            next_id: partition_offset,
+            baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+        }))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+struct GenerateIdStream {
+    input_stream: SendableRecordBatchStream,
+    schema: SchemaRef,
+    next_id: i64,
+    baseline_metrics: BaselineMetrics,
+}
+
+impl RecordBatchStream for GenerateIdStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for GenerateIdStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.input_stream.poll_next_unpin(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(Some(Ok(batch))) => {
+                let start_id = self.next_id;
+                self.next_id += batch.num_rows() as i64;
+
+                let elapsed_compute = self.baseline_metrics.elapsed_compute().clone();
+                let timer = elapsed_compute.timer();
+
+                let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(start_id..self.next_id));
+                let mut columns = batch.columns().to_vec();
+                columns.push(ids);
+                let output_batch = RecordBatch::try_new(self.schema.clone(), columns)
+                    .map_err(DataFusionError::from);
+                drop(timer);
+                self.baseline_metrics
+                    .record_poll(Poll::Ready(Some(output_batch)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::generate_id_exec::GenerateIdExec;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::cast::as_int64_array;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_generate_id() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Int32, false)]));
+        let build_batch = |values: Vec<i32>| {
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))])
+        };
+
+        // three partitions with 3, 0 and 4 rows
+        let partitions = vec![
+            vec![build_batch(vec![1, 2])?, build_batch(vec![3])?],
+            vec![],
+            vec![build_batch(vec![4, 5, 6, 7])?],
+        ];
+        let input = Arc::new(MemoryExec::try_new(&partitions, schema.clone(), None)?);
+        let exec = GenerateIdExec::new(input, "id".to_string());
+
+        let partition_offsets = GenerateIdExec::partition_offsets_from_row_counts(&[3, 0, 4]);
+        assert_eq!(partition_offsets, vec![0, 3, 3]);
+        exec.set_partition_offsets(partition_offsets)?;
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let mut output = vec![];
+        for partition in 0..3 {
+            output.extend(common::collect(exec.execute(partition, task_ctx.clone())?).await?);
+        }
+        let expected = vec![
+            "+---+----+",
+            "| v | id |",
+            "+---+----+",
+            "| 1 | 0  |",
+            "| 2 | 1  |",
+            "| 3 | 2  |",
+            "| 4 | 3  |",
+            "| 5 | 4  |",
+            "| 6 | 5  |",
+            "| 7 | 6  |",
+            "+---+----+",
+        ];
+        assert_batches_eq!(expected, &output);
+
+        // ids are contiguous and non-overlapping across partitions
+        let mut ids = output
+            .iter()
+            .flat_map(|batch| as_int64_array(batch.column(1)).unwrap().values().to_vec())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, (0..7).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_id_without_offsets() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Int32, false)]));
+        let input = Arc::new(MemoryExec::try_new(&[vec![]], schema, None)?);
+        let exec = GenerateIdExec::new(input, "id".to_string());
+
+        let session_ctx = SessionContext::new();
+        let err = exec.execute(0, session_ctx.task_ctx()).err().unwrap();
+        assert!(err.to_string().contains("is not set"));
+        assert!(exec.set_partition_offsets(vec![0, 1]).is_err());
+        Ok(())
+    }
+}
@@ -27,7 +27,8 @@ use datafusion::physical_plan::Partitioning::UnknownPartitioning;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use datafusion::physical_plan::Statistics;
 use datafusion::physical_plan::{DisplayAs, DisplayFormatType};
-use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
+use datafusion_ext_commons::metrics::{decompress_time, io_time};
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
 use datafusion_ext_commons::streams::ipc_stream::{IpcReadMode, IpcReaderStream};
 use jni::objects::JObject;
 use std::any::Any;
@@ -119,20 +120,43 @@ impl ExecutionPlan for IpcReaderExec {
 
         let schema = self.schema.clone();
         let mode = self.mode;
-        let ipc_stream = Box::pin(IpcReaderStream::new(
+        let mut ipc_stream = IpcReaderStream::new(
             schema,
             segments,
             mode,
             baseline_metrics,
             size_counter,
-        ));
-        Ok(Box::pin(CoalesceStream::new(
-            ipc_stream,
-            context.session_config().batch_size(),
-            BaselineMetrics::new(&self.metrics, partition)
-                .elapsed_compute()
-                .clone(),
-        )))
+            self.ipc_provider_resource_id.clone(),
+            partition,
+        )
+        .with_io_metrics(
+            io_time(&self.metrics, partition),
+            decompress_time(&self.metrics, partition),
+        );
+        if let IpcReadMode::ChannelAndFileSegment = mode {
+            let prefetch_num_segments =
+                jni_call_static!(BlazeConf.shuffleReadPrefetchSegments() -> i32)?;
+            let prefetch_mem_bytes =
+                jni_call_static!(BlazeConf.shuffleReadPrefetchMemThreshold() -> i32)?;
+            let mmap_enabled = jni_call_static!(BlazeConf.shuffleReadMmapEnabled() -> bool)?;
+            ipc_stream = ipc_stream
+                .with_prefetch(
+                    prefetch_num_segments.max(0) as usize,
+                    prefetch_mem_bytes.max(0) as usize,
+                )
+                .with_mmap(mmap_enabled);
+        }
+        let ipc_stream = ipc_stream.into_blocking_read();
+        Ok(Box::pin(
+            CoalesceStream::new(
+                ipc_stream,
+                context.session_config().batch_size(),
+                BaselineMetrics::new(&self.metrics, partition)
+                    .elapsed_compute()
+                    .clone(),
+            )
+            .with_byte_budget(batch_byte_budget()?),
+        ))
     }
 
     fn metrics(&self) -> Option<MetricsSet> {
@@ -16,6 +16,7 @@ use arrow::datatypes::SchemaRef;
 use arrow::error::ArrowError;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
+use blaze_jni_bridge::jni_blocking::spawn_jni_blocking;
 use blaze_jni_bridge::{
     jni_call, jni_call_static, jni_new_direct_byte_buffer, jni_new_global_ref, jni_new_string,
 };
@@ -31,7 +32,8 @@ use datafusion::physical_plan::{
     Statistics,
 };
 use datafusion_ext_commons::concat_batches;
-use datafusion_ext_commons::io::write_one_batch;
+use datafusion_ext_commons::io::{write_one_batch_with_codec, IpcCompressionCodec};
+use datafusion_ext_commons::jni_buffer::acquire_jni_buffer;
 
 use futures::StreamExt;
 use futures::TryFutureExt;
@@ -46,6 +48,7 @@ use std::sync::Arc;
 pub struct IpcWriterExec {
     input: Arc<dyn ExecutionPlan>,
     ipc_consumer_resource_id: String,
+    compression_codec: IpcCompressionCodec,
     metrics: ExecutionPlanMetricsSet,
 }
 
@@ -54,9 +57,18 @@ impl IpcWriterExec {
         Self {
             input,
             ipc_consumer_resource_id,
+            compression_codec: IpcCompressionCodec::default(),
             metrics: ExecutionPlanMetricsSet::new(),
         }
     }
+
+    /// Sets the codec for compressing the ipc data
+    pub fn with_compression_codec(self, compression_codec: IpcCompressionCodec) -> Self {
+        Self {
+            compression_codec,
+            ..self
+        }
+    }
 }
 
 impl DisplayAs for IpcWriterExec {
@@ -96,10 +108,10 @@ impl ExecutionPlan for IpcWriterExec {
                 "IpcWriterExec expects one children".to_string(),
             ));
         }
-        Ok(Arc::new(IpcWriterExec::new(
-            self.input.clone(),
-            self.ipc_consumer_resource_id.clone(),
-        )))
+        Ok(Arc::new(
+            IpcWriterExec::new(self.input.clone(), self.ipc_consumer_resource_id.clone())
+                .with_compression_codec(self.compression_codec),
+        ))
     }
 
     fn execute(
@@ -122,6 +134,7 @@ impl ExecutionPlan for IpcWriterExec {
                     input,
                     context.session_config().batch_size(),
                     ipc_consumer,
+                    self.compression_codec,
                     baseline_metrics,
                 )
                 .map_err(|e| ArrowError::ExternalError(Box::new(e))),
@@ -143,11 +156,13 @@ pub async fn write_ipc(
     mut input: SendableRecordBatchStream,
     batch_size: usize,
     ipc_consumer: GlobalRef,
+    compression_codec: IpcCompressionCodec,
     metrics: BaselineMetrics,
 ) -> Result<SendableRecordBatchStream> {
     let schema = input.schema();
     let mut batches: Vec<RecordBatch> = vec![];
     let mut num_rows = 0;
+    let mut jni_buffer = acquire_jni_buffer()?;
 
     macro_rules! flush_batches {
         () => {{
@@ -158,18 +173,32 @@ pub async fn write_ipc(
             num_rows = 0;
 
             let mut buffer = vec![];
-            write_one_batch(
+            write_one_batch_with_codec(
                 &batch,
                 &mut Cursor::new(&mut buffer),
-                true,
+                Some(compression_codec),
                 None,
             )?;
             drop(timer);
 
-            let buf = jni_new_direct_byte_buffer!(&buffer)?;
-            let _consumed = jni_call!(
-                ScalaFunction1(ipc_consumer.as_obj()).apply(buf.as_obj()) -> JObject
-            )?;
+            // consuming blocks on jvm side, so it is called on a blocking thread
+            let ipc_consumer = ipc_consumer.clone();
+            jni_buffer = spawn_jni_blocking(move || {
+                // small batches are passed in the reusable jni buffer
+                if buffer.len() <= jni_buffer.capacity() {
+                    jni_buffer.write_to_jvm(&buffer)?;
+                    let _consumed = jni_call!(
+                        ScalaFunction1(ipc_consumer.as_obj()).apply(jni_buffer.as_obj()) -> JObject
+                    )?;
+                } else {
+                    let buf = jni_new_direct_byte_buffer!(&buffer)?;
+                    let _consumed = jni_call!(
+                        ScalaFunction1(ipc_consumer.as_obj()).apply(buf.as_obj()) -> JObject
+                    )?;
+                }
+                Ok(jni_buffer)
+            })
+            .await?;
         }}
     }
 
@@ -17,27 +17,40 @@
 
 pub mod agg;
 pub mod agg_exec;
+pub mod batch_memory_guard_exec;
+pub mod broadcast_hash_join_exec;
 pub mod broadcast_join_exec;
 pub mod broadcast_nested_loop_join_exec;
+pub mod collect_limit_exec;
 pub mod common;
+pub mod csv_scan_exec;
 pub mod debug_exec;
+pub mod decimal_repr_exec;
 pub mod empty_partitions_exec;
 pub mod expand_exec;
 pub mod ffi_reader_exec;
 pub mod filter_exec;
+pub mod flatten_struct_exec;
 pub mod generate;
 pub mod generate_exec;
+pub mod generate_id_exec;
 pub mod ipc_reader_exec;
 pub mod ipc_writer_exec;
 pub mod limit_exec;
 pub mod parquet_exec;
 pub mod parquet_sink_exec;
+pub mod partition_by_column_exec;
 pub mod project_exec;
 pub mod rename_columns_exec;
+pub mod reservoir_sample_exec;
 pub mod rss_shuffle_writer_exec;
 mod shuffle;
+pub mod shuffled_hash_join_exec;
 pub mod shuffle_writer_exec;
 pub mod sort_exec;
 pub mod sort_merge_join_exec;
+pub mod topk_exec;
+pub mod tracing_exec;
+pub mod union_exec;
 pub mod window;
 pub mod window_exec;
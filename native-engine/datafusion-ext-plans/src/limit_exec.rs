@@ -15,10 +15,13 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+/// Skips the first `offset` rows of each partition, then takes at most `limit`
+/// of the remaining rows.
 #[derive(Debug)]
 pub struct LimitExec {
     input: Arc<dyn ExecutionPlan>,
     limit: u64,
+    offset: u64,
     pub metrics: ExecutionPlanMetricsSet,
 }
 
@@ -27,14 +30,31 @@ impl LimitExec {
         Self {
             input,
             limit,
+            offset: 0,
             metrics: ExecutionPlanMetricsSet::new(),
         }
     }
+
+    pub fn with_offset(self, offset: u64) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
 }
 
 impl DisplayAs for LimitExec {
     fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
-        write!(f, "LimitExec(limit={})", self.limit)
+        if self.offset > 0 {
+            write!(f, "LimitExec(limit={}, offset={})", self.limit, self.offset)
+        } else {
+            write!(f, "LimitExec(limit={})", self.limit)
+        }
     }
 }
 
@@ -64,7 +84,9 @@ impl ExecutionPlan for LimitExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(Self::new(children[0].clone(), self.limit))),
+            1 => Ok(Arc::new(
+                Self::new(children[0].clone(), self.limit).with_offset(self.offset),
+            )),
             _ => Err(DataFusionError::Internal(
                 "LimitExec wrong number of children".to_string(),
             )),
@@ -81,6 +103,7 @@ impl ExecutionPlan for LimitExec {
             input_stream,
             limit: self.limit,
             cur: 0,
+            num_rows_to_skip: self.offset,
             baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
         }))
     }
@@ -94,6 +117,7 @@ struct LimitStream {
     input_stream: SendableRecordBatchStream,
     limit: u64,
     cur: u64,
+    num_rows_to_skip: u64,
     baseline_metrics: BaselineMetrics,
 }
 
@@ -112,21 +136,33 @@ impl Stream for LimitStream {
             return Poll::Ready(None);
         }
 
-        match self.input_stream.poll_next_unpin(cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
-            Poll::Ready(Some(Ok(batch))) => {
-                let batch = if batch.num_rows() <= rest as usize {
-                    self.cur += batch.num_rows() as u64;
-                    batch
-                } else {
-                    self.cur += rest;
-                    batch.slice(0, rest as usize)
-                };
-                self.baseline_metrics
-                    .record_poll(Poll::Ready(Some(Ok(batch))))
-            }
+        loop {
+            return match self.input_stream.poll_next_unpin(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+                Poll::Ready(Some(Ok(mut batch))) => {
+                    // skip offset rows, which may span multiple batches
+                    if self.num_rows_to_skip > 0 {
+                        let num_skipped = batch.num_rows().min(self.num_rows_to_skip as usize);
+                        self.num_rows_to_skip -= num_skipped as u64;
+                        batch = batch.slice(num_skipped, batch.num_rows() - num_skipped);
+                        if batch.num_rows() == 0 {
+                            continue;
+                        }
+                    }
+
+                    let batch = if batch.num_rows() <= rest as usize {
+                        self.cur += batch.num_rows() as u64;
+                        batch
+                    } else {
+                        self.cur += rest;
+                        batch.slice(0, rest as usize)
+                    };
+                    self.baseline_metrics
+                        .record_poll(Poll::Ready(Some(Ok(batch))))
+                }
+            };
         }
     }
 }
@@ -141,7 +177,7 @@ mod test {
     use datafusion::assert_batches_eq;
     use datafusion::common::Result;
     use datafusion::physical_plan::memory::MemoryExec;
-    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::physical_plan::{common, displayable, ExecutionPlan};
     use datafusion::prelude::SessionContext;
     use std::sync::Arc;
 
@@ -202,4 +238,40 @@ mod test {
         assert_batches_eq!(expected, &batches);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_limit_exec_with_offset() -> Result<()> {
+        MemManager::init(10000);
+        let batch = build_table_i32(
+            ("a", &vec![9, 8, 7, 6, 5, 4, 3, 2, 1, 0]),
+            ("b", &vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]),
+            ("c", &vec![5, 6, 7, 8, 9, 0, 1, 2, 3, 4]),
+        );
+        let schema = batch.schema();
+
+        // the skipped rows span multiple batches
+        let batches = (0..5).map(|i| batch.slice(i * 2, 2)).collect::<Vec<_>>();
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None)?);
+        let limit_exec = LimitExec::new(input, 3_u64).with_offset(5);
+        assert_eq!(
+            displayable(&limit_exec).one_line().to_string().trim(),
+            "LimitExec(limit=3, offset=5)",
+        );
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let output = limit_exec.execute(0, task_ctx).unwrap();
+        let batches = common::collect(output).await?;
+
+        let expected = vec![
+            "+---+---+---+",
+            "| a | b | c |",
+            "+---+---+---+",
+            "| 4 | 5 | 0 |",
+            "| 3 | 6 | 1 |",
+            "| 2 | 7 | 2 |",
+            "+---+---+---+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
 }
@@ -19,14 +19,15 @@
 
 use fmt::Debug;
 use std::any::Any;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Formatter;
 use std::ops::Range;
 use std::sync::Arc;
 
-use arrow::array::ArrayRef;
-use arrow::datatypes::{DataType, SchemaRef};
-use datafusion::common::DataFusionError;
+use arrow::array::{Array, ArrayRef};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::{DataFusionError, ScalarValue};
 use datafusion::datasource::physical_plan::parquet::page_filter::PagePruningPredicate;
 use datafusion::datasource::physical_plan::parquet::ParquetOpener;
 use datafusion::datasource::physical_plan::{
@@ -36,9 +37,9 @@ use datafusion::parquet::arrow::async_reader::{fetch_parquet_metadata, AsyncFile
 use datafusion::parquet::errors::ParquetError;
 use datafusion::parquet::file::metadata::ParquetMetaData;
 use datafusion::physical_optimizer::pruning::PruningPredicate;
-use datafusion::physical_plan::metrics::{BaselineMetrics, MetricValue, Time};
+use datafusion::physical_plan::metrics::{BaselineMetrics, Count};
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
-use datafusion::physical_plan::{DisplayAs, Metric, PhysicalExpr, RecordBatchStream};
+use datafusion::physical_plan::{DisplayAs, PhysicalExpr, RecordBatchStream};
 use datafusion::{
     error::Result,
     execution::context::TaskContext,
@@ -55,9 +56,12 @@ use object_store::ObjectMeta;
 
 use base64::prelude::BASE64_URL_SAFE_NO_PAD;
 use base64::Engine;
-use blaze_jni_bridge::{jni_call_static, jni_new_global_ref, jni_new_string};
+use blaze_jni_bridge::{jni_call, jni_call_static, jni_new_global_ref, jni_new_string};
 use bytes::Bytes;
 use datafusion_ext_commons::hadoop_fs::{FsDataInputStream, FsProvider};
+use datafusion_ext_commons::metrics::io_time;
+use datafusion_ext_commons::streams::ffi_stream::FFIReaderStream;
+use jni::objects::JObject;
 use once_cell::sync::OnceCell;
 
 use crate::common::output::output_with_sender;
@@ -70,11 +74,21 @@ fn schema_adapter_cast_column(
     datafusion_ext_commons::cast::cast_scan_input_array(col.as_ref(), data_type)
 }
 
+/// Dynamic partition pruning filter of a parquet scan. the key set is
+/// provided by the JVM side at execution time, files whose value of the
+/// partition column is not in the key set are skipped.
+#[derive(Debug, Clone)]
+pub struct DynamicPruningFilter {
+    pub partition_column_index: usize,
+    pub key_set_provider_resource_id: String,
+}
+
 /// Execution plan for scanning one or more Parquet partitions
 #[derive(Debug, Clone)]
 pub struct ParquetExec {
     fs_resource_id: String,
     base_config: FileScanConfig,
+    dynamic_pruning_filters: Vec<DynamicPruningFilter>,
     projected_statistics: Statistics,
     projected_schema: SchemaRef,
     projected_output_ordering: Vec<Vec<PhysicalSortExpr>>,
@@ -90,6 +104,7 @@ impl ParquetExec {
         base_config: FileScanConfig,
         fs_resource_id: String,
         predicate: Option<Arc<dyn PhysicalExpr>>,
+        dynamic_pruning_filters: Vec<DynamicPruningFilter>,
     ) -> Self {
         let metrics = ExecutionPlanMetricsSet::new();
         let predicate_creation_errors =
@@ -127,6 +142,7 @@ impl ParquetExec {
         Self {
             fs_resource_id,
             base_config,
+            dynamic_pruning_filters,
             projected_schema,
             projected_statistics,
             projected_output_ordering,
@@ -205,15 +221,7 @@ impl ExecutionPlan for ParquetExec {
         let baseline_metrics = BaselineMetrics::new(&self.metrics, partition_index);
         let timer = baseline_metrics.elapsed_compute().timer();
 
-        let io_time = Time::default();
-        let io_time_metric = Arc::new(Metric::new(
-            MetricValue::Time {
-                name: "io_time".into(),
-                time: io_time.clone(),
-            },
-            Some(partition_index),
-        ));
-        self.metrics.register(io_time_metric);
+        let io_time = io_time(&self.metrics, partition_index);
 
         // get fs object from jni bridge resource
         let resource_id = jni_new_string!(&self.fs_resource_id)?;
@@ -241,20 +249,54 @@ impl ExecutionPlan for ParquetExec {
             reorder_filters: false,
             enable_page_index: false,
         };
+        let key_set_streams = self
+            .dynamic_pruning_filters
+            .iter()
+            .map(|filter| {
+                let key_set_stream = self.create_key_set_stream(filter, partition_index)?;
+                Ok((filter.partition_column_index, key_set_stream))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let files_pruned =
+            MetricBuilder::new(&self.metrics).counter("files_pruned", partition_index);
+        let ignore_corrupted_files = jni_call_static!(BlazeConf.ignoreCorruptedFiles() -> bool)?;
         drop(timer);
 
         let baseline_metrics = BaselineMetrics::new(&self.metrics, partition_index);
         let elapsed_compute = baseline_metrics.elapsed_compute().clone();
-        let mut file_stream =
-            FileStream::new(&self.base_config, partition_index, opener, &self.metrics)?;
-        if jni_call_static!(BlazeConf.ignoreCorruptedFiles() -> bool)? {
-            file_stream = file_stream.with_on_error(OnError::Skip);
-        }
-        let mut stream = Box::pin(file_stream);
+        let mut base_config = self.base_config.clone();
+        let metrics = self.metrics.clone();
 
         Ok(Box::pin(RecordBatchStreamAdapter::new(
             self.schema(),
             once(async move {
+                // prune files with the key sets of dynamic partition pruning,
+                // which are available only at execution time
+                for (partition_column_index, mut key_set_stream) in key_set_streams {
+                    let mut key_set = HashSet::new();
+                    while let Some(batch) = key_set_stream.next().await.transpose()? {
+                        let keys = batch.column(0);
+                        for i in 0..keys.len() {
+                            if keys.is_valid(i) {
+                                key_set.insert(ScalarValue::try_from_array(keys, i)?);
+                            }
+                        }
+                    }
+                    let file_group = &mut base_config.file_groups[partition_index];
+                    let num_files = file_group.len();
+                    file_group.retain(|file| {
+                        let value = &file.partition_values[partition_column_index];
+                        !value.is_null() && key_set.contains(value)
+                    });
+                    files_pruned.add(num_files - file_group.len());
+                }
+
+                let mut file_stream =
+                    FileStream::new(&base_config, partition_index, opener, &metrics)?;
+                if ignore_corrupted_files {
+                    file_stream = file_stream.with_on_error(OnError::Skip);
+                }
+                let mut stream = Box::pin(file_stream);
                 output_with_sender(
                     "ParquetScan",
                     context,
@@ -281,6 +323,34 @@ impl ExecutionPlan for ParquetExec {
     }
 }
 
+impl ParquetExec {
+    fn create_key_set_stream(
+        &self,
+        filter: &DynamicPruningFilter,
+        partition_index: usize,
+    ) -> Result<FFIReaderStream> {
+        let (name, data_type) =
+            &self.base_config.table_partition_cols[filter.partition_column_index];
+        let key_set_schema = Arc::new(Schema::new(vec![Field::new(name, data_type.clone(), true)]));
+
+        let resource_id = jni_new_string!(&filter.key_set_provider_resource_id)?;
+        let export_iter_provider =
+            jni_call_static!(JniBridge.getResource(resource_id.as_obj()) -> JObject)?;
+        let export_iter_local =
+            jni_call!(ScalaFunction0(export_iter_provider.as_obj()).apply() -> JObject)?;
+        let export_iter = jni_new_global_ref!(export_iter_local.as_obj())?;
+
+        // key sets are not counted into the scan's output metrics
+        let key_set_metrics = ExecutionPlanMetricsSet::new();
+        Ok(FFIReaderStream::new(
+            key_set_schema,
+            export_iter,
+            BaselineMetrics::new(&key_set_metrics, partition_index),
+            Count::new(),
+        ))
+    }
+}
+
 #[derive(Clone)]
 pub struct FsReaderFactory {
     fs_provider: Arc<FsProvider>,
@@ -0,0 +1,550 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
+use crate::common::onheap_spill::{try_new_spill, Spill};
+use crate::common::output::output_with_sender;
+use crate::common::BatchTaker;
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use datafusion::common::cast::as_int32_array;
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+};
+use datafusion_ext_commons::io::read_one_batch;
+use datafusion_ext_commons::spill::SpillManager;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::sync::{Arc, Weak};
+use tokio::sync::Notify;
+
+/// Routing tasks are aborted when dropped, so they are shared by all output
+/// partitions and live until the last output partition is dropped.
+type RoutingStreams = Arc<Mutex<Vec<SendableRecordBatchStream>>>;
+
+/// Routes each row to the output partition given by a precomputed `Int32`
+/// partition id column, without rehashing the partition keys.
+///
+/// all input partitions are read once when the first output partition is
+/// executed. routing never waits for the output partitions, routed batches are
+/// buffered per output partition until taken, so an output partition can be
+/// consumed alone. buffered batches are reported to the memory manager and
+/// spilled when memory is insufficient.
+pub struct PartitionByColumnExec {
+    input: Arc<dyn ExecutionPlan>,
+    partition_id_column: usize,
+    num_partitions: usize,
+    routed: Arc<Mutex<Option<Vec<Option<(Arc<RoutedBuffers>, RoutingStreams)>>>>>,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl PartitionByColumnExec {
+    pub fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        partition_id_column: usize,
+        num_partitions: usize,
+    ) -> Result<Self> {
+        let input_schema = input.schema();
+        if partition_id_column >= input_schema.fields().len()
+            || input_schema.field(partition_id_column).data_type() != &DataType::Int32
+        {
+            return Err(DataFusionError::Plan(format!(
+                "PartitionByColumnExec: column {} is not an Int32 partition id column",
+                partition_id_column,
+            )));
+        }
+        Ok(Self {
+            input,
+            partition_id_column,
+            num_partitions,
+            routed: Arc::default(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+
+    fn start_routing(
+        &self,
+        context: Arc<TaskContext>,
+    ) -> Result<Vec<Option<(Arc<RoutedBuffers>, RoutingStreams)>>> {
+        let num_input_partitions = self.input.output_partitioning().partition_count();
+        let routed_buffers = Arc::new(RoutedBuffers {
+            name: "PartitionByColumn.RoutedBuffers".to_string(),
+            mem_consumer_info: None,
+            schema: self.schema(),
+            spill_manager: SpillManager::get(&context),
+            state: Mutex::new(RoutedState {
+                partitions: (0..self.num_partitions)
+                    .map(|_| Default::default())
+                    .collect(),
+                mem_used: 0,
+                num_routing: num_input_partitions,
+                error: None,
+            }),
+            routed: Notify::new(),
+        });
+        MemManager::register_consumer(routed_buffers.clone(), true);
+        let routing_streams = RoutingStreams::default();
+
+        for input_partition in 0..num_input_partitions {
+            let mut input = self.input.execute(input_partition, context.clone())?;
+            let routed_buffers = routed_buffers.clone();
+            let partition_id_column = self.partition_id_column;
+            let num_partitions = self.num_partitions;
+
+            // routing tasks output nothing, routed batches and errors are
+            // buffered for the output partitions
+            let routing_stream = output_with_sender(
+                "PartitionByColumn.Routing",
+                context.clone(),
+                Arc::new(Schema::empty()),
+                move |_| async move {
+                    let result: Result<()> = async {
+                        while let Some(batch) = input.next().await.transpose()? {
+                            let routed = route_batch(&batch, partition_id_column, num_partitions)?;
+                            let mem_used = routed_buffers.push(routed);
+                            routed_buffers.routed.notify_waiters();
+                            routed_buffers.update_mem_used(mem_used).await?;
+                        }
+                        Ok(())
+                    }
+                    .await;
+                    routed_buffers.finish_routing(result);
+                    Ok(())
+                },
+            )?;
+            routing_streams.lock().push(routing_stream);
+        }
+        Ok((0..self.num_partitions)
+            .map(|_| Some((routed_buffers.clone(), routing_streams.clone())))
+            .collect())
+    }
+}
+
+/// Batches routed to the output partitions but not yet taken
+struct RoutedBuffers {
+    name: String,
+    mem_consumer_info: Option<Weak<MemConsumerInfo>>,
+    schema: SchemaRef,
+    spill_manager: Arc<SpillManager>,
+    state: Mutex<RoutedState>,
+    routed: Notify,
+}
+
+struct RoutedState {
+    partitions: Vec<RoutedPartition>,
+    mem_used: usize,
+    num_routing: usize, // number of routing tasks not yet finished
+    error: Option<Arc<DataFusionError>>,
+}
+
+#[derive(Default)]
+struct RoutedPartition {
+    batches: Vec<RecordBatch>,
+    spills: Vec<Box<dyn Spill>>,
+}
+
+enum Routed {
+    Batches(Vec<RecordBatch>, usize), // taken batches and memory used after taking
+    Spill(Box<dyn Spill>),
+    Pending,
+    Finished,
+}
+
+impl RoutedBuffers {
+    /// Buffers routed batches, returns memory used after buffering
+    fn push(&self, routed: Vec<(usize, RecordBatch)>) -> usize {
+        let mut state = self.state.lock();
+        for (partition, batch) in routed {
+            state.mem_used += batch.get_array_memory_size();
+            state.partitions[partition].batches.push(batch);
+        }
+        state.mem_used
+    }
+
+    fn finish_routing(&self, result: Result<()>) {
+        let mut state = self.state.lock();
+        state.num_routing -= 1;
+        if let Err(err) = result {
+            state.error.get_or_insert(Arc::new(err));
+        }
+        drop(state);
+        self.routed.notify_waiters();
+    }
+
+    /// Takes the spills or buffered batches of an output partition. routing
+    /// errors are reported to all output partitions.
+    fn take(&self, partition: usize) -> Result<Routed> {
+        let mut state = self.state.lock();
+        if let Some(err) = &state.error {
+            return Err(DataFusionError::External(Box::new(err.clone())));
+        }
+        let routed_partition = &mut state.partitions[partition];
+        if let Some(spill) = routed_partition.spills.pop() {
+            return Ok(Routed::Spill(spill));
+        }
+        if !routed_partition.batches.is_empty() {
+            let batches = std::mem::take(&mut routed_partition.batches);
+            let batches_mem_size = batches
+                .iter()
+                .map(|batch| batch.get_array_memory_size())
+                .sum::<usize>();
+            state.mem_used -= batches_mem_size;
+            return Ok(Routed::Batches(batches, state.mem_used));
+        }
+        if state.num_routing == 0 {
+            return Ok(Routed::Finished);
+        }
+        Ok(Routed::Pending)
+    }
+}
+
+#[async_trait]
+impl MemConsumer for RoutedBuffers {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_consumer_info(&mut self, consumer_info: Weak<MemConsumerInfo>) {
+        self.mem_consumer_info = Some(consumer_info);
+    }
+
+    fn get_consumer_info(&self) -> &Weak<MemConsumerInfo> {
+        self.mem_consumer_info
+            .as_ref()
+            .expect("consumer info not set")
+    }
+
+    async fn spill(&self) -> Result<()> {
+        // the state lock must not be held across awaits
+        {
+            let mut state = self.state.lock();
+            for routed_partition in &mut state.partitions {
+                if routed_partition.batches.is_empty() {
+                    continue;
+                }
+                let spill = try_new_spill(&self.spill_manager)?;
+                let mut spill_writer = spill.get_buf_writer();
+                for batch in std::mem::take(&mut routed_partition.batches) {
+                    self.spill_manager.write_batch(&batch, &mut spill_writer)?;
+                }
+                spill_writer.flush()?;
+                drop(spill_writer);
+                spill.complete()?;
+                routed_partition.spills.push(spill);
+            }
+            state.mem_used = 0;
+        }
+
+        // adjust memory usage
+        self.update_mem_used(0).await?;
+        Ok(())
+    }
+}
+
+impl Drop for RoutedBuffers {
+    fn drop(&mut self) {
+        MemManager::deregister_consumer(self);
+    }
+}
+
+impl Debug for PartitionByColumnExec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PartitionByColumnExec")
+    }
+}
+
+impl DisplayAs for PartitionByColumnExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "PartitionByColumnExec(partition_id_column={}, num_partitions={})",
+            self.partition_id_column, self.num_partitions,
+        )
+    }
+}
+
+impl ExecutionPlan for PartitionByColumnExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(self.num_partitions)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::try_new(
+                children[0].clone(),
+                self.partition_id_column,
+                self.num_partitions,
+            )?)),
+            _ => Err(DataFusionError::Internal(
+                "PartitionByColumnExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        if partition >= self.num_partitions {
+            return Err(DataFusionError::Internal(format!(
+                "PartitionByColumnExec invalid partition {}",
+                partition
+            )));
+        }
+
+        let (routed_buffers, routing_streams) = {
+            let mut routed = self.routed.lock();
+            if routed.is_none() {
+                *routed = Some(self.start_routing(context.clone())?);
+            }
+            routed.as_mut().unwrap()[partition].take().ok_or_else(|| {
+                DataFusionError::Execution(format!(
+                    "PartitionByColumnExec partition {} already executed",
+                    partition
+                ))
+            })?
+        };
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        output_with_sender(
+            "PartitionByColumn",
+            context,
+            self.schema(),
+            move |sender| async move {
+                let _routing_streams = routing_streams;
+                loop {
+                    // wait for routing only if nothing is taken
+                    let mut routed = std::pin::pin!(routed_buffers.routed.notified());
+                    routed.as_mut().enable();
+
+                    match routed_buffers.take(partition)? {
+                        Routed::Batches(batches, mem_used) => {
+                            routed_buffers.update_mem_used(mem_used).await?;
+                            for batch in batches {
+                                baseline_metrics.record_output(batch.num_rows());
+                                sender.send(Ok(batch), None).await;
+                            }
+                        }
+                        Routed::Spill(spill) => {
+                            let mut spill_reader = spill.get_buf_reader();
+                            let schema = routed_buffers.schema.clone();
+                            while let Some(batch) =
+                                read_one_batch(&mut spill_reader, Some(schema.clone()), true)?
+                            {
+                                baseline_metrics.record_output(batch.num_rows());
+                                sender.send(Ok(batch), None).await;
+                            }
+                        }
+                        Routed::Pending => routed.await,
+                        Routed::Finished => return Ok(()),
+                    }
+                }
+            },
+        )
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+fn route_batch(
+    batch: &RecordBatch,
+    partition_id_column: usize,
+    num_partitions: usize,
+) -> Result<Vec<(usize, RecordBatch)>> {
+    let partition_ids = as_int32_array(batch.column(partition_id_column))?;
+    let mut partition_indices: Vec<Vec<u32>> = vec![vec![]; num_partitions];
+
+    for (row_idx, partition_id) in partition_ids.iter().enumerate() {
+        match partition_id {
+            Some(id) if id >= 0 && (id as usize) < num_partitions => {
+                partition_indices[id as usize].push(row_idx as u32);
+            }
+            _ => {
+                return Err(DataFusionError::Execution(format!(
+                    "PartitionByColumnExec: partition id {:?} at row {} is out of range [0, {})",
+                    partition_id, row_idx, num_partitions,
+                )));
+            }
+        }
+    }
+
+    partition_indices
+        .into_iter()
+        .enumerate()
+        .filter(|(_, indices)| !indices.is_empty())
+        .map(|(partition, indices)| Ok((partition, BatchTaker(batch).take(indices)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::{MemConsumer, MemManager};
+    use crate::partition_by_column_exec::PartitionByColumnExec;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::cast::as_int32_array;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    fn build_input(partitions: Vec<Vec<(i32, i32)>>) -> Result<Arc<dyn ExecutionPlan>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("v", DataType::Int32, false),
+            Field::new("pid", DataType::Int32, true),
+        ]));
+        let partitions = partitions
+            .into_iter()
+            .map(|rows| {
+                Ok(vec![RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int32Array::from_iter_values(rows.iter().map(|r| r.0))),
+                        Arc::new(Int32Array::from_iter_values(rows.iter().map(|r| r.1))),
+                    ],
+                )?])
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Arc::new(MemoryExec::try_new(&partitions, schema, None)?))
+    }
+
+    #[tokio::test]
+    async fn test_partition_by_column() -> Result<()> {
+        let input = build_input(vec![
+            vec![(1, 0), (2, 2), (3, 0)],
+            vec![(4, 1), (5, 2), (6, 0)],
+        ])?;
+        MemManager::init(1000000);
+        let exec = PartitionByColumnExec::try_new(input, 1, 3)?;
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        // each output partition is consumed alone
+        let mut outputs = vec![];
+        for partition in 0..3 {
+            outputs.push(common::collect(exec.execute(partition, task_ctx.clone())?).await?);
+        }
+        let expected = vec![
+            "+---+-----+",
+            "| v | pid |",
+            "+---+-----+",
+            "| 1 | 0   |",
+            "| 3 | 0   |",
+            "| 6 | 0   |",
+            "+---+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &outputs[0]);
+        let expected =
+            vec!["+---+-----+", "| v | pid |", "+---+-----+", "| 4 | 1   |", "+---+-----+"];
+        assert_batches_sorted_eq!(expected, &outputs[1]);
+        let expected = vec![
+            "+---+-----+",
+            "| v | pid |",
+            "+---+-----+",
+            "| 2 | 2   |",
+            "| 5 | 2   |",
+            "+---+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &outputs[2]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partition_by_column_single_partition() -> Result<()> {
+        MemManager::init(1000000);
+        let input = build_input(
+            (0..4)
+                .map(|p| (0..1000).map(|i| (p * 1000 + i, i % 3)).collect())
+                .collect(),
+        )?;
+        let exec = PartitionByColumnExec::try_new(input, 1, 3)?;
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        // only one output partition is read, routing is not blocked by the others
+        let output = common::collect(exec.execute(1, task_ctx.clone())?).await?;
+        let num_rows = output.iter().map(|batch| batch.num_rows()).sum::<usize>();
+        assert_eq!(num_rows, 1332);
+
+        // batches buffered for other partitions are spilled and read back
+        let routed_buffers = exec.routed.lock().as_ref().unwrap()[2]
+            .as_ref()
+            .unwrap()
+            .0
+            .clone();
+        routed_buffers.spill().await?;
+        let output = common::collect(exec.execute(2, task_ctx)?).await?;
+        let num_rows = output.iter().map(|batch| batch.num_rows()).sum::<usize>();
+        assert_eq!(num_rows, 1332);
+        for batch in &output {
+            let partition_ids = as_int32_array(batch.column(1))?;
+            assert!(partition_ids.values().iter().all(|&pid| pid == 2));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partition_by_column_out_of_range() -> Result<()> {
+        MemManager::init(1000000);
+        let input = build_input(vec![vec![(1, 0), (2, 3)]])?;
+        let exec = PartitionByColumnExec::try_new(input, 1, 3)?;
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        let err = common::collect(exec.execute(0, task_ctx)?)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("partition id Some(3) at row 1 is out of range [0, 3)"));
+        Ok(())
+    }
+}
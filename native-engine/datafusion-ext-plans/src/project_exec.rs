@@ -71,6 +71,10 @@ impl ProjectExec {
             metrics: ExecutionPlanMetricsSet::new(),
         })
     }
+
+    pub fn exprs(&self) -> &[(PhysicalExprRef, String)] {
+        &self.expr
+    }
 }
 
 impl DisplayAs for ProjectExec {
@@ -128,7 +132,15 @@ impl ExecutionPlan for ProjectExec {
 
         let exprs: Vec<PhysicalExprRef> = self.expr.iter().map(|(e, _name)| e.clone()).collect();
 
-        let fut = if let Some(filter_exec) = self.input.as_any().downcast_ref::<FilterExec>() {
+        // fuse with the input filter, unless it prunes rows with a pushed-down
+        // bloom filter, which is applied by the filter itself
+        let fused_filter_exec = self
+            .input
+            .as_any()
+            .downcast_ref::<FilterExec>()
+            .filter(|filter_exec| filter_exec.bloom_filter_pushdown().is_none());
+
+        let fut = if let Some(filter_exec) = fused_filter_exec {
             execute_project_with_filtering(
                 filter_exec.children()[0].clone(),
                 partition,
@@ -233,3 +245,188 @@ async fn execute_project_with_filtering(
         },
     )
 }
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::MemManager;
+    use crate::filter_exec::FilterExec;
+    use crate::project_exec::ProjectExec;
+    use arrow::array::{Int32Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::logical_expr::Operator;
+    use datafusion::physical_expr::expressions::{col, BinaryExpr, Column, Literal};
+    use datafusion::physical_expr::{PhysicalExpr, PhysicalExprRef};
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ColumnarValue, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use datafusion_ext_commons::spark_bloom_filter::SparkBloomFilter;
+    use std::any::Any;
+    use std::fmt::{Display, Formatter};
+    use std::hash::Hasher;
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::sync::Arc;
+
+    /// An expr wrapper counting how many times it is evaluated
+    #[derive(Debug)]
+    struct CountingExpr {
+        inner: PhysicalExprRef,
+        num_evaluated: Arc<AtomicUsize>,
+    }
+
+    impl Display for CountingExpr {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Counting({})", self.inner)
+        }
+    }
+
+    impl PartialEq<dyn Any> for CountingExpr {
+        fn eq(&self, other: &dyn Any) -> bool {
+            other
+                .downcast_ref::<Self>()
+                .map(|other| self.inner.as_ref().eq(other.inner.as_any()))
+                .unwrap_or(false)
+        }
+    }
+
+    impl PhysicalExpr for CountingExpr {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn data_type(&self, input_schema: &Schema) -> Result<DataType> {
+            self.inner.data_type(input_schema)
+        }
+
+        fn nullable(&self, input_schema: &Schema) -> Result<bool> {
+            self.inner.nullable(input_schema)
+        }
+
+        fn evaluate(&self, batch: &RecordBatch) -> Result<ColumnarValue> {
+            self.num_evaluated.fetch_add(1, SeqCst);
+            self.inner.evaluate(batch)
+        }
+
+        fn children(&self) -> Vec<PhysicalExprRef> {
+            vec![self.inner.clone()]
+        }
+
+        fn with_new_children(
+            self: Arc<Self>,
+            children: Vec<PhysicalExprRef>,
+        ) -> Result<PhysicalExprRef> {
+            Ok(Arc::new(Self {
+                inner: children[0].clone(),
+                num_evaluated: self.num_evaluated.clone(),
+            }))
+        }
+
+        fn dyn_hash(&self, state: &mut dyn Hasher) {
+            self.inner.dyn_hash(state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_project_with_common_subexprs() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(Int32Array::from(vec![10, 20, 30])),
+            ],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None)?);
+
+        // (a + b) is shared by both projected exprs
+        let num_evaluated = Arc::new(AtomicUsize::new(0));
+        let build_shared = || -> PhysicalExprRef {
+            Arc::new(CountingExpr {
+                inner: Arc::new(BinaryExpr::new(
+                    Arc::new(Column::new("a", 0)),
+                    Operator::Plus,
+                    Arc::new(Column::new("b", 1)),
+                )),
+                num_evaluated: num_evaluated.clone(),
+            })
+        };
+        let build_lit = |v: i32| Arc::new(Literal::new(ScalarValue::Int32(Some(v))));
+        let exprs: Vec<(PhysicalExprRef, String)> = vec![
+            (
+                Arc::new(BinaryExpr::new(
+                    build_shared(),
+                    Operator::Plus,
+                    build_lit(1),
+                )),
+                "c1".to_string(),
+            ),
+            (
+                Arc::new(BinaryExpr::new(
+                    build_shared(),
+                    Operator::Multiply,
+                    build_lit(2),
+                )),
+                "c2".to_string(),
+            ),
+        ];
+        let project = ProjectExec::try_new(exprs, input)?;
+
+        let session_ctx = SessionContext::new();
+        let output = common::collect(project.execute(0, session_ctx.task_ctx())?).await?;
+        let expected = vec![
+            "+----+----+",
+            "| c1 | c2 |",
+            "+----+----+",
+            "| 12 | 22 |",
+            "| 23 | 44 |",
+            "| 34 | 66 |",
+            "+----+----+",
+        ];
+        assert_batches_eq!(expected, &output);
+
+        // the shared subexpr is evaluated once per batch
+        assert_eq!(num_evaluated.load(SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_project_with_bloom_filter_pushdown() -> Result<()> {
+        MemManager::init(10000);
+        let schema = Arc::new(Schema::new(vec![Field::new("k", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int64Array::from(vec![1, 2, 3, 200, 300]))],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None)?);
+        let predicate = Arc::new(BinaryExpr::new(
+            col("k", &schema)?,
+            Operator::Gt,
+            Arc::new(Literal::new(ScalarValue::Int64(Some(1)))),
+        ));
+        let filter = Arc::new(
+            FilterExec::try_new(vec![predicate], input)?
+                .try_with_bloom_filter_pushdown(col("k", &schema)?)?,
+        );
+        let mut bloom_filter = SparkBloomFilter::new(100, 4096);
+        for key in 1..=100 {
+            bloom_filter.put_long(key);
+        }
+        filter.inject_bloom_filter(Arc::new(bloom_filter))?;
+
+        // the bloom filter is still applied under a projection
+        let project = ProjectExec::try_new(vec![(col("k", &schema)?, "k".to_string())], filter)?;
+        let session_ctx = SessionContext::new();
+        let output = common::collect(project.execute(0, session_ctx.task_ctx())?).await?;
+        let expected = vec![
+            "+---+", //
+            "| k |", "+---+", "| 2 |", "| 3 |", "+---+",
+        ];
+        assert_batches_eq!(expected, &output);
+        Ok(())
+    }
+}
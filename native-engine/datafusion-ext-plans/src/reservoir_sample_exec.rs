@@ -0,0 +1,335 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Samples rows of each partition for computing range partitioning bounds,
+//! see spark's `RangePartitioner.sketch()`.
+
+use crate::common::output::output_with_sender;
+use arrow::array::{ArrayRef, Float32Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+};
+use datafusion_ext_commons::spark_hash::spark_compatible_murmur3_hash;
+use futures::stream::once;
+use futures::{StreamExt, TryFutureExt, TryStreamExt};
+use std::any::Any;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+pub const SAMPLE_WEIGHT_COLUMN_NAME: &str = "__sample_weight__";
+
+/// Draws at most `sample_size` rows from each partition with reservoir sampling.
+/// the sampled rows are output with an extra float column of their weights, which
+/// equals to `num_input_rows / num_sampled_rows` of the partition.
+#[derive(Debug)]
+pub struct ReservoirSampleExec {
+    input: Arc<dyn ExecutionPlan>,
+    sample_size: usize,
+    seed: i32,
+    output_schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl ReservoirSampleExec {
+    /// `seed` is the id of the sampled rdd, from which the per-partition seeds are
+    /// derived in the same way as spark.
+    pub fn new(input: Arc<dyn ExecutionPlan>, sample_size: usize, seed: i32) -> Self {
+        let output_schema = Arc::new(Schema::new(
+            [
+                input.schema().fields().to_vec(),
+                vec![Arc::new(Field::new(
+                    SAMPLE_WEIGHT_COLUMN_NAME,
+                    DataType::Float32,
+                    false,
+                ))],
+            ]
+            .concat(),
+        ));
+        Self {
+            input,
+            sample_size,
+            seed,
+            output_schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl DisplayAs for ReservoirSampleExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "ReservoirSampleExec(sample_size={}, seed={})",
+            self.sample_size, self.seed
+        )
+    }
+}
+
+impl ExecutionPlan for ReservoirSampleExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.output_schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::new(
+                children[0].clone(),
+                self.sample_size,
+                self.seed,
+            ))),
+            _ => Err(DataFusionError::Internal(
+                "ReservoirSampleExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let input = self.input.execute(partition, context.clone())?;
+        let metrics = BaselineMetrics::new(&self.metrics, partition);
+
+        // same as spark: byteswap32(idx ^ (shuffleId << 16))
+        let partition_seed = byteswap32(partition as i32 ^ (self.seed << 16));
+
+        let output = execute_reservoir_sample(
+            input,
+            context,
+            self.output_schema.clone(),
+            self.sample_size,
+            partition_seed as i64,
+            metrics,
+        );
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            once(output.map_err(ArrowError::from)).try_flatten(),
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+async fn execute_reservoir_sample(
+    mut input: SendableRecordBatchStream,
+    context: Arc<TaskContext>,
+    output_schema: SchemaRef,
+    sample_size: usize,
+    seed: i64,
+    metrics: BaselineMetrics,
+) -> Result<SendableRecordBatchStream> {
+    let input_schema = input.schema();
+
+    output_with_sender(
+        "ReservoirSample",
+        context,
+        output_schema.clone(),
+        move |sender| async move {
+            let mut reservoir = RecordBatch::new_empty(input_schema);
+            let mut num_rows: u64 = 0;
+            let mut rand = XORShiftRandom::new(seed);
+
+            while let Some(batch) =
+                input.next().await.transpose().map_err(|err| {
+                    err.context("reservoir_sample: polling batches from input error")
+                })?
+            {
+                let _timer = metrics.elapsed_compute().timer();
+
+                // slots of the new reservoir, pointing to (0=old reservoir/1=batch, row_idx)
+                let mut slots: Vec<(usize, usize)> =
+                    (0..reservoir.num_rows()).map(|i| (0, i)).collect();
+                for row_idx in 0..batch.num_rows() {
+                    num_rows += 1;
+                    if slots.len() < sample_size {
+                        slots.push((1, row_idx));
+                        continue;
+                    }
+                    let replacement_idx = (rand.next_double() * num_rows as f64) as u64;
+                    if replacement_idx < sample_size as u64 {
+                        slots[replacement_idx as usize] = (1, row_idx);
+                    }
+                }
+
+                // rebuild reservoir, only sampled rows are kept in memory
+                let columns = reservoir
+                    .columns()
+                    .iter()
+                    .zip(batch.columns())
+                    .map(|(reservoir_col, batch_col)| {
+                        Ok(arrow::compute::interleave(
+                            &[reservoir_col.as_ref(), batch_col.as_ref()],
+                            &slots,
+                        )?)
+                    })
+                    .collect::<Result<Vec<ArrayRef>>>()?;
+                reservoir = RecordBatch::try_new_with_options(
+                    reservoir.schema(),
+                    columns,
+                    &RecordBatchOptions::new().with_row_count(Some(slots.len())),
+                )?;
+            }
+
+            if reservoir.num_rows() == 0 {
+                return Ok(());
+            }
+            let mut timer = metrics.elapsed_compute().timer();
+            let weight = (num_rows as f64 / reservoir.num_rows() as f64) as f32;
+            let weights: ArrayRef =
+                Arc::new(Float32Array::from(vec![weight; reservoir.num_rows()]));
+            let output_batch = RecordBatch::try_new(
+                output_schema,
+                [reservoir.columns().to_vec(), vec![weights]].concat(),
+            )?;
+            metrics.record_output(output_batch.num_rows());
+            sender.send(Ok(output_batch), Some(&mut timer)).await;
+            Ok(())
+        },
+    )
+}
+
+fn byteswap32(v: i32) -> i32 {
+    let hc = v.wrapping_mul(0x9e3775cd_u32 as i32);
+    hc.swap_bytes().wrapping_mul(0x9e3775cd_u32 as i32)
+}
+
+/// Port of spark's `XORShiftRandom`, producing identical sequences for identical seeds
+struct XORShiftRandom {
+    seed: i64,
+}
+
+impl XORShiftRandom {
+    fn new(init: i64) -> Self {
+        // hashSeed(): uses scala's MurmurHash3.bytesHash(), which is identical to
+        // spark's murmur3 for 4-bytes aligned input
+        const ARRAY_SEED: u32 = 0x3c074a61;
+        let bytes = init.to_be_bytes();
+        let low_bits = spark_compatible_murmur3_hash(&bytes, ARRAY_SEED);
+        let high_bits = spark_compatible_murmur3_hash(&bytes, low_bits);
+        Self {
+            seed: ((high_bits as i64) << 32) | (low_bits as i64 & 0xffffffff),
+        }
+    }
+
+    fn next(&mut self, bits: i32) -> i32 {
+        let mut next_seed = self.seed ^ (self.seed << 21);
+        next_seed ^= ((next_seed as u64) >> 35) as i64;
+        next_seed ^= next_seed << 4;
+        self.seed = next_seed;
+        (next_seed & ((1i64 << bits) - 1)) as i32
+    }
+
+    fn next_double(&mut self) -> f64 {
+        // same as java.util.Random.nextDouble()
+        let a = self.next(26) as i64;
+        let b = self.next(27) as i64;
+        ((a << 27) + b) as f64 * (1.0 / (1i64 << 53) as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::MemManager;
+    use crate::reservoir_sample_exec::{ReservoirSampleExec, SAMPLE_WEIGHT_COLUMN_NAME};
+    use arrow::array::{Float32Array, Int32Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::cast::as_float32_array;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    async fn sample(sample_size: usize, seed: i32) -> Result<Vec<RecordBatch>> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batches = (0..10)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from_iter_values(i * 100..(i + 1) * 100))],
+                )
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None)?);
+        let sample = ReservoirSampleExec::new(input, sample_size, seed);
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        common::collect(sample.execute(0, task_ctx)?).await
+    }
+
+    #[tokio::test]
+    async fn test_reservoir_sample() -> Result<()> {
+        MemManager::init(10000);
+
+        let sampled = sample(20, 7).await?;
+        let sampled = arrow::compute::concat_batches(&sampled[0].schema(), &sampled)?;
+        assert_eq!(sampled.num_rows(), 20);
+        assert_eq!(
+            as_float32_array(sampled.column_by_name(SAMPLE_WEIGHT_COLUMN_NAME).unwrap())?,
+            &Float32Array::from(vec![50.0; 20]),
+        );
+
+        // deterministic with the same seed
+        let sampled_again = sample(20, 7).await?;
+        let sampled_again =
+            arrow::compute::concat_batches(&sampled_again[0].schema(), &sampled_again)?;
+        assert_eq!(sampled, sampled_again);
+
+        // all rows are taken if sample size exceeds the number of rows
+        let sampled = sample(2000, 7).await?;
+        let sampled = arrow::compute::concat_batches(&sampled[0].schema(), &sampled)?;
+        assert_eq!(sampled.num_rows(), 1000);
+        assert_eq!(
+            as_float32_array(sampled.column_by_name(SAMPLE_WEIGHT_COLUMN_NAME).unwrap())?,
+            &Float32Array::from(vec![1.0; 1000]),
+        );
+        Ok(())
+    }
+}
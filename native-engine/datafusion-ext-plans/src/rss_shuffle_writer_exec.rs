@@ -29,7 +29,7 @@ use crate::common::memory_manager::MemManager;
 use crate::shuffle::rss_bucket_repartitioner::RssBucketShuffleRepartitioner;
 use crate::shuffle::rss_single_repartitioner::RssSingleShuffleRepartitioner;
 use crate::shuffle::rss_sort_repartitioner::RssSortShuffleRepartitioner;
-use crate::shuffle::{can_use_bucket_repartitioner, ShuffleRepartitioner};
+use crate::shuffle::{can_use_bucket_repartitioner, ShuffleRepartitioner, ShuffleWriteMetrics};
 use blaze_jni_bridge::{jni_call_static, jni_new_global_ref, jni_new_string};
 use datafusion::physical_plan::expressions::PhysicalSortExpr;
 use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet};
@@ -40,6 +40,7 @@ use datafusion::physical_plan::Partitioning;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use datafusion::physical_plan::Statistics;
 use datafusion::physical_plan::{DisplayAs, DisplayFormatType};
+use datafusion_ext_commons::io::IpcCompressionCodec;
 use futures::stream::once;
 use futures::{TryFutureExt, TryStreamExt};
 
@@ -53,6 +54,8 @@ pub struct RssShuffleWriterExec {
     partitioning: Partitioning,
     /// scala rssShuffleWriter
     pub rss_partition_writer_resource_id: String,
+    /// codec for compressing the shuffle data
+    compression_codec: IpcCompressionCodec,
     /// Metrics
     metrics: ExecutionPlanMetricsSet,
 }
@@ -96,11 +99,14 @@ impl ExecutionPlan for RssShuffleWriterExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(RssShuffleWriterExec::try_new(
-                children[0].clone(),
-                self.partitioning.clone(),
-                self.rss_partition_writer_resource_id.clone(),
-            )?)),
+            1 => Ok(Arc::new(
+                RssShuffleWriterExec::try_new(
+                    children[0].clone(),
+                    self.partitioning.clone(),
+                    self.rss_partition_writer_resource_id.clone(),
+                )?
+                .with_compression_codec(self.compression_codec),
+            )),
             _ => Err(DataFusionError::Internal(
                 "RssShuffleWriterExec wrong number of children".to_string(),
             )),
@@ -120,12 +126,15 @@ impl ExecutionPlan for RssShuffleWriterExec {
 
         // record uncompressed data size
         let data_size_metric = MetricBuilder::new(&self.metrics).counter("data_size", partition);
+        let write_metrics = ShuffleWriteMetrics::new(&self.metrics, partition);
 
         let input = self.input.execute(partition, context.clone())?;
         let repartitioner: Arc<dyn ShuffleRepartitioner> = match &self.partitioning {
             p if p.partition_count() == 1 => Arc::new(RssSingleShuffleRepartitioner::new(
                 rss_partition_writer,
                 data_size_metric,
+                write_metrics.bytes_written.clone(),
+                self.compression_codec,
             )),
             p @ Partitioning::Hash(_, _)
                 if can_use_bucket_repartitioner(&self.input.schema())
@@ -137,6 +146,8 @@ impl ExecutionPlan for RssShuffleWriterExec {
                     self.schema(),
                     self.partitioning.clone(),
                     data_size_metric,
+                    write_metrics.bytes_written.clone(),
+                    self.compression_codec,
                     context.clone(),
                 ));
                 MemManager::register_consumer(partitioner.clone(), true);
@@ -149,6 +160,8 @@ impl ExecutionPlan for RssShuffleWriterExec {
                     self.schema(),
                     self.partitioning.clone(),
                     data_size_metric,
+                    write_metrics.bytes_written.clone(),
+                    self.compression_codec,
                     context.clone(),
                 ));
                 MemManager::register_consumer(partitioner.clone(), true);
@@ -163,6 +176,7 @@ impl ExecutionPlan for RssShuffleWriterExec {
                 input,
                 context.session_config().batch_size(),
                 BaselineMetrics::new(&self.metrics, partition),
+                write_metrics,
             )
             .map_err(|e| ArrowError::ExternalError(Box::new(e)));
 
@@ -192,7 +206,16 @@ impl RssShuffleWriterExec {
             input,
             partitioning,
             rss_partition_writer_resource_id,
+            compression_codec: IpcCompressionCodec::default(),
             metrics: ExecutionPlanMetricsSet::new(),
         })
     }
+
+    /// Sets the codec for compressing the shuffle data
+    pub fn with_compression_codec(self, compression_codec: IpcCompressionCodec) -> Self {
+        Self {
+            compression_codec,
+            ..self
+        }
+    }
 }
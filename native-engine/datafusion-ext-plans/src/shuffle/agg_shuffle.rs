@@ -0,0 +1,449 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Partial aggregation merged into shuffle writing. when the input of a shuffle
+//! writer is a partial hash aggregation, rows are aggregated by the shuffle
+//! writer in a bounded hash map of each reduce partition, instead of a table
+//! of the whole map task which is spilled and merged when memory is short.
+//! once the map of a partition exceeds its budget, the partition is flushed
+//! and falls back to plain writing, in which every row is written as its own
+//! aggregation record. memory of all maps is reported to the memory manager,
+//! which flushes the maps when spilling is requested.
+
+use crate::agg::agg_buf::AggBuf;
+use crate::agg::{AggExecMode, GroupingExpr};
+use crate::agg_exec::AggExec;
+use crate::common::batch_statisitcs::{stat_input, InputBatchStatistics};
+use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
+use crate::common::output::output_with_sender;
+use crate::shuffle::{evaluate_hashes, evaluate_partition_ids};
+use arrow::array::ArrayRef;
+use arrow::datatypes::FieldRef;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use arrow::row::{RowConverter, SortField};
+use async_trait::async_trait;
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static};
+use datafusion::common::Result;
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::expressions::Column;
+use datafusion::physical_plan::metrics::BaselineMetrics;
+use datafusion::physical_plan::{ExecutionPlan, Partitioning, SendableRecordBatchStream};
+use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::{Arc, Weak};
+
+const DEFAULT_MEM_BUDGET: usize = 16777216;
+
+/// Returns whether partial aggregations are merged into shuffle writing,
+/// configured by `spark.blaze.shuffle.aggregate.enabled`.
+pub fn agg_shuffle_enabled() -> Result<bool> {
+    if !is_jni_bridge_inited() {
+        return Ok(false);
+    }
+    Ok(jni_call_static!(BlazeConf.shuffleAggregateEnabled() -> bool)?)
+}
+
+/// Returns the memory budget of hash maps of all reduce partitions, configured
+/// by `spark.blaze.shuffle.aggregate.mem.bytes`.
+pub fn agg_shuffle_mem_budget() -> Result<usize> {
+    if !is_jni_bridge_inited() {
+        return Ok(DEFAULT_MEM_BUDGET);
+    }
+    let mem_budget = jni_call_static!(BlazeConf.shuffleAggregateMemBytes() -> i32)?;
+    Ok(mem_budget.max(0) as usize)
+}
+
+/// Returns the partial hash aggregation which can be merged into a shuffle
+/// writer with the partitioning. all partitioning keys must be grouping
+/// columns, so that partitions can be computed from grouping values before
+/// aggregating.
+pub fn mergeable_partial_agg<'a>(
+    input: &'a Arc<dyn ExecutionPlan>,
+    partitioning: &Partitioning,
+) -> Option<&'a AggExec> {
+    let agg = input.as_any().downcast_ref::<AggExec>()?;
+    let agg_ctx = agg.agg_ctx();
+    if agg_ctx.exec_mode != AggExecMode::HashAgg
+        || agg_ctx.groupings.is_empty()
+        || agg_ctx.need_final_merge
+    {
+        return None;
+    }
+    let partition_exprs = match partitioning {
+        Partitioning::Hash(exprs, _) => exprs,
+        _ => return None,
+    };
+    let all_grouping_columns = partition_exprs.iter().all(|expr| {
+        expr.as_any()
+            .downcast_ref::<Column>()
+            .map(|column| column.index() < agg_ctx.groupings.len())
+            .unwrap_or(false)
+    });
+    all_grouping_columns.then_some(agg)
+}
+
+#[derive(Default)]
+struct PartitionTable {
+    map: HashMap<Box<[u8]>, AggBuf>,
+    mem_used: usize,
+    fallen_back: bool,
+}
+
+/// Reports memory of all partition maps to the memory manager. spilling is
+/// requested by setting `spill_requested`, which is served by flushing the
+/// maps in the aggregating loop.
+struct AggShuffleMemConsumer {
+    name: String,
+    mem_consumer_info: Option<Weak<MemConsumerInfo>>,
+    spill_requested: AtomicBool,
+}
+
+#[async_trait]
+impl MemConsumer for AggShuffleMemConsumer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_consumer_info(&mut self, consumer_info: Weak<MemConsumerInfo>) {
+        self.mem_consumer_info = Some(consumer_info);
+    }
+
+    fn get_consumer_info(&self) -> &Weak<MemConsumerInfo> {
+        self.mem_consumer_info
+            .as_ref()
+            .expect("consumer info not set")
+    }
+
+    async fn spill(&self) -> Result<()> {
+        self.spill_requested.store(true, SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for AggShuffleMemConsumer {
+    fn drop(&mut self) {
+        MemManager::deregister_consumer(self);
+    }
+}
+
+/// Executes the input of a partial aggregation and aggregates it, outputs
+/// batches in the output schema of the aggregation. metrics are reported to
+/// the aggregation. `mem_budget` is evenly divided to hash maps of all reduce
+/// partitions.
+pub fn execute_agg_shuffle_input(
+    agg: &AggExec,
+    partition: usize,
+    partitioning: Partitioning,
+    mem_budget: usize,
+    context: Arc<TaskContext>,
+) -> Result<SendableRecordBatchStream> {
+    let agg_ctx = agg.agg_ctx().clone();
+    let baseline_metrics = BaselineMetrics::new(agg.metrics_set(), partition);
+    let elapsed_compute = baseline_metrics.elapsed_compute().clone();
+    let input = stat_input(
+        InputBatchStatistics::from_metrics_set_and_blaze_conf(agg.metrics_set(), partition)?,
+        agg.input().execute(partition, context.clone())?,
+    )?;
+
+    let mem_consumer = Arc::new(AggShuffleMemConsumer {
+        name: format!("AggShuffle[partition={}]", partition),
+        mem_consumer_info: None,
+        spill_requested: AtomicBool::new(false),
+    });
+    MemManager::register_consumer(mem_consumer.clone(), true);
+
+    let batch_size = context.session_config().batch_size();
+    let num_partitions = partitioning.partition_count();
+    let partition_mem_budget = mem_budget / num_partitions;
+
+    // create grouping row converter and parser
+    let mut grouping_row_converter = RowConverter::new(
+        agg_ctx
+            .grouping_schema
+            .fields()
+            .iter()
+            .map(|field: &FieldRef| SortField::new(field.data_type().clone()))
+            .collect(),
+    )?;
+
+    let mut coalesced = Box::pin(CoalesceStream::new(
+        input,
+        batch_size,
+        elapsed_compute.clone(),
+    ));
+    output_with_sender(
+        "AggShuffle",
+        context,
+        agg_ctx.output_schema.clone(),
+        move |sender| async move {
+            let mut tables: Vec<PartitionTable> = (0..num_partitions)
+                .map(|_| PartitionTable::default())
+                .collect();
+            let mut staging_records: Vec<(Box<[u8]>, AggBuf)> = vec![];
+            let mut num_input_rows = 0;
+            let mut num_output_rows = 0;
+            let mut timer = elapsed_compute.timer();
+            timer.stop();
+
+            macro_rules! output_records {
+                ($records:expr) => {{
+                    let mut records = $records;
+                    while !records.is_empty() {
+                        let remaining = records.split_off(records.len().min(batch_size));
+                        let batch = agg_ctx.convert_records_to_batch(
+                            &mut grouping_row_converter,
+                            std::mem::replace(&mut records, remaining),
+                        )?;
+                        num_output_rows += batch.num_rows();
+                        baseline_metrics.record_output(batch.num_rows());
+                        sender.send(Ok(batch), Some(&mut timer)).await;
+                    }
+                }};
+            }
+
+            while let Some(input_batch) = coalesced.next().await.transpose()? {
+                timer.restart();
+                num_input_rows += input_batch.num_rows();
+
+                // compute grouping rows and partitions
+                let grouping_arrays: Vec<ArrayRef> = agg_ctx
+                    .groupings
+                    .iter()
+                    .map(|grouping: &GroupingExpr| grouping.expr.evaluate(&input_batch))
+                    .map(|r| r.map(|columnar| columnar.into_array(input_batch.num_rows())))
+                    .collect::<Result<_>>()
+                    .map_err(|err| err.context("agg shuffle: evaluating grouping arrays error"))?;
+                let grouping_rows = grouping_row_converter.convert_columns(&grouping_arrays)?;
+                let grouping_batch = RecordBatch::try_new_with_options(
+                    agg_ctx.grouping_schema.clone(),
+                    grouping_arrays,
+                    &RecordBatchOptions::new().with_row_count(Some(input_batch.num_rows())),
+                )?;
+                let hashes = evaluate_hashes(&partitioning, &grouping_batch)?;
+                let partition_ids = evaluate_partition_ids(&hashes, num_partitions);
+
+                // compute input arrays
+                let input_arrays = agg_ctx
+                    .create_input_arrays(&input_batch)
+                    .map_err(|err| err.context("agg shuffle: evaluating input arrays error"))?;
+                let agg_buf_array = agg_ctx
+                    .get_input_agg_buf_array(&input_batch)
+                    .map_err(|err| err.context("agg shuffle: evaluating agg-buf arrays error"))?;
+                let update_agg_buf = |agg_buf: &mut AggBuf, row_idx: usize| -> Result<()> {
+                    agg_ctx.partial_update_input(agg_buf, &input_arrays, row_idx)?;
+                    agg_ctx.partial_merge_input(agg_buf, agg_buf_array, row_idx)?;
+                    Ok(())
+                };
+
+                let mut flushed_records = vec![];
+                for (row_idx, grouping_row) in grouping_rows.iter().enumerate() {
+                    let table = &mut tables[partition_ids[row_idx] as usize];
+                    let key = grouping_row.as_ref();
+
+                    // partition fallen back, write row as its own record
+                    if table.fallen_back {
+                        let mut agg_buf = agg_ctx.initial_agg_buf.clone();
+                        update_agg_buf(&mut agg_buf, row_idx)?;
+                        staging_records.push((key.into(), agg_buf));
+                        continue;
+                    }
+
+                    // merge row into the partition's map
+                    if !table.map.contains_key(key) {
+                        table.mem_used += size_of::<(Box<[u8]>, AggBuf)>()
+                            + key.len()
+                            + agg_ctx.initial_agg_buf.mem_size();
+                        table
+                            .map
+                            .insert(key.into(), agg_ctx.initial_agg_buf.clone());
+                    }
+                    let agg_buf = table.map.get_mut(key).unwrap();
+                    let old_mem_size = agg_buf.mem_size();
+                    update_agg_buf(agg_buf, row_idx)?;
+                    table.mem_used += agg_buf.mem_size().saturating_sub(old_mem_size);
+
+                    // map exceeds its budget, flush it and fall back to plain writing
+                    if table.mem_used > partition_mem_budget {
+                        flushed_records.extend(std::mem::take(&mut table.map));
+                        table.mem_used = 0;
+                        table.fallen_back = true;
+                    }
+                }
+                output_records!(flushed_records);
+                if staging_records.len() >= batch_size {
+                    output_records!(std::mem::take(&mut staging_records));
+                }
+
+                // report memory of all maps, flush them if spilling is requested
+                let mem_used = tables.iter().map(|table| table.mem_used).sum();
+                mem_consumer.update_mem_used(mem_used).await?;
+                if mem_consumer.spill_requested.swap(false, SeqCst) {
+                    for table in &mut tables {
+                        let records: Vec<_> = std::mem::take(&mut table.map).into_iter().collect();
+                        table.mem_used = 0;
+                        output_records!(records);
+                    }
+                    mem_consumer.update_mem_used(0).await?;
+                }
+                timer.stop();
+            }
+
+            // output all remaining records
+            timer.restart();
+            let num_fallen_back = tables.iter().filter(|table| table.fallen_back).count();
+            for table in &mut tables {
+                let records: Vec<_> = std::mem::take(&mut table.map).into_iter().collect();
+                table.mem_used = 0;
+                output_records!(records);
+            }
+            output_records!(std::mem::take(&mut staging_records));
+            mem_consumer.update_mem_used(0).await?;
+            log::info!(
+                "agg shuffle finished: num_input_rows={}, num_output_rows={}, \
+                fallen back partitions: {}/{}",
+                num_input_rows,
+                num_output_rows,
+                num_fallen_back,
+                num_partitions,
+            );
+            Ok(())
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::agg::AggExecMode::HashAgg;
+    use crate::agg::AggMode::{Final, Partial};
+    use crate::agg::{create_agg, AggExpr, AggFunction, GroupingExpr};
+    use crate::agg_exec::AggExec;
+    use crate::common::memory_manager::MemManager;
+    use crate::shuffle::agg_shuffle::{execute_agg_shuffle_input, mergeable_partial_agg};
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::physical_expr::expressions as phys_expr;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan, Partitioning};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_agg_shuffle() -> Result<()> {
+        MemManager::init(10000);
+
+        // select k, sum(v) from t group by k
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("k", DataType::Int32, false),
+            Field::new("v", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_iter_values((0..20).map(|i| i % 5))),
+                Arc::new(Int32Array::from_iter_values(0..20)),
+            ],
+        )?;
+        let input: Arc<dyn ExecutionPlan> =
+            Arc::new(MemoryExec::try_new(&[vec![batch]], schema.clone(), None)?);
+        let agg_sum = create_agg(AggFunction::Sum, &[phys_expr::col("v", &schema)?], &schema)?;
+        let agg_exec_partial: Arc<dyn ExecutionPlan> = Arc::new(AggExec::try_new(
+            HashAgg,
+            vec![GroupingExpr {
+                field_name: "k".to_string(),
+                expr: Arc::new(Column::new("k", 0)),
+            }],
+            vec![AggExpr {
+                field_name: "sum".to_string(),
+                mode: Partial,
+                agg: agg_sum.clone(),
+            }],
+            0,
+            input,
+        )?);
+
+        // partitioning by non-grouping columns cannot be merged
+        let partitioning = Partitioning::Hash(vec![Arc::new(Column::new("k", 0))], 3);
+        let non_grouping_partitioning =
+            Partitioning::Hash(vec![Arc::new(Column::new("sum", 1))], 3);
+        assert!(mergeable_partial_agg(&agg_exec_partial, &non_grouping_partitioning).is_none());
+        let agg = mergeable_partial_agg(&agg_exec_partial, &partitioning).unwrap();
+        let agg_ctx = agg.agg_ctx().clone();
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let expected = vec![
+            "+---+-----+",
+            "| k | sum |",
+            "+---+-----+",
+            "| 0 | 30  |",
+            "| 1 | 34  |",
+            "| 2 | 38  |",
+            "| 3 | 42  |",
+            "| 4 | 46  |",
+            "+---+-----+",
+        ];
+
+        // rows are fully merged with enough memory, and written as their own
+        // records once all maps exceed the budget
+        let mut total_num_rows = 0;
+        for (mem_budget, expected_num_rows) in [(1048576, 5), (0, 20)] {
+            let output = execute_agg_shuffle_input(
+                agg,
+                0,
+                partitioning.clone(),
+                mem_budget,
+                task_ctx.clone(),
+            )?;
+            let batches = common::collect(output).await?;
+            let num_rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(num_rows, expected_num_rows);
+
+            // output rows are reported to the metrics of the aggregation
+            total_num_rows += num_rows;
+            assert_eq!(agg.metrics().unwrap().output_rows(), Some(total_num_rows));
+
+            let agg_exec_final = AggExec::try_new(
+                HashAgg,
+                vec![GroupingExpr {
+                    field_name: "k".to_string(),
+                    expr: Arc::new(Column::new("k", 0)),
+                }],
+                vec![AggExpr {
+                    field_name: "sum".to_string(),
+                    mode: Final,
+                    agg: agg_sum.with_new_exprs(vec![Arc::new(phys_expr::Literal::new(
+                        ScalarValue::Null,
+                    ))])?,
+                }],
+                0,
+                Arc::new(MemoryExec::try_new(
+                    &[batches],
+                    agg_ctx.output_schema.clone(),
+                    None,
+                )?),
+            )?;
+            let output = agg_exec_final.execute(0, task_ctx.clone())?;
+            assert_batches_sorted_eq!(expected, &common::collect(output).await?);
+        }
+        Ok(())
+    }
+}
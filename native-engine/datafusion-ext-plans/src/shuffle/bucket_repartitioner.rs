@@ -16,6 +16,7 @@
 
 use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
 use crate::common::onheap_spill::{try_new_spill, Spill};
+use crate::shuffle::compaction::{compaction_target_size, BatchCompactor};
 use crate::shuffle::{evaluate_hashes, evaluate_partition_ids, ShuffleRepartitioner, ShuffleSpill};
 use arrow::array::*;
 use arrow::datatypes::*;
@@ -28,7 +29,8 @@ use datafusion::physical_plan::metrics::{BaselineMetrics, Count};
 use datafusion::physical_plan::Partitioning;
 use datafusion_ext_commons::array_builder::{builder_extend, make_batch, new_array_builders};
 use datafusion_ext_commons::concat_batches;
-use datafusion_ext_commons::io::write_one_batch;
+use datafusion_ext_commons::io::{write_one_batch_with_codec, IpcCompressionCodec};
+use datafusion_ext_commons::spill::SpillManager;
 use futures::lock::Mutex;
 use itertools::Itertools;
 use std::fs::{File, OpenOptions};
@@ -40,10 +42,15 @@ pub struct BucketShuffleRepartitioner {
     mem_consumer_info: Option<Weak<MemConsumerInfo>>,
     output_data_file: String,
     output_index_file: String,
+    schema: SchemaRef,
     buffered_partitions: Mutex<Vec<PartitionBuffer>>,
+    flushed_partitions: Mutex<FlushedPartitions>,
     spills: Mutex<Vec<ShuffleSpill>>,
     partitioning: Partitioning,
     num_output_partitions: usize,
+    partition_flush_threshold: usize,
+    compression_codec: IpcCompressionCodec,
+    spill_manager: Arc<SpillManager>,
     metrics: BaselineMetrics,
 }
 
@@ -57,26 +64,42 @@ impl BucketShuffleRepartitioner {
         partitioning: Partitioning,
         metrics: BaselineMetrics,
         data_size_metric: Count,
+        compression_codec: IpcCompressionCodec,
+        partition_flush_threshold: usize,
         context: Arc<TaskContext>,
     ) -> Self {
         let num_output_partitions = partitioning.partition_count();
         let batch_size = context.session_config().batch_size();
+        let flushed_partitions = FlushedPartitions::new(
+            format!("{}.flushed", output_data_file),
+            num_output_partitions,
+        );
 
         Self {
             name: format!("BucketShufflePartitioner[partition={}]", partition_id),
             mem_consumer_info: None,
             output_data_file,
             output_index_file,
+            schema: schema.clone(),
             buffered_partitions: Mutex::new(
                 (0..num_output_partitions)
                     .map(|_| {
-                        PartitionBuffer::new(schema.clone(), batch_size, data_size_metric.clone())
+                        PartitionBuffer::new(
+                            schema.clone(),
+                            batch_size,
+                            data_size_metric.clone(),
+                            compression_codec,
+                        )
                     })
                     .collect::<Vec<_>>(),
             ),
+            flushed_partitions: Mutex::new(flushed_partitions),
             spills: Mutex::new(vec![]),
             partitioning,
             num_output_partitions,
+            partition_flush_threshold,
+            compression_codec,
+            spill_manager: SpillManager::get(&context),
             metrics,
         }
     }
@@ -148,6 +171,18 @@ impl ShuffleRepartitioner for BucketShuffleRepartitioner {
                 )?;
                 mem_diff += output.append_batch(batch)?;
             }
+
+            // flush frozen data of a big partition to disk to keep memory bounded
+            if self.partition_flush_threshold > 0
+                && output.frozen.len() >= self.partition_flush_threshold
+            {
+                mem_diff -= output.frozen.capacity() as isize;
+                let frozen = std::mem::take(&mut output.frozen);
+                self.flushed_partitions
+                    .lock()
+                    .await
+                    .flush(partition_id, &frozen)?;
+            }
             drop(buffered_partitions);
         }
         self.update_mem_used_with_diff(mem_diff).await?;
@@ -164,11 +199,21 @@ impl ShuffleRepartitioner for BucketShuffleRepartitioner {
         self.set_spillable(false);
         let mut spills = std::mem::take(&mut *self.spills.lock().await);
         let mut buffered_partitions = std::mem::take(&mut *self.buffered_partitions.lock().await);
+        let mut flushed_partitions = self.flushed_partitions.lock().await;
+        let flushed_path = flushed_partitions.path.clone();
+        let flushed_segments = std::mem::take(&mut flushed_partitions.segments);
+        let mut flushed_file = flushed_partitions.file.take();
+        drop(flushed_partitions);
 
         log::info!(
-            "bucket partitioner start writing with {} ({} spills)",
+            "bucket partitioner start writing with {} ({} spills, {} flushed bytes)",
             self.name(),
             spills.len(),
+            flushed_segments
+                .iter()
+                .flatten()
+                .map(|(_, length)| length)
+                .sum::<u64>(),
         );
 
         let mut output_batches: Vec<Vec<u8>> = vec![vec![]; self.num_output_partitions];
@@ -192,6 +237,11 @@ impl ShuffleRepartitioner for BucketShuffleRepartitioner {
         let data_file = self.output_data_file.clone();
         let index_file = self.output_index_file.clone();
         let num_output_partitions = self.num_output_partitions;
+        let mut compactor = BatchCompactor::new(
+            self.schema.clone(),
+            compaction_target_size()?,
+            self.compression_codec,
+        );
         tokio::task::spawn_blocking(move || {
             let mut offsets = vec![0; num_output_partitions + 1];
             let mut output_data = OpenOptions::new()
@@ -202,15 +252,24 @@ impl ShuffleRepartitioner for BucketShuffleRepartitioner {
 
             for i in 0..num_output_partitions {
                 offsets[i] = output_data.stream_position()?;
+
+                // append partition flushed to disk
+                if let Some(flushed_file) = &mut flushed_file {
+                    for &(offset, length) in &flushed_segments[i] {
+                        flushed_file.seek(SeekFrom::Start(offset))?;
+                        std::io::copy(&mut (&mut *flushed_file).take(length), &mut output_data)?;
+                    }
+                }
                 output_data.write_all(&std::mem::take(&mut output_batches[i]))?;
 
-                // append partition in each spills
+                // append partition in each spills, small batches in spills are compacted
                 for (reader, offsets) in &mut spill_readers {
                     let length = offsets[i + 1] - offsets[i];
                     if length > 0 {
-                        std::io::copy(&mut reader.take(length), &mut output_data)?;
+                        compactor.write_segment(reader, length, &mut output_data)?;
                     }
                 }
+                compactor.flush(&mut output_data)?;
             }
             output_data.sync_data()?;
             output_data.flush()?;
@@ -223,6 +282,10 @@ impl ShuffleRepartitioner for BucketShuffleRepartitioner {
             }
             output_index.sync_data()?;
             output_index.flush()?;
+
+            if flushed_file.is_some() {
+                std::fs::remove_file(flushed_path)?;
+            }
             Ok::<(), DataFusionError>(())
         })
         .await
@@ -243,6 +306,7 @@ impl ShuffleRepartitioner for BucketShuffleRepartitioner {
 fn spill_buffered_partitions(
     buffered_partitions: &mut [PartitionBuffer],
     num_output_partitions: usize,
+    spill_manager: &Arc<SpillManager>,
 ) -> Result<Option<ShuffleSpill>> {
     // no data to spill
     if buffered_partitions
@@ -253,7 +317,7 @@ fn spill_buffered_partitions(
     }
 
     let mut output_batches: Vec<Vec<u8>> = vec![vec![]; num_output_partitions];
-    let spill = try_new_spill()?;
+    let spill = try_new_spill(spill_manager)?;
     let mut spill_writer = spill.get_buf_writer();
 
     for i in 0..num_output_partitions {
@@ -299,6 +363,7 @@ impl MemConsumer for BucketShuffleRepartitioner {
         spills.extend(spill_buffered_partitions(
             &mut partitions,
             self.num_output_partitions,
+            &self.spill_manager,
         )?);
         drop(spills);
         drop(partitions);
@@ -311,6 +376,47 @@ impl MemConsumer for BucketShuffleRepartitioner {
 impl Drop for BucketShuffleRepartitioner {
     fn drop(&mut self) {
         MemManager::deregister_consumer(self);
+
+        // remove the flushed file if shuffle_write() is not completed
+        if self.flushed_partitions.get_mut().file.is_some() {
+            let _ = std::fs::remove_file(&self.flushed_partitions.get_mut().path);
+        }
+    }
+}
+
+/// Frozen data of partitions flushed to a local file before writing the final
+/// output, which is then copied to the data file in partition order.
+struct FlushedPartitions {
+    path: String,
+    file: Option<File>,
+    segments: Vec<Vec<(u64, u64)>>, // (offset, length) of flushed data of each partition
+}
+
+impl FlushedPartitions {
+    fn new(path: String, num_output_partitions: usize) -> Self {
+        Self {
+            path,
+            file: None,
+            segments: vec![vec![]; num_output_partitions],
+        }
+    }
+
+    fn flush(&mut self, partition_id: usize, data: &[u8]) -> Result<()> {
+        if self.file.is_none() {
+            self.file = Some(
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&self.path)?,
+            );
+        }
+        let file = self.file.as_mut().unwrap();
+        let offset = file.stream_position()?;
+        file.write_all(data)?;
+        self.segments[partition_id].push((offset, data.len() as u64));
+        Ok(())
     }
 }
 
@@ -325,10 +431,16 @@ struct PartitionBuffer {
     batch_size: usize,
     staging_size: usize,
     data_size_metric: Count,
+    compression_codec: IpcCompressionCodec,
 }
 
 impl PartitionBuffer {
-    fn new(schema: SchemaRef, batch_size: usize, data_size_metric: Count) -> Self {
+    fn new(
+        schema: SchemaRef,
+        batch_size: usize,
+        data_size_metric: Count,
+        compression_codec: IpcCompressionCodec,
+    ) -> Self {
         let staging_size = batch_size / (batch_size as f64 + 1.0).log2() as usize;
         Self {
             schema,
@@ -341,6 +453,7 @@ impl PartitionBuffer {
             batch_size,
             staging_size,
             data_size_metric,
+            compression_codec,
         }
     }
 
@@ -446,10 +559,10 @@ impl PartitionBuffer {
         self.data_size_metric
             .add(frozen_batch.get_array_memory_size());
         let mut num_bytes_written_uncompressed = 0;
-        write_one_batch(
+        write_one_batch_with_codec(
             &frozen_batch,
             &mut cursor,
-            true,
+            Some(self.compression_codec),
             Some(&mut num_bytes_written_uncompressed),
         )?;
         self.data_size_metric.add(num_bytes_written_uncompressed);
@@ -518,3 +631,106 @@ fn slot_size(len: usize, data_type: &DataType) -> usize {
         dt => unimplemented!("data type not supported in shuffle write: {:?}", dt),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::MemManager;
+    use crate::shuffle::bucket_repartitioner::BucketShuffleRepartitioner;
+    use crate::shuffle::{ShuffleRepartitioner, ShuffleWriteMetrics};
+    use arrow::array::{as_primitive_array, Int32Array};
+    use arrow::datatypes::{DataType, Field, Int32Type, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::metrics::{
+        BaselineMetrics, ExecutionPlanMetricsSet, MetricBuilder,
+    };
+    use datafusion::physical_plan::{common, ExecutionPlan, Partitioning};
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use datafusion_ext_commons::io::{read_one_batch, IpcCompressionCodec};
+    use std::io::Cursor;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    // writes shuffle and returns sorted values of each output partition
+    async fn shuffle_write(dir: &Path, partition_flush_threshold: usize) -> Result<Vec<Vec<i32>>> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batches = (0..10)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from_iter_values(i * 100..(i + 1) * 100))],
+                )
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let input = MemoryExec::try_new(&[batches], schema.clone(), None)?;
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(16));
+        let task_ctx = session_ctx.task_ctx();
+
+        let data_file = dir.join(format!("shuffle-{}.data", partition_flush_threshold));
+        let index_file = dir.join(format!("shuffle-{}.index", partition_flush_threshold));
+        let metrics = ExecutionPlanMetricsSet::new();
+        let repartitioner = Arc::new(BucketShuffleRepartitioner::new(
+            0,
+            data_file.to_string_lossy().to_string(),
+            index_file.to_string_lossy().to_string(),
+            schema.clone(),
+            Partitioning::Hash(vec![Arc::new(Column::new("a", 0))], 4),
+            BaselineMetrics::new(&metrics, 0),
+            MetricBuilder::new(&metrics).counter("data_size", 0),
+            IpcCompressionCodec::default(),
+            partition_flush_threshold,
+            task_ctx.clone(),
+        ));
+        MemManager::register_consumer(repartitioner.clone(), true);
+        let repartitioner: Arc<dyn ShuffleRepartitioner> = repartitioner;
+        let output = repartitioner
+            .execute(
+                task_ctx.clone(),
+                input.execute(0, task_ctx)?,
+                16,
+                BaselineMetrics::new(&metrics, 0),
+                ShuffleWriteMetrics::new(&metrics, 0),
+            )
+            .await?;
+        assert!(common::collect(output).await?.is_empty());
+        assert!(!dir
+            .join(format!(
+                "shuffle-{}.data.flushed",
+                partition_flush_threshold
+            ))
+            .exists());
+
+        let data = std::fs::read(&data_file)?;
+        let offsets = std::fs::read(&index_file)?
+            .chunks(8)
+            .map(|offset| i64::from_le_bytes(offset.try_into().unwrap()) as usize)
+            .collect::<Vec<_>>();
+        let mut partitions = vec![];
+        for (&start, &end) in offsets.iter().zip(&offsets[1..]) {
+            let mut values = vec![];
+            let mut cursor = Cursor::new(&data[start..end]);
+            while let Some(batch) = read_one_batch(&mut cursor, Some(schema.clone()), true)? {
+                values.extend(as_primitive_array::<Int32Type>(batch.column(0)).values());
+            }
+            values.sort();
+            partitions.push(values);
+        }
+        Ok(partitions)
+    }
+
+    #[tokio::test]
+    async fn test_partition_flushing() -> Result<()> {
+        MemManager::init(10000);
+        let tmp_dir = tempfile::tempdir()?;
+
+        // flushing every partition does not change the output
+        let partitions = shuffle_write(tmp_dir.path(), 0).await?;
+        let flushed_partitions = shuffle_write(tmp_dir.path(), 1).await?;
+        assert_eq!(partitions.len(), 4);
+        assert_eq!(partitions.iter().map(|p| p.len()).sum::<usize>(), 1000);
+        assert_eq!(partitions, flushed_partitions);
+        Ok(())
+    }
+}
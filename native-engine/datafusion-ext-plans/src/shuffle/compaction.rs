@@ -0,0 +1,165 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compaction of small encoded batches in shuffle output. spills of a shuffle
+//! with many reduce partitions contain only a few rows of each partition, and
+//! these tiny batches compress poorly when they are encoded individually.
+
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static};
+use datafusion::common::Result;
+use datafusion_ext_commons::concat_batches;
+use datafusion_ext_commons::io::{read_one_batch, write_one_batch_with_codec, IpcCompressionCodec};
+use std::io::{Read, Seek, Write};
+
+const DEFAULT_COMPACTION_TARGET_SIZE: usize = 1048576;
+
+/// Returns the target size of compacted batches configured by
+/// `spark.blaze.shuffle.compaction.target.bytes`. 0 disables compaction.
+pub fn compaction_target_size() -> Result<usize> {
+    if !is_jni_bridge_inited() {
+        return Ok(DEFAULT_COMPACTION_TARGET_SIZE);
+    }
+    let target_size = jni_call_static!(BlazeConf.shuffleCompactionTargetBytes() -> i32)?;
+    Ok(target_size.max(0) as usize)
+}
+
+/// Writes encoded segments of a reduce partition to the shuffle output. small
+/// segments are decoded and staged, then concatenated and encoded into one
+/// frame once the staging batches reach the target size. bigger segments are
+/// copied as is.
+pub struct BatchCompactor {
+    schema: SchemaRef,
+    target_size: usize,
+    compression_codec: IpcCompressionCodec,
+    staging_batches: Vec<RecordBatch>,
+    staging_rows: usize,
+    staging_mem_size: usize,
+}
+
+impl BatchCompactor {
+    pub fn new(
+        schema: SchemaRef,
+        target_size: usize,
+        compression_codec: IpcCompressionCodec,
+    ) -> Self {
+        Self {
+            schema,
+            target_size,
+            compression_codec,
+            staging_batches: vec![],
+            staging_rows: 0,
+            staging_mem_size: 0,
+        }
+    }
+
+    /// Writes an encoded segment of `length` bytes read from `segment`
+    pub fn write_segment<R: Read, W: Write + Seek>(
+        &mut self,
+        segment: R,
+        length: u64,
+        output: &mut W,
+    ) -> Result<()> {
+        let mut segment = segment.take(length);
+        if length as usize >= self.target_size {
+            self.flush(output)?;
+            std::io::copy(&mut segment, output)?;
+            return Ok(());
+        }
+
+        while let Some(batch) = read_one_batch(&mut segment, Some(self.schema.clone()), true)? {
+            self.staging_rows += batch.num_rows();
+            self.staging_mem_size += batch.get_array_memory_size();
+            self.staging_batches.push(batch);
+            if self.staging_mem_size >= self.target_size {
+                self.flush(output)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes all staging batches into one frame, must be called at the end
+    /// of each partition.
+    pub fn flush<W: Write + Seek>(&mut self, output: &mut W) -> Result<()> {
+        if self.staging_batches.is_empty() {
+            return Ok(());
+        }
+        let batch = concat_batches(
+            &self.schema,
+            &std::mem::take(&mut self.staging_batches),
+            self.staging_rows,
+        )?;
+        self.staging_rows = 0;
+        self.staging_mem_size = 0;
+        write_one_batch_with_codec(&batch, output, Some(self.compression_codec), None)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::shuffle::compaction::BatchCompactor;
+    use arrow::array::{as_primitive_array, Int32Array};
+    use arrow::datatypes::{DataType, Field, Int32Type, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion_ext_commons::io::{
+        read_one_batch, write_one_batch_with_codec, IpcCompressionCodec,
+    };
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_batch_compactor() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let codec = IpcCompressionCodec::default();
+        let segments = (0..5)
+            .map(|i| {
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![Arc::new(Int32Array::from_iter_values(i * 3..(i + 1) * 3))],
+                )?;
+                let mut segment = Cursor::new(vec![]);
+                write_one_batch_with_codec(&batch, &mut segment, Some(codec), None)?;
+                Ok(segment.into_inner())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // small segments are compacted into one frame
+        let mut output = Cursor::new(vec![]);
+        let mut compactor = BatchCompactor::new(schema.clone(), 1048576, codec);
+        for segment in &segments {
+            compactor.write_segment(&segment[..], segment.len() as u64, &mut output)?;
+        }
+        compactor.flush(&mut output)?;
+        output.set_position(0);
+        let batch = read_one_batch(&mut output, Some(schema.clone()), true)?.unwrap();
+        assert_eq!(
+            as_primitive_array::<Int32Type>(batch.column(0)),
+            &Int32Array::from_iter_values(0..15)
+        );
+        assert!(read_one_batch(&mut output, Some(schema.clone()), true)?.is_none());
+
+        // segments are copied as is without compaction
+        let mut output = Cursor::new(vec![]);
+        let mut compactor = BatchCompactor::new(schema.clone(), 0, codec);
+        for segment in &segments {
+            compactor.write_segment(&segment[..], segment.len() as u64, &mut output)?;
+        }
+        compactor.flush(&mut output)?;
+        assert_eq!(output.into_inner(), segments.concat());
+        Ok(())
+    }
+}
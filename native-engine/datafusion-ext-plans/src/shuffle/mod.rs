@@ -18,18 +18,23 @@ use arrow::datatypes::SchemaRef;
 use arrow::error::Result as ArrowResult;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static};
 use datafusion::common::Result;
 use datafusion::error::DataFusionError;
 use datafusion::execution::context::TaskContext;
-use datafusion::physical_plan::metrics::BaselineMetrics;
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, Time,
+};
 use datafusion::physical_plan::{Partitioning, SendableRecordBatchStream};
 use datafusion_ext_commons::array_builder::has_array_builder_supported;
 use datafusion_ext_commons::spark_hash::{create_hashes, pmod};
-use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
 use futures::StreamExt;
 use std::sync::Arc;
 
+pub mod agg_shuffle;
 pub mod bucket_repartitioner;
+pub mod compaction;
 pub mod single_repartitioner;
 pub mod sort_repartitioner;
 
@@ -38,6 +43,19 @@ pub mod rss_bucket_repartitioner;
 pub mod rss_single_repartitioner;
 pub mod rss_sort_repartitioner;
 
+const DEFAULT_PARTITION_FLUSH_THRESHOLD: usize = 1048576;
+
+/// Returns the size of buffered data of a reduce partition to be flushed to
+/// disk during shuffle writing, configured by
+/// `spark.blaze.shuffle.partition.flush.bytes`. 0 disables flushing.
+pub fn partition_flush_threshold() -> Result<usize> {
+    if !is_jni_bridge_inited() {
+        return Ok(DEFAULT_PARTITION_FLUSH_THRESHOLD);
+    }
+    let threshold = jni_call_static!(BlazeConf.shufflePartitionFlushBytes() -> i32)?;
+    Ok(threshold.max(0) as usize)
+}
+
 pub fn can_use_bucket_repartitioner(schema: &SchemaRef) -> bool {
     schema
         .fields()
@@ -45,6 +63,28 @@ pub fn can_use_bucket_repartitioner(schema: &SchemaRef) -> bool {
         .all(|field| has_array_builder_supported(field.data_type()))
 }
 
+/// Metrics of a shuffle map task, reported to spark's `ShuffleWriteMetrics`
+#[derive(Debug, Clone)]
+pub struct ShuffleWriteMetrics {
+    /// Bytes written to the final shuffle output
+    pub bytes_written: Count,
+    /// Number of records written
+    pub records_written: Count,
+    /// Time (in nanoseconds) spent on partitioning, spilling and writing
+    pub write_time: Time,
+}
+
+impl ShuffleWriteMetrics {
+    pub fn new(metrics: &ExecutionPlanMetricsSet, partition: usize) -> Self {
+        Self {
+            bytes_written: MetricBuilder::new(metrics).counter("shuffle_bytes_written", partition),
+            records_written: MetricBuilder::new(metrics)
+                .counter("shuffle_records_written", partition),
+            write_time: MetricBuilder::new(metrics).subset_time("shuffle_write_time", partition),
+        }
+    }
+}
+
 #[async_trait]
 pub trait ShuffleRepartitioner: Send + Sync {
     async fn insert_batch(&self, input: RecordBatch) -> Result<()>;
@@ -58,26 +98,29 @@ impl dyn ShuffleRepartitioner {
         input: SendableRecordBatchStream,
         batch_size: usize,
         metrics: BaselineMetrics,
+        write_metrics: ShuffleWriteMetrics,
     ) -> Result<SendableRecordBatchStream> {
         let input_schema = input.schema();
 
         // coalesce input
-        let mut coalesced = Box::pin(CoalesceStream::new(
-            input,
-            batch_size,
-            metrics.elapsed_compute().clone(),
-        ));
+        let mut coalesced = Box::pin(
+            CoalesceStream::new(input, batch_size, metrics.elapsed_compute().clone())
+                .with_byte_budget(batch_byte_budget()?),
+        );
 
         // process all input batches
         output_with_sender("Shuffle", context, input_schema, |_| async move {
             while let Some(batch) = coalesced.next().await.transpose()? {
                 let _timer = metrics.elapsed_compute().timer();
+                let _write_timer = write_metrics.write_time.timer();
                 metrics.record_output(batch.num_rows());
+                write_metrics.records_written.add(batch.num_rows());
                 self.insert_batch(batch)
                     .await
                     .map_err(|err| err.context("shuffle: executing insert_batch() error"))?;
             }
             let _timer = metrics.elapsed_compute().timer();
+            let _write_timer = write_metrics.write_time.timer();
             self.shuffle_write()
                 .await
                 .map_err(|err| err.context("shuffle: executing shuffle_write() error"))?;
@@ -94,14 +137,13 @@ struct ShuffleSpill {
 fn evaluate_hashes(partitioning: &Partitioning, batch: &RecordBatch) -> ArrowResult<Vec<u32>> {
     match partitioning {
         Partitioning::Hash(exprs, _) => {
-            let mut hashes_buf = vec![];
             let arrays = exprs
                 .iter()
                 .map(|expr| Ok(expr.evaluate(batch)?.into_array(batch.num_rows())))
                 .collect::<Result<Vec<_>>>()?;
 
             // use identical seed as spark hash partition
-            hashes_buf.resize(arrays[0].len(), 42);
+            let mut hashes_buf = vec![42; batch.num_rows()];
 
             // compute hash array
             create_hashes(&arrays, &mut hashes_buf)?;
@@ -117,3 +159,41 @@ fn evaluate_partition_ids(hashes: &[u32], num_partitions: usize) -> Vec<u32> {
         .map(|hash| pmod(*hash, num_partitions) as u32)
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use crate::shuffle::{evaluate_hashes, evaluate_partition_ids};
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::Partitioning;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_spark_compatible_partition_ids() -> Result<()> {
+        let ids: ArrayRef = Arc::new(Int64Array::from(vec![
+            Some(1),
+            Some(0),
+            Some(-1),
+            Some(i64::MAX),
+            Some(i64::MIN),
+            None,
+        ]));
+        let names: ArrayRef = Arc::new(StringArray::from(vec![None::<&str>; 6]));
+        let batch = RecordBatch::try_from_iter(vec![("id", ids), ("name", names)])?;
+
+        // null keys are skipped in hashing, so null names do not change partitions
+        let partitioning = Partitioning::Hash(
+            vec![Arc::new(Column::new("id", 0)), Arc::new(Column::new("name", 1))],
+            200,
+        );
+        let hashes = evaluate_hashes(&partitioning, &batch)?;
+        let partition_ids = evaluate_partition_ids(&hashes, 200);
+
+        // expected partitions from spark with n=200, a row of all nulls hashes to
+        // the seed 42
+        assert_eq!(partition_ids, vec![69, 5, 193, 171, 115, 42]);
+        Ok(())
+    }
+}
@@ -15,7 +15,8 @@
 use arrow::record_batch::RecordBatch;
 use blaze_jni_bridge::{jni_call, jni_new_direct_byte_buffer};
 use datafusion::common::Result;
-use datafusion_ext_commons::io::write_one_batch;
+use datafusion::physical_plan::metrics::Count;
+use datafusion_ext_commons::io::{write_one_batch_with_codec, IpcCompressionCodec};
 use jni::objects::GlobalRef;
 use std::io::Cursor;
 
@@ -23,14 +24,16 @@ pub fn rss_write_batch(
     rss_partition_writer: &GlobalRef,
     partition_id: usize,
     batch: RecordBatch,
+    compression_codec: IpcCompressionCodec,
     uncompressed_size: &mut usize,
+    bytes_written: &Count,
 ) -> Result<()> {
     let mut data = vec![];
 
-    write_one_batch(
+    write_one_batch_with_codec(
         &batch,
         &mut Cursor::new(&mut data),
-        true,
+        Some(compression_codec),
         Some(uncompressed_size),
     )?;
     let data_len = data.len();
@@ -39,6 +42,7 @@ pub fn rss_write_batch(
         BlazeRssPartitionWriterBase(rss_partition_writer.as_obj())
         .write(partition_id as i32, buf.as_obj(), data_len as i32) -> ()
     )?;
+    bytes_written.add(data_len);
     Ok(())
 }
 
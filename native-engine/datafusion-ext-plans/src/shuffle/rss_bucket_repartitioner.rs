@@ -27,6 +27,7 @@ use datafusion::execution::context::TaskContext;
 use datafusion::physical_plan::metrics::Count;
 use datafusion::physical_plan::Partitioning;
 use datafusion_ext_commons::array_builder::{builder_extend, make_batch, new_array_builders};
+use datafusion_ext_commons::io::IpcCompressionCodec;
 use futures::lock::Mutex;
 use itertools::Itertools;
 use jni::objects::GlobalRef;
@@ -49,6 +50,8 @@ impl RssBucketShuffleRepartitioner {
         schema: SchemaRef,
         partitioning: Partitioning,
         data_size_metric: Count,
+        bytes_written: Count,
+        compression_codec: IpcCompressionCodec,
         context: Arc<TaskContext>,
     ) -> Self {
         let num_output_partitions = partitioning.partition_count();
@@ -62,6 +65,8 @@ impl RssBucketShuffleRepartitioner {
                         i,
                         rss_partition_writer.clone(),
                         data_size_metric.clone(),
+                        bytes_written.clone(),
+                        compression_codec,
                     )
                 })
                 .collect(),
@@ -206,6 +211,8 @@ struct PartitionBuffer {
     num_active_rows: usize,
     rss_batch_size: usize,
     data_size_metric: Count,
+    bytes_written: Count,
+    compression_codec: IpcCompressionCodec,
 }
 
 impl PartitionBuffer {
@@ -215,6 +222,8 @@ impl PartitionBuffer {
         partition_id: usize,
         rss_partition_writer: GlobalRef,
         data_size_metric: Count,
+        bytes_written: Count,
+        compression_codec: IpcCompressionCodec,
     ) -> Self {
         // use smaller batch size for rss to trigger more flushes
         let rss_batch_size = batch_size / (batch_size as f64 + 1.0).log2() as usize;
@@ -226,6 +235,8 @@ impl PartitionBuffer {
             num_active_rows: 0,
             rss_batch_size,
             data_size_metric,
+            bytes_written,
+            compression_codec,
         }
     }
 
@@ -269,7 +280,9 @@ impl PartitionBuffer {
             &self.rss_partition_writer,
             self.partition_id,
             batch,
+            self.compression_codec,
             &mut num_bytes_written_uncompressed,
+            &self.bytes_written,
         )?;
         self.data_size_metric.add(num_bytes_written_uncompressed);
         Ok(())
@@ -289,7 +302,9 @@ impl PartitionBuffer {
             &self.rss_partition_writer,
             self.partition_id,
             batch,
+            self.compression_codec,
             &mut num_bytes_written_uncompressed,
+            &self.bytes_written,
         )?;
         self.data_size_metric.add(num_bytes_written_uncompressed);
         Ok(())
@@ -18,20 +18,29 @@ use blaze_jni_bridge::{jni_call, jni_new_direct_byte_buffer};
 use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::common::Result;
 use datafusion::physical_plan::metrics::Count;
-use datafusion_ext_commons::io::write_one_batch;
+use datafusion_ext_commons::io::{write_one_batch_with_codec, IpcCompressionCodec};
 use jni::objects::GlobalRef;
 use std::io::Cursor;
 
 pub struct RssSingleShuffleRepartitioner {
     rss_partition_writer: GlobalRef,
     data_size_metric: Count,
+    bytes_written: Count,
+    compression_codec: IpcCompressionCodec,
 }
 
 impl RssSingleShuffleRepartitioner {
-    pub fn new(rss_partition_writer: GlobalRef, data_size_metric: Count) -> Self {
+    pub fn new(
+        rss_partition_writer: GlobalRef,
+        data_size_metric: Count,
+        bytes_written: Count,
+        compression_codec: IpcCompressionCodec,
+    ) -> Self {
         Self {
             rss_partition_writer,
             data_size_metric,
+            bytes_written,
+            compression_codec,
         }
     }
 }
@@ -41,10 +50,10 @@ impl ShuffleRepartitioner for RssSingleShuffleRepartitioner {
     async fn insert_batch(&self, input: RecordBatch) -> Result<()> {
         let mut cursor = Cursor::new(Vec::<u8>::new());
         let mut num_bytes_written_uncompressed = 0;
-        write_one_batch(
+        write_one_batch_with_codec(
             &input,
             &mut cursor,
-            true,
+            Some(self.compression_codec),
             Some(&mut num_bytes_written_uncompressed),
         )?;
         self.data_size_metric.add(num_bytes_written_uncompressed);
@@ -56,6 +65,7 @@ impl ShuffleRepartitioner for RssSingleShuffleRepartitioner {
         if length != 0 {
             jni_call!(BlazeRssPartitionWriterBase(self.rss_partition_writer.as_obj())
                 .write(0_i32, rss_buffer.as_obj(), length as i32) -> ())?;
+            self.bytes_written.add(length);
         }
         Ok(())
     }
@@ -24,6 +24,7 @@ use datafusion::common::Result;
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_plan::metrics::Count;
 use datafusion::physical_plan::Partitioning;
+use datafusion_ext_commons::io::IpcCompressionCodec;
 use futures::lock::Mutex;
 use jni::objects::GlobalRef;
 use std::mem::size_of;
@@ -39,15 +40,20 @@ pub struct RssSortShuffleRepartitioner {
     num_output_partitions: usize,
     batch_size: usize,
     data_size_metric: Count,
+    bytes_written: Count,
+    compression_codec: IpcCompressionCodec,
 }
 
 impl RssSortShuffleRepartitioner {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         partition_id: usize,
         rss_partition_writer: GlobalRef,
         schema: SchemaRef,
         partitioning: Partitioning,
         data_size_metric: Count,
+        bytes_written: Count,
+        compression_codec: IpcCompressionCodec,
         context: Arc<TaskContext>,
     ) -> Self {
         let num_output_partitions = partitioning.partition_count();
@@ -63,6 +69,8 @@ impl RssSortShuffleRepartitioner {
             num_output_partitions,
             batch_size,
             data_size_metric,
+            bytes_written,
+            compression_codec,
         }
     }
 
@@ -116,7 +124,9 @@ impl RssSortShuffleRepartitioner {
                     &self.rss_partition_writer,
                     cur_partition_id,
                     sub_batch,
+                    self.compression_codec,
                     &mut num_bytes_written_uncompressed,
+                    &self.bytes_written,
                 )?;
                 self.data_size_metric.add(num_bytes_written_uncompressed);
             }};
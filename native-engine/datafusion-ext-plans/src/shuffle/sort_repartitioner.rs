@@ -15,6 +15,7 @@
 use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
 use crate::common::onheap_spill::{try_new_spill, Spill};
 use crate::common::BatchesInterleaver;
+use crate::shuffle::compaction::{compaction_target_size, BatchCompactor};
 use crate::shuffle::{evaluate_hashes, evaluate_partition_ids, ShuffleRepartitioner, ShuffleSpill};
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
@@ -23,8 +24,9 @@ use datafusion::common::{DataFusionError, Result};
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_plan::metrics::{BaselineMetrics, Count};
 use datafusion::physical_plan::Partitioning;
-use datafusion_ext_commons::io::write_one_batch;
+use datafusion_ext_commons::io::{write_one_batch_with_codec, IpcCompressionCodec};
 use datafusion_ext_commons::loser_tree::LoserTree;
+use datafusion_ext_commons::spill::SpillManager;
 use derivative::Derivative;
 use futures::lock::Mutex;
 use std::fs::{File, OpenOptions};
@@ -44,9 +46,12 @@ pub struct SortShuffleRepartitioner {
     batch_size: usize,
     metrics: BaselineMetrics,
     data_size_metric: Count,
+    compression_codec: IpcCompressionCodec,
+    spill_manager: Arc<SpillManager>,
 }
 
 impl SortShuffleRepartitioner {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         partition_id: usize,
         output_data_file: String,
@@ -55,6 +60,7 @@ impl SortShuffleRepartitioner {
         partitioning: Partitioning,
         metrics: BaselineMetrics,
         data_size_metric: Count,
+        compression_codec: IpcCompressionCodec,
         context: Arc<TaskContext>,
     ) -> Self {
         let num_output_partitions = partitioning.partition_count();
@@ -73,6 +79,8 @@ impl SortShuffleRepartitioner {
             batch_size,
             metrics,
             data_size_metric,
+            compression_codec,
+            spill_manager: SpillManager::get(&context),
         }
     }
 
@@ -130,10 +138,10 @@ impl SortShuffleRepartitioner {
 
                 let mut buf = vec![];
                 let mut num_bytes_written_uncompressed = 0;
-                write_one_batch(
+                write_one_batch_with_codec(
                     &sub_batch,
                     &mut Cursor::new(&mut buf),
-                    true,
+                    Some(self.compression_codec),
                     Some(&mut num_bytes_written_uncompressed),
                 )?;
                 self.data_size_metric.add(num_bytes_written_uncompressed);
@@ -171,7 +179,7 @@ impl SortShuffleRepartitioner {
         let pi_vec = self.build_sorted_pi_vec(buffered_batches)?;
 
         // write to in-mem spill
-        let spill = try_new_spill()?;
+        let spill = try_new_spill(&self.spill_manager)?;
         let offsets =
             self.write_buffered_batches(buffered_batches, pi_vec, &mut spill.get_buf_writer())?;
         spill.complete()?;
@@ -338,6 +346,11 @@ impl ShuffleRepartitioner for SortShuffleRepartitioner {
             .truncate(true)
             .open(data_file)?;
         let mut cur_partition_id = 0;
+        let mut compactor = BatchCompactor::new(
+            self.schema.clone(),
+            compaction_target_size()?,
+            self.compression_codec,
+        );
 
         // append partition in each spills
         tokio::task::spawn_blocking(move || {
@@ -349,6 +362,7 @@ impl ShuffleRepartitioner for SortShuffleRepartitioner {
                     }
 
                     while cur_partition_id < min_spill.cur {
+                        compactor.flush(&mut output_data)?;
                         offsets.push(output_data.stream_position()?);
                         cur_partition_id += 1;
                     }
@@ -359,13 +373,14 @@ impl ShuffleRepartitioner for SortShuffleRepartitioner {
 
                     let spill_range = spill_offset_start as usize..spill_offset_end as usize;
                     let reader = &mut min_spill.reader;
-                    std::io::copy(&mut reader.take(spill_range.len() as u64), &mut output_data)?;
+                    compactor.write_segment(reader, spill_range.len() as u64, &mut output_data)?;
 
                     // forward partition id in min_spill
                     min_spill.cur += 1;
                     min_spill.skip_empty_partitions();
                 }
             }
+            compactor.flush(&mut output_data)?;
             output_data.sync_data()?;
             output_data.flush()?;
 
@@ -410,3 +425,89 @@ pub struct PI {
     #[derivative(Ord = "ignore")]
     pub row_idx: u32,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::{MemConsumer, MemManager};
+    use crate::shuffle::sort_repartitioner::SortShuffleRepartitioner;
+    use crate::shuffle::{evaluate_hashes, evaluate_partition_ids, ShuffleRepartitioner};
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::cast::as_int64_array;
+    use datafusion::common::Result;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::metrics::{
+        BaselineMetrics, ExecutionPlanMetricsSet, MetricBuilder,
+    };
+    use datafusion::physical_plan::Partitioning;
+    use datafusion::prelude::SessionContext;
+    use datafusion_ext_commons::io::{read_one_batch, IpcCompressionCodec};
+    use std::collections::HashSet;
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_sort_repartitioner_merges_spills() -> Result<()> {
+        MemManager::init(1000000);
+        let schema = Arc::new(Schema::new(vec![Field::new("k", DataType::Int64, false)]));
+        let num_partitions = 7;
+        let partitioning = Partitioning::Hash(vec![Arc::new(Column::new("k", 0))], num_partitions);
+
+        let tmp_dir = tempfile::tempdir()?;
+        let data_file = tmp_dir.path().join("shuffle.data");
+        let index_file = tmp_dir.path().join("shuffle.index");
+        let metrics = ExecutionPlanMetricsSet::new();
+        let repartitioner = Arc::new(SortShuffleRepartitioner::new(
+            0,
+            data_file.to_string_lossy().to_string(),
+            index_file.to_string_lossy().to_string(),
+            schema.clone(),
+            partitioning.clone(),
+            BaselineMetrics::new(&metrics, 0),
+            MetricBuilder::new(&metrics).counter("data_size", 0),
+            IpcCompressionCodec::default(),
+            SessionContext::new().task_ctx(),
+        ));
+        MemManager::register_consumer(repartitioner.clone(), true);
+
+        // spill after every batch, so the output is merged from sorted spills
+        for i in 0..5 {
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int64Array::from_iter_values(i * 1000..(i + 1) * 1000))],
+            )?;
+            repartitioner.insert_batch(batch).await?;
+            repartitioner.spill().await?;
+        }
+        repartitioner.shuffle_write().await?;
+
+        // every row is written once into its own partition
+        let offsets = std::fs::read(&index_file)?
+            .chunks(8)
+            .map(|offset| i64::from_le_bytes(offset.try_into().unwrap()) as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets.len(), num_partitions + 1);
+        let data = std::fs::read(&data_file)?;
+        assert_eq!(offsets[num_partitions], data.len());
+
+        let mut keys = HashSet::new();
+        for partition_id in 0..num_partitions {
+            let mut cursor = Cursor::new(&data[offsets[partition_id]..offsets[partition_id + 1]]);
+            let mut num_frames = 0;
+            while let Some(batch) = read_one_batch(&mut cursor, Some(schema.clone()), true)? {
+                num_frames += 1;
+                let hashes = evaluate_hashes(&partitioning, &batch)?;
+                assert!(evaluate_partition_ids(&hashes, num_partitions)
+                    .iter()
+                    .all(|&id| id as usize == partition_id));
+                keys.extend(as_int64_array(batch.column(0))?.values().iter().copied());
+            }
+
+            // small batches from all spills are compacted into one frame
+            assert_eq!(num_frames, 1);
+        }
+        assert_eq!(keys, (0..5000).collect::<HashSet<i64>>());
+        Ok(())
+    }
+}
@@ -20,10 +20,16 @@ use std::sync::Arc;
 
 use crate::common::batch_statisitcs::{stat_input, InputBatchStatistics};
 use crate::common::memory_manager::MemManager;
+use crate::shuffle::agg_shuffle::{
+    agg_shuffle_enabled, agg_shuffle_mem_budget, execute_agg_shuffle_input, mergeable_partial_agg,
+};
 use crate::shuffle::bucket_repartitioner::BucketShuffleRepartitioner;
 use crate::shuffle::single_repartitioner::SingleShuffleRepartitioner;
 use crate::shuffle::sort_repartitioner::SortShuffleRepartitioner;
-use crate::shuffle::{can_use_bucket_repartitioner, ShuffleRepartitioner};
+use crate::shuffle::{
+    can_use_bucket_repartitioner, partition_flush_threshold, ShuffleRepartitioner,
+    ShuffleWriteMetrics,
+};
 use arrow::datatypes::SchemaRef;
 use arrow::error::ArrowError;
 use async_trait::async_trait;
@@ -37,9 +43,10 @@ use datafusion::physical_plan::ExecutionPlan;
 use datafusion::physical_plan::Partitioning;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use datafusion::physical_plan::Statistics;
-use datafusion::physical_plan::{DisplayAs, DisplayFormatType};
+use datafusion::physical_plan::{DisplayAs, DisplayFormatType, EmptyRecordBatchStream};
+use datafusion_ext_commons::io::IpcCompressionCodec;
 use futures::stream::once;
-use futures::{TryFutureExt, TryStreamExt};
+use futures::{StreamExt, TryFutureExt, TryStreamExt};
 
 /// The shuffle writer operator maps each input partition to M output partitions based on a
 /// partitioning scheme. No guarantees are made about the order of the resulting partitions.
@@ -53,6 +60,8 @@ pub struct ShuffleWriterExec {
     output_data_file: String,
     /// Output index file path
     output_index_file: String,
+    /// codec for compressing the shuffle data
+    compression_codec: IpcCompressionCodec,
     /// Metrics
     metrics: ExecutionPlanMetricsSet,
 }
@@ -92,12 +101,15 @@ impl ExecutionPlan for ShuffleWriterExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match children.len() {
-            1 => Ok(Arc::new(ShuffleWriterExec::try_new(
-                children[0].clone(),
-                self.partitioning.clone(),
-                self.output_data_file.clone(),
-                self.output_index_file.clone(),
-            )?)),
+            1 => Ok(Arc::new(
+                ShuffleWriterExec::try_new(
+                    children[0].clone(),
+                    self.partitioning.clone(),
+                    self.output_data_file.clone(),
+                    self.output_index_file.clone(),
+                )?
+                .with_compression_codec(self.compression_codec),
+            )),
             _ => Err(DataFusionError::Internal(
                 "ShuffleWriterExec wrong number of children".to_string(),
             )),
@@ -118,6 +130,7 @@ impl ExecutionPlan for ShuffleWriterExec {
                 self.output_index_file.clone(),
                 BaselineMetrics::new(&self.metrics, partition),
                 data_size_metric,
+                self.compression_codec,
             )),
             p @ Partitioning::Hash(_, _)
                 if can_use_bucket_repartitioner(&self.input.schema())
@@ -131,6 +144,8 @@ impl ExecutionPlan for ShuffleWriterExec {
                     self.partitioning.clone(),
                     BaselineMetrics::new(&self.metrics, partition),
                     data_size_metric,
+                    self.compression_codec,
+                    partition_flush_threshold()?,
                     context.clone(),
                 ));
                 MemManager::register_consumer(partitioner.clone(), true);
@@ -145,6 +160,7 @@ impl ExecutionPlan for ShuffleWriterExec {
                     self.partitioning.clone(),
                     BaselineMetrics::new(&self.metrics, partition),
                     data_size_metric,
+                    self.compression_codec,
                     context.clone(),
                 ));
                 MemManager::register_consumer(partitioner.clone(), true);
@@ -153,17 +169,45 @@ impl ExecutionPlan for ShuffleWriterExec {
             p => unreachable!("unsupported partitioning: {:?}", p),
         };
 
+        // partial aggregation before shuffling can be merged into shuffle writing
+        let merged_partial_agg = if agg_shuffle_enabled()? {
+            mergeable_partial_agg(&self.input, &self.partitioning)
+        } else {
+            None
+        };
         let input = stat_input(
             InputBatchStatistics::from_metrics_set_and_blaze_conf(&self.metrics, partition)?,
-            self.input.execute(partition, context.clone())?,
+            match merged_partial_agg {
+                Some(agg) => execute_agg_shuffle_input(
+                    agg,
+                    partition,
+                    self.partitioning.clone(),
+                    agg_shuffle_mem_budget()?,
+                    context.clone(),
+                )?,
+                None => self.input.execute(partition, context.clone())?,
+            },
         )?;
+        let write_metrics = ShuffleWriteMetrics::new(&self.metrics, partition);
+        let bytes_written = write_metrics.bytes_written.clone();
+        let output_data_file = self.output_data_file.clone();
+        let output_schema = self.schema();
         let stream = repartitioner
             .execute(
                 context.clone(),
                 input,
                 context.session_config().batch_size(),
                 BaselineMetrics::new(&self.metrics, partition),
+                write_metrics,
             )
+            .and_then(|mut stream| async move {
+                // shuffle writer produces no output, wait for the data file
+                // to be completely written and record its size
+                while stream.next().await.transpose()?.is_some() {}
+                bytes_written.add(std::fs::metadata(&output_data_file)?.len() as usize);
+                Ok::<_, DataFusionError>(Box::pin(EmptyRecordBatchStream::new(output_schema))
+                    as SendableRecordBatchStream)
+            })
             .map_err(|e| ArrowError::ExternalError(Box::new(e)));
 
         Ok(Box::pin(RecordBatchStreamAdapter::new(
@@ -195,6 +239,78 @@ impl ShuffleWriterExec {
             metrics: ExecutionPlanMetricsSet::new(),
             output_data_file,
             output_index_file,
+            compression_codec: IpcCompressionCodec::default(),
         })
     }
+
+    /// Sets the codec for compressing the shuffle data
+    pub fn with_compression_codec(self, compression_codec: IpcCompressionCodec) -> Self {
+        Self {
+            compression_codec,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::MemManager;
+    use crate::shuffle_writer_exec::ShuffleWriterExec;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan, Partitioning};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_shuffle_write_metrics() -> Result<()> {
+        MemManager::init(10000);
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3, 4, 5]))],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(
+            &[vec![batch.clone(), batch]],
+            schema,
+            None,
+        )?);
+
+        let tmp_dir = tempfile::tempdir()?;
+        let data_file = tmp_dir.path().join("shuffle.data");
+        let index_file = tmp_dir.path().join("shuffle.index");
+        let shuffle_writer = ShuffleWriterExec::try_new(
+            input,
+            Partitioning::UnknownPartitioning(1),
+            data_file.to_string_lossy().to_string(),
+            index_file.to_string_lossy().to_string(),
+        )?;
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let output = shuffle_writer.execute(0, task_ctx)?;
+        assert!(common::collect(output).await?.is_empty());
+
+        // these are the values reported to spark's ShuffleWriteMetrics
+        let metrics = shuffle_writer.metrics().unwrap();
+        let bytes_written = metrics.sum_by_name("shuffle_bytes_written").unwrap();
+        let records_written = metrics.sum_by_name("shuffle_records_written").unwrap();
+        let write_time = metrics.sum_by_name("shuffle_write_time").unwrap();
+        assert_eq!(
+            bytes_written.as_usize(),
+            std::fs::metadata(&data_file)?.len() as usize
+        );
+        assert!(bytes_written.as_usize() > 0);
+        assert_eq!(records_written.as_usize(), 10);
+        assert!(write_time.as_usize() > 0);
+
+        // the last offset of the index file equals to the bytes written
+        let index = std::fs::read(&index_file)?;
+        let last_offset = i64::from_le_bytes(index[index.len() - 8..].try_into().unwrap());
+        assert_eq!(last_offset as usize, bytes_written.as_usize());
+        Ok(())
+    }
 }
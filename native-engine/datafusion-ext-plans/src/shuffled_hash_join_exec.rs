@@ -0,0 +1,1088 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::join_hash_map::{evaluate_keys, JoinHashMap};
+use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
+use crate::common::onheap_spill::{try_new_spill, Spill};
+use crate::common::output::{output_with_sender, WrappedRecordBatchSender};
+use crate::common::BatchTaker;
+use arrow::array::{new_null_array, ArrayRef, BooleanArray, UInt32Array, UInt32Builder};
+use arrow::compute::{concat_batches, filter_record_batch, take};
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use async_trait::async_trait;
+use datafusion::common::{Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::logical_expr::JoinType;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::expressions::Column;
+use datafusion::physical_plan::joins::utils::{
+    build_join_schema, check_join_is_valid, JoinOn, JoinSide,
+};
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
+};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+};
+use datafusion_ext_commons::io::read_one_batch;
+use datafusion_ext_commons::spark_hash::{create_hashes, pmod};
+use datafusion_ext_commons::spill::SpillManager;
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
+use futures::stream::once;
+use futures::{StreamExt, TryStreamExt};
+use parking_lot::Mutex;
+use std::any::Any;
+use std::fmt::Formatter;
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Weak};
+
+// number of partitions the build side is split into, partitions are spilled
+// and joined independently
+const NUM_SPILL_PARTITIONS: usize = 16;
+
+// rows are already shuffled by murmur3 hash with seed 42, use another seed to
+// get evenly distributed spill partitions. spilled partitions which are split
+// again use a different seed on each level
+const SPILL_PARTITION_HASH_SEED: u32 = 0x3c6ef372;
+
+// max number of times a spilled partition is split again, partitions are not
+// split further if most rows have the same key
+const MAX_REPARTITION_LEVEL: usize = 3;
+
+/// Shuffled hash join, builds a hash map from the build side of each partition
+/// and probes it with the other side.
+///
+/// the build side is hash partitioned while being read. when exceeding the
+/// memory budget, the largest in-memory partition is spilled. probed rows
+/// belonging to spilled partitions are spilled as well, and spilled partitions
+/// are joined one by one after the in-memory ones (hybrid hash join). spilled
+/// partitions too large to fit in memory are split again into smaller ones.
+#[derive(Debug)]
+pub struct ShuffledHashJoinExec {
+    left: Arc<dyn ExecutionPlan>,
+    right: Arc<dyn ExecutionPlan>,
+    on: JoinOn,
+    join_type: JoinType,
+    build_side: JoinSide,
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl ShuffledHashJoinExec {
+    pub fn try_new(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        on: JoinOn,
+        join_type: JoinType,
+        build_side: JoinSide,
+    ) -> Result<Self> {
+        let left_schema = left.schema();
+        let right_schema = right.schema();
+        check_join_is_valid(&left_schema, &right_schema, &on)?;
+        let schema = Arc::new(build_join_schema(&left_schema, &right_schema, &join_type).0);
+
+        Ok(Self {
+            left,
+            right,
+            on,
+            join_type,
+            build_side,
+            schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+
+    fn create_joiner(
+        &self,
+        partition: usize,
+        context: &Arc<TaskContext>,
+    ) -> Arc<ShuffledHashJoiner> {
+        let batch_size = context.session_config().batch_size();
+        let (left_keys, right_keys): (Vec<Column>, Vec<Column>) = self.on.iter().cloned().unzip();
+        let (build, probe, build_keys, probe_keys) = match self.build_side {
+            JoinSide::Left => (&self.left, &self.right, left_keys, right_keys),
+            JoinSide::Right => (&self.right, &self.left, right_keys, left_keys),
+        };
+        let joiner = Arc::new(ShuffledHashJoiner {
+            name: format!("ShuffledHashJoin[partition={}]", partition),
+            mem_consumer_info: None,
+            ctx: JoinContext {
+                join_type: self.join_type,
+                build_side: self.build_side,
+                build_keys,
+                probe_keys,
+                build_schema: build.schema(),
+                probe_schema: probe.schema(),
+                output_schema: self.schema(),
+                batch_size: batch_size.max(1),
+            },
+            partitions: Mutex::new(
+                (0..NUM_SPILL_PARTITIONS)
+                    .map(|_| BuildPartition::default())
+                    .collect(),
+            ),
+            spill_manager: SpillManager::get(context),
+            metrics: BaselineMetrics::new(&self.metrics, partition),
+            spill_count: MetricBuilder::new(&self.metrics).spill_count(partition),
+        });
+        MemManager::register_consumer(joiner.clone(), true);
+        joiner
+    }
+}
+
+impl DisplayAs for ShuffledHashJoinExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "ShuffledHashJoin")
+    }
+}
+
+impl ExecutionPlan for ShuffledHashJoinExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        match self.build_side {
+            JoinSide::Left => self.right.output_partitioning(),
+            JoinSide::Right => self.left.output_partitioning(),
+        }
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.left.clone(), self.right.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(Self::try_new(
+            children[0].clone(),
+            children[1].clone(),
+            self.on.clone(),
+            self.join_type,
+            self.build_side,
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let batch_size = context.session_config().batch_size();
+        let joiner = self.create_joiner(partition, &context);
+        let (build_stream, probe_stream) = match self.build_side {
+            JoinSide::Left => (
+                self.left.execute(partition, context.clone())?,
+                self.right.execute(partition, context.clone())?,
+            ),
+            JoinSide::Right => (
+                self.right.execute(partition, context.clone())?,
+                self.left.execute(partition, context.clone())?,
+            ),
+        };
+        let output = Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            once(execute_shuffled_hash_join(
+                joiner,
+                build_stream,
+                probe_stream,
+                context,
+            ))
+            .try_flatten(),
+        ));
+        let elapsed_compute = BaselineMetrics::new(&self.metrics, partition)
+            .elapsed_compute()
+            .clone();
+        Ok(Box::pin(
+            CoalesceStream::new(output, batch_size, elapsed_compute)
+                .with_byte_budget(batch_byte_budget()?),
+        ))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+async fn execute_shuffled_hash_join(
+    joiner: Arc<ShuffledHashJoiner>,
+    mut build_stream: SendableRecordBatchStream,
+    probe_stream: SendableRecordBatchStream,
+    context: Arc<TaskContext>,
+) -> Result<SendableRecordBatchStream> {
+    while let Some(batch) = build_stream
+        .next()
+        .await
+        .transpose()
+        .map_err(|err| err.context("shuffled_hash_join: polling build batches error"))?
+    {
+        joiner
+            .insert_build_batch(batch)
+            .await
+            .map_err(|err| err.context("shuffled_hash_join: inserting build batch error"))?;
+    }
+
+    let output_schema = joiner.ctx.output_schema.clone();
+    output_with_sender(
+        "ShuffledHashJoin",
+        context,
+        output_schema,
+        move |sender| async move {
+            joiner.output(probe_stream, sender).await?;
+            Ok(())
+        },
+    )
+}
+
+#[derive(Clone)]
+struct JoinContext {
+    join_type: JoinType,
+    build_side: JoinSide,
+    build_keys: Vec<Column>,
+    probe_keys: Vec<Column>,
+    build_schema: SchemaRef,
+    probe_schema: SchemaRef,
+    output_schema: SchemaRef,
+    batch_size: usize,
+}
+
+impl JoinContext {
+    fn build_is_left(&self) -> bool {
+        matches!(self.build_side, JoinSide::Left)
+    }
+
+    fn outputs_pairs(&self) -> bool {
+        matches!(
+            self.join_type,
+            JoinType::Inner | JoinType::Left | JoinType::Right | JoinType::Full
+        )
+    }
+
+    fn probe_outer(&self) -> bool {
+        match self.join_type {
+            JoinType::Left => !self.build_is_left(),
+            JoinType::Right => self.build_is_left(),
+            JoinType::Full => true,
+            _ => false,
+        }
+    }
+
+    fn build_outer(&self) -> bool {
+        match self.join_type {
+            JoinType::Left => self.build_is_left(),
+            JoinType::Right => !self.build_is_left(),
+            JoinType::Full => true,
+            _ => false,
+        }
+    }
+
+    /// returns Some(true) for semi join and Some(false) for anti join, if only
+    /// probed rows are output
+    fn probe_semi(&self) -> Option<bool> {
+        match (self.join_type, self.build_is_left()) {
+            (JoinType::LeftSemi, false) | (JoinType::RightSemi, true) => Some(true),
+            (JoinType::LeftAnti, false) | (JoinType::RightAnti, true) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// returns Some(true) for semi join and Some(false) for anti join, if only
+    /// build side rows are output
+    fn build_semi(&self) -> Option<bool> {
+        match (self.join_type, self.build_is_left()) {
+            (JoinType::LeftSemi, true) | (JoinType::RightSemi, false) => Some(true),
+            (JoinType::LeftAnti, true) | (JoinType::RightAnti, false) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn tracks_visited(&self) -> bool {
+        self.build_outer() || self.build_semi().is_some()
+    }
+}
+
+#[derive(Default)]
+struct BuildPartition {
+    batches: Vec<RecordBatch>,
+    mem_size: usize,
+    spills: Vec<Box<dyn Spill>>,
+    spilled_mem_size: usize,
+}
+
+impl BuildPartition {
+    fn spill(&mut self, spill_manager: &Arc<SpillManager>) -> Result<()> {
+        if self.batches.is_empty() {
+            return Ok(());
+        }
+        let spill = try_new_spill(spill_manager)?;
+        let mut writer = spill.get_buf_writer();
+        for batch in std::mem::take(&mut self.batches) {
+            spill_manager.write_batch(&batch, &mut writer)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        spill.complete()?;
+        self.spills.push(spill);
+        self.spilled_mem_size += std::mem::take(&mut self.mem_size);
+        Ok(())
+    }
+}
+
+/// A spilled partition of both sides, joined after the in-memory partitions
+struct SpilledPartition {
+    build_spills: Vec<Box<dyn Spill>>,
+    build_mem_size: usize,
+    probe_spill: Box<dyn Spill>,
+    level: usize,
+}
+
+impl SpilledPartition {
+    fn disk_usage(&self) -> Result<u64> {
+        let mut disk_usage = self.probe_spill.get_disk_usage()?;
+        for build_spill in &self.build_spills {
+            disk_usage += build_spill.get_disk_usage()?;
+        }
+        Ok(disk_usage)
+    }
+}
+
+struct ShuffledHashJoiner {
+    name: String,
+    mem_consumer_info: Option<Weak<MemConsumerInfo>>,
+    ctx: JoinContext,
+    partitions: Mutex<Vec<BuildPartition>>,
+    spill_manager: Arc<SpillManager>,
+    metrics: BaselineMetrics,
+    spill_count: Count,
+}
+
+#[async_trait]
+impl MemConsumer for ShuffledHashJoiner {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_consumer_info(&mut self, consumer_info: Weak<MemConsumerInfo>) {
+        self.mem_consumer_info = Some(consumer_info);
+    }
+
+    fn get_consumer_info(&self) -> &Weak<MemConsumerInfo> {
+        self.mem_consumer_info
+            .as_ref()
+            .expect("consumer info not set")
+    }
+
+    async fn spill(&self) -> Result<()> {
+        // spill out the largest in-memory partition
+        let mem_used = {
+            let mut partitions = self.partitions.lock();
+            if let Some(max_partition) = partitions
+                .iter_mut()
+                .filter(|partition| partition.mem_size > 0)
+                .max_by_key(|partition| partition.mem_size)
+            {
+                max_partition.spill(&self.spill_manager)?;
+                self.spill_count.add(1);
+            }
+            partitions.iter().map(|p| p.mem_size).sum::<usize>()
+        };
+
+        // adjust memory usage
+        self.update_mem_used(mem_used).await?;
+        Ok(())
+    }
+}
+
+impl Drop for ShuffledHashJoiner {
+    fn drop(&mut self) {
+        MemManager::deregister_consumer(self);
+    }
+}
+
+impl ShuffledHashJoiner {
+    async fn insert_build_batch(&self, batch: RecordBatch) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        let mem_used = {
+            let _timer = self.metrics.elapsed_compute().timer();
+            let (sorted, offsets) = partition_batch(&batch, &self.ctx.build_keys, 0)?;
+            let sorted_mem_size = sorted.get_array_memory_size();
+
+            let mut partitions = self.partitions.lock();
+            for (partition_id, partition) in partitions.iter_mut().enumerate() {
+                let len = offsets[partition_id + 1] - offsets[partition_id];
+                if len > 0 {
+                    // slices share buffers of the sorted batch, estimate memory
+                    // usage by number of rows
+                    partition.mem_size += sorted_mem_size * len / sorted.num_rows();
+                    partition
+                        .batches
+                        .push(sorted.slice(offsets[partition_id], len));
+                }
+            }
+            partitions.iter().map(|p| p.mem_size).sum::<usize>()
+        };
+
+        // adjust memory usage
+        self.update_mem_used(mem_used).await?;
+        Ok(())
+    }
+
+    async fn output(
+        self: Arc<Self>,
+        mut probe_stream: SendableRecordBatchStream,
+        sender: Arc<WrappedRecordBatchSender>,
+    ) -> Result<()> {
+        self.set_spillable(false);
+        let ctx = &self.ctx;
+
+        // a partition is either fully in memory or fully spilled
+        let (in_mem_batches, spilled) = {
+            let mut partitions = self.partitions.lock();
+            let mut in_mem_batches = vec![];
+            let mut spilled = vec![];
+            for (partition_id, partition) in partitions.iter_mut().enumerate() {
+                if partition.spills.is_empty() {
+                    in_mem_batches.extend(std::mem::take(&mut partition.batches));
+                } else {
+                    partition.spill(&self.spill_manager)?;
+                    spilled.push((
+                        partition_id,
+                        std::mem::take(&mut partition.spills),
+                        std::mem::take(&mut partition.spilled_mem_size),
+                    ));
+                }
+            }
+            (in_mem_batches, spilled)
+        };
+        log::info!(
+            "{} starts outputting with {} spilled partitions",
+            self.name(),
+            spilled.len(),
+        );
+
+        let hash_map = JoinHashMap::try_new(
+            concat_batches(&ctx.build_schema, &in_mem_batches)?,
+            &ctx.build_keys,
+        )?;
+        drop(in_mem_batches);
+        self.update_mem_used(hash_map.mem_size()).await?;
+
+        // probed rows of spilled partitions are written to spills
+        let mut probe_spills: Vec<Option<(Box<dyn Spill>, BufWriter<Box<dyn Write + Send>>)>> =
+            (0..NUM_SPILL_PARTITIONS).map(|_| None).collect();
+        for (partition_id, ..) in &spilled {
+            let spill = try_new_spill(&self.spill_manager)?;
+            let writer = spill.get_buf_writer();
+            probe_spills[*partition_id] = Some((spill, writer));
+        }
+
+        let mut visited = vec![false; hash_map.batch().num_rows()];
+        while let Some(batch) = probe_stream.next().await.transpose()? {
+            let mut timer = self.metrics.elapsed_compute().timer();
+            let in_mem_probed = if spilled.is_empty() {
+                batch
+            } else {
+                let (sorted, offsets) = partition_batch(&batch, &ctx.probe_keys, 0)?;
+                let mut in_mem_indices = vec![];
+                for partition_id in 0..NUM_SPILL_PARTITIONS {
+                    let start = offsets[partition_id];
+                    let end = offsets[partition_id + 1];
+                    match &mut probe_spills[partition_id] {
+                        Some((_, writer)) if end > start => {
+                            self.spill_manager
+                                .write_batch(&sorted.slice(start, end - start), writer)?;
+                        }
+                        Some(_) => {}
+                        None => in_mem_indices.extend(start..end),
+                    }
+                }
+                BatchTaker(&sorted).take(in_mem_indices)?
+            };
+            for output_batch in join_probed_batch(ctx, &hash_map, &mut visited, &in_mem_probed)? {
+                self.metrics.record_output(output_batch.num_rows());
+                sender.send(Ok(output_batch), Some(&mut timer)).await;
+            }
+        }
+        for output_batch in output_build_side_remaining(ctx, &hash_map, &visited)? {
+            self.metrics.record_output(output_batch.num_rows());
+            sender.send(Ok(output_batch), None).await;
+        }
+        drop(hash_map);
+
+        // join spilled partitions one by one
+        let mut spilled_partitions = vec![];
+        for (partition_id, build_spills, build_mem_size) in spilled {
+            let (probe_spill, mut probe_spill_writer) = probe_spills[partition_id]
+                .take()
+                .expect("missing probe spill");
+            probe_spill_writer.flush()?;
+            drop(probe_spill_writer);
+            probe_spill.complete()?;
+            spilled_partitions.push(SpilledPartition {
+                build_spills,
+                build_mem_size,
+                probe_spill,
+                level: 0,
+            });
+        }
+
+        let mm = MemManager::get();
+        let max_partition_mem_size = mm.total() / mm.num_consumers().max(1);
+        let mut spill_disk_usage = 0;
+        while let Some(spilled) = spilled_partitions.pop() {
+            spill_disk_usage += spilled.disk_usage()?;
+
+            // split partitions which cannot fit in memory
+            if spilled.build_mem_size > max_partition_mem_size {
+                if spilled.level < MAX_REPARTITION_LEVEL {
+                    spilled_partitions.extend(self.repartition_spilled(spilled)?);
+                    continue;
+                }
+                log::warn!(
+                    "{} joins an oversized spilled partition ({} bytes) after {} repartitions",
+                    self.name(),
+                    spilled.build_mem_size,
+                    spilled.level,
+                );
+            }
+
+            let mut build_batches = vec![];
+            for build_spill in &spilled.build_spills {
+                let mut reader = build_spill.get_buf_reader();
+                while let Some(batch) =
+                    read_one_batch(&mut reader, Some(ctx.build_schema.clone()), true)?
+                {
+                    build_batches.push(batch);
+                }
+            }
+            let hash_map = JoinHashMap::try_new(
+                concat_batches(&ctx.build_schema, &build_batches)?,
+                &ctx.build_keys,
+            )?;
+            drop(build_batches);
+            self.update_mem_used(hash_map.mem_size()).await?;
+
+            let mut visited = vec![false; hash_map.batch().num_rows()];
+            let mut reader = spilled.probe_spill.get_buf_reader();
+            while let Some(batch) =
+                read_one_batch(&mut reader, Some(ctx.probe_schema.clone()), true)?
+            {
+                let mut timer = self.metrics.elapsed_compute().timer();
+                for output_batch in join_probed_batch(ctx, &hash_map, &mut visited, &batch)? {
+                    self.metrics.record_output(output_batch.num_rows());
+                    sender.send(Ok(output_batch), Some(&mut timer)).await;
+                }
+            }
+            for output_batch in output_build_side_remaining(ctx, &hash_map, &visited)? {
+                self.metrics.record_output(output_batch.num_rows());
+                sender.send(Ok(output_batch), None).await;
+            }
+        }
+        self.metrics.record_spill(spill_disk_usage as usize);
+        self.update_mem_used(0).await?;
+        Ok(())
+    }
+
+    /// Splits a spilled partition with the hash seed of the next level. rows
+    /// with the same key are in the same sub-partition on both sides.
+    fn repartition_spilled(&self, spilled: SpilledPartition) -> Result<Vec<SpilledPartition>> {
+        let ctx = &self.ctx;
+        let level = spilled.level + 1;
+        let (build_spills, build_mem_sizes) = repartition_spills(
+            &self.spill_manager,
+            &spilled.build_spills,
+            &ctx.build_schema,
+            &ctx.build_keys,
+            level,
+        )?;
+        let (probe_spills, _) = repartition_spills(
+            &self.spill_manager,
+            std::slice::from_ref(&spilled.probe_spill),
+            &ctx.probe_schema,
+            &ctx.probe_keys,
+            level,
+        )?;
+        Ok(build_spills
+            .into_iter()
+            .zip(build_mem_sizes)
+            .zip(probe_spills)
+            .map(
+                |((build_spill, build_mem_size), probe_spill)| SpilledPartition {
+                    build_spills: vec![build_spill],
+                    build_mem_size,
+                    probe_spill,
+                    level,
+                },
+            )
+            .collect())
+    }
+}
+
+/// Joins a probed batch with the hash map, marking matched build side rows as
+/// visited. outputs batches with at most `batch_size` rows.
+fn join_probed_batch(
+    ctx: &JoinContext,
+    hash_map: &JoinHashMap,
+    visited: &mut [bool],
+    probed_batch: &RecordBatch,
+) -> Result<Vec<RecordBatch>> {
+    if probed_batch.num_rows() == 0 {
+        return Ok(vec![]);
+    }
+    let matched = hash_map.probe(probed_batch, &ctx.probe_keys)?;
+    if ctx.tracks_visited() {
+        for &build_idx in matched.iter().flatten().flat_map(|m| m.iter()) {
+            visited[build_idx as usize] = true;
+        }
+    }
+
+    if let Some(semi) = ctx.probe_semi() {
+        let selected = BooleanArray::from_iter(matched.iter().map(|m| Some(m.is_some() == semi)));
+        let filtered = filter_record_batch(probed_batch, &selected)?;
+        if filtered.num_rows() == 0 {
+            return Ok(vec![]);
+        }
+        return Ok(vec![RecordBatch::try_new(
+            ctx.output_schema.clone(),
+            filtered.columns().to_vec(),
+        )?]);
+    }
+    if !ctx.outputs_pairs() {
+        return Ok(vec![]); // build side semi/anti join outputs after probing
+    }
+
+    let probe_outer = ctx.probe_outer();
+    let mut build_indices = UInt32Builder::new();
+    let mut probed_indices = UInt32Builder::new();
+    for (row_idx, matched) in matched.iter().enumerate() {
+        match matched {
+            Some(matched) => {
+                for &build_idx in matched.iter() {
+                    build_indices.append_value(build_idx);
+                    probed_indices.append_value(row_idx as u32);
+                }
+            }
+            None if probe_outer => {
+                build_indices.append_null();
+                probed_indices.append_value(row_idx as u32);
+            }
+            None => {}
+        }
+    }
+    let build_indices = build_indices.finish();
+    let probed_indices = probed_indices.finish();
+
+    let mut output_batches = vec![];
+    for start in (0..probed_indices.len()).step_by(ctx.batch_size) {
+        let len = ctx.batch_size.min(probed_indices.len() - start);
+        let build_cols = take_cols(hash_map.batch(), &build_indices.slice(start, len))?;
+        let probe_cols = take_cols(probed_batch, &probed_indices.slice(start, len))?;
+        output_batches.push(build_output_batch(ctx, build_cols, probe_cols, len)?);
+    }
+    Ok(output_batches)
+}
+
+/// Outputs build side rows after all rows are probed, including unmatched rows
+/// of outer joins and matched/unmatched rows of semi/anti joins.
+fn output_build_side_remaining(
+    ctx: &JoinContext,
+    hash_map: &JoinHashMap,
+    visited: &[bool],
+) -> Result<Vec<RecordBatch>> {
+    let output_visited = match (ctx.build_outer(), ctx.build_semi()) {
+        (true, _) => false,
+        (false, Some(semi)) => semi,
+        (false, None) => return Ok(vec![]),
+    };
+    let indices: UInt32Array = visited
+        .iter()
+        .enumerate()
+        .filter(|(_, &v)| v == output_visited)
+        .map(|(idx, _)| idx as u32)
+        .collect();
+
+    let mut output_batches = vec![];
+    for start in (0..indices.len()).step_by(ctx.batch_size) {
+        let len = ctx.batch_size.min(indices.len() - start);
+        let build_cols = take_cols(hash_map.batch(), &indices.slice(start, len))?;
+        if ctx.build_semi().is_some() {
+            output_batches.push(RecordBatch::try_new(ctx.output_schema.clone(), build_cols)?);
+            continue;
+        }
+        let probe_cols = ctx
+            .probe_schema
+            .fields()
+            .iter()
+            .map(|field| new_null_array(field.data_type(), len))
+            .collect();
+        output_batches.push(build_output_batch(ctx, build_cols, probe_cols, len)?);
+    }
+    Ok(output_batches)
+}
+
+fn build_output_batch(
+    ctx: &JoinContext,
+    build_cols: Vec<ArrayRef>,
+    probe_cols: Vec<ArrayRef>,
+    num_rows: usize,
+) -> Result<RecordBatch> {
+    let cols = if ctx.build_is_left() {
+        [build_cols, probe_cols].concat()
+    } else {
+        [probe_cols, build_cols].concat()
+    };
+    Ok(RecordBatch::try_new_with_options(
+        ctx.output_schema.clone(),
+        cols,
+        &RecordBatchOptions::new().with_row_count(Some(num_rows)),
+    )?)
+}
+
+fn take_cols(batch: &RecordBatch, indices: &UInt32Array) -> Result<Vec<ArrayRef>> {
+    Ok(batch
+        .columns()
+        .iter()
+        .map(|col| take(col, indices, None))
+        .collect::<std::result::Result<_, _>>()?)
+}
+
+/// Reorders rows by their spill partitions of the given level, returns the
+/// reordered batch and the row offsets of each partition.
+fn partition_batch(
+    batch: &RecordBatch,
+    key_exprs: &[Column],
+    level: usize,
+) -> Result<(RecordBatch, Vec<usize>)> {
+    let keys = evaluate_keys(batch, key_exprs)?;
+    let seed = SPILL_PARTITION_HASH_SEED.wrapping_add(level as u32);
+    let mut hashes = vec![seed; batch.num_rows()];
+    create_hashes(&keys, &mut hashes)?;
+    let partition_ids: Vec<usize> = hashes
+        .iter()
+        .map(|&hash| pmod(hash, NUM_SPILL_PARTITIONS))
+        .collect();
+
+    let mut offsets = vec![0; NUM_SPILL_PARTITIONS + 1];
+    for &partition_id in &partition_ids {
+        offsets[partition_id + 1] += 1;
+    }
+    for i in 0..NUM_SPILL_PARTITIONS {
+        offsets[i + 1] += offsets[i];
+    }
+    let mut cur_offsets = offsets.clone();
+    let mut indices = vec![0; batch.num_rows()];
+    for (row_idx, &partition_id) in partition_ids.iter().enumerate() {
+        indices[cur_offsets[partition_id]] = row_idx;
+        cur_offsets[partition_id] += 1;
+    }
+    Ok((BatchTaker(batch).take(indices)?, offsets))
+}
+
+/// Reads all batches of the spills and writes them to a new spill for each
+/// partition of the given level, returns the new spills and the estimated
+/// memory sizes of batches written to them.
+fn repartition_spills(
+    spill_manager: &Arc<SpillManager>,
+    spills: &[Box<dyn Spill>],
+    schema: &SchemaRef,
+    key_exprs: &[Column],
+    level: usize,
+) -> Result<(Vec<Box<dyn Spill>>, Vec<usize>)> {
+    let output_spills = (0..NUM_SPILL_PARTITIONS)
+        .map(|_| try_new_spill(spill_manager))
+        .collect::<Result<Vec<_>>>()?;
+    let mut writers: Vec<_> = output_spills
+        .iter()
+        .map(|spill| spill.get_buf_writer())
+        .collect();
+    let mut mem_sizes = vec![0; NUM_SPILL_PARTITIONS];
+
+    for spill in spills {
+        let mut reader = spill.get_buf_reader();
+        while let Some(batch) = read_one_batch(&mut reader, Some(schema.clone()), true)? {
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            let (sorted, offsets) = partition_batch(&batch, key_exprs, level)?;
+            let sorted_mem_size = sorted.get_array_memory_size();
+            for (partition_id, writer) in writers.iter_mut().enumerate() {
+                let len = offsets[partition_id + 1] - offsets[partition_id];
+                if len > 0 {
+                    mem_sizes[partition_id] += sorted_mem_size * len / sorted.num_rows();
+                    spill_manager.write_batch(&sorted.slice(offsets[partition_id], len), writer)?;
+                }
+            }
+        }
+    }
+    for (spill, mut writer) in output_spills.iter().zip(writers) {
+        writer.flush()?;
+        drop(writer);
+        spill.complete()?;
+    }
+    Ok((output_spills, mem_sizes))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::{MemConsumer, MemManager};
+    use crate::shuffled_hash_join_exec::{
+        execute_shuffled_hash_join, ShuffledHashJoinExec, NUM_SPILL_PARTITIONS,
+    };
+    use arrow::array::*;
+    use arrow::datatypes::*;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::{JoinType, Result};
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_plan::joins::utils::JoinSide;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    fn build_table(
+        a: (&str, Vec<Option<i32>>),
+        b: (&str, Vec<Option<i32>>),
+    ) -> Arc<dyn ExecutionPlan> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(a.0, DataType::Int32, true),
+            Field::new(b.0, DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(a.1)), Arc::new(Int32Array::from(b.1))],
+        )
+        .unwrap();
+        Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap())
+    }
+
+    fn build_join(join_type: JoinType, build_side: JoinSide) -> Result<ShuffledHashJoinExec> {
+        let left = build_table(
+            ("k1", vec![Some(1), Some(2), Some(2), Some(4), None]),
+            ("a1", vec![Some(10), Some(20), Some(21), Some(40), Some(50)]),
+        );
+        let right = build_table(
+            ("k2", vec![Some(2), Some(3), None, Some(1), Some(2)]),
+            (
+                "b2",
+                vec![Some(200), Some(300), Some(400), Some(100), Some(201)],
+            ),
+        );
+        let on = vec![(
+            Column::new_with_schema("k1", &left.schema())?,
+            Column::new_with_schema("k2", &right.schema())?,
+        )];
+        ShuffledHashJoinExec::try_new(left, right, on, join_type, build_side)
+    }
+
+    async fn join_collect(
+        join_type: JoinType,
+        build_side: JoinSide,
+        force_spill: bool,
+    ) -> Result<Vec<RecordBatch>> {
+        let join = build_join(join_type, build_side)?;
+        join_collect_with(join, build_side, force_spill).await
+    }
+
+    async fn join_collect_with(
+        join: ShuffledHashJoinExec,
+        build_side: JoinSide,
+        force_spill: bool,
+    ) -> Result<Vec<RecordBatch>> {
+        MemManager::init(10000);
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        if !force_spill {
+            return common::collect(join.execute(0, task_ctx)?).await;
+        }
+
+        // spill all build side partitions before probing
+        let joiner = join.create_joiner(0, &task_ctx);
+        let (build, probe) = match build_side {
+            JoinSide::Left => (join.left.clone(), join.right.clone()),
+            JoinSide::Right => (join.right.clone(), join.left.clone()),
+        };
+        let mut build_batches = common::collect(build.execute(0, task_ctx.clone())?).await?;
+        joiner.insert_build_batch(build_batches.remove(0)).await?;
+        for _ in 0..NUM_SPILL_PARTITIONS {
+            joiner.spill().await?;
+        }
+        let build_stream_empty = Box::pin(RecordBatchStreamAdapter::new(
+            build.schema(),
+            futures::stream::empty(),
+        ));
+        let output = execute_shuffled_hash_join(
+            joiner,
+            build_stream_empty,
+            probe.execute(0, task_ctx.clone())?,
+            task_ctx,
+        )
+        .await?;
+        let batches = common::collect(output).await?;
+        let metrics = join.metrics().unwrap();
+        assert!(metrics.sum_by_name("spill_count").unwrap().as_usize() > 0);
+        Ok(batches)
+    }
+
+    async fn assert_join(
+        join_type: JoinType,
+        build_side: JoinSide,
+        expected: Vec<&str>,
+    ) -> Result<()> {
+        for force_spill in [false, true] {
+            let batches = join_collect(join_type, build_side, force_spill).await?;
+            assert_batches_sorted_eq!(expected, &batches);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inner_join() -> Result<()> {
+        let expected = vec![
+            "+----+----+----+-----+",
+            "| k1 | a1 | k2 | b2  |",
+            "+----+----+----+-----+",
+            "| 1  | 10 | 1  | 100 |",
+            "| 2  | 20 | 2  | 200 |",
+            "| 2  | 20 | 2  | 201 |",
+            "| 2  | 21 | 2  | 200 |",
+            "| 2  | 21 | 2  | 201 |",
+            "+----+----+----+-----+",
+        ];
+        assert_join(JoinType::Inner, JoinSide::Left, expected.clone()).await?;
+        assert_join(JoinType::Inner, JoinSide::Right, expected).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_outer_join() -> Result<()> {
+        let expected = vec![
+            "+----+----+----+-----+",
+            "| k1 | a1 | k2 | b2  |",
+            "+----+----+----+-----+",
+            "|    | 50 |    |     |",
+            "| 1  | 10 | 1  | 100 |",
+            "| 2  | 20 | 2  | 200 |",
+            "| 2  | 20 | 2  | 201 |",
+            "| 2  | 21 | 2  | 200 |",
+            "| 2  | 21 | 2  | 201 |",
+            "| 4  | 40 |    |     |",
+            "+----+----+----+-----+",
+        ];
+        assert_join(JoinType::Left, JoinSide::Left, expected.clone()).await?;
+        assert_join(JoinType::Left, JoinSide::Right, expected).await?;
+
+        let expected = vec![
+            "+----+----+----+-----+",
+            "| k1 | a1 | k2 | b2  |",
+            "+----+----+----+-----+",
+            "|    |    |    | 400 |",
+            "|    |    | 3  | 300 |",
+            "|    | 50 |    |     |",
+            "| 1  | 10 | 1  | 100 |",
+            "| 2  | 20 | 2  | 200 |",
+            "| 2  | 20 | 2  | 201 |",
+            "| 2  | 21 | 2  | 200 |",
+            "| 2  | 21 | 2  | 201 |",
+            "| 4  | 40 |    |     |",
+            "+----+----+----+-----+",
+        ];
+        assert_join(JoinType::Full, JoinSide::Left, expected.clone()).await?;
+        assert_join(JoinType::Full, JoinSide::Right, expected).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semi_anti_join() -> Result<()> {
+        let expected = vec![
+            "+----+----+",
+            "| k1 | a1 |",
+            "+----+----+",
+            "| 1  | 10 |",
+            "| 2  | 20 |",
+            "| 2  | 21 |",
+            "+----+----+",
+        ];
+        assert_join(JoinType::LeftSemi, JoinSide::Left, expected.clone()).await?;
+        assert_join(JoinType::LeftSemi, JoinSide::Right, expected).await?;
+
+        let expected = vec![
+            "+----+----+",
+            "| k1 | a1 |",
+            "+----+----+",
+            "|    | 50 |",
+            "| 4  | 40 |",
+            "+----+----+",
+        ];
+        assert_join(JoinType::LeftAnti, JoinSide::Left, expected.clone()).await?;
+        assert_join(JoinType::LeftAnti, JoinSide::Right, expected).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_oversized_spilled_partitions() -> Result<()> {
+        // spilled partitions of the build side exceed the memory budget and
+        // are split again before joining
+        let left = build_table(
+            ("k1", (0..20000).map(Some).collect()),
+            ("a1", (0..20000).map(|v| Some(v * 10)).collect()),
+        );
+        let right = build_table(
+            ("k2", (0..20000).step_by(2).map(Some).collect()),
+            ("b2", (0..20000).step_by(2).map(|v| Some(v * 100)).collect()),
+        );
+        let on = vec![(
+            Column::new_with_schema("k1", &left.schema())?,
+            Column::new_with_schema("k2", &right.schema())?,
+        )];
+        let join = ShuffledHashJoinExec::try_new(left, right, on, JoinType::Inner, JoinSide::Left)?;
+        let batches = join_collect_with(join, JoinSide::Left, true).await?;
+
+        let mut num_rows = 0;
+        for batch in &batches {
+            let k1 = as_primitive_array::<Int32Type>(batch.column(0));
+            let a1 = as_primitive_array::<Int32Type>(batch.column(1));
+            let k2 = as_primitive_array::<Int32Type>(batch.column(2));
+            let b2 = as_primitive_array::<Int32Type>(batch.column(3));
+            for i in 0..batch.num_rows() {
+                assert_eq!(k1.value(i) % 2, 0);
+                assert_eq!(k1.value(i), k2.value(i));
+                assert_eq!(a1.value(i), k1.value(i) * 10);
+                assert_eq!(b2.value(i), k2.value(i) * 100);
+            }
+            num_rows += batch.num_rows();
+        }
+        assert_eq!(num_rows, 10000);
+        Ok(())
+    }
+}
@@ -24,15 +24,19 @@ use crate::common::output::{
 };
 use crate::common::slim_bytes::SlimBytes;
 use crate::common::{BatchTaker, BatchesInterleaver};
-use arrow::array::ArrayRef;
-use arrow::datatypes::SchemaRef;
+use arrow::array::{as_struct_array, Array, ArrayRef, BooleanArray};
+use arrow::buffer::BooleanBuffer;
+use arrow::compute::SortOptions;
+use arrow::datatypes::{DataType, SchemaRef};
 use arrow::record_batch::RecordBatch;
 use arrow::row::{RowConverter, SortField};
 use async_trait::async_trait;
-use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::common::{Result, Statistics};
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_expr::PhysicalSortExpr;
-use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
+};
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
@@ -41,17 +45,17 @@ use datafusion_ext_commons::io::{
     read_bytes_slice, read_len, read_one_batch, write_len, write_one_batch,
 };
 use datafusion_ext_commons::loser_tree::LoserTree;
+use datafusion_ext_commons::spill::SpillManager;
 use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
 use futures::lock::Mutex;
 use futures::stream::once;
 use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
-use lz4_flex::frame::FrameDecoder;
 use parking_lot::Mutex as SyncMutex;
 use std::any::Any;
 use std::collections::VecDeque;
 use std::fmt::Formatter;
-use std::io::{BufReader, Cursor, Read, Write};
+use std::io::{Cursor, Read, Write};
 use std::mem::size_of;
 use std::sync::{Arc, Weak};
 
@@ -83,6 +87,14 @@ impl SortExec {
             metrics,
         }
     }
+
+    pub fn exprs(&self) -> &[PhysicalSortExpr] {
+        &self.exprs
+    }
+
+    pub fn fetch(&self) -> Option<usize> {
+        self.fetch
+    }
 }
 
 impl DisplayAs for SortExec {
@@ -158,7 +170,10 @@ struct ExternalSorter {
     sort_row_converter: SyncMutex<RowConverter>,
     levels: Mutex<Vec<Option<SortedBatches>>>,
     spills: Mutex<Vec<Box<dyn Spill>>>,
+    spill_manager: Arc<SpillManager>,
     baseline_metrics: BaselineMetrics,
+    spilled_rows: Count,
+    spill_count: Count,
     projection: Vec<usize>,
 }
 
@@ -186,10 +201,10 @@ impl MemConsumer for ExternalSorter {
             Some(max_level_id) => std::mem::take(&mut levels[max_level_id]).unwrap(),
             None => return Ok(()),
         };
-        self.spills
-            .lock()
-            .await
-            .extend(max_level_in_mem_batches.try_into_spill()?);
+        if let Some(spill) = max_level_in_mem_batches.try_into_spill()? {
+            self.spills.lock().await.push(spill);
+            self.spill_count.add(1);
+        }
 
         let mem_used = levels.iter().flatten().map(|b| b.mem_size()).sum::<usize>();
         drop(levels);
@@ -211,17 +226,12 @@ impl ExecuteWithColumnPruning for SortExec {
         let batch_size = context.session_config().batch_size();
         let sub_batch_size = batch_size / batch_size.ilog2() as usize;
 
-        let sort_row_converter = RowConverter::new(
-            self.exprs
-                .iter()
-                .map(|expr: &PhysicalSortExpr| {
-                    Ok(SortField::new_with_options(
-                        expr.expr.data_type(&input_schema)?,
-                        expr.options,
-                    ))
-                })
-                .collect::<Result<Vec<SortField>>>()?,
-        )?;
+        let mut sort_fields = vec![];
+        for expr in &self.exprs {
+            let data_type = expr.expr.data_type(&input_schema)?;
+            flatten_sort_fields(&data_type, expr.options, &mut sort_fields);
+        }
+        let sort_row_converter = RowConverter::new(sort_fields)?;
 
         let external_sorter = Arc::new(ExternalSorter {
             name: format!("ExternalSorter[partition={}]", partition),
@@ -234,7 +244,10 @@ impl ExecuteWithColumnPruning for SortExec {
             sort_row_converter: SyncMutex::new(sort_row_converter),
             levels: Mutex::new((0..NUM_LEVELS).map(|_| None).collect()),
             spills: Default::default(),
+            spill_manager: SpillManager::get(&context),
             baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+            spilled_rows: MetricBuilder::new(&self.metrics).counter("spilled_rows", partition),
+            spill_count: MetricBuilder::new(&self.metrics).spill_count(partition),
         });
         MemManager::register_consumer(external_sorter.clone(), true);
 
@@ -527,15 +540,11 @@ impl SortedBatches {
 
     fn from_batch(sorter: Arc<ExternalSorter>, batch: RecordBatch) -> Result<Self> {
         // compute key cols
-        let key_cols: Vec<ArrayRef> = sorter
-            .exprs
-            .iter()
-            .map(|expr| {
-                expr.expr
-                    .evaluate(&batch)
-                    .map(|cv| cv.into_array(batch.num_rows()))
-            })
-            .collect::<Result<_>>()?;
+        let mut key_cols: Vec<ArrayRef> = vec![];
+        for expr in &sorter.exprs {
+            let key_col = expr.expr.evaluate(&batch)?.into_array(batch.num_rows());
+            flatten_sort_keys(key_col, expr.options, &mut key_cols)?;
+        }
 
         // sort keys
         let mut key_data = BytesArena::default();
@@ -729,9 +738,13 @@ impl SortedBatches {
             return Ok(None);
         }
 
-        let spill = try_new_spill()?;
-        let mut writer = lz4_flex::frame::FrameEncoder::new(spill.get_buf_writer());
+        let spill = try_new_spill(&self.sorter.spill_manager)?;
+        let mut writer = self
+            .sorter
+            .spill_manager
+            .compressed_writer(spill.get_buf_writer())?;
         let mut key_idx = 0;
+        self.sorter.spilled_rows.add(self.keys.len());
 
         // write batch1 + keys1, batch2 + keys2, ...
         for batch in self.batches {
@@ -748,9 +761,8 @@ impl SortedBatches {
                 writer.write_all(key)?;
             }
         }
-        writer
-            .finish()
-            .map_err(|err| DataFusionError::Execution(format!("{}", err)))?;
+        writer.flush()?;
+        drop(writer); // finishes the compressed frame
         spill.complete()?;
         Ok(Some(spill))
     }
@@ -759,7 +771,7 @@ impl SortedBatches {
 struct SpillCursor {
     id: usize,
     sorter: Arc<ExternalSorter>,
-    input: FrameDecoder<BufReader<Box<dyn Read + Send>>>,
+    input: Box<dyn Read + Send>,
     cur_batch_num_rows: usize,
     cur_loaded_num_rows: usize,
     cur_batches: Vec<RecordBatch>,
@@ -779,7 +791,7 @@ impl SpillCursor {
         let mut iter = SpillCursor {
             id,
             sorter,
-            input: FrameDecoder::new(buf_reader),
+            input: SpillManager::compressed_reader(buf_reader)?,
             cur_batch_num_rows: 0,
             cur_loaded_num_rows: 0,
             cur_batches: vec![],
@@ -842,6 +854,70 @@ impl SpillCursor {
     }
 }
 
+// spark compares struct keys field by field, where fields are always ordered in
+// ascending/nulls-first (or descending/nulls-last for descending keys), regardless
+// of the null ordering of the key itself. so struct keys are flattened into a
+// validity key followed by its field keys, instead of relying on the struct
+// encoding of the row format.
+fn struct_field_sort_options(options: SortOptions) -> SortOptions {
+    SortOptions {
+        descending: options.descending,
+        nulls_first: !options.descending,
+    }
+}
+
+pub(crate) fn flatten_sort_fields(
+    data_type: &DataType,
+    options: SortOptions,
+    flattened: &mut Vec<SortField>,
+) {
+    match data_type {
+        DataType::Struct(fields) => {
+            flattened.push(SortField::new_with_options(DataType::Boolean, options));
+            for field in fields {
+                flatten_sort_fields(
+                    field.data_type(),
+                    struct_field_sort_options(options),
+                    flattened,
+                );
+            }
+        }
+        data_type => {
+            flattened.push(SortField::new_with_options(data_type.clone(), options));
+        }
+    }
+}
+
+pub(crate) fn flatten_sort_keys(
+    key: ArrayRef,
+    options: SortOptions,
+    flattened: &mut Vec<ArrayRef>,
+) -> Result<()> {
+    match key.data_type() {
+        DataType::Struct(_) => {
+            let struct_key = as_struct_array(&key);
+            let validity = BooleanArray::new(
+                BooleanBuffer::new_set(struct_key.len()),
+                struct_key.nulls().cloned(),
+            );
+            let struct_is_null = arrow::compute::is_null(&key)?;
+            flattened.push(Arc::new(validity));
+
+            for field_key in struct_key.columns() {
+                // fields of null structs are ignored in comparison
+                let field_key = if struct_key.null_count() > 0 {
+                    arrow::compute::nullif(field_key, &struct_is_null)?
+                } else {
+                    field_key.clone()
+                };
+                flatten_sort_keys(field_key, struct_field_sort_options(options), flattened)?;
+            }
+        }
+        _ => flattened.push(key),
+    }
+    Ok(())
+}
+
 fn max_level_id(levels: &[Option<SortedBatches>]) -> Option<usize> {
     levels
         .iter()
@@ -853,10 +929,11 @@ fn max_level_id(levels: &[Option<SortedBatches>]) -> Option<usize> {
 
 #[cfg(test)]
 mod test {
+    use crate::common::memory_manager::MemManager;
     use crate::sort_exec::SortExec;
-    use arrow::array::Int32Array;
+    use arrow::array::{as_primitive_array, ArrayRef, Int32Array, StringArray, StructArray};
     use arrow::compute::SortOptions;
-    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::datatypes::{DataType, Field, Fields, Int32Type, Schema};
     use arrow::record_batch::RecordBatch;
     use datafusion::assert_batches_eq;
     use datafusion::common::Result;
@@ -932,6 +1009,61 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_sort_struct_key() -> Result<()> {
+        MemManager::init(10000);
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        let s_fields = Fields::from(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Utf8, true),
+        ]);
+        let s: ArrayRef = Arc::new(StructArray::new(
+            s_fields.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 0, 9, 1, 0])),
+                Arc::new(StringArray::from(vec![
+                    Some("b"),
+                    None,
+                    Some("z"),
+                    None,
+                    Some("a"),
+                ])),
+            ],
+            Some(vec![true, true, false, true, true].into()),
+        ));
+        let v: ArrayRef = Arc::new(Int32Array::from(vec![0, 1, 2, 3, 4]));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Struct(s_fields), true),
+            Field::new("v", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![s, v])?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None)?);
+
+        // null structs are last, while null fields are still first
+        let sort_exprs = vec![PhysicalSortExpr {
+            expr: Arc::new(Column::new("s", 0)),
+            options: SortOptions {
+                descending: false,
+                nulls_first: false,
+            },
+        }];
+        let sort = SortExec::new(input, sort_exprs, None);
+        let output = sort.execute(0, task_ctx)?;
+        let batches = common::collect(output).await?;
+        let sorted_v = batches
+            .iter()
+            .flat_map(|batch| {
+                as_primitive_array::<Int32Type>(batch.column(1))
+                    .values()
+                    .to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sorted_v, vec![1, 4, 3, 0, 2]);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -946,6 +1078,7 @@ mod fuzztest {
     use datafusion::physical_expr::math_expressions::random;
     use datafusion::physical_expr::PhysicalSortExpr;
     use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::ExecutionPlan;
     use datafusion::prelude::{SessionConfig, SessionContext};
     use datafusion_ext_commons::concat_batches;
     use std::sync::Arc;
@@ -993,8 +1126,11 @@ mod fuzztest {
             None,
         )?);
         let sort = Arc::new(SortExec::new(input, sort_exprs.clone(), None));
-        let output = datafusion::physical_plan::collect(sort, task_ctx.clone()).await?;
+        let output = datafusion::physical_plan::collect(sort.clone(), task_ctx.clone()).await?;
         let a = concat_batches(&schema, &output, n)?;
+        let metrics = sort.metrics().unwrap();
+        assert!(metrics.sum_by_name("spilled_rows").unwrap().as_usize() > 0);
+        assert!(metrics.sum_by_name("spill_count").unwrap().as_usize() > 0);
 
         let input = Arc::new(MemoryExec::try_new(
             &[batches.clone()],
@@ -13,14 +13,17 @@
 // limitations under the License.
 
 use crate::common::column_pruning::ExecuteWithColumnPruning;
+use crate::common::memory_manager::{MemConsumer, MemConsumerInfo, MemManager};
+use crate::common::onheap_spill::{try_new_spill, Spill};
 use crate::common::output::{output_with_sender, WrappedRecordBatchSender};
 use crate::common::{BatchTaker, BatchesInterleaver};
 use arrow::array::*;
 use arrow::buffer::NullBuffer;
 use arrow::compute::{prep_null_mask_filter, SortOptions};
-use arrow::datatypes::{DataType, Schema, SchemaRef};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use arrow::record_batch::{RecordBatch, RecordBatchOptions};
 use arrow::row::{Row, RowConverter, Rows, SortField};
+use async_trait::async_trait;
 use datafusion::error::{DataFusionError, Result};
 use datafusion::execution::context::TaskContext;
 use datafusion::logical_expr::JoinType;
@@ -37,13 +40,17 @@ use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
     Statistics,
 };
-use datafusion_ext_commons::streams::coalesce_stream::CoalesceStream;
+use datafusion_ext_commons::io::read_one_batch;
+use datafusion_ext_commons::spill::SpillManager;
+use datafusion_ext_commons::streams::coalesce_stream::{batch_byte_budget, CoalesceStream};
 use futures::{StreamExt, TryStreamExt};
 use parking_lot::Mutex as SyncMutex;
 use std::any::Any;
 use std::cmp::Ordering;
 use std::fmt::Formatter;
-use std::sync::Arc;
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::{Arc, Weak};
 
 #[derive(Debug)]
 pub struct SortMergeJoinExec {
@@ -63,6 +70,10 @@ pub struct SortMergeJoinExec {
     metrics: ExecutionPlanMetricsSet,
     /// Sort options of join columns used in sorting left and right execution plans
     sort_options: Vec<SortOptions>,
+    /// Number of rows of an equal-key run buffered in memory before spilling
+    buffer_spill_threshold: usize,
+    /// Name of the exists column if this is an existence join
+    existence_column_name: Option<String>,
 }
 
 impl SortMergeJoinExec {
@@ -104,6 +115,37 @@ impl SortMergeJoinExec {
             schema,
             metrics: ExecutionPlanMetricsSet::new(),
             sort_options,
+            buffer_spill_threshold: usize::MAX,
+            existence_column_name: None,
+        })
+    }
+
+    /// Sets the max number of rows of an equal-key run buffered in memory. the
+    /// run is spilled when it exceeds the threshold, or when the memory manager
+    /// asks for spilling.
+    pub fn with_buffer_spill_threshold(self, buffer_spill_threshold: usize) -> Self {
+        Self {
+            buffer_spill_threshold,
+            ..self
+        }
+    }
+
+    /// Turns the left semi join into an existence join, which outputs all left
+    /// rows with an extra non-null boolean column telling whether each row has
+    /// matched rows.
+    pub fn with_existence_column(self, name: String) -> Result<Self> {
+        if self.join_type != LeftSemi {
+            return Err(DataFusionError::Plan(format!(
+                "SortMergeJoin existence join requires LeftSemi join type, got: {:?}",
+                self.join_type,
+            )));
+        }
+        let mut fields = self.schema.fields().to_vec();
+        fields.push(Arc::new(Field::new(&name, DataType::Boolean, false)));
+        Ok(Self {
+            schema: Arc::new(Schema::new(fields)),
+            existence_column_name: Some(name),
+            ..self
         })
     }
 
@@ -119,6 +161,7 @@ impl SortMergeJoinExec {
         // use smaller batch size and coalesce batches at the end, to avoid buffer overflowing
         JoinParams {
             join_type: self.join_type,
+            existence_join: self.existence_column_name.is_some(),
             output_schema: self.schema(),
             on_left,
             on_right,
@@ -126,6 +169,7 @@ impl SortMergeJoinExec {
             join_filter: self.join_filter.clone(),
             sort_options: self.sort_options.clone(),
             batch_size: sub_batch_size,
+            buffer_spill_threshold: self.buffer_spill_threshold,
             left_output_projection: (0..self.left.schema().fields().len()).collect(),
             right_output_projection: (0..self.right.schema().fields().len()).collect(),
         }
@@ -173,14 +217,21 @@ impl ExecutionPlan for SortMergeJoinExec {
         children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         match &children[..] {
-            [left, right] => Ok(Arc::new(SortMergeJoinExec::try_new(
-                left.clone(),
-                right.clone(),
-                self.on.clone(),
-                self.join_type,
-                self.join_filter.clone(),
-                self.sort_options.clone(),
-            )?)),
+            [left, right] => {
+                let mut join = SortMergeJoinExec::try_new(
+                    left.clone(),
+                    right.clone(),
+                    self.on.clone(),
+                    self.join_type,
+                    self.join_filter.clone(),
+                    self.sort_options.clone(),
+                )?
+                .with_buffer_spill_threshold(self.buffer_spill_threshold);
+                if let Some(existence_column_name) = &self.existence_column_name {
+                    join = join.with_existence_column(existence_column_name.clone())?;
+                }
+                Ok(Arc::new(join))
+            }
             _ => Err(DataFusionError::Internal(
                 "SortMergeJoin wrong number of children".to_string(),
             )),
@@ -197,7 +248,7 @@ impl ExecutionPlan for SortMergeJoinExec {
         let join_params = self.create_join_params(batch_size);
         let left = self.left.execute(partition, context.clone())?;
         let right = self.right.execute(partition, context.clone())?;
-        execute_with_join_params(context, join_params, left, right, metrics)
+        execute_with_join_params(context, partition, join_params, left, right, metrics)
     }
 
     fn metrics(&self) -> Option<MetricsSet> {
@@ -216,6 +267,11 @@ impl ExecuteWithColumnPruning for SortMergeJoinExec {
         context: Arc<TaskContext>,
         projection: &[usize],
     ) -> Result<SendableRecordBatchStream> {
+        // the exists column is not pruned, fallback to projecting the output
+        if self.existence_column_name.is_some() {
+            let plan: &(dyn ExecutionPlan + 'static) = self;
+            return plan.execute_projected(partition, context, projection);
+        }
         let metrics = Arc::new(BaselineMetrics::new(&self.metrics, partition));
         let batch_size = context.session_config().batch_size();
 
@@ -227,13 +283,14 @@ impl ExecuteWithColumnPruning for SortMergeJoinExec {
         let right = self
             .right
             .execute_projected(partition, context.clone(), &right_projection)?;
-        execute_with_join_params(context, join_params, left, right, metrics)
+        execute_with_join_params(context, partition, join_params, left, right, metrics)
     }
 }
 
 #[derive(Clone)]
 struct JoinParams {
     join_type: JoinType,
+    existence_join: bool,
     output_schema: SchemaRef,
     on_left: Vec<usize>,
     on_right: Vec<usize>,
@@ -243,6 +300,7 @@ struct JoinParams {
     left_output_projection: Vec<usize>,
     right_output_projection: Vec<usize>,
     batch_size: usize,
+    buffer_spill_threshold: usize,
 }
 
 impl JoinParams {
@@ -329,6 +387,7 @@ impl JoinParams {
 
         let projected = Self {
             join_type: self.join_type,
+            existence_join: self.existence_join,
             output_schema: Arc::new(self.output_schema.project(projection)?),
             on_left: on_left_projected,
             on_right: on_right_projected,
@@ -336,6 +395,7 @@ impl JoinParams {
             sort_options: self.sort_options.clone(),
             join_filter: join_filter_projected,
             batch_size: self.batch_size,
+            buffer_spill_threshold: self.buffer_spill_threshold,
             left_output_projection: (0..num_left_output_columns).collect(),
             right_output_projection: (0..num_right_output_columns).collect(),
         };
@@ -345,6 +405,7 @@ impl JoinParams {
 
 fn execute_with_join_params(
     context: Arc<TaskContext>,
+    partition: usize,
     join_params: JoinParams,
     left: SendableRecordBatchStream,
     right: SendableRecordBatchStream,
@@ -353,28 +414,38 @@ fn execute_with_join_params(
     let batch_size = join_params.batch_size;
     let metrics_cloned = metrics.clone();
     let output_schema = join_params.output_schema.clone();
+    let spill_manager = SpillManager::get(&context);
     let output_stream = Box::pin(RecordBatchStreamAdapter::new(
         join_params.output_schema.clone(),
         futures::stream::once(async move {
             output_with_sender("SortMergeJoin", context, output_schema, move |sender| {
-                execute_join(left, right, join_params, metrics_cloned, sender)
+                execute_join(
+                    left,
+                    right,
+                    partition,
+                    join_params,
+                    spill_manager,
+                    metrics_cloned,
+                    sender,
+                )
             })
         })
         .try_flatten(),
     ));
 
-    let output_coalesced = Box::pin(CoalesceStream::new(
-        output_stream,
-        batch_size,
-        metrics.elapsed_compute().clone(),
-    ));
+    let output_coalesced = Box::pin(
+        CoalesceStream::new(output_stream, batch_size, metrics.elapsed_compute().clone())
+            .with_byte_budget(batch_byte_budget()?),
+    );
     Ok(output_coalesced)
 }
 
 async fn execute_join(
     lstream: SendableRecordBatchStream,
     rstream: SendableRecordBatchStream,
+    partition: usize,
     join_params: JoinParams,
+    spill_manager: Arc<SpillManager>,
     metrics: Arc<BaselineMetrics>,
     sender: Arc<WrappedRecordBatchSender>,
 ) -> Result<()> {
@@ -411,10 +482,161 @@ async fn execute_join(
     .await?;
 
     let join_type = join_params.join_type;
+    let existence_join = join_params.existence_join;
     let mut joiner = Joiner::new();
     let mut leqs = vec![];
     let mut reqs = vec![];
 
+    let run_mem_consumer = Arc::new(BufferedRunMemConsumer {
+        name: format!("SortMergeJoin[partition={}]", partition),
+        mem_consumer_info: None,
+        spill_requested: AtomicBool::new(false),
+    });
+    MemManager::register_consumer(run_mem_consumer.clone(), true);
+    let mut run_mem_used = 0;
+
+    macro_rules! send_output {
+        ($batch:expr) => {{
+            if let Some(batch) = $batch {
+                metrics.record_output(batch.num_rows());
+                sender.send(Ok(batch), Some(&mut timer)).await;
+            }
+        }};
+    }
+
+    // memory of the batches buffered by a run
+    macro_rules! run_mem_size {
+        ($cur:expr, $run:expr) => {{
+            let run = &$run;
+            $cur.batches[run[0].0..=run[run.len() - 1].0]
+                .iter()
+                .map(|batch| batch.get_array_memory_size())
+                .sum::<usize>()
+        }};
+    }
+
+    // spills a run which is too large, or when the memory manager asks for
+    // spilling. only runs spanning multiple batches are worth spilling.
+    macro_rules! should_spill_run {
+        ($fixed:expr, $run:expr) => {{
+            let run = &$run;
+            let first_bidx = run[0].0;
+            let last_bidx = run[run.len() - 1].0;
+            matches!(join_type, Inner | Left | Right | Full)
+                && (run.len() > join_params.buffer_spill_threshold
+                    || (first_bidx < last_bidx && {
+                        run_mem_used = run_mem_size!($fixed, run);
+                        run_mem_consumer.update_mem_used(run_mem_used).await?;
+                        run_mem_consumer.spill_requested.swap(false, SeqCst)
+                    }))
+        }};
+    }
+
+    // joins rows of the streamed side with the same key as the fixed run. the
+    // fixed run is spilled, including its rest rows not yet buffered, and read
+    // in one pass for each chunk of streamed rows. streamed rows already
+    // buffered in `$streamed_run` are joined first.
+    macro_rules! join_with_spilled_run {
+        ($streamed:ident, $fixed:ident, $run:expr, $streamed_run:expr, $spilled_side:expr) => {{
+            // flush pending pairs, so that batches of the run can be released
+            if !joiner.is_empty() {
+                send_output!(joiner.flush_pairs(&join_params, &mut lcur, &mut rcur)?);
+            }
+            let run_key = $fixed.row($run[0]).owned();
+            let mut run_writer =
+                SpilledRunWriter::try_new(&$fixed, join_params.batch_size, &spill_manager)?;
+            run_writer.write(&$fixed, &$run)?;
+
+            // rest rows of the run are spilled per batch
+            let mut run_rest = vec![];
+            while !$fixed.finished && $fixed.row($fixed.cur_idx) == run_key.row() {
+                run_rest.push($fixed.cur_idx);
+                $fixed.next(&mut timer).await?;
+                if $fixed.cur_idx.1 == 0 || run_rest.len() >= join_params.batch_size {
+                    run_writer.write(&$fixed, &run_rest)?;
+                    run_rest.clear();
+                    $fixed.clear_outdated(usize::MAX);
+                }
+            }
+            run_writer.write(&$fixed, &run_rest)?;
+            let mut spilled_run = run_writer.finish()?;
+            $fixed.clear_outdated(usize::MAX);
+            if run_mem_used > 0 {
+                run_mem_used = 0;
+                run_mem_consumer.update_mem_used(0).await?;
+            }
+
+            let mut run_joiner = Joiner::new();
+            let mut streamed_idxs: Vec<(usize, usize)> = $streamed_run.to_vec();
+            loop {
+                let same_key =
+                    !$streamed.finished && $streamed.row($streamed.cur_idx) == run_key.row();
+                if same_key {
+                    streamed_idxs.push($streamed.cur_idx);
+                    $streamed.next(&mut timer).await?;
+                }
+                if !streamed_idxs.is_empty()
+                    && (!same_key || streamed_idxs.len() >= join_params.batch_size)
+                {
+                    let mut run_reader = spilled_run.read()?;
+                    while let Some(run_batch) = run_reader.next_batch()? {
+                        let run_projected = [run_batch.project(&$fixed.projection)?];
+                        let run_num_rows = run_batch.num_rows();
+                        let run_batches = [run_batch];
+                        let (lbatches, lprojected, rbatches, rprojected) = match $spilled_side {
+                            JoinSide::Left => (
+                                &run_batches[..],
+                                &run_projected[..],
+                                &$streamed.batches[..],
+                                &$streamed.projected_batches[..],
+                            ),
+                            JoinSide::Right => (
+                                &$streamed.batches[..],
+                                &$streamed.projected_batches[..],
+                                &run_batches[..],
+                                &run_projected[..],
+                            ),
+                        };
+                        for &streamed_idx in &streamed_idxs {
+                            for run_row_idx in 0..run_num_rows {
+                                let (l, r) = match $spilled_side {
+                                    JoinSide::Left => ((0, run_row_idx), streamed_idx),
+                                    JoinSide::Right => (streamed_idx, (0, run_row_idx)),
+                                };
+                                run_joiner.ljoins.push(l);
+                                run_joiner.rjoins.push(r);
+                                if run_joiner.ljoins.len() >= join_params.batch_size {
+                                    send_output!(run_joiner.flush_pairs_of_batches(
+                                        &join_params,
+                                        lbatches,
+                                        lprojected,
+                                        rbatches,
+                                        rprojected,
+                                    )?);
+                                }
+                            }
+                        }
+                        if !run_joiner.is_empty() {
+                            send_output!(run_joiner.flush_pairs_of_batches(
+                                &join_params,
+                                lbatches,
+                                lprojected,
+                                rbatches,
+                                rprojected,
+                            )?);
+                        }
+                    }
+                    spilled_run = run_reader.finish()?;
+                    streamed_idxs.clear();
+                    $streamed.clear_outdated(usize::MAX);
+                }
+                if !same_key {
+                    break;
+                }
+            }
+        }};
+    }
+
     macro_rules! joiner_accept_pair {
         ($lidx:expr, $ridx:expr) => {{
             let lidx = $lidx;
@@ -432,7 +654,7 @@ async fn execute_join(
         let r = compare_cursor(&lcur, lcur.cur_idx, &rcur, rcur.cur_idx);
         match r {
             Ordering::Less => {
-                if matches!(join_type, Left | LeftAnti | Full) {
+                if matches!(join_type, Left | LeftAnti | Full) || existence_join {
                     joiner_accept_pair!(Some(lcur.cur_idx), None);
                 }
                 lcur.next(&mut timer).await?;
@@ -455,6 +677,7 @@ async fn execute_join(
 
                 let mut leq = true;
                 let mut req = true;
+                let mut spilled_side = None;
                 while leq && req {
                     if leq && !lcur.finished && lcur.row(lcur.cur_idx) == lcur.row(lidx0) {
                         leqs.push(lcur.cur_idx);
@@ -468,67 +691,114 @@ async fn execute_join(
                     } else {
                         req = false;
                     }
-                }
 
-                match join_type {
-                    Inner | Left | Right | Full => {
-                        for &l in &leqs {
-                            for &r in &reqs {
-                                joiner_accept_pair!(Some(l), Some(r));
+                    // both runs are growing, checks spilling whenever a run grows
+                    // into a new batch, so a key skewed in both sides never
+                    // buffers unbounded memory
+                    if leq && req && matches!(join_type, Inner | Left | Right | Full) {
+                        let lgrown = leqs[leqs.len() - 1].0 != leqs[leqs.len() - 2].0;
+                        let rgrown = reqs[reqs.len() - 1].0 != reqs[reqs.len() - 2].0;
+                        let threshold = join_params.buffer_spill_threshold;
+                        let exceeded = leqs.len() > threshold || reqs.len() > threshold;
+                        if lgrown || rgrown || exceeded {
+                            let lmem = run_mem_size!(lcur, leqs);
+                            let rmem = run_mem_size!(rcur, reqs);
+                            run_mem_used = lmem + rmem;
+                            run_mem_consumer.update_mem_used(run_mem_used).await?;
+                            if run_mem_consumer.spill_requested.swap(false, SeqCst) || exceeded {
+                                // spills the larger run
+                                spilled_side = Some(if (lmem, leqs.len()) >= (rmem, reqs.len()) {
+                                    JoinSide::Left
+                                } else {
+                                    JoinSide::Right
+                                });
+                                break;
                             }
                         }
                     }
-                    LeftSemi => {
-                        for &l in &leqs {
-                            joiner_accept_pair!(Some(l), None);
-                        }
-                    }
-                    RightSemi => {
-                        for &r in &reqs {
-                            joiner_accept_pair!(None, Some(r));
-                        }
-                    }
-                    LeftAnti | RightAnti => {}
                 }
 
-                if leq {
-                    while !lcur.finished && lcur.row(lcur.cur_idx) == rcur.row(ridx0) {
+                match spilled_side {
+                    Some(JoinSide::Left) => {
+                        join_with_spilled_run!(rcur, lcur, leqs, reqs, JoinSide::Left);
+                    }
+                    Some(JoinSide::Right) => {
+                        join_with_spilled_run!(lcur, rcur, reqs, leqs, JoinSide::Right);
+                    }
+                    None => {
                         match join_type {
                             Inner | Left | Right | Full => {
-                                for &r in &reqs {
-                                    joiner_accept_pair!(Some(lcur.cur_idx), Some(r));
+                                for &l in &leqs {
+                                    for &r in &reqs {
+                                        joiner_accept_pair!(Some(l), Some(r));
+                                    }
                                 }
                             }
                             LeftSemi => {
-                                joiner_accept_pair!(Some(lcur.cur_idx), None);
-                            }
-                            RightSemi | LeftAnti | RightAnti => {}
-                        }
-                        lcur.next(&mut timer).await?;
-                        lcur.clear_outdated(joiner.l_min_reserved_bidx);
-                    }
-                }
-                if req {
-                    while !rcur.finished && rcur.row(rcur.cur_idx) == lcur.row(lidx0) {
-                        match join_type {
-                            Inner | Left | Right | Full => {
+                                // existence join marks matched rows with the first
+                                // matched right row
+                                let matched = existence_join.then_some(ridx0);
                                 for &l in &leqs {
-                                    joiner_accept_pair!(Some(l), Some(rcur.cur_idx));
+                                    joiner_accept_pair!(Some(l), matched);
                                 }
                             }
                             RightSemi => {
-                                joiner_accept_pair!(None, Some(rcur.cur_idx));
+                                for &r in &reqs {
+                                    joiner_accept_pair!(None, Some(r));
+                                }
+                            }
+                            LeftAnti | RightAnti => {}
+                        }
+
+                        if leq && should_spill_run!(rcur, reqs) {
+                            join_with_spilled_run!(lcur, rcur, reqs, [], JoinSide::Right);
+                        } else if leq {
+                            while !lcur.finished && lcur.row(lcur.cur_idx) == rcur.row(ridx0) {
+                                match join_type {
+                                    Inner | Left | Right | Full => {
+                                        for &r in &reqs {
+                                            joiner_accept_pair!(Some(lcur.cur_idx), Some(r));
+                                        }
+                                    }
+                                    LeftSemi => {
+                                        let matched = existence_join.then_some(ridx0);
+                                        joiner_accept_pair!(Some(lcur.cur_idx), matched);
+                                    }
+                                    RightSemi | LeftAnti | RightAnti => {}
+                                }
+                                lcur.next(&mut timer).await?;
+                                lcur.clear_outdated(joiner.l_min_reserved_bidx);
+                            }
+                        }
+                        if req && should_spill_run!(lcur, leqs) {
+                            join_with_spilled_run!(rcur, lcur, leqs, [], JoinSide::Left);
+                        } else if req {
+                            while !rcur.finished && rcur.row(rcur.cur_idx) == lcur.row(lidx0) {
+                                match join_type {
+                                    Inner | Left | Right | Full => {
+                                        for &l in &leqs {
+                                            joiner_accept_pair!(Some(l), Some(rcur.cur_idx));
+                                        }
+                                    }
+                                    RightSemi => {
+                                        joiner_accept_pair!(None, Some(rcur.cur_idx));
+                                    }
+                                    LeftSemi | LeftAnti | RightAnti => {}
+                                }
+                                rcur.next(&mut timer).await?;
+                                rcur.clear_outdated(joiner.r_min_reserved_bidx);
                             }
-                            LeftSemi | LeftAnti | RightAnti => {}
                         }
-                        rcur.next(&mut timer).await?;
-                        rcur.clear_outdated(joiner.r_min_reserved_bidx);
                     }
                 }
                 leqs.clear();
                 reqs.clear();
                 lcur.clear_outdated(joiner.l_min_reserved_bidx);
                 rcur.clear_outdated(joiner.r_min_reserved_bidx);
+                if run_mem_used > 0 {
+                    run_mem_used = 0;
+                    run_mem_consumer.update_mem_used(0).await?;
+                }
             }
         }
 
@@ -542,7 +812,7 @@ async fn execute_join(
     }
 
     // process rest records in inexhausted side
-    if matches!(join_type, Left | LeftAnti | Full) {
+    if matches!(join_type, Left | LeftAnti | Full) || existence_join {
         while !lcur.finished {
             joiner_accept_pair!(Some(lcur.cur_idx), None);
             lcur.next(&mut timer).await?;
@@ -748,6 +1018,23 @@ impl Joiner {
         join_params: &JoinParams,
         lcur: &mut StreamCursor,
         rcur: &mut StreamCursor,
+    ) -> Result<Option<RecordBatch>> {
+        self.flush_pairs_of_batches(
+            join_params,
+            &lcur.batches,
+            &lcur.projected_batches,
+            &rcur.batches,
+            &rcur.projected_batches,
+        )
+    }
+
+    fn flush_pairs_of_batches(
+        &mut self,
+        join_params: &JoinParams,
+        lbatches: &[RecordBatch],
+        lprojected_batches: &[RecordBatch],
+        rbatches: &[RecordBatch],
+        rprojected_batches: &[RecordBatch],
     ) -> Result<Option<RecordBatch>> {
         self.l_min_reserved_bidx = usize::MAX;
         self.r_min_reserved_bidx = usize::MAX;
@@ -758,12 +1045,11 @@ impl Joiner {
                 .column_indices()
                 .iter()
                 .map(|ci| {
-                    let (cur, joins) = match ci.side {
-                        JoinSide::Left => (&lcur, &self.ljoins),
-                        JoinSide::Right => (&rcur, &self.rjoins),
+                    let (batches, joins) = match ci.side {
+                        JoinSide::Left => (lbatches, &self.ljoins),
+                        JoinSide::Right => (rbatches, &self.rjoins),
                     };
-                    let arrays = cur
-                        .batches
+                    let arrays = batches
                         .iter()
                         .map(|b| b.column(ci.index).as_ref())
                         .collect::<Vec<_>>();
@@ -802,8 +1088,8 @@ impl Joiner {
         }
 
         let lcols = || -> Result<Vec<ArrayRef>> {
-            Ok(if !lcur.projection.is_empty() {
-                BatchesInterleaver::new(lcur.projected_batches[0].schema(), &lcur.projected_batches)
+            Ok(if lprojected_batches[0].num_columns() > 0 {
+                BatchesInterleaver::new(lprojected_batches[0].schema(), lprojected_batches)
                     .interleave(&self.ljoins)?
                     .columns()
                     .to_vec()
@@ -812,8 +1098,8 @@ impl Joiner {
             })
         };
         let rcols = || -> Result<Vec<ArrayRef>> {
-            Ok(if !rcur.projection.is_empty() {
-                BatchesInterleaver::new(rcur.projected_batches[0].schema(), &rcur.projected_batches)
+            Ok(if rprojected_batches[0].num_columns() > 0 {
+                BatchesInterleaver::new(rprojected_batches[0].schema(), rprojected_batches)
                     .interleave(&self.rjoins)?
                     .columns()
                     .to_vec()
@@ -823,6 +1109,11 @@ impl Joiner {
         };
 
         let output_columns = match join_params.join_type {
+            LeftSemi if join_params.existence_join => {
+                let exists =
+                    BooleanArray::from_iter(self.rjoins.iter().map(|&ridx| Some(ridx != (0, 0))));
+                [lcols()?, vec![Arc::new(exists) as ArrayRef]].concat()
+            }
             LeftSemi | LeftAnti => lcols()?,
             RightSemi | RightAnti => rcols()?,
             _ => [lcols()?, rcols()?].concat(),
@@ -839,6 +1130,151 @@ impl Joiner {
     }
 }
 
+/// Rows of an equal-key run moved out of memory. a spill can only be read
+/// once, so each pass over the run copies it into a new spill for the next pass.
+struct SpilledRun {
+    schema: SchemaRef,
+    spill: Box<dyn Spill>,
+    spill_manager: Arc<SpillManager>,
+}
+
+/// Writes rows of an equal-key run to a new spill. rows are written in
+/// multiple calls, so batches of a growing run can be released once written.
+struct SpilledRunWriter {
+    schema: SchemaRef,
+    batch_size: usize,
+    spill: Box<dyn Spill>,
+    spill_writer: BufWriter<Box<dyn Write + Send>>,
+    spill_manager: Arc<SpillManager>,
+}
+
+impl SpilledRunWriter {
+    fn try_new(
+        cur: &StreamCursor,
+        batch_size: usize,
+        spill_manager: &Arc<SpillManager>,
+    ) -> Result<Self> {
+        let spill = try_new_spill(spill_manager)?;
+        Ok(Self {
+            schema: cur.batches[0].schema(),
+            batch_size,
+            spill_writer: spill.get_buf_writer(),
+            spill,
+            spill_manager: spill_manager.clone(),
+        })
+    }
+
+    fn write(&mut self, cur: &StreamCursor, run: &[(usize, usize)]) -> Result<()> {
+        let interleaver = BatchesInterleaver::new(self.schema.clone(), &cur.batches);
+        for run_chunk in run.chunks(self.batch_size) {
+            let batch = interleaver.interleave(run_chunk)?;
+            self.spill_manager
+                .write_batch(&batch, &mut self.spill_writer)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<SpilledRun> {
+        self.spill_writer.flush()?;
+        drop(self.spill_writer);
+        self.spill.complete()?;
+        Ok(SpilledRun {
+            schema: self.schema,
+            spill: self.spill,
+            spill_manager: self.spill_manager,
+        })
+    }
+}
+
+impl SpilledRun {
+    fn read(self) -> Result<SpilledRunReader> {
+        let next_spill = try_new_spill(&self.spill_manager)?;
+        Ok(SpilledRunReader {
+            schema: self.schema,
+            spill_manager: self.spill_manager,
+            spill_reader: self.spill.get_buf_reader(),
+            next_spill_writer: next_spill.get_buf_writer(),
+            next_spill,
+            _spill: self.spill,
+        })
+    }
+}
+
+struct SpilledRunReader {
+    schema: SchemaRef,
+    spill_manager: Arc<SpillManager>,
+    spill_reader: BufReader<Box<dyn Read + Send>>,
+    next_spill_writer: BufWriter<Box<dyn Write + Send>>,
+    next_spill: Box<dyn Spill>,
+    _spill: Box<dyn Spill>,
+}
+
+impl SpilledRunReader {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        // copy the serialized batch to the next spill without decoding
+        let mut ipc_length_buf = [0u8; 8];
+        if let Err(e) = self.spill_reader.read_exact(&mut ipc_length_buf) {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                return Ok(None);
+            }
+            return Err(e.into());
+        }
+        let ipc_length = u64::from_le_bytes(ipc_length_buf) as usize;
+        let mut buf = vec![0u8; 8 + ipc_length];
+        buf[..8].copy_from_slice(&ipc_length_buf);
+        self.spill_reader.read_exact(&mut buf[8..])?;
+        self.next_spill_writer.write_all(&buf)?;
+        read_one_batch(&mut Cursor::new(&buf), Some(self.schema.clone()), true)
+    }
+
+    fn finish(mut self) -> Result<SpilledRun> {
+        self.next_spill_writer.flush()?;
+        drop(self.next_spill_writer);
+        self.next_spill.complete()?;
+        Ok(SpilledRun {
+            schema: self.schema,
+            spill: self.next_spill,
+            spill_manager: self.spill_manager,
+        })
+    }
+}
+
+/// Reports memory of the buffered run to the memory manager. spilling is
+/// requested by setting `spill_requested`, which is served by the join loop.
+struct BufferedRunMemConsumer {
+    name: String,
+    mem_consumer_info: Option<Weak<MemConsumerInfo>>,
+    spill_requested: AtomicBool,
+}
+
+#[async_trait]
+impl MemConsumer for BufferedRunMemConsumer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_consumer_info(&mut self, consumer_info: Weak<MemConsumerInfo>) {
+        self.mem_consumer_info = Some(consumer_info);
+    }
+
+    fn get_consumer_info(&self) -> &Weak<MemConsumerInfo> {
+        self.mem_consumer_info
+            .as_ref()
+            .expect("consumer info not set")
+    }
+
+    async fn spill(&self) -> Result<()> {
+        self.spill_requested.store(true, SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for BufferedRunMemConsumer {
+    fn drop(&mut self) {
+        MemManager::deregister_consumer(self);
+    }
+}
+
 fn compare_cursor(
     lcur: &StreamCursor,
     lidx: (usize, usize),
@@ -869,6 +1305,7 @@ fn compare_cursor(
 
 #[cfg(test)]
 mod tests {
+    use crate::common::memory_manager::MemManager;
     use crate::sort_merge_join_exec::SortMergeJoinExec;
     use arrow;
     use arrow::array::*;
@@ -1038,6 +1475,7 @@ mod tests {
         join_type: JoinType,
         sort_options: Vec<SortOptions>,
     ) -> Result<(Vec<String>, Vec<RecordBatch>)> {
+        MemManager::init(1000000);
         let session_ctx = SessionContext::new();
         let task_ctx = session_ctx.task_ctx();
         let join = join_with_options(left, right, on, join_type, sort_options)?;
@@ -1054,6 +1492,7 @@ mod tests {
         on: JoinOn,
         join_type: JoinType,
     ) -> Result<(Vec<String>, Vec<RecordBatch>)> {
+        MemManager::init(1000000);
         let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(2));
         let task_ctx = session_ctx.task_ctx();
         let join = join(left, right, on, join_type)?;
@@ -1064,6 +1503,23 @@ mod tests {
         Ok((columns, batches))
     }
 
+    async fn join_collect_with_spilled_runs(
+        left: Arc<dyn ExecutionPlan>,
+        right: Arc<dyn ExecutionPlan>,
+        on: JoinOn,
+        join_type: JoinType,
+    ) -> Result<(Vec<String>, Vec<RecordBatch>)> {
+        MemManager::init(1000000);
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(4));
+        let task_ctx = session_ctx.task_ctx();
+        let join = join(left, right, on, join_type)?.with_buffer_spill_threshold(1);
+        let columns = columns(&join.schema());
+
+        let stream = join.execute(0, task_ctx)?;
+        let batches = common::collect(stream).await?;
+        Ok((columns, batches))
+    }
+
     #[tokio::test]
     async fn join_inner_one() -> Result<()> {
         let left = build_table(
@@ -1464,6 +1920,43 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn join_existence() -> Result<()> {
+        MemManager::init(1000000);
+        let left = build_table_i32_nullable(
+            ("a1", &vec![Some(1), Some(2), Some(2), Some(3), Some(4)]),
+            ("b1", &vec![None, Some(4), Some(5), Some(5), Some(7)]), // 7 does not exist on the right
+            ("c1", &vec![Some(6), Some(7), Some(8), Some(8), Some(9)]),
+        );
+        let right = build_table_i32_nullable(
+            ("a2", &vec![Some(10), Some(20), Some(30), Some(40)]),
+            ("b1", &vec![None, Some(4), Some(5), Some(6)]),
+            ("c2", &vec![Some(60), Some(70), Some(80), Some(90)]),
+        );
+        let on = vec![(
+            Column::new_with_schema("b1", &left.schema())?,
+            Column::new_with_schema("b1", &right.schema())?,
+        )];
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let join = join(left, right, on, LeftSemi)?.with_existence_column("exists".to_owned())?;
+        let batches = common::collect(join.execute(0, task_ctx)?).await?;
+        let expected = vec![
+            "+----+----+----+--------+",
+            "| a1 | b1 | c1 | exists |",
+            "+----+----+----+--------+",
+            "| 1  |    | 6  | false  |",
+            "| 2  | 4  | 7  | true   |",
+            "| 2  | 5  | 8  | true   |",
+            "| 3  | 5  | 8  | true   |",
+            "| 4  | 7  | 9  | false  |",
+            "+----+----+----+--------+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn join_with_duplicated_column_names() -> Result<()> {
         let left = build_table(
@@ -1780,4 +2273,134 @@ mod tests {
         assert_batches_sorted_eq!(expected, &batches);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn join_full_with_spilled_runs() -> Result<()> {
+        let left = build_table(
+            ("a1", &vec![1, 2, 3, 4]),
+            ("b1", &vec![1, 1, 2, 3]),
+            ("c1", &vec![7, 8, 9, 10]),
+        );
+        let right = build_table(
+            ("a2", &vec![10, 20, 30, 40, 50, 60, 70, 80]),
+            ("b1", &vec![1, 1, 1, 1, 1, 1, 1, 3]),
+            ("c2", &vec![70, 80, 90, 100, 110, 120, 130, 140]),
+        );
+
+        // the shorter run of the left side is spilled and joined with the rest
+        // of the right run in multiple passes
+        let on = vec![(
+            Column::new_with_schema("b1", &left.schema())?,
+            Column::new_with_schema("b1", &right.schema())?,
+        )];
+        let (_, batches) =
+            join_collect_with_spilled_runs(left.clone(), right.clone(), on, Full).await?;
+        let expected = vec![
+            "+----+----+----+----+----+-----+",
+            "| a1 | b1 | c1 | a2 | b1 | c2  |",
+            "+----+----+----+----+----+-----+",
+            "| 1  | 1  | 7  | 10 | 1  | 70  |",
+            "| 1  | 1  | 7  | 20 | 1  | 80  |",
+            "| 1  | 1  | 7  | 30 | 1  | 90  |",
+            "| 1  | 1  | 7  | 40 | 1  | 100 |",
+            "| 1  | 1  | 7  | 50 | 1  | 110 |",
+            "| 1  | 1  | 7  | 60 | 1  | 120 |",
+            "| 1  | 1  | 7  | 70 | 1  | 130 |",
+            "| 2  | 1  | 8  | 10 | 1  | 70  |",
+            "| 2  | 1  | 8  | 20 | 1  | 80  |",
+            "| 2  | 1  | 8  | 30 | 1  | 90  |",
+            "| 2  | 1  | 8  | 40 | 1  | 100 |",
+            "| 2  | 1  | 8  | 50 | 1  | 110 |",
+            "| 2  | 1  | 8  | 60 | 1  | 120 |",
+            "| 2  | 1  | 8  | 70 | 1  | 130 |",
+            "| 3  | 2  | 9  |    |    |     |",
+            "| 4  | 3  | 10 | 80 | 3  | 140 |",
+            "+----+----+----+----+----+-----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+
+        // the shorter run of the right side is spilled
+        let on = vec![(
+            Column::new_with_schema("b1", &right.schema())?,
+            Column::new_with_schema("b1", &left.schema())?,
+        )];
+        let (_, batches) = join_collect_with_spilled_runs(right, left, on, Full).await?;
+        let expected = vec![
+            "+----+----+-----+----+----+----+",
+            "| a2 | b1 | c2  | a1 | b1 | c1 |",
+            "+----+----+-----+----+----+----+",
+            "|    |    |     | 3  | 2  | 9  |",
+            "| 10 | 1  | 70  | 1  | 1  | 7  |",
+            "| 10 | 1  | 70  | 2  | 1  | 8  |",
+            "| 20 | 1  | 80  | 1  | 1  | 7  |",
+            "| 20 | 1  | 80  | 2  | 1  | 8  |",
+            "| 30 | 1  | 90  | 1  | 1  | 7  |",
+            "| 30 | 1  | 90  | 2  | 1  | 8  |",
+            "| 40 | 1  | 100 | 1  | 1  | 7  |",
+            "| 40 | 1  | 100 | 2  | 1  | 8  |",
+            "| 50 | 1  | 110 | 1  | 1  | 7  |",
+            "| 50 | 1  | 110 | 2  | 1  | 8  |",
+            "| 60 | 1  | 120 | 1  | 1  | 7  |",
+            "| 60 | 1  | 120 | 2  | 1  | 8  |",
+            "| 70 | 1  | 130 | 1  | 1  | 7  |",
+            "| 70 | 1  | 130 | 2  | 1  | 8  |",
+            "| 80 | 3  | 140 | 4  | 3  | 10 |",
+            "+----+----+-----+----+----+----+",
+        ];
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_full_with_runs_spilled_while_buffering() -> Result<()> {
+        // key 1 spans multiple batches on both sides, so the runs must be
+        // spilled while they are still being accumulated
+        let left = build_table_from_batches(vec![
+            build_table_i32(
+                ("a1", &vec![1, 2, 3]),
+                ("b1", &vec![0, 1, 1]),
+                ("c1", &vec![7, 8, 9]),
+            ),
+            build_table_i32(
+                ("a1", &vec![4, 5, 6]),
+                ("b1", &vec![1, 1, 1]),
+                ("c1", &vec![10, 11, 12]),
+            ),
+            build_table_i32(
+                ("a1", &vec![7, 8]),
+                ("b1", &vec![1, 2]),
+                ("c1", &vec![13, 14]),
+            ),
+        ]);
+        let right = build_table_from_batches(vec![
+            build_table_i32(
+                ("a2", &vec![10, 20]),
+                ("b1", &vec![1, 1]),
+                ("c2", &vec![70, 80]),
+            ),
+            build_table_i32(
+                ("a2", &vec![30, 40, 50]),
+                ("b1", &vec![1, 1, 1]),
+                ("c2", &vec![90, 100, 110]),
+            ),
+            build_table_i32(
+                ("a2", &vec![60, 70]),
+                ("b1", &vec![1, 3]),
+                ("c2", &vec![120, 130]),
+            ),
+        ]);
+
+        let on = vec![(
+            Column::new_with_schema("b1", &left.schema())?,
+            Column::new_with_schema("b1", &right.schema())?,
+        )];
+        let (_, expected) = join_collect(left.clone(), right.clone(), on.clone(), Full).await?;
+        let (_, batches) = join_collect_with_spilled_runs(left, right, on, Full).await?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 39);
+
+        let expected = arrow::util::pretty::pretty_format_batches(&expected)?.to_string();
+        let expected = expected.trim().lines().collect::<Vec<_>>();
+        assert_batches_sorted_eq!(expected, &batches);
+        Ok(())
+    }
 }
@@ -0,0 +1,394 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::output::output_with_sender;
+use crate::common::BatchesInterleaver;
+use crate::sort_exec::{flatten_sort_fields, flatten_sort_keys};
+use arrow::array::ArrayRef;
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use arrow::row::{OwnedRow, RowConverter};
+use blaze_jni_bridge::{is_jni_bridge_inited, jni_call_static};
+use datafusion::common::{DataFusionError, Result, Statistics};
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::{
+    BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricsSet,
+};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, SendableRecordBatchStream,
+};
+use futures::stream::once;
+use futures::{StreamExt, TryStreamExt};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::Formatter;
+use std::sync::Arc;
+
+const DEFAULT_TOPK_MAX_LIMIT: usize = 100000;
+
+/// Max limit executed with `TopKExec`. rows retained in the heap are not
+/// accounted by the memory manager and cannot be spilled, so larger limits
+/// should be executed with a SortExec with fetch limit.
+pub fn topk_max_limit() -> Result<usize> {
+    if !is_jni_bridge_inited() {
+        return Ok(DEFAULT_TOPK_MAX_LIMIT);
+    }
+    let max_limit = jni_call_static!(BlazeConf.topKMaxLimit() -> i32)?;
+    Ok(max_limit.max(0) as usize)
+}
+
+/// Takes the first `limit` rows of each partition in the order of the sort
+/// exprs, like a SortExec with fetch limit. instead of sorting all input rows,
+/// a bounded binary heap over the sort keys retains the top rows, and other
+/// rows are pruned as soon as they are read.
+#[derive(Debug)]
+pub struct TopKExec {
+    input: Arc<dyn ExecutionPlan>,
+    exprs: Vec<PhysicalSortExpr>,
+    limit: usize,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl TopKExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>, exprs: Vec<PhysicalSortExpr>, limit: usize) -> Self {
+        Self {
+            input,
+            exprs,
+            limit,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl DisplayAs for TopKExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        let exprs = self
+            .exprs
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "TopKExec: limit={}, {}", self.limit, exprs)
+    }
+}
+
+impl ExecutionPlan for TopKExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        Some(&self.exprs)
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![self.input.clone()]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::new(
+                children[0].clone(),
+                self.exprs.clone(),
+                self.limit,
+            ))),
+            _ => Err(DataFusionError::Internal(
+                "TopKExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let input = self.input.execute(partition, context.clone())?;
+        let topk = TopK::try_new(input.schema(), self.exprs.clone(), self.limit)?;
+        let pruned_rows = MetricBuilder::new(&self.metrics).counter("pruned_rows", partition);
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            once(execute_topk(
+                input,
+                context,
+                topk,
+                pruned_rows,
+                baseline_metrics,
+            ))
+            .try_flatten(),
+        )))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        todo!()
+    }
+}
+
+async fn execute_topk(
+    mut input: SendableRecordBatchStream,
+    context: Arc<TaskContext>,
+    mut topk: TopK,
+    pruned_rows: Count,
+    metrics: BaselineMetrics,
+) -> Result<SendableRecordBatchStream> {
+    while let Some(batch) = input.next().await.transpose()? {
+        let _timer = metrics.elapsed_compute().timer();
+        pruned_rows.add(topk.insert_batch(batch)?);
+    }
+
+    let batch_size = context.session_config().batch_size();
+    output_with_sender("TopK", context, input.schema(), move |sender| async move {
+        let mut timer = metrics.elapsed_compute().timer();
+        for batch in topk.into_sorted_batches(batch_size)? {
+            metrics.record_output(batch.num_rows());
+            sender.send(Ok(batch), Some(&mut timer)).await;
+        }
+        Ok(())
+    })
+}
+
+struct TopK {
+    schema: SchemaRef,
+    exprs: Vec<PhysicalSortExpr>,
+    limit: usize,
+    row_converter: RowConverter,
+    heap: BinaryHeap<TopKRow>,
+
+    // batches referenced by rows in the heap, compacted when too many rows
+    // are retained
+    batches: Vec<RecordBatch>,
+    num_batches_rows: usize,
+}
+
+impl TopK {
+    fn try_new(schema: SchemaRef, exprs: Vec<PhysicalSortExpr>, limit: usize) -> Result<Self> {
+        let mut sort_fields = vec![];
+        for expr in &exprs {
+            let data_type = expr.expr.data_type(&schema)?;
+            flatten_sort_fields(&data_type, expr.options, &mut sort_fields);
+        }
+        Ok(Self {
+            schema,
+            exprs,
+            limit,
+            row_converter: RowConverter::new(sort_fields)?,
+            heap: BinaryHeap::with_capacity(limit.min(65536)),
+            batches: vec![],
+            num_batches_rows: 0,
+        })
+    }
+
+    /// Inserts rows of the batch into the heap, returns number of pruned rows
+    fn insert_batch(&mut self, batch: RecordBatch) -> Result<usize> {
+        if self.limit == 0 || batch.num_rows() == 0 {
+            return Ok(batch.num_rows());
+        }
+
+        let mut key_cols: Vec<ArrayRef> = vec![];
+        for expr in &self.exprs {
+            let key_col = expr.expr.evaluate(&batch)?.into_array(batch.num_rows());
+            flatten_sort_keys(key_col, expr.options, &mut key_cols)?;
+        }
+        let rows = self.row_converter.convert_columns(&key_cols)?;
+
+        let batch_idx = self.batches.len();
+        let mut num_inserted = 0;
+        let mut num_pruned = 0;
+        for (row_idx, row) in rows.iter().enumerate() {
+            if self.heap.len() >= self.limit {
+                // prune rows not less than the current k-th row
+                let mut max = self.heap.peek_mut().unwrap();
+                if row >= max.key.row() {
+                    num_pruned += 1;
+                    continue;
+                }
+                *max = TopKRow {
+                    key: row.owned(),
+                    batch_idx,
+                    row_idx,
+                };
+                num_inserted += 1;
+                num_pruned += 1;
+                continue;
+            }
+            self.heap.push(TopKRow {
+                key: row.owned(),
+                batch_idx,
+                row_idx,
+            });
+            num_inserted += 1;
+        }
+
+        if num_inserted > 0 {
+            self.num_batches_rows += batch.num_rows();
+            self.batches.push(batch);
+            if self.num_batches_rows > self.limit * 2 {
+                self.compact()?;
+            }
+        }
+        Ok(num_pruned)
+    }
+
+    /// Copies rows in the heap into a single batch, releasing the pruned rows
+    fn compact(&mut self) -> Result<()> {
+        let mut heap_rows = std::mem::take(&mut self.heap).into_vec();
+        let indices = heap_rows
+            .iter()
+            .map(|row| (row.batch_idx, row.row_idx))
+            .collect::<Vec<_>>();
+        let batch =
+            BatchesInterleaver::new(self.schema.clone(), &self.batches).interleave(&indices)?;
+
+        for (row_idx, row) in heap_rows.iter_mut().enumerate() {
+            row.batch_idx = 0;
+            row.row_idx = row_idx;
+        }
+        self.heap = BinaryHeap::from(heap_rows);
+        self.num_batches_rows = batch.num_rows();
+        self.batches = vec![batch];
+        Ok(())
+    }
+
+    fn into_sorted_batches(self, batch_size: usize) -> Result<Vec<RecordBatch>> {
+        let sorted_rows = self.heap.into_sorted_vec();
+        let interleaver = BatchesInterleaver::new(self.schema, &self.batches);
+        sorted_rows
+            .chunks(batch_size)
+            .map(|chunk| {
+                let indices = chunk
+                    .iter()
+                    .map(|row| (row.batch_idx, row.row_idx))
+                    .collect::<Vec<_>>();
+                interleaver.interleave(&indices)
+            })
+            .collect()
+    }
+}
+
+struct TopKRow {
+    key: OwnedRow,
+    batch_idx: usize,
+    row_idx: usize,
+}
+
+impl PartialEq for TopKRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.key.row() == other.key.row()
+    }
+}
+
+impl Eq for TopKRow {}
+
+impl PartialOrd for TopKRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TopKRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.row().cmp(&other.key.row())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::topk_exec::TopKExec;
+    use arrow::array::Int32Array;
+    use arrow::compute::SortOptions;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::physical_expr::expressions::Column;
+    use datafusion::physical_expr::PhysicalSortExpr;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_topk() -> Result<()> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batches = (0..10)
+            .map(|i| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int32Array::from_iter(
+                            (0..100).map(|j| (j % 7 != 3).then_some((i * 37 + j * 11) % 1000)),
+                        )),
+                        Arc::new(Int32Array::from_iter_values(i * 100..(i + 1) * 100)),
+                    ],
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None)?);
+
+        // ORDER BY a DESC NULLS LAST LIMIT 5
+        let topk = TopKExec::new(
+            input,
+            vec![PhysicalSortExpr {
+                expr: Arc::new(Column::new("a", 0)),
+                options: SortOptions {
+                    descending: true,
+                    nulls_first: false,
+                },
+            }],
+            5,
+        );
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(2));
+        let output = common::collect(topk.execute(0, session_ctx.task_ctx())?).await?;
+        let expected = vec![
+            "+-----+-----+",
+            "| a   | b   |",
+            "+-----+-----+",
+            "| 999 | 574 |",
+            "| 998 | 284 |",
+            "| 996 | 767 |",
+            "| 995 | 477 |",
+            "| 993 | 960 |",
+            "+-----+-----+",
+        ];
+        assert_batches_eq!(expected, &output);
+        assert_eq!(output.len(), 3);
+
+        let pruned_rows = topk.metrics().unwrap().sum_by_name("pruned_rows").unwrap();
+        assert_eq!(pruned_rows.as_usize(), 995);
+        Ok(())
+    }
+}
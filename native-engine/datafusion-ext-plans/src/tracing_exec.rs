@@ -0,0 +1,185 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use datafusion::error::Result;
+use datafusion::execution::context::TaskContext;
+use datafusion::physical_expr::PhysicalSortExpr;
+use datafusion::physical_plan::metrics::MetricsSet;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+    SendableRecordBatchStream, Statistics,
+};
+use futures::{Stream, StreamExt};
+use std::any::Any;
+use std::fmt::Formatter;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tracing::Span;
+
+/// Executes the inner plan inside a tracing span named after it, and polls
+/// its output stream inside the span. the wrapper is transparent to plan
+/// traversing: `as_any()`, children and metrics are all delegated to the
+/// inner plan, so metrics updating and downcasting work as unwrapped.
+#[derive(Debug)]
+pub struct TracingExec {
+    inner: Arc<dyn ExecutionPlan>,
+}
+
+impl TracingExec {
+    /// Wraps every node of the plan tree
+    pub fn instrument(plan: Arc<dyn ExecutionPlan>) -> Result<Arc<dyn ExecutionPlan>> {
+        let children = plan
+            .children()
+            .into_iter()
+            .map(Self::instrument)
+            .collect::<Result<Vec<_>>>()?;
+        let inner = if children.is_empty() {
+            plan
+        } else {
+            plan.with_new_children(children)?
+        };
+        Ok(Arc::new(Self { inner }))
+    }
+
+    fn span_name(&self) -> String {
+        struct Wrapper<'a>(&'a dyn ExecutionPlan);
+        impl std::fmt::Display for Wrapper<'_> {
+            fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+                self.0.fmt_as(DisplayFormatType::Default, f)
+            }
+        }
+        Wrapper(self.inner.as_ref()).to_string()
+    }
+}
+
+impl DisplayAs for TracingExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        self.inner.fmt_as(t, f)
+    }
+}
+
+impl ExecutionPlan for TracingExec {
+    fn as_any(&self) -> &dyn Any {
+        self.inner.as_any()
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.inner.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.inner.output_partitioning()
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        self.inner.output_ordering()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        self.inner.children()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(Self {
+            inner: self.inner.clone().with_new_children(children)?,
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        // children are executed inside this span, so their spans are nested
+        let span = tracing::info_span!("execute", plan = %self.span_name(), partition);
+        let input = span.in_scope(|| self.inner.execute(partition, context))?;
+        Ok(Box::pin(TracingStream { input, span }))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        self.inner.metrics()
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.inner.statistics()
+    }
+}
+
+struct TracingStream {
+    input: SendableRecordBatchStream,
+    span: Span,
+}
+
+impl RecordBatchStream for TracingStream {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
+
+impl Stream for TracingStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let span = self.span.clone();
+        let _entered = span.enter();
+        self.input.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::debug_exec::DebugExec;
+    use crate::tracing_exec::TracingExec;
+    use arrow::array::Int32Array;
+    use arrow::record_batch::RecordBatch;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_tracing_exec() -> Result<()> {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "a",
+            Arc::new(Int32Array::from(vec![1, 2, 3])) as _,
+        )])?;
+        let memory_exec = Arc::new(MemoryExec::try_new(
+            &[vec![batch.clone()]],
+            batch.schema(),
+            None,
+        )?);
+        let plan = Arc::new(DebugExec::new(memory_exec, "test".to_string()));
+        let traced = TracingExec::instrument(plan)?;
+
+        // wrapper is transparent to downcasting and traversing
+        assert!(traced.as_any().downcast_ref::<DebugExec>().is_some());
+        assert!(traced.children()[0]
+            .as_any()
+            .downcast_ref::<MemoryExec>()
+            .is_some());
+        assert!(traced.metrics().is_some());
+
+        let task_ctx = SessionContext::new().task_ctx();
+        let output = common::collect(traced.execute(0, task_ctx)?).await?;
+        assert_eq!(output, vec![batch]);
+        Ok(())
+    }
+}
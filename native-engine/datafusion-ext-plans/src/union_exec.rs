@@ -0,0 +1,267 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Union with spark compatible type widening, see spark's
+//! `TypeCoercion.WidenSetOperationTypes`.
+
+use crate::project_exec::ProjectExec;
+use arrow::datatypes::{DataType, TimeUnit, DECIMAL128_MAX_PRECISION};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::physical_expr::expressions::Column;
+use datafusion::physical_expr::PhysicalExprRef;
+use datafusion::physical_plan::union::UnionExec;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion_ext_exprs::cast::TryCastExpr;
+use std::sync::Arc;
+
+/// Creates a `UnionExec` whose column types are widened to the common types of
+/// all children. columns of children not matching the common types are casted
+/// before being unioned.
+pub fn try_new_union_with_type_widening(
+    children: Vec<Arc<dyn ExecutionPlan>>,
+) -> Result<UnionExec> {
+    let num_columns = children
+        .first()
+        .map(|child| child.schema().fields().len())
+        .ok_or_else(|| DataFusionError::Plan("Union requires at least one child".to_string()))?;
+    if children
+        .iter()
+        .any(|child| child.schema().fields().len() != num_columns)
+    {
+        return Err(DataFusionError::Plan(
+            "Union children must have the same number of columns".to_string(),
+        ));
+    }
+
+    // find the common type of each column
+    let mut wider_types = children[0]
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field.data_type().clone())
+        .collect::<Vec<_>>();
+    for child in &children[1..] {
+        for (wider_type, field) in wider_types.iter_mut().zip(child.schema().fields()) {
+            *wider_type = find_wider_type(wider_type, field.data_type()).ok_or_else(|| {
+                DataFusionError::Plan(format!(
+                    "Union cannot find a common type of {:?} and {:?} for column {}",
+                    wider_type,
+                    field.data_type(),
+                    field.name(),
+                ))
+            })?;
+        }
+    }
+
+    // cast children to the common types
+    let children = children
+        .into_iter()
+        .map(|child| {
+            let schema = child.schema();
+            if schema
+                .fields()
+                .iter()
+                .zip(&wider_types)
+                .all(|(field, wider_type)| field.data_type() == wider_type)
+            {
+                return Ok(child);
+            }
+            let exprs = schema
+                .fields()
+                .iter()
+                .zip(&wider_types)
+                .enumerate()
+                .map(|(i, (field, wider_type))| {
+                    let col: PhysicalExprRef = Arc::new(Column::new(field.name(), i));
+                    let expr: PhysicalExprRef = if field.data_type() == wider_type {
+                        col
+                    } else {
+                        Arc::new(TryCastExpr::new(col, wider_type.clone()))
+                    };
+                    (expr, field.name().clone())
+                })
+                .collect();
+            let casted: Arc<dyn ExecutionPlan> = Arc::new(ProjectExec::try_new(exprs, child)?);
+            Ok(casted)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(UnionExec::new(children))
+}
+
+/// Finds the wider type of two types like spark's `findWiderTypeForTwo()`
+pub fn find_wider_type(t1: &DataType, t2: &DataType) -> Option<DataType> {
+    find_tightest_common_type(t1, t2)
+        .or_else(|| find_wider_type_for_decimal(t1, t2))
+        .or_else(|| string_promotion(t1, t2))
+}
+
+fn find_tightest_common_type(t1: &DataType, t2: &DataType) -> Option<DataType> {
+    match (t1, t2) {
+        (t1, t2) if t1 == t2 => Some(t1.clone()),
+        (DataType::Null, t) | (t, DataType::Null) => Some(t.clone()),
+        (DataType::Date32, t @ DataType::Timestamp(TimeUnit::Microsecond, _))
+        | (t @ DataType::Timestamp(TimeUnit::Microsecond, _), DataType::Date32) => Some(t.clone()),
+        (t1, t2) => {
+            let p1 = numeric_precedence(t1)?;
+            let p2 = numeric_precedence(t2)?;
+            Some(if p1 >= p2 { t1.clone() } else { t2.clone() })
+        }
+    }
+}
+
+fn find_wider_type_for_decimal(t1: &DataType, t2: &DataType) -> Option<DataType> {
+    match (t1, t2) {
+        (&DataType::Decimal128(p1, s1), &DataType::Decimal128(p2, s2)) => {
+            Some(wider_decimal_type(p1, s1, p2, s2))
+        }
+        (&DataType::Decimal128(p1, s1), t) | (t, &DataType::Decimal128(p1, s1)) => match t {
+            DataType::Float32 | DataType::Float64 => Some(DataType::Float64),
+            t => {
+                let (p2, s2) = integral_decimal_precision(t)?;
+                Some(wider_decimal_type(p1, s1, p2, s2))
+            }
+        },
+        _ => None,
+    }
+}
+
+fn string_promotion(t1: &DataType, t2: &DataType) -> Option<DataType> {
+    match (t1, t2) {
+        (DataType::Utf8, t) | (t, DataType::Utf8)
+            if !matches!(t, DataType::Binary | DataType::Boolean) && !t.is_nested() =>
+        {
+            Some(DataType::Utf8)
+        }
+        _ => None,
+    }
+}
+
+fn numeric_precedence(t: &DataType) -> Option<usize> {
+    Some(match t {
+        DataType::Int8 => 0,
+        DataType::Int16 => 1,
+        DataType::Int32 => 2,
+        DataType::Int64 => 3,
+        DataType::Float32 => 4,
+        DataType::Float64 => 5,
+        _ => return None,
+    })
+}
+
+fn integral_decimal_precision(t: &DataType) -> Option<(u8, i8)> {
+    Some(match t {
+        DataType::Int8 => (3, 0),
+        DataType::Int16 => (5, 0),
+        DataType::Int32 => (10, 0),
+        DataType::Int64 => (20, 0),
+        _ => return None,
+    })
+}
+
+fn wider_decimal_type(p1: u8, s1: i8, p2: u8, s2: i8) -> DataType {
+    let scale = s1.max(s2);
+    let range = (p1 as i8 - s1).max(p2 as i8 - s2);
+    let precision = (range + scale) as u8;
+    DataType::Decimal128(
+        precision.min(DECIMAL128_MAX_PRECISION),
+        scale.min(DECIMAL128_MAX_PRECISION as i8),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::memory_manager::MemManager;
+    use crate::union_exec::{find_wider_type, try_new_union_with_type_widening};
+    use arrow::array::{Int32Array, Int64Array};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::Result;
+    use datafusion::physical_plan::memory::MemoryExec;
+    use datafusion::physical_plan::{common, ExecutionPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_find_wider_type() {
+        assert_eq!(
+            find_wider_type(&DataType::Int32, &DataType::Int64),
+            Some(DataType::Int64)
+        );
+        assert_eq!(
+            find_wider_type(&DataType::Int64, &DataType::Float32),
+            Some(DataType::Float32)
+        );
+        assert_eq!(
+            find_wider_type(&DataType::Int32, &DataType::Decimal128(5, 2)),
+            Some(DataType::Decimal128(12, 2))
+        );
+        assert_eq!(
+            find_wider_type(&DataType::Decimal128(10, 2), &DataType::Decimal128(38, 10)),
+            Some(DataType::Decimal128(38, 10))
+        );
+        assert_eq!(
+            find_wider_type(&DataType::Decimal128(10, 2), &DataType::Float32),
+            Some(DataType::Float64)
+        );
+        assert_eq!(
+            find_wider_type(&DataType::Int32, &DataType::Utf8),
+            Some(DataType::Utf8)
+        );
+        assert_eq!(find_wider_type(&DataType::Boolean, &DataType::Int32), None);
+    }
+
+    #[tokio::test]
+    async fn test_union_with_type_widening() -> Result<()> {
+        MemManager::init(10000);
+        let int_schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let int_batch = RecordBatch::try_new(
+            int_schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2]))],
+        )?;
+        let long_schema = Arc::new(Schema::new(vec![Field::new("b", DataType::Int64, false)]));
+        let long_batch = RecordBatch::try_new(
+            long_schema.clone(),
+            vec![Arc::new(Int64Array::from(vec![3, 10000000000]))],
+        )?;
+
+        let union = try_new_union_with_type_widening(vec![
+            Arc::new(MemoryExec::try_new(&[vec![int_batch]], int_schema, None)?),
+            Arc::new(MemoryExec::try_new(&[vec![long_batch]], long_schema, None)?),
+        ])?;
+        assert_eq!(union.schema().field(0).data_type(), &DataType::Int64);
+
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+        let mut output = vec![];
+        for partition in 0..union.output_partitioning().partition_count() {
+            output.extend(common::collect(union.execute(partition, task_ctx.clone())?).await?);
+        }
+        assert!(output
+            .iter()
+            .all(|batch| batch.column(0).data_type() == &DataType::Int64));
+        let expected = vec![
+            "+-------------+",
+            "| a           |",
+            "+-------------+",
+            "| 1           |",
+            "| 2           |",
+            "| 3           |",
+            "| 10000000000 |",
+            "+-------------+",
+        ];
+        assert_batches_sorted_eq!(expected, &output);
+        Ok(())
+    }
+}
@@ -14,6 +14,8 @@
 
 use crate::agg::{create_agg, AggFunction};
 use crate::window::processors::agg_processor::AggProcessor;
+use crate::window::processors::offset_processor::OffsetProcessor;
+use crate::window::processors::range_agg_processor::{check_range_order_spec, RangeAggProcessor};
 use crate::window::processors::rank_processor::RankProcessor;
 use crate::window::processors::row_number_processor::RowNumberProcessor;
 use crate::window::window_context::WindowContext;
@@ -31,6 +33,7 @@ pub mod window_context;
 pub enum WindowFunction {
     RankLike(WindowRankType),
     Agg(AggFunction),
+    Offset(WindowOffsetType),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -40,6 +43,28 @@ pub enum WindowRankType {
     DenseRank,
 }
 
+/// LAG/LEAD functions, children are the input and an optional default value
+#[derive(Debug, Clone, Copy)]
+pub enum WindowOffsetType {
+    /// input value of the row `n` rows before the current row
+    Lag(i64),
+
+    /// input value of the row `n` rows after the current row
+    Lead(i64),
+}
+
+/// Frame of agg window functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowFrame {
+    /// ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
+    Rows,
+
+    /// RANGE BETWEEN `preceding` PRECEDING AND CURRENT ROW on the single order
+    /// key, `None` for UNBOUNDED PRECEDING. the offset is in units of the order
+    /// key (microseconds for timestamps and days for dates).
+    Range { preceding: Option<i64> },
+}
+
 pub trait WindowFunctionProcessor: Send + Sync {
     fn process_batch(&mut self, context: &WindowContext, batch: &RecordBatch) -> Result<ArrayRef>;
     fn process_batch_without_partitions(
@@ -47,6 +72,14 @@ pub trait WindowFunctionProcessor: Send + Sync {
         context: &WindowContext,
         batch: &RecordBatch,
     ) -> Result<ArrayRef>;
+
+    /// Number of rows following the batch required to process it. if not
+    /// zero, the batch is followed by at least this number of lookahead rows
+    /// (unless input is finished), which are processed again in the next
+    /// batch, and only outputs of rows before them are used.
+    fn lookahead_rows(&self) -> usize {
+        0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +87,7 @@ pub struct WindowExpr {
     field: FieldRef,
     func: WindowFunction,
     children: Vec<Arc<dyn PhysicalExpr>>,
+    frame: WindowFrame,
 }
 
 impl WindowExpr {
@@ -66,9 +100,22 @@ impl WindowExpr {
             field,
             func,
             children,
+            frame: WindowFrame::Rows,
         }
     }
 
+    pub fn with_frame(self, frame: WindowFrame) -> Self {
+        Self { frame, ..self }
+    }
+
+    pub fn func(&self) -> WindowFunction {
+        self.func
+    }
+
+    pub fn frame(&self) -> WindowFrame {
+        self.frame
+    }
+
     pub fn create_processor(
         &self,
         context: &Arc<WindowContext>,
@@ -85,7 +132,24 @@ impl WindowExpr {
             }
             WindowFunction::Agg(agg_func) => {
                 let agg = create_agg(agg_func, &self.children, &context.input_schema)?;
-                Ok(Box::new(AggProcessor::try_new(agg)?))
+                match self.frame {
+                    WindowFrame::Rows => Ok(Box::new(AggProcessor::try_new(agg)?)),
+                    WindowFrame::Range { preceding } => {
+                        check_range_order_spec(context)?;
+                        Ok(Box::new(RangeAggProcessor::try_new(agg, preceding)?))
+                    }
+                }
+            }
+            WindowFunction::Offset(offset_type) => {
+                let offset = match offset_type {
+                    WindowOffsetType::Lag(n) => -n,
+                    WindowOffsetType::Lead(n) => n,
+                };
+                Ok(Box::new(OffsetProcessor::new(
+                    self.children[0].clone(),
+                    self.children.get(1).cloned(),
+                    offset,
+                )))
             }
         }
     }
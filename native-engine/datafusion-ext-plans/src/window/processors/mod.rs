@@ -13,5 +13,7 @@
 // limitations under the License.
 
 pub mod agg_processor;
+pub mod offset_processor;
+pub mod range_agg_processor;
 pub mod rank_processor;
 pub mod row_number_processor;
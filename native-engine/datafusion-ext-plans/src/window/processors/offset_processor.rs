@@ -0,0 +1,145 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::window::window_context::WindowContext;
+use crate::window::WindowFunctionProcessor;
+use arrow::array::{new_empty_array, new_null_array, ArrayRef, UInt32Array};
+use arrow::compute::{cast, concat, take};
+use arrow::record_batch::RecordBatch;
+use datafusion::common::Result;
+use datafusion::physical_expr::PhysicalExpr;
+use std::sync::Arc;
+
+/// Processes LAG/LEAD functions, the output of each row is the input value of
+/// the row `offset` rows after it in the same partition (negative for LAG), or
+/// the default value if that row is out of the partition.
+///
+/// for LAG, input values of the last `-offset` rows of the current partition
+/// are kept for the next batch. for LEAD, the batch is followed by at least
+/// `offset` lookahead rows, see `lookahead_rows()`.
+pub struct OffsetProcessor {
+    input: Arc<dyn PhysicalExpr>,
+    default: Option<Arc<dyn PhysicalExpr>>,
+    offset: i64,
+    cur_partition: Box<[u8]>,
+    history: Option<ArrayRef>,
+}
+
+impl OffsetProcessor {
+    pub fn new(
+        input: Arc<dyn PhysicalExpr>,
+        default: Option<Arc<dyn PhysicalExpr>>,
+        offset: i64,
+    ) -> Self {
+        Self {
+            input,
+            default,
+            offset,
+            cur_partition: Box::default(),
+            history: None,
+        }
+    }
+
+    /// `partition_ends` are the end row indices of partitions in the batch,
+    /// and the first partition is continued from the previous batch if
+    /// `continued` is set.
+    fn process(
+        &mut self,
+        batch: &RecordBatch,
+        partition_ends: &[usize],
+        continued: bool,
+    ) -> Result<ArrayRef> {
+        let num_rows = batch.num_rows();
+        let input = self.input.evaluate(batch)?.into_array(num_rows);
+        let default = match &self.default {
+            Some(default) => cast(
+                &default.evaluate(batch)?.into_array(num_rows),
+                input.data_type(),
+            )?,
+            None => new_null_array(input.data_type(), num_rows),
+        };
+        let history = match (continued, self.history.take()) {
+            (true, Some(history)) => history,
+            _ => new_empty_array(input.data_type()),
+        };
+
+        // take from history, input and default values concatenated
+        let num_history_rows = history.len();
+        let values = concat(&[history.as_ref(), input.as_ref(), default.as_ref()])?;
+        let mut indices = Vec::with_capacity(num_rows);
+        let mut partition_start = 0;
+        let mut partition_value_start = 0;
+        for &partition_end in partition_ends {
+            if partition_start > 0 || !continued {
+                partition_value_start = num_history_rows + partition_start;
+            }
+            for row_idx in partition_start..partition_end {
+                let target = (num_history_rows + row_idx) as i64 + self.offset;
+                if target >= partition_value_start as i64
+                    && target < (num_history_rows + partition_end) as i64
+                {
+                    indices.push(target as u32);
+                } else {
+                    indices.push((num_history_rows + num_rows + row_idx) as u32);
+                }
+            }
+            partition_start = partition_end;
+        }
+        let output = take(&values, &UInt32Array::from(indices), None)?;
+
+        // keep the last values of the current partition for LAG
+        if self.offset < 0 {
+            let history_end = num_history_rows + num_rows;
+            let history_start = partition_value_start
+                .max(history_end.saturating_sub(self.offset.unsigned_abs() as usize));
+            let history_indices =
+                UInt32Array::from_iter_values((history_start..history_end).map(|idx| idx as u32));
+            self.history = Some(take(&values, &history_indices, None)?);
+        }
+        Ok(output)
+    }
+}
+
+impl WindowFunctionProcessor for OffsetProcessor {
+    fn process_batch(&mut self, context: &WindowContext, batch: &RecordBatch) -> Result<ArrayRef> {
+        if batch.num_rows() == 0 {
+            return self.process(batch, &[], true);
+        }
+        let partition_rows = context.get_partition_rows(batch)?;
+        let mut partition_ends = vec![];
+        for row_idx in 1..batch.num_rows() {
+            if partition_rows.row(row_idx) != partition_rows.row(row_idx - 1) {
+                partition_ends.push(row_idx);
+            }
+        }
+        partition_ends.push(batch.num_rows());
+
+        let first_partition = partition_rows.row(0);
+        let continued = first_partition.as_ref() == self.cur_partition.as_ref();
+        self.cur_partition = partition_rows.row(batch.num_rows() - 1).as_ref().into();
+        self.process(batch, &partition_ends, continued)
+    }
+
+    fn process_batch_without_partitions(
+        &mut self,
+        _: &WindowContext,
+        batch: &RecordBatch,
+    ) -> Result<ArrayRef> {
+        self.process(batch, &[batch.num_rows()], true)
+    }
+
+    fn lookahead_rows(&self) -> usize {
+        self.offset.max(0) as usize
+    }
+}
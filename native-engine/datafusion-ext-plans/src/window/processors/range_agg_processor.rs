@@ -0,0 +1,331 @@
+// Copyright 2022 The Blaze Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::agg::agg_buf::{create_agg_buf_from_initial_value, AggBuf};
+use crate::agg::Agg;
+use crate::common::slim_bytes::SlimBytes;
+use crate::window::window_context::WindowContext;
+use crate::window::WindowFunctionProcessor;
+use arrow::array::{new_empty_array, ArrayRef};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use arrow::record_batch::RecordBatch;
+use arrow::row::Rows;
+use datafusion::common::cast::as_int64_array;
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Processes agg functions with `RANGE BETWEEN <preceding> AND CURRENT ROW`
+/// frames. the frame of a row contains all rows of the same partition whose
+/// order key is in `[key - preceding, key]` (both inclusive, like spark), so
+/// peers of the current row are always included.
+///
+/// requires that peer rows are never split into different batches.
+pub struct RangeAggProcessor {
+    cur_partition: SlimBytes,
+    agg: Arc<dyn Agg>,
+    agg_buf_init: AggBuf,
+    agg_buf_addrs: Box<[u64]>,
+    preceding: Option<i64>,
+
+    // rows of the current partition which may still be in frames of later rows,
+    // only used with bounded preceding
+    sliding_frame: SlidingFrame,
+
+    // all rows of the current partition, only used with unbounded preceding
+    running_agg_buf: AggBuf,
+}
+
+impl RangeAggProcessor {
+    /// `preceding` is the lower bound in units of the order key (microseconds for
+    /// timestamps and days for dates), `None` for UNBOUNDED PRECEDING.
+    pub fn try_new(agg: Arc<dyn Agg>, preceding: Option<i64>) -> Result<Self> {
+        let (agg_buf, agg_buf_addrs) = create_agg_buf_from_initial_value(agg.accums_initial())?;
+        Ok(Self {
+            cur_partition: Default::default(),
+            agg,
+            agg_buf_init: agg_buf.clone(),
+            agg_buf_addrs,
+            preceding,
+            sliding_frame: SlidingFrame::new(agg_buf.clone()),
+            running_agg_buf: agg_buf,
+        })
+    }
+
+    fn process(
+        &mut self,
+        context: &WindowContext,
+        batch: &RecordBatch,
+        partition_rows: Option<Rows>,
+    ) -> Result<ArrayRef> {
+        if batch.num_rows() == 0 {
+            return Ok(new_empty_array(self.agg.data_type()));
+        }
+        let cols: Vec<ArrayRef> = self
+            .agg
+            .exprs()
+            .iter()
+            .map(|expr| expr.evaluate(batch).map(|v| v.into_array(batch.num_rows())))
+            .collect::<Result<_>>()?;
+        let keys = get_order_keys(context, batch)?;
+
+        let same_partition = |row_idx1: usize, row_idx2: usize| match &partition_rows {
+            Some(partition_rows) => partition_rows.row(row_idx1) == partition_rows.row(row_idx2),
+            None => true,
+        };
+
+        let mut output = Vec::with_capacity(batch.num_rows());
+        let mut row_idx = 0;
+        while row_idx < keys.len() {
+            if let Some(partition_rows) = &partition_rows {
+                let partition_row = partition_rows.row(row_idx);
+                if partition_row.as_ref() != self.cur_partition.as_ref() {
+                    self.cur_partition = partition_row.as_ref().into();
+                    self.running_agg_buf = self.agg_buf_init.clone();
+                    self.sliding_frame = SlidingFrame::new(self.agg_buf_init.clone());
+                }
+            }
+
+            // peers share the same output value
+            let mut peers_end = row_idx + 1;
+            while peers_end < keys.len()
+                && keys[peers_end] == keys[row_idx]
+                && same_partition(peers_end, row_idx)
+            {
+                peers_end += 1;
+            }
+
+            let value = match self.preceding {
+                None => {
+                    for i in row_idx..peers_end {
+                        partial_update(
+                            &self.agg,
+                            &mut self.running_agg_buf,
+                            &self.agg_buf_addrs,
+                            &cols,
+                            i,
+                        )?;
+                    }
+                    self.agg
+                        .final_merge(&mut self.running_agg_buf.clone(), &self.agg_buf_addrs)?
+                }
+                Some(preceding) => {
+                    // peers share the same key
+                    let frame = &mut self.sliding_frame;
+                    for i in row_idx..peers_end {
+                        let mut agg_buf = self.agg_buf_init.clone();
+                        partial_update(&self.agg, &mut agg_buf, &self.agg_buf_addrs, &cols, i)?;
+                        frame.push(&self.agg, &self.agg_buf_addrs, agg_buf, keys[row_idx])?;
+                    }
+
+                    // keys are sorted within a partition, so the frame start only
+                    // moves forward. null keys are only peers of each other.
+                    let num_peers = peers_end - row_idx;
+                    while frame.len() > num_peers {
+                        let out_of_frame = match (keys[row_idx], frame.front_key()) {
+                            (Some(key), Some(front_key)) => {
+                                front_key < key.saturating_sub(preceding)
+                            }
+                            (Some(_), None) => true,
+                            (None, front_key) => front_key.is_some(),
+                        };
+                        if !out_of_frame {
+                            break;
+                        }
+                        frame.pop_front(&self.agg, &self.agg_buf_init, &self.agg_buf_addrs)?;
+                    }
+                    self.agg.final_merge(
+                        &mut frame.merged(&self.agg, &self.agg_buf_addrs)?,
+                        &self.agg_buf_addrs,
+                    )?
+                }
+            };
+            output.extend(std::iter::repeat(value).take(peers_end - row_idx));
+            row_idx = peers_end;
+        }
+        Ok(Arc::new(ScalarValue::iter_to_array(output.into_iter())?))
+    }
+}
+
+impl WindowFunctionProcessor for RangeAggProcessor {
+    fn process_batch(&mut self, context: &WindowContext, batch: &RecordBatch) -> Result<ArrayRef> {
+        let partition_rows = context.get_partition_rows(batch)?;
+        self.process(context, batch, Some(partition_rows))
+    }
+
+    fn process_batch_without_partitions(
+        &mut self,
+        context: &WindowContext,
+        batch: &RecordBatch,
+    ) -> Result<ArrayRef> {
+        self.process(context, batch, None)
+    }
+}
+
+/// Rows of a sliding frame, which are pushed to the back and popped from the
+/// front. agg functions can be merged but not retracted, so the frame is split
+/// into front rows, holding the aggregate of each front row and all front rows
+/// after it, and back rows, holding the aggregate of all back rows. when the
+/// front rows are used up, all back rows become front rows, so each row is
+/// merged O(1) times amortized.
+struct SlidingFrame {
+    keys: VecDeque<Option<i64>>,
+    front_agg_bufs: VecDeque<AggBuf>,
+    back_row_agg_bufs: Vec<AggBuf>,
+    back_agg_buf: AggBuf,
+}
+
+impl SlidingFrame {
+    fn new(agg_buf_init: AggBuf) -> Self {
+        Self {
+            keys: VecDeque::new(),
+            front_agg_bufs: VecDeque::new(),
+            back_row_agg_bufs: vec![],
+            back_agg_buf: agg_buf_init,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn front_key(&self) -> Option<i64> {
+        self.keys.front().cloned().flatten()
+    }
+
+    /// pushes a row with its own aggregate
+    fn push(
+        &mut self,
+        agg: &Arc<dyn Agg>,
+        agg_buf_addrs: &[u64],
+        row_agg_buf: AggBuf,
+        key: Option<i64>,
+    ) -> Result<()> {
+        partial_merge(
+            agg,
+            &mut self.back_agg_buf,
+            &mut row_agg_buf.clone(),
+            agg_buf_addrs,
+        )?;
+        self.back_row_agg_bufs.push(row_agg_buf);
+        self.keys.push_back(key);
+        Ok(())
+    }
+
+    fn pop_front(
+        &mut self,
+        agg: &Arc<dyn Agg>,
+        agg_buf_init: &AggBuf,
+        agg_buf_addrs: &[u64],
+    ) -> Result<()> {
+        if self.front_agg_bufs.is_empty() {
+            // aggregate back rows in reverse order
+            for mut agg_buf in std::mem::take(&mut self.back_row_agg_bufs)
+                .into_iter()
+                .rev()
+            {
+                if let Some(next_agg_buf) = self.front_agg_bufs.front() {
+                    partial_merge(agg, &mut agg_buf, &mut next_agg_buf.clone(), agg_buf_addrs)?;
+                }
+                self.front_agg_bufs.push_front(agg_buf);
+            }
+            self.back_agg_buf = agg_buf_init.clone();
+        }
+        self.front_agg_bufs.pop_front();
+        self.keys.pop_front();
+        Ok(())
+    }
+
+    /// returns the aggregate of all rows in the frame
+    fn merged(&self, agg: &Arc<dyn Agg>, agg_buf_addrs: &[u64]) -> Result<AggBuf> {
+        match self.front_agg_bufs.front() {
+            Some(front_agg_buf) => {
+                let mut agg_buf = front_agg_buf.clone();
+                partial_merge(
+                    agg,
+                    &mut agg_buf,
+                    &mut self.back_agg_buf.clone(),
+                    agg_buf_addrs,
+                )?;
+                Ok(agg_buf)
+            }
+            None => Ok(self.back_agg_buf.clone()),
+        }
+    }
+}
+
+fn partial_update(
+    agg: &Arc<dyn Agg>,
+    agg_buf: &mut AggBuf,
+    agg_buf_addrs: &[u64],
+    cols: &[ArrayRef],
+    row_idx: usize,
+) -> Result<()> {
+    agg.partial_update(agg_buf, agg_buf_addrs, cols, row_idx)
+        .map_err(|err| err.context("window: range_agg_processor partial_update() error"))
+}
+
+fn partial_merge(
+    agg: &Arc<dyn Agg>,
+    agg_buf: &mut AggBuf,
+    merging_agg_buf: &mut AggBuf,
+    agg_buf_addrs: &[u64],
+) -> Result<()> {
+    agg.partial_merge(agg_buf, merging_agg_buf, agg_buf_addrs)
+        .map_err(|err| err.context("window: range_agg_processor partial_merge() error"))
+}
+
+/// Checks that the order spec has a single integral, date or timestamp key
+pub fn check_range_order_spec(context: &WindowContext) -> Result<()> {
+    if context.order_spec.len() != 1 {
+        return Err(DataFusionError::Plan(format!(
+            "window: RANGE frame requires exactly one order key, got {}",
+            context.order_spec.len(),
+        )));
+    }
+    match context.order_schema.field(0).data_type() {
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::Date32
+        | DataType::Timestamp(..) => Ok(()),
+        other => Err(DataFusionError::Plan(format!(
+            "window: RANGE frame does not support order key type: {}",
+            other,
+        ))),
+    }
+}
+
+/// Evaluates the order key as i64, negated for descending order so that keys
+/// are always ascending within a partition
+fn get_order_keys(context: &WindowContext, batch: &RecordBatch) -> Result<Vec<Option<i64>>> {
+    let order = &context.order_spec[0];
+    let key_col = order.expr.evaluate(batch)?.into_array(batch.num_rows());
+    let key_col = cast(&key_col, &DataType::Int64)?;
+    let keys = as_int64_array(&key_col)?;
+    Ok(keys
+        .iter()
+        .map(|key| {
+            key.map(|k| {
+                if order.options.descending {
+                    k.saturating_neg()
+                } else {
+                    k
+                }
+            })
+        })
+        .collect())
+}
@@ -14,11 +14,13 @@
 
 use crate::common::output::output_with_sender;
 use crate::window::window_context::WindowContext;
-use crate::window::{WindowExpr, WindowFunctionProcessor};
+use crate::window::{WindowExpr, WindowFrame, WindowFunctionProcessor};
 use arrow::array::ArrayRef;
+use arrow::compute::concat_batches;
 use arrow::datatypes::SchemaRef;
 use arrow::error::ArrowError;
 use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use arrow::row::Rows;
 use datafusion::common::{Result, Statistics};
 use datafusion::execution::context::TaskContext;
 use datafusion::physical_expr::PhysicalSortExpr;
@@ -107,7 +109,8 @@ impl ExecutionPlan for WindowExec {
         partition: usize,
         context: Arc<TaskContext>,
     ) -> Result<SendableRecordBatchStream> {
-        // at this moment only supports ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
+        // agg functions support ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW and
+        // RANGE BETWEEN <preceding> PRECEDING AND CURRENT ROW
         let input = self.input.execute(partition, context.clone())?;
         let coalesced = Box::pin(CoalesceStream::new(
             input,
@@ -152,24 +155,73 @@ async fn execute_window(
         .map(|expr: &WindowExpr| expr.create_processor(&context))
         .collect::<Result<_>>()?;
 
+    // RANGE frames require that peer rows are processed in the same batch, and
+    // LEAD requires rows following the batch
+    let align_peers = context
+        .window_exprs
+        .iter()
+        .any(|expr| matches!(expr.frame(), WindowFrame::Range { .. }));
+    let lookahead_rows = processors
+        .iter()
+        .map(|processor| processor.lookahead_rows())
+        .max()
+        .unwrap_or(0);
+
     // start processing input batches
     output_with_sender(
         "Window",
         task_context,
         context.output_schema.clone(),
         |sender| async move {
-            while let Some(batch) = input.next().await.transpose()? {
+            let mut staged_rows = StagedRows::new(context.clone(), align_peers, lookahead_rows);
+            let mut input_finished = false;
+            loop {
+                let batch = match input_finished {
+                    false => input.next().await.transpose()?,
+                    true => None,
+                };
+                let batch = match batch {
+                    Some(batch) if align_peers || lookahead_rows > 0 => {
+                        match staged_rows.stage(batch)? {
+                            Some(batch) => batch,
+                            None => continue,
+                        }
+                    }
+                    Some(batch) => batch,
+                    None => {
+                        input_finished = true;
+                        match staged_rows.take_all()? {
+                            Some(batch) => batch,
+                            None => break,
+                        }
+                    }
+                };
                 let elapsed_time = metrics.elapsed_compute().clone();
                 let mut timer = elapsed_time.timer();
 
+                // staged rows are the lookahead rows of the batch
+                let lookahead_batch = if lookahead_rows > 0 && staged_rows.num_rows > 0 {
+                    concat_batches(
+                        &batch.schema(),
+                        std::iter::once(&batch).chain(&staged_rows.batches),
+                    )?
+                } else {
+                    batch.clone()
+                };
+
                 let window_cols: Vec<ArrayRef> = processors
                     .iter_mut()
                     .map(|processor| {
-                        if context.partition_spec.is_empty() {
-                            processor.process_batch_without_partitions(&context, &batch)
+                        let processed_batch = match processor.lookahead_rows() {
+                            0 => &batch,
+                            _ => &lookahead_batch,
+                        };
+                        let window_col = if context.partition_spec.is_empty() {
+                            processor.process_batch_without_partitions(&context, processed_batch)
                         } else {
-                            processor.process_batch(&context, &batch)
-                        }
+                            processor.process_batch(&context, processed_batch)
+                        }?;
+                        Ok(window_col.slice(0, batch.num_rows()))
                     })
                     .collect::<Result<_>>()?;
 
@@ -188,20 +240,166 @@ async fn execute_window(
     )
 }
 
+/// Trailing input rows staged and prepended to the following batches. at least
+/// `lookahead_rows` rows are staged, and if `align_peers` is set, the staged
+/// rows never start in the middle of peer rows (rows with the same partition
+/// and order keys), so peer rows are always processed in the same batch.
+///
+/// staged batches are kept separately along with their partition and order
+/// rows, and are concatenated only once when their leading rows are released,
+/// so a long run of staged rows is not copied again for every input batch.
+struct StagedRows {
+    context: Arc<WindowContext>,
+    align_peers: bool,
+    lookahead_rows: usize,
+    batches: Vec<RecordBatch>,
+    peer_keys: Vec<(Option<Rows>, Rows)>,
+    num_rows: usize,
+    trailing_peers_start: usize,
+}
+
+impl StagedRows {
+    fn new(context: Arc<WindowContext>, align_peers: bool, lookahead_rows: usize) -> Self {
+        Self {
+            context,
+            align_peers,
+            lookahead_rows,
+            batches: vec![],
+            peer_keys: vec![],
+            num_rows: 0,
+            trailing_peers_start: 0,
+        }
+    }
+
+    /// Stages the batch, returns the leading rows to process, or `None` if all
+    /// rows stay staged.
+    fn stage(&mut self, batch: RecordBatch) -> Result<Option<RecordBatch>> {
+        self.push(batch)?;
+        if self.num_rows <= self.lookahead_rows {
+            return Ok(None);
+        }
+
+        let mut num_processed_rows = self.num_rows - self.lookahead_rows;
+        if self.align_peers {
+            // the last row may be continued by the next batch, so its peers are
+            // always staged
+            let first_staged_row_idx = num_processed_rows.min(self.num_rows - 1);
+            num_processed_rows = if first_staged_row_idx >= self.trailing_peers_start {
+                self.trailing_peers_start
+            } else {
+                let mut row_idx = first_staged_row_idx;
+                while row_idx > 0 && self.is_peer(row_idx - 1, first_staged_row_idx) {
+                    row_idx -= 1;
+                }
+                row_idx
+            };
+        }
+        if num_processed_rows == 0 {
+            return Ok(None);
+        }
+
+        let batch = self.take_all()?.expect("staged rows must not be empty");
+        self.push(batch.slice(num_processed_rows, batch.num_rows() - num_processed_rows))?;
+        Ok(Some(batch.slice(0, num_processed_rows)))
+    }
+
+    /// Takes all staged rows in a single batch.
+    fn take_all(&mut self) -> Result<Option<RecordBatch>> {
+        let batches = std::mem::take(&mut self.batches);
+        self.peer_keys.clear();
+        self.num_rows = 0;
+        self.trailing_peers_start = 0;
+        match batches.len() {
+            0 => Ok(None),
+            1 => Ok(batches.into_iter().next()),
+            _ => Ok(Some(concat_batches(&batches[0].schema(), &batches)?)),
+        }
+    }
+
+    fn push(&mut self, batch: RecordBatch) -> Result<()> {
+        let num_rows = batch.num_rows();
+        if num_rows == 0 {
+            return Ok(());
+        }
+
+        if self.align_peers {
+            let partition_rows = match self.context.has_partition() {
+                true => Some(self.context.get_partition_rows(&batch)?),
+                false => None,
+            };
+            let order_rows = self.context.get_order_rows(&batch)?;
+            let keys = (partition_rows, order_rows);
+
+            // find the peers of the last row, which continue the trailing peers
+            // of previous batches if the first row is also a peer
+            let last_row_idx = num_rows - 1;
+            let mut peers_start = last_row_idx;
+            while peers_start > 0 && keys_eq(&keys, peers_start - 1, &keys, last_row_idx) {
+                peers_start -= 1;
+            }
+            let continued = peers_start == 0
+                && self.peer_keys.last().is_some_and(|prev_keys| {
+                    keys_eq(prev_keys, prev_keys.1.num_rows() - 1, &keys, last_row_idx)
+                });
+            if !continued {
+                self.trailing_peers_start = self.num_rows + peers_start;
+            }
+            self.peer_keys.push(keys);
+        }
+        self.batches.push(batch);
+        self.num_rows += num_rows;
+        Ok(())
+    }
+
+    fn is_peer(&self, row_idx1: usize, row_idx2: usize) -> bool {
+        let (keys1, row_idx1) = self.locate(row_idx1);
+        let (keys2, row_idx2) = self.locate(row_idx2);
+        keys_eq(keys1, row_idx1, keys2, row_idx2)
+    }
+
+    fn locate(&self, mut row_idx: usize) -> (&(Option<Rows>, Rows), usize) {
+        for keys in &self.peer_keys {
+            if row_idx < keys.1.num_rows() {
+                return (keys, row_idx);
+            }
+            row_idx -= keys.1.num_rows();
+        }
+        unreachable!("staged row index out of bounds")
+    }
+}
+
+fn keys_eq(
+    keys1: &(Option<Rows>, Rows),
+    row_idx1: usize,
+    keys2: &(Option<Rows>, Rows),
+    row_idx2: usize,
+) -> bool {
+    keys1.1.row(row_idx1) == keys2.1.row(row_idx2)
+        && match (&keys1.0, &keys2.0) {
+            (Some(partition_rows1), Some(partition_rows2)) => {
+                partition_rows1.row(row_idx1) == partition_rows2.row(row_idx2)
+            }
+            _ => true,
+        }
+}
+
 #[cfg(test)]
 mod test {
     use crate::agg::AggFunction;
-    use crate::window::{WindowExpr, WindowFunction, WindowRankType};
+    use crate::window::{
+        WindowExpr, WindowFrame, WindowFunction, WindowOffsetType, WindowRankType,
+    };
     use crate::window_exec::WindowExec;
     use arrow::array::*;
     use arrow::datatypes::*;
     use arrow::record_batch::RecordBatch;
     use datafusion::assert_batches_eq;
-    use datafusion::physical_expr::expressions::Column;
+    use datafusion::common::ScalarValue;
+    use datafusion::physical_expr::expressions::{Column, Literal};
     use datafusion::physical_expr::PhysicalSortExpr;
     use datafusion::physical_plan::memory::MemoryExec;
     use datafusion::physical_plan::ExecutionPlan;
-    use datafusion::prelude::SessionContext;
+    use datafusion::prelude::{SessionConfig, SessionContext};
     use std::sync::Arc;
 
     fn build_table_i32(
@@ -348,4 +546,277 @@ mod test {
         assert_batches_eq!(expected, &batches);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_window_lag_lead() -> Result<(), Box<dyn std::error::Error>> {
+        // a small batch size splits partitions into different batches
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(2));
+        let task_ctx = session_ctx.task_ctx();
+
+        let input = build_table(
+            ("a1", &vec![1, 1, 1, 1, 2, 3, 3]),
+            ("b1", &vec![1, 2, 2, 3, 4, 1, 1]),
+            ("c1", &vec![0, 0, 0, 0, 0, 0, 0]),
+        );
+        let window = Arc::new(WindowExec::try_new(
+            input,
+            vec![
+                WindowExpr::new(
+                    WindowFunction::Offset(WindowOffsetType::Lag(1)),
+                    vec![Arc::new(Column::new("b1", 1))],
+                    Arc::new(Field::new("b1_lag", DataType::Int32, true)),
+                ),
+                WindowExpr::new(
+                    WindowFunction::Offset(WindowOffsetType::Lead(2)),
+                    vec![
+                        Arc::new(Column::new("b1", 1)),
+                        Arc::new(Literal::new(ScalarValue::Int32(Some(-1)))),
+                    ],
+                    Arc::new(Field::new("b1_lead", DataType::Int32, true)),
+                ),
+            ],
+            vec![Arc::new(Column::new("a1", 0))],
+            vec![PhysicalSortExpr {
+                expr: Arc::new(Column::new("b1", 1)),
+                options: Default::default(),
+            }],
+        )?);
+        let stream = window.execute(0, task_ctx.clone())?;
+        let batches = datafusion::physical_plan::common::collect(stream).await?;
+        let expected = vec![
+            "+----+----+----+--------+---------+",
+            "| a1 | b1 | c1 | b1_lag | b1_lead |",
+            "+----+----+----+--------+---------+",
+            "| 1  | 1  | 0  |        | 2       |",
+            "| 1  | 2  | 0  | 1      | 3       |",
+            "| 1  | 2  | 0  | 2      | -1      |",
+            "| 1  | 3  | 0  | 2      | -1      |",
+            "| 2  | 4  | 0  |        | -1      |",
+            "| 3  | 1  | 0  |        | -1      |",
+            "| 3  | 1  | 0  | 1      | -1      |",
+            "+----+----+----+--------+---------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_window_lag_lead_split_batches() -> Result<(), Box<dyn std::error::Error>> {
+        let session_ctx = SessionContext::new();
+        let task_ctx = session_ctx.task_ctx();
+
+        // partitions and offset rows span different input batches
+        let batch = build_table_i32(
+            ("a1", &vec![1, 1, 1, 1, 2, 3, 3]),
+            ("b1", &vec![1, 2, 2, 3, 4, 1, 1]),
+            ("c1", &vec![0, 0, 0, 0, 0, 0, 0]),
+        );
+        let schema = batch.schema();
+        let batches =
+            vec![batch.slice(0, 2), batch.slice(2, 1), batch.slice(3, 3), batch.slice(6, 1)];
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None)?);
+        let window = Arc::new(WindowExec::try_new(
+            input,
+            vec![
+                WindowExpr::new(
+                    WindowFunction::Offset(WindowOffsetType::Lag(2)),
+                    vec![Arc::new(Column::new("b1", 1))],
+                    Arc::new(Field::new("b1_lag", DataType::Int32, true)),
+                ),
+                WindowExpr::new(
+                    WindowFunction::Offset(WindowOffsetType::Lead(2)),
+                    vec![
+                        Arc::new(Column::new("b1", 1)),
+                        Arc::new(Literal::new(ScalarValue::Int32(Some(-1)))),
+                    ],
+                    Arc::new(Field::new("b1_lead", DataType::Int32, true)),
+                ),
+            ],
+            vec![Arc::new(Column::new("a1", 0))],
+            vec![PhysicalSortExpr {
+                expr: Arc::new(Column::new("b1", 1)),
+                options: Default::default(),
+            }],
+        )?);
+        let stream = window.execute(0, task_ctx.clone())?;
+        let batches = datafusion::physical_plan::common::collect(stream).await?;
+        let expected = vec![
+            "+----+----+----+--------+---------+",
+            "| a1 | b1 | c1 | b1_lag | b1_lead |",
+            "+----+----+----+--------+---------+",
+            "| 1  | 1  | 0  |        | 2       |",
+            "| 1  | 2  | 0  |        | 3       |",
+            "| 1  | 2  | 0  | 1      | -1      |",
+            "| 1  | 3  | 0  | 2      | -1      |",
+            "| 2  | 4  | 0  |        | -1      |",
+            "| 3  | 1  | 0  |        | -1      |",
+            "| 3  | 1  | 0  |        | -1      |",
+            "+----+----+----+--------+---------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_window_range_frame() -> Result<(), Box<dyn std::error::Error>> {
+        // a small batch size splits the peers of 01:00:00 into different batches
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(3));
+        let task_ctx = session_ctx.task_ctx();
+
+        let minutes = |m: i64| m * 60 * 1000000;
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a1", DataType::Int32, false),
+            Field::new(
+                "ts",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new("v", DataType::Int64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 1, 1, 1, 1, 1, 2])),
+                Arc::new(TimestampMicrosecondArray::from(vec![
+                    minutes(0),
+                    minutes(30),
+                    minutes(60),
+                    minutes(60),
+                    minutes(150),
+                    minutes(200),
+                    minutes(0),
+                ])),
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5, 6, 7])),
+            ],
+        )?;
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None)?);
+
+        // RANGE BETWEEN INTERVAL 1 HOUR PRECEDING AND CURRENT ROW, and
+        // RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
+        let window = Arc::new(WindowExec::try_new(
+            input,
+            vec![
+                WindowExpr::new(
+                    WindowFunction::Agg(AggFunction::Sum),
+                    vec![Arc::new(Column::new("v", 2))],
+                    Arc::new(Field::new("v_sum_1h", DataType::Int64, true)),
+                )
+                .with_frame(WindowFrame::Range {
+                    preceding: Some(minutes(60)),
+                }),
+                WindowExpr::new(
+                    WindowFunction::Agg(AggFunction::Sum),
+                    vec![Arc::new(Column::new("v", 2))],
+                    Arc::new(Field::new("v_sum", DataType::Int64, true)),
+                )
+                .with_frame(WindowFrame::Range { preceding: None }),
+            ],
+            vec![Arc::new(Column::new("a1", 0))],
+            vec![PhysicalSortExpr {
+                expr: Arc::new(Column::new("ts", 1)),
+                options: Default::default(),
+            }],
+        )?);
+        let stream = window.execute(0, task_ctx.clone())?;
+        let batches = datafusion::physical_plan::common::collect(stream).await?;
+        let expected = vec![
+            "+----+---------------------+---+----------+-------+",
+            "| a1 | ts                  | v | v_sum_1h | v_sum |",
+            "+----+---------------------+---+----------+-------+",
+            "| 1  | 1970-01-01T00:00:00 | 1 | 1        | 1     |",
+            "| 1  | 1970-01-01T00:30:00 | 2 | 3        | 3     |",
+            "| 1  | 1970-01-01T01:00:00 | 3 | 10       | 10    |",
+            "| 1  | 1970-01-01T01:00:00 | 4 | 10       | 10    |",
+            "| 1  | 1970-01-01T02:30:00 | 5 | 5        | 15    |",
+            "| 1  | 1970-01-01T03:20:00 | 6 | 11       | 21    |",
+            "| 2  | 1970-01-01T00:00:00 | 7 | 7        | 7     |",
+            "+----+---------------------+---+----------+-------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_window_range_frame_peers_across_batches() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let session_ctx = SessionContext::with_config(SessionConfig::new().with_batch_size(1));
+        let task_ctx = session_ctx.task_ctx();
+
+        // every row is an input batch, peers of 01:00:00 span four batches
+        let minutes = |m: i64| m * 60 * 1000000;
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a1", DataType::Int32, false),
+            Field::new(
+                "ts",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new("v", DataType::Int64, false),
+        ]));
+        let batches = [0, 60, 60, 60, 60, 120]
+            .into_iter()
+            .enumerate()
+            .map(|(i, m)| {
+                RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Int32Array::from(vec![1])),
+                        Arc::new(TimestampMicrosecondArray::from(vec![minutes(m)])),
+                        Arc::new(Int64Array::from(vec![i as i64 + 1])),
+                    ],
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let input = Arc::new(MemoryExec::try_new(&[batches], schema, None)?);
+
+        let window = Arc::new(WindowExec::try_new(
+            input,
+            vec![
+                WindowExpr::new(
+                    WindowFunction::Agg(AggFunction::Sum),
+                    vec![Arc::new(Column::new("v", 2))],
+                    Arc::new(Field::new("v_sum_1h", DataType::Int64, true)),
+                )
+                .with_frame(WindowFrame::Range {
+                    preceding: Some(minutes(60)),
+                }),
+                WindowExpr::new(
+                    WindowFunction::Agg(AggFunction::Sum),
+                    vec![Arc::new(Column::new("v", 2))],
+                    Arc::new(Field::new("v_sum", DataType::Int64, true)),
+                )
+                .with_frame(WindowFrame::Range { preceding: None }),
+            ],
+            vec![Arc::new(Column::new("a1", 0))],
+            vec![PhysicalSortExpr {
+                expr: Arc::new(Column::new("ts", 1)),
+                options: Default::default(),
+            }],
+        )?);
+        let stream = window.execute(0, task_ctx.clone())?;
+        let batches = datafusion::physical_plan::common::collect(stream).await?;
+        let expected = vec![
+            "+----+---------------------+---+----------+-------+",
+            "| a1 | ts                  | v | v_sum_1h | v_sum |",
+            "+----+---------------------+---+----------+-------+",
+            "| 1  | 1970-01-01T00:00:00 | 1 | 1        | 1     |",
+            "| 1  | 1970-01-01T01:00:00 | 2 | 15       | 15    |",
+            "| 1  | 1970-01-01T01:00:00 | 3 | 15       | 15    |",
+            "| 1  | 1970-01-01T01:00:00 | 4 | 15       | 15    |",
+            "| 1  | 1970-01-01T01:00:00 | 5 | 15       | 15    |",
+            "| 1  | 1970-01-01T02:00:00 | 6 | 20       | 21    |",
+            "+----+---------------------+---+----------+-------+",
+        ];
+        assert_batches_eq!(expected, &batches);
+
+        // peer rows are always output in the same batch
+        assert_eq!(
+            batches
+                .iter()
+                .map(|batch| batch.num_rows())
+                .collect::<Vec<_>>(),
+            vec![1, 4, 1]
+        );
+        Ok(())
+    }
 }
@@ -33,6 +33,7 @@ import org.apache.spark.shuffle.ShuffleWriteMetricsReporter
 import org.apache.spark.sql.catalyst.expressions.aggregate.AggregateExpression
 import org.apache.spark.sql.catalyst.expressions.Expression
 import org.apache.spark.sql.catalyst.expressions.aggregate.First
+import org.apache.spark.sql.catalyst.expressions.aggregate.Last
 import org.apache.spark.sql.catalyst.expressions.Like
 import org.apache.spark.sql.catalyst.expressions.Literal
 import org.apache.spark.sql.catalyst.expressions.StringSplit
@@ -59,6 +60,7 @@ import org.apache.spark.sql.execution.FileSourceScanExec
 import org.apache.spark.sql.execution.blaze.plan.NativeBroadcastExchangeBase
 import org.apache.spark.sql.execution.blaze.plan.NativeBroadcastExchangeExec
 import org.apache.spark.sql.execution.exchange.BroadcastExchangeLike
+import org.apache.spark.sql.execution.joins.BroadcastHashJoinExec
 import org.apache.spark.sql.execution.UnaryExecNode
 import org.apache.spark.sql.execution.adaptive.BroadcastQueryStageExec
 import org.apache.spark.sql.types.IntegerType
@@ -90,6 +92,8 @@ import org.apache.spark.sql.execution.blaze.plan.NativeFilterBase
 import org.apache.spark.sql.execution.blaze.plan.NativeFilterExec
 import org.apache.spark.sql.execution.blaze.plan.NativeGenerateBase
 import org.apache.spark.sql.execution.blaze.plan.NativeGenerateExec
+import org.apache.spark.sql.execution.blaze.plan.NativeGenerateIdBase
+import org.apache.spark.sql.execution.blaze.plan.NativeGenerateIdExec
 import org.apache.spark.sql.execution.blaze.plan.NativeGlobalLimitBase
 import org.apache.spark.sql.execution.blaze.plan.NativeGlobalLimitExec
 import org.apache.spark.sql.execution.blaze.plan.NativeLocalLimitBase
@@ -106,6 +110,8 @@ import org.apache.spark.sql.execution.blaze.plan.NativeSortBase
 import org.apache.spark.sql.execution.blaze.plan.NativeSortExec
 import org.apache.spark.sql.execution.blaze.plan.NativeSortMergeJoinBase
 import org.apache.spark.sql.execution.blaze.plan.NativeSortMergeJoinExec
+import org.apache.spark.sql.execution.blaze.plan.NativeShuffledHashJoinBase
+import org.apache.spark.sql.execution.blaze.plan.NativeShuffledHashJoinExec
 import org.apache.spark.sql.execution.blaze.plan.NativeTakeOrderedBase
 import org.apache.spark.sql.execution.blaze.plan.NativeTakeOrderedExec
 import org.apache.spark.sql.execution.blaze.plan.NativeUnionBase
@@ -118,6 +124,7 @@ import org.apache.spark.sql.execution.datasources.WriteTaskStats
 import org.apache.spark.sql.execution.datasources.WriteTaskStatsTracker
 import org.apache.spark.sql.execution.metric.SQLMetric
 import org.apache.spark.sql.hive.execution.InsertIntoHiveTable
+import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.types.DataType
 import org.apache.spark.util.SerializableConfiguration
 import org.blaze.{protobuf => pb}
@@ -157,7 +164,9 @@ class ShimsImpl extends Shims with Logging {
       leftKeys: Seq[Expression],
       rightKeys: Seq[Expression],
       joinType: JoinType,
-      condition: Option[Expression]): NativeBroadcastJoinBase =
+      condition: Option[Expression],
+      broadcastSide: pb.JoinSide,
+      isNullAwareAntiJoin: Boolean): NativeBroadcastJoinBase =
     NativeBroadcastJoinExec(
       left,
       right,
@@ -165,7 +174,9 @@ class ShimsImpl extends Shims with Logging {
       leftKeys,
       rightKeys,
       joinType,
-      condition)
+      condition,
+      broadcastSide,
+      isNullAwareAntiJoin)
 
   override def createNativeBroadcastNestedLoopJoinExec(
       left: SparkPlan,
@@ -183,6 +194,15 @@ class ShimsImpl extends Shims with Logging {
       condition: Option[Expression]): NativeSortMergeJoinBase =
     NativeSortMergeJoinExec(left, right, leftKeys, rightKeys, joinType, condition)
 
+  override def createNativeShuffledHashJoinExec(
+      left: SparkPlan,
+      right: SparkPlan,
+      leftKeys: Seq[Expression],
+      rightKeys: Seq[Expression],
+      joinType: JoinType,
+      buildSide: pb.JoinSide): NativeShuffledHashJoinBase =
+    NativeShuffledHashJoinExec(left, right, leftKeys, rightKeys, joinType, buildSide)
+
   override def createNativeExpandExec(
       projections: Seq[Seq[Expression]],
       output: Seq[Attribute],
@@ -200,6 +220,11 @@ class ShimsImpl extends Shims with Logging {
       child: SparkPlan): NativeGenerateBase =
     NativeGenerateExec(generator, requiredChildOutput, outer, generatorOutput, child)
 
+  override def createNativeGenerateIdExec(
+      child: SparkPlan,
+      idColumnName: String): NativeGenerateIdBase =
+    NativeGenerateIdExec(child, idColumnName)
+
   override def createNativeGlobalLimitExec(limit: Long, child: SparkPlan): NativeGlobalLimitBase =
     NativeGlobalLimitExec(limit, child)
 
@@ -268,6 +293,9 @@ class ShimsImpl extends Shims with Logging {
     }
   }
 
+  override def isNullAwareAntiJoin(exec: BroadcastHashJoinExec): Boolean =
+    false
+
   override def isNative(plan: SparkPlan): Boolean =
     plan match {
       case _: NativeSupports => true
@@ -410,6 +438,17 @@ class ShimsImpl extends Shims with Logging {
     expr.asInstanceOf[Like].escapeChar
   }
 
+  override def isArithmeticFailOnError(expr: Expression): Boolean = {
+    // arithmetic expressions check overflows with the session conf in spark3.0
+    SQLConf.get.ansiEnabled
+  }
+
+  override def isCastAnsiEnabled(expr: Expression): Boolean = {
+    // casts are created with the session conf in spark3.0, ansi casts are
+    // represented by AnsiCast which is not converted natively
+    SQLConf.get.ansiEnabled
+  }
+
   override def convertAggregateExpr(e: AggregateExpression): Option[pb.PhysicalExprNode] = {
     assert(getAggregateExpressionFilter(e).isEmpty)
     val aggBuilder = pb.PhysicalAggExprNode.newBuilder()
@@ -424,6 +463,15 @@ class ShimsImpl extends Shims with Logging {
         aggBuilder.addChildren(NativeConverters.convertExpr(child))
         Some(pb.PhysicalExprNode.newBuilder().setAggExpr(aggBuilder).build())
 
+      case Last(child, ignoresNull) =>
+        aggBuilder.setAggFunction(if (ignoresNull) {
+          pb.AggFunction.LAST_IGNORES_NULL
+        } else {
+          pb.AggFunction.LAST
+        })
+        aggBuilder.addChildren(NativeConverters.convertExpr(child))
+        Some(pb.PhysicalExprNode.newBuilder().setAggExpr(aggBuilder).build())
+
       case _ => None
     }
   }
@@ -21,7 +21,9 @@ import org.apache.spark.sql.catalyst.plans.physical.Partitioning
 import org.apache.spark.sql.execution.SparkPlan
 import org.apache.spark.sql.execution.joins
 import org.apache.spark.sql.execution.joins.BuildLeft
+import org.apache.spark.sql.execution.joins.BuildRight
 import org.apache.spark.sql.execution.joins.HashJoin
+import org.blaze.{protobuf => pb}
 
 case class NativeBroadcastJoinExec(
     override val left: SparkPlan,
@@ -30,7 +32,9 @@ case class NativeBroadcastJoinExec(
     override val leftKeys: Seq[Expression],
     override val rightKeys: Seq[Expression],
     override val joinType: JoinType,
-    override val condition: Option[Expression])
+    override val condition: Option[Expression],
+    broadcastSide: pb.JoinSide,
+    isNullAwareAntiJoin: Boolean)
     extends NativeBroadcastJoinBase(
       left,
       right,
@@ -38,10 +42,15 @@ case class NativeBroadcastJoinExec(
       leftKeys,
       rightKeys,
       joinType,
-      condition)
+      condition,
+      broadcastSide,
+      isNullAwareAntiJoin)
     with HashJoin {
 
-  override val buildSide: joins.BuildSide = BuildLeft
+  override val buildSide: joins.BuildSide = broadcastSide match {
+    case pb.JoinSide.RIGHT_SIDE => BuildRight
+    case _ => BuildLeft
+  }
 
   override def withNewChildren(newChildren: Seq[SparkPlan]): SparkPlan =
     copy(left = newChildren(0), right = newChildren(1))
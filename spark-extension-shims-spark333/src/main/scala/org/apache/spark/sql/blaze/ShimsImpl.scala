@@ -32,8 +32,16 @@ import org.apache.spark.shuffle.ShuffleHandle
 import org.apache.spark.shuffle.ShuffleWriteMetricsReporter
 import org.apache.spark.sql.catalyst.expressions.aggregate.AggregateExpression
 import org.apache.spark.sql.catalyst.expressions.Expression
+import org.apache.spark.sql.catalyst.expressions.aggregate.BloomFilterAggregate
 import org.apache.spark.sql.catalyst.expressions.aggregate.First
+import org.apache.spark.sql.catalyst.expressions.aggregate.Last
+import org.apache.spark.sql.catalyst.expressions.BloomFilterMightContain
+import org.apache.spark.sql.catalyst.expressions.Cast
+import org.apache.spark.sql.catalyst.expressions.Add
+import org.apache.spark.sql.catalyst.expressions.Divide
 import org.apache.spark.sql.catalyst.expressions.Like
+import org.apache.spark.sql.catalyst.expressions.Multiply
+import org.apache.spark.sql.catalyst.expressions.Subtract
 import org.apache.spark.sql.catalyst.expressions.Literal
 import org.apache.spark.sql.catalyst.expressions.StringSplit
 import org.apache.spark.sql.catalyst.plans.physical.BroadcastMode
@@ -58,6 +66,7 @@ import org.apache.spark.sql.execution.FileSourceScanExec
 import org.apache.spark.sql.execution.blaze.plan.NativeBroadcastExchangeBase
 import org.apache.spark.sql.execution.blaze.plan.NativeBroadcastExchangeExec
 import org.apache.spark.sql.execution.exchange.BroadcastExchangeLike
+import org.apache.spark.sql.execution.joins.BroadcastHashJoinExec
 import org.apache.spark.sql.execution.UnaryExecNode
 import org.apache.spark.sql.execution.adaptive.BroadcastQueryStageExec
 import org.apache.spark.sql.types.IntegerType
@@ -85,6 +94,8 @@ import org.apache.spark.sql.execution.blaze.plan.NativeFilterBase
 import org.apache.spark.sql.execution.blaze.plan.NativeFilterExec
 import org.apache.spark.sql.execution.blaze.plan.NativeGenerateBase
 import org.apache.spark.sql.execution.blaze.plan.NativeGenerateExec
+import org.apache.spark.sql.execution.blaze.plan.NativeGenerateIdBase
+import org.apache.spark.sql.execution.blaze.plan.NativeGenerateIdExec
 import org.apache.spark.sql.execution.blaze.plan.NativeGlobalLimitBase
 import org.apache.spark.sql.execution.blaze.plan.NativeGlobalLimitExec
 import org.apache.spark.sql.execution.blaze.plan.NativeLocalLimitBase
@@ -112,8 +123,10 @@ import org.apache.spark.sql.execution.datasources.WriteTaskStats
 import org.apache.spark.sql.execution.datasources.WriteTaskStatsTracker
 import org.apache.spark.sql.execution.joins.blaze.plan.NativeBroadcastJoinExec
 import org.apache.spark.sql.execution.joins.blaze.plan.NativeBroadcastNestedLoopJoinExec
+import org.apache.spark.sql.execution.joins.blaze.plan.NativeShuffledHashJoinExec
 import org.apache.spark.sql.execution.joins.blaze.plan.NativeSortMergeJoinExec
 import org.apache.spark.sql.hive.execution.InsertIntoHiveTable
+import org.apache.spark.sql.internal.SQLConf
 import org.apache.spark.sql.types.DataType
 import org.apache.spark.util.SerializableConfiguration
 import org.blaze.{protobuf => pb}
@@ -156,7 +169,9 @@ class ShimsImpl extends Shims with Logging {
       leftKeys: Seq[Expression],
       rightKeys: Seq[Expression],
       joinType: JoinType,
-      condition: Option[Expression]): NativeBroadcastJoinBase =
+      condition: Option[Expression],
+      broadcastSide: pb.JoinSide,
+      isNullAwareAntiJoin: Boolean): NativeBroadcastJoinBase =
     NativeBroadcastJoinExec(
       left,
       right,
@@ -164,7 +179,9 @@ class ShimsImpl extends Shims with Logging {
       leftKeys,
       rightKeys,
       joinType,
-      condition)
+      condition,
+      broadcastSide,
+      isNullAwareAntiJoin)
 
   override def createNativeBroadcastNestedLoopJoinExec(
       left: SparkPlan,
@@ -182,6 +199,15 @@ class ShimsImpl extends Shims with Logging {
       condition: Option[Expression]): NativeSortMergeJoinBase =
     NativeSortMergeJoinExec(left, right, leftKeys, rightKeys, joinType, condition)
 
+  override def createNativeShuffledHashJoinExec(
+      left: SparkPlan,
+      right: SparkPlan,
+      leftKeys: Seq[Expression],
+      rightKeys: Seq[Expression],
+      joinType: JoinType,
+      buildSide: pb.JoinSide): NativeShuffledHashJoinBase =
+    NativeShuffledHashJoinExec(left, right, leftKeys, rightKeys, joinType, buildSide)
+
   override def createNativeExpandExec(
       projections: Seq[Seq[Expression]],
       output: Seq[Attribute],
@@ -199,6 +225,11 @@ class ShimsImpl extends Shims with Logging {
       child: SparkPlan): NativeGenerateBase =
     NativeGenerateExec(generator, requiredChildOutput, outer, generatorOutput, child)
 
+  override def createNativeGenerateIdExec(
+      child: SparkPlan,
+      idColumnName: String): NativeGenerateIdBase =
+    NativeGenerateIdExec(child, idColumnName)
+
   override def createNativeGlobalLimitExec(limit: Long, child: SparkPlan): NativeGlobalLimitBase =
     NativeGlobalLimitExec(limit, child)
 
@@ -268,6 +299,9 @@ class ShimsImpl extends Shims with Logging {
     }
   }
 
+  override def isNullAwareAntiJoin(exec: BroadcastHashJoinExec): Boolean =
+    exec.isNullAwareAntiJoin
+
   override def isNative(plan: SparkPlan): Boolean =
     plan match {
       case _: NativeSupports => true
@@ -404,6 +438,17 @@ class ShimsImpl extends Shims with Logging {
                 .setReturnType(NativeConverters.convertDataType(StringType)))
             .build())
 
+      case e: BloomFilterMightContain =>
+        Some(
+          pb.PhysicalExprNode
+            .newBuilder()
+            .setBloomFilterMightContainExpr(
+              pb.BloomFilterMightContainExprNode
+                .newBuilder()
+                .setBloomFilterExpr(NativeConverters.convertExpr(e.bloomFilterExpression))
+                .setValueExpr(NativeConverters.convertExpr(e.valueExpression)))
+            .build())
+
       case _ => None
     }
   }
@@ -412,6 +457,20 @@ class ShimsImpl extends Shims with Logging {
     expr.asInstanceOf[Like].escapeChar
   }
 
+  override def isArithmeticFailOnError(expr: Expression): Boolean = {
+    expr match {
+      case e: Add => e.failOnError
+      case e: Subtract => e.failOnError
+      case e: Multiply => e.failOnError
+      case e: Divide => e.failOnError
+      case _ => SQLConf.get.ansiEnabled
+    }
+  }
+
+  override def isCastAnsiEnabled(expr: Expression): Boolean = {
+    expr.asInstanceOf[Cast].ansiEnabled
+  }
+
   override def convertAggregateExpr(e: AggregateExpression): Option[pb.PhysicalExprNode] = {
     assert(getAggregateExpressionFilter(e).isEmpty)
     val aggBuilder = pb.PhysicalAggExprNode.newBuilder()
@@ -426,6 +485,29 @@ class ShimsImpl extends Shims with Logging {
         aggBuilder.addChildren(NativeConverters.convertExpr(child))
         Some(pb.PhysicalExprNode.newBuilder().setAggExpr(aggBuilder).build())
 
+      case Last(child, ignoresNull) =>
+        aggBuilder.setAggFunction(if (ignoresNull) {
+          pb.AggFunction.LAST_IGNORES_NULL
+        } else {
+          pb.AggFunction.LAST
+        })
+        aggBuilder.addChildren(NativeConverters.convertExpr(child))
+        Some(pb.PhysicalExprNode.newBuilder().setAggExpr(aggBuilder).build())
+
+      case agg: BloomFilterAggregate =>
+        // clamp the same way as spark does
+        val estimatedNumItems = math.min(
+          agg.estimatedNumItemsExpression.eval().asInstanceOf[Number].longValue,
+          SQLConf.get.getConf(SQLConf.RUNTIME_BLOOM_FILTER_MAX_NUM_ITEMS))
+        val numBits = math.min(
+          agg.numBitsExpression.eval().asInstanceOf[Number].longValue,
+          SQLConf.get.getConf(SQLConf.RUNTIME_BLOOM_FILTER_MAX_NUM_BITS))
+        aggBuilder.setAggFunction(pb.AggFunction.BLOOM_FILTER)
+        aggBuilder.addChildren(NativeConverters.convertExpr(agg.child))
+        aggBuilder.addChildren(NativeConverters.convertExpr(Literal(estimatedNumItems)))
+        aggBuilder.addChildren(NativeConverters.convertExpr(Literal(numBits)))
+        Some(pb.PhysicalExprNode.newBuilder().setAggExpr(aggBuilder).build())
+
       case _ => None
     }
   }
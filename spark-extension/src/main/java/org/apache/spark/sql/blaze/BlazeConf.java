@@ -24,6 +24,12 @@ public class BlazeConf {
         return intConf("spark.blaze.batchSize", 10000);
     }
 
+    /// suggested memory size of arrow batches. batches of wide rows are split to fit this size
+    /// in native shuffle reading/writing and joins.
+    public static int batchMemBytes() {
+        return intConf("spark.blaze.batch.mem.bytes", 67108864);
+    }
+
     /// suggested fraction of off-heap memory used in native execution.
     /// actual off-heap memory usage is expected to be spark.executor.memoryOverhead * fraction.
     public static double memoryFraction() {
@@ -53,6 +59,13 @@ public class BlazeConf {
         return intConf("spark.blaze.bhjFallbacksToSmj.mem.bytes", 134217728);
     }
 
+    /// builds the hash map of the broadcasted table once per executor and shares it among tasks
+    /// when executing BroadcastHashJoin. takes precedence over spark.blaze.enable.bhjFallbacksToSmj
+    /// for supported join types.
+    public static boolean enableBhjBuildSideReuse() {
+        return booleanConf("spark.blaze.enable.bhjBuildSideReuse", false);
+    }
+
     /// enable converting upper/lower functions to native, special cases may provide different
     /// outputs from spark due to different unicode versions.
     public static boolean enableCaseConvertFunctions() {
@@ -67,6 +80,119 @@ public class BlazeConf {
         return booleanConf("spark.blaze.enableInputBatchStatistics", false);
     }
 
+    /// counts batches flowing through each native operator and logs them when the task
+    /// finishes. for debugging only.
+    public static boolean enableBatchAccounting() {
+        return booleanConf("spark.blaze.debug.enableBatchAccounting", false);
+    }
+
+    /// zstd level for compressing native shuffle and broadcast data. higher levels trade more
+    /// cpu for better compression ratio.
+    public static int shuffleCompressionLevel() {
+        return intConf("spark.blaze.shuffle.compression.level", 1);
+    }
+
+    /// codec for compressing native shuffle and broadcast data, one of zstd, lz4, snappy and
+    /// uncompressed. the compression level only applies to zstd.
+    public static String shuffleCompressionCodec() {
+        return stringConf("spark.blaze.shuffle.compression.codec", "zstd");
+    }
+
+    /// number of shuffle file segments prefetched and decoded in background while reading the
+    /// current segment in native shuffle readers. 0 disables prefetching.
+    public static int shuffleReadPrefetchSegments() {
+        return intConf("spark.blaze.shuffle.read.prefetch.segments", 0);
+    }
+
+    /// stops prefetching more shuffle file segments when the pending segments exceed this size.
+    /// requires spark.blaze.shuffle.read.prefetch.segments > 0.
+    public static int shuffleReadPrefetchMemThreshold() {
+        return intConf("spark.blaze.shuffle.read.prefetch.mem.bytes", 67108864);
+    }
+
+    /// reads shuffle file segments from memory-mapped data files, falls back to normal reading
+    /// if mapping fails.
+    public static boolean shuffleReadMmapEnabled() {
+        return booleanConf("spark.blaze.shuffle.read.mmap.enabled", false);
+    }
+
+    /// flushes buffered data of a reduce partition to disk once it exceeds this size while
+    /// writing shuffle, keeping memory of shuffle writers bounded. 0 disables flushing.
+    public static int shufflePartitionFlushBytes() {
+        return intConf("spark.blaze.shuffle.partition.flush.bytes", 1048576);
+    }
+
+    /// small batches of a reduce partition in shuffle spills are decoded and concatenated into
+    /// batches of this size before being written to the shuffle output, improving compression
+    /// ratio of shuffles with many partitions. 0 disables compaction.
+    public static int shuffleCompactionTargetBytes() {
+        return intConf("spark.blaze.shuffle.compaction.target.bytes", 1048576);
+    }
+
+    /// merges partial hash aggregations into the following shuffle writers, rows are aggregated
+    /// in a bounded hash map of each reduce partition before being written.
+    public static boolean shuffleAggregateEnabled() {
+        return booleanConf("spark.blaze.shuffle.aggregate.enabled", false);
+    }
+
+    /// memory budget of hash maps of all reduce partitions in shuffle aggregation. a partition
+    /// whose map exceeds its share falls back to writing rows without merging.
+    public static int shuffleAggregateMemBytes() {
+        return intConf("spark.blaze.shuffle.aggregate.mem.bytes", 16777216);
+    }
+
+    /// size of reusable buffers for transferring bytes between native and jvm through channels.
+    public static int jniBufferSize() {
+        return intConf("spark.blaze.jni.buffer.size", 65536);
+    }
+
+    /// kind of reusable buffers for transferring bytes between native and jvm, one of direct
+    /// (direct byte buffers over native memory) and heap (byte arrays copied by jni).
+    public static String jniTransport() {
+        return stringConf("spark.blaze.jni.transport", "direct");
+    }
+
+    /// codec for compressing data spilled by native operators, one of lz4 and zstd. spills of
+    /// shuffle writers are compressed with the shuffle codec instead.
+    public static String spillCompressionCodec() {
+        return stringConf("spark.blaze.spill.compression.codec", "lz4");
+    }
+
+    /// spill native operators to the jvm-managed on-heap spill manager instead of files in
+    /// spark local dirs.
+    public static boolean spillOnHeapEnabled() {
+        return booleanConf("spark.blaze.spill.onHeap.enabled", true);
+    }
+
+    /// max limit of ordered limits executed with an in-memory top-k heap, larger limits are
+    /// executed with the spillable native sort.
+    public static int topKMaxLimit() {
+        return intConf("spark.blaze.topK.maxLimit", 100000);
+    }
+
+    /// comma-separated paths of native udf plugin libraries, which are loaded by native engine
+    /// on first use of NativeUDF expressions.
+    public static String nativeUdfLibraries() {
+        return stringConf("spark.blaze.nativeUdf.libraries", "");
+    }
+
+    /// traces execution of native operators and writes a chrome trace file per task, which can
+    /// be viewed in chrome://tracing or converted to flamegraphs. for debugging only.
+    public static boolean traceEnabled() {
+        return booleanConf("spark.blaze.trace.enabled", false);
+    }
+
+    /// directory of native trace files, defaults to the system temp directory.
+    public static String traceDir() {
+        return stringConf("spark.blaze.trace.dir", "");
+    }
+
+    /// renders the executed native plan with metrics after each task, which is logged and
+    /// collected into the "Native.plan" accumulator of the stage.
+    public static boolean explainNativePlanEnabled() {
+        return booleanConf("spark.blaze.explainNativePlan.enabled", false);
+    }
+
     public static boolean ignoreCorruptedFiles() {
         return booleanConf("spark.files.ignoreCorruptFiles", false);
     }
@@ -83,6 +209,10 @@ public class BlazeConf {
         return conf().getBoolean(key, defaultValue);
     }
 
+    private static String stringConf(String key, String defaultValue) {
+        return conf().get(key, defaultValue);
+    }
+
     private static SparkConf conf() {
         return SparkEnv$.MODULE$.get().conf();
     }
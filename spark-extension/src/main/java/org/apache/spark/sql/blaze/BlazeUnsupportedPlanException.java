@@ -0,0 +1,69 @@
+/*
+ * Copyright 2022 The Blaze Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.apache.spark.sql.blaze;
+
+/**
+ * Thrown by the native side when a plan or one of its expressions is not supported by the native
+ * engine.
+ */
+public class BlazeUnsupportedPlanException extends RuntimeException {
+
+    /** names are kept in sync with UnsupportedReason in blaze-serde */
+    public enum Reason {
+        OPERATOR,
+        EXPRESSION,
+        DATA_TYPE,
+        FUNCTION,
+
+        // jvm side only, converting the exec to native failed
+        CONVERSION_FAILED,
+    }
+
+    private final String operator;
+    private final String expression;
+    private final Reason reason;
+
+    public BlazeUnsupportedPlanException(String operator, String expression, Reason reason) {
+        super(formatMessage(operator, expression, reason));
+        this.operator = operator;
+        this.expression = expression;
+        this.reason = reason;
+    }
+
+    // called by native side
+    public BlazeUnsupportedPlanException(String operator, String expression, String reason) {
+        this(operator, expression, Reason.valueOf(reason));
+    }
+
+    public String getOperator() {
+        return operator;
+    }
+
+    /** the unsupported expression, or null if the operator itself is unsupported */
+    public String getExpression() {
+        return expression;
+    }
+
+    public Reason getReason() {
+        return reason;
+    }
+
+    private static String formatMessage(String operator, String expression, Reason reason) {
+        return "unsupported plan: operator=" + operator
+                + (expression != null ? ", expression=" + expression : "")
+                + ", reason=" + reason;
+    }
+}
@@ -15,9 +15,15 @@
  */
 package org.apache.spark.sql.blaze;
 
+import java.io.File;
+import java.util.Arrays;
 import java.util.concurrent.ConcurrentHashMap;
+import java.util.stream.Collectors;
+import org.apache.spark.SparkEnv$;
 import org.apache.spark.TaskContext;
 import org.apache.spark.TaskContext$;
+import org.apache.spark.sql.blaze.memory.NativeMemoryConsumer;
+import org.apache.spark.sql.blaze.memory.NativeMemoryConsumer$;
 import org.apache.spark.sql.blaze.memory.OnHeapSpillManager;
 import org.apache.spark.sql.blaze.memory.OnHeapSpillManager$;
 
@@ -29,8 +35,12 @@ public class JniBridge {
 
     public static native long callNative(BlazeCallNativeWrapper wrapper);
 
+    public static native String explainNative(long ptr);
+
     public static native void finalizeNative(long ptr);
 
+    public static native long spillNative(NativeMemoryConsumer consumer, long size);
+
     public static ClassLoader getContextClassLoader() {
         return Thread.currentThread().getContextClassLoader();
     }
@@ -55,6 +65,15 @@ public class JniBridge {
         return OnHeapSpillManager$.MODULE$.current();
     }
 
+    public static NativeMemoryConsumer getTaskNativeMemoryConsumer() {
+        return NativeMemoryConsumer$.MODULE$.current();
+    }
+
+    public static String getSparkLocalDirs() {
+        File[] localDirs = SparkEnv$.MODULE$.get().blockManager().diskBlockManager().localDirs();
+        return Arrays.stream(localDirs).map(File::getPath).collect(Collectors.joining(","));
+    }
+
     public static boolean isTaskRunning() {
         TaskContext tc = getTaskContext();
         if (tc == null) { // driver is always running
@@ -31,6 +31,7 @@ import org.blaze.protobuf.TaskDefinition
 import org.apache.spark.internal.Logging
 import org.apache.spark.sql.catalyst.InternalRow
 import org.apache.spark.sql.execution.blaze.arrowio.ArrowFFIStreamImportIterator
+import org.apache.spark.util.CollectionAccumulator
 import org.apache.spark.util.CompletionIterator
 import org.apache.spark.util.Utils
 
@@ -38,7 +39,8 @@ case class BlazeCallNativeWrapper(
     nativePlan: PhysicalPlanNode,
     partition: Partition,
     context: Option[TaskContext],
-    metrics: MetricNode)
+    metrics: MetricNode,
+    nativePlanDescriptions: Option[CollectionAccumulator[String]] = None)
     extends Logging {
 
   BlazeCallNativeWrapper.initNative()
@@ -109,6 +111,11 @@ case class BlazeCallNativeWrapper(
         rowIterator = null
       }
       if (nativeRuntimePtr != 0) {
+        if (BlazeConf.explainNativePlanEnabled()) {
+          val description = JniBridge.explainNative(nativeRuntimePtr)
+          logInfo(s"Executed native plan (partition=${partition.index}):\n$description")
+          nativePlanDescriptions.foreach(_.add(s"partition ${partition.index}:\n$description"))
+        }
         JniBridge.finalizeNative(nativeRuntimePtr)
         nativeRuntimePtr = 0
       }
@@ -19,6 +19,7 @@ import org.apache.spark.internal.Logging
 import org.apache.spark.sql.catalyst.trees.TreeNodeTag
 import org.apache.spark.sql.execution.ProjectExec
 import org.apache.spark.sql.execution.SparkPlan
+import org.apache.spark.sql.execution.joins.ShuffledHashJoinExec
 import org.apache.spark.sql.execution.joins.SortMergeJoinExec
 import org.apache.spark.sql.execution.FileSourceScanExec
 import org.apache.spark.sql.execution.FilterExec
@@ -46,6 +47,7 @@ object BlazeConvertStrategy extends Logging {
 
   val convertibleTag: TreeNodeTag[Boolean] = TreeNodeTag("blaze.convertible")
   val convertStrategyTag: TreeNodeTag[ConvertStrategy] = TreeNodeTag("blaze.convert.strategy")
+  val fallbackReasonTag: TreeNodeTag[FallbackReason] = TreeNodeTag("blaze.fallback.reason")
 
   def apply(exec: SparkPlan): Unit = {
     exec.foreach(_.setTagValue(convertibleTag, true))
@@ -64,6 +66,7 @@ object BlazeConvertStrategy extends Logging {
         case _ =>
           exec.setTagValue(convertibleTag, false)
           exec.setTagValue(convertStrategyTag, NeverConvert)
+          converted.getTagValue(fallbackReasonTag).foreach(exec.setTagValue(fallbackReasonTag, _))
       }
       danglingChildren = newDangling :+ converted
     }
@@ -98,6 +101,8 @@ object BlazeConvertStrategy extends Logging {
         e.setTagValue(convertStrategyTag, AlwaysConvert)
       case e: SortMergeJoinExec if e.children.exists(isAlwaysConvert) =>
         e.setTagValue(convertStrategyTag, AlwaysConvert)
+      case e: ShuffledHashJoinExec if e.children.exists(isAlwaysConvert) =>
+        e.setTagValue(convertStrategyTag, AlwaysConvert)
       case e: BroadcastHashJoinExec if e.children.forall(isAlwaysConvert) =>
         e.setTagValue(convertStrategyTag, AlwaysConvert)
       case e: BroadcastNestedLoopJoinExec if e.children.forall(isAlwaysConvert) =>
@@ -213,3 +218,17 @@ sealed trait ConvertStrategy {}
 case object Default extends ConvertStrategy
 case object AlwaysConvert extends ConvertStrategy
 case object NeverConvert extends ConvertStrategy
+
+/**
+ * Reason why an exec falls back to spark.
+ *
+ * @param operator node name of the exec
+ * @param expression the unsupported expression, if known
+ * @param reason reason of the fallback
+ * @param message detailed message
+ */
+case class FallbackReason(
+    operator: String,
+    expression: Option[String],
+    reason: BlazeUnsupportedPlanException.Reason,
+    message: String)
@@ -27,6 +27,7 @@ import org.apache.spark.broadcast.Broadcast
 import org.apache.spark.internal.Logging
 import org.apache.spark.sql.blaze.BlazeConvertStrategy.convertibleTag
 import org.apache.spark.sql.blaze.BlazeConvertStrategy.convertStrategyTag
+import org.apache.spark.sql.blaze.BlazeConvertStrategy.fallbackReasonTag
 import org.apache.spark.sql.blaze.BlazeConvertStrategy.isNeverConvert
 import org.apache.spark.sql.catalyst.expressions.Alias
 import org.apache.spark.sql.catalyst.expressions.Attribute
@@ -52,6 +53,8 @@ import org.apache.spark.sql.execution.TakeOrderedAndProjectExec
 import org.apache.spark.sql.execution.UnionExec
 import org.apache.spark.sql.execution.aggregate.HashAggregateExec
 import org.apache.spark.sql.execution.aggregate.ObjectHashAggregateExec
+import org.apache.spark.sql.execution.adaptive.AdaptiveSparkPlanExec
+import org.apache.spark.sql.execution.adaptive.QueryStageExec
 import org.apache.spark.sql.execution.blaze.plan._
 import org.apache.spark.sql.execution.blaze.plan.NativeAggBase
 import org.apache.spark.sql.execution.blaze.plan.NativeProjectBase
@@ -71,6 +74,7 @@ import org.apache.spark.sql.execution.LocalTableScanExec
 import org.apache.spark.sql.execution.UnaryExecNode
 import org.apache.spark.sql.execution.blaze.plan.NativeParquetScanBase
 import org.apache.spark.sql.hive.execution.InsertIntoHiveTable
+import org.blaze.{protobuf => pb}
 
 object BlazeConverters extends Logging {
   val enableScan: Boolean =
@@ -85,6 +89,8 @@ object BlazeConverters extends Logging {
     SparkEnv.get.conf.getBoolean("spark.blaze.enable.union", defaultValue = true)
   val enableSmj: Boolean =
     SparkEnv.get.conf.getBoolean("spark.blaze.enable.smj", defaultValue = true)
+  val enableShj: Boolean =
+    SparkEnv.get.conf.getBoolean("spark.blaze.enable.shj", defaultValue = true)
   val enableBhj: Boolean =
     SparkEnv.get.conf.getBoolean("spark.blaze.enable.bhj", defaultValue = true)
   val enableBnlj: Boolean =
@@ -146,6 +152,8 @@ object BlazeConverters extends Logging {
         tryConvert(e, convertUnionExec)
       case e: SortMergeJoinExec if enableSmj => // sort merge join
         tryConvert(e, convertSortMergeJoinExec)
+      case e: ShuffledHashJoinExec if enableShj => // shuffled hash join
+        tryConvert(e, convertShuffledHashJoinExec)
       case e: BroadcastHashJoinExec if enableBhj => // broadcast hash join
         tryConvert(e, convertBroadcastHashJoinExec)
       case e: BroadcastNestedLoopJoinExec if enableBnlj => // broadcast nested loop join
@@ -215,6 +223,13 @@ object BlazeConverters extends Logging {
             } else {
               exec.setTagValue(convertibleTag, false)
               exec.setTagValue(convertStrategyTag, NeverConvert)
+              exec.setTagValue(
+                fallbackReasonTag,
+                FallbackReason(
+                  exec.nodeName,
+                  None,
+                  BlazeUnsupportedPlanException.Reason.OPERATOR,
+                  "operator is not supported or disabled"))
             }
             exec
         }
@@ -231,10 +246,33 @@ object BlazeConverters extends Logging {
         logWarning(s"Error converting exec: ${exec.getClass.getSimpleName}: ${e.getMessage}", e)
         exec.setTagValue(convertibleTag, false)
         exec.setTagValue(convertStrategyTag, NeverConvert)
+        exec.setTagValue(
+          fallbackReasonTag,
+          FallbackReason(
+            exec.nodeName,
+            None,
+            BlazeUnsupportedPlanException.Reason.CONVERSION_FAILED,
+            s"${e.getClass.getSimpleName}: ${e.getMessage}"))
         exec
     }
   }
 
+  /**
+   * Collects reasons of all execs falling back to spark in an executed plan, including those in
+   * adaptive query stages. typically used as:
+   * {{{
+   *   BlazeConverters.collectFallbackReasons(df.queryExecution.executedPlan)
+   * }}}
+   */
+  def collectFallbackReasons(plan: SparkPlan): Seq[FallbackReason] = {
+    plan match {
+      case p: AdaptiveSparkPlanExec => collectFallbackReasons(p.executedPlan)
+      case p: QueryStageExec => collectFallbackReasons(p.plan)
+      case p =>
+        p.getTagValue(fallbackReasonTag).toSeq ++ p.children.flatMap(collectFallbackReasons)
+    }
+  }
+
   def convertShuffleExchangeExec(exec: ShuffleExchangeExec): SparkPlan = {
     val (outputPartitioning, child) = (exec.outputPartitioning, exec.child)
     logDebug(s"Converting ShuffleExchangeExec: ${Shims.get.simpleStringWithNodeId(exec)}")
@@ -269,9 +307,12 @@ object BlazeConverters extends Logging {
       exec.optionalBucketSet,
       exec.dataFilters,
       exec.tableIdentifier)
+    // only parquet is scanned natively, other formats like orc fall back to
+    // the spark scan since there is no native reader for them. the native csv
+    // reader is not used either since it lacks options like dateFormat
     assert(
       relation.fileFormat.isInstanceOf[ParquetFileFormat],
-      "Cannot convert non-parquet scan exec")
+      s"Cannot convert non-parquet scan exec: ${relation.fileFormat} is not supported natively")
     logDebug(s"Converting FileSourceScanExec: ${Shims.get.simpleStringWithNodeId(exec)}")
     logDebug(s"  relation: ${relation}")
     logDebug(s"  relation.location: ${relation.location}")
@@ -367,6 +408,56 @@ object BlazeConverters extends Logging {
     }
   }
 
+  def convertShuffledHashJoinExec(exec: ShuffledHashJoinExec): SparkPlan = {
+    val (leftKeys, rightKeys, joinType, buildSide, condition, left, right) = (
+      exec.leftKeys,
+      exec.rightKeys,
+      exec.joinType,
+      exec.buildSide,
+      exec.condition,
+      exec.left,
+      exec.right)
+    logDebug(s"Converting ShuffledHashJoinExec: ${Shims.get.simpleStringWithNodeId(exec)}")
+    assert(condition.isEmpty, "ShuffledHashJoin with condition is not supported yet")
+
+    var nativeLeft = convertToNative(left)
+    var nativeRight = convertToNative(right)
+    var modifiedLeftKeys = leftKeys
+    var modifiedRightKeys = rightKeys
+    var needPostProject = false
+
+    if (leftKeys.exists(!_.isInstanceOf[AttributeReference])) {
+      val (keys, exec) = buildJoinColumnsProject(nativeLeft, leftKeys)
+      modifiedLeftKeys = keys
+      nativeLeft = exec
+      needPostProject = true
+    }
+    if (rightKeys.exists(!_.isInstanceOf[AttributeReference])) {
+      val (keys, exec) = buildJoinColumnsProject(nativeRight, rightKeys)
+      modifiedRightKeys = keys
+      nativeRight = exec
+      needPostProject = true
+    }
+
+    val nativeBuildSide = buildSide match {
+      case BuildLeft => pb.JoinSide.LEFT_SIDE
+      case BuildRight => pb.JoinSide.RIGHT_SIDE
+    }
+    val shj = Shims.get.createNativeShuffledHashJoinExec(
+      addRenameColumnsExec(nativeLeft),
+      addRenameColumnsExec(nativeRight),
+      modifiedLeftKeys,
+      modifiedRightKeys,
+      joinType,
+      nativeBuildSide)
+
+    if (needPostProject) {
+      buildPostJoinProject(shj, exec.output)
+    } else {
+      shj
+    }
+  }
+
   def convertBroadcastHashJoinExec(exec: BroadcastHashJoinExec): SparkPlan = {
     try {
       val (leftKeys, rightKeys, joinType, buildSide, condition, left, right) = (
@@ -417,39 +508,68 @@ object BlazeConverters extends Logging {
         needPostProject = true
       }
 
-      val modifiedJoinType = buildSide match {
-        case BuildLeft => joinType
-        case BuildRight =>
-          needPostProject = true
-          val modifiedJoinType = joinType match { // reverse join type
-            case Inner => Inner
-            case FullOuter => FullOuter
-            case LeftOuter => RightOuter
-            case RightOuter => LeftOuter
-            case _ =>
-              throw new NotImplementedError(
-                "BHJ Semi/Anti join with BuildRight is not yet supported")
+      val isNullAwareAntiJoin = Shims.get.isNullAwareAntiJoin(exec)
+      val bhj = (buildSide, joinType) match {
+        // semi/anti/existence joins output the probed side only, so the
+        // children are kept in place and the right side is broadcasted
+        case (BuildRight, LeftSemi | LeftAnti | _: ExistenceJoin) =>
+          val bhjOrig = BroadcastHashJoinExec(
+            modifiedProbedKeys,
+            modifiedHashedKeys,
+            joinType,
+            BuildRight,
+            condition,
+            addRenameColumnsExec(nativeProbed),
+            addRenameColumnsExec(hashed))
+
+          Shims.get.createNativeBroadcastJoinExec(
+            bhjOrig.left,
+            bhjOrig.right,
+            bhjOrig.outputPartitioning,
+            bhjOrig.leftKeys,
+            bhjOrig.rightKeys,
+            bhjOrig.joinType,
+            bhjOrig.condition,
+            pb.JoinSide.RIGHT_SIDE,
+            isNullAwareAntiJoin)
+
+        case _ =>
+          val modifiedJoinType = buildSide match {
+            case BuildLeft => joinType
+            case BuildRight =>
+              needPostProject = true
+              val modifiedJoinType = joinType match { // reverse join type
+                case Inner => Inner
+                case FullOuter => FullOuter
+                case LeftOuter => RightOuter
+                case RightOuter => LeftOuter
+                case _ =>
+                  throw new NotImplementedError(
+                    s"BHJ $joinType join with BuildRight is not yet supported")
+              }
+              modifiedJoinType
           }
-          modifiedJoinType
-      }
 
-      val bhjOrig = BroadcastHashJoinExec(
-        modifiedHashedKeys,
-        modifiedProbedKeys,
-        modifiedJoinType,
-        BuildLeft,
-        condition,
-        addRenameColumnsExec(hashed),
-        addRenameColumnsExec(nativeProbed))
-
-      val bhj = Shims.get.createNativeBroadcastJoinExec(
-        bhjOrig.left,
-        bhjOrig.right,
-        bhjOrig.outputPartitioning,
-        bhjOrig.leftKeys,
-        bhjOrig.rightKeys,
-        bhjOrig.joinType,
-        bhjOrig.condition)
+          val bhjOrig = BroadcastHashJoinExec(
+            modifiedHashedKeys,
+            modifiedProbedKeys,
+            modifiedJoinType,
+            BuildLeft,
+            condition,
+            addRenameColumnsExec(hashed),
+            addRenameColumnsExec(nativeProbed))
+
+          Shims.get.createNativeBroadcastJoinExec(
+            bhjOrig.left,
+            bhjOrig.right,
+            bhjOrig.outputPartitioning,
+            bhjOrig.leftKeys,
+            bhjOrig.rightKeys,
+            bhjOrig.joinType,
+            bhjOrig.condition,
+            pb.JoinSide.LEFT_SIDE,
+            isNullAwareAntiJoin = false)
+      }
 
       if (needPostProject) {
         buildPostJoinProject(bhj, exec.output)
@@ -497,21 +617,21 @@ object BlazeConverters extends Logging {
       // reference: https://docs.rs/datafusion/latest/datafusion/physical_plan/joins/struct.NestedLoopJoinExec.html
       var needPostProject = false
       val (modifiedLeft, modifiedRight, modifiedJoinType) = (buildSide, joinType) match {
-        case (BuildLeft, RightOuter | FullOuter) => (broadcasted, nativeProbed, joinType)
+        case (BuildLeft, RightOuter) => (broadcasted, nativeProbed, joinType)
         case (BuildRight, Inner | LeftOuter | LeftSemi | LeftAnti) =>
           (nativeProbed, broadcasted, joinType)
-        case _ =>
+        case (BuildLeft, Inner) =>
           needPostProject = true
-          val modifiedJoinType = joinType match { // reverse join type
-            case Inner => (nativeProbed, broadcasted, Inner)
-            case FullOuter => (broadcasted, nativeProbed, FullOuter)
-            case LeftOuter => (broadcasted, nativeProbed, RightOuter)
-            case RightOuter => (nativeProbed, broadcasted, LeftOuter)
-            case _ =>
-              throw new NotImplementedError(
-                s"BNLJ $joinType with $buildSide is not yet supported")
-          }
-          modifiedJoinType
+          (nativeProbed, broadcasted, Inner)
+
+        // unmatched rows of the broadcasted side are emitted by every probed
+        // partition, so these join types are only correct with a single one
+        case (BuildLeft, _) if nativeProbed.outputPartitioning.numPartitions == 1 =>
+          (broadcasted, nativeProbed, joinType)
+        case (BuildRight, _) if nativeProbed.outputPartitioning.numPartitions == 1 =>
+          (nativeProbed, broadcasted, joinType)
+        case _ =>
+          throw new NotImplementedError(s"BNLJ $joinType with $buildSide is not yet supported")
       }
 
       val bnlj = Shims.get.createNativeBroadcastNestedLoopJoinExec(
@@ -56,7 +56,12 @@ object BlazeSparkSessionExtension extends Logging {
       .getOrElse(false)
     val strategy =
       exec.getTagValue(BlazeConvertStrategy.convertStrategyTag).getOrElse(Default)
-    logInfo(s" +${"-" * depth} $nodeName (convertible=$convertible, strategy=$strategy)")
+    val fallbackReason = exec
+      .getTagValue(BlazeConvertStrategy.fallbackReasonTag)
+      .map(reason => s", fallback=${reason.reason}: ${reason.message}")
+      .getOrElse("")
+    logInfo(
+      s" +${"-" * depth} $nodeName (convertible=$convertible, strategy=$strategy$fallbackReason)")
     exec.children.foreach(dumpSimpleSparkPlanTreeNode(_, depth + 1))
   }
 }
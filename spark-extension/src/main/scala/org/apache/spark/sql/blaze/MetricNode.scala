@@ -31,4 +31,32 @@ case class MetricNode(
     metrics.get(metricName).foreach(_.add(v))
     metricValueHandler.foreach(_.apply(metricName, v))
   }
+
+  /**
+   * Adds metrics of this node and all descendants, which are visited in pre-order. for
+   * each node, nodeShapes contains the number of its metrics and children, and its metrics
+   * are the next entries of metricNames/metricValues. metrics of native nodes without
+   * matching children are ignored.
+   */
+  def addAll(
+      nodeShapes: Array[Long],
+      metricNames: Array[String],
+      metricValues: Array[Long]): Unit = {
+    var nodeIdx = 0
+    var metricIdx = 0
+
+    def addNode(node: Option[MetricNode]): Unit = {
+      val numMetrics = nodeShapes(nodeIdx * 2).toInt
+      val numChildren = nodeShapes(nodeIdx * 2 + 1).toInt
+      nodeIdx += 1
+      for (i <- metricIdx until metricIdx + numMetrics) {
+        node.foreach(_.add(metricNames(i), metricValues(i)))
+      }
+      metricIdx += numMetrics
+      for (i <- 0 until numChildren) {
+        addNode(node.flatMap(_.children.lift(i)))
+      }
+    }
+    addNode(Some(this))
+  }
 }
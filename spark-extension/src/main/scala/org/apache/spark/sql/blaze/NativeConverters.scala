@@ -27,7 +27,7 @@ import com.google.protobuf.ByteString
 import org.apache.spark.SparkEnv
 import org.blaze.{protobuf => pb}
 import org.apache.spark.internal.Logging
-import org.apache.spark.sql.catalyst.expressions.{Abs, Acos, Add, Alias, And, Asin, Atan, AttributeReference, BitwiseAnd, BitwiseOr, BoundReference, CaseWhen, Cast, Ceil, CheckOverflow, Coalesce, Concat, ConcatWs, Contains, Cos, CreateArray, CreateNamedStruct, Divide, EndsWith, EqualTo, Exp, Expression, Floor, GetArrayItem, GetMapValue, GetStructField, GreaterThan, GreaterThanOrEqual, If, In, InSet, IsNotNull, IsNull, Length, LessThan, LessThanOrEqual, Like, Literal, Log, Log10, Log2, Lower, MakeDecimal, Md5, Multiply, Murmur3Hash, Not, NullIf, OctetLength, Or, Pmod, PromotePrecision, Remainder, Sha2, ShiftLeft, ShiftRight, Signum, Sin, Sqrt, StartsWith, StringRepeat, StringSpace, StringTrim, StringTrimLeft, StringTrimRight, Substring, Subtract, Tan, TruncDate, Unevaluable, UnscaledValue, Upper}
+import org.apache.spark.sql.catalyst.expressions.{Abs, Acos, Add, Alias, And, ArrayAggregate, ArrayContains, ArrayExists, ArrayFilter, ArrayJoin, ArrayTransform, Asin, Atan, AttributeReference, BRound, BitwiseAnd, BitwiseOr, BoundReference, CaseWhen, Cast, Ceil, CheckOverflow, Coalesce, Concat, ConcatWs, Contains, Cos, CreateArray, CreateNamedStruct, DateAdd, DateDiff, DateFormatClass, DateSub, Divide, ElementAt, EndsWith, EqualTo, Exp, Expression, Factorial, Floor, FromUnixTime, GetArrayItem, GetJsonObject, GetMapValue, GetStructField, GreaterThan, GreaterThanOrEqual, Hypot, If, In, InSet, IsNotNull, IsNull, JsonToStructs, LambdaFunction, Length, LessThan, LessThanOrEqual, Like, Literal, Log, Log10, Log2, Logarithm, Lower, MakeDecimal, MapConcat, MapFromArrays, MapKeys, MapValues, Md5, Multiply, Murmur3Hash, NaNvl, NamedLambdaVariable, Not, NullIf, OctetLength, Or, Overlay, Pmod, PromotePrecision, RLike, RegExpExtract, RegExpReplace, Remainder, Rint, Round, ScalaUDF, Sha2, ShiftLeft, ShiftRight, Signum, Sin, Size, Slice, SortArray, Sqrt, StartsWith, StringLPad, StringRPad, StringRepeat, StringSpace, StringTranslate, StringTrim, StringTrimLeft, StringTrimRight, Substring, Subtract, Tan, ToUnixTimestamp, TruncDate, Unevaluable, UnixTimestamp, UnscaledValue, Upper, XxHash64}
 import org.apache.spark.sql.catalyst.expressions.aggregate.AggregateExpression
 import org.apache.spark.sql.catalyst.expressions.aggregate.Average
 import org.apache.spark.sql.catalyst.expressions.aggregate.CollectList
@@ -39,6 +39,7 @@ import org.apache.spark.sql.catalyst.expressions.aggregate.Sum
 import org.apache.spark.sql.catalyst.expressions.Attribute
 import org.apache.spark.sql.catalyst.expressions.BinaryArithmetic
 import org.apache.spark.sql.catalyst.expressions.aggregate.First
+import org.apache.spark.sql.catalyst.expressions.aggregate.Last
 import org.apache.spark.sql.catalyst.plans.FullOuter
 import org.apache.spark.sql.catalyst.plans.Inner
 import org.apache.spark.sql.catalyst.plans.JoinType
@@ -74,7 +75,6 @@ import org.apache.spark.sql.types.StringType
 import org.apache.spark.sql.types.StructField
 import org.apache.spark.sql.types.StructType
 import org.apache.spark.sql.types.TimestampType
-import org.apache.spark.unsafe.types.UTF8String
 import org.apache.spark.util.Utils
 import org.blaze.protobuf.PhysicalExprNode
 
@@ -294,8 +294,9 @@ object NativeConverters extends Logging {
 
     try {
       // get number of inconvertible children
+      // lambda functions are converted along with their higher-order functions
       var numInconvertibleChildren = 0
-      sparkExpr.children.foreach { child =>
+      sparkExpr.children.filterNot(_.isInstanceOf[LambdaFunction]).foreach { child =>
         try {
           convertExprWithFallback(child, isPruningExpr = false, fallbackToError)
         } catch {
@@ -311,16 +312,18 @@ object NativeConverters extends Logging {
       numInconvertibleChildren match {
         case 0 => convertExprWithFallback(sparkExpr, isPruningExpr = false, fallbackToError)
         case 1 =>
-          val childrenConverted = sparkExpr.mapChildren { child =>
-            try {
-              val converted =
-                convertExprWithFallback(child, isPruningExpr = false, fallbackToError)
-              Shims.get.createNativeExprWrapper(converted, child.dataType, child.nullable)
-            } catch {
-              case _: NotImplementedError =>
-                val fallbacked = convertExpr(child)
-                Shims.get.createNativeExprWrapper(fallbacked, child.dataType, child.nullable)
-            }
+          val childrenConverted = sparkExpr.mapChildren {
+            case child: LambdaFunction => child
+            case child =>
+              try {
+                val converted =
+                  convertExprWithFallback(child, isPruningExpr = false, fallbackToError)
+                Shims.get.createNativeExprWrapper(converted, child.dataType, child.nullable)
+              } catch {
+                case _: NotImplementedError =>
+                  val fallbacked = convertExpr(child)
+                  Shims.get.createNativeExprWrapper(fallbacked, child.dataType, child.nullable)
+              }
           }
           convertExprWithFallback(childrenConverted, isPruningExpr = false, fallbackToError)
         case _ =>
@@ -423,6 +426,52 @@ object NativeConverters extends Logging {
             .setReturnType(convertDataType(dataType)))
       }
 
+    def buildLambdaFunction(function: Expression): pb.PhysicalLambdaFunctionNode = {
+      val lambda = function.asInstanceOf[LambdaFunction]
+      val variables = lambda.arguments.map(_.asInstanceOf[NamedLambdaVariable])
+      val variableIds = variables.map(_.exprId).toSet
+
+      // variables are bound as named columns when evaluating the lambda body, replace them
+      // with native wrappers so that the body can be converted like other expressions
+      val body = lambda.function.transform {
+        case v: NamedLambdaVariable if variableIds.contains(v.exprId) =>
+          val variableNode = buildExprNode {
+            _.setLambdaVariable(
+              pb.PhysicalLambdaVariableExprNode
+                .newBuilder()
+                .setName(lambdaVariableName(v))
+                .setDataType(convertDataType(v.dataType))
+                .setNullable(v.nullable))
+          }
+          Shims.get.createNativeExprWrapper(variableNode, v.dataType, v.nullable)
+      }
+      pb.PhysicalLambdaFunctionNode
+        .newBuilder()
+        .setBody(convertExprWithFallback(body, isPruningExpr, fallback))
+        .addAllVariableNames(variables.map(lambdaVariableName).asJava)
+        .build()
+    }
+
+    def buildHigherOrderFunction(
+        func: pb.HigherOrderFunction,
+        array: Expression,
+        args: Seq[Expression],
+        lambdas: Seq[Expression],
+        dataType: DataType,
+        followThreeValuedLogic: Boolean = false): pb.PhysicalExprNode =
+      buildExprNode {
+        _.setHigherOrderFunction(
+          pb.PhysicalHigherOrderFunctionExprNode
+            .newBuilder()
+            .setFunc(func)
+            .setArray(convertExprWithFallback(array, isPruningExpr, fallback))
+            .addAllArgs(
+              args.map(expr => convertExprWithFallback(expr, isPruningExpr, fallback)).asJava)
+            .addAllLambdas(lambdas.map(buildLambdaFunction).asJava)
+            .setReturnType(convertDataType(dataType))
+            .setFollowThreeValuedLogic(followThreeValuedLogic))
+      }
+
     def buildExtScalarFunction(
         name: String,
         args: Seq[Expression],
@@ -438,6 +487,35 @@ object NativeConverters extends Logging {
             .setReturnType(convertDataType(dataType)))
       }
 
+    // overflow and division by zero follow spark's legacy/ansi semantics natively.
+    // decimal operands keep their own precisions and scales, and the result is
+    // computed exactly and rounded to spark's result type
+    def buildBinaryArithmeticExprNode(
+        e: BinaryArithmetic,
+        op: pb.BinaryArithmeticOp): pb.PhysicalExprNode = {
+      val resultType = arithDecimalReturnType(e)
+      val (lhs, rhs) = resultType match {
+        case _: DecimalType =>
+          def toDecimal(expr: Expression) = expr.dataType match {
+            case _: DecimalType => expr
+            case _ => Cast(expr, resultType)
+          }
+          (toDecimal(e.left), toDecimal(e.right))
+        case _ =>
+          (castIfNecessary(e.left, resultType), castIfNecessary(e.right, resultType))
+      }
+      buildExprNode {
+        _.setBinaryArithmeticExpr(
+          pb.PhysicalBinaryArithmeticExprNode
+            .newBuilder()
+            .setL(convertExprWithFallback(lhs, isPruningExpr, fallback))
+            .setR(convertExprWithFallback(rhs, isPruningExpr, fallback))
+            .setOp(op)
+            .setReturnType(convertDataType(resultType))
+            .setFailOnError(Shims.get.isArithmeticFailOnError(e)))
+      }
+    }
+
     def castIfNecessary(expr: Expression, dataType: DataType): Expression = {
       if (expr.dataType == dataType) {
         return expr
@@ -457,11 +535,11 @@ object NativeConverters extends Logging {
         buildExprNode { b =>
           if (value == null) {
             dataType match {
-              case at: ArrayType =>
+              case _: ArrayType | _: StructType | _: MapType =>
                 b.setTryCast(
                   pb.PhysicalTryCastNode
                     .newBuilder()
-                    .setArrowType(convertDataType(at))
+                    .setArrowType(convertDataType(dataType))
                     .setExpr(buildExprNode {
                       _.setLiteral(
                         pb.ScalarValue.newBuilder().setNullValue(convertScalarType(NullType)))
@@ -520,8 +598,36 @@ object NativeConverters extends Logging {
               .setReturnNullable(subquery.nullable))
         }
 
+      // cast string to date/timestamp, timestamps without zone ids are parsed
+      // in the session timezone
+      case cast: Cast
+          if cast.child.dataType == StringType &&
+            Seq(DateType, TimestampType).contains(cast.dataType) =>
+        buildExprNode {
+          _.setTryCast(
+            pb.PhysicalTryCastNode
+              .newBuilder()
+              .setExpr(convertExprWithFallback(cast.child, isPruningExpr, fallback))
+              .setArrowType(convertDataType(cast.dataType))
+              .setSessionTimezone(cast.timeZoneId.getOrElse(SQLConf.get.sessionLocalTimeZone))
+              .setAnsi(Shims.get.isCastAnsiEnabled(cast))
+              .build())
+        }
+
+      // cast timestamp to string, formatted in the session timezone
+      case cast: Cast if cast.child.dataType == TimestampType && cast.dataType == StringType =>
+        buildExprNode {
+          _.setTryCast(
+            pb.PhysicalTryCastNode
+              .newBuilder()
+              .setExpr(convertExprWithFallback(cast.child, isPruningExpr, fallback))
+              .setArrowType(convertDataType(cast.dataType))
+              .setSessionTimezone(cast.timeZoneId.getOrElse(SQLConf.get.sessionLocalTimeZone))
+              .build())
+        }
+
       // cast
-      // not performing native cast for timestamp/dates (will use UDFWrapper instead)
+      // not performing native cast for other timestamp/dates (will use UDFWrapper instead)
       case cast: Cast
           if !Seq(cast.dataType, cast.child.dataType).contains(TimestampType) &&
             !Seq(cast.dataType, cast.child.dataType).contains(DateType) =>
@@ -531,6 +637,7 @@ object NativeConverters extends Logging {
               .newBuilder()
               .setExpr(convertExprWithFallback(cast.child, isPruningExpr, fallback))
               .setArrowType(convertDataType(cast.dataType))
+              .setAnsi(Shims.get.isCastAnsiEnabled(cast))
               .build())
         }
 
@@ -546,19 +653,15 @@ object NativeConverters extends Logging {
         }
 
       // in
-      case InSet(value, set) if set.forall(_.isInstanceOf[Literal]) =>
+      // hset holds internal values of the value's type, including nulls
+      case InSet(value, set) =>
         buildExprNode {
           _.setInList(
             pb.PhysicalInListNode
               .newBuilder()
               .setExpr(convertExprWithFallback(value, isPruningExpr, fallback))
-              .addAllList(set.map {
-                case utf8string: UTF8String =>
-                  convertExprWithFallback(
-                    Literal(utf8string, StringType),
-                    isPruningExpr,
-                    fallback)
-                case v => convertExprWithFallback(Literal.apply(v), isPruningExpr, fallback)
+              .addAllList(set.toSeq.map { v =>
+                convertExprWithFallback(Literal(v, value.dataType), isPruningExpr, fallback)
               }.asJava))
         }
 
@@ -579,7 +682,8 @@ object NativeConverters extends Logging {
               .setExpr(convertExprWithFallback(child, isPruningExpr, fallback))
               .build())
         }
-      case Not(EqualTo(lhs, rhs)) => buildBinaryExprNode(lhs, rhs, "NotEq")
+      case Not(EqualTo(lhs, rhs)) if !lhs.dataType.isInstanceOf[StructType] =>
+        buildBinaryExprNode(lhs, rhs, "NotEq")
       case Not(child) =>
         buildExprNode {
           _.setNotExpr(
@@ -590,89 +694,25 @@ object NativeConverters extends Logging {
         }
 
       // binary ops
+      case EqualTo(lhs, rhs) if lhs.dataType.isInstanceOf[StructType] =>
+        if (!isSupportedStructEqualType(lhs.dataType) || lhs.dataType != rhs.dataType) {
+          throw new NotImplementedError(s"struct equality not supported: ${lhs.dataType}")
+        }
+        buildExtScalarFunction("StructEqual", lhs :: rhs :: Nil, BooleanType)
       case EqualTo(lhs, rhs) => buildBinaryExprNode(lhs, rhs, "Eq")
       case GreaterThan(lhs, rhs) => buildBinaryExprNode(lhs, rhs, "Gt")
       case LessThan(lhs, rhs) => buildBinaryExprNode(lhs, rhs, "Lt")
       case GreaterThanOrEqual(lhs, rhs) => buildBinaryExprNode(lhs, rhs, "GtEq")
       case LessThanOrEqual(lhs, rhs) => buildBinaryExprNode(lhs, rhs, "LtEq")
 
-      case e: Add =>
-        val lhs = e.left
-        val rhs = e.right
-        if (lhs.dataType.isInstanceOf[DecimalType] || rhs.dataType.isInstanceOf[DecimalType]) {
-          val resultType = arithDecimalReturnType(e)
-          buildExprNode {
-            _.setBinaryExpr(
-              pb.PhysicalBinaryExprNode
-                .newBuilder()
-                .setL(convertExprWithFallback(Cast(lhs, resultType), isPruningExpr, fallback))
-                .setR(convertExprWithFallback(rhs, isPruningExpr, fallback))
-                .setOp("Plus"))
-          }
-        } else {
-          buildBinaryExprNode(lhs, rhs, "Plus")
-        }
-
-      case e: Subtract =>
-        val lhs = e.left
-        val rhs = e.right
-        if (lhs.dataType.isInstanceOf[DecimalType] || rhs.dataType.isInstanceOf[DecimalType]) {
-          val resultType = arithDecimalReturnType(e)
-          buildExprNode {
-            _.setBinaryExpr(
-              pb.PhysicalBinaryExprNode
-                .newBuilder()
-                .setL(convertExprWithFallback(Cast(lhs, resultType), isPruningExpr, fallback))
-                .setR(convertExprWithFallback(rhs, isPruningExpr, fallback))
-                .setOp("Minus"))
-          }
-        } else {
-          buildBinaryExprNode(lhs, rhs, "Minus")
-        }
-
-      case e: Multiply =>
-        val lhs = e.left
-        val rhs = e.right
-        if (lhs.dataType.isInstanceOf[DecimalType] || rhs.dataType.isInstanceOf[DecimalType]) {
-          val resultType = arithDecimalReturnType(e)
-          buildExprNode {
-            _.setBinaryExpr(
-              pb.PhysicalBinaryExprNode
-                .newBuilder()
-                .setL(convertExprWithFallback(Cast(lhs, resultType), isPruningExpr, fallback))
-                .setR(convertExprWithFallback(rhs, isPruningExpr, fallback))
-                .setOp("Multiply"))
-          }
-        } else {
-          buildBinaryExprNode(lhs, rhs, "Multiply")
-        }
-
-      case e: Divide =>
-        val lhs = e.left
-        val rhs = e.right
-        if (lhs.dataType.isInstanceOf[DecimalType] || rhs.dataType.isInstanceOf[DecimalType]) {
-          val resultType = arithDecimalReturnType(e)
-          buildExprNode {
-            _.setBinaryExpr(
-              pb.PhysicalBinaryExprNode
-                .newBuilder()
-                .setL(convertExprWithFallback(Cast(lhs, resultType), isPruningExpr, fallback))
-                .setR(convertExprWithFallback(rhs, isPruningExpr, fallback))
-                .setOp("Divide"))
-          }
-        } else {
-          val resultType = e.dataType
-          val lhsCasted = castIfNecessary(lhs, resultType)
-          val rhsCasted = castIfNecessary(rhs, resultType)
-          buildExprNode {
-            _.setBinaryExpr(
-              pb.PhysicalBinaryExprNode
-                .newBuilder()
-                .setL(convertExprWithFallback(lhsCasted, isPruningExpr, fallback))
-                .setR(buildExtScalarFunction("NullIfZero", rhsCasted :: Nil, rhs.dataType))
-                .setOp("Divide"))
-          }
-        }
+      case e: Add if isSupportedArithmeticType(e.dataType) =>
+        buildBinaryArithmeticExprNode(e, pb.BinaryArithmeticOp.ADD)
+      case e: Subtract if isSupportedArithmeticType(e.dataType) =>
+        buildBinaryArithmeticExprNode(e, pb.BinaryArithmeticOp.SUBTRACT)
+      case e: Multiply if isSupportedArithmeticType(e.dataType) =>
+        buildBinaryArithmeticExprNode(e, pb.BinaryArithmeticOp.MULTIPLY)
+      case e: Divide if isSupportedArithmeticType(e.dataType) =>
+        buildBinaryArithmeticExprNode(e, pb.BinaryArithmeticOp.DIVIDE)
 
       case e: Remainder =>
         val lhs = e.left
@@ -707,8 +747,9 @@ object NativeConverters extends Logging {
               .setPattern(convertExprWithFallback(e.right, isPruningExpr, fallback)))
         }
 
-      // if rhs is complex in and/or operators, use short-circuiting implementation
-      case And(lhs, rhs) if rhs.find(HiveUDFUtil.isHiveUDF).isDefined =>
+      // if rhs is complex in and/or operators, use short-circuiting implementation.
+      // udfs are evaluated through spark udf wrapper and may rely on lhs as a guard
+      case And(lhs, rhs) if rhs.find(isUDF).isDefined =>
         buildExprNode {
           _.setScAndExpr(
             pb.PhysicalSCAndExprNode
@@ -716,7 +757,7 @@ object NativeConverters extends Logging {
               .setLeft(convertExprWithFallback(lhs, isPruningExpr, fallback))
               .setRight(convertExprWithFallback(rhs, isPruningExpr, fallback)))
         }
-      case Or(lhs, rhs) if rhs.find(HiveUDFUtil.isHiveUDF).isDefined =>
+      case Or(lhs, rhs) if rhs.find(isUDF).isDefined =>
         buildExprNode {
           _.setScOrExpr(
             pb.PhysicalSCOrExprNode
@@ -749,6 +790,21 @@ object NativeConverters extends Logging {
       case e: Log => buildScalarFunction(pb.ScalarFunction.Ln, e.children, e.dataType)
       case e: Log2 => buildScalarFunction(pb.ScalarFunction.Log2, e.children, e.dataType)
       case e: Log10 => buildScalarFunction(pb.ScalarFunction.Log10, e.children, e.dataType)
+      case e: Logarithm =>
+        val args = Seq(e.left, e.right).map(castIfNecessary(_, DoubleType))
+        buildExtScalarFunction("LogWithBase", args, e.dataType)
+      case e: Hypot =>
+        val args = Seq(e.left, e.right).map(castIfNecessary(_, DoubleType))
+        buildExtScalarFunction("Hypot", args, e.dataType)
+      case e: Rint =>
+        buildExtScalarFunction("Rint", castIfNecessary(e.child, DoubleType) :: Nil, e.dataType)
+      case e: Factorial =>
+        val args = castIfNecessary(e.child, IntegerType) :: Nil
+        buildExtScalarFunction("Factorial", args, LongType)
+      case e: Pmod
+          if e.left.dataType == e.right.dataType && isSupportedArithmeticType(e.dataType) =>
+        val ansi = Literal(SQLConf.get.ansiEnabled)
+        buildExtScalarFunction("Pmod", e.left :: e.right :: ansi :: Nil, e.dataType)
       case e: Floor if !e.dataType.isInstanceOf[DecimalType] =>
         buildExprNode {
           _.setTryCast(
@@ -768,9 +824,12 @@ object NativeConverters extends Logging {
               .build())
         }
 
-      // TODO: datafusion's round() has different behavior from spark
-      // case e @ Round(_1, Literal(n: Int, _)) if _1.dataType.isInstanceOf[FractionalType] =>
-      //   buildScalarFunction(pb.ScalarFunction.Round, Seq(_1, Literal(n.toLong)), e.dataType)
+      // datafusion's round() has different behavior from spark, use native HALF_UP/HALF_EVEN
+      // implementations instead. decimals with negative scales are not supported
+      case e: Round if isSupportedRound(e.child, e.scale) =>
+        buildExtScalarFunction("Round", e.child :: e.scale :: Nil, e.dataType)
+      case e: BRound if isSupportedRound(e.child, e.scale) =>
+        buildExtScalarFunction("BRound", e.child :: e.scale :: Nil, e.dataType)
 
       case e: Signum => buildScalarFunction(pb.ScalarFunction.Signum, e.children, e.dataType)
       case e: Abs if e.dataType.isInstanceOf[FloatType] || e.dataType.isInstanceOf[DoubleType] =>
@@ -793,8 +852,38 @@ object NativeConverters extends Logging {
         buildScalarFunction(pb.ScalarFunction.Rtrim, e.srcStr +: e.trimStr.toSeq, e.dataType)
       case e @ NullIf(left, right, _) =>
         buildScalarFunction(pb.ScalarFunction.NullIf, left :: right :: Nil, e.dataType)
+      case e: DateAdd =>
+        buildExtScalarFunction("DateAdd", e.children, DateType)
+      case e: DateSub =>
+        buildExtScalarFunction("DateSub", e.children, DateType)
+      case e: DateDiff =>
+        buildExtScalarFunction("DateDiff", e.children, IntegerType)
       case e: TruncDate =>
-        buildScalarFunction(pb.ScalarFunction.DateTrunc, e.children, e.dataType)
+        buildExtScalarFunction("TruncDate", e.children, DateType)
+      case e: DateFormatClass if isSupportedDateFormat(e.right) =>
+        val timeZoneId = e.timeZoneId.getOrElse(SQLConf.get.sessionLocalTimeZone)
+        buildExtScalarFunction(
+          "DateFormat",
+          e.left :: e.right :: Literal(timeZoneId) :: Nil,
+          StringType)
+      case e: FromUnixTime if isSupportedDateFormat(e.format) =>
+        val timeZoneId = e.timeZoneId.getOrElse(SQLConf.get.sessionLocalTimeZone)
+        buildExtScalarFunction(
+          "FromUnixTime",
+          e.sec :: e.format :: Literal(timeZoneId) :: Nil,
+          StringType)
+      case e: UnixTimestamp if isSupportedUnixTimestampInput(e.timeExp, e.format) =>
+        val timeZoneId = e.timeZoneId.getOrElse(SQLConf.get.sessionLocalTimeZone)
+        buildExtScalarFunction(
+          "UnixTimestamp",
+          e.timeExp :: e.format :: Literal(timeZoneId) :: Nil,
+          LongType)
+      case e: ToUnixTimestamp if isSupportedUnixTimestampInput(e.timeExp, e.format) =>
+        val timeZoneId = e.timeZoneId.getOrElse(SQLConf.get.sessionLocalTimeZone)
+        buildExtScalarFunction(
+          "UnixTimestamp",
+          e.timeExp :: e.format :: Literal(timeZoneId) :: Nil,
+          LongType)
       case Md5(_1) =>
         buildScalarFunction(pb.ScalarFunction.MD5, Seq(unpackBinaryTypeCast(_1)), StringType)
       case Sha2(_1, Literal(224, _)) =>
@@ -809,6 +898,8 @@ object NativeConverters extends Logging {
         buildScalarFunction(pb.ScalarFunction.SHA512, Seq(unpackBinaryTypeCast(_1)), StringType)
       case Murmur3Hash(children, 42) =>
         buildExtScalarFunction("Murmur3Hash", children, IntegerType)
+      case XxHash64(children, 42L) =>
+        buildExtScalarFunction("XxHash64", children, LongType)
 
       // startswith is converted to scalar function in pruning-expr mode
       case StartsWith(expr, Literal(prefix, StringType)) if isPruningExpr =>
@@ -861,6 +952,38 @@ object NativeConverters extends Logging {
       case StringRepeat(str, n @ Literal(_, IntegerType)) =>
         buildExtScalarFunction("StringRepeat", str :: n :: Nil, StringType)
 
+      case StringLPad(str, len, pad) if str.dataType == StringType =>
+        buildExtScalarFunction("StringLPad", str :: len :: pad :: Nil, StringType)
+
+      case StringRPad(str, len, pad) if str.dataType == StringType =>
+        buildExtScalarFunction("StringRPad", str :: len :: pad :: Nil, StringType)
+
+      case StringTranslate(
+            str,
+            matching @ Literal(_, StringType),
+            replace @ Literal(_, StringType)) =>
+        buildExtScalarFunction("StringTranslate", str :: matching :: replace :: Nil, StringType)
+
+      case e: Overlay if e.dataType == StringType =>
+        buildExtScalarFunction(
+          "StringOverlay",
+          e.input :: e.replace :: e.pos :: e.len :: Nil,
+          StringType)
+
+      case RLike(str, pattern @ Literal(_, StringType)) if isSupportedJavaRegex(pattern) =>
+        buildExtScalarFunction("RegexpLike", str :: pattern :: Nil, BooleanType)
+
+      case e: RegExpReplace if isSupportedJavaRegex(e.children(1)) =>
+        // the position parameter is not available before spark 3.1
+        val pos = e.children.lift(3).getOrElse(Literal(1))
+        buildExtScalarFunction("RegexpReplace", e.children.take(3) :+ pos, StringType)
+
+      case e: RegExpExtract if isSupportedJavaRegex(e.regexp) =>
+        buildExtScalarFunction(
+          "RegexpExtract",
+          e.subject :: e.regexp :: e.idx :: Nil,
+          StringType)
+
       case e: Concat if e.children.forall(_.dataType == StringType) =>
         buildExtScalarFunction("StringConcat", e.children, e.dataType)
 
@@ -873,14 +996,24 @@ object NativeConverters extends Logging {
           "only supports concat_ws with string or array<string> type")
         buildExtScalarFunction("StringConcatWs", e.children, e.dataType)
 
-      case e: Coalesce => buildScalarFunction(pb.ScalarFunction.Coalesce, e.children, e.dataType)
+      // conditional null functions, children are casted to the result type as the
+      // native implementations require identical input types. nvl/ifnull/nvl2 are
+      // runtime-replaceable and arrive here as their coalesce/if replacements
+      case e: Coalesce =>
+        val children = e.children.map(castIfNecessary(_, e.dataType))
+        buildScalarFunction(pb.ScalarFunction.Coalesce, children, e.dataType)
+
+      case e @ NaNvl(left, right) =>
+        val children = Seq(left, right).map(castIfNecessary(_, e.dataType))
+        buildExtScalarFunction("NaNvl", children, e.dataType)
 
       case If(predicate, trueValue, falseValue) =>
         val caseWhen = CaseWhen(Seq((predicate, trueValue)), falseValue)
         convertExprWithFallback(caseWhen, isPruningExpr, fallback)
 
-      case CaseWhen(branches, elseValue) =>
-        val caseExpr = pb.PhysicalCaseNode.newBuilder()
+      case e @ CaseWhen(branches, elseValue) =>
+        // branch values are evaluated lazily and casted to the return type natively
+        val caseExpr = pb.PhysicalCaseNode.newBuilder().setReturnType(convertDataType(e.dataType))
         val whenThens = branches.map { case (w, t) =>
           val whenThen = pb.PhysicalWhenThen.newBuilder()
           whenThen.setWhenExpr(convertExprWithFallback(w, isPruningExpr, fallback))
@@ -923,6 +1056,8 @@ object NativeConverters extends Logging {
             .apply(precision, IntegerType) :: Literal.apply(scale, IntegerType) :: Nil
         buildExtScalarFunction("CheckOverflow", args, DecimalType(precision, scale))
 
+      case e: NativeUDF => buildExtScalarFunction(s"NativeUDF:${e.name}", e.children, e.dataType)
+
       case e: CreateArray => buildExtScalarFunction("MakeArray", e.children, e.dataType)
 
       case e: CreateNamedStruct =>
@@ -963,6 +1098,96 @@ object NativeConverters extends Logging {
               .setKey(convertValue(value, dataType)))
         }
 
+      case e: ElementAt
+          if !SQLConf.get.ansiEnabled && e.left.dataType.isInstanceOf[MapType] && e.right
+            .isInstanceOf[Literal] =>
+        val value = e.right.asInstanceOf[Literal].value
+        val dataType = e.right.asInstanceOf[Literal].dataType
+        buildExprNode {
+          _.setGetMapValueExpr(
+            pb.PhysicalGetMapValueExprNode
+              .newBuilder()
+              .setExpr(convertExprWithFallback(e.left, isPruningExpr, fallback))
+              .setKey(convertValue(value, dataType)))
+        }
+
+      case e: ElementAt if !SQLConf.get.ansiEnabled && e.left.dataType.isInstanceOf[ArrayType] =>
+        buildExtScalarFunction("ArrayElementAt", e.left :: e.right :: Nil, e.dataType)
+
+      case e: Size if e.child.dataType.isInstanceOf[ArrayType] || e.child.dataType
+            .isInstanceOf[MapType] =>
+        val legacySizeOfNull = Literal(e.legacySizeOfNull)
+        buildExtScalarFunction("ArraySize", e.child :: legacySizeOfNull :: Nil, IntegerType)
+
+      case e: ArrayContains =>
+        buildExtScalarFunction("ArrayContains", e.left :: e.right :: Nil, BooleanType)
+
+      case e: SortArray
+          if e.ascendingOrder.isInstanceOf[Literal] && e.ascendingOrder
+            .asInstanceOf[Literal]
+            .value != null && isSupportedSortArrayType(e.base.dataType) =>
+        buildExtScalarFunction("ArraySort", e.base :: e.ascendingOrder :: Nil, e.dataType)
+
+      case e: Slice =>
+        buildExtScalarFunction("ArraySlice", e.x :: e.start :: e.length :: Nil, e.dataType)
+
+      case e: ArrayJoin =>
+        val args = e.array :: e.delimiter :: e.nullReplacement.toList
+        buildExtScalarFunction("ArrayJoin", args, StringType)
+
+      case e: GetMapValue if isSupportedMapKeyType(e.key.dataType) =>
+        buildExtScalarFunction("MapGetValue", e.child :: e.key :: Nil, e.dataType)
+
+      case e: ElementAt
+          if !SQLConf.get.ansiEnabled && e.left.dataType.isInstanceOf[MapType] &&
+            isSupportedMapKeyType(e.right.dataType) =>
+        buildExtScalarFunction("MapGetValue", e.left :: e.right :: Nil, e.dataType)
+
+      case e: MapKeys => buildExtScalarFunction("MapKeys", e.child :: Nil, e.dataType)
+      case e: MapValues => buildExtScalarFunction("MapValues", e.child :: Nil, e.dataType)
+
+      case e: MapFromArrays
+          if isSupportedMapKeyType(e.left.dataType.asInstanceOf[ArrayType].elementType) =>
+        val args = e.left :: e.right :: mapKeyDedupPolicy :: Nil
+        buildExtScalarFunction("MapFromArrays", args, e.dataType)
+
+      case e: MapConcat
+          if e.children.nonEmpty &&
+            isSupportedMapKeyType(e.dataType.asInstanceOf[MapType].keyType) =>
+        val args = e.children :+ mapKeyDedupPolicy
+        buildExtScalarFunction("MapConcat", args, e.dataType)
+
+      // higher-order functions
+      case e: ArrayTransform if isLambdaFunction(e.function) =>
+        buildHigherOrderFunction(
+          pb.HigherOrderFunction.TRANSFORM,
+          e.argument,
+          Nil,
+          e.function :: Nil,
+          e.dataType)
+      case e: ArrayFilter if isLambdaFunction(e.function) =>
+        buildHigherOrderFunction(
+          pb.HigherOrderFunction.FILTER,
+          e.argument,
+          Nil,
+          e.function :: Nil,
+          e.dataType)
+      case e: ArrayExists if isLambdaFunction(e.function) =>
+        buildHigherOrderFunction(
+          pb.HigherOrderFunction.EXISTS,
+          e.argument,
+          Nil,
+          e.function :: Nil,
+          e.dataType,
+          followThreeValuedLogic = e.followThreeValuedLogic)
+      case e: ArrayAggregate if isLambdaFunction(e.merge) && isLambdaFunction(e.finish) =>
+        buildHigherOrderFunction(
+          pb.HigherOrderFunction.AGGREGATE,
+          e.argument,
+          e.zero :: Nil,
+          e.merge :: e.finish :: Nil,
+          e.dataType)
+
       case e: GetStructField =>
         buildExprNode {
           _.setGetIndexedFieldExpr(
@@ -972,6 +1197,24 @@ object NativeConverters extends Logging {
               .setKey(convertValue(e.ordinal, IntegerType)))
         }
 
+      case e: GetJsonObject if isSupportedJsonPath(e.path) =>
+        // use GetParsedJsonObject + ParseJson for reusing parsed json value in native
+        val parsed = Shims.get.createNativeExprWrapper(
+          buildExtScalarFunction("ParseJson", e.json :: Nil, BinaryType),
+          BinaryType,
+          nullable = false)
+        buildExtScalarFunction(
+          "GetParsedJsonObject",
+          parsed :: e.path :: Literal(true) :: Nil, // with spark path semantics
+          StringType)
+
+      // the schema is passed as a typed null literal
+      case e: JsonToStructs
+          if e.options.isEmpty && e.child.dataType == StringType &&
+            e.dataType.isInstanceOf[StructType] && isSupportedFromJsonType(e.dataType) =>
+        val schema = Literal(null, e.dataType)
+        buildExtScalarFunction("FromJson", e.child :: schema :: Nil, e.dataType)
+
       // hive UDFJson
       case e
           if (isHiveSimpleUDF(e)
@@ -1044,10 +1287,22 @@ object NativeConverters extends Logging {
         })
         aggBuilder.addChildren(convertExpr(child))
 
-      case CollectList(child, _, _) if child.dataType.isInstanceOf[AtomicType] =>
+      case Last(child, ignoresNullExpr) =>
+        val ignoresNull = ignoresNullExpr.asInstanceOf[Any] match {
+          case Literal(v: Boolean, BooleanType) => v
+          case v: Boolean => v
+        }
+        aggBuilder.setAggFunction(if (ignoresNull) {
+          pb.AggFunction.LAST_IGNORES_NULL
+        } else {
+          pb.AggFunction.LAST
+        })
+        aggBuilder.addChildren(convertExpr(child))
+
+      case CollectList(child, _, _) if isCollectableType(child.dataType) =>
         aggBuilder.setAggFunction(pb.AggFunction.COLLECT_LIST)
         aggBuilder.addChildren(convertExpr(child))
-      case CollectSet(child, _, _) if child.dataType.isInstanceOf[AtomicType] =>
+      case CollectSet(child, _, _) if isCollectableType(child.dataType) =>
         aggBuilder.setAggFunction(pb.AggFunction.COLLECT_SET)
         aggBuilder.addChildren(convertExpr(child))
 
@@ -1064,6 +1319,163 @@ object NativeConverters extends Logging {
       .build()
   }
 
+  // collected values are buffered as scalars, map types are not supported
+  private def isCollectableType(dataType: DataType): Boolean = {
+    dataType match {
+      case _: AtomicType => true
+      case at: ArrayType => isCollectableType(at.elementType)
+      case st: StructType => st.fields.forall(field => isCollectableType(field.dataType))
+      case _ => false
+    }
+  }
+
+  // only simple paths of child names and array indices are supported
+  private def isSupportedJsonPath(path: Expression): Boolean = {
+    path match {
+      case Literal(null, _) => true
+      case Literal(p, StringType) => p.toString.matches("\\$(\\.[^.\\[\\]*']+|\\[\\d+\\])*")
+      case _ => false
+    }
+  }
+
+  private def isSupportedFromJsonType(dataType: DataType): Boolean = {
+    dataType match {
+      case BooleanType | ByteType | ShortType | IntegerType | LongType => true
+      case FloatType | DoubleType | StringType => true
+      case at: ArrayType => isSupportedFromJsonType(at.elementType)
+      case mt: MapType => mt.keyType == StringType && isSupportedFromJsonType(mt.valueType)
+      case st: StructType => st.fields.forall(field => isSupportedFromJsonType(field.dataType))
+      case _ => false
+    }
+  }
+
+  private def isLambdaFunction(function: Expression): Boolean = {
+    function match {
+      case lambda: LambdaFunction => lambda.arguments.forall(_.isInstanceOf[NamedLambdaVariable])
+      case _ => false
+    }
+  }
+
+  // lambda variables are named uniquely with expr ids to support nested lambda functions
+  private def lambdaVariableName(variable: NamedLambdaVariable): String =
+    s"${variable.name}#${variable.exprId.id}"
+
+  private def isUDF(e: Expression): Boolean =
+    HiveUDFUtil.isHiveUDF(e) || e.isInstanceOf[ScalaUDF]
+
+  private def isSupportedRound(child: Expression, scale: Expression): Boolean = {
+    scale match {
+      case Literal(n: Int, IntegerType) =>
+        child.dataType match {
+          case ByteType | ShortType | IntegerType | LongType | FloatType | DoubleType => true
+          case _: DecimalType => n >= 0
+          case _ => false
+        }
+      case _ => false
+    }
+  }
+
+  private def isSupportedArithmeticType(dataType: DataType): Boolean = {
+    dataType match {
+      case ByteType | ShortType | IntegerType | LongType | FloatType | DoubleType => true
+      case _: DecimalType => true
+      case _ => false
+    }
+  }
+
+  // floating fields are excluded since spark treats -0.0 and 0.0 as equal
+  private def isSupportedStructEqualType(dataType: DataType): Boolean = {
+    dataType match {
+      case BooleanType | ByteType | ShortType | IntegerType | LongType => true
+      case StringType | BinaryType | DateType | TimestampType | _: DecimalType => true
+      case st: StructType => st.fields.forall(field => isSupportedStructEqualType(field.dataType))
+      case _ => false
+    }
+  }
+
+  // floating keys are excluded since spark normalizes -0.0 and NaN before deduplication
+  private def isSupportedMapKeyType(dataType: DataType): Boolean = {
+    dataType match {
+      case BooleanType | ByteType | ShortType | IntegerType | LongType => true
+      case StringType | BinaryType | DateType | TimestampType | _: DecimalType => true
+      case _ => false
+    }
+  }
+
+  private def mapKeyDedupPolicy: Literal =
+    Literal(SQLConf.get.getConf(SQLConf.MAP_KEY_DEDUP_POLICY))
+
+  // floating types are excluded since spark treats -0.0 and 0.0 as equal when sorting
+  private def isSupportedSortArrayType(dataType: DataType): Boolean = {
+    dataType match {
+      case at: ArrayType =>
+        at.elementType match {
+          case BooleanType | ByteType | ShortType | IntegerType | LongType => true
+          case StringType | DateType | TimestampType | _: DecimalType => true
+          case _ => false
+        }
+      case _ => false
+    }
+  }
+
+  // java regex constructs which cannot be translated to the native regex dialect
+  private val unsupportedJavaRegexPatterns = Seq(
+    "\\(\\?=",
+    "\\(\\?!",
+    "\\(\\?<[=!]",
+    "\\(\\?>",
+    "\\\\[1-9kGZRXhHV]",
+    "\\\\[pP]\\{(In|java)",
+    "\\(\\?[a-zA-Z-]*U",
+    "[*+?}]\\+").map(_.r)
+
+  // only literal patterns are supported, so unsupported constructs can be
+  // detected here and fall back to spark
+  private def isSupportedJavaRegex(pattern: Expression): Boolean = {
+    pattern match {
+      case Literal(null, _) => true
+      case Literal(regex, StringType) =>
+        !unsupportedJavaRegexPatterns.exists(_.findFirstIn(regex.toString).isDefined)
+      case _ => false
+    }
+  }
+
+  // pattern letters supported by native date_format, and their max repeats
+  private val supportedDateFormatLetters =
+    "yMLdDHhkKmsaE".map(_ -> Int.MaxValue).toMap ++
+      Map('S' -> 9, 'Z' -> 3, 'X' -> 3, 'x' -> 3)
+
+  // day-of-year and day-of-week are not supported in native parsing
+  private val supportedDateParsingLetters = supportedDateFormatLetters -- Seq('D', 'E')
+
+  private def isSupportedDateFormat(
+      format: Expression,
+      supportedLetters: Map[Char, Int] = supportedDateFormatLetters): Boolean = {
+    format match {
+      case Literal(null, _) => true
+      case Literal(pattern, StringType) =>
+        val unquoted = pattern.toString.replaceAll("'[^']*'", "")
+        "([a-zA-Z])\\1*".r.findAllIn(unquoted).forall { letters =>
+          supportedLetters.get(letters.head).exists(letters.length <= _)
+        }
+      case _ => false
+    }
+  }
+
+  // strings are parsed natively only with the non-legacy parser policy, because
+  // spark raises upgrade errors (instead of returning null) in the default policy
+  // and fails on unparsable strings in ansi mode
+  private def isSupportedUnixTimestampInput(timeExp: Expression, format: Expression): Boolean = {
+    timeExp.dataType match {
+      case TimestampType | DateType => true
+      case StringType =>
+        SQLConf.get.legacyTimeParserPolicy == SQLConf.LegacyBehaviorPolicy.CORRECTED &&
+        !SQLConf.get.ansiEnabled &&
+        isSupportedDateFormat(format, supportedDateParsingLetters)
+      case _ => false
+    }
+  }
+
   def convertJoinType(joinType: JoinType): pb.JoinType = {
     joinType match {
       case Inner => pb.JoinType.INNER
@@ -31,6 +31,7 @@ import org.apache.spark.sql.catalyst.InternalRow
 import org.apache.spark.sql.execution.SparkPlan
 import org.apache.spark.sql.execution.metric.SQLMetric
 import org.apache.spark.sql.execution.metric.SQLMetrics
+import org.apache.spark.util.CollectionAccumulator
 
 object NativeHelper extends Logging {
   val currentUser: UserGroupInformation = UserGroupInformation.getCurrentUser
@@ -71,11 +72,14 @@ object NativeHelper extends Logging {
       nativePlan: PhysicalPlanNode,
       metrics: MetricNode,
       partition: Partition,
-      context: Option[TaskContext]): Iterator[InternalRow] = {
+      context: Option[TaskContext],
+      nativePlanDescriptions: Option[CollectionAccumulator[String]] = None)
+      : Iterator[InternalRow] = {
     if (nativePlan == null) {
       return Iterator.empty
     }
-    BlazeCallNativeWrapper(nativePlan, partition, context, metrics).getRowIterator
+    BlazeCallNativeWrapper(nativePlan, partition, context, metrics, nativePlanDescriptions)
+      .getRowIterator
   }
 
   def getDefaultNativeMetrics(sc: SparkContext): Map[String, SQLMetric] = {
@@ -84,7 +88,12 @@ object NativeHelper extends Logging {
       "output_batches" -> SQLMetrics.createMetric(sc, "Native.output_batches"),
       "elapsed_compute" -> SQLMetrics.createNanoTimingMetric(sc, "Native.elapsed_compute"),
       "join_time" -> SQLMetrics.createNanoTimingMetric(sc, "Native.join_time"),
-      "spilled_bytes" -> SQLMetrics.createSizeMetric(sc, "Native.spilled_bytes"))
+      "spilled_bytes" -> SQLMetrics.createSizeMetric(sc, "Native.spilled_bytes"),
+      "spilled_rows" -> SQLMetrics.createMetric(sc, "Native.spilled_rows"),
+      "spill_count" -> SQLMetrics.createMetric(sc, "Native.spill_count"),
+      "io_time" -> SQLMetrics.createNanoTimingMetric(sc, "Native.io_time"),
+      "jni_time" -> SQLMetrics.createNanoTimingMetric(sc, "Native.jni_time"),
+      "decompress_time" -> SQLMetrics.createNanoTimingMetric(sc, "Native.decompress_time"))
 
     if (BlazeConf.enableInputBatchStatistics()) {
       metrics ++= TreeMap(
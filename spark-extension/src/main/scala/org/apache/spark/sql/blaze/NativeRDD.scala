@@ -22,6 +22,7 @@ import org.apache.spark.Dependency
 import org.apache.spark.Partition
 import org.apache.spark.SparkContext
 import org.apache.spark.TaskContext
+import org.apache.spark.util.CollectionAccumulator
 import org.blaze.protobuf.PhysicalPlanNode
 
 class NativeRDD(
@@ -42,11 +43,24 @@ class NativeRDD(
   def isShuffleReadFull: Boolean = Shims.get.getRDDShuffleReadFull(this)
   Shims.get.setRDDShuffleReadFull(this, rddShuffleReadFull)
 
+  // rendered native plans of executed tasks, see BlazeConf.explainNativePlanEnabled
+  val nativePlanDescriptions: Option[CollectionAccumulator[String]] =
+    if (BlazeConf.explainNativePlanEnabled()) {
+      Some(rddSparkContext.collectionAccumulator[String]("Native.plan"))
+    } else {
+      None
+    }
+
   override protected def getPartitions: Array[Partition] = rddPartitions
   override protected def getDependencies: Seq[Dependency[_]] = rddDependencies
 
   override def compute(split: Partition, context: TaskContext): Iterator[InternalRow] = {
     val computingNativePlan = nativePlan(split, context)
-    NativeHelper.executeNativePlan(computingNativePlan, metrics, split, Some(context))
+    NativeHelper.executeNativePlan(
+      computingNativePlan,
+      metrics,
+      split,
+      Some(context),
+      nativePlanDescriptions)
   }
 }
@@ -0,0 +1,45 @@
+/*
+ * Copyright 2022 The Blaze Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.apache.spark.sql.blaze
+
+import org.apache.spark.sql.Column
+import org.apache.spark.sql.catalyst.expressions.Expression
+import org.apache.spark.sql.catalyst.expressions.Unevaluable
+import org.apache.spark.sql.types.DataType
+
+/**
+ * References a native udf registered by plugin libraries configured in
+ * spark.blaze.nativeUdf.libraries. the udf is only evaluated natively, so it
+ * must not be used in operators which are not converted to native.
+ */
+case class NativeUDF(
+    name: String,
+    dataType: DataType,
+    nullable: Boolean,
+    children: Seq[Expression])
+    extends Expression
+    with Unevaluable {
+
+  override def prettyName: String = s"native_udf_$name"
+
+  protected def withNewChildrenInternal(newChildren: IndexedSeq[Expression]): Expression =
+    copy(children = newChildren)
+}
+
+object NativeUDF {
+  def apply(name: String, dataType: DataType, args: Column*): Column =
+    new Column(NativeUDF(name, dataType, nullable = true, args.map(_.expr)))
+}
@@ -35,6 +35,7 @@ import org.apache.spark.sql.execution.blaze.plan._
 import org.apache.spark.sql.execution.blaze.shuffle.RssPartitionWriterBase
 import org.apache.spark.sql.execution.datasources.BasicWriteTaskStats
 import org.apache.spark.sql.execution.exchange.BroadcastExchangeLike
+import org.apache.spark.sql.execution.joins.BroadcastHashJoinExec
 import org.apache.spark.sql.SQLContext
 import org.apache.spark.sql.catalyst.expressions.Attribute
 import org.apache.spark.sql.catalyst.expressions.Generator
@@ -79,7 +80,9 @@ abstract class Shims {
       leftKeys: Seq[Expression],
       rightKeys: Seq[Expression],
       joinType: JoinType,
-      condition: Option[Expression]): NativeBroadcastJoinBase
+      condition: Option[Expression],
+      broadcastSide: pb.JoinSide,
+      isNullAwareAntiJoin: Boolean): NativeBroadcastJoinBase
 
   def createNativeBroadcastNestedLoopJoinExec(
       left: SparkPlan,
@@ -95,6 +98,14 @@ abstract class Shims {
       joinType: JoinType,
       condition: Option[Expression]): NativeSortMergeJoinBase
 
+  def createNativeShuffledHashJoinExec(
+      left: SparkPlan,
+      right: SparkPlan,
+      leftKeys: Seq[Expression],
+      rightKeys: Seq[Expression],
+      joinType: JoinType,
+      buildSide: pb.JoinSide): NativeShuffledHashJoinBase
+
   def createNativeExpandExec(
       projections: Seq[Seq[Expression]],
       output: Seq[Attribute],
@@ -109,6 +120,8 @@ abstract class Shims {
       generatorOutput: Seq[Attribute],
       child: SparkPlan): NativeGenerateBase
 
+  def createNativeGenerateIdExec(child: SparkPlan, idColumnName: String): NativeGenerateIdBase
+
   def createNativeGlobalLimitExec(limit: Long, child: SparkPlan): NativeGlobalLimitBase
 
   def createNativeLocalLimitExec(limit: Long, child: SparkPlan): NativeLocalLimitBase
@@ -162,6 +175,8 @@ abstract class Shims {
 
   def getUnderlyingBroadcast(plan: SparkPlan): BroadcastExchangeLike
 
+  def isNullAwareAntiJoin(exec: BroadcastHashJoinExec): Boolean
+
   def executeNative(plan: SparkPlan): NativeRDD
 
   def isQueryStageInput(plan: SparkPlan): Boolean
@@ -188,6 +203,10 @@ abstract class Shims {
 
   def getLikeEscapeChar(expr: Expression): Char
 
+  def isArithmeticFailOnError(expr: Expression): Boolean
+
+  def isCastAnsiEnabled(expr: Expression): Boolean
+
   def getAggregateExpressionFilter(expr: Expression): Option[Expression]
 
   def createFileSegment(file: File, offset: Long, length: Long, numRecords: Long): FileSegment
@@ -0,0 +1,98 @@
+/*
+ * Copyright 2022 The Blaze Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.apache.spark.sql.blaze.memory
+
+import scala.collection.mutable
+
+import org.apache.spark.TaskContext
+import org.apache.spark.internal.Logging
+import org.apache.spark.memory.MemoryConsumer
+import org.apache.spark.sql.blaze.JniBridge
+import org.apache.spark.util.Utils
+
+/**
+ * Accounts memory used by native consumers of a task in spark's task memory manager, so native
+ * operators share the execution memory budget with other jvm operators. native side acquires and
+ * releases memory through this consumer, and spills its largest consumers when spark asks this
+ * consumer to free memory.
+ */
+class NativeMemoryConsumer(taskContext: TaskContext)
+    extends MemoryConsumer(
+      taskContext.taskMemoryManager,
+      taskContext.taskMemoryManager.pageSizeBytes(),
+      taskContext.taskMemoryManager.getTungstenMemoryMode)
+    with Logging {
+
+  // release all remaining memory on task completion
+  taskContext.addTaskCompletionListener { _ =>
+    val remaining = memUsed
+    if (remaining > 0) {
+      logWarning(s"task completed with unreleased native memory: ${Utils.bytesToString(remaining)}")
+      release(remaining)
+    }
+    NativeMemoryConsumer.all.synchronized {
+      NativeMemoryConsumer.all.remove(taskContext.taskAttemptId())
+    }
+  }
+
+  def memUsed: Long = getUsed
+
+  /**
+   * acquire execution memory for native consumers
+   * @return
+   *   granted size, may be less than the requested size
+   */
+  def acquire(size: Long): Long = {
+    // do not use acquireMemory() here, because updating `used` must not be done
+    // while holding the task memory manager lock
+    val granted = taskMemoryManager.acquireExecutionMemory(size, this)
+    updateUsed(granted)
+    granted
+  }
+
+  def release(size: Long): Unit = {
+    taskMemoryManager.releaseExecutionMemory(size, this)
+    updateUsed(-size)
+  }
+
+  private def updateUsed(diff: Long): Unit = synchronized {
+    used += diff
+  }
+
+  override def spill(size: Long, trigger: MemoryConsumer): Long = {
+    // native consumers spill themselves when memory cannot be acquired
+    if ((trigger eq this) || memUsed == 0) {
+      return 0L
+    }
+    logInfo(
+      s"starts spilling native memory, size=${Utils.bytesToString(size)}" +
+        s", memUsed=${Utils.bytesToString(memUsed)}")
+    val released = JniBridge.spillNative(this, size)
+    logInfo(s"finished spilling native memory, released=${Utils.bytesToString(released)}")
+    released
+  }
+}
+
+object NativeMemoryConsumer extends Logging {
+  val all: mutable.Map[Long, NativeMemoryConsumer] = mutable.Map()
+
+  def current: NativeMemoryConsumer = {
+    val taskContext = TaskContext.get
+    all.synchronized {
+      all.getOrElseUpdate(taskContext.taskAttemptId(), new NativeMemoryConsumer(taskContext))
+    }
+  }
+}
@@ -49,7 +49,7 @@ abstract class ConvertToNativeBase(override val child: SparkPlan)
   override lazy val metrics: Map[String, SQLMetric] = Map(
     NativeHelper
       .getDefaultNativeMetrics(sparkContext)
-      .filterKeys(Set("output_rows", "elapsed_compute"))
+      .filterKeys(Set("output_rows", "elapsed_compute", "jni_time"))
       .toSeq :+
       ("size", SQLMetrics.createSizeMetric(sparkContext, "Native.batch_bytes_size")): _*)
 
@@ -73,6 +73,7 @@ abstract class NativeAggBase(
           "output_rows",
           "elapsed_compute",
           "spilled_bytes",
+          "spill_count",
           "input_batch_count",
           "input_batch_mem_size_total",
           "input_batch_mem_size_avg",
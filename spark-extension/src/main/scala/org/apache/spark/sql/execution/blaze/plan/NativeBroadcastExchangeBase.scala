@@ -177,7 +177,7 @@ abstract class NativeBroadcastExchangeBase(mode: BroadcastMode, override val chi
           JniBridge.resourcesMap.put(
             resourceId,
             (byteBuffer: ByteBuffer) => {
-              val byteArray = new Array[Byte](byteBuffer.capacity())
+              val byteArray = new Array[Byte](byteBuffer.remaining())
               byteBuffer.get(byteArray)
               ipcs += byteArray
               metrics("dataSize") += byteArray.length
@@ -191,6 +191,9 @@ abstract class NativeBroadcastExchangeBase(mode: BroadcastMode, override val chi
                 .newBuilder()
                 .setInput(input)
                 .setIpcConsumerResourceId(resourceId)
+                .setCompressionLevel(BlazeConf.shuffleCompressionLevel())
+                .setCompressionCodec(
+                  pb.CompressionCodec.valueOf(BlazeConf.shuffleCompressionCodec().toUpperCase))
                 .build())
             .build()
 
@@ -300,6 +303,9 @@ object NativeBroadcastExchangeBase {
       .newBuilder()
       .setInput(pb.PhysicalPlanNode.newBuilder().setSort(sortExec))
       .setIpcConsumerResourceId(writerIpcProviderResourceId)
+      .setCompressionLevel(BlazeConf.shuffleCompressionLevel())
+      .setCompressionCodec(
+        pb.CompressionCodec.valueOf(BlazeConf.shuffleCompressionCodec().toUpperCase))
 
     // build native sorter
     val exec = pb.PhysicalPlanNode
@@ -319,7 +325,7 @@ object NativeBroadcastExchangeBase {
     // output
     val bos = new ByteArrayOutputStream()
     val consumeIpc = (byteBuffer: ByteBuffer) => {
-      val byteArray = new Array[Byte](byteBuffer.capacity())
+      val byteArray = new Array[Byte](byteBuffer.remaining())
       byteBuffer.get(byteArray)
       bos.write(byteArray)
     }
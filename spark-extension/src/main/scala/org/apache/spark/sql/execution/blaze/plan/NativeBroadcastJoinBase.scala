@@ -16,6 +16,7 @@
 package org.apache.spark.sql.execution.blaze.plan
 
 import scala.collection.JavaConverters._
+import scala.util.Try
 
 import org.apache.spark.OneToOneDependency
 import org.apache.spark.Partition
@@ -25,10 +26,14 @@ import org.apache.spark.sql.blaze.NativeConverters
 import org.apache.spark.sql.blaze.NativeHelper
 import org.apache.spark.sql.blaze.NativeRDD
 import org.apache.spark.sql.blaze.NativeSupports
+import org.apache.spark.sql.blaze.Shims
 import org.apache.spark.sql.catalyst.expressions.Expression
+import org.apache.spark.sql.catalyst.plans.ExistenceJoin
+import org.apache.spark.sql.catalyst.plans.Inner
 import org.apache.spark.sql.catalyst.plans.JoinType
 import org.apache.spark.sql.catalyst.plans.LeftAnti
 import org.apache.spark.sql.catalyst.plans.LeftSemi
+import org.apache.spark.sql.catalyst.plans.RightOuter
 import org.apache.spark.sql.catalyst.plans.physical.Partitioning
 import org.apache.spark.sql.execution.SparkPlan
 import org.apache.spark.sql.execution.metric.SQLMetric
@@ -42,13 +47,21 @@ abstract class NativeBroadcastJoinBase(
     leftKeys: Seq[Expression],
     rightKeys: Seq[Expression],
     joinType: JoinType,
-    condition: Option[Expression])
+    condition: Option[Expression],
+    broadcastSide: pb.JoinSide,
+    isNullAwareAntiJoin: Boolean)
     extends BinaryExecNode
     with NativeSupports {
 
   assert(
-    (joinType != LeftSemi && joinType != LeftAnti) || condition.isEmpty,
-    "Semi/Anti join with filter is not supported yet")
+    !joinType.isInstanceOf[ExistenceJoin] && joinType != LeftSemi && joinType != LeftAnti
+      || condition.isEmpty,
+    "Semi/Anti/Existence join with filter is not supported yet")
+
+  assert(
+    broadcastSide == pb.JoinSide.LEFT_SIDE || joinType.isInstanceOf[ExistenceJoin]
+      || joinType == LeftSemi || joinType == LeftAnti,
+    s"BHJ $joinType with broadcasted right side is not supported")
 
   assert(
     !BlazeConf.enableBhjFallbacksToSmj() || BlazeConf
@@ -79,7 +92,31 @@ abstract class NativeBroadcastJoinBase(
       .build()
   }
 
-  private def nativeJoinType = NativeConverters.convertJoinType(joinType)
+  private def nativeJoinType = joinType match {
+    case _: ExistenceJoin => pb.JoinType.SEMI
+    case _ => NativeConverters.convertJoinType(joinType)
+  }
+
+  private def nativeExistenceColumnName = joinType match {
+    case ExistenceJoin(exists) => Some(Util.getFieldNameByExprId(exists))
+    case _ => None
+  }
+
+  // hash maps built from the same broadcast with the same keys are shared by
+  // tasks, including tasks of other joins reusing the broadcast exchange
+  private def nativeCachedBuildHashMapId: Option[String] = {
+    val (broadcastPlan, broadcastKeys) = broadcastSide match {
+      case pb.JoinSide.RIGHT_SIDE => (right, rightKeys)
+      case _ => (left, leftKeys)
+    }
+    Try(Shims.get.getUnderlyingBroadcast(broadcastPlan)).toOption match {
+      case Some(exec: NativeBroadcastExchangeBase) =>
+        val broadcastId = exec.doExecuteBroadcastNative[Array[Array[Byte]]]().id
+        val keys = broadcastKeys.map(NativeConverters.convertExpr(_).getColumn.getName)
+        Some(s"NativeBroadcastJoin:$broadcastId:${keys.mkString(",")}")
+      case _ => None
+    }
+  }
 
   private def nativeJoinFilter =
     condition.map(NativeConverters.convertJoinFilter(_, left.output, right.output))
@@ -91,32 +128,56 @@ abstract class NativeBroadcastJoinBase(
   override def doExecuteNative(): NativeRDD = {
     val leftRDD = NativeHelper.executeNative(left)
     val rightRDD = NativeHelper.executeNative(right)
-    val nativeMetrics = MetricNode(metrics, leftRDD.metrics :: rightRDD.metrics :: Nil)
     val nativeJoinType = this.nativeJoinType
     val nativeJoinOn = this.nativeJoinOn
     val nativeJoinFilter = this.nativeJoinFilter
-    val partitions = rightRDD.partitions
+    val nativeExistenceColumnName = this.nativeExistenceColumnName
+    val broadcastSide = this.broadcastSide
+    val isNullAwareAntiJoin = this.isNullAwareAntiJoin
+    val (broadcastRDD, probedRDD) = broadcastSide match {
+      case pb.JoinSide.RIGHT_SIDE => (rightRDD, leftRDD)
+      case _ => (leftRDD, rightRDD)
+    }
+    val partitions = probedRDD.partitions
+
+    // the broadcasted side is always the left child of the native join
+    val nativeMetrics =
+      MetricNode(metrics, broadcastRDD.metrics :: probedRDD.metrics :: Nil)
+    val cachedBuildHashMapId = joinType match {
+      case Inner | RightOuter | LeftSemi | LeftAnti | _: ExistenceJoin
+          if condition.isEmpty && BlazeConf.enableBhjBuildSideReuse() =>
+        nativeCachedBuildHashMapId
+      case _ => None
+    }
 
     new NativeRDD(
       sparkContext,
       nativeMetrics,
       partitions,
-      rddDependencies = new OneToOneDependency(rightRDD) :: Nil,
-      rightRDD.isShuffleReadFull,
+      rddDependencies = new OneToOneDependency(probedRDD) :: Nil,
+      probedRDD.isShuffleReadFull,
       (partition, context) => {
         val partition0 = new Partition() {
           override def index: Int = 0
         }
-        val leftChild = leftRDD.nativePlan(partition0, context)
-        val rightChild = rightRDD.nativePlan(rightRDD.partitions(partition.index), context)
+        val broadcastChild = broadcastRDD.nativePlan(partition0, context)
+        val probedChild = probedRDD.nativePlan(probedRDD.partitions(partition.index), context)
+        val (leftChild, rightChild) = broadcastSide match {
+          case pb.JoinSide.RIGHT_SIDE => (probedChild, broadcastChild)
+          case _ => (broadcastChild, probedChild)
+        }
         val broadcastJoinExec = pb.BroadcastJoinExecNode
           .newBuilder()
           .setLeft(leftChild)
           .setRight(rightChild)
           .setJoinType(nativeJoinType)
           .addAllOn(nativeJoinOn.asJava)
+          .setBroadcastSide(broadcastSide)
+          .setNullAwareAntiJoin(isNullAwareAntiJoin)
 
         nativeJoinFilter.foreach(joinFilter => broadcastJoinExec.setJoinFilter(joinFilter))
+        cachedBuildHashMapId.foreach(id => broadcastJoinExec.setCachedBuildHashMapId(id))
+        nativeExistenceColumnName.foreach(name => broadcastJoinExec.setExistenceColumnName(name))
         pb.PhysicalPlanNode.newBuilder().setBroadcastJoin(broadcastJoinExec).build()
       },
       friendlyName = "NativeRDD.BroadcastJoin")
@@ -26,6 +26,8 @@ import org.apache.spark.sql.blaze.NativeSupports
 import org.apache.spark.sql.catalyst.expressions.Attribute
 import org.apache.spark.sql.catalyst.expressions.Explode
 import org.apache.spark.sql.catalyst.expressions.Generator
+import org.apache.spark.sql.catalyst.expressions.Inline
+import org.apache.spark.sql.catalyst.expressions.JsonTuple
 import org.apache.spark.sql.catalyst.expressions.PosExplode
 import org.apache.spark.sql.catalyst.expressions.SortOrder
 import org.apache.spark.sql.catalyst.plans.physical.Partitioning
@@ -74,6 +76,18 @@ abstract class NativeGenerateBase(
         .setFunc(pb.GenerateFunction.PosExplode)
         .addChild(NativeConverters.convertExpr(child))
         .build()
+    case Inline(child) =>
+      pb.Generator
+        .newBuilder()
+        .setFunc(pb.GenerateFunction.Inline)
+        .addChild(NativeConverters.convertExpr(child))
+        .build()
+    case JsonTuple(children) =>
+      pb.Generator
+        .newBuilder()
+        .setFunc(pb.GenerateFunction.JsonTuple)
+        .addAllChild(children.map(NativeConverters.convertExpr).asJava)
+        .build()
     case other =>
       throw new NotImplementedError(s"generator not supported: $other")
   }
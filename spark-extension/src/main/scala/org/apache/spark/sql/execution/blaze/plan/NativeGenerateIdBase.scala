@@ -0,0 +1,92 @@
+/*
+ * Copyright 2022 The Blaze Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.apache.spark.sql.execution.blaze.plan
+
+import scala.collection.JavaConverters._
+
+import org.apache.spark.sql.blaze.MetricNode
+import org.apache.spark.sql.blaze.NativeRDD
+import org.apache.spark.sql.blaze.NativeHelper
+import org.apache.spark.sql.catalyst.expressions.Attribute
+import org.apache.spark.sql.catalyst.expressions.AttributeReference
+import org.apache.spark.sql.catalyst.expressions.SortOrder
+import org.apache.spark.sql.catalyst.plans.physical.Partitioning
+import org.apache.spark.sql.execution.SparkPlan
+import org.apache.spark.sql.execution.UnaryExecNode
+import org.apache.spark.sql.execution.blaze.plan.NativeGenerateIdBase.buildGenerateIdExec
+import org.apache.spark.sql.execution.metric.SQLMetric
+import org.apache.spark.sql.types.LongType
+import org.apache.spark.util.Utils
+import org.apache.spark.OneToOneDependency
+import org.blaze.protobuf.GenerateIdExecNode
+import org.blaze.protobuf.PhysicalPlanNode
+
+import org.apache.spark.sql.blaze.NativeSupports
+
+abstract class NativeGenerateIdBase(override val child: SparkPlan, idColumnName: String)
+    extends UnaryExecNode
+    with NativeSupports {
+
+  private val idAttr = AttributeReference(idColumnName, LongType, nullable = false)()
+
+  override def output: Seq[Attribute] = child.output :+ idAttr
+  override def outputPartitioning: Partitioning = child.outputPartitioning
+  override def outputOrdering: Seq[SortOrder] = child.outputOrdering
+
+  override lazy val metrics: Map[String, SQLMetric] = Map()
+
+  override def doExecuteNative(): NativeRDD = {
+    val inputRDD = NativeHelper.executeNative(child)
+    val nativeMetrics = MetricNode(metrics, inputRDD.metrics :: Nil)
+
+    // like RDD.zipWithIndex, the input is executed once more to count rows of
+    // each partition, from which the starting id of each partition is computed
+    val partitionOffsets = sparkContext
+      .runJob(inputRDD, Utils.getIteratorSize _)
+      .scanLeft(0L)(_ + _)
+      .dropRight(1)
+      .toSeq
+
+    new NativeRDD(
+      sparkContext,
+      nativeMetrics,
+      rddPartitions = inputRDD.partitions,
+      rddDependencies = new OneToOneDependency(inputRDD) :: Nil,
+      inputRDD.isShuffleReadFull,
+      (partition, taskContext) => {
+        val inputPlan = inputRDD.nativePlan(inputRDD.partitions(partition.index), taskContext)
+        buildGenerateIdExec(inputPlan, idColumnName, partitionOffsets)
+      },
+      friendlyName = "NativeRDD.GenerateId")
+  }
+}
+
+object NativeGenerateIdBase {
+  def buildGenerateIdExec(
+      input: PhysicalPlanNode,
+      idColumnName: String,
+      partitionOffsets: Seq[Long]): PhysicalPlanNode = {
+    PhysicalPlanNode
+      .newBuilder()
+      .setGenerateId(
+        GenerateIdExecNode
+          .newBuilder()
+          .setInput(input)
+          .setIdColumnName(idColumnName)
+          .addAllPartitionOffsets(partitionOffsets.map(Long.box).asJava))
+      .build()
+  }
+}
@@ -78,9 +78,8 @@ abstract class NativeParquetInsertIntoHiveTableBase(
     .LinkedHashMap(
       NativeHelper
         .getDefaultNativeMetrics(sparkContext)
-        .filterKeys(Set("output_rows", "elapsed_compute"))
+        .filterKeys(Set("output_rows", "elapsed_compute", "io_time"))
         .toSeq
-        :+ ("io_time", SQLMetrics.createNanoTimingMetric(sparkContext, "Native.io_time"))
         :+ ("bytes_written", SQLMetrics
           .createSizeMetric(sparkContext, "Native.bytes_written")): _*)
     .toMap
@@ -23,6 +23,7 @@ import scala.collection.JavaConverters._
 import scala.collection.mutable
 
 import org.apache.hadoop.fs.FileSystem
+import org.apache.spark.InterruptibleIterator
 import org.apache.spark.Partition
 import org.apache.spark.TaskContext
 import org.blaze.{protobuf => pb}
@@ -34,9 +35,13 @@ import org.apache.spark.sql.blaze.NativeHelper
 import org.apache.spark.sql.blaze.NativeRDD
 import org.apache.spark.sql.blaze.NativeSupports
 import org.apache.spark.sql.blaze.Shims
+import org.apache.spark.sql.catalyst.InternalRow
 import org.apache.spark.sql.catalyst.expressions.Attribute
+import org.apache.spark.sql.catalyst.expressions.DynamicPruningExpression
+import org.apache.spark.sql.catalyst.expressions.Expression
 import org.apache.spark.sql.catalyst.plans.physical.Partitioning
 import org.apache.spark.sql.execution.FileSourceScanExec
+import org.apache.spark.sql.execution.InSubqueryExec
 import org.apache.spark.sql.execution.LeafExecNode
 import org.apache.spark.sql.execution.datasources.FileScanRDD
 import org.apache.spark.sql.execution.metric.SQLMetric
@@ -44,6 +49,7 @@ import org.apache.spark.sql.execution.datasources.FilePartition
 import org.apache.spark.sql.execution.datasources.PartitionedFile
 import org.apache.spark.sql.execution.metric.SQLMetrics
 import org.apache.spark.sql.execution.SparkPlan
+import org.apache.spark.sql.execution.blaze.arrowio.ArrowFFIExportIterator
 import org.apache.spark.sql.types.NullType
 import org.apache.spark.sql.types.StructField
 import org.apache.spark.sql.types.StructType
@@ -57,14 +63,15 @@ abstract class NativeParquetScanBase(basedFileScan: FileSourceScanExec)
     .LinkedHashMap(
       NativeHelper
         .getDefaultNativeMetrics(sparkContext)
-        .filterKeys(Set("output_rows", "elapsed_compute"))
+        .filterKeys(Set("output_rows", "elapsed_compute", "io_time"))
         .toSeq :+
         ("predicate_evaluation_errors", SQLMetrics
           .createMetric(sparkContext, "Native.predicate_evaluation_errors")) :+
         ("row_groups_pruned", SQLMetrics
           .createMetric(sparkContext, "Native.row_groups_pruned")) :+
+        ("files_pruned", SQLMetrics
+          .createMetric(sparkContext, "Native.files_pruned")) :+
         ("bytes_scanned", SQLMetrics.createSizeMetric(sparkContext, "Native.bytes_scanned")) :+
-        ("io_time", SQLMetrics.createNanoTimingMetric(sparkContext, "Native.io_time")) :+
         ("io_time_getfs", SQLMetrics
           .createNanoTimingMetric(sparkContext, "Native.io_time_getfs")): _*)
     .toMap
@@ -72,15 +79,37 @@ abstract class NativeParquetScanBase(basedFileScan: FileSourceScanExec)
   override val output: Seq[Attribute] = basedFileScan.output
   override val outputPartitioning: Partitioning = basedFileScan.outputPartitioning
 
+  private val partitionSchema = basedFileScan.relation.partitionSchema
+
+  // dynamic partition pruning subqueries are not evaluated until execution, so
+  // input files are listed with static partition filters only and pruned in
+  // native side with the key sets of the subqueries
+  private val (staticPartitionFilters, dynamicPartitionFilters) =
+    NativeParquetScanBase.splitDynamicPartitionFilters(basedFileScan.partitionFilters)
+
+  private val dynamicPruningSubqueries: Seq[(Int, InSubqueryExec)] =
+    dynamicPartitionFilters.flatMap {
+      case DynamicPruningExpression(e: InSubqueryExec) =>
+        e.child match {
+          case attr: Attribute if partitionSchema.fieldNames.contains(attr.name) =>
+            Some((partitionSchema.fieldIndex(attr.name), e))
+          case _ => None // unsupported filters are simply not applied
+        }
+      case _ => None
+    }
+
   private val inputFileScanRDD = {
-    basedFileScan.inputRDDs().head match {
+    val staticFileScan = if (dynamicPartitionFilters.nonEmpty) {
+      basedFileScan.copy(partitionFilters = staticPartitionFilters)
+    } else {
+      basedFileScan
+    }
+    staticFileScan.inputRDDs().head match {
       case rdd: FileScanRDD => rdd
       case rdd: MapPartitionsRDD[_, _] => rdd.prev.asInstanceOf[FileScanRDD]
     }
   }
 
-  private val partitionSchema = basedFileScan.relation.partitionSchema
-
   private val fileSizes = inputFileScanRDD.filePartitions
     .flatMap(_.files)
     .groupBy(_.filePath)
@@ -163,6 +192,15 @@ abstract class NativeParquetScanBase(basedFileScan: FileSourceScanExec)
       sparkSession.sparkContext.broadcast(new SerializableConfiguration(hadoopConf))
     val numPartitions = partitions.length
 
+    // evaluate dynamic pruning subqueries and broadcast their key sets
+    val broadcastedKeySets = dynamicPruningSubqueries.map { case (partitionColumnIndex, e) =>
+      if (e.values().isEmpty) {
+        e.updateResult()
+      }
+      val keySetSchema = StructType(partitionSchema(partitionColumnIndex) :: Nil)
+      (partitionColumnIndex, keySetSchema, sparkContext.broadcast(e.values().get))
+    }
+
     new NativeRDD(
       sparkContext,
       nativeMetrics,
@@ -186,6 +224,24 @@ abstract class NativeParquetScanBase(basedFileScan: FileSourceScanExec)
             fs
           })
 
+        val nativeDynamicPruningFilters = broadcastedKeySets.map {
+          case (partitionColumnIndex, keySetSchema, broadcastedKeySet) =>
+            val keySetResourceId = s"NativeParquetScanExec:${UUID.randomUUID().toString}"
+            JniBridge.resourcesMap.put(
+              keySetResourceId,
+              () => {
+                val keyIter = broadcastedKeySet.value.iterator.map(key => InternalRow(key))
+                new InterruptibleIterator(
+                  context,
+                  new ArrowFFIExportIterator(keyIter, keySetSchema, context))
+              })
+            pb.DynamicPruningFilterNode
+              .newBuilder()
+              .setPartitionColumnIndex(partitionColumnIndex)
+              .setKeySetProviderResourceId(keySetResourceId)
+              .build()
+        }
+
         val nativeFileGroup = nativeFileGroups(partition.asInstanceOf[FilePartition])
         val nativeParquetScanConf = pb.FileScanExecConf
           .newBuilder()
@@ -203,6 +259,7 @@ abstract class NativeParquetScanBase(basedFileScan: FileSourceScanExec)
           .setBaseConf(nativeParquetScanConf)
           .setFsResourceId(resourceId)
           .addAllPruningPredicates(nativePruningPredicateFilters.asJava)
+          .addAllDynamicPruningFilters(nativeDynamicPruningFilters.asJava)
 
         pb.PhysicalPlanNode
           .newBuilder()
@@ -217,3 +274,14 @@ abstract class NativeParquetScanBase(basedFileScan: FileSourceScanExec)
 
   override protected def doCanonicalize(): SparkPlan = basedFileScan.canonicalized
 }
+
+object NativeParquetScanBase {
+
+  // splits partition filters into (static, dynamic) filters like spark's
+  // FileSourceScanExec. only dynamic pruning filters are dynamic, other filters
+  // (including ones with ordinary subqueries) are already removed from the filter
+  // above the scan, so they must be applied when listing files
+  def splitDynamicPartitionFilters(
+      partitionFilters: Seq[Expression]): (Seq[Expression], Seq[Expression]) =
+    partitionFilters.partition(_.find(_.isInstanceOf[DynamicPruningExpression]).isEmpty)
+}
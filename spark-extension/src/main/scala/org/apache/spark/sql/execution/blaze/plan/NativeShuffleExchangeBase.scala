@@ -172,10 +172,13 @@ abstract class NativeShuffleExchangeBase(
       nativeInputRDD.metrics :: Nil,
       Some({
         case ("data_size", v) => metrics("dataSize") += v
-        case ("output_rows", v) =>
+        case ("shuffle_bytes_written", v) =>
+          val shuffleWriteMetrics = TaskContext.get.taskMetrics().shuffleWriteMetrics
+          new SQLShuffleWriteMetricsReporter(shuffleWriteMetrics, metrics).incBytesWritten(v)
+        case ("shuffle_records_written", v) =>
           val shuffleWriteMetrics = TaskContext.get.taskMetrics().shuffleWriteMetrics
           new SQLShuffleWriteMetricsReporter(shuffleWriteMetrics, metrics).incRecordsWritten(v)
-        case ("compute_elapsed", v) =>
+        case ("shuffle_write_time", v) =>
           val shuffleWriteMetrics = TaskContext.get.taskMetrics().shuffleWriteMetrics
           new SQLShuffleWriteMetricsReporter(shuffleWriteMetrics, metrics).incWriteTime(v)
         case ("spilled_bytes", v) => metrics("spilled_bytes").add(v)
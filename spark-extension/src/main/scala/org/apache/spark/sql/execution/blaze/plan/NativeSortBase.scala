@@ -54,6 +54,8 @@ abstract class NativeSortBase(
         "output_rows",
         "elapsed_compute",
         "spilled_bytes",
+        "spilled_rows",
+        "spill_count",
         "input_batch_count",
         "input_batch_mem_size_total",
         "input_batch_mem_size_avg",
@@ -38,6 +38,7 @@ import org.blaze.protobuf.JoinOn
 import org.blaze.protobuf.PhysicalPlanNode
 import org.blaze.protobuf.SortMergeJoinExecNode
 import org.blaze.protobuf.SortOptions
+import org.blaze.{protobuf => pb}
 
 abstract class NativeSortMergeJoinBase(
     override val left: SparkPlan,
@@ -50,8 +51,9 @@ abstract class NativeSortMergeJoinBase(
     with NativeSupports {
 
   assert(
-    (joinType != LeftSemi && joinType != LeftAnti) || condition.isEmpty,
-    "Semi/Anti join with filter is not supported yet")
+    !joinType.isInstanceOf[ExistenceJoin] && joinType != LeftSemi && joinType != LeftAnti
+      || condition.isEmpty,
+    "Semi/Anti/Existence join with filter is not supported yet")
 
   assert(
     BlazeConf.enableSmjInequalityJoin() || condition.isEmpty,
@@ -89,7 +91,15 @@ abstract class NativeSortMergeJoinBase(
       .build()
   })
 
-  private def nativeJoinType = NativeConverters.convertJoinType(joinType)
+  private def nativeJoinType = joinType match {
+    case _: ExistenceJoin => pb.JoinType.SEMI
+    case _ => NativeConverters.convertJoinType(joinType)
+  }
+
+  private def nativeExistenceColumnName = joinType match {
+    case ExistenceJoin(exists) => Some(Util.getFieldNameByExprId(exists))
+    case _ => None
+  }
 
   private def nativeJoinFilter =
     condition.map(NativeConverters.convertJoinFilter(_, left.output, right.output))
@@ -108,6 +118,8 @@ abstract class NativeSortMergeJoinBase(
     val nativeJoinOn = this.nativeJoinOn
     val nativeJoinType = this.nativeJoinType
     val nativeJoinFilter = this.nativeJoinFilter
+    val nativeExistenceColumnName = this.nativeExistenceColumnName
+    val bufferSpillThreshold = conf.sortMergeJoinExecBufferSpillThreshold
 
     val partitions = if (joinType != RightOuter) {
       leftRDD.partitions
@@ -148,8 +160,10 @@ abstract class NativeSortMergeJoinBase(
           .setJoinType(nativeJoinType)
           .addAllOn(nativeJoinOn.asJava)
           .addAllSortOptions(nativeSortOptions.asJava)
+          .setBufferSpillThreshold(bufferSpillThreshold)
 
         nativeJoinFilter.foreach(joinFilter => sortMergeJoinExec.setJoinFilter(joinFilter))
+        nativeExistenceColumnName.foreach(name => sortMergeJoinExec.setExistenceColumnName(name))
         PhysicalPlanNode.newBuilder().setSortMergeJoin(sortMergeJoinExec).build()
       },
       friendlyName = "NativeRDD.SortMergeJoin")
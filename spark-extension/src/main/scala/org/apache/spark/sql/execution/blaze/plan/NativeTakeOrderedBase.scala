@@ -33,6 +33,7 @@ import org.apache.spark.sql.catalyst.expressions.codegen.LazilyGeneratedOrdering
 import org.apache.spark.sql.execution.SparkPlan
 import org.apache.spark.sql.execution.UnaryExecNode
 import org.apache.spark.sql.execution.metric.SQLMetric
+import org.apache.spark.sql.execution.metric.SQLMetrics
 import org.apache.spark.OneToOneDependency
 import org.apache.spark.sql.blaze.NativeSupports
 import org.apache.spark.sql.blaze.Shims
@@ -40,7 +41,7 @@ import org.apache.spark.sql.catalyst.plans.physical.UnknownPartitioning
 import org.blaze.protobuf.PhysicalExprNode
 import org.blaze.protobuf.PhysicalPlanNode
 import org.blaze.protobuf.PhysicalSortExprNode
-import org.blaze.protobuf.SortExecNode
+import org.blaze.protobuf.TopKExecNode
 
 abstract class NativeTakeOrderedBase(
     limit: Long,
@@ -53,7 +54,8 @@ abstract class NativeTakeOrderedBase(
     NativeHelper
       .getDefaultNativeMetrics(sparkContext)
       .filterKeys(Set("output_rows", "elapsed_compute"))
-      .toSeq: _*)
+      .toSeq :+
+      ("pruned_rows", SQLMetrics.createMetric(sparkContext, "Native.pruned_rows")): _*)
 
   override def output: Seq[Attribute] = child.output
   override def outputPartitioning: Partitioning = SinglePartition
@@ -132,13 +134,13 @@ abstract class NativeTakeOrderedBase(
       rddShuffleReadFull = false,
       (_, taskContext) => {
         val inputPartition = shuffledRDD.partitions(0)
-        val nativeTakeOrderedExec = SortExecNode
+        val nativeTakeOrderedExec = TopKExecNode
           .newBuilder()
           .setInput(shuffledRDD.nativePlan(inputPartition, taskContext))
           .addAllExpr(nativeSortExprs.asJava)
-          .setFetchLimit(limit)
+          .setLimit(limit)
           .build()
-        PhysicalPlanNode.newBuilder().setSort(nativeTakeOrderedExec).build()
+        PhysicalPlanNode.newBuilder().setTopK(nativeTakeOrderedExec).build()
       },
       friendlyName = "NativeRDD.FinalTakeOrdered")
   }
@@ -183,13 +185,13 @@ abstract class NativePartialTakeOrderedBase(
       rddShuffleReadFull = false,
       (partition, taskContext) => {
         val inputPartition = inputRDD.partitions(partition.index)
-        val nativeTakeOrderedExec = SortExecNode
+        val nativeTakeOrderedExec = TopKExecNode
           .newBuilder()
           .setInput(inputRDD.nativePlan(inputPartition, taskContext))
           .addAllExpr(nativeSortExprs.asJava)
-          .setFetchLimit(limit)
+          .setLimit(limit)
           .build()
-        PhysicalPlanNode.newBuilder().setSort(nativeTakeOrderedExec).build()
+        PhysicalPlanNode.newBuilder().setTopK(nativeTakeOrderedExec).build()
       },
       friendlyName = "NativeRDD.PartialTakeOrdered")
   }
@@ -26,11 +26,16 @@ import org.apache.spark.sql.blaze.NativeRDD
 import org.apache.spark.sql.blaze.NativeSupports
 import org.apache.spark.sql.catalyst.expressions.Ascending
 import org.apache.spark.sql.catalyst.expressions.Attribute
+import org.apache.spark.sql.catalyst.expressions.CurrentRow
 import org.apache.spark.sql.catalyst.expressions.Expression
 import org.apache.spark.sql.catalyst.expressions.NamedExpression
 import org.apache.spark.sql.catalyst.expressions.NullsFirst
+import org.apache.spark.sql.catalyst.expressions.RangeFrame
 import org.apache.spark.sql.catalyst.expressions.Rank
 import org.apache.spark.sql.catalyst.expressions.SortOrder
+import org.apache.spark.sql.catalyst.expressions.SpecifiedWindowFrame
+import org.apache.spark.sql.catalyst.expressions.UnboundedPreceding
+import org.apache.spark.sql.catalyst.expressions.WindowSpecDefinition
 import org.apache.spark.sql.catalyst.plans.physical.AllTuples
 import org.apache.spark.sql.catalyst.plans.physical.ClusteredDistribution
 import org.apache.spark.sql.catalyst.plans.physical.Distribution
@@ -38,9 +43,15 @@ import org.apache.spark.sql.catalyst.plans.physical.Partitioning
 import org.apache.spark.sql.execution.SparkPlan
 import org.apache.spark.sql.execution.UnaryExecNode
 import org.apache.spark.sql.execution.metric.SQLMetric
+import org.apache.spark.sql.types.DateType
+import org.apache.spark.sql.types.IntegralType
+import org.apache.spark.sql.types.TimestampType
+import org.apache.spark.unsafe.types.CalendarInterval
 import org.blaze.{protobuf => pb}
 
 import org.apache.spark.sql.catalyst.expressions.DenseRank
+import org.apache.spark.sql.catalyst.expressions.Lag
+import org.apache.spark.sql.catalyst.expressions.Lead
 import org.apache.spark.sql.catalyst.expressions.RowNumber
 import org.apache.spark.sql.catalyst.expressions.WindowExpression
 import org.apache.spark.sql.catalyst.expressions.aggregate.Average
@@ -103,43 +114,43 @@ abstract class NativeWindowBase(
             windowExprBuilder.setFuncType(pb.WindowFunctionType.Window)
             windowExprBuilder.setWindowFunc(pb.WindowFunction.DENSE_RANK)
 
+          case e: Lag =>
+            windowExprBuilder.setFuncType(pb.WindowFunctionType.Window)
+            windowExprBuilder.setWindowFunc(pb.WindowFunction.LAG)
+            buildOffsetWindowExpr(windowExprBuilder, e)
+
+          case e: Lead =>
+            windowExprBuilder.setFuncType(pb.WindowFunctionType.Window)
+            windowExprBuilder.setWindowFunc(pb.WindowFunction.LEAD)
+            buildOffsetWindowExpr(windowExprBuilder, e)
+
           case e: Sum =>
-            assert(
-              spec.frameSpecification == RowNumber().frame, // only supports RowFrame(Unbounde, CurrentRow)
-              s"window frame not supported: ${spec.frameSpecification}")
             windowExprBuilder.setFuncType(pb.WindowFunctionType.Agg)
+            buildAggWindowFrame(windowExprBuilder, spec)
             windowExprBuilder.setAggFunc(pb.AggFunction.SUM)
             windowExprBuilder.addChildren(NativeConverters.convertExpr(e.child))
 
           case e: Average =>
-            assert(
-              spec.frameSpecification == RowNumber().frame, // only supports RowFrame(Unbounded, CurrentRow)
-              s"window frame not supported: ${spec.frameSpecification}")
             windowExprBuilder.setFuncType(pb.WindowFunctionType.Agg)
+            buildAggWindowFrame(windowExprBuilder, spec)
             windowExprBuilder.setAggFunc(pb.AggFunction.AVG)
             windowExprBuilder.addChildren(NativeConverters.convertExpr(e.child))
 
           case e: Max =>
-            assert(
-              spec.frameSpecification == RowNumber().frame, // only supports RowFrame(Unbounded, CurrentRow)
-              s"window frame not supported: ${spec.frameSpecification}")
             windowExprBuilder.setFuncType(pb.WindowFunctionType.Agg)
+            buildAggWindowFrame(windowExprBuilder, spec)
             windowExprBuilder.setAggFunc(pb.AggFunction.MAX)
             windowExprBuilder.addChildren(NativeConverters.convertExpr(e.child))
 
           case e: Min =>
-            assert(
-              spec.frameSpecification == RowNumber().frame, // only supports RowFrame(Unbounded, CurrentRow)
-              s"window frame not supported: ${spec.frameSpecification}")
             windowExprBuilder.setFuncType(pb.WindowFunctionType.Agg)
+            buildAggWindowFrame(windowExprBuilder, spec)
             windowExprBuilder.setAggFunc(pb.AggFunction.MIN)
             windowExprBuilder.addChildren(NativeConverters.convertExpr(e.child))
 
           case Count(child :: Nil) =>
-            assert(
-              spec.frameSpecification == RowNumber().frame, // only supports RowFrame(Unbounded, CurrentRow)
-              s"window frame not supported: ${spec.frameSpecification}")
             windowExprBuilder.setFuncType(pb.WindowFunctionType.Agg)
+            buildAggWindowFrame(windowExprBuilder, spec)
             windowExprBuilder.setAggFunc(pb.AggFunction.COUNT)
             windowExprBuilder.addChildren(NativeConverters.convertExpr(child))
 
@@ -152,6 +163,56 @@ abstract class NativeWindowBase(
     windowExprBuilder.build()
   }
 
+  // agg functions support ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW, and
+  // RANGE BETWEEN <preceding> AND CURRENT ROW on a single integral, date or
+  // timestamp order key. the offset is in days for dates and microseconds for
+  // timestamps
+  private def buildAggWindowFrame(
+      windowExprBuilder: pb.WindowExprNode.Builder,
+      spec: WindowSpecDefinition): Unit = {
+    spec.frameSpecification match {
+      case frame if frame == RowNumber().frame =>
+      case SpecifiedWindowFrame(RangeFrame, lower, CurrentRow) =>
+        assert(orderSpec.length == 1, s"RANGE frame requires exactly one order key: $orderSpec")
+        val orderType = orderSpec.head.dataType
+        val rangeFrame = pb.WindowRangeFrame.newBuilder()
+        (lower, orderType) match {
+          case (UnboundedPreceding, (_: IntegralType) | DateType | TimestampType) =>
+            rangeFrame.setUnboundedPreceding(true)
+          case (offset, (_: IntegralType) | DateType) if offset.foldable =>
+            rangeFrame.setPreceding(-offset.eval().asInstanceOf[Number].longValue())
+          case (offset, TimestampType) if offset.foldable =>
+            val preceding = offset.eval() match {
+              case micros: java.lang.Long => -micros // day-time intervals
+              case interval: CalendarInterval if interval.months == 0 =>
+                -(interval.days * 86400000000L + interval.microseconds)
+              case other =>
+                throw new NotImplementedError(s"RANGE frame offset not supported: $other")
+            }
+            rangeFrame.setPreceding(preceding)
+          case _ =>
+            throw new NotImplementedError(
+              s"window frame not supported: ${spec.frameSpecification} on $orderType")
+        }
+        windowExprBuilder.setRangeFrame(rangeFrame.build())
+      case frame =>
+        throw new NotImplementedError(s"window frame not supported: $frame")
+    }
+  }
+
+  // children of LAG/LEAD are (input, offset, default) in all spark versions,
+  // ignoreNulls is the only boolean field and only available since spark 3.2
+  private def buildOffsetWindowExpr(
+      windowExprBuilder: pb.WindowExprNode.Builder,
+      e: Expression with Product): Unit = {
+    assert(!e.productIterator.contains(true), s"window function not supported: $e")
+    val Seq(input, offset, default) = e.children
+    assert(offset.foldable, s"window function offset must be foldable: $e")
+    windowExprBuilder.setOffset(offset.eval().asInstanceOf[Number].longValue())
+    windowExprBuilder.addChildren(NativeConverters.convertExpr(input))
+    windowExprBuilder.addChildren(NativeConverters.convertExpr(default))
+  }
+
   private def nativePartitionSpecExprs = partitionSpec.map { partition =>
     NativeConverters.convertExpr(partition)
   }
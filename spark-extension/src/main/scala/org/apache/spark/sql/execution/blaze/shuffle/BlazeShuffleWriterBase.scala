@@ -24,6 +24,7 @@ import org.apache.spark.Partition
 import org.apache.spark.ShuffleDependency
 import org.apache.spark.SparkEnv
 import org.apache.spark.TaskContext
+import org.blaze.protobuf.CompressionCodec
 import org.blaze.protobuf.PhysicalPlanNode
 import org.blaze.protobuf.ShuffleWriterExecNode
 
@@ -32,6 +33,7 @@ import org.apache.spark.scheduler.MapStatus
 import org.apache.spark.shuffle.IndexShuffleBlockResolver
 import org.apache.spark.shuffle.ShuffleWriteMetricsReporter
 import org.apache.spark.shuffle.ShuffleWriter
+import org.apache.spark.sql.blaze.BlazeConf
 import org.apache.spark.sql.blaze.NativeHelper
 import org.apache.spark.sql.blaze.NativeRDD
 import org.apache.spark.sql.blaze.Shims
@@ -71,6 +73,9 @@ abstract class BlazeShuffleWriterBase[K, V](metrics: ShuffleWriteMetricsReporter
           .newBuilder(nativeShuffleRDD.nativePlan(partition, context).getShuffleWriter)
           .setOutputDataFile(tempDataFilename)
           .setOutputIndexFile(tempIndexFilename)
+          .setCompressionLevel(BlazeConf.shuffleCompressionLevel())
+          .setCompressionCodec(
+            CompressionCodec.valueOf(BlazeConf.shuffleCompressionCodec().toUpperCase))
           .build())
       .build()
     val iterator = NativeHelper.executeNativePlan(
@@ -95,9 +100,9 @@ abstract class BlazeShuffleWriterBase[K, V](metrics: ShuffleWriteMetricsReporter
       })
       .toArray
 
-    // update metrics
+    // length of the data file to commit. bytes written are reported by the native
+    // shuffle_bytes_written metric instead of this file size
     val dataSize = Files.size(tempDataFilePath)
-    metrics.incBytesWritten(dataSize)
 
     Shims.get.commit(
       dep,
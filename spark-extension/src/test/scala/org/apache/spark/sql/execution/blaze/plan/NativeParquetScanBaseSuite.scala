@@ -0,0 +1,42 @@
+/*
+ * Copyright 2022 The Blaze Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package org.apache.spark.sql.execution.blaze.plan
+
+import org.apache.spark.sql.catalyst.expressions.AttributeReference
+import org.apache.spark.sql.catalyst.expressions.DynamicPruningExpression
+import org.apache.spark.sql.catalyst.expressions.EqualTo
+import org.apache.spark.sql.catalyst.expressions.GreaterThan
+import org.apache.spark.sql.catalyst.expressions.Literal
+import org.apache.spark.sql.catalyst.expressions.ScalarSubquery
+import org.apache.spark.sql.catalyst.plans.logical.LocalRelation
+import org.apache.spark.sql.types.IntegerType
+import org.scalatest.funsuite.AnyFunSuite
+
+class NativeParquetScanBaseSuite extends AnyFunSuite {
+
+  test("scalar subquery partition filters are static") {
+    val partitionColumn = AttributeReference("p", IntegerType)()
+    val subquery = ScalarSubquery(LocalRelation(AttributeReference("v", IntegerType)()))
+    val subqueryFilter = EqualTo(partitionColumn, subquery)
+    val literalFilter = GreaterThan(partitionColumn, Literal(0))
+    val dynamicPruningFilter = DynamicPruningExpression(Literal.TrueLiteral)
+
+    val (staticFilters, dynamicFilters) = NativeParquetScanBase.splitDynamicPartitionFilters(
+      Seq(subqueryFilter, dynamicPruningFilter, literalFilter))
+    assert(staticFilters == Seq(subqueryFilter, literalFilter))
+    assert(dynamicFilters == Seq(dynamicPruningFilter))
+  }
+}